@@ -0,0 +1,321 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Measures how long the scanner, parser and type checker take over a
+//! representative program, inputs/large.ruka, and how long a project of many
+//! modules takes to check with and without --parallel. Run with `zig build bench`,
+//! which builds the benchmarks optimized whatever -Doptimize is.
+//!
+//! zig build bench -- [--save <baseline>] [--compare <baseline>]
+//!
+//! --save writes the mean time of each benchmark to baseline as json, --compare
+//! fails if any benchmark is more than 15% slower than in baseline, or if there is
+//! no baseline
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+
+const ruka = @import("ruka");
+const Compiler = ruka.Compiler;
+const MemFs = ruka.VirtualFs.MemFs;
+const Unit = ruka.Unit;
+
+const input: []const u8 = @embedFile("inputs/large.ruka");
+
+/// How much slower than its baseline a benchmark may be before it fails
+const tolerance = 0.15;
+/// Benchmarks run for at least this long, and this many iterations
+const min_time = std.time.ns_per_s;
+const min_iterations = 10;
+const warmup_iterations = 3;
+
+/// The modules main.ruka imports in the project compiled by the module benchmarks,
+/// and the functions each defines on a line of its own
+const project_modules = 20;
+const project_functions = 40;
+const project_lines = project_modules * project_functions + project_modules + 1;
+
+const Benchmark = struct {
+    name: []const u8,
+    /// Runs the benchmark once, returning the nanoseconds the measured part took.
+    /// Setup, such as scanning before parsing, isn't measured
+    run: *const fn (allocator: Allocator) anyerror!u64,
+    /// The lines of input each run covers, those of inputs/large.ruka if null
+    lines: ?usize = null
+};
+
+const benchmarks = [_]Benchmark{
+    .{ .name = "scanner", .run = scan },
+    .{ .name = "parser", .run = parse },
+    .{ .name = "type checker", .run = check },
+    .{ .name = "modules alone", .run = compileAlone, .lines = project_lines },
+    .{ .name = "modules concurrently", .run = compileConcurrently, .lines = project_lines }
+};
+
+const Measurement = struct {
+    name: []const u8,
+    mean: u64,
+    min: u64
+};
+
+pub fn main() !void {
+    var gpa = std.heap.GeneralPurposeAllocator(.{}){};
+    defer _ = gpa.deinit();
+    const allocator = gpa.allocator();
+
+    const args = try std.process.argsAlloc(allocator);
+    defer std.process.argsFree(allocator, args);
+
+    var save: ?[]const u8 = null;
+    var compare: ?[]const u8 = null;
+
+    var i: usize = 1;
+    while (i < args.len) : (i = i + 1) {
+        const flag = args[i];
+        if (i + 1 == args.len) return usage(flag);
+
+        if (std.mem.eql(u8, flag, "--save")) {
+            save = args[i + 1];
+        } else if (std.mem.eql(u8, flag, "--compare")) {
+            compare = args[i + 1];
+        } else {
+            return usage(flag);
+        }
+        i = i + 1;
+    }
+
+    // Units allocate a lot, so each iteration's memory comes from an arena as
+    // the compiler's does
+    var arena = ArenaAllocator.init(std.heap.page_allocator);
+    defer arena.deinit();
+
+    const lines = std.mem.count(u8, input, "\n");
+    const stdout = std.io.getStdOut().writer();
+
+    var measurements: [benchmarks.len]Measurement = undefined;
+    for (benchmarks, &measurements) |benchmark, *measurement| {
+        measurement.* = try measure(benchmark, &arena);
+
+        const seconds = @as(f64, @floatFromInt(measurement.mean)) / std.time.ns_per_s;
+        try stdout.print("{s:<20} mean {d:>9.3}ms  min {d:>9.3}ms  {d:>10.0} lines/s\n", .{
+            measurement.name,
+            @as(f64, @floatFromInt(measurement.mean)) / std.time.ns_per_ms,
+            @as(f64, @floatFromInt(measurement.min)) / std.time.ns_per_ms,
+            @as(f64, @floatFromInt(benchmark.lines orelse lines)) / seconds
+        });
+    }
+
+    if (save) |path| try saveBaseline(path, &measurements);
+    if (compare) |path| {
+        if (!try compareBaseline(allocator, path, &measurements)) std.process.exit(1);
+    }
+}
+
+fn usage(flag: []const u8) !void {
+    std.log.err("unexpected argument {s}, expected --save <baseline> or --compare <baseline>", .{flag});
+    std.process.exit(2);
+}
+
+fn measure(benchmark: Benchmark, arena: *ArenaAllocator) !Measurement {
+    for (0..warmup_iterations) |_| {
+        _ = try benchmark.run(arena.allocator());
+        _ = arena.reset(.retain_capacity);
+    }
+
+    var total: u64 = 0;
+    var min: u64 = std.math.maxInt(u64);
+    var iterations: u64 = 0;
+    while (iterations < min_iterations or total < min_time) : (iterations = iterations + 1) {
+        const elapsed = try benchmark.run(arena.allocator());
+        _ = arena.reset(.retain_capacity);
+
+        total = total + elapsed;
+        min = @min(min, elapsed);
+    }
+
+    return .{
+        .name = benchmark.name,
+        .mean = total / iterations,
+        .min = min
+    };
+}
+
+fn initUnit(allocator: Allocator, stream: *std.io.FixedBufferStream([]const u8)) !*Unit {
+    return try Unit.init(.{
+        .input = "large.ruka",
+        .output = "",
+        .reader = stream.reader().any(),
+        .writer = std.io.null_writer.any(),
+        .allocator = allocator
+    });
+}
+
+fn scan(allocator: Allocator) anyerror!u64 {
+    var stream = std.io.fixedBufferStream(input);
+    const unit = try initUnit(allocator, &stream);
+    defer unit.deinit();
+
+    var timer = try std.time.Timer.start();
+    const tokens = try unit.scan();
+    const elapsed = timer.read();
+
+    for (tokens.items) |token| token.deinit();
+    tokens.deinit();
+
+    return elapsed;
+}
+
+fn parse(allocator: Allocator) anyerror!u64 {
+    var stream = std.io.fixedBufferStream(input);
+    const unit = try initUnit(allocator, &stream);
+    defer unit.deinit();
+
+    const tokens = try unit.scan();
+    defer {
+        for (tokens.items) |token| token.deinit();
+        tokens.deinit();
+    }
+
+    var timer = try std.time.Timer.start();
+    const ast = try unit.parseTokens(tokens.items);
+    const elapsed = timer.read();
+
+    ast.deinit();
+    return elapsed;
+}
+
+fn check(allocator: Allocator) anyerror!u64 {
+    var stream = std.io.fixedBufferStream(input);
+    const unit = try initUnit(allocator, &stream);
+    defer unit.deinit();
+
+    const ast = try unit.parse();
+    defer ast.deinit();
+
+    var timer = try std.time.Timer.start();
+    const types = try unit.checkAst(ast);
+    const elapsed = timer.read();
+
+    types.deinit();
+    return elapsed;
+}
+
+fn compileAlone(allocator: Allocator) anyerror!u64 {
+    return try compileProject(allocator, false);
+}
+
+fn compileConcurrently(allocator: Allocator) anyerror!u64 {
+    return try compileProject(allocator, true);
+}
+
+// Checks a project whose main.ruka imports modules which don't import each other,
+// measuring only the compile
+fn compileProject(allocator: Allocator, parallel: bool) !u64 {
+    // The arena isn't safe to share between the compiler's threads
+    var thread_safe: std.heap.ThreadSafeAllocator = .{ .child_allocator = allocator };
+    const shared = thread_safe.allocator();
+
+    var memory = MemFs.init(shared);
+    defer memory.deinit();
+    try writeProject(allocator, &memory);
+
+    var compiler = try Compiler.initWithFs(shared, "main.ruka", null, .{
+        .check_only = true,
+        .parallel = parallel
+    }, memory.fs());
+    defer compiler.deinit();
+
+    var timer = try std.time.Timer.start();
+    const result = try compiler.compile();
+    const elapsed = timer.read();
+
+    if (result) |compiled| compiled.deinit();
+    return elapsed;
+}
+
+fn writeProject(allocator: Allocator, memory: *MemFs) !void {
+    var main_source = std.ArrayList(u8).init(allocator);
+    defer main_source.deinit();
+
+    for (0..project_modules) |module| {
+        try main_source.writer().print("import lib_{}\n", .{module});
+
+        var source = std.ArrayList(u8).init(allocator);
+        defer source.deinit();
+
+        for (0..project_functions) |function| {
+            try source.writer().print(
+                "pub fn lib_{}_f_{}(x: i64) -> i64 {{ x * {} + 1 }}\n",
+                .{module, function, function}
+            );
+        }
+
+        const path = try std.fmt.allocPrint(allocator, "lib_{}.ruka", .{module});
+        defer allocator.free(path);
+        try memory.write(path, source.items);
+    }
+
+    try main_source.appendSlice("let y: i64 = lib_0_f_1(2)\n");
+    try memory.write("main.ruka", main_source.items);
+}
+
+// Baselines are json objects holding the mean nanoseconds of each benchmark by name
+fn saveBaseline(path: []const u8, measurements: []const Measurement) !void {
+    const file = try std.fs.cwd().createFile(path, .{});
+    defer file.close();
+
+    var buffered = std.io.bufferedWriter(file.writer());
+    const writer = buffered.writer();
+
+    try writer.writeByte('{');
+    for (measurements, 0..) |measurement, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writer.writeAll("\n    ");
+        try std.json.encodeJsonString(measurement.name, .{}, writer);
+        try writer.print(": {}", .{measurement.mean});
+    }
+    try writer.writeAll("\n}\n");
+
+    try buffered.flush();
+}
+
+// Whether no benchmark is slower than its baseline by more than the tolerance.
+// Benchmarks missing from the baseline are new, so are only noted, but a missing
+// baseline fails as nothing could be compared
+fn compareBaseline(allocator: Allocator, path: []const u8, measurements: []const Measurement) !bool {
+    const contents = std.fs.cwd().readFileAlloc(allocator, path, 1 << 20) catch |err| switch (err) {
+        error.FileNotFound => {
+            std.log.err("there is no baseline at {s} to compare with, record one with --save", .{path});
+            return false;
+        },
+        else => |e| return e
+    };
+    defer allocator.free(contents);
+
+    const parsed = try std.json.parseFromSlice(std.json.Value, allocator, contents, .{});
+    defer parsed.deinit();
+
+    if (parsed.value != .object) return error.InvalidBaseline;
+    const baseline = parsed.value.object;
+
+    var passed = true;
+    for (measurements) |measurement| {
+        const expected: f64 = switch (baseline.get(measurement.name) orelse {
+            std.log.info("{s} isn't in the baseline", .{measurement.name});
+            continue;
+        }) {
+            .integer => |integer| @floatFromInt(integer),
+            .float => |float| float,
+            else => return error.InvalidBaseline
+        };
+
+        const change = (@as(f64, @floatFromInt(measurement.mean)) - expected) / expected;
+        if (change > tolerance) {
+            std.log.err("{s} is {d:.1}% slower than the baseline", .{measurement.name, change * 100});
+            passed = false;
+        }
+    }
+
+    return passed;
+}
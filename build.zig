@@ -9,6 +9,9 @@ pub fn build(b: *std.Build) void {
 
     const optimize = b.standardOptimizeOption(.{});
 
+    const llvm_backend = b.option(bool, "llvm-backend", "Include the llvm backend, which links against libLLVM") orelse false;
+    const wasm_backend = b.option(bool, "wasm-backend", "Include the backend generating WebAssembly modules") orelse false;
+
     const bin = b.addExecutable(.{
         .name = "ruka",
         .root_source_file = b.path("src/main.zig"),
@@ -22,8 +25,15 @@ pub fn build(b: *std.Build) void {
     options.addOption(std.SemanticVersion, "version", getVersion(b));
     options.addOption([]const u8, "version_date", getDate(b));
     options.addOption([]const u8, "description", description);
+    options.addOption(bool, "llvm_backend", llvm_backend);
+    options.addOption(bool, "wasm_backend", wasm_backend);
     bin.root_module.addOptions("options", options);
 
+    if (llvm_backend) {
+        bin.linkLibC();
+        bin.linkSystemLibrary("LLVM");
+    }
+
     const run_cmd = b.addRunArtifact(bin);
 
     run_cmd.step.dependOn(b.getInstallStep());
@@ -44,12 +54,72 @@ pub fn build(b: *std.Build) void {
         .optimize = optimize,
     });
 
+    bin_unit_tests.root_module.addOptions("options", options);
+
+    if (llvm_backend) {
+        bin_unit_tests.linkLibC();
+        bin_unit_tests.linkSystemLibrary("LLVM");
+    }
+
     const run_bin_unit_tests = b.addRunArtifact(bin_unit_tests);
     run_bin_unit_tests.addArg("--suite bin");
 
     const test_step = b.step("test", "Run unit tests");
     test_step.dependOn(&run_bin_unit_tests.step);
 
+    // Benchmarks, always optimized as their timings are only meaningful when they are
+    const ruka_module = b.createModule(.{
+        .root_source_file = b.path("src/prelude.zig"),
+        .target = target,
+        .optimize = .ReleaseFast
+    });
+    ruka_module.addOptions("options", options);
+
+    const bench = b.addExecutable(.{
+        .name = "bench",
+        .root_source_file = b.path("benches/main.zig"),
+        .target = target,
+        .optimize = .ReleaseFast
+    });
+    bench.root_module.addImport("ruka", ruka_module);
+
+    if (llvm_backend) {
+        bench.linkLibC();
+        bench.linkSystemLibrary("LLVM");
+    }
+
+    const run_bench = b.addRunArtifact(bench);
+    if (b.args) |args| {
+        run_bench.addArgs(args);
+    }
+
+    const bench_step = b.step("bench", "Run the benchmarks");
+    bench_step.dependOn(&run_bench.step);
+
+    // Fuzz targets, run over their corpus or fuzzed with `zig build fuzz --fuzz`
+    const fuzz_module = b.createModule(.{
+        .root_source_file = b.path("src/prelude.zig"),
+        .target = target,
+        .optimize = optimize
+    });
+    fuzz_module.addOptions("options", options);
+
+    const fuzz = b.addTest(.{
+        .name = "fuzz",
+        .root_source_file = b.path("fuzz/main.zig"),
+        .target = target,
+        .optimize = optimize
+    });
+    fuzz.root_module.addImport("ruka", fuzz_module);
+
+    if (llvm_backend) {
+        fuzz.linkLibC();
+        fuzz.linkSystemLibrary("LLVM");
+    }
+
+    const fuzz_step = b.step("fuzz", "Run the fuzz targets");
+    fuzz_step.dependOn(&b.addRunArtifact(fuzz).step);
+
     const coverage_step = b.step("coverage", "Generate test coverage");
 
     const merge_step = std.Build.Step.Run.create(b, "merge coverage");
@@ -0,0 +1,73 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Fuzz targets feeding arbitrary bytes to the scanner and parser, which must
+//! report what they can't make sense of rather than panic, loop forever or leak.
+//! Run with `zig build fuzz --fuzz`, without --fuzz each target is only run over
+//! its corpus.
+//!
+//! Inputs aren't filtered to valid utf8, as the compiler reads source files
+//! without validating them. Inputs which crashed the targets are added to the
+//! corpus once fixed, such as deeply nested input which overflowed the parser's
+//! stack before it limited nesting to Parser.max_depth
+
+const std = @import("std");
+
+const ruka = @import("ruka");
+const Parser = ruka.Parser;
+const Unit = ruka.Unit;
+
+const corpus = [_][]const u8{
+    "",
+    "let x = 1\n",
+    "fn add(a: i64, b: i64) -> i64 {\n    a + b\n}\n",
+    "var total = 0\nfor i in 0..10 {\n    total = total + i\n}\n",
+    "enum Shape { Circle(i64) }\nmatch s { Shape::Circle(r) => r * r }\n",
+    "struct Point { x: i64, y: i64 }\nimpl Point { fn len(self) -> i64 { self.x } }\n",
+    "let f = fn(x: i64) -> i64 { x * 2 }\n",
+    "let s = \"unterminated\n",
+    "/* unterminated comment",
+    "0x 1e+ 1.. 'a '\\u{zz}'",
+    "(" ** (Parser.max_depth + 1) ++ "1",
+    "- " ** (Parser.max_depth + 1) ++ "1",
+    "let x: " ++ "[" ** (Parser.max_depth + 1) ++ "i64",
+    "{" ** (Parser.max_depth + 1)
+};
+
+test "fuzz scanner" {
+    try std.testing.fuzz(scanOne, .{ .corpus = &corpus });
+}
+
+test "fuzz parser" {
+    try std.testing.fuzz(parseOne, .{ .corpus = &corpus });
+}
+
+fn initUnit(stream: *std.io.FixedBufferStream([]const u8)) !*Unit {
+    return try Unit.init(.{
+        .input = "fuzz.ruka",
+        .output = "",
+        .reader = stream.reader().any(),
+        .writer = std.io.null_writer.any(),
+        .allocator = std.testing.allocator
+    });
+}
+
+fn scanOne(input: []const u8) anyerror!void {
+    var stream = std.io.fixedBufferStream(input);
+    const unit = try initUnit(&stream);
+    defer unit.deinit();
+
+    // Errors scanning are reported in the unit's diagnostics, only panics fail
+    const tokens = unit.scan() catch return;
+    for (tokens.items) |token| token.deinit();
+    tokens.deinit();
+}
+
+fn parseOne(input: []const u8) anyerror!void {
+    var stream = std.io.fixedBufferStream(input);
+    const unit = try initUnit(&stream);
+    defer unit.deinit();
+
+    const ast = unit.parse() catch return;
+    ast.deinit();
+}
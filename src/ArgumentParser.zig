@@ -7,6 +7,7 @@ const ArrayList = std.ArrayList;
 const LinearFifo = std.fifo.LinearFifo;
 
 const libruka = @import("prelude.zig");
+const Options = libruka.Options;
 const Transport = libruka.Transport;
 const constants = @import("constants.zig");
 
@@ -25,6 +26,7 @@ const Subcommand = enum {
     @"test",
     run,
     repl,
+    lsp,
     version,
     help
 };
@@ -35,12 +37,25 @@ const subcommandsMap = std.StaticStringMap(Subcommand).initComptime(.{
     .{"test", .@"test"},
     .{"run", .run},
     .{"repl", .repl},
+    .{"lsp", .lsp},
     .{"version", .version},
     .{"help", .help}
 });
 
 const Option = union(enum) {
     change_dir: []const u8,
+    color: Options.ColorChoice,
+    dependency_file: []const u8,
+    emit: Options.EmitKind,
+    linker_flag: []const u8,
+    max_errors: usize,
+    target: []const u8,
+    check,
+    fmt,
+    lint,
+    profile_compile_time,
+    @"test",
+    watch,
 
     pub fn init(option: []const u8, value: []const u8) ?Option {
         if (std.mem.eql(u8, option, "change_dir")
@@ -49,6 +64,64 @@ const Option = union(enum) {
             return .{ .change_dir = value };
         }
 
+        if (std.mem.eql(u8, option, "color")) {
+            return .{ .color = Options.ColorChoice.fromName(value) orelse return null };
+        }
+
+        if (std.mem.eql(u8, option, "dependency-file")) {
+            return .{ .dependency_file = value };
+        }
+
+        if (std.mem.eql(u8, option, "emit")) {
+            return .{ .emit = Options.EmitKind.fromName(value) orelse return null };
+        }
+
+        if (std.mem.eql(u8, option, "linker-flag")) {
+            return .{ .linker_flag = value };
+        }
+
+        if (std.mem.eql(u8, option, "max-errors")) {
+            return .{ .max_errors = std.fmt.parseInt(usize, value, 10) catch return null };
+        }
+
+        if (std.mem.eql(u8, option, "target")) {
+            return .{ .target = value };
+        }
+
+        return null;
+    }
+
+    /// Creates the option for flags which don't take a value
+    pub fn initFlag(option: []const u8) ?Option {
+        if (std.mem.eql(u8, option, "check")) {
+            return .check;
+        }
+
+        if (std.mem.eql(u8, option, "fmt")) {
+            return .fmt;
+        }
+
+        if (std.mem.eql(u8, option, "lint")) {
+            return .lint;
+        }
+
+        if (std.mem.eql(u8, option, "profile-compile-time")) {
+            return .profile_compile_time;
+        }
+
+        if (std.mem.eql(u8, option, "test")) {
+            return .@"test";
+        }
+
+        if (std.mem.eql(u8, option, "watch")) {
+            return .watch;
+        }
+
+        // Shorthand for --emit ast-json
+        if (std.mem.eql(u8, option, "print-ast")) {
+            return .{ .emit = .ast_json };
+        }
+
         return null;
     }
 };
@@ -90,6 +163,12 @@ pub fn parse(self: *ArgumentParser) !void {
 
     if (subcommandsMap.get(subcommand_arg.?)) |subcommand| {
         try self.subcommands.writeItem(subcommand);
+    } else if (std.mem.eql(u8, subcommand_arg.?, "--repl")) {
+        // The flag form of the repl subcommand
+        try self.subcommands.writeItem(.repl);
+    } else if (std.mem.eql(u8, subcommand_arg.?, "--lsp")) {
+        // The flag form of the lsp subcommand, which is how editors usually start servers
+        try self.subcommands.writeItem(.lsp);
     } else {
         try self.transport.print("{s}\n{s}\n\nInvalid subcommand: {s}\n", .{
             constants.usage,
@@ -102,6 +181,17 @@ pub fn parse(self: *ArgumentParser) !void {
 
     while (args.next()) |arg| {
         if (std.mem.startsWith(u8, arg, "--")) {
+            // Options may be given their values after an =, as in --color=never
+            if (std.mem.indexOfScalar(u8, arg, '=')) |equals| {
+                try self.addOption(arg[0..equals], arg[equals + 1..], 2);
+                continue;
+            }
+
+            if (Option.initFlag(arg[2..])) |option| {
+                try self.options.writeItem(option);
+                continue;
+            }
+
             if (args.next()) |value| {
                 try self.addOption(arg, value, 2);
                 continue;
@@ -147,5 +237,19 @@ test "argumentParser" {
 }
 
 const tests = struct {
-
+    const testing = std.testing;
+
+    test "options with values" {
+        try testing.expectEqual(Option{ .color = .never }, Option.init("color", "never").?);
+        try testing.expectEqual(Option{ .color = .always }, Option.init("color", "always").?);
+        try testing.expectEqual(null, Option.init("color", "sometimes"));
+        try testing.expectEqual(Option{ .emit = .ast_json }, Option.init("emit", "ast-json").?);
+        try testing.expectEqual(Option{ .max_errors = 10 }, Option.init("max-errors", "10").?);
+        try testing.expectEqualStrings("build.d", Option.init("dependency-file", "build.d").?.dependency_file);
+        try testing.expectEqual(Option{ .emit = .ast_json }, Option.initFlag("print-ast").?);
+        try testing.expectEqual(Option.@"test", Option.initFlag("test").?);
+        try testing.expectEqual(Option.profile_compile_time, Option.initFlag("profile-compile-time").?);
+        try testing.expectEqual(Option{ .emit = .timing_json }, Option.init("emit", "timing-json").?);
+        try testing.expectEqual(null, Option.init("max-errors", "ten"));
+    }
 };
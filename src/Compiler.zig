@@ -2,74 +2,207 @@
 // @created: 2024-03-04
 
 const std = @import("std");
+const builtin = @import("builtin");
 const Allocator = std.mem.Allocator;
 const ArenaAllocator = std.heap.ArenaAllocator;
 const ArrayList = std.ArrayList;
 const Dir = std.fs.Dir;
-const LinearFifo = std.fifo.LinearFifo;
 const Mutex = std.Thread.Mutex;
 const Pool = std.Thread.Pool;
+const StringHashMap = std.StringHashMap;
 const WaitGroup = std.Thread.WaitGroup;
 
 const ruka = @import("prelude.zig");
+const analysis = ruka.analysis;
 const Ast = ruka.Ast;
-const Error = ruka.Error;
+const Chrono = ruka.Chrono;
+const constants = ruka.constants;
+const Formatter = ruka.Formatter;
+const LanguageServer = ruka.LanguageServer;
+const lints = ruka.lints;
+const Node = ruka.Node;
+const optimizer = ruka.optimizer;
 const Scanner = ruka.Scanner;
+const Span = ruka.Span;
+const Token = ruka.Token;
 const Transport = ruka.Transport;
+const Type = ruka.Type;
+const Visitor = ruka.Visitor;
 
 cwd: Dir,
-errors: ArrayList(Error),
+/// Where sources are read from and outputs written to, real_fs unless the
+/// compiler was given another
+fs: VirtualFs,
+/// Reads and writes the files of cwd
+real_fs: VirtualFs.RealFs,
+options: Options,
+/// The files compiled together by compile, along with their contents
+sources: ArrayList(Source),
+/// Where compile writes its output, derived from the first source when null
+output: ?[]const u8,
+/// The modules loaded by the last compile, keyed by their resolved paths so each
+/// file is loaded once however many files import it
+modules: StringHashMap(Module),
+/// The paths of the files read by the last compile, in the order they were
+/// loaded, written to options.dependency_file
+dependencies: ArrayList([]const u8),
+/// Diagnostics from every unit of the last compile, copied into the compilation arena
+diagnostics: ArrayList(Diagnostic),
+/// The errors left out of diagnostics by options.max_errors, counted across every
+/// unit and noted once when compilation ends
+hidden_errors: usize,
+/// Loaded from options.cache_dir by the first compile
+cache: ?Cache,
+/// The time each phase of the last compile took, recorded when compiling with
+/// options.profile_compile_time or emitting timing json
+profile: Profile,
+/// Why the last public function to fail did, null if none has
+failure: ?Failure,
 transport: *Transport,
 
-root: *Ast,
-unprocessed_asts: ArrayList(*Ast),
-
 allocator: Allocator,
+/// Owns what lives as long as the compiler, its sources and output path
 arena: ArenaAllocator,
+/// Owns what compiling allocates, such as diagnostics, imported modules and reloaded
+/// sources. Replaced each time watching reloads the sources, freeing the last
+compilation: ArenaAllocator,
 
+/// Guards the diagnostics kept and the cache while files are parsed on the
+/// thread pool, see CompileGraph
 mutex: Mutex,
 thread_pool: Pool,
-wait_group: WaitGroup,
-job_queue: LinearFifo(Job, .Dynamic),
 
 const Compiler = @This();
 
 const log = std.log.scoped(.compiler);
 
+pub const Cache = @import("compiler/Cache.zig");
+pub const CompileGraph = @import("compiler/CompileGraph.zig");
+pub const Diagnostic = @import("compiler/Diagnostic.zig");
+pub const Interner = @import("compiler/Interner.zig");
+pub const Options = @import("compiler/Options.zig");
+pub const Profile = @import("compiler/Profile.zig");
+pub const Scope = @import("compiler/Scope.zig");
+pub const SymbolTable = @import("compiler/SymbolTable.zig");
+pub const Target = @import("compiler/Target.zig");
 pub const Unit = @import("compiler/Unit.zig");
+pub const VirtualFs = @import("compiler/VirtualFs.zig");
 
-const Status = enum {
-
+/// A file given to the compiler and its contents, both owned by the compiler
+pub const Source = struct {
+    path: []const u8,
+    contents: []const u8,
+    /// When the file was last modified as its contents were read, null if they
+    /// weren't read from it
+    mtime: ?i128 = null
 };
 
-pub const Job = union(enum) {
-    parse_file: []const u8,
-    combine_asts,
-    check_ast_semantics,
+/// Every error returned by the compiler's public functions, so callers can
+/// switch on them exhaustively. Errors from deeper within the compiler are
+/// converted to IoFailed or InternalError, with failure holding the original
+pub const Error = Allocator.Error || error{
+    /// Reading or writing a file failed
+    IoFailed,
+    InvalidExtension,
+    UnknownTarget,
+    ScanningFailed,
+    ExpansionFailed,
+    ParsingFailed,
+    ResolutionFailed,
+    MergingFailed,
+    TypeCheckingFailed,
+    AnalysisFailed,
+    CodegenFailed,
+    /// The system's c compiler failed, or couldn't be run
+    NativeBuildFailed,
+    /// options.emit selects a backend this build of the compiler doesn't include
+    BackendUnavailable,
+    /// An error the compiler didn't expect to return
+    InternalError
+};
 
-    const SynchronizationMode = enum {
-        exclusive,
-        shared,
-        atomic
-    };
+/// What went wrong when a public function returned an error, kept in failure
+pub const Failure = union(enum) {
+    /// The error reading or writing a file
+    io: anyerror,
+    /// A file given to the compiler doesn't have the .ruka extension
+    invalid_extension,
+    /// The triple of options.target
+    unknown_target: []const u8,
+    /// How many errors were reported, by the phase the error names
+    errors: usize,
+    /// What the c compiler printed, or why it couldn't be run
+    link: []const u8,
+    out_of_memory,
+    internal: anyerror,
 
-    pub fn deinit(self: Job, allocator: Allocator) void {
+    pub fn format(self: Failure, comptime _: []const u8, _: std.fmt.FormatOptions, writer: anytype) !void {
         switch (self) {
-            .parse_file => |file| allocator.free(file),
-            else => {}
+            .io => |err| try writer.print("a file couldn't be read or written: {s}", .{@errorName(err)}),
+            .invalid_extension => try writer.writeAll("a file given to the compiler doesn't have the ." ++ extension ++ " extension"),
+            .unknown_target => |triple| try writer.print("unknown target triple {s}", .{triple}),
+            .errors => |count| try writer.print("{} error{s} reported", .{count, if (count == 1) "" else "s"}),
+            .link => |message| try writer.writeAll(message),
+            .out_of_memory => try writer.writeAll("out of memory"),
+            .internal => |err| try writer.print("internal compiler error: {s}", .{@errorName(err)})
         }
     }
+};
 
-    fn syncMode(self: Job) SynchronizationMode {
-        return switch (self) {
-            .parse_file => |_| .shared,
-            .combine_asts => .exclusive,
-            .check_ast_semantics => .exclusive,
-        };
+// The errors of files and processes, converted to Error.IoFailed
+const IoError = std.fs.File.OpenError || std.fs.File.ReadError || std.fs.File.WriteError ||
+    std.fs.File.StatError || std.fs.Dir.MakeError || std.fs.Dir.DeleteFileError;
+
+/// A file loaded by compile, either one of the sources or a file they import
+const Module = struct {
+    source: Source,
+    /// Null until the file is parsed, or if it failed to parse, and once it is
+    /// merged into the compiled ast
+    ast: ?*Ast,
+    /// Whether the file is one of the sources, which share their top level names
+    /// as they compile as a single module
+    root: bool,
+    /// Set while the module's imports are being loaded, so cycles are detected
+    loading: bool,
+    /// The resolved paths of the modules it imports
+    imports: ArrayList([]const u8),
+    /// Its top level names, mapped to whether they are exported
+    definitions: StringHashMap(bool),
+
+    fn deinit(self: *Module) void {
+        if (self.ast) |ast| ast.deinit();
+        self.imports.deinit();
+        self.definitions.deinit();
+    }
+
+    fn isImporting(self: *const Module, path: []const u8) bool {
+        for (self.imports.items) |imported| {
+            if (std.mem.eql(u8, imported, path)) return true;
+        }
+
+        return false;
     }
 };
 
-pub fn init(allocator: Allocator) !*Compiler {
+/// The extension of source files
+const extension = "ruka";
+
+/// How often watch checks whether the files it compiled have changed
+const watch_interval = 100 * std.time.ns_per_ms;
+/// How long watch waits after a file changes before compiling, as editors often
+/// write a file more than once when saving it
+const watch_debounce = 50 * std.time.ns_per_ms;
+
+/// Set by ctrl+c while watching, which then stops
+var interrupted = std.atomic.Value(bool).init(false);
+
+/// Creates a compiler for the project in the current directory, options apply to
+/// every unit it compiles
+pub fn init(allocator: Allocator, options: Options) Error!*Compiler {
+    return create(allocator, options) catch |err| return errorOf(err);
+}
+
+fn create(allocator: Allocator, options: Options) !*Compiler {
     const compiler = try allocator.create(Compiler);
     errdefer compiler.deinit();
 
@@ -78,21 +211,31 @@ pub fn init(allocator: Allocator) !*Compiler {
 
     compiler.* = .{
         .cwd = std.fs.cwd(),
-        .errors = .init(allocator),
+        .fs = undefined,
+        .real_fs = undefined,
+        .options = options,
+        .sources = .init(allocator),
+        .output = null,
+        .modules = .init(allocator),
+        .dependencies = .init(allocator),
+        .diagnostics = .init(allocator),
+        .hidden_errors = 0,
+        .cache = null,
+        .profile = .init(allocator),
+        .failure = null,
         .transport = try .init(allocator, stdin.any(), stderr.any()),
 
-        .root = undefined,
-        .unprocessed_asts = .init(allocator),
-
         .allocator = allocator,
         .arena = .init(allocator),
+        .compilation = .init(allocator),
 
         .mutex = .{},
-        .thread_pool = undefined,
-        .wait_group = .{},
-        .job_queue = .init(allocator)
+        .thread_pool = undefined
     };
 
+    compiler.real_fs = .init(&compiler.cwd);
+    compiler.fs = compiler.real_fs.fs();
+
     try compiler.thread_pool.init(.{
         .allocator = allocator,
         .n_jobs = 4
@@ -101,167 +244,2509 @@ pub fn init(allocator: Allocator) !*Compiler {
     return compiler;
 }
 
+/// Creates a compiler for the files at inputs, which compile together as a single
+/// module. Each must have the .ruka extension
+pub fn initFiles(
+    allocator: Allocator,
+    inputs: []const []const u8,
+    output: ?[]const u8,
+    options: Options
+) Error!*Compiler {
+    const compiler = try init(allocator, options);
+    errdefer compiler.deinit();
+
+    if (output) |path| compiler.output = try compiler.arena.allocator().dupe(u8, path);
+
+    try compiler.sources.ensureTotalCapacity(inputs.len);
+    for (inputs) |input| compiler.addPath(input) catch |err| return compiler.fail(err);
+
+    return compiler;
+}
+
+/// Creates a compiler for the single file at input
+pub fn initFile(allocator: Allocator, input: []const u8, output: ?[]const u8, options: Options) Error!*Compiler {
+    return try initPath(allocator, input, output, options);
+}
+
+/// Creates a compiler for the single file at path, which may be absolute or
+/// relative to the current directory and may hold `.` and `..` components
+pub fn initPath(allocator: Allocator, path: []const u8, output: ?[]const u8, options: Options) Error!*Compiler {
+    const compiler = try init(allocator, options);
+    errdefer compiler.deinit();
+
+    if (output) |out| compiler.output = try compiler.arena.allocator().dupe(u8, out);
+    compiler.addPath(path) catch |err| return compiler.fail(err);
+
+    return compiler;
+}
+
+/// Creates a compiler for the single file at input which reads and writes files
+/// through fs rather than the real file system, fs must outlive the compiler
+pub fn initWithFs(
+    allocator: Allocator,
+    input: []const u8,
+    output: ?[]const u8,
+    options: Options,
+    fs: VirtualFs
+) Error!*Compiler {
+    const compiler = try init(allocator, options);
+    errdefer compiler.deinit();
+
+    compiler.fs = fs;
+    if (output) |out| compiler.output = try compiler.arena.allocator().dupe(u8, out);
+    compiler.addPath(input) catch |err| return compiler.fail(err);
+
+    return compiler;
+}
+
+// Reads the source at path, which must have the .ruka extension. The path is
+// resolved first, so the file is named as loadImport would name it when imported
+fn addPath(self: *Compiler, path: []const u8) !void {
+    if (!isProperExtension(path)) {
+        log.err("{s}: invalid file extension, expected .ruka, got: {s}", .{path, std.fs.path.extension(path)});
+        return error.InvalidExtension;
+    }
+
+    const arena = self.arena.allocator();
+    try self.sources.append(try readSource(self.fs, arena, try std.fs.path.resolve(arena, &.{path})));
+}
+
+/// Adds each source file of the project, those within its src directory, as a
+/// source compiled by compile
+pub fn addProjectSources(self: *Compiler) Error!void {
+    self.readProjectSources() catch |err| return self.fail(err);
+}
+
+fn readProjectSources(self: *Compiler) !void {
+    var src = try self.cwd.openDir("src", .{ .iterate = true });
+    defer src.close();
+
+    var iter = try src.walk(self.allocator);
+    defer iter.deinit();
+
+    const arena = self.arena.allocator();
+    while (try iter.next()) |item| {
+        if (item.kind != .file or !isProperExtension(item.path)) continue;
+
+        const path = try std.fs.path.join(arena, &.{"src", item.path});
+        try self.sources.append(try readSource(self.fs, arena, path));
+    }
+}
+
+/// Creates a compiler for a single file whose contents are given rather than
+/// read, such as a document being edited which hasn't been saved. The files it
+/// imports are still read
+pub fn initSource(allocator: Allocator, source: Source, options: Options) Error!*Compiler {
+    const compiler = try init(allocator, options);
+    errdefer compiler.deinit();
+
+    const arena = compiler.arena.allocator();
+    try compiler.sources.append(.{
+        .path = try arena.dupe(u8, source.path),
+        .contents = try arena.dupe(u8, source.contents)
+    });
+
+    return compiler;
+}
+
 pub fn deinit(self: *Compiler) void {
-    self.wait_group.wait();
     self.thread_pool.deinit();
-    while (self.job_queue.readItem()) |job| job.deinit(self.allocator);
-    self.job_queue.deinit();
-    self.errors.deinit();
+    self.sources.deinit();
+    self.clearModules();
+    self.modules.deinit();
+    self.dependencies.deinit();
+    self.diagnostics.deinit();
+    if (self.cache) |*cache| cache.deinit();
+    self.profile.deinit();
+    self.compilation.deinit();
     self.arena.deinit();
     self.transport.deinit();
-    for (self.unprocessed_asts.items) |ast| {
-        ast.deinit();
-    }
-    self.unprocessed_asts.deinit();
     self.allocator.destroy(self);
 }
 
-pub fn buildProject(self: *Compiler) !void {
-    try self.verifyProject(self.cwd);
-    try self.sendProject(self.cwd);
+/// Formats each source file of the project in place, or only checks them when
+/// options.check_only is set, logging those which aren't formatted. Returns the
+/// number of files which weren't formatted
+pub fn formatProject(self: *Compiler) Error!usize {
+    return self.formatSources() catch |err| return self.fail(err);
+}
 
-    try self.job_queue.ensureUnusedCapacity(2);
-    try self.job_queue.writeItem(.combine_asts);
-    try self.job_queue.writeItem(.check_ast_semantics);
+fn formatSources(self: *Compiler) !usize {
+    var src = try self.cwd.openDir("src", .{ .iterate = true });
+    defer src.close();
 
-    while (self.job_queue.readItem()) |job| {
-        switch (job.syncMode()) {
-            .exclusive => {
-                self.waitAndWork();
-                self.processJob(job, null);
-            },
-            .shared => {
-                self.wait_group.start();
-                errdefer job.deinit(self.allocator);
-                try self.thread_pool.spawn(processJob, .{self, job, &self.wait_group});
-            },
-            .atomic => {
-                errdefer job.deinit(self.allocator);
-                try self.thread_pool.spawn(processJob, .{self, job, null});
-            }
+    var iter = try src.walk(self.allocator);
+    defer iter.deinit();
+
+    var formatter = Formatter.init(self.allocator, self.options.format);
+    defer formatter.deinit();
+
+    var unformatted: usize = 0;
+    while (try iter.next()) |item| {
+        if (item.kind != .file or !isProperExtension(item.path)) continue;
+
+        const source = try src.readFileAlloc(self.allocator, item.path, std.math.maxInt(usize));
+        defer self.allocator.free(source);
+
+        const formatted = try self.formatFile(&formatter, src, item.path, source);
+        defer self.allocator.free(formatted);
+
+        if (std.mem.eql(u8, source, formatted)) continue;
+        unformatted = unformatted + 1;
+
+        if (self.options.check_only) {
+            log.err("{s}: isn't formatted", .{item.path});
+        } else {
+            try src.writeFile(.{ .sub_path = item.path, .data = formatted });
         }
     }
+
+    return unformatted;
+}
+
+// Formats source in a unit of its own, reporting the errors parsing it
+fn formatFile(self: *Compiler, formatter: *Formatter, dir: Dir, path: []const u8, source: []const u8) ![]u8 {
+    var input = std.io.fixedBufferStream(source);
+    const reader = input.reader();
+    const writer = std.io.null_writer;
+
+    var unit = try Unit.init(.{
+        .input = path,
+        .output = path,
+        .reader = reader.any(),
+        .writer = writer.any(),
+        .allocator = self.allocator,
+        .options = self.options
+    });
+    defer unit.deinit();
+
+    // Formatting stops at the first file which can't be parsed
+    return formatter.formatUnit(unit, source) catch |err| {
+        try self.reportDiagnostics(unit, dir, path);
+        try self.noteHiddenErrors();
+        return err;
+    };
 }
 
-fn waitAndWork(self: *Compiler) void {
-    self.thread_pool.waitAndWork(&self.wait_group);
-    self.wait_group.reset();
+fn isProperExtension(file: []const u8) bool {
+    return std.mem.eql(u8, std.fs.path.extension(file), "." ++ extension);
 }
 
-fn sendFile(self: *Compiler, file: []const u8) !void {
-    errdefer self.allocator.free(file);
+// Renders the unit's diagnostics to stderr and keeps copies of them
+fn reportDiagnostics(self: *Compiler, unit: *Unit, dir: Dir, path: []const u8) !void {
+    if (unit.diagnostics.items.len == 0) return;
+
+    const source = try dir.readFileAlloc(self.allocator, path, std.math.maxInt(usize));
+    defer self.allocator.free(source);
 
-    try self.job_queue.ensureUnusedCapacity(1);
-    try self.job_queue.writeItem(.{ .parse_file = file });
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    const stderr = std.io.getStdErr();
+    const writer = stderr.writer();
+    var terminal = Diagnostic.TerminalEmitter.init(writer.any(), self.options.color.enabled(stderr));
+
+    try self.keepDiagnostics(unit, &terminal, source);
 }
 
-fn verifyProject(self: *Compiler, dir: Dir) !void {
-    _ = self;
-    _ = dir;
+/// Scans and parses each source on its own, along with the files they import, then
+/// type checks them as a single module and writes what options.emit selects to the
+/// output. Names defined at the top level of more than one file are errors, as are
+/// uses of names imported files don't export. Nothing is written when
+/// options.check_only is set. Returns null if options.emit stops compilation
+/// before type checking
+pub fn compile(self: *Compiler) Error!?Unit.Result {
+    var generated = ArrayList(u8).init(self.allocator);
+    defer generated.deinit();
+
+    return self.compileInto(&generated, true) catch |err| return self.fail(err);
 }
 
-fn isProperExtension(file: []const u8) bool {
-    var path_iter = std.mem.splitBackwardsSequence(u8, file, ".");
-    const extension = path_iter.first();
+/// Compiles the sources as compile does, returning what options.emit selects
+/// rather than writing it to stdout. It is still written to the output as well
+/// when one was given. Checking returns nothing, and building binaries the c they
+/// were built from. The caller owns the returned memory
+pub fn compileToString(self: *Compiler) Error![]u8 {
+    var generated = ArrayList(u8).init(self.allocator);
+    errdefer generated.deinit();
 
-    return std.mem.eql(u8, "ruka", extension);
+    const result = self.compileInto(&generated, false) catch |err| return self.fail(err);
+    if (result) |compiled| compiled.deinit();
+
+    return try generated.toOwnedSlice();
 }
 
-fn sendProject(self: *Compiler, dir: Dir) !void {
-    const src = try dir.openDir("src", .{.iterate = true});
-    var iter = try src.walk(self.allocator);
-    defer iter.deinit();
+// Converts err, from anywhere within the compiler, to one of the errors of Error
+fn errorOf(err: anyerror) Error {
+    inline for (@typeInfo(Error).error_set.?) |known| {
+        if (err == @field(Error, known.name)) return @errorCast(err);
+    }
+    inline for (@typeInfo(IoError).error_set.?) |io| {
+        if (err == @field(IoError, io.name)) return error.IoFailed;
+    }
 
-    while (try iter.next()) |item| {
-        switch (item.kind) {
-            .file => {
-                if (!isProperExtension(item.path)) {
-                    var path_iter = std.mem.splitBackwardsSequence(u8, item.path, ".");
-                    log.err(
-                        "Invalid file extension, expected .ruka, got: .{s}\n",
-                        .{path_iter.first()}
-                    );
-
-                    continue;
-                }
+    return error.InternalError;
+}
+
+// Converts err as errorOf does, keeping what went wrong in failure
+fn fail(self: *Compiler, err: anyerror) Error {
+    const converted = errorOf(err);
+    self.failure = switch (converted) {
+        error.IoFailed => .{ .io = err },
+        error.InternalError => .{ .internal = err },
+        error.OutOfMemory => .out_of_memory,
+        error.InvalidExtension => .invalid_extension,
+        error.UnknownTarget => .{ .unknown_target = self.options.target orelse "" },
+        error.NativeBuildFailed => .{
+            .link = if (self.diagnostics.getLastOrNull()) |diagnostic| diagnostic.message else ""
+        },
+        else => .{ .errors = self.errorCount() }
+    };
 
-                const path = try self.allocator.dupe(u8, item.path);
-                errdefer self.allocator.free(path);
+    return converted;
+}
 
-                try self.sendFile(path);
-            },
-            else => continue
-        }
+// The errors kept from every unit
+fn errorCount(self: *const Compiler) usize {
+    var count: usize = 0;
+    for (self.diagnostics.items) |diagnostic| {
+        if (diagnostic.severity == .@"error") count = count + 1;
     }
+
+    return count;
 }
 
-fn processJob(self: *Compiler, job: Job, wait_group: ?*WaitGroup) void {
-    defer if (wait_group != null) wait_group.?.finish();
-    defer job.deinit(self.allocator);
+// Compiles the sources, generating into generated. It is written to the output,
+// or to stdout if there is none and to_stdout is set
+fn compileInto(self: *Compiler, generated: *ArrayList(u8), to_stdout: bool) !?Unit.Result {
+    self.hidden_errors = 0;
+    const result = self.compileSources(generated, to_stdout) catch |err| {
+        try self.noteHiddenErrors();
+        return err;
+    };
+    errdefer if (result) |compiled| compiled.deinit();
 
-    switch (job) {
-        .parse_file => |file| {
-            self.parseFile(file, null) catch |err| {
-                log.err("Failed to compile file: {}", .{err});
-            };
+    try self.noteHiddenErrors();
+    return result;
+}
+
+fn compileSources(self: *Compiler, generated: *ArrayList(u8), to_stdout: bool) !?Unit.Result {
+    std.debug.assert(self.sources.items.len > 0);
+    self.failure = null;
+    self.diagnostics.clearRetainingCapacity();
+    self.clearModules();
+    self.dependencies.clearRetainingCapacity();
+    self.profile.reset();
+
+    // Checked before any work is done, which would be wasted
+    if (self.options.target) |triple| if (Target.find(triple) == null) {
+        try self.reportError(try std.fmt.allocPrint(self.compilation.allocator(), "unknown target triple {s}", .{triple}));
+        return error.UnknownTarget;
+    };
+
+    try self.openCache();
+    defer self.saveCache();
+
+    const output = try self.outputPath();
+    const name = stem(output orelse self.sources.items[0].path);
+
+    var empty = std.io.fixedBufferStream("");
+    const reader = empty.reader();
+    const writer = generated.writer();
+
+    // Tokens are written before anything is parsed
+    if (self.options.emit == .tokens and !self.options.check_only) {
+        for (self.sources.items) |source| try self.scanSource(source, writer.any());
+
+        try self.writeOutput(output, generated.items, to_stdout);
+        return null;
+    }
+
+    const ast = try Ast.init(self.allocator, name);
+    errdefer ast.deinit();
+
+    var unit = try Unit.init(.{
+        .input = name,
+        .output = output orelse "",
+        .reader = reader.any(),
+        .writer = writer.any(),
+        .allocator = self.allocator,
+        .options = self.options
+    });
+    defer unit.deinit();
+    unit.compiler = self;
+    unit.profile = self.profiler();
+
+    // Modules in the order they are merged, each after the modules it imports
+    var order = ArrayList([]const u8).init(self.allocator);
+    defer order.deinit();
+
+    self.loadModules(unit, &order) catch |err| {
+        try self.reportSourceDiagnostics(unit);
+        return err;
+    };
+
+    var resolving = Profile.begin(self.profiler(), "resolve");
+    self.mergeModules(unit, ast, order.items) catch |err| {
+        try self.reportSourceDiagnostics(unit);
+        return err;
+    };
+    try resolving.end();
+
+    // Every file compiled has been read once the modules are merged
+    if (self.options.dependency_file) |path| try self.writeDependencyFile(path, output);
+
+    // The modules were parsed by units of their own
+    try unit.addLintLevels(ast);
+
+    if (!self.options.check_only) switch (self.options.emit) {
+        .ast, .ast_json => {
+            try unit.emitAst(ast);
+            try self.writeOutput(output, generated.items, to_stdout);
+
+            ast.deinit();
+            return null;
         },
-        .combine_asts => {
-            self.combineAsts() catch |err| {
-                log.err("Failed to combine asts: {}", .{err});
-            };
+        else => {}
+    };
+
+    const result = checkMerged(unit, ast) catch |err| {
+        try self.reportSourceDiagnostics(unit);
+        return err;
+    };
+    errdefer result.types.deinit();
+
+    try self.reportSourceDiagnostics(unit);
+    defer self.reportProfile();
+    if (self.options.check_only) return result;
+
+    switch (self.options.emit) {
+        .native => {
+            var linking = Profile.begin(self.profiler(), "link");
+            try self.buildNative(output.?, generated.items);
+            try linking.end();
         },
-        .check_ast_semantics => {
-            self.checkAstSemantics() catch |err| {
-                log.err("Failed to check ast semantics: {}", .{err});
-            };
+        .timing_json => {
+            generated.clearRetainingCapacity();
+            try self.profile.writeJson(generated.writer());
+            try self.writeOutput(output, generated.items, to_stdout);
+        },
+        else => try self.writeOutput(output, generated.items, to_stdout)
+    }
+    if (self.options.emit == .c) try self.writeSourceMap(unit, output);
+
+    return result;
+}
+
+// The profile phases are recorded in, null when compilation isn't profiled
+fn profiler(self: *Compiler) ?*Profile {
+    const profiled = self.options.profile_compile_time or self.options.emit == .timing_json;
+    return if (profiled) &self.profile else null;
+}
+
+// Prints the time each phase of the last compile took to stderr, unless it was
+// emitted as timing json instead
+fn reportProfile(self: *Compiler) void {
+    if (!self.options.profile_compile_time or self.options.emit == .timing_json) return;
+
+    var table = ArrayList(u8).init(self.allocator);
+    defer table.deinit();
+
+    self.profile.writeTable(table.writer()) catch return;
+    self.transport.writeAll(table.items) catch |err| log.warn("the profile couldn't be printed: {}", .{err});
+}
+
+/// Compiles the sources, then compiles them again whenever any file read by
+/// the last compilation changes, until interrupted by ctrl+c. Sources whose
+/// contents were given rather than read aren't watched
+pub fn watch(self: *Compiler) Error!void {
+    self.watchSources() catch |err| return self.fail(err);
+}
+
+fn watchSources(self: *Compiler) !void {
+    if (builtin.os.tag != .windows) {
+        const action = std.posix.Sigaction{
+            .handler = .{ .handler = interrupt },
+            .mask = std.posix.empty_sigset,
+            .flags = 0
+        };
+        std.posix.sigaction(std.posix.SIG.INT, &action, null);
+    }
+
+    while (true) {
+        try self.recompile();
+
+        var watched = try self.watchedFiles();
+        defer watched.deinit();
+
+        while (self.changedFile(watched.items) == null) {
+            if (interrupted.load(.monotonic)) return;
+            std.time.sleep(watch_interval);
         }
+        std.time.sleep(watch_debounce);
+
+        const time = Chrono.now(.UTC);
+        try self.transport.print("\n---- {d:02}:{d:02}:{d:02} recompiling ----\n", .{time.hour, time.minute, time.second});
+        try self.reloadSources();
     }
 }
 
-fn parseFile(
-    self: *Compiler,
-    in: []const u8,
-    out: ?[]const u8
-) !void {
-    const src = try self.cwd.openDir("src", .{});
-    const input = try src.openFile(in, .{});
-    defer input.close();
+fn interrupt(_: i32) callconv(.C) void {
+    interrupted.store(true, .monotonic);
+}
 
-    var buf: [10]u8 = undefined;
-    var output = std.io.fixedBufferStream(&buf);
+// Compiles the sources again, replacing the diagnostics of the last compilation.
+// The diagnostics are rendered as they are reported, so failures are only noted
+fn recompile(self: *Compiler) !void {
+    const result = self.compile() catch {
+        try self.transport.print("Compilation failed: {}\n", .{self.failure.?});
+        return;
+    };
+    if (result) |compiled| compiled.deinit();
 
-    var unit = try Unit.init(.{
-        .input = in,
-        .output = out orelse "no output",
-        .reader = input.reader().any(),
-        .writer = output.writer().any(),
-        .allocator = self.allocator
+    try self.transport.write("Compilation succeeded\n");
+}
+
+// The files read by the last compilation, the sources and the files they import
+fn watchedFiles(self: *Compiler) !ArrayList(Source) {
+    var watched = ArrayList(Source).init(self.allocator);
+    errdefer watched.deinit();
+
+    for (self.sources.items) |source| if (source.mtime != null) try watched.append(source);
+
+    var modules = self.modules.valueIterator();
+    while (modules.next()) |module| {
+        if (module.root or module.source.mtime == null) continue;
+        try watched.append(module.source);
+    }
+
+    return watched;
+}
+
+// The path of the first of watched modified since it was read. Files which can't
+// be found are left until they are restored, so removing an import is only
+// noticed once a file importing it changes
+fn changedFile(self: *Compiler, watched: []const Source) ?[]const u8 {
+    for (watched) |source| {
+        const mtime = (self.fs.mtime(source.path) catch continue) orelse continue;
+        if (mtime != source.mtime.?) return source.path;
+    }
+
+    return null;
+}
+
+// Reads the sources which were read from files again, into a new compilation arena
+// which replaces the last compilation's. Those which can't be read keep their last
+// contents, which are copied over before the last arena is freed
+fn reloadSources(self: *Compiler) !void {
+    var last = self.compilation;
+    self.compilation = .init(self.allocator);
+    defer last.deinit();
+
+    const arena = self.compilation.allocator();
+    for (self.sources.items) |*source| {
+        if (source.mtime == null) continue;
+
+        source.* = readSource(self.fs, arena, source.path) catch |err| {
+            log.err("{s}: couldn't be read: {}", .{source.path, err});
+            source.contents = try arena.dupe(u8, source.contents);
+            continue;
+        };
+    }
+}
+
+// Where compile writes its output, null for stdout. Binaries can't be written to
+// stdout, so they are named after the first source by default
+fn outputPath(self: *Compiler) !?[]const u8 {
+    if (self.options.check_only) return null;
+    if (self.output) |output| return output;
+    if (self.options.emit != .native) return null;
+
+    return try std.fmt.allocPrint(self.compilation.allocator(), "{s}{s}", .{
+        stem(self.sources.items[0].path),
+        self.options.emit.extension()
     });
-    defer unit.deinit();
+}
 
-    const ast = try unit.compile();
-    errdefer ast.deinit();
+// Writes the source map of the c written to output next to it, if one was
+// generated. Source maps of c written to stdout aren't written
+fn writeSourceMap(self: *Compiler, unit: *const Unit, output: ?[]const u8) !void {
+    const map = unit.source_map orelse return;
+    const path = output orelse {
+        log.warn("source maps are only written alongside c written to a file", .{});
+        return;
+    };
+
+    const map_path = try std.fmt.allocPrint(self.compilation.allocator(), "{s}.map.json", .{stem(path)});
+    try self.fs.write(map_path, map);
+}
+
+// Writes data to the file at output, or to stdout when output is null and to_stdout is set
+fn writeOutput(self: *Compiler, output: ?[]const u8, data: []const u8, to_stdout: bool) !void {
+    const path = output orelse {
+        if (to_stdout) try std.io.getStdOut().writeAll(data);
+        return;
+    };
+
+    try self.fs.write(path, data);
+}
+
+// Writes the files read by the last compile to path as a make rule, so build
+// systems compile again when any of them change. The rule is for output, or the
+// file it would be named by default when written to stdout
+fn writeDependencyFile(self: *Compiler, path: []const u8, output: ?[]const u8) !void {
+    const target = output orelse try std.fmt.allocPrint(self.compilation.allocator(), "{s}{s}", .{
+        stem(self.sources.items[0].path),
+        self.options.emit.extension()
+    });
+
+    var rule = ArrayList(u8).init(self.allocator);
+    defer rule.deinit();
+
+    try writeMakeRule(rule.writer(), target, self.dependencies.items);
+    try self.fs.write(path, rule.items);
+}
+
+// The column make rules are wrapped before, continuing on the next line
+const make_rule_width = 80;
+
+// Writes `target: prerequisite...`, escaping the characters make treats specially
+fn writeMakeRule(writer: anytype, target: []const u8, prerequisites: []const []const u8) !void {
+    try writeMakePath(writer, target);
+    try writer.writeByte(':');
+
+    var column = target.len + 1;
+    for (prerequisites) |prerequisite| {
+        if (column + 1 + prerequisite.len > make_rule_width - 2) {
+            try writer.writeAll(" \\\n ");
+            column = 1;
+        }
+
+        try writer.writeByte(' ');
+        try writeMakePath(writer, prerequisite);
+        column = column + 1 + prerequisite.len;
+    }
+
+    try writer.writeByte('\n');
+}
+
+fn writeMakePath(writer: anytype, path: []const u8) !void {
+    for (path) |c| switch (c) {
+        ' ', '#' => try writer.print("\\{c}", .{c}),
+        '$' => try writer.writeAll("$$"),
+        else => try writer.writeByte(c)
+    };
+}
+
+// Compiles the generated c into a binary at output with the system's c compiler
+fn buildNative(self: *Compiler, output: []const u8, source: []const u8) !void {
+    // Only clang can compile for targets other than the one it runs on
+    if (self.options.target) |target| if (!self.isClang()) {
+        const message = try std.fmt.allocPrint(self.compilation.allocator(), "unsupported cross target {s}, the c compiler isn't clang", .{target});
+        try self.reportError(message);
+        return error.NativeBuildFailed;
+    };
+
+    const c_path = try std.fmt.allocPrint(self.compilation.allocator(), "{s}.c", .{output});
+    try self.fs.write(c_path, source);
+    defer self.fs.delete(c_path) catch {};
+
+    var argv = ArrayList([]const u8).init(self.allocator);
+    defer argv.deinit();
+
+    try argv.appendSlice(&.{"cc", "-o", output, c_path, "-lm"});
+    try argv.append(switch (self.options.optimization_level) {
+        .debug => "-O0",
+        .release_safe => "-O2",
+        .release_fast => "-O3",
+        .release_small => "-Os"
+    });
+    if (self.options.debug_info) try argv.append("-g");
+    if (self.options.target) |target| try argv.appendSlice(&.{"-target", target});
+    try argv.appendSlice(self.options.linker_flags);
+
+    const result = std.process.Child.run(.{
+        .allocator = self.allocator,
+        .argv = argv.items,
+        .cwd_dir = self.cwd
+    }) catch |err| {
+        const message = try std.fmt.allocPrint(self.compilation.allocator(), "couldn't run the c compiler: {}", .{err});
+        try self.reportError(message);
+        return error.NativeBuildFailed;
+    };
+    defer {
+        self.allocator.free(result.stdout);
+        self.allocator.free(result.stderr);
+    }
+
+    switch (result.term) {
+        .Exited => |code| if (code == 0) return,
+        else => {}
+    }
+
+    const message = try std.fmt.allocPrint(self.compilation.allocator(), "the c compiler failed:\n{s}", .{
+        std.mem.trimRight(u8, result.stderr, "\n")
+    });
+    try self.reportError(message);
+    return error.NativeBuildFailed;
+}
+
+// Whether the system's c compiler is clang, which names itself in its version
+fn isClang(self: *Compiler) bool {
+    const result = std.process.Child.run(.{
+        .allocator = self.allocator,
+        .argv = &.{"cc", "--version"}
+    }) catch return false;
+    defer {
+        self.allocator.free(result.stdout);
+        self.allocator.free(result.stderr);
+    }
+
+    return std.mem.indexOf(u8, result.stdout, "clang") != null;
+}
+
+// Records an error that has no location in the sources, such as a failed native
+// build, placing it at the start of the first
+fn reportError(self: *Compiler, message: []const u8) !void {
+    const diagnostic = Diagnostic.init(.@"error", message, .{ .file = self.sources.items[0].path });
 
     self.mutex.lock();
     defer self.mutex.unlock();
 
-    try self.unprocessed_asts.append(ast);
-    try self.errors.appendSlice(unit.errors.items);
+    log.err("{s}", .{message});
+    try self.diagnostics.append(diagnostic);
 }
 
-fn combineAsts(_: *Compiler) !void {
-    std.debug.print("\ncombining asts\n", .{});
+// Scans source in a unit of its own, writing its tokens to writer
+fn scanSource(self: *Compiler, source: Source, writer: std.io.AnyWriter) !void {
+    var input = std.io.fixedBufferStream(source.contents);
+    const reader = input.reader();
+
+    var unit = try Unit.init(.{
+        .input = source.path,
+        .output = "",
+        .reader = reader.any(),
+        .writer = writer,
+        .allocator = self.allocator,
+        .options = self.options
+    });
+    defer unit.deinit();
+
+    unit.emitTokens() catch |err| {
+        try self.reportSourceDiagnostics(unit);
+        return err;
+    };
+
+    try self.reportSourceDiagnostics(unit);
 }
 
-fn checkAstSemantics(_: *Compiler) !void {
-    std.debug.print("\nverifying ast\n", .{});
+// The path without its extension
+fn stem(path: []const u8) []const u8 {
+    return path[0..path.len - std.fs.path.extension(path).len];
 }
 
-test "compiler modules" {
-    _ = tests;
-    _ = Unit;
+fn clearModules(self: *Compiler) void {
+    var modules = self.modules.valueIterator();
+    while (modules.next()) |module| module.deinit();
+    self.modules.clearRetainingCapacity();
 }
 
-const tests = struct {
+// Loads each source and the files they import. Every file is parsed so all of
+// their errors are reported, but loading fails if any couldn't be parsed or imported
+fn loadModules(self: *Compiler, unit: *Unit, order: *ArrayList([]const u8)) !void {
+    const error_count = unit.errorCount();
+
+    var failure: ?anyerror = null;
+    if (self.options.parallel) {
+        try self.loadConcurrently(unit, order, &failure);
+    } else for (self.sources.items) |source| {
+        const path = try std.fs.path.resolve(self.compilation.allocator(), &.{source.path});
+        if (self.modules.getPtr(path)) |imported| {
+            imported.root = true;
+            continue;
+        }
+
+        try self.loadModule(unit, path, source, true, order, &failure);
+    }
+
+    if (failure) |err| return err;
+
+    const import_errors = unit.errorCount() - error_count;
+    if (import_errors > 0) {
+        log.err("{s}: resolving imports failed with {} error(s)", .{unit.input, import_errors});
+        return error.ResolutionFailed;
+    }
+}
+
+// Parses source and the files it imports, which are loaded first so modules are
+// appended to order after their imports. Files which fail to parse set failure
+// rather than stopping the others from loading
+fn loadModule(
+    self: *Compiler,
+    unit: *Unit,
+    path: []const u8,
+    source: Source,
+    root: bool,
+    order: *ArrayList([]const u8),
+    failure: *?anyerror
+) !void {
+    try self.addModule(path, source, root);
+    self.modules.getPtr(path).?.loading = true;
+
+    const ast = self.parseSource(source) catch |err| switch (err) {
+        error.ScanningFailed, error.ExpansionFailed, error.ParsingFailed => {
+            if (failure.* == null) failure.* = err;
+            self.modules.getPtr(path).?.loading = false;
+            return;
+        },
+        else => |e| return e
+    };
+    self.modules.getPtr(path).?.ast = ast;
 
+    // Loading imports may grow the map, so the module is looked up again each time
+    for (ast.root.kind.module.items) |item| switch (item.kind) {
+        .import => |import| try self.loadImport(unit, path, item, import, order, failure),
+        else => try self.addDefinition(path, item)
+    };
+
+    self.modules.getPtr(path).?.loading = false;
+    try order.append(path);
+}
+
+// Adds the module at path, one of the files compiled, before it is parsed
+fn addModule(self: *Compiler, path: []const u8, source: Source, root: bool) !void {
+    try self.dependencies.append(path);
+    try self.modules.putNoClobber(path, .{
+        .source = source,
+        .ast = null,
+        .root = root,
+        .loading = false,
+        .imports = .init(self.allocator),
+        .definitions = .init(self.allocator)
+    });
+}
+
+// Records the name item defines at the top level of the module at path
+fn addDefinition(self: *Compiler, path: []const u8, item: *const Node) !void {
+    const defined = definedName(item.unwrapExport()) orelse return;
+    try self.modules.getPtr(path).?.definitions.put(defined, item.kind == .@"export");
+}
+
+// The dotted name an import is written with, and the resolved path of the file
+// it names
+const ImportTarget = struct {
+    name: []const u8,
+    path: []const u8
+};
+
+// Resolves an import of the module at importer to the file it names, relative to
+// the importing file
+fn importTarget(self: *Compiler, importer: []const u8, import: Node.Import) !ImportTarget {
+    const arena = self.compilation.allocator();
+
+    const relative = try std.mem.join(arena, std.fs.path.sep_str, import.path);
+    const file = try std.fmt.allocPrint(arena, "{s}.{s}", .{relative, extension});
+
+    const directory = std.fs.path.dirname(self.modules.get(importer).?.source.path) orelse ".";
+    const path = try std.fs.path.resolve(arena, &.{directory, file});
+    std.debug.assert(isProperExtension(path));
+
+    return .{
+        .name = try std.mem.join(arena, ".", import.path),
+        .path = path
+    };
+}
+
+// Loads the sources and the files they import a wave at a time, parsing the
+// files of each wave concurrently, then orders them as loadModule would. Cycles
+// are only reported once every file is loaded
+fn loadConcurrently(self: *Compiler, unit: *Unit, order: *ArrayList([]const u8), failure: *?anyerror) !void {
+    var graph = CompileGraph.init(self.allocator);
+    defer graph.deinit();
+
+    var roots = ArrayList([]const u8).init(self.allocator);
+    defer roots.deinit();
+
+    var wave = ArrayList([]const u8).init(self.allocator);
+    defer wave.deinit();
+
+    for (self.sources.items) |source| {
+        const path = try std.fs.path.resolve(self.compilation.allocator(), &.{source.path});
+        if (self.modules.getPtr(path)) |imported| {
+            imported.root = true;
+            continue;
+        }
+
+        try self.addModule(path, source, true);
+        _ = try graph.addModule(path);
+        try roots.append(path);
+        try wave.append(path);
+    }
+
+    while (wave.items.len > 0) {
+        try self.parseWave(wave.items, failure);
+
+        // The files imported by this wave, which haven't been loaded yet
+        var next = ArrayList([]const u8).init(self.allocator);
+        errdefer next.deinit();
+
+        for (wave.items) |path| {
+            const ast = self.modules.get(path).?.ast orelse continue;
+            for (ast.root.kind.module.items) |item| switch (item.kind) {
+                .import => |import| try self.queueImport(unit, path, item, import, &graph, &next),
+                else => try self.addDefinition(path, item)
+            };
+        }
+
+        wave.deinit();
+        wave = next;
+    }
+
+    var cycles = ArrayList(CompileGraph.Import).init(self.allocator);
+    defer cycles.deinit();
+
+    try graph.sort(roots.items, order, &cycles);
+    for (cycles.items) |import| {
+        try unit.createErrorFmt(import.node.span, "importing {s} creates a cycle", .{import.name});
+    }
+}
+
+// Parses the modules at paths on the thread pool, keeping the asts of those which
+// parse. Those which don't set failure, once every file has been parsed
+fn parseWave(self: *Compiler, paths: []const []const u8, failure: *?anyerror) !void {
+    const results = try self.allocator.alloc(anyerror!*Ast, paths.len);
+    defer self.allocator.free(results);
+
+    var wait_group: WaitGroup = .{};
+    for (paths, results) |path, *result| {
+        wait_group.start();
+        self.thread_pool.spawn(parseJob, .{self, self.modules.get(path).?.source, result, &wait_group}) catch |err| {
+            wait_group.finish();
+            result.* = err;
+        };
+    }
+    self.thread_pool.waitAndWork(&wait_group);
+
+    // Every ast is kept before any other error is returned, so none are leaked
+    var unexpected: ?anyerror = null;
+    for (paths, results) |path, result| {
+        const ast = result catch |err| {
+            switch (err) {
+                error.ScanningFailed, error.ExpansionFailed, error.ParsingFailed => {
+                    if (failure.* == null) failure.* = err;
+                },
+                else => if (unexpected == null) {
+                    unexpected = err;
+                }
+            }
+            continue;
+        };
+
+        self.modules.getPtr(path).?.ast = ast;
+    }
+
+    if (unexpected) |err| return err;
+}
+
+fn parseJob(self: *Compiler, source: Source, result: *anyerror!*Ast, wait_group: *WaitGroup) void {
+    defer wait_group.finish();
+    result.* = self.parseSource(source);
+}
+
+// Adds an import of the module at importer to graph, reading the file it names
+// and adding it to next if it hasn't been loaded
+fn queueImport(
+    self: *Compiler,
+    unit: *Unit,
+    importer: []const u8,
+    node: *const Node,
+    import: Node.Import,
+    graph: *CompileGraph,
+    next: *ArrayList([]const u8)
+) !void {
+    const target = try self.importTarget(importer, import);
+
+    try self.modules.getPtr(importer).?.imports.append(target.path);
+    try graph.addImport(importer, .{ .path = target.path, .name = target.name, .node = node });
+
+    if (self.modules.contains(target.path)) return;
+
+    const source = readSource(self.fs, self.compilation.allocator(), target.path) catch |err| switch (err) {
+        error.FileNotFound => {
+            try unit.createErrorFmt(node.span, "module {s} could not be found at {s}", .{target.name, target.path});
+            return;
+        },
+        else => |e| return e
+    };
+
+    try self.addModule(target.path, source, false);
+    _ = try graph.addModule(target.path);
+    try next.append(target.path);
+}
+
+// Resolves an import to the file it names, relative to the importing file, loading
+// it if it hasn't been already
+fn loadImport(
+    self: *Compiler,
+    unit: *Unit,
+    importer: []const u8,
+    node: *const Node,
+    import: Node.Import,
+    order: *ArrayList([]const u8),
+    failure: *?anyerror
+) !void {
+    const target = try self.importTarget(importer, import);
+    const name = target.name;
+    const path = target.path;
+
+    try self.modules.getPtr(importer).?.imports.append(path);
+
+    if (self.modules.get(path)) |imported| {
+        if (imported.loading) try unit.createErrorFmt(node.span, "importing {s} creates a cycle", .{name});
+        return;
+    }
+
+    const source = readSource(self.fs, self.compilation.allocator(), path) catch |err| switch (err) {
+        error.FileNotFound => {
+            try unit.createErrorFmt(node.span, "module {s} could not be found at {s}", .{name, path});
+            return;
+        },
+        else => |e| return e
+    };
+
+    try self.loadModule(unit, path, source, false, order, failure);
+}
+
+// The name a top level declaration defines
+fn definedName(node: *const Node) ?[]const u8 {
+    return switch (node.kind) {
+        .fn_def => |fn_def| fn_def.name,
+        .extern_fn => |extern_fn| extern_fn.name,
+        .struct_def => |struct_def| struct_def.name,
+        .union_def => |union_def| union_def.name,
+        .enum_def => |enum_def| enum_def.name,
+        .trait_def => |trait_def| trait_def.name,
+        .var_decl, .const_decl => |binding| binding.name,
+        else => null
+    };
+}
+
+// Scans and parses source in a unit of its own
+fn parseSource(self: *Compiler, source: Source) !*Ast {
+    var input = std.io.fixedBufferStream(source.contents);
+    const reader = input.reader();
+
+    var unit = try Unit.init(.{
+        .input = source.path,
+        .output = "",
+        .reader = reader.any(),
+        .writer = std.io.null_writer.any(),
+        .allocator = self.allocator,
+        .options = self.options
+    });
+    defer unit.deinit();
+
+    const ast = self.parseUnit(unit, source) catch |err| {
+        try self.reportSourceDiagnostics(unit);
+        return err;
+    };
+    errdefer ast.deinit();
+
+    try self.reportSourceDiagnostics(unit);
+    return ast;
+}
+
+// Parses the unit's input, which is read from source, from the cached tokens of
+// source if it hasn't changed since they were cached
+fn parseUnit(self: *Compiler, unit: *Unit, source: Source) !*Ast {
+    var scanning = Profile.begin(self.profiler(), "scan");
+    var tokens = try self.tokensOf(unit, source);
+    defer {
+        for (tokens.items) |token| token.deinit();
+        tokens.deinit();
+    }
+    try scanning.end();
+
+    var parsing = Profile.begin(self.profiler(), "parse");
+    const ast = try unit.parseTokens(tokens.items);
+    errdefer ast.deinit();
+    try parsing.end();
+
+    return ast;
+}
+
+fn tokensOf(self: *Compiler, unit: *Unit, source: Source) !ArrayList(Token) {
+    const cache = if (self.cache) |*cache| cache else return try unit.scan();
+    const mtime = source.mtime orelse return try unit.scan();
+
+    // Files may be scanned on the thread pool, see CompileGraph
+    {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        if (cache.lookup(source.path, mtime)) |tokens| {
+            log.debug("{s}: unchanged since it was cached", .{source.path});
+            return try cache.copyTokens(tokens, source.path, unit.allocator, &unit.interner);
+        }
+    }
+
+    const diagnostic_count = unit.diagnostics.items.len;
+
+    var tokens = try unit.scan();
+    errdefer {
+        for (tokens.items) |token| token.deinit();
+        tokens.deinit();
+    }
+
+    // Files whose scans report anything are scanned each time, so it is reported each time
+    if (unit.diagnostics.items.len == diagnostic_count) {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        try cache.put(source.path, mtime, tokens.items, &unit.interner);
+    }
+
+    return tokens;
+}
+
+// Loads the cache in options.cache_dir, if it isn't already
+fn openCache(self: *Compiler) !void {
+    if (self.cache != null) return;
+    const path = self.options.cache_dir orelse return;
+
+    var dir = try self.cwd.makeOpenPath(path, .{});
+    defer dir.close();
+
+    self.cache = try Cache.load(self.allocator, dir);
+}
+
+// Failing to save the cache only means the next compile scans every file, so it
+// is logged rather than failing this one
+fn saveCache(self: *Compiler) void {
+    const cache = if (self.cache) |*cache| cache else return;
+
+    var dir = self.cwd.makeOpenPath(self.options.cache_dir.?, .{}) catch |err| {
+        log.warn("the cache couldn't be saved: {}", .{err});
+        return;
+    };
+    defer dir.close();
+
+    cache.save(dir) catch |err| log.warn("the cache couldn't be saved: {}", .{err});
+}
+
+// Reads the file at path from fs, noting when it was last modified
+fn readSource(fs: VirtualFs, allocator: Allocator, path: []const u8) !Source {
+    const file = try fs.read(allocator, path);
+    return .{
+        .path = path,
+        .contents = file.contents,
+        .mtime = file.mtime
+    };
+}
+
+// Resolves the names of each module, then merges them into ast in order
+fn mergeModules(self: *Compiler, unit: *Unit, ast: *Ast, order: []const []const u8) !void {
+    var error_count = unit.errorCount();
+    for (order) |path| {
+        var resolver: Resolver = .{
+            .compiler = self,
+            .unit = unit,
+            .module = self.modules.getPtr(path).?,
+            .order = order,
+            .locals = .init(self.allocator)
+        };
+        defer resolver.locals.deinit();
+
+        try Resolver.Walk.visit(&resolver, resolver.module.ast.?.root);
+    }
+
+    const resolution_errors = unit.errorCount() - error_count;
+    if (resolution_errors > 0) {
+        log.err("{s}: resolving names failed with {} error(s)", .{unit.input, resolution_errors});
+        return error.ResolutionFailed;
+    }
+
+    for (order) |path| {
+        const module = self.modules.getPtr(path).?;
+        try ast.adopt(module.ast.?);
+        module.ast = null;
+    }
+
+    error_count = unit.errorCount();
+    try self.checkDuplicates(unit, ast);
+
+    const merge_errors = unit.errorCount() - error_count;
+    if (merge_errors > 0) {
+        log.err("{s}: merging failed with {} error(s)", .{unit.input, merge_errors});
+        return error.MergingFailed;
+    }
+}
+
+// Optimizes, checks, analyses and lints the merged ast, then generates its code
+// unless it is only checked
+fn checkMerged(unit: *Unit, ast: *Ast) !Unit.Result {
+    try optimizer.optimize(unit, ast);
+
+    var checking = Profile.begin(unit.profile, "typecheck");
+    const result: Unit.Result = .{
+        .ast = ast,
+        .types = try unit.checkAst(ast)
+    };
+    errdefer result.types.deinit();
+    try checking.end();
+
+    var analyzing = Profile.begin(unit.profile, "analysis");
+    try analysis.analyze(unit, ast);
+    try analyzing.end();
+
+    if (unit.options.lint) {
+        var linting = Profile.begin(unit.profile, "lint");
+        try lints.lint(unit, ast);
+        try linting.end();
+    }
+
+    if (unit.options.check_only) return result;
+
+    var generating = Profile.begin(unit.profile, "codegen");
+    try unit.emit(result);
+    try generating.end();
+
+    return result;
+}
+
+// Reports uses of names defined at the top level of other modules which aren't
+// visible to the module being resolved. Names defined nowhere are left to the
+// type checker
+const Resolver = struct {
+    compiler: *Compiler,
+    unit: *Unit,
+    module: *const Module,
+    order: []const []const u8,
+    /// The names bound within the declaration being resolved, innermost last
+    locals: ArrayList([]const u8),
+
+    const Walk = Visitor(Resolver, Allocator.Error, void);
+
+    // Whether name is bound locally, in the module, or is exported by a module it imports
+    fn isVisible(self: *const Resolver, name: []const u8) bool {
+        for (self.locals.items) |local| {
+            if (std.mem.eql(u8, local, name)) return true;
+        }
+
+        if (self.module.definitions.contains(name)) return true;
+
+        for (self.module.imports.items) |path| {
+            if (self.compiler.modules.get(path).?.definitions.get(name) orelse false) return true;
+        }
+
+        if (!self.module.root) return false;
+        for (self.order) |path| {
+            const other = self.compiler.modules.getPtr(path).?;
+            if (other.root and other.definitions.contains(name)) return true;
+        }
+
+        return false;
+    }
+
+    pub fn visitIdentifier(self: *Resolver, node: *const Node, name: []const u8) Allocator.Error!void {
+        if (self.isVisible(name)) return;
+
+        for (self.order) |path| {
+            const other = self.compiler.modules.getPtr(path).?;
+            if (!other.definitions.contains(name)) continue;
+
+            if (self.module.isImporting(path)) {
+                try self.unit.createErrorFmt(node.span, "{s} isn't exported by {s}", .{name, other.source.path});
+            } else {
+                try self.unit.createErrorFmt(node.span, "{s} is defined in {s}, which isn't imported", .{
+                    name,
+                    other.source.path
+                });
+            }
+
+            return;
+        }
+    }
+
+    // Annotations name types rather than bindings, so only bodies are resolved
+    pub fn visitFnDef(self: *Resolver, _: *const Node, fn_def: Node.FunctionDef) Allocator.Error!void {
+        const scope = self.locals.items.len;
+        defer self.locals.shrinkRetainingCapacity(scope);
+
+        for (fn_def.params) |param| try self.locals.append(param.name);
+        try Walk.visit(self, fn_def.body);
+    }
+
+    // The types of fields are annotations, so structs, unions and enums have nothing to resolve
+    pub fn visitStructDef(_: *Resolver, _: *const Node, _: Node.StructDef) Allocator.Error!void {}
+
+    pub fn visitUnionDef(_: *Resolver, _: *const Node, _: Node.UnionDef) Allocator.Error!void {}
+
+    pub fn visitEnumDef(_: *Resolver, _: *const Node, _: Node.EnumDef) Allocator.Error!void {}
+
+    // Only the defaults of a trait have bodies
+    pub fn visitTraitDef(self: *Resolver, _: *const Node, trait_def: Node.TraitDef) Allocator.Error!void {
+        for (trait_def.methods) |method| if (method.default) |default| try Walk.visit(self, default);
+    }
+
+    pub fn visitVarDecl(self: *Resolver, _: *const Node, binding: Node.Binding) Allocator.Error!void {
+        try self.resolveBinding(binding);
+    }
+
+    pub fn visitConstDecl(self: *Resolver, _: *const Node, binding: Node.Binding) Allocator.Error!void {
+        try self.resolveBinding(binding);
+    }
+
+    fn resolveBinding(self: *Resolver, binding: Node.Binding) Allocator.Error!void {
+        if (binding.value) |value| try Walk.visit(self, value);
+        try self.locals.append(binding.name);
+    }
+
+    pub fn visitDestructure(self: *Resolver, _: *const Node, destructure: Node.Destructure) Allocator.Error!void {
+        try Walk.visit(self, destructure.value);
+        try self.bindPattern(destructure.pattern);
+    }
+
+    fn bindPattern(self: *Resolver, pattern: Node.Pattern) Allocator.Error!void {
+        switch (pattern.kind) {
+            .identifier => |name| try self.locals.append(name),
+            .literal, .wildcard => {},
+            .@"struct" => |structure| for (structure.fields) |field| try self.bindPattern(field.pattern),
+            .variant => |variant| switch (variant.fields) {
+                .unit => {},
+                .tuple => |fields| for (fields) |field| try self.bindPattern(field),
+                .@"struct" => |fields| for (fields) |field| try self.bindPattern(field.pattern)
+            },
+            .tuple, .@"or" => |patterns| for (patterns) |inner| try self.bindPattern(inner)
+        }
+    }
+
+    pub fn visitBlock(self: *Resolver, _: *const Node, block: Node.Block) Allocator.Error!void {
+        const scope = self.locals.items.len;
+        defer self.locals.shrinkRetainingCapacity(scope);
+
+        // Functions can be referenced anywhere in their block
+        for (block.statements) |statement| switch (statement.kind) {
+            .fn_def => |fn_def| try self.locals.append(fn_def.name),
+            .extern_fn => |extern_fn| try self.locals.append(extern_fn.name),
+            else => {}
+        };
+
+        for (block.statements) |statement| try Walk.visit(self, statement);
+    }
+
+    pub fn visitFor(self: *Resolver, _: *const Node, loop: Node.For) Allocator.Error!void {
+        try Walk.visit(self, loop.iterable);
+
+        const scope = self.locals.items.len;
+        defer self.locals.shrinkRetainingCapacity(scope);
+
+        try self.locals.append(loop.variable);
+        try Walk.visit(self, loop.body);
+    }
+};
+
+// Reports names defined at the top level of more than one file, duplicates within
+// a single file are left to the type checker
+fn checkDuplicates(self: *Compiler, unit: *Unit, ast: *const Ast) !void {
+    var definitions = StringHashMap(Span).init(self.allocator);
+    defer definitions.deinit();
+
+    for (ast.root.kind.module.items) |item| {
+        const defined = definedName(item.unwrapExport()) orelse continue;
+
+        const entry = try definitions.getOrPut(defined);
+        if (!entry.found_existing) {
+            entry.value_ptr.* = item.span;
+            continue;
+        }
+
+        const first = entry.value_ptr.*;
+        if (std.mem.eql(u8, first.file, item.span.file)) continue;
+
+        try unit.createErrorFmt(item.span, "{s} is already defined in {s}", .{defined, first.file});
+    }
+}
+
+// Renders the unit's diagnostics beneath the sources they refer to and keeps copies of them
+fn reportSourceDiagnostics(self: *Compiler, unit: *Unit) !void {
+    if (unit.diagnostics.items.len == 0) return;
+
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    const stderr = std.io.getStdErr();
+    const writer = stderr.writer();
+    var terminal = Diagnostic.TerminalEmitter.init(writer.any(), self.options.color.enabled(stderr));
+
+    try self.keepDiagnostics(unit, &terminal, null);
+}
+
+// Renders the unit's diagnostics beneath source, or the sources they refer to when
+// null, and keeps copies of them. Errors past options.max_errors, counting those
+// of every unit, are left out and counted in hidden_errors. Must be called with
+// the mutex locked
+fn keepDiagnostics(self: *Compiler, unit: *Unit, terminal: *Diagnostic.TerminalEmitter, source: ?[]const u8) !void {
+    var errors = self.errorCount();
+    for (unit.diagnostics.items) |*diagnostic| {
+        if (diagnostic.severity == .@"error") {
+            if (errors >= self.options.max_errors orelse std.math.maxInt(usize)) {
+                self.hidden_errors = self.hidden_errors + 1;
+                continue;
+            }
+            errors = errors + 1;
+        }
+
+        try terminal.emit(diagnostic, source orelse self.findSource(diagnostic.span.file) orelse "");
+        try self.diagnostics.append(try diagnostic.clone(self.compilation.allocator()));
+    }
+}
+
+// Renders a single note saying how many errors were left out, once every unit's
+// diagnostics have been kept
+fn noteHiddenErrors(self: *Compiler) !void {
+    if (self.hidden_errors == 0) return;
+
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    const hidden = self.hidden_errors;
+    self.hidden_errors = 0;
+
+    const file = if (self.diagnostics.getLastOrNull()) |last| last.span.file else "";
+    const note = Diagnostic.init(.note, try std.fmt.allocPrint(self.compilation.allocator(), "and {} more error{s} not shown", .{
+        hidden,
+        if (hidden == 1) "" else "s"
+    }), .{ .file = file });
+
+    const stderr = std.io.getStdErr();
+    var terminal = Diagnostic.TerminalEmitter.init(stderr.writer().any(), self.options.color.enabled(stderr));
+
+    try terminal.emit(&note, "");
+    try self.diagnostics.append(note);
+}
+
+/// Writes the diagnostics of the last compile as a json array, for tools using
+/// the compiler. See Diagnostic.writeJson for the fields of each
+pub fn writeDiagnosticsJson(self: *const Compiler, writer: anytype) !void {
+    try writer.writeByte('[');
+    for (self.diagnostics.items, 0..) |diagnostic, i| {
+        if (i > 0) try writer.writeByte(',');
+        try diagnostic.writeJson(writer, self.findSource(diagnostic.span.file) orelse "");
+    }
+    try writer.writeAll("]\n");
+}
+
+/// Writes the diagnostics of the last compile as a sarif 2.1.0 log, which code
+/// scanning services such as github's read. Warnings of lints are results of the
+/// rule named after the lint, and children are related locations
+pub fn writeDiagnosticsSarif(self: *const Compiler, writer: anytype) !void {
+    try writer.writeAll(
+        "{\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\"version\":\"2.1.0\"," ++
+        "\"runs\":[{\"tool\":{\"driver\":{\"name\":\"ruka\",\"version\":\"" ++ constants.version_str ++ "\",\"rules\":["
+    );
+    for (std.enums.values(Diagnostic.Lint), 0..) |lint, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writer.print("{{\"id\":\"{s}\"}}", .{@tagName(lint)});
+    }
+    try writer.writeAll("]}},\"results\":[");
+
+    for (self.diagnostics.items, 0..) |diagnostic, i| {
+        if (i > 0) try writer.writeByte(',');
+
+        const level = switch (diagnostic.severity) {
+            .@"error" => "error",
+            .warning => "warning",
+            .note, .help => "note"
+        };
+
+        try writer.writeByte('{');
+        if (diagnostic.lint) |lint| try writer.print("\"ruleId\":\"{s}\",", .{@tagName(lint)});
+        try writer.print("\"level\":\"{s}\",\"message\":{{\"text\":", .{level});
+        try std.json.encodeJsonString(diagnostic.message, .{}, writer);
+        try writer.writeByte('}');
+
+        // Diagnostics at line 0 have no location, such as those counting hidden errors
+        if (diagnostic.span.pos.line > 0) {
+            try writer.writeAll(",\"locations\":[{");
+            try self.writeSarifLocation(writer, diagnostic.span);
+            try writer.writeAll("}]");
+        }
+
+        try writer.writeAll(",\"relatedLocations\":[");
+        var related: usize = 0;
+        for (diagnostic.children) |child| {
+            if (child.span.pos.line == 0) continue;
+            if (related > 0) try writer.writeByte(',');
+            related = related + 1;
+
+            try writer.writeAll("{\"message\":{\"text\":");
+            try std.json.encodeJsonString(child.message, .{}, writer);
+            try writer.writeAll("},");
+            try self.writeSarifLocation(writer, child.span);
+            try writer.writeByte('}');
+        }
+        try writer.writeAll("]}");
+    }
+
+    try writer.writeAll("]}]}\n");
+}
+
+// Writes the physicalLocation member of a sarif location object, whose columns
+// are one based with the end column just past the span as in Span.endPos
+fn writeSarifLocation(self: *const Compiler, writer: anytype, span: Span) !void {
+    const end = span.endPos(self.findSource(span.file) orelse "");
+
+    try writer.writeAll("\"physicalLocation\":{\"artifactLocation\":{\"uri\":");
+    try LanguageServer.writeUri(writer, span.file);
+    try writer.print("}},\"region\":{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{}}}}}", .{
+        span.pos.line,
+        span.pos.col,
+        end.line,
+        end.col
+    });
+}
+
+/// The contents of a source, or a module loaded by the last compile, at path
+pub fn findSource(self: *const Compiler, path: []const u8) ?[]const u8 {
+    var modules = self.modules.valueIterator();
+    while (modules.next()) |module| {
+        if (std.mem.eql(u8, module.source.path, path)) return module.source.contents;
+    }
+
+    for (self.sources.items) |source| {
+        if (std.mem.eql(u8, source.path, path)) return source.contents;
+    }
+
+    return null;
+}
+
+test "compiler modules" {
+    _ = tests;
+    _ = Cache;
+    _ = CompileGraph;
+    _ = Profile;
+    _ = Target;
+    _ = Unit;
+    _ = VirtualFs;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    // Skips tests building binaries where there is no c compiler to build them
+    fn skipWithoutCc() !void {
+        const result = std.process.Child.run(.{
+            .allocator = testing.allocator,
+            .argv = &.{"cc", "--version"}
+        }) catch |err| switch (err) {
+            error.FileNotFound => return error.SkipZigTest,
+            else => return err
+        };
+
+        testing.allocator.free(result.stdout);
+        testing.allocator.free(result.stderr);
+    }
+
+    // The absolute path of name within dir
+    fn pathIn(dir: Dir, name: []const u8) ![]const u8 {
+        const root = try dir.realpathAlloc(testing.allocator, ".");
+        defer testing.allocator.free(root);
+
+        return try std.fs.path.join(testing.allocator, &.{root, name});
+    }
+
+    // Writes each file into dir, returning the absolute paths they were written to
+    fn writeFiles(dir: Dir, files: []const [2][]const u8) ![][]const u8 {
+        var paths = ArrayList([]const u8).init(testing.allocator);
+        errdefer {
+            for (paths.items) |path| testing.allocator.free(path);
+            paths.deinit();
+        }
+
+        for (files) |file| {
+            try dir.writeFile(.{ .sub_path = file[0], .data = file[1] });
+            try paths.append(try pathIn(dir, file[0]));
+        }
+
+        return try paths.toOwnedSlice();
+    }
+
+    fn freePaths(paths: [][]const u8) void {
+        for (paths) |path| testing.allocator.free(path);
+        testing.allocator.free(paths);
+    }
+
+    // A file system holding each file in memory
+    fn memoryFs(files: []const [2][]const u8) !VirtualFs.MemFs {
+        var memory = VirtualFs.MemFs.init(testing.allocator);
+        errdefer memory.deinit();
+
+        for (files) |file| try memory.write(file[0], file[1]);
+        return memory;
+    }
+
+    test "compiling multiple files" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{
+            .{"a.ruka", "let x: i32 = 1\n"},
+            .{"b.ruka", "let y = x + 2\n"}
+        });
+        defer freePaths(paths);
+
+        const output = try pathIn(tmp.dir, "program.c");
+        defer testing.allocator.free(output);
+
+        var compiler = try Compiler.initFiles(testing.allocator, paths, output, .{});
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        const items = result.ast.root.kind.module.items;
+        try testing.expectEqual(2, items.len);
+        try testing.expectEqualStrings(paths[1], items[1].span.file);
+        try testing.expectEqual(Type.@"i32", result.types.get(items[1].kind.var_decl.value.?.id).?);
+
+        const generated = try tmp.dir.readFileAlloc(testing.allocator, "program.c", 1 << 20);
+        defer testing.allocator.free(generated);
+
+        try testing.expect(std.mem.indexOf(u8, generated, "static int32_t y;") != null);
+    }
+
+    test "source maps are written next to the generated c" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka",
+            \\let x: i32 = 1
+            \\fn f() -> i32 { x + 1 }
+            \\
+        }});
+        defer freePaths(paths);
+
+        const output = try pathIn(tmp.dir, "program.c");
+        defer testing.allocator.free(output);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], output, .{ .emit_source_map = true });
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        const generated = try tmp.dir.readFileAlloc(testing.allocator, "program.c", 1 << 20);
+        defer testing.allocator.free(generated);
+        const json = try tmp.dir.readFileAlloc(testing.allocator, "program.map.json", 1 << 20);
+        defer testing.allocator.free(json);
+
+        const parsed = try std.json.parseFromSlice(std.json.Value, testing.allocator, json, .{});
+        defer parsed.deinit();
+
+        try testing.expectEqualStrings(output, parsed.value.object.get("file").?.string);
+
+        // Each statement's range holds the c generated for it
+        const expected = [_][]const u8{"return x + 1;", "x = 1;"};
+        const mappings = parsed.value.object.get("mappings").?.array.items;
+        try testing.expectEqual(expected.len, mappings.len);
+
+        for (mappings, expected, [_]i64{2, 1}) |mapping, statement, line| {
+            const start: usize = @intCast(mapping.object.get("gen_start").?.integer);
+            const end: usize = @intCast(mapping.object.get("gen_end").?.integer);
+            const span = mapping.object.get("orig_span").?.object;
+
+            try testing.expectEqualStrings(statement, std.mem.trim(u8, generated[start..end], " \n"));
+            try testing.expectEqual(line, span.get("line").?.integer);
+            try testing.expectEqualStrings(paths[0], span.get("file").?.string);
+        }
+    }
+
+    test "diagnostics as json and sarif" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let x: bool = 1\n"}});
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], null, .{ .check_only = true });
+        defer compiler.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, compiler.compile());
+
+        var out = ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        {
+            try compiler.writeDiagnosticsJson(out.writer());
+
+            const parsed = try std.json.parseFromSlice(std.json.Value, testing.allocator, out.items, .{});
+            defer parsed.deinit();
+
+            const diagnostic = parsed.value.array.items[0].object;
+            try testing.expectEqualStrings("error", diagnostic.get("severity").?.string);
+            try testing.expectEqualStrings(paths[0], diagnostic.get("file").?.string);
+            try testing.expectEqual(1, diagnostic.get("start_line").?.integer);
+        }
+
+        out.clearRetainingCapacity();
+        try compiler.writeDiagnosticsSarif(out.writer());
+
+        const parsed = try std.json.parseFromSlice(std.json.Value, testing.allocator, out.items, .{});
+        defer parsed.deinit();
+
+        try testing.expectEqualStrings("2.1.0", parsed.value.object.get("version").?.string);
+
+        const run = parsed.value.object.get("runs").?.array.items[0].object;
+        const result = run.get("results").?.array.items[0].object;
+        try testing.expectEqualStrings("error", result.get("level").?.string);
+
+        const location = result.get("locations").?.array.items[0].object.get("physicalLocation").?.object;
+        try testing.expectStringStartsWith(location.get("artifactLocation").?.object.get("uri").?.string, "file://");
+        try testing.expectEqual(1, location.get("region").?.object.get("startLine").?.integer);
+    }
+
+    test "compiling to a string" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let x: i32 = 1\n"}});
+        defer freePaths(paths);
+
+        {
+            var compiler = try Compiler.initFile(testing.allocator, paths[0], null, .{});
+            defer compiler.deinit();
+
+            const generated = try compiler.compileToString();
+            defer testing.allocator.free(generated);
+
+            try testing.expect(std.mem.indexOf(u8, generated, "int main") != null);
+            try testing.expect(std.mem.indexOf(u8, generated, "static int32_t x;") != null);
+        }
+
+        // Outputs given are still written
+        const output = try pathIn(tmp.dir, "main.c");
+        defer testing.allocator.free(output);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], output, .{});
+        defer compiler.deinit();
+
+        const generated = try compiler.compileToString();
+        defer testing.allocator.free(generated);
+
+        const written = try tmp.dir.readFileAlloc(testing.allocator, "main.c", 1 << 20);
+        defer testing.allocator.free(written);
+
+        try testing.expectEqualStrings(written, generated);
+    }
+
+    test "profiles record each phase, and are emitted as json" {
+        var compiler = try Compiler.initSource(testing.allocator, .{
+            .path = "main.ruka",
+            .contents = "let x: i32 = 1 + 2\n"
+        }, .{ .emit = .timing_json });
+        defer compiler.deinit();
+
+        const generated = try compiler.compileToString();
+        defer testing.allocator.free(generated);
+
+        const expected = [_][]const u8{
+            "scan", "parse", "resolve", "constant folding", "dead code elimination", "typecheck", "analysis", "codegen"
+        };
+        try testing.expectEqual(expected.len, compiler.profile.phases.items.len);
+        for (expected, compiler.profile.phases.items) |name, phase| try testing.expectEqualStrings(name, phase.name);
+
+        // The json replaces the c
+        const parsed = try std.json.parseFromSlice(std.json.Value, testing.allocator, generated, .{});
+        defer parsed.deinit();
+
+        const phases = parsed.value.object.get("phases").?.array.items;
+        try testing.expectEqual(expected.len, phases.len);
+        try testing.expectEqualStrings("scan", phases[0].object.get("name").?.string);
+        try testing.expect(std.mem.indexOf(u8, generated, "int main") == null);
+    }
+
+    test "outputs are written to stdout by default, except binaries" {
+        var compiler = try Compiler.init(testing.allocator, .{});
+        defer compiler.deinit();
+
+        try compiler.sources.append(.{ .path = "src/main.ruka", .contents = "" });
+        try testing.expectEqual(null, try compiler.outputPath());
+
+        // Binaries are named after the first source
+        compiler.options.emit = .native;
+        try testing.expectEqualStrings("src/main" ++ Options.EmitKind.native.extension(), (try compiler.outputPath()).?);
+
+        compiler.output = "program";
+        try testing.expectEqualStrings("program", (try compiler.outputPath()).?);
+    }
+
+    test "emitting tokens stops before parsing" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let = 1\n"}});
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], null, .{ .emit = .tokens });
+        defer compiler.deinit();
+
+        const tokens = try compiler.compileToString();
+        defer testing.allocator.free(tokens);
+
+        try testing.expect(std.mem.indexOf(u8, tokens, ":1:1 keyword let\n") != null);
+        try testing.expect(std.mem.indexOf(u8, tokens, ":1:5 assign =\n") != null);
+    }
+
+    test "emitting json stops before type checking" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let x: bool = 1 + 2\n"}});
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], null, .{ .emit = .ast_json });
+        defer compiler.deinit();
+
+        const json = try compiler.compileToString();
+        defer testing.allocator.free(json);
+
+        const parsed = try std.json.parseFromSlice(std.json.Value, testing.allocator, json, .{});
+        defer parsed.deinit();
+
+        // The infix wasn't folded, as the optimizer runs after the ast is emitted
+        const item = parsed.value.object.get("items").?.array.items[0].object;
+        try testing.expectEqualStrings("infix", item.get("value").?.object.get("kind").?.string);
+    }
+
+    test "checking produces no output" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{
+            .{"good.ruka", "let x: i32 = 1\n"},
+            .{"bad.ruka", "let y: bool = 1\n"}
+        });
+        defer freePaths(paths);
+
+        const output = try pathIn(tmp.dir, "program.c");
+        defer testing.allocator.free(output);
+
+        {
+            var compiler = try Compiler.initFile(testing.allocator, paths[0], output, .{ .check_only = true });
+            defer compiler.deinit();
+
+            const result = (try compiler.compile()).?;
+            defer result.deinit();
+
+            try testing.expectError(error.FileNotFound, tmp.dir.access("program.c", .{}));
+        }
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[1], output, .{ .check_only = true });
+        defer compiler.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, compiler.compile());
+        try testing.expectEqual(.@"error", compiler.diagnostics.items[0].severity);
+    }
+
+    test "binaries are built with the system's c compiler" {
+        try skipWithoutCc();
+
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"hello.ruka",
+            \\let n = math.max(2, 3)
+            \\if n == 3 { io.println("hello") }
+            \\
+        }});
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], null, .{
+            .emit = .native,
+            .linker_flags = &.{"-lm"}
+        });
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        // Named after the source, and the generated c is removed once built
+        const binary = try pathIn(tmp.dir, "hello" ++ Options.EmitKind.native.extension());
+        defer testing.allocator.free(binary);
+        try testing.expectError(error.FileNotFound, tmp.dir.access("hello" ++ Options.EmitKind.native.extension() ++ ".c", .{}));
+
+        const run = try std.process.Child.run(.{ .allocator = testing.allocator, .argv = &.{binary} });
+        defer {
+            testing.allocator.free(run.stdout);
+            testing.allocator.free(run.stderr);
+        }
+
+        try testing.expectEqual(std.process.Child.Term{ .Exited = 0 }, run.term);
+        try testing.expectEqualStrings("hello\n", run.stdout);
+    }
+
+    test "goto state machines run as written" {
+        try skipWithoutCc();
+
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"machine.ruka",
+            \\fn main() {
+            \\    var state = 0
+            \\    unsafe {
+            \\        dispatch:
+            \\        if state == 0 { goto idle }
+            \\        if state == 1 { goto running }
+            \\        goto stopped
+            \\        idle:
+            \\        io.println("idle")
+            \\        state = 1
+            \\        goto dispatch
+            \\        running:
+            \\        io.println("running")
+            \\        state = 2
+            \\        goto dispatch
+            \\        stopped:
+            \\        io.println("stopped")
+            \\    }
+            \\}
+            \\
+        }});
+        defer freePaths(paths);
+
+        const binary = try pathIn(tmp.dir, "machine");
+        defer testing.allocator.free(binary);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], binary, .{ .emit = .native });
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        const run = try std.process.Child.run(.{ .allocator = testing.allocator, .argv = &.{binary} });
+        defer {
+            testing.allocator.free(run.stdout);
+            testing.allocator.free(run.stderr);
+        }
+
+        try testing.expectEqual(std.process.Child.Term{ .Exited = 0 }, run.term);
+        try testing.expectEqualStrings("idle\nrunning\nstopped\n", run.stdout);
+    }
+
+    test "c compiler failures are reported as errors" {
+        try skipWithoutCc();
+
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let x = 1\n"}});
+        defer freePaths(paths);
+
+        const output = try pathIn(tmp.dir, "main");
+        defer testing.allocator.free(output);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], output, .{
+            .emit = .native,
+            .linker_flags = &.{"-lruka_missing_library"}
+        });
+        defer compiler.deinit();
+
+        try testing.expectError(error.NativeBuildFailed, compiler.compile());
+
+        const diagnostic = compiler.diagnostics.items[0];
+        try testing.expectEqual(.@"error", diagnostic.severity);
+        try testing.expectEqualStrings(paths[0], diagnostic.span.file);
+        try testing.expect(std.mem.indexOf(u8, diagnostic.message, "ruka_missing_library") != null);
+    }
+
+    test "cross targets are only built with clang" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let x = 1\n"}});
+        defer freePaths(paths);
+
+        const output = try pathIn(tmp.dir, "main");
+        defer testing.allocator.free(output);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], output, .{
+            .emit = .native,
+            .target = "riscv64-linux-gnu"
+        });
+        defer compiler.deinit();
+
+        if (compiler.isClang()) return error.SkipZigTest;
+
+        try testing.expectError(error.NativeBuildFailed, compiler.compile());
+        try testing.expectEqualStrings(
+            "unsupported cross target riscv64-linux-gnu, the c compiler isn't clang",
+            compiler.diagnostics.items[0].message
+        );
+        try testing.expectError(error.FileNotFound, tmp.dir.access("main.c", .{}));
+    }
+
+    test "unknown targets are reported before compiling" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let x: bool = 1\n"}});
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], null, .{ .target = "x86_64-linux" });
+        defer compiler.deinit();
+
+        // The type error isn't reported, as the source is never checked
+        try testing.expectError(error.UnknownTarget, compiler.compile());
+        try testing.expectEqual(1, compiler.diagnostics.items.len);
+        try testing.expectEqualStrings("unknown target triple x86_64-linux", compiler.diagnostics.items[0].message);
+    }
+
+    // Benchmarks checking against compiling the same source, taking the fastest of
+    // several runs of each to smooth out noise
+    test "checking never generates code" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let x: i64 = 2 * 3 + 1\n"}});
+        defer freePaths(paths);
+
+        const output = try pathIn(tmp.dir, "main.c");
+        defer testing.allocator.free(output);
+
+        for ([_]bool{false, true}) |check_only| {
+            var compiler = try Compiler.initFile(testing.allocator, paths[0], output, .{
+                .check_only = check_only,
+                .emit = .timing_json
+            });
+            defer compiler.deinit();
+
+            const generated = try compiler.compileToString();
+            defer testing.allocator.free(generated);
+
+            // The profile records every phase which ran, codegen included
+            try testing.expect(compiler.profile.durationOf("typecheck") != null);
+            try testing.expectEqual(!check_only, compiler.profile.durationOf("codegen") != null);
+            try testing.expectEqual(check_only, generated.len == 0);
+        }
+
+        // Nor is anything written to the output
+        try tmp.dir.deleteFile("main.c");
+
+        var compiler = try Compiler.initFile(testing.allocator, paths[0], output, .{ .check_only = true });
+        defer compiler.deinit();
+
+        const generated = try compiler.compileToString();
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqual(0, generated.len);
+        try testing.expectError(error.FileNotFound, tmp.dir.access("main.c", .{}));
+    }
+
+    test "formatting the project" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.makeDir("src");
+        try tmp.dir.writeFile(.{ .sub_path = "src/main.ruka", .data = "let x=(1)+2" });
+        try tmp.dir.writeFile(.{ .sub_path = "src/done.ruka", .data = "let y = 3\n" });
+
+        var compiler = try Compiler.init(testing.allocator, .{ .check_only = true });
+        defer compiler.deinit();
+        compiler.cwd = tmp.dir;
+
+        // Checking leaves the files as they were
+        try testing.expectEqual(1, try compiler.formatProject());
+
+        compiler.options.check_only = false;
+        try testing.expectEqual(1, try compiler.formatProject());
+        try testing.expectEqual(0, try compiler.formatProject());
+
+        const formatted = try tmp.dir.readFileAlloc(testing.allocator, "src/main.ruka", 1 << 20);
+        defer testing.allocator.free(formatted);
+
+        try testing.expectEqualStrings("let x = 1 + 2\n", formatted);
+    }
+
+    test "names defined in more than one file" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{
+            .{"a.ruka", "let x = 1\n"},
+            .{"b.ruka", "let y = 2\nconst x = 3\n"}
+        });
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFiles(testing.allocator, paths, null, .{});
+        defer compiler.deinit();
+
+        try testing.expectError(error.MergingFailed, compiler.compile());
+        try testing.expectEqual(1, compiler.diagnostics.items.len);
+
+        const diagnostic = compiler.diagnostics.items[0];
+        try testing.expectStringStartsWith(diagnostic.message, "x is already defined in ");
+        try testing.expectStringEndsWith(diagnostic.message, "a.ruka");
+        try testing.expectEqualStrings(paths[1], diagnostic.span.file);
+    }
+
+    test "parsing continues past files with errors" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{
+            .{"a.ruka", "let = 1\n"},
+            .{"b.ruka", "let y 2\n"}
+        });
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFiles(testing.allocator, paths, null, .{});
+        defer compiler.deinit();
+
+        try testing.expectError(error.ParsingFailed, compiler.compile());
+        try testing.expectEqualStrings(paths[0], compiler.diagnostics.items[0].span.file);
+        try testing.expectEqualStrings(paths[1], compiler.diagnostics.items[compiler.diagnostics.items.len - 1].span.file);
+    }
+
+    test "unchanged files are parsed from cached tokens" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{
+            .{"a.ruka", "let x: i32 = 1\n"},
+            .{"b.ruka", "let y = x + 2\n"},
+            .{"c.ruka", "let z = y * 3\n"}
+        });
+        defer freePaths(paths);
+
+        const cache_dir = try pathIn(tmp.dir, ".ruka_cache");
+        defer testing.allocator.free(cache_dir);
+
+        {
+            var compiler = try Compiler.initFiles(testing.allocator, paths, null, .{ .cache_dir = cache_dir, .check_only = true });
+            defer compiler.deinit();
+
+            const result = (try compiler.compile()).?;
+            defer result.deinit();
+
+            try testing.expectEqual(0, compiler.cache.?.hits);
+            try testing.expectEqual(3, compiler.cache.?.misses);
+        }
+
+        // Set the modification time explicitly, rewriting within the resolution of the
+        // file system's timestamps would leave it unchanged
+        try tmp.dir.writeFile(.{ .sub_path = "b.ruka", .data = "let y = x - 2\n" });
+        {
+            const file = try tmp.dir.openFile("b.ruka", .{ .mode = .read_write });
+            defer file.close();
+
+            const stat = try file.stat();
+            try file.updateTimes(stat.atime, stat.mtime + std.time.ns_per_s);
+        }
+
+        var compiler = try Compiler.initFiles(testing.allocator, paths, null, .{ .cache_dir = cache_dir, .check_only = true });
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        try testing.expectEqual(2, compiler.cache.?.hits);
+        try testing.expectEqual(1, compiler.cache.?.misses);
+
+        const items = result.ast.root.kind.module.items;
+        try testing.expectEqual(3, items.len);
+        try testing.expectEqualStrings(paths[1], items[1].span.file);
+        try testing.expectEqual(.subtract, items[1].kind.var_decl.value.?.kind.infix.operator);
+    }
+
+    test "errors past the maximum are left out" {
+        const compiler = try Compiler.initSource(testing.allocator, .{
+            .path = "test.ruka",
+            .contents = "let = 1\n" ** 50
+        }, .{ .check_only = true, .max_errors = 10 });
+        defer compiler.deinit();
+
+        try testing.expectError(error.ParsingFailed, compiler.compile());
+
+        const diagnostics = compiler.diagnostics.items;
+        try testing.expectEqual(11, diagnostics.len);
+        for (diagnostics[0..10]) |diagnostic| try testing.expectEqual(.@"error", diagnostic.severity);
+
+        try testing.expectEqual(.note, diagnostics[10].severity);
+        try testing.expectEqualStrings("and 40 more errors not shown", diagnostics[10].message);
+    }
+
+    test "errors past the maximum are counted across files" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{
+            .{"a.ruka", "let = 1\n" ** 8},
+            .{"b.ruka", "let = 2\n" ** 8}
+        });
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFiles(testing.allocator, paths, null, .{ .check_only = true, .max_errors = 10 });
+        defer compiler.deinit();
+
+        try testing.expectError(error.ParsingFailed, compiler.compile());
+
+        // A single note follows the errors of every file
+        const diagnostics = compiler.diagnostics.items;
+        try testing.expectEqual(11, diagnostics.len);
+        for (diagnostics[0..10]) |diagnostic| try testing.expectEqual(.@"error", diagnostic.severity);
+
+        try testing.expectEqual(.note, diagnostics[10].severity);
+        try testing.expectEqualStrings("and 6 more errors not shown", diagnostics[10].message);
+        try testing.expectEqual(Failure{ .errors = 10 }, compiler.failure.?);
+    }
+
+    test "the maximum only counts the errors of the current compilation" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let = 1\n" ** 16}});
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFiles(testing.allocator, paths, null, .{ .check_only = true, .max_errors = 10 });
+        defer compiler.deinit();
+
+        for (0..2) |_| {
+            try testing.expectError(error.ParsingFailed, compiler.compile());
+
+            const diagnostics = compiler.diagnostics.items;
+            try testing.expectEqual(11, diagnostics.len);
+            for (diagnostics[0..10]) |diagnostic| try testing.expectEqual(.@"error", diagnostic.severity);
+            try testing.expectEqualStrings("and 6 more errors not shown", diagnostics[10].message);
+        }
+    }
+
+    test "watching the files read by compilation" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.makePath("lib");
+        const paths = try writeFiles(tmp.dir, &.{
+            .{"main.ruka", "import lib.math\nlet y = x + 1\n"},
+            .{"lib/math.ruka", "pub let x: i32 = 2\n"}
+        });
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFiles(testing.allocator, paths[0..1], null, .{ .check_only = true });
+        defer compiler.deinit();
+
+        try compiler.recompile();
+        try testing.expectEqual(0, compiler.diagnostics.items.len);
+
+        var watched = try compiler.watchedFiles();
+        defer watched.deinit();
+
+        try testing.expectEqual(2, watched.items.len);
+        try testing.expectEqual(null, compiler.changedFile(watched.items));
+
+        try tmp.dir.writeFile(.{ .sub_path = "main.ruka", .data = "import lib.math\nlet y = x + true\n" });
+        {
+            const file = try tmp.dir.openFile("main.ruka", .{ .mode = .read_write });
+            defer file.close();
+
+            const stat = try file.stat();
+            try file.updateTimes(stat.atime, stat.mtime + std.time.ns_per_s);
+        }
+
+        try testing.expectEqualStrings(paths[0], compiler.changedFile(watched.items).?);
+
+        try compiler.reloadSources();
+        try compiler.recompile();
+        try testing.expect(compiler.diagnostics.items.len > 0);
+        try testing.expectEqual(.@"error", compiler.diagnostics.items[0].severity);
+    }
+
+    test "reloading frees the last compilation" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let y = 1 + true\n"}});
+        defer freePaths(paths);
+
+        var compiler = try Compiler.initFiles(testing.allocator, paths, null, .{ .check_only = true });
+        defer compiler.deinit();
+
+        try compiler.recompile();
+        const capacity = compiler.arena.queryCapacity();
+
+        // What compiling allocates isn't kept in the compiler's own arena
+        for (0..3) |_| {
+            try compiler.reloadSources();
+            try compiler.recompile();
+
+            try testing.expectEqual(.@"error", compiler.diagnostics.items[0].severity);
+            try testing.expectEqual(capacity, compiler.arena.queryCapacity());
+        }
+
+        // Sources which can't be read keep their contents across the arenas
+        try tmp.dir.deleteFile("main.ruka");
+        try compiler.reloadSources();
+        try testing.expectEqualStrings("let y = 1 + true\n", compiler.sources.items[0].contents);
+    }
+
+    test "importing exported names" {
+        var memory = try memoryFs(&.{
+            .{"main.ruka", "import lib.math\nlet y = x + 1\n"},
+            .{"lib/math.ruka", "pub let x: i32 = 2\nlet hidden = x\n"}
+        });
+        defer memory.deinit();
+
+        var compiler = try Compiler.initWithFs(testing.allocator, "main.ruka", null, .{}, memory.fs());
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        // Imported modules come before the modules importing them
+        const items = result.ast.root.kind.module.items;
+        try testing.expectEqual(4, items.len);
+        try testing.expectEqualStrings("lib/math.ruka", items[0].span.file);
+        try testing.expect(items[0].kind == .@"export");
+        try testing.expect(items[2].kind == .import);
+        try testing.expectEqual(Type.@"i32", result.types.get(items[3].kind.var_decl.value.?.id).?);
+    }
+
+    test "unexported names aren't visible to importers" {
+        var memory = try memoryFs(&.{
+            .{"main.ruka", "import util\nlet y = x + z\n"},
+            .{"util.ruka", "let x = 1\n"},
+            .{"other.ruka", "import main\nlet z = 2\n"}
+        });
+        defer memory.deinit();
+
+        var compiler = try Compiler.initWithFs(testing.allocator, "other.ruka", null, .{}, memory.fs());
+        defer compiler.deinit();
+
+        try testing.expectError(error.ResolutionFailed, compiler.compile());
+        try testing.expectEqual(2, compiler.diagnostics.items.len);
+
+        const unexported = compiler.diagnostics.items[0];
+        try testing.expectStringStartsWith(unexported.message, "x isn't exported by ");
+        try testing.expectStringEndsWith(unexported.message, "util.ruka");
+
+        const unimported = compiler.diagnostics.items[1];
+        try testing.expectStringStartsWith(unimported.message, "z is defined in ");
+        try testing.expectStringEndsWith(unimported.message, "other.ruka, which isn't imported");
+    }
+
+    test "import cycles and missing modules" {
+        var memory = try memoryFs(&.{
+            .{"a.ruka", "import b\nimport missing\n"},
+            .{"b.ruka", "import a\n"}
+        });
+        defer memory.deinit();
+
+        var compiler = try Compiler.initWithFs(testing.allocator, "a.ruka", null, .{}, memory.fs());
+        defer compiler.deinit();
+
+        try testing.expectError(error.ResolutionFailed, compiler.compile());
+        try testing.expectEqual(2, compiler.diagnostics.items.len);
+        try testing.expectEqualStrings("importing a creates a cycle", compiler.diagnostics.items[0].message);
+        try testing.expectEqualStrings("b.ruka", compiler.diagnostics.items[0].span.file);
+        try testing.expectEqualStrings("module missing could not be found at missing.ruka", compiler.diagnostics.items[1].message);
+    }
+
+    test "modules loaded concurrently are merged in the order they are loaded alone" {
+        var memory = try memoryFs(&.{
+            .{"main.ruka", "import a\nimport b\nlet z = x + y\n"},
+            .{"a.ruka", "import shared\npub let x: i32 = s + 1\n"},
+            .{"b.ruka", "import shared\npub let y: i32 = s + 2\n"},
+            .{"shared.ruka", "pub let s: i32 = 3\n"}
+        });
+        defer memory.deinit();
+
+        for ([_]bool{false, true}, [_][]const u8{"alone.c", "concurrently.c"}) |parallel, output| {
+            var compiler = try Compiler.initWithFs(testing.allocator, "main.ruka", output, .{ .parallel = parallel }, memory.fs());
+            defer compiler.deinit();
+
+            const result = (try compiler.compile()).?;
+            defer result.deinit();
+
+            try testing.expectEqual(4, compiler.dependencies.items.len);
+        }
+
+        try testing.expectEqualStrings(memory.get("alone.c").?, memory.get("concurrently.c").?);
+    }
+
+    test "import cycles and missing modules loaded concurrently" {
+        var memory = try memoryFs(&.{
+            .{"a.ruka", "import b\nimport missing\n"},
+            .{"b.ruka", "import a\n"}
+        });
+        defer memory.deinit();
+
+        var compiler = try Compiler.initWithFs(testing.allocator, "a.ruka", null, .{ .parallel = true }, memory.fs());
+        defer compiler.deinit();
+
+        // Files are only found missing as they are loaded, before cycles are sorted out
+        try testing.expectError(error.ResolutionFailed, compiler.compile());
+        try testing.expectEqual(2, compiler.diagnostics.items.len);
+        try testing.expectEqualStrings("module missing could not be found at missing.ruka", compiler.diagnostics.items[0].message);
+        try testing.expectEqualStrings("importing a creates a cycle", compiler.diagnostics.items[1].message);
+        try testing.expectEqualStrings("b.ruka", compiler.diagnostics.items[1].span.file);
+    }
+
+    test "outputs are written through the compiler's file system" {
+        var memory = try memoryFs(&.{
+            .{"main.ruka", "import lib.math\nlet y = x + 1\n"},
+            .{"lib/math.ruka", "pub let x: i32 = 2\n"}
+        });
+        defer memory.deinit();
+
+        var compiler = try Compiler.initWithFs(testing.allocator, "main.ruka", "main.c", .{}, memory.fs());
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        const generated = memory.get("main.c").?;
+        try testing.expect(std.mem.indexOf(u8, generated, "static int32_t y;") != null);
+    }
+
+    test "dependency files list every file read" {
+        var memory = try memoryFs(&.{
+            .{"main.ruka", "import lib.math\nimport lib.shapes\nlet y = x + side\n"},
+            .{"lib/math.ruka", "import shapes\npub let x: i32 = 2\n"},
+            .{"lib/shapes.ruka", "pub let side: i32 = 3\n"}
+        });
+        defer memory.deinit();
+
+        var compiler = try Compiler.initWithFs(testing.allocator, "main.ruka", "main.c", .{ .dependency_file = "main.d" }, memory.fs());
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        // Files imported more than once are listed once
+        try testing.expectEqualStrings("main.c: main.ruka lib/math.ruka lib/shapes.ruka\n", memory.get("main.d").?);
+    }
+
+    test "make rules wrap long lists of files" {
+        var rule = ArrayList(u8).init(testing.allocator);
+        defer rule.deinit();
+
+        const path = "src/very/long/module_path_1.ruka";
+        try writeMakeRule(rule.writer(), "out.c", &.{path, path, path, "my file$.ruka"});
+        try testing.expectEqualStrings(
+            "out.c: " ++ path ++ " " ++ path ++ " \\\n  " ++ path ++ " my\\ file$$.ruka\n",
+            rule.items
+        );
+    }
+
+    test "files must have the ruka extension" {
+        try testing.expectError(
+            error.InvalidExtension,
+            Compiler.initFiles(testing.allocator, &.{"notes.txt"}, null, .{})
+        );
+        try testing.expectError(error.InvalidExtension, Compiler.initPath(testing.allocator, "ruka", null, .{}));
+        try testing.expectError(error.InvalidExtension, Compiler.initPath(testing.allocator, "src.ruka/notes", null, .{}));
+    }
+
+    test "failures adding files are recorded" {
+        var compiler = try Compiler.init(testing.allocator, .{});
+        defer compiler.deinit();
+
+        try testing.expectEqual(error.InvalidExtension, compiler.fail(error.InvalidExtension));
+        try testing.expectEqual(Failure.invalid_extension, compiler.failure.?);
+
+        try testing.expectEqual(error.IoFailed, compiler.fail(error.FileNotFound));
+        try testing.expectEqual(Failure{ .io = error.FileNotFound }, compiler.failure.?);
+    }
+
+    test "compiling files at absolute paths and paths with parent components" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.makeDir("sub");
+        const paths = try writeFiles(tmp.dir, &.{.{"main.ruka", "let x = 1\n"}});
+        defer freePaths(paths);
+
+        const indirect = try pathIn(tmp.dir, "sub/../sub/./../main.ruka");
+        defer testing.allocator.free(indirect);
+
+        for ([_][]const u8{paths[0], indirect}) |path| {
+            var compiler = try Compiler.initPath(testing.allocator, path, null, .{ .check_only = true });
+            defer compiler.deinit();
+
+            // Both name the file as imports of it would
+            try testing.expectEqualStrings(paths[0], compiler.sources.items[0].path);
+
+            const result = (try compiler.compile()).?;
+            result.deinit();
+        }
+    }
+
+    // Compiles main.ruka among files, expecting it to fail with expected, and
+    // returns what the compiler kept of the failure
+    fn expectFailure(files: []const [2][]const u8, options: Options, expected: Error) !Failure {
+        var memory = try memoryFs(files);
+        defer memory.deinit();
+
+        var compiler = try Compiler.initWithFs(testing.allocator, "main.ruka", null, options, memory.fs());
+        defer compiler.deinit();
+
+        try testing.expectError(expected, compiler.compile());
+        return compiler.failure.?;
+    }
+
+    test "failures are returned as the errors of the public api" {
+        const parsing = try expectFailure(&.{.{"main.ruka", "let = 1\n"}}, .{}, error.ParsingFailed);
+        try testing.expect(parsing == .errors and parsing.errors > 0);
+
+        const checking = try expectFailure(&.{.{"main.ruka", "let x: bool = 1\n"}}, .{}, error.TypeCheckingFailed);
+        try testing.expectEqual(Failure{ .errors = 1 }, checking);
+
+        const resolving = try expectFailure(&.{.{"main.ruka", "import missing\n"}}, .{}, error.ResolutionFailed);
+        try testing.expectEqual(Failure{ .errors = 1 }, resolving);
+
+        const targeting = try expectFailure(&.{.{"main.ruka", "let x = 1\n"}}, .{ .target = "x86_64-linux" }, error.UnknownTarget);
+        try testing.expectEqualStrings("x86_64-linux", targeting.unknown_target);
+
+        var memory = VirtualFs.MemFs.init(testing.allocator);
+        defer memory.deinit();
+        try testing.expectError(error.IoFailed, Compiler.initWithFs(testing.allocator, "main.ruka", null, .{}, memory.fs()));
+        try testing.expectError(error.InvalidExtension, Compiler.initWithFs(testing.allocator, "main.txt", null, .{}, memory.fs()));
+    }
+
+    test "errors from within the compiler are converted at its boundary" {
+        try testing.expectEqual(error.IoFailed, errorOf(error.AccessDenied));
+        try testing.expectEqual(error.ParsingFailed, errorOf(error.ParsingFailed));
+        try testing.expectEqual(error.OutOfMemory, errorOf(error.OutOfMemory));
+        try testing.expectEqual(error.InternalError, errorOf(error.InvalidAst));
+
+        var printed = ArrayList(u8).init(testing.allocator);
+        defer printed.deinit();
+
+        const failures = [_]Failure{
+            .{ .errors = 2 },
+            .{ .io = error.FileNotFound },
+            .invalid_extension,
+            .{ .internal = error.InvalidAst }
+        };
+        for (failures) |failure| try printed.writer().print("{}\n", .{failure});
+        try testing.expectEqualStrings(
+            \\2 errors reported
+            \\a file couldn't be read or written: FileNotFound
+            \\a file given to the compiler doesn't have the .ruka extension
+            \\internal compiler error: InvalidAst
+            ++ "\n",
+            printed.items
+        );
+    }
+
+    test "compiling files which don't exist" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        const path = try pathIn(tmp.dir, "missing.ruka");
+        defer testing.allocator.free(path);
+
+        try testing.expectError(error.IoFailed, Compiler.initPath(testing.allocator, path, null, .{}));
+    }
 };
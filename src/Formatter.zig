@@ -0,0 +1,974 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Writes parsed source back out in its canonical format. The output only depends
+//! on the ast and the comments of the source, so formatting formatted source leaves
+//! it unchanged. Comments are written on lines of their own before the declaration,
+//! statement, field or arm they precede, or after the one whose line they end
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Precedence = Node.Precedence;
+const Span = ruka.Span;
+const Unit = ruka.Unit;
+
+options: Options,
+
+output: ArrayList(u8),
+/// Where the line being written starts in the output
+line_start: usize,
+/// Set while trying to fit a list on a single line, so the lists within it aren't broken up
+flat: bool,
+/// The source being formatted and the spans of its comments, in source order
+source: []const u8,
+comments: []const Span,
+/// The index of the next comment to be written
+comment: usize,
+
+allocator: Allocator,
+
+const Formatter = @This();
+
+// Explicit so the mutually recursive writing functions can resolve their error sets
+const Error = Allocator.Error;
+
+pub const Options = struct {
+    /// The number of spaces per level of indentation
+    indent_width: usize = 4,
+    /// Lines longer than this have their argument and parameter lists broken up, one
+    /// item per line
+    max_line_length: usize = 100,
+    /// End the output with a newline
+    trailing_newline: bool = true
+};
+
+pub fn init(allocator: Allocator, options: Options) Formatter {
+    return Formatter {
+        .options = options,
+        .output = .init(allocator),
+        .line_start = 0,
+        .flat = false,
+        .source = "",
+        .comments = &.{},
+        .comment = 0,
+        .allocator = allocator
+    };
+}
+
+pub fn deinit(self: *Formatter) void {
+    self.output.deinit();
+}
+
+/// Formats source, the result is owned by the caller. Fails if source doesn't parse
+pub fn format(allocator: Allocator, source: []const u8, options: Options) ![]u8 {
+    var input = std.io.fixedBufferStream(source);
+    const reader = input.reader();
+    const writer = std.io.null_writer;
+
+    var unit = try Unit.init(.{
+        .input = "source",
+        .output = "",
+        .reader = reader.any(),
+        .writer = writer.any(),
+        .allocator = allocator
+    });
+    defer unit.deinit();
+
+    var formatter = Formatter.init(allocator, options);
+    defer formatter.deinit();
+
+    return try formatter.formatUnit(unit, source);
+}
+
+/// Parses the unit's input, which is source, and formats it along with its comments.
+/// The result is owned by the caller
+pub fn formatUnit(self: *Formatter, unit: *Unit, source: []const u8) ![]u8 {
+    const ast = try unit.parse();
+    defer ast.deinit();
+
+    self.source = source;
+    self.comments = unit.comments.items;
+    defer {
+        self.source = "";
+        self.comments = &.{};
+    }
+
+    return try self.formatAst(ast);
+}
+
+/// Formats ast, the result is owned by the caller
+pub fn formatAst(self: *Formatter, ast: *const Ast) ![]u8 {
+    self.output.clearRetainingCapacity();
+    self.line_start = 0;
+    self.flat = false;
+    self.comment = 0;
+
+    const items = ast.root.kind.module.items;
+    for (items, 0..) |item, i| {
+        if (i > 0) {
+            try self.newline();
+            if (isSeparated(items[i - 1], item)) try self.newline();
+        }
+
+        try self.writeLeadingComments(item.span.start, 0);
+        try self.writeNode(item, 0);
+        try self.writeTrailingComments(item.span.end);
+    }
+
+    // Comments after the last item end the module
+    while (self.nextCommentBefore(std.math.maxInt(usize))) |comment| {
+        if (self.output.items.len > 0) try self.newline();
+        try self.writeComment(comment);
+    }
+
+    if (self.output.items.len > 0 and self.options.trailing_newline) try self.newline();
+
+    return try self.allocator.dupe(u8, self.output.items);
+}
+
+// Imports are kept apart from the declarations following them, and functions,
+// structs, enums and impls from everything around them
+fn isSeparated(previous: *Node, next: *Node) bool {
+    const before = previous.unwrapExport().kind;
+    const after = next.unwrapExport().kind;
+
+    if (isDefinition(before) or isDefinition(after)) return true;
+    return before == .import and after != .import;
+}
+
+fn isDefinition(kind: Node.Kind) bool {
+    return switch (kind) {
+        .fn_def, .extern_fn, .struct_def, .union_def, .enum_def, .impl, .trait_def => true,
+        else => false
+    };
+}
+
+fn write(self: *Formatter, bytes: []const u8) Error!void {
+    try self.output.appendSlice(bytes);
+}
+
+fn print(self: *Formatter, comptime fmt: []const u8, args: anytype) Error!void {
+    try self.output.writer().print(fmt, args);
+}
+
+fn newline(self: *Formatter) Error!void {
+    try self.output.append('\n');
+    self.line_start = self.output.items.len;
+}
+
+fn indent(self: *Formatter, depth: usize) Error!void {
+    try self.output.appendNTimes(' ', depth * self.options.indent_width);
+}
+
+// Takes the next comment if it starts before offset
+fn nextCommentBefore(self: *Formatter, offset: usize) ?Span {
+    if (!self.hasCommentBefore(offset)) return null;
+
+    self.comment = self.comment + 1;
+    return self.comments[self.comment - 1];
+}
+
+fn hasCommentBefore(self: *const Formatter, offset: usize) bool {
+    return self.comment < self.comments.len and self.comments[self.comment].start < offset;
+}
+
+fn writeComment(self: *Formatter, comment: Span) Error!void {
+    try self.write(std.mem.trimRight(u8, self.source[comment.start..comment.end], " \t\r"));
+}
+
+// Writes the comments before offset, where the line at depth has just been started,
+// each on a line of its own ahead of what begins at offset. These include comments
+// within the previous item which weren't written there
+fn writeLeadingComments(self: *Formatter, offset: usize, depth: usize) Error!void {
+    while (self.nextCommentBefore(offset)) |comment| {
+        try self.writeComment(comment);
+        try self.newline();
+        try self.indent(depth);
+    }
+}
+
+// Writes the comments following end on the same line, end being where the item
+// just written ends. A newline is always written after them
+fn writeTrailingComments(self: *Formatter, end: usize) Error!void {
+    while (self.comment < self.comments.len) {
+        const comment = self.comments[self.comment];
+        if (comment.start < end or std.mem.indexOfScalar(u8, self.source[end..comment.start], '\n') != null) return;
+
+        self.comment = self.comment + 1;
+        try self.write(" ");
+        try self.writeComment(comment);
+    }
+}
+
+// Writes the comments before offset on lines of their own at depth, those after the
+// last item of a block or definition, before its closing brace
+fn writeRemainingComments(self: *Formatter, offset: usize, depth: usize) Error!void {
+    while (self.nextCommentBefore(offset)) |comment| {
+        try self.newline();
+        try self.indent(depth);
+        try self.writeComment(comment);
+    }
+}
+
+// Whether everything written since mark is on the current line, which fits
+fn fits(self: *const Formatter, mark: usize) bool {
+    if (std.mem.indexOfScalar(u8, self.output.items[mark..], '\n') != null) return false;
+    return self.output.items.len - self.line_start <= self.options.max_line_length;
+}
+
+fn writeNode(self: *Formatter, node: *const Node, depth: usize) Error!void {
+    for (node.attributes) |attribute| {
+        try self.print("{}", .{attribute});
+        try self.newline();
+        try self.indent(depth);
+    }
+
+    switch (node.kind) {
+        .module => unreachable,
+        .fn_def => |fn_def| {
+            if (fn_def.@"async") try self.write("async ");
+            try self.print("fn {s}", .{fn_def.name});
+            try self.writeTypeParams(fn_def.type_params);
+            try self.writeList(fn_def.params, depth, writeParameter);
+            try self.write(" ");
+            if (fn_def.return_type) |return_type| {
+                try self.write("-> ");
+                try self.writeOperand(return_type, depth, .primary);
+                try self.write(" ");
+            }
+            try self.writeNode(fn_def.body, depth);
+        },
+        .extern_fn => |extern_fn| {
+            try self.print("extern fn {s}", .{extern_fn.name});
+
+            const close = if (!extern_fn.variadic) ")" else if (extern_fn.params.len > 0) ", ...)" else "...)";
+            try self.writeDelimited(extern_fn.params, depth, "(", close, writeParameter);
+            if (extern_fn.return_type) |return_type| {
+                try self.write(" -> ");
+                try self.writeOperand(return_type, depth, .primary);
+            }
+        },
+        .struct_def => |struct_def| {
+            try self.print("struct {s}", .{struct_def.name});
+            try self.writeTypeParams(struct_def.type_params);
+            try self.write(" ");
+            try self.writeFieldDefinitions(struct_def.fields, node.span.end, depth);
+        },
+        .union_def => |union_def| {
+            try self.print("union {s} ", .{union_def.name});
+            try self.writeFieldDefinitions(union_def.fields, node.span.end, depth);
+        },
+        .enum_def => |enum_def| {
+            try self.print("enum {s} ", .{enum_def.name});
+            if (enum_def.variants.len == 0 and !self.hasCommentBefore(node.span.end)) return try self.write("{}");
+
+            try self.write("{");
+            for (enum_def.variants) |variant| {
+                try self.newline();
+                try self.indent(depth + 1);
+                try self.writeLeadingComments(variant.span.start, depth + 1);
+                try self.write(variant.name);
+                switch (variant.fields) {
+                    .unit => {},
+                    .tuple => |annotations| try self.writeList(annotations, depth + 1, writeNode),
+                    .@"struct" => |fields| {
+                        try self.write(" {");
+                        for (fields, 0..) |field, i| {
+                            try self.write(if (i > 0) ", " else " ");
+                            try self.print("{s}: ", .{field.name});
+                            try self.writeOperand(field.annotation, depth + 1, .primary);
+                        }
+                        try self.write(if (fields.len > 0) " }" else "}");
+                    }
+                }
+                try self.writeTrailingComments(variant.span.end);
+            }
+            try self.writeRemainingComments(node.span.end, depth + 1);
+            try self.newline();
+            try self.indent(depth);
+            try self.write("}");
+        },
+        .impl => |impl| {
+            if (impl.trait) |trait| try self.print("impl {s} for {s} ", .{trait, impl.name})
+                else try self.print("impl {s} ", .{impl.name});
+            if (impl.functions.len == 0 and !self.hasCommentBefore(node.span.end)) return try self.write("{}");
+
+            try self.write("{");
+            for (impl.functions, 0..) |function, i| {
+                if (i > 0) try self.newline();
+                try self.newline();
+                try self.indent(depth + 1);
+                try self.writeLeadingComments(function.span.start, depth + 1);
+                try self.writeNode(function, depth + 1);
+                try self.writeTrailingComments(function.span.end);
+            }
+            try self.writeRemainingComments(node.span.end, depth + 1);
+            try self.newline();
+            try self.indent(depth);
+            try self.write("}");
+        },
+        .trait_def => |trait_def| {
+            try self.print("trait {s} ", .{trait_def.name});
+            if (trait_def.methods.len == 0 and !self.hasCommentBefore(node.span.end)) return try self.write("{}");
+
+            // Methods without bodies are written on consecutive lines, those with
+            // bodies are separated from the others by a blank line
+            try self.write("{");
+            for (trait_def.methods, 0..) |method, i| {
+                if (i > 0 and (method.default != null or trait_def.methods[i - 1].default != null)) try self.newline();
+                try self.newline();
+                try self.indent(depth + 1);
+                try self.writeLeadingComments(method.span.start, depth + 1);
+                if (method.default) |default| {
+                    try self.writeNode(default, depth + 1);
+                } else {
+                    try self.print("fn {s}", .{method.name});
+                    try self.writeList(method.params, depth + 1, writeParameter);
+                    if (method.return_type) |return_type| {
+                        try self.write(" -> ");
+                        try self.writeOperand(return_type, depth + 1, .primary);
+                    }
+                }
+                try self.writeTrailingComments(method.span.end);
+            }
+            try self.writeRemainingComments(node.span.end, depth + 1);
+            try self.newline();
+            try self.indent(depth);
+            try self.write("}");
+        },
+        .var_decl, .const_decl => |binding| {
+            const keyword = if (node.kind == .const_decl) "const"
+                else if (binding.mutable) "var"
+                else "let";
+
+            try self.print("{s} {s}", .{keyword, binding.name});
+            if (binding.annotation) |annotation| {
+                try self.write(": ");
+                try self.writeOperand(annotation, depth, .primary);
+            }
+            if (binding.value) |value| {
+                try self.write(" = ");
+                try self.writeNode(value, depth);
+            }
+        },
+        .destructure => |destructure| {
+            try self.write("let ");
+            try Ast.writePattern(self.output.writer(), destructure.pattern);
+            if (destructure.annotation) |annotation| {
+                try self.write(": ");
+                try self.writeOperand(annotation, depth, .primary);
+            }
+            try self.write(" = ");
+            try self.writeNode(destructure.value, depth);
+        },
+        .import => |import| {
+            try self.write("import ");
+            for (import.path, 0..) |segment, i| {
+                if (i > 0) try self.write(".");
+                try self.write(segment);
+            }
+            if (import.alias) |alias| try self.print(" as {s}", .{alias});
+        },
+        .@"export" => |declaration| {
+            try self.write("pub ");
+            try self.writeNode(declaration, depth);
+        },
+        .block => |block| {
+            if (block.statements.len == 0 and !self.hasCommentBefore(node.span.end)) return try self.write("{}");
+
+            try self.write("{");
+            for (block.statements) |statement| {
+                try self.newline();
+                try self.indent(depth + 1);
+                try self.writeLeadingComments(statement.span.start, depth + 1);
+                try self.writeNode(statement, depth + 1);
+                try self.writeTrailingComments(statement.span.end);
+            }
+            try self.writeRemainingComments(node.span.end, depth + 1);
+            try self.newline();
+            try self.indent(depth);
+            try self.write("}");
+        },
+        .@"if" => |conditional| {
+            try self.write("if ");
+            try self.writeCondition(conditional.condition, depth);
+            try self.write(" ");
+            try self.writeNode(conditional.then_block, depth);
+            if (conditional.else_block) |else_block| {
+                try self.write(" else ");
+                try self.writeNode(else_block, depth);
+            }
+        },
+        .@"while" => |loop| {
+            try self.write("while ");
+            try self.writeCondition(loop.condition, depth);
+            try self.write(" ");
+            try self.writeNode(loop.body, depth);
+        },
+        .@"for" => |loop| {
+            try self.print("for {s} in ", .{loop.variable});
+            try self.writeCondition(loop.iterable, depth);
+            try self.write(" ");
+            try self.writeNode(loop.body, depth);
+        },
+        .match => |match| {
+            try self.write("match ");
+            try self.writeCondition(match.subject, depth);
+            try self.write(" {");
+            for (match.arms) |arm| {
+                try self.newline();
+                try self.indent(depth + 1);
+                try self.writeLeadingComments(arm.pattern.span.start, depth + 1);
+                try Ast.writePattern(self.output.writer(), arm.pattern);
+                if (arm.guard) |guard| {
+                    try self.write(" if ");
+                    try self.writeNode(guard, depth + 1);
+                }
+                try self.write(" => ");
+                try self.writeNode(arm.body, depth + 1);
+                try self.writeTrailingComments(arm.body.span.end);
+            }
+            try self.writeRemainingComments(node.span.end, depth + 1);
+            try self.newline();
+            try self.indent(depth);
+            try self.write("}");
+        },
+        .@"return" => |value| {
+            try self.write("return");
+            if (value) |v| {
+                try self.write(" ");
+                try self.writeNode(v, depth);
+            }
+        },
+        .@"break" => |value| {
+            try self.write("break");
+            if (value) |v| {
+                try self.write(" ");
+                try self.writeNode(v, depth);
+            }
+        },
+        .@"continue" => try self.write("continue"),
+        .@"defer" => |body| {
+            try self.write("defer ");
+            try self.writeNode(body, depth);
+        },
+        .@"unsafe" => |body| {
+            try self.write("unsafe ");
+            try self.writeNode(body, depth);
+        },
+        .@"comptime" => |body| {
+            try self.write("comptime ");
+            try self.writeNode(body, depth);
+        },
+        .label => |name| {
+            try self.write(name);
+            try self.write(":");
+        },
+        .goto => |target| {
+            try self.write("goto ");
+            try self.write(target);
+        },
+        .assignment => |assignment| {
+            try self.writeOperand(assignment.lhs, depth, Precedence.assignment.next());
+            try self.write(" = ");
+            try self.writeOperand(assignment.rhs, depth, .assignment);
+        },
+        .prefix => |prefix| {
+            // Nested prefixes are parenthesized too, so `-(-x)` isn't written as `--x`
+            try self.write(prefix.operator.toStr());
+            try self.writeOperand(prefix.operand, depth, Precedence.prefix.next());
+        },
+        .infix => |infix| {
+            const precedence = infix.operator.precedence();
+            const associativity = infix.operator.associativity();
+
+            try self.writeOperand(infix.lhs, depth, if (associativity == .left) precedence else precedence.next());
+            try self.print(" {s} ", .{infix.operator.toStr()});
+            try self.writeOperand(infix.rhs, depth, if (associativity == .right) precedence else precedence.next());
+        },
+        .fn_call => |call| {
+            try self.writeOperand(call.callee, depth, .primary);
+            try self.writeList(call.arguments, depth, writeNode);
+        },
+        .struct_literal => |literal| {
+            try self.print("{s} ", .{literal.name});
+            try self.writeFieldValues(literal.fields, depth);
+        },
+        .variant_literal => |literal| {
+            if (literal.qualified) try self.print("{s}::", .{literal.@"enum"});
+            try self.write(literal.variant);
+            switch (literal.fields) {
+                .unit => {},
+                .tuple => |values| try self.writeList(values, depth, writeNode),
+                .@"struct" => |fields| {
+                    try self.write(" ");
+                    try self.writeFieldValues(fields, depth);
+                }
+            }
+        },
+        .field_access => |access| {
+            try self.writeOperand(access.object, depth, .primary);
+            try self.print(".{s}", .{access.field});
+        },
+        .array_literal => |elements| try self.writeDelimited(elements, depth, "[", "]", writeNode),
+        .index => |index| {
+            try self.writeOperand(index.object, depth, .primary);
+            try self.write("[");
+            try self.writeNode(index.index, depth);
+            try self.write("]");
+        },
+        // A single value in parentheses is only grouped, so it keeps its trailing comma
+        .tuple_literal => |elements| if (elements.len == 1) {
+            try self.write("(");
+            try self.writeNode(elements[0], depth);
+            try self.write(",)");
+        } else try self.writeList(elements, depth, writeNode),
+        .tuple_index => |access| {
+            try self.writeOperand(access.object, depth, .primary);
+            try self.print(".{}", .{access.index});
+        },
+        .@"try" => |operand| {
+            try self.writeOperand(operand, depth, .primary);
+            try self.write("?");
+        },
+        .reference, .dereference => |operand| {
+            try self.write(if (node.kind == .reference) "&" else "*");
+            try self.writeOperand(operand, depth, Precedence.prefix.next());
+        },
+        .@"await" => |operand| {
+            try self.write("await ");
+            try self.writeOperand(operand, depth, Precedence.prefix.next());
+        },
+        .cast => |cast| {
+            try self.writeOperand(cast.value, depth, .cast);
+            try self.write(" as ");
+            try self.writeNode(cast.target, depth);
+        },
+        .sizeof => |annotation| {
+            try self.write("sizeof(");
+            try self.writeNode(annotation, depth);
+            try self.write(")");
+        },
+        .closure => |closure| {
+            try self.write("fn");
+            try self.writeList(closure.params, depth, writeParameter);
+            try self.write(" ");
+            if (closure.return_type) |return_type| {
+                try self.write("-> ");
+                try self.writeOperand(return_type, depth, .primary);
+                try self.write(" ");
+            }
+            try self.writeNode(closure.body, depth);
+        },
+        .identifier => |identifier| try self.write(identifier),
+        .literal => |literal| try Ast.writeLiteral(self.output.writer(), literal),
+        .type_expr => |type_expr| switch (type_expr) {
+            .generic => |generic| {
+                try self.print("{s}<", .{generic.name});
+                for (generic.arguments, 0..) |argument, i| {
+                    if (i > 0) try self.write(", ");
+                    try self.writeNode(argument, depth);
+                }
+                try self.write(">");
+            },
+            .array => |array| {
+                try self.write("[");
+                try self.writeNode(array.element, depth);
+                try self.write("; ");
+                try self.writeNode(array.len, depth);
+                try self.write("]");
+            },
+            .slice => |element| {
+                try self.write("[");
+                try self.writeNode(element, depth);
+                try self.write("]");
+            },
+            // A single type in parentheses is only grouped, so its trailing comma is kept
+            .tuple => |elements| if (elements.len == 1) {
+                try self.write("(");
+                try self.writeNode(elements[0], depth);
+                try self.write(",)");
+            } else try self.writeList(elements, depth, writeNode),
+            .function => |function| {
+                try self.write("fn");
+                try self.writeList(function.params, depth, writeNode);
+                if (function.return_type) |return_type| {
+                    try self.write(" -> ");
+                    try self.writeNode(return_type, depth);
+                }
+            },
+            .reference => |referenced| {
+                try self.write("&");
+                try self.writeNode(referenced, depth);
+            },
+            .impl => |trait| try self.print("impl {s}", .{trait}),
+            .variadic => try self.write("...")
+        }
+    }
+}
+
+fn writeTypeParams(self: *Formatter, type_params: []const []const u8) Error!void {
+    if (type_params.len == 0) return;
+
+    try self.write("<");
+    for (type_params, 0..) |type_param, i| {
+        if (i > 0) try self.write(", ");
+        try self.write(type_param);
+    }
+    try self.write(">");
+}
+
+// The fields of a struct or union definition, each on a line of its own. The
+// definition ends at end
+fn writeFieldDefinitions(self: *Formatter, fields: []const Node.StructDef.Field, end: usize, depth: usize) Error!void {
+    if (fields.len == 0 and !self.hasCommentBefore(end)) return try self.write("{}");
+
+    try self.write("{");
+    for (fields) |field| {
+        try self.newline();
+        try self.indent(depth + 1);
+        try self.writeLeadingComments(field.span.start, depth + 1);
+        try self.print("{s}: ", .{field.name});
+        try self.writeOperand(field.annotation, depth + 1, .primary);
+        try self.writeTrailingComments(field.span.end);
+    }
+    try self.writeRemainingComments(end, depth + 1);
+    try self.newline();
+    try self.indent(depth);
+    try self.write("}");
+}
+
+fn writeFieldValues(self: *Formatter, fields: []const Node.StructLiteral.Field, depth: usize) Error!void {
+    try self.write("{");
+    for (fields, 0..) |field, i| {
+        try self.write(if (i > 0) ", " else " ");
+        try self.print("{s}: ", .{field.name});
+        try self.writeNode(field.value, depth);
+    }
+    try self.write(if (fields.len > 0) " }" else "}");
+}
+
+// Operands binding looser than min are parenthesized, so they are read back
+// as the same tree
+fn writeOperand(self: *Formatter, node: *const Node, depth: usize, min: Precedence) Error!void {
+    if (node.precedence().lessThan(min)) {
+        try self.write("(");
+        try self.writeNode(node, depth);
+        try self.write(")");
+    } else {
+        try self.writeNode(node, depth);
+    }
+}
+
+// Struct literals in conditions are parenthesized, so their braces aren't read as
+// beginning the block after the condition
+fn writeCondition(self: *Formatter, node: *const Node, depth: usize) Error!void {
+    if (node.hasBareStructLiteral()) {
+        try self.write("(");
+        try self.writeNode(node, depth);
+        try self.write(")");
+    } else {
+        try self.writeNode(node, depth);
+    }
+}
+
+fn writeParameter(self: *Formatter, param: Node.Parameter, depth: usize) Error!void {
+    if (param.shorthand) return try self.write(if (Ast.isReferenceType(param.annotation.?)) "&self" else "self");
+
+    try self.write(param.name);
+    if (param.annotation) |annotation| {
+        try self.write(": ");
+        try self.writeOperand(annotation, depth, .primary);
+    }
+}
+
+// Writes items within parens on the current line if they fit, otherwise each on a
+// line of its own
+fn writeList(self: *Formatter, items: anytype, depth: usize, comptime writeItem: anytype) Error!void {
+    try self.writeDelimited(items, depth, "(", ")", writeItem);
+}
+
+fn writeDelimited(
+    self: *Formatter,
+    items: anytype,
+    depth: usize,
+    open: []const u8,
+    close: []const u8,
+    comptime writeItem: anytype
+) Error!void {
+    const mark = self.output.items.len;
+    const flat = self.flat;
+    // Comments written in the attempt are written again if it is undone
+    const comment = self.comment;
+
+    self.flat = true;
+    try self.write(open);
+    for (items, 0..) |item, i| {
+        if (i > 0) try self.write(", ");
+        try writeItem(self, item, depth);
+    }
+    try self.write(close);
+    self.flat = flat;
+
+    if (flat or items.len == 0 or self.fits(mark)) return;
+
+    self.output.shrinkRetainingCapacity(mark);
+    self.comment = comment;
+    try self.write(open);
+    for (items, 0..) |item, i| {
+        try self.newline();
+        try self.indent(depth + 1);
+        try writeItem(self, item, depth + 1);
+        if (i + 1 < items.len) try self.write(",");
+    }
+    try self.newline();
+    try self.indent(depth);
+    try self.write(close);
+}
+
+test "formatter" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const AstBuilder = ruka.AstBuilder;
+
+    fn expectFormatted(expected: []const u8, source: []const u8, options: Options) !void {
+        const formatted = try format(testing.allocator, source, options);
+        defer testing.allocator.free(formatted);
+
+        try testing.expectEqualStrings(expected, formatted);
+    }
+
+    test "canonical format" {
+        try expectFormatted(
+            \\let x = 1 + 2 * 3
+            \\var y: i32 = x
+            \\import std.math as m
+            \\
+            \\pub const z = x - 1
+            \\
+        ,
+            \\let   x=1+(2*3);var y : i32 = x
+            \\
+            \\
+            \\import std.math   as m
+            \\pub const z = ((x) - 1)
+        , .{});
+    }
+
+    test "parentheses are only kept where precedence needs them" {
+        try expectFormatted(
+            \\let a = (1 + 2) * 3
+            \\let b = 1 - (2 - 3)
+            \\let c = 1 - 2 - 3
+            \\let d = 2 ** 3 ** 2
+            \\let e = (2 ** 3) ** 2
+            \\let f = -2 ** 2
+            \\let g = (-2) ** 2
+            \\let h = !(-x)
+            \\x = y = a or b and c
+            \\
+        ,
+            \\let a = (1 + 2) * 3
+            \\let b = 1 - (2 - 3)
+            \\let c = (1 - 2) - 3
+            \\let d = 2 ** (3 ** 2)
+            \\let e = (2 ** 3) ** 2
+            \\let f = -(2 ** 2)
+            \\let g = (-2) ** 2
+            \\let h = !-x
+            \\x = (y = (a or (b and c)))
+        , .{});
+    }
+
+    test "trailing newlines" {
+        try expectFormatted("let x = 1", "let x = 1\n\n", .{ .trailing_newline = false });
+        try expectFormatted("", "\n\n", .{});
+    }
+
+    test "attributes" {
+        try expectFormatted(
+            \\#[allow(dead_code, shadowing)]
+            \\fn f() -> i64 {
+            \\    #[deny(unused_variable)]
+            \\    var x = 1
+            \\    x
+            \\}
+            \\
+        ,
+            \\#[allow(dead_code,shadowing)] fn f() -> i64 {
+            \\    #[deny( unused_variable )]
+            \\
+            \\    var x = 1
+            \\    x
+            \\}
+        , .{});
+    }
+
+    test "comments are kept" {
+        const expected =
+            \\/// Adds one to x
+            \\fn inc(x: i64) -> i64 {
+            \\    // the result
+            \\    x + 1 /* can't overflow */
+            \\}
+            \\
+            \\fn later() {
+            \\    // nothing yet
+            \\}
+            \\
+            \\/* a block
+            \\   comment */
+            \\let y = inc(1) // trailing
+            \\/// at the end
+            \\
+        ;
+
+        try expectFormatted(expected,
+            \\/// Adds one to x
+            \\fn inc(x: i64)->i64 {
+            \\  // the result
+            \\  x+1 /* can't overflow */
+            \\}
+            \\fn later() {
+            \\        // nothing yet
+            \\}
+            \\/* a block
+            \\   comment */
+            \\
+            \\let y = inc(1)   // trailing
+            \\/// at the end
+        , .{});
+        try expectFormatted(expected, expected, .{});
+    }
+
+    test "formatting is idempotent" {
+        const sources = [_][]const u8{
+            "let x = 1",
+            "var x = 1\nvar y = 2",
+            "const x: i32 = 42",
+            "let s = \"hello\\tworld\\n\"",
+            "let c = 'c'; let d = '\\n'",
+            "let f = 1.5 + 2.0",
+            "let e = 100_000 * 3",
+            "let b = true and false or true",
+            "let n = (1 + 2) * (3 + 4)",
+            "let deep = ((((1))))",
+            "let r = a + (b - (c * (d / e)))",
+            "let cmp = x <= y == (z > w)",
+            "let bits = a & b | (c ^ d) << 2",
+            "let range = 0 .. 10",
+            "let inclusive = 0 ..= 10",
+            "let joined = \"a\" <> \"b\"",
+            "let piped = x |> f <| g",
+            "import std",
+            "import std.math.trig as t\nimport util\nlet x = t",
+            "pub let x = 1\npub var y = 2\npub const z = 3",
+            "let x = 1;;;\n\n\n;let y = x",
+            "let big = 1 ** 2 % 3 >> 1",
+            "x + 1\ny * (2 - x)",
+            "x = y = -z ** 2 < 1 == true",
+            "fn id<T>(x: T) -> T {\n    return x\n}",
+            "fn f() {}\nlet y = f()(1, g(2))",
+            "let x = if a {\n    1\n} else if b {\n    2\n} else {\n    3\n}",
+            "while true {\n    break\n}\nfor i in 0..10 {\n    continue\n}",
+            "let y = match x {\n    0 | -1 => 0\n    (a, (b,)) if a > b => a\n    P { q, r: _ } => q\n    E::V(()) => 1\n    E::W { x: 1, y } => y\n}",
+            "let (a, (b,)) = t\nlet P { x, y: py }: P = p",
+            "let r = &x\n*r = *r * 2\nf(&(*r), *p.x)",
+            "let s = sizeof(i64) * sizeof([(i8, &P); 4])",
+            "let c = -x as i32 * 2 + (a + b) as f64 as i64\nlet p = &x as &i8",
+            "fn f() {\n    defer close(x)\n    defer {\n        flush()\n    }\n}",
+            "extern fn printf(fmt: str, ...) -> i32\nextern fn abort()\nunsafe {\n    printf(\"%d\", 1)\n}",
+            "fn f() {\n    unsafe {\n        start:\n        goto start\n    }\n}",
+            "let bits = comptime {\n    if TARGET_BITS == 64 {\n        8\n    } else {\n        4\n    }\n}",
+            "struct P {\n    x: f64\n    y: f64\n}\n\nimpl P {\n    fn f(self: P) {}\n\n    fn g() {}\n}",
+            "impl P {\n    fn f(self) -> P {\n        self\n    }\n\n    fn g(&self, x: f64) {}\n}",
+            "trait Shape {\n    fn area(&self) -> f64\n    fn name(self) -> str\n\n    fn describe(&self) -> str {\n        \"shape\"\n    }\n}\n\nimpl Shape for P {\n    fn area(&self) -> f64 {\n        0.0\n    }\n}\n\nfn show(s: &impl Shape) {}",
+            "async fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nasync fn twice() -> i32 {\n    await add(1, 2) * 2\n}\n\nlet f: Future<i32> = twice()",
+            "let p = P { x: 1.0, y: (P {}).z.w }\nif (P { x: 1 }).x == p.x {}\nmatch (a == P {}) {}",
+            "union Bits {\n    i: i32\n    f: f32\n}\n\nlet b = Bits { f: 1.0 }",
+            "enum E {\n    A\n    B(i64, f64)\n    C { x: i64 }\n}\n\nlet e = E::C { x: E::B(1, 2.0) }\nif e == E::A {}\nwhile (e == E::C { x: 1 }) {}",
+            "fn f(g: fn(i64, (f64, bool)) -> &[u8], o: Option<Vec<(i64,)>>) -> fn() {}\nlet r: Result<(), str> = x",
+            "let add = fn(x: i64, y) -> i64 {\n    x + y\n}\nfn() {}()",
+            "let r = Ok(-f()?.y)\nlet e = Err(r?)",
+            "// first\nstruct P {\n    x: f64 // across\n    /* and */\n    y: f64\n    // last\n}\n\nimpl P {\n    // none\n}",
+            "enum E {\n    /// docs\n    A\n    B // bee\n}\n\nlet y = match x {\n    // zero\n    0 => 0 /* nothing */\n    _ => f(1, /* inner */ 2)\n}"
+        };
+
+        for (sources) |source| {
+            const once = try format(testing.allocator, source, .{});
+            defer testing.allocator.free(once);
+
+            const twice = try format(testing.allocator, once, .{});
+            defer testing.allocator.free(twice);
+
+            try testing.expectEqualStrings(once, twice);
+        }
+    }
+
+    test "formatted source parses to the same tree" {
+        const source = "let x = 1 + (2 * 3) - 4; const y: i32 = (x) == 'a'";
+
+        const formatted = try format(testing.allocator, source, .{});
+        defer testing.allocator.free(formatted);
+
+        var trees: [2]ArrayList(u8) = .{.init(testing.allocator), .init(testing.allocator)};
+        defer for (&trees) |*tree| tree.deinit();
+
+        for ([_][]const u8{source, formatted}, &trees) |input, *tree| {
+            var stream = std.io.fixedBufferStream(input);
+
+            var unit = try Unit.init(.testing(stream.reader().any(), std.io.null_writer.any()));
+            defer unit.deinit();
+
+            const ast = try unit.parse();
+            defer ast.deinit();
+
+            try tree.writer().print("{}", .{ast.root});
+        }
+
+        try testing.expectEqualStrings(trees[0].items, trees[1].items);
+    }
+
+    test "sources which don't parse aren't formatted" {
+        try testing.expectError(error.ParsingFailed, format(testing.allocator, "let = 1", .{}));
+    }
+
+    test "indentation and long lists" {
+        const ast = try Ast.init(testing.allocator, "test source");
+        defer ast.deinit();
+
+        // `fn f(first, second) { g(first, second) }`
+        const b = AstBuilder.init(ast);
+        const call = try b.call("g", &.{try b.identifier("first"), try b.identifier("second")});
+        try b.module(&.{
+            try b.function("f", &.{.{"first", null}, .{"second", null}}, null, try b.block(&.{call}))
+        });
+
+        var formatter = Formatter.init(testing.allocator, .{ .indent_width = 2, .max_line_length = 16 });
+        defer formatter.deinit();
+
+        const formatted = try formatter.formatAst(ast);
+        defer testing.allocator.free(formatted);
+
+        try testing.expectEqualStrings(
+            \\fn f(
+            \\  first,
+            \\  second
+            \\) {
+            \\  g(
+            \\    first,
+            \\    second
+            \\  )
+            \\}
+            \\
+        , formatted);
+    }
+};
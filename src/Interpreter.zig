@@ -3,32 +3,2021 @@
 
 const std = @import("std");
 const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
 
 const ruka = @import("prelude.zig");
+const Ast = ruka.Ast;
+const ConstantFolder = ruka.optimizer.ConstantFolder;
+const Node = ruka.Node;
+const Span = ruka.Span;
+const stdlib = ruka.stdlib;
+const Target = ruka.Target;
+const Unit = ruka.Unit;
+
+unit: *Unit,
+env: *Environment,
+/// The value of the return unwinding to the innermost call
+returned: Value,
+/// The number of calls being evaluated
+depth: usize,
+/// The bodies of the defers of the blocks being evaluated, each block runs those
+/// deferred within it as it is left
+defers: ArrayList(*const Node),
+/// The trait_defs evaluated by name, whose defaults are bound for the impls of
+/// them which don't define those methods
+traits: std.StringHashMap(*const Node),
 
 allocator: Allocator,
 
 const Interpreter = @This();
 
-pub fn init(allocator: Allocator) !*Interpreter {
-    const interpreter = try allocator.create(Interpreter);
-    errdefer interpreter.deinit();
+pub const Environment = @import("interpreter/Environment.zig");
+pub const Executor = @import("interpreter/Executor.zig");
+
+const log = std.log.scoped(.interpreter);
+
+// Explicit so the mutually recursive evaluating functions can resolve their error sets.
+// Return unwinds to the innermost call, with the value in returned, and Break and
+// Continue unwind to the innermost loop
+pub const EvalError = error{RuntimeError, Return, Break, Continue} || Allocator.Error;
+
+/// Calls nested deeper than this fail, rather than overflowing the stack
+const max_depth = 512;
+
+/// The result of evaluating a node
+pub const Value = union(enum) {
+    integer: i64,
+    float: f64,
+    boolean: bool,
+    /// Owned by the environment the value was produced in
+    string: []const u8,
+    character: u8,
+    unit,
+    /// The definition of the function, the ast containing it must outlive the value
+    function: *const Node,
+    /// Owned by the environment the value was produced in
+    closure: *const Closure,
+    range: Range,
+    /// The elements of an array or a slice of one, owned by the environment the
+    /// value was produced in
+    array: []const Value,
+    /// The elements of a tuple, owned by the environment the value was produced in
+    tuple: []const Value,
+    @"struct": Struct,
+    variant: Variant,
+    method: Method,
+    option_method: OptionMethod,
+    /// A function of one of the built in modules
+    builtin: *const stdlib.Function,
+    reference: Reference,
+    /// Owned by the environment the value was produced in
+    future: *Future,
+
+    pub const Range = struct {
+        start: i64,
+        end: i64,
+        inclusive: bool
+    };
+
+    /// The fields are in the order the literal producing the struct gave them, and
+    /// are owned by the environment the value was produced in
+    pub const Struct = struct {
+        name: []const u8,
+        fields: []const Field,
+
+        pub const Field = struct {
+            name: []const u8,
+            value: Value
+        };
+
+        pub fn get(self: Struct, name: []const u8) ?Value {
+            return getField(self.fields, name);
+        }
+
+        pub fn getField(fields: []const Field, name: []const u8) ?Value {
+            for (fields) |field| if (std.mem.eql(u8, field.name, name)) return field.value;
+            return null;
+        }
+    };
+
+    /// A value of an enum, its fields are owned by the environment the value was
+    /// produced in
+    pub const Variant = struct {
+        @"enum": []const u8,
+        name: []const u8,
+        fields: Fields,
+
+        pub const Fields = union(enum) {
+            unit,
+            tuple: []const Value,
+            @"struct": []const Struct.Field
+        };
+    };
+
+    /// An anonymous function and the values of the bindings it captures, as they
+    /// were when it was evaluated. The ast containing it must outlive the value
+    pub const Closure = struct {
+        node: *const Node,
+        captured: std.StringHashMap(Value)
+    };
+
+    /// Refers to a binding by the scope it is bound in, or to a field or element of
+    /// one by the path followed to it from the binding
+    pub const Reference = struct {
+        scope: usize,
+        name: []const u8,
+        /// Owned by the environment the value was produced in
+        path: []const Step,
+
+        pub const Step = union(enum) {
+            field: []const u8,
+            /// An element of a tuple
+            position: usize,
+            /// An element of an array
+            element: usize,
+
+            pub fn eql(self: Step, other: Step) bool {
+                if (std.meta.activeTag(self) != std.meta.activeTag(other)) return false;
+
+                return switch (self) {
+                    .field => |name| std.mem.eql(u8, name, other.field),
+                    .position => |position| position == other.position,
+                    .element => |element| element == other.element
+                };
+            }
+
+            pub fn format(self: Step, comptime _: []const u8, _: std.fmt.FormatOptions, writer: anytype) !void {
+                switch (self) {
+                    .field => |name| try writer.print(".{s}", .{name}),
+                    .position => |position| try writer.print(".{}", .{position}),
+                    .element => |element| try writer.print("[{}]", .{element})
+                }
+            }
+        };
+    };
+
+    /// A call of an async function, which like a closure captures the arguments it
+    /// was given. The function runs once the future is polled by an executor
+    pub const Future = struct {
+        function: *const Node,
+        arguments: []const Value,
+        /// What the function returned, once it has run
+        result: ?Value = null
+    };
+
+    /// A method accessed through a value, which is passed to it as its first argument
+    pub const Method = struct {
+        function: *const Node,
+        receiver: *const Value
+    };
+
+    /// A method of the prelude's Option accessed through a value, impls can't be
+    /// written for generic enums so the interpreter implements them
+    pub const OptionMethod = struct {
+        kind: Kind,
+        receiver: *const Value,
+
+        pub const Kind = enum { map, and_then, unwrap_or, is_some, is_none };
+    };
+
+    pub fn eql(self: Value, other: Value) bool {
+        if (std.meta.activeTag(self) != std.meta.activeTag(other)) return false;
+
+        return switch (self) {
+            .integer => |integer| integer == other.integer,
+            .float => |float| float == other.float,
+            .boolean => |boolean| boolean == other.boolean,
+            .string => |string| std.mem.eql(u8, string, other.string),
+            .character => |character| character == other.character,
+            .unit => true,
+            .function => |function| function == other.function,
+            .closure => |closure| closure == other.closure,
+            .range => |range| std.meta.eql(range, other.range),
+            .array => |elements| valuesEql(elements, other.array),
+            .tuple => |elements| valuesEql(elements, other.tuple),
+            .@"struct" => |structure| std.mem.eql(u8, structure.name, other.@"struct".name)
+                and fieldsEql(structure.fields, other.@"struct".fields),
+            .variant => |variant| {
+                if (!std.mem.eql(u8, variant.@"enum", other.variant.@"enum")) return false;
+                if (!std.mem.eql(u8, variant.name, other.variant.name)) return false;
+                if (std.meta.activeTag(variant.fields) != std.meta.activeTag(other.variant.fields)) return false;
+
+                return switch (variant.fields) {
+                    .unit => true,
+                    .tuple => |values| {
+                        if (values.len != other.variant.fields.tuple.len) return false;
+                        for (values, other.variant.fields.tuple) |value, other_value| {
+                            if (!value.eql(other_value)) return false;
+                        }
+
+                        return true;
+                    },
+                    .@"struct" => |fields| fieldsEql(fields, other.variant.fields.@"struct")
+                };
+            },
+            .method => |method| method.function == other.method.function and method.receiver.eql(other.method.receiver.*),
+            .option_method => |method| method.kind == other.option_method.kind
+                and method.receiver.eql(other.option_method.receiver.*),
+            .builtin => |function| function == other.builtin,
+            .future => |future| future == other.future,
+            .reference => |reference| {
+                if (reference.scope != other.reference.scope) return false;
+                if (!std.mem.eql(u8, reference.name, other.reference.name)) return false;
+                if (reference.path.len != other.reference.path.len) return false;
+                for (reference.path, other.reference.path) |step, other_step| {
+                    if (!step.eql(other_step)) return false;
+                }
+
+                return true;
+            }
+        };
+    }
+
+    fn valuesEql(values: []const Value, others: []const Value) bool {
+        if (values.len != others.len) return false;
+        for (values, others) |value, other| {
+            if (!value.eql(other)) return false;
+        }
+
+        return true;
+    }
+
+    // Fields are compared by name, as literals can give them in any order
+    fn fieldsEql(fields: []const Struct.Field, others: []const Struct.Field) bool {
+        if (fields.len != others.len) return false;
+
+        for (fields) |field| {
+            const other_value = Struct.getField(others, field.name) orelse return false;
+            if (!field.value.eql(other_value)) return false;
+        }
+
+        return true;
+    }
+
+    /// Formats the value as the literal which would produce it
+    pub fn format(self: Value, comptime _: []const u8, _: std.fmt.FormatOptions, writer: anytype) !void {
+        switch (self) {
+            .integer => |integer| try writer.print("{}", .{integer}),
+            .float => |float| {
+                // Large enough for the longest decimal representation of an f64
+                var buf: [512]u8 = undefined;
+                const str = std.fmt.bufPrint(&buf, "{d}", .{float}) catch unreachable;
+                try writer.writeAll(str);
+
+                // Floats always include a decimal point, so they aren't mistaken for integers
+                if (std.mem.indexOfAny(u8, str, ".ein") == null) try writer.writeAll(".0");
+            },
+            .boolean => |boolean| try writer.writeAll(if (boolean) "true" else "false"),
+            .string => |string| try writer.print("\"{s}\"", .{string}),
+            .character => |character| try writer.print("'{c}'", .{character}),
+            .unit => try writer.writeAll("()"),
+            .function, .method => try writer.print("fn {s}", .{self.functionNode().kind.fn_def.name}),
+            .option_method => |method| try writer.print("fn {s}", .{@tagName(method.kind)}),
+            .builtin => |function| try writer.print("fn {s}.{s}", .{function.module, function.name}),
+            .future => |future| try writer.print("future fn {s}", .{future.function.kind.fn_def.name}),
+            .reference => |reference| {
+                try writer.print("&{s}", .{reference.name});
+                for (reference.path) |step| try writer.print("{}", .{step});
+            },
+            .closure => |closure| {
+                try writer.writeAll("fn(");
+                for (closure.node.kind.closure.params, 0..) |param, i| {
+                    if (i > 0) try writer.writeAll(", ");
+                    try writer.writeAll(param.name);
+                }
+                try writer.writeAll(") {...}");
+            },
+            .range => |range| try writer.print("{}{s}{}", .{
+                range.start,
+                if (range.inclusive) "..=" else "..",
+                range.end
+            }),
+            .array => |elements| {
+                try writer.writeByte('[');
+                for (elements, 0..) |element, i| {
+                    if (i > 0) try writer.writeAll(", ");
+                    try writer.print("{}", .{element});
+                }
+                try writer.writeByte(']');
+            },
+            .tuple => |elements| {
+                try writer.writeByte('(');
+                for (elements, 0..) |element, i| {
+                    if (i > 0) try writer.writeAll(", ");
+                    try writer.print("{}", .{element});
+                }
+                if (elements.len == 1) try writer.writeByte(',');
+                try writer.writeByte(')');
+            },
+            .@"struct" => |structure| {
+                try writer.print("{s} ", .{structure.name});
+                try formatFields(structure.fields, writer);
+            },
+            .variant => |variant| {
+                try writer.print("{s}::{s}", .{variant.@"enum", variant.name});
+                switch (variant.fields) {
+                    .unit => {},
+                    .tuple => |values| {
+                        try writer.writeByte('(');
+                        for (values, 0..) |value, i| {
+                            if (i > 0) try writer.writeAll(", ");
+                            try writer.print("{}", .{value});
+                        }
+                        try writer.writeByte(')');
+                    },
+                    .@"struct" => |fields| {
+                        try writer.writeByte(' ');
+                        try formatFields(fields, writer);
+                    }
+                }
+            }
+        }
+    }
+
+    fn formatFields(fields: []const Struct.Field, writer: anytype) !void {
+        try writer.writeByte('{');
+        for (fields, 0..) |field, i| {
+            try writer.writeAll(if (i > 0) ", " else " ");
+            try writer.print("{s}: {}", .{field.name, field.value});
+        }
+        try writer.writeAll(if (fields.len > 0) " }" else "}");
+    }
+
+    fn functionNode(self: Value) *const Node {
+        return switch (self) {
+            .function => |function| function,
+            .method => |method| method.function,
+            else => unreachable
+        };
+    }
+};
+
+/// Creates an interpreter binding top level names in env, which outlives it
+pub fn init(unit: *Unit, env: *Environment) !*Interpreter {
+    const interpreter = try unit.allocator.create(Interpreter);
 
     interpreter.* = .{
-        .allocator = allocator
+        .unit = unit,
+        .env = env,
+        .returned = .unit,
+        .depth = 0,
+        .defers = .init(unit.allocator),
+        .traits = .init(unit.allocator),
+        .allocator = unit.allocator
     };
 
     return interpreter;
 }
 
 pub fn deinit(self: *Interpreter) void {
+    self.defers.deinit();
+    self.traits.deinit();
     self.allocator.destroy(self);
 }
 
+/// Evaluates node, which for a module evaluates its items in order and then calls
+/// its main function if it defines one. Evaluation stops at the first runtime
+/// error, which is recorded in the unit
+pub fn eval(self: *Interpreter, node: *const Node) !Value {
+    return self.evaluate(node) catch |err| switch (err) {
+        // Returning outside of a function ends the program with the value
+        error.Return => self.returned,
+        error.Break, error.Continue => {
+            try self.unit.createError("break or continue outside of a loop", node.span);
+            return error.EvaluationFailed;
+        },
+        error.RuntimeError => {
+            log.err("{s}: evaluation failed with {} error(s)", .{self.unit.input, self.unit.errorCount()});
+            return error.EvaluationFailed;
+        },
+        else => |e| return e
+    };
+}
+
+fn runtimeError(self: *Interpreter, span: Span, comptime fmt: []const u8, args: anytype) EvalError {
+    self.unit.createErrorFmt(span, fmt, args) catch |err| return err;
+    return error.RuntimeError;
+}
+
+fn evaluate(self: *Interpreter, node: *const Node) EvalError!Value {
+    return switch (node.kind) {
+        .module => |module| try self.evalModule(node, module),
+        .fn_def => |fn_def| {
+            try self.env.define(fn_def.name, .{ .function = node });
+            return .unit;
+        },
+        .struct_def, .union_def, .enum_def => .unit,
+        .impl => |impl| {
+            for (impl.functions) |function| {
+                const name = try std.fmt.allocPrint(self.allocator, "{s}.{s}", .{impl.name, function.kind.fn_def.name});
+                defer self.allocator.free(name);
+
+                try self.env.define(name, .{ .function = function });
+            }
+
+            const trait = self.traits.get(impl.trait orelse return .unit) orelse return .unit;
+            for (trait.kind.trait_def.methods) |method| {
+                const default = method.default orelse continue;
+                const defined = for (impl.functions) |function| {
+                    if (std.mem.eql(u8, function.kind.fn_def.name, method.name)) break true;
+                } else false;
+                if (defined) continue;
+
+                const name = try std.fmt.allocPrint(self.allocator, "{s}.{s}", .{impl.name, method.name});
+                defer self.allocator.free(name);
+
+                try self.env.define(name, .{ .function = default });
+            }
+
+            return .unit;
+        },
+        .trait_def => |trait_def| {
+            try self.traits.put(trait_def.name, node);
+            return .unit;
+        },
+        .var_decl, .const_decl => |binding| {
+            const value = if (binding.value) |v| try self.evaluate(v) else .unit;
+            try self.env.define(binding.name, coerce(binding.annotation, value));
+
+            return .unit;
+        },
+        // The checker only allows irrefutable patterns, which match any value of their type
+        .destructure => |destructure| {
+            const value = coerce(destructure.annotation, try self.evaluate(destructure.value));
+            if (!try self.matchPattern(destructure.pattern, value)) {
+                return self.runtimeError(node.span, "{} doesn't match the binding's pattern", .{value});
+            }
+
+            return .unit;
+        },
+        .import => self.runtimeError(node.span, "imports are only supported when compiling", .{}),
+        .extern_fn => self.runtimeError(node.span, "extern functions are only supported when compiling", .{}),
+        .@"export" => |declaration| try self.evaluate(declaration),
+        .block => |block| try self.evalBlock(block),
+        .@"if" => |conditional| try self.evalIf(conditional),
+        .@"while" => |loop| try self.evalWhile(loop),
+        .@"for" => |loop| try self.evalFor(loop),
+        .match => |match| try self.evalMatch(node, match),
+        .@"return" => |value| {
+            self.returned = if (value) |v| try self.evaluate(v) else .unit;
+            return error.Return;
+        },
+        // Loops evaluate to (), so the value is only evaluated for its effects
+        .@"break" => |value| {
+            if (value) |v| _ = try self.evaluate(v);
+            return error.Break;
+        },
+        .@"continue" => error.Continue,
+        .@"defer" => |body| {
+            try self.defers.append(body);
+            return .unit;
+        },
+        .@"unsafe" => |body| try self.evaluate(body),
+        .@"comptime" => |body| try self.evalComptime(body),
+        .label => .unit,
+        .goto => self.runtimeError(node.span, "gotos are only supported when compiling", .{}),
+        .assignment => |assignment| try self.evalAssignment(assignment),
+        .prefix => |prefix| try self.evalPrefix(node, prefix),
+        .infix => |infix| try self.evalInfix(node, infix),
+        .fn_call => |call| {
+            if (self.isPreludeFunction(call.callee, "assert")) return try self.evalAssert(node, call.arguments);
+            if (self.isPreludeFunction(call.callee, "fmt")) return try self.evalFmt(node, call.arguments);
+            if (self.isPreludeFunction(call.callee, "block_on")) return try self.evalBlockOn(node, call.arguments);
+
+            const values = try self.allocator.alloc(Value, call.arguments.len);
+            defer self.allocator.free(values);
+
+            for (values, call.arguments) |*value, argument| value.* = try self.evaluate(argument);
+
+            return try self.call(node.span, try self.evaluate(call.callee), values);
+        },
+        .struct_literal => |literal| .{ .@"struct" = .{
+            .name = literal.name,
+            .fields = try self.evalFields(literal.fields)
+        }},
+        .variant_literal => |literal| try self.evalVariantLiteral(literal),
+        .array_literal => |elements| block: {
+            const values = try self.env.valueAllocator().alloc(Value, elements.len);
+            for (values, elements) |*value, element| value.* = try self.evaluate(element);
+
+            break :block .{ .array = values };
+        },
+        .index => |index| try self.evalIndex(node, index),
+        .tuple_literal => |elements| block: {
+            const values = try self.env.valueAllocator().alloc(Value, elements.len);
+            for (values, elements) |*value, element| value.* = try self.evaluate(element);
+
+            break :block .{ .tuple = values };
+        },
+        .tuple_index => |access| try self.evalTupleIndex(node, access),
+        .field_access => |access| try self.evalFieldAccess(node, access),
+        .closure => |closure| try self.evalClosure(node, closure),
+        .@"try" => |operand| try self.evalTry(node, operand),
+        .reference => |operand| .{ .reference = try self.evalPlace(operand) },
+        .dereference => try self.load(node.span, try self.evalPlace(node)),
+        .@"await" => |operand| {
+            const future = try self.evaluate(operand);
+            if (future != .future) return self.runtimeError(operand.span, "only futures can be awaited, found {}", .{future});
+
+            return try self.blockOn(node.span, future.future);
+        },
+        // The interpreter doesn't check programs, so only knows the sizes of the built in types
+        .sizeof => |annotation| {
+            const size = ConstantFolder.knownSize(annotation, Target.pointerSizeOf(self.unit.options.target)) orelse {
+                return self.runtimeError(node.span, "sizeof is only supported for built in types and references when interpreting", .{});
+            };
+
+            return .{ .integer = @intCast(size) };
+        },
+        .cast => |cast| {
+            const value = try self.evaluate(cast.value);
+            const target = switch (cast.target.kind) {
+                .identifier => |name| name,
+                // Pointer casts leave the reference as it was
+                else => return value
+            };
+
+            return castValue(value, target);
+        },
+        .identifier => |name| self.env.lookup(name) orelse return self.runtimeError(node.span, "{s} is not defined", .{name}),
+        .literal => |literal| switch (literal) {
+            .integer => |integer| .{ .integer = integer },
+            .float => |float| .{ .float = float },
+            .boolean => |boolean| .{ .boolean = boolean },
+            .string => |string| .{ .string = try self.env.valueAllocator().dupe(u8, string) },
+            .character => |character| .{ .character = character },
+            .unit => .unit
+        },
+        // Only written in annotations, which aren't evaluated
+        .type_expr => unreachable
+    };
+}
+
+// Integers bound with a float annotation become floats, as the type checker
+// coerces their literals
+fn coerce(annotation: ?*Node, value: Value) Value {
+    const name = if (annotation) |a| switch (a.kind) {
+        .identifier => |identifier| identifier,
+        else => return value
+    } else return value;
+
+    const float = std.mem.eql(u8, name, "f32") or std.mem.eql(u8, name, "f64");
+    return if (float and value == .integer) .{ .float = @floatFromInt(value.integer) } else value;
+}
+
+fn evalModule(self: *Interpreter, node: *const Node, module: Node.Module) EvalError!Value {
+    // Functions and the functions of impls are bound first, so they can be called
+    // before they are defined. Traits are found before the impls of them, which
+    // are given their defaults
+    var main: ?*const Node = null;
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def => |fn_def| {
+                if (std.mem.eql(u8, fn_def.name, "main")) main = item;
+                _ = try self.evaluate(item);
+            },
+            .trait_def => _ = try self.evaluate(item),
+            else => {}
+        }
+    }
+    for (module.items) |item| if (item.unwrapExport().kind == .impl) {
+        _ = try self.evaluate(item.unwrapExport());
+    };
+
+    var value: Value = .unit;
+    for (module.items) |item| switch (item.unwrapExport().kind) {
+        .fn_def, .impl, .trait_def => {},
+        else => value = try self.evaluate(item)
+    };
+
+    // Programs with a main function evaluate to its result, others to their last
+    // item. The future of an async main is run to completion
+    if (main) |function| {
+        const result = try self.call(node.span, .{ .function = function }, &.{});
+        return if (result == .future) try self.blockOn(node.span, result.future) else result;
+    }
+    return value;
+}
+
+// The fields are allocated in the environment, which owns the values produced in it
+fn evalFields(self: *Interpreter, literal_fields: []const Node.StructLiteral.Field) EvalError![]const Value.Struct.Field {
+    const fields = try self.env.valueAllocator().alloc(Value.Struct.Field, literal_fields.len);
+    for (fields, literal_fields) |*field, given| field.* = .{
+        .name = given.name,
+        .value = try self.evaluate(given.value)
+    };
+
+    return fields;
+}
+
+fn evalVariantLiteral(self: *Interpreter, literal: Node.VariantLiteral) EvalError!Value {
+    const fields: Value.Variant.Fields = switch (literal.fields) {
+        .unit => .unit,
+        .tuple => |given| block: {
+            const values = try self.env.valueAllocator().alloc(Value, given.len);
+            for (values, given) |*value, node| value.* = try self.evaluate(node);
+
+            break :block .{ .tuple = values };
+        },
+        .@"struct" => |given| .{ .@"struct" = try self.evalFields(given) }
+    };
+
+    return .{ .variant = .{
+        .@"enum" = literal.@"enum",
+        .name = literal.variant,
+        .fields = fields
+    }};
+}
+
+// Ok and Some values are unwrapped, Err and None values are returned from the
+// enclosing function as they are
+fn evalTry(self: *Interpreter, node: *const Node, operand: *const Node) EvalError!Value {
+    const value = try self.evaluate(operand);
+    if (isVariantOf(value, "Result") or isVariantOf(value, "Option")) {
+        const variant = value.variant;
+        if (variant.fields == .tuple and (std.mem.eql(u8, variant.name, "Ok") or std.mem.eql(u8, variant.name, "Some"))) {
+            return variant.fields.tuple[0];
+        }
+
+        self.returned = value;
+        return error.Return;
+    }
+
+    return self.runtimeError(node.span, "? can only be applied to Result or Option values, found {}", .{value});
+}
+
+fn isVariantOf(value: Value, enum_name: []const u8) bool {
+    return value == .variant and std.mem.eql(u8, value.variant.@"enum", enum_name);
+}
+
+// Programs aren't checked before they are interpreted, so comptime blocks are
+// evaluated when they are reached, knowing the target as the checker would
+fn evalComptime(self: *Interpreter, body: *const Node) EvalError!Value {
+    try self.env.enterScope();
+    defer self.env.exitScope();
+
+    const target = self.unit.options.target;
+    try self.env.define("TARGET_BITS", .{ .integer = @as(i64, Target.pointerSizeOf(target)) * 8 });
+    try self.env.define("TARGET_OS", .{ .string = Target.osOf(target) });
+    try self.env.define("TARGET_ARCH", .{ .string = Target.archOf(target) });
+
+    return try self.evaluate(body);
+}
+
+fn evalBlock(self: *Interpreter, block: Node.Block) EvalError!Value {
+    try self.env.enterScope();
+    defer self.env.exitScope();
+
+    // Returns, breaks and continues unwind through the block, which runs its defers
+    // before passing them on. Runtime errors end evaluation without running them
+    const mark = self.defers.items.len;
+    const value = self.evalStatements(block.statements) catch |err| switch (err) {
+        error.Return, error.Break, error.Continue => {
+            try self.runDefers(mark);
+            return err;
+        },
+        else => {
+            self.defers.shrinkRetainingCapacity(mark);
+            return err;
+        }
+    };
+    try self.runDefers(mark);
+
+    return value;
+}
+
+fn evalStatements(self: *Interpreter, statements: []const *Node) EvalError!Value {
+    var value: Value = .unit;
+    for (statements) |statement| value = try self.evaluate(statement);
+
+    return value;
+}
+
+// Runs the defers after mark, the last deferred first. Defers may call functions,
+// which mustn't replace the value of a return unwinding through them
+fn runDefers(self: *Interpreter, mark: usize) EvalError!void {
+    const returned = self.returned;
+    defer self.returned = returned;
+
+    while (self.defers.items.len > mark) {
+        const body = self.defers.items[self.defers.items.len - 1];
+        self.defers.shrinkRetainingCapacity(self.defers.items.len - 1);
+
+        _ = try self.evaluate(body);
+    }
+}
+
+fn evalCondition(self: *Interpreter, node: *const Node) EvalError!bool {
+    return switch (try self.evaluate(node)) {
+        .boolean => |boolean| boolean,
+        else => |value| self.runtimeError(node.span, "expected a boolean condition, found {}", .{value})
+    };
+}
+
+fn evalIf(self: *Interpreter, conditional: Node.If) EvalError!Value {
+    if (try self.evalCondition(conditional.condition)) return try self.evaluate(conditional.then_block);
+
+    const else_block = conditional.else_block orelse return .unit;
+    return try self.evaluate(else_block);
+}
+
+fn evalWhile(self: *Interpreter, loop: Node.While) EvalError!Value {
+    while (try self.evalCondition(loop.condition)) {
+        if (!try self.evalLoopBody(loop.body)) break;
+    }
+
+    return .unit;
+}
+
+// Evaluates the body of a loop, returning false if it broke out of the loop
+fn evalLoopBody(self: *Interpreter, body: *const Node) EvalError!bool {
+    _ = self.evaluate(body) catch |err| switch (err) {
+        error.Break => return false,
+        error.Continue => return true,
+        else => |e| return e
+    };
+
+    return true;
+}
+
+fn evalFor(self: *Interpreter, loop: Node.For) EvalError!Value {
+    const range = switch (try self.evaluate(loop.iterable)) {
+        .range => |range| range,
+        else => |value| return self.runtimeError(loop.iterable.span, "expected a range to iterate, found {}", .{value})
+    };
+
+    // Computed as the last value, so inclusive ranges ending at the largest integer don't overflow
+    if (range.start > range.end or (range.start == range.end and !range.inclusive)) return .unit;
+    const last = if (range.inclusive) range.end else range.end - 1;
+
+    var i = range.start;
+    while (true): (i = i + 1) {
+        try self.env.enterScope();
+        defer self.env.exitScope();
+
+        try self.env.define(loop.variable, .{ .integer = i });
+        if (!try self.evalLoopBody(loop.body)) break;
+
+        if (i == last) break;
+    }
+
+    return .unit;
+}
+
+fn evalMatch(self: *Interpreter, node: *const Node, match: Node.Match) EvalError!Value {
+    const subject = try self.evaluate(match.subject);
+
+    for (match.arms) |arm| {
+        // Names bound by arms which don't match are discarded with their scope
+        try self.env.enterScope();
+        defer self.env.exitScope();
+
+        if (!try self.matchPattern(arm.pattern, subject)) continue;
+        if (arm.guard) |guard| if (!try self.evalCondition(guard)) continue;
+
+        return try self.evaluate(arm.body);
+    }
+
+    return self.runtimeError(node.span, "no match arm matched {}", .{subject});
+}
+
+// Binds the names in pattern to the parts of value they match, returning whether it matched
+fn matchPattern(self: *Interpreter, pattern: Node.Pattern, value: Value) EvalError!bool {
+    return switch (pattern.kind) {
+        .literal => |literal| value.eql(switch (literal) {
+            .integer => |integer| .{ .integer = integer },
+            .float => |float| .{ .float = float },
+            .boolean => |boolean| .{ .boolean = boolean },
+            .string => |string| .{ .string = string },
+            .character => |character| .{ .character = character },
+            .unit => .unit
+        }),
+        .identifier => |name| {
+            try self.env.define(name, value);
+            return true;
+        },
+        .wildcard => true,
+        .@"or" => |alternatives| for (alternatives) |alternative| {
+            if (try self.matchPattern(alternative, value)) break true;
+        } else false,
+        .@"struct" => |structure_pattern| switch (value) {
+            .@"struct" => |structure| {
+                if (!std.mem.eql(u8, structure_pattern.name, structure.name)) return false;
+                return try self.matchFields(structure.name, structure_pattern.fields, structure.fields);
+            },
+            else => self.invalidPattern(pattern, value)
+        },
+        .variant => |variant_pattern| switch (value) {
+            .variant => |variant| try self.matchVariant(pattern, variant_pattern, variant),
+            else => self.invalidPattern(pattern, value)
+        },
+        .tuple => |patterns| switch (value) {
+            .tuple => |elements| {
+                if (patterns.len != elements.len) return self.invalidPattern(pattern, value);
+
+                for (patterns, elements) |element_pattern, element| {
+                    if (!try self.matchPattern(element_pattern, element)) return false;
+                }
+                return true;
+            },
+            else => self.invalidPattern(pattern, value)
+        }
+    };
+}
+
+fn matchFields(
+    self: *Interpreter,
+    name: []const u8,
+    patterns: []const Node.Pattern.StructPattern.Field,
+    fields: []const Value.Struct.Field
+) EvalError!bool {
+    for (patterns) |field| {
+        const value = Value.Struct.getField(fields, field.name) orelse {
+            return self.runtimeError(field.pattern.span, "{s} has no field named {s}", .{name, field.name});
+        };
+
+        if (!try self.matchPattern(field.pattern, value)) return false;
+    }
+
+    return true;
+}
+
+// Patterns naming the variant by its name alone match the variant of any enum
+fn matchVariant(
+    self: *Interpreter,
+    pattern: Node.Pattern,
+    variant_pattern: Node.Pattern.VariantPattern,
+    variant: Value.Variant
+) EvalError!bool {
+    const path = variant_pattern.path;
+    if (!std.mem.eql(u8, path[path.len - 1], variant.name)) return false;
+    if (path.len > 1 and !std.mem.eql(u8, path[path.len - 2], variant.@"enum")) return false;
+
+    if (std.meta.activeTag(variant_pattern.fields) != std.meta.activeTag(variant.fields)) {
+        return self.mismatchedFields(pattern, variant);
+    }
+
+    switch (variant_pattern.fields) {
+        .unit => return true,
+        .tuple => |patterns| {
+            const values = variant.fields.tuple;
+            if (patterns.len != values.len) return self.mismatchedFields(pattern, variant);
+
+            for (patterns, values) |field, value| if (!try self.matchPattern(field, value)) return false;
+            return true;
+        },
+        .@"struct" => |patterns| return try self.matchFields(variant.name, patterns, variant.fields.@"struct")
+    }
+}
+
+fn mismatchedFields(self: *Interpreter, pattern: Node.Pattern, variant: Value.Variant) EvalError {
+    return self.runtimeError(pattern.span, "pattern does not match the fields of {s}::{s}", .{
+        variant.@"enum",
+        variant.name
+    });
+}
+
+fn invalidPattern(self: *Interpreter, pattern: Node.Pattern, value: Value) EvalError {
+    return self.runtimeError(pattern.span, "{s} patterns can't match {}", .{@tagName(pattern.kind), value});
+}
+
+fn evalTupleIndex(self: *Interpreter, node: *const Node, access: Node.TupleIndex) EvalError!Value {
+    const object = try self.evaluate(access.object);
+    const elements = switch (object) {
+        .tuple => |elements| elements,
+        else => return self.runtimeError(access.object.span, "values like {} have no positional fields", .{object})
+    };
+
+    if (access.index >= elements.len) {
+        return self.runtimeError(node.span, "tuples of {} elements have no element {}", .{elements.len, access.index});
+    }
+
+    return elements[access.index];
+}
+
+// Slices share the elements of the array they are taken from
+fn evalIndex(self: *Interpreter, node: *const Node, index: Node.Index) EvalError!Value {
+    const object = try self.evaluate(index.object);
+    const position = try self.evaluate(index.index);
+
+    const elements = switch (object) {
+        .array => |elements| elements,
+        else => return self.runtimeError(index.object.span, "values like {} can't be indexed", .{object})
+    };
+
+    switch (position) {
+        .integer => |integer| {
+            if (integer < 0 or integer >= elements.len) {
+                return self.runtimeError(node.span, "index {} is out of bounds for an array of length {}", .{integer, elements.len});
+            }
+
+            return elements[@intCast(integer)];
+        },
+        .range => |range| {
+            const end = if (range.inclusive) range.end + 1 else range.end;
+            if (range.start < 0 or range.start > end or end > elements.len) {
+                return self.runtimeError(node.span, "range {} is out of bounds for an array of length {}", .{position, elements.len});
+            }
+
+            return .{ .array = elements[@intCast(range.start)..@intCast(end)] };
+        },
+        else => return self.runtimeError(index.index.span, "arrays can't be indexed by {}", .{position})
+    }
+}
+
+// Functions without a self parameter are accessed through the name of their struct,
+// the others through its values. The functions of the built in modules are accessed
+// through the module's name, unless a binding shadows it
+fn evalFieldAccess(self: *Interpreter, node: *const Node, access: Node.FieldAccess) EvalError!Value {
+    if (access.object.kind == .identifier) {
+        const name = access.object.kind.identifier;
+        if (try self.lookupFunction(name, access.field)) |function| return function;
+
+        if (self.env.lookup(name) == null and stdlib.isModule(name)) {
+            const function = stdlib.find(name, access.field) orelse {
+                return self.runtimeError(node.span, "{s} has no function named {s}", .{name, access.field});
+            };
+
+            return .{ .builtin = function };
+        }
+    }
+
+    const object = try self.evaluate(access.object);
+    if (isVariantOf(object, "Option")) {
+        const kind = std.meta.stringToEnum(Value.OptionMethod.Kind, access.field) orelse {
+            return self.runtimeError(node.span, "Option has no method named {s}", .{access.field});
+        };
+
+        const receiver = try self.env.valueAllocator().create(Value);
+        receiver.* = object;
+
+        return .{ .option_method = .{ .kind = kind, .receiver = receiver } };
+    }
+
+    // Fields and methods are accessed through references as through the values they refer to
+    const value = if (object == .reference) try self.load(access.object.span, object.reference) else object;
+    const structure = switch (value) {
+        .@"struct" => |structure| structure,
+        else => return self.runtimeError(access.object.span, "{} has no fields", .{object})
+    };
+
+    if (structure.get(access.field)) |field| return field;
+
+    const method = try self.lookupFunction(structure.name, access.field) orelse {
+        return self.runtimeError(node.span, "{s} has no field or method named {s}", .{structure.name, access.field});
+    };
+
+    // Methods whose self is a reference are given one to the value they're accessed through
+    const receiver = try self.env.valueAllocator().create(Value);
+    receiver.* = if (!takesReference(method.function)) value
+        else if (object == .reference) object
+        else .{ .reference = try self.evalPlace(access.object) };
+
+    return .{ .method = .{ .function = method.function, .receiver = receiver } };
+}
+
+// Whether the first parameter of function is a reference, as `&self` is
+fn takesReference(function: *const Node) bool {
+    const params = function.kind.fn_def.params;
+    if (params.len == 0) return false;
+
+    const annotation = params[0].annotation orelse return false;
+    return Ast.isReferenceType(annotation);
+}
+
+/// The function named name defined by an impl for the struct named struct_name
+fn lookupFunction(self: *Interpreter, struct_name: []const u8, name: []const u8) EvalError!?Value {
+    const qualified = try std.fmt.allocPrint(self.allocator, "{s}.{s}", .{struct_name, name});
+    defer self.allocator.free(qualified);
+
+    return self.env.lookup(qualified);
+}
+
+// Closures copy the values of the bindings they capture, so assignments to those
+// bindings after the closure is evaluated aren't seen by it
+fn evalClosure(self: *Interpreter, node: *const Node, closure: Node.Closure) EvalError!Value {
+    const allocator = self.env.valueAllocator();
+
+    var captured = std.StringHashMap(Value).init(allocator);
+    for (closure.captures) |name| {
+        const value = self.env.lookup(name) orelse return self.runtimeError(node.span, "{s} is not defined", .{name});
+        try captured.put(name, value);
+    }
+
+    const value = try allocator.create(Value.Closure);
+    value.* = .{ .node = node, .captured = captured };
+
+    return .{ .closure = value };
+}
+
+fn evalAssignment(self: *Interpreter, assignment: Node.Assignment) EvalError!Value {
+    const name = switch (assignment.lhs.kind) {
+        .identifier => |identifier| identifier,
+        .dereference => {
+            const reference = try self.evalPlace(assignment.lhs);
+            try self.store(assignment.lhs.span, reference, try self.evaluate(assignment.rhs));
+
+            return .unit;
+        },
+        else => return self.runtimeError(assignment.lhs.span, "invalid assignment target", .{})
+    };
+
+    const value = try self.evaluate(assignment.rhs);
+    if (!self.env.assign(name, value)) return self.runtimeError(assignment.lhs.span, "{s} is not defined", .{name});
+
+    return .unit;
+}
+
+// The reference to the binding, field or element node is, the reference itself for
+// dereferences. Each step is checked to exist as the path is followed
+fn evalPlace(self: *Interpreter, node: *const Node) EvalError!Value.Reference {
+    const place: struct { *const Node, Value.Reference.Step } = switch (node.kind) {
+        .identifier => |name| {
+            const scope = self.env.locate(name) orelse return self.runtimeError(node.span, "{s} is not defined", .{name});
+            return .{ .scope = scope, .name = name, .path = &.{} };
+        },
+        .dereference => |operand| {
+            const value = try self.evaluate(operand);
+            if (value != .reference) return self.runtimeError(operand.span, "{} isn't a reference", .{value});
+
+            return value.reference;
+        },
+        .field_access => |access| .{access.object, .{ .field = access.field }},
+        .tuple_index => |access| .{access.object, .{ .position = access.index }},
+        .index => |index| block: {
+            const position = try self.evaluate(index.index);
+            const element = if (position == .integer) std.math.cast(usize, position.integer) else null;
+
+            break :block .{index.object, .{ .element = element orelse {
+                return self.runtimeError(index.index.span, "only elements of arrays can be referenced, not {}", .{position});
+            }}};
+        },
+        else => return self.runtimeError(node.span, "only bindings, fields and elements can be referenced", .{})
+    };
+
+    const base = try self.evalPlace(place[0]);
+
+    const path = try self.env.valueAllocator().alloc(Value.Reference.Step, base.path.len + 1);
+    @memcpy(path[0..base.path.len], base.path);
+    path[base.path.len] = place[1];
+
+    const reference: Value.Reference = .{ .scope = base.scope, .name = base.name, .path = path };
+    _ = try self.load(node.span, reference);
+
+    return reference;
+}
+
+// References to bindings whose scope has been exited are reported when used, as
+// the checker only warns about them
+fn load(self: *Interpreter, span: Span, reference: Value.Reference) EvalError!Value {
+    var value = self.env.get(reference.scope, reference.name) orelse {
+        return self.runtimeError(span, "the reference to {s} outlived it", .{reference.name});
+    };
+    for (reference.path) |step| value = try self.follow(span, value, step);
+
+    return value;
+}
+
+fn store(self: *Interpreter, span: Span, reference: Value.Reference, value: Value) EvalError!void {
+    const bound = self.env.get(reference.scope, reference.name) orelse {
+        return self.runtimeError(span, "the reference to {s} outlived it", .{reference.name});
+    };
+
+    _ = self.env.set(reference.scope, reference.name, try self.replace(span, bound, reference.path, value));
+}
+
+fn follow(self: *Interpreter, span: Span, value: Value, step: Value.Reference.Step) EvalError!Value {
+    switch (step) {
+        .field => |name| if (value == .@"struct") {
+            if (value.@"struct".get(name)) |field| return field;
+        },
+        .position => |position| if (value == .tuple and position < value.tuple.len) return value.tuple[position],
+        .element => |element| if (value == .array and element < value.array.len) return value.array[element]
+    }
+
+    return self.runtimeError(span, "{} has no field or element at {}", .{value, step});
+}
+
+// Copies value with what path refers to within it replaced, as values share their
+// fields and elements with the values they were copied from
+fn replace(self: *Interpreter, span: Span, value: Value, path: []const Value.Reference.Step, replacement: Value) EvalError!Value {
+    if (path.len == 0) return replacement;
+
+    const inner = try self.replace(span, try self.follow(span, value, path[0]), path[1..], replacement);
+    const allocator = self.env.valueAllocator();
+
+    switch (path[0]) {
+        .field => |name| {
+            const fields = try allocator.dupe(Value.Struct.Field, value.@"struct".fields);
+            for (fields) |*field| {
+                if (std.mem.eql(u8, field.name, name)) field.value = inner;
+            }
+
+            return .{ .@"struct" = .{ .name = value.@"struct".name, .fields = fields } };
+        },
+        .position => |position| {
+            const elements = try allocator.dupe(Value, value.tuple);
+            elements[position] = inner;
+
+            return .{ .tuple = elements };
+        },
+        .element => |element| {
+            const elements = try allocator.dupe(Value, value.array);
+            elements[element] = inner;
+
+            return .{ .array = elements };
+        }
+    }
+}
+
+fn call(self: *Interpreter, span: Span, callee: Value, arguments: []const Value) EvalError!Value {
+    switch (callee) {
+        .function => |function| {
+            const fn_def = function.kind.fn_def;
+
+            // Calls of async functions give a future, the body runs once it is polled
+            if (fn_def.@"async") {
+                const future = try self.env.valueAllocator().create(Value.Future);
+                future.* = .{
+                    .function = function,
+                    .arguments = try self.env.valueAllocator().dupe(Value, arguments)
+                };
+
+                return .{ .future = future };
+            }
+
+            return try self.invoke(span, fn_def.name, fn_def.params, fn_def.body, null, arguments);
+        },
+        .closure => |closure| {
+            const definition = closure.node.kind.closure;
+            return try self.invoke(span, "closure", definition.params, definition.body, &closure.captured, arguments);
+        },
+        .method => |method| {
+            const with_receiver = try self.allocator.alloc(Value, arguments.len + 1);
+            defer self.allocator.free(with_receiver);
+
+            with_receiver[0] = method.receiver.*;
+            @memcpy(with_receiver[1..], arguments);
+
+            return try self.call(span, .{ .function = method.function }, with_receiver);
+        },
+        .option_method => |method| return try self.callOptionMethod(span, method, arguments),
+        .builtin => |function| return try self.callBuiltin(span, function, arguments),
+        else => return self.runtimeError(span, "{} is not a function", .{callee})
+    }
+}
+
+// Whether callee refers to the prelude's assert, rather than a binding shadowing it
+fn isPreludeFunction(self: *Interpreter, callee: *const Node, name: []const u8) bool {
+    if (callee.kind != .identifier or !std.mem.eql(u8, callee.kind.identifier, name)) return false;
+    return self.env.lookup(name) == null;
+}
+
+// Failing assertions end evaluation, reporting the condition's source and the
+// message if given
+fn evalAssert(self: *Interpreter, node: *const Node, arguments: []const *Node) EvalError!Value {
+    if (arguments.len == 0 or arguments.len > 2) {
+        return self.runtimeError(node.span, "assert expects a condition and optionally a message", .{});
+    }
+
+    const condition = try self.evaluate(arguments[0]);
+    if (condition != .boolean) return self.runtimeError(arguments[0].span, "assert's condition must be a bool, found {}", .{condition});
+    if (condition.boolean) return .unit;
+
+    const text = try self.unit.sourceText(arguments[0]);
+    if (arguments.len == 1) return self.runtimeError(arguments[0].span, "assertion failed: {s}", .{text});
+
+    const message = try self.evaluate(arguments[1]);
+    if (message != .string) return self.runtimeError(arguments[1].span, "assert's message must be a str, found {}", .{message});
+
+    return self.runtimeError(arguments[0].span, "assertion failed: {s}, {s}", .{text, message.string});
+}
+
+// The template is the string literal the checker counted the arguments against
+fn evalFmt(self: *Interpreter, node: *const Node, arguments: []const *Node) EvalError!Value {
+    const template = if (arguments.len > 0 and arguments[0].kind == .literal and arguments[0].kind.literal == .string)
+        arguments[0].kind.literal.string
+    else
+        return self.runtimeError(node.span, "fmt expects a template and its arguments", .{});
+
+    const values = try self.allocator.alloc(Value, arguments.len - 1);
+    defer self.allocator.free(values);
+
+    for (values, arguments[1..]) |*value, argument| value.* = try self.evaluate(argument);
+
+    const formatted = stdlib.fmt.format(self.env.valueAllocator(), template, values) catch |err| return switch (err) {
+        error.InvalidArguments => self.runtimeError(node.span, "fmt's arguments don't match its template", .{}),
+        error.UnclosedPlaceholder, error.UnknownSpecifier, error.UnmatchedBrace => |e| self.runtimeError(
+            arguments[0].span,
+            "{s}",
+            .{stdlib.fmt.describe(e)}
+        ),
+        error.OutOfMemory => error.OutOfMemory
+    };
+
+    return .{ .string = formatted };
+}
+
+// block_on is written outside of async functions, to await a future there
+fn evalBlockOn(self: *Interpreter, node: *const Node, arguments: []const *Node) EvalError!Value {
+    if (arguments.len != 1) return self.runtimeError(node.span, "block_on expects a future", .{});
+
+    const future = try self.evaluate(arguments[0]);
+    if (future != .future) return self.runtimeError(arguments[0].span, "block_on expects a future, found {}", .{future});
+
+    return try self.blockOn(node.span, future.future);
+}
+
+fn blockOn(self: *Interpreter, span: Span, future: *Value.Future) EvalError!Value {
+    var executor = Executor.init(self);
+    return try executor.blockOn(span, future);
+}
+
+/// Runs the async function future calls with the arguments it captured, giving
+/// what the function returns
+pub fn runFuture(self: *Interpreter, span: Span, future: *const Value.Future) EvalError!Value {
+    const fn_def = future.function.kind.fn_def;
+    return try self.invoke(span, fn_def.name, fn_def.params, fn_def.body, null, future.arguments);
+}
+
+fn callBuiltin(self: *Interpreter, span: Span, function: *const stdlib.Function, arguments: []const Value) EvalError!Value {
+    if (arguments.len != function.params.len) {
+        return self.runtimeError(span, "{s}.{s} expects {} argument(s), found {}", .{
+            function.module,
+            function.name,
+            function.params.len,
+            arguments.len
+        });
+    }
+
+    const context: stdlib.Context = .{ .allocator = self.env.valueAllocator(), .streams = self.env.io };
+    return stdlib.call(function, arguments, context) catch |err| switch (err) {
+        error.InvalidArguments => self.runtimeError(span, "invalid arguments to {s}.{s}", .{function.module, function.name}),
+        error.Overflow => self.runtimeError(span, "integer overflow", .{}),
+        error.IoFailed => self.runtimeError(span, "{s}.{s} failed", .{function.module, function.name}),
+        error.OutOfMemory => error.OutOfMemory
+    };
+}
+
+fn callOptionMethod(self: *Interpreter, span: Span, method: Value.OptionMethod, arguments: []const Value) EvalError!Value {
+    const expected: usize = switch (method.kind) {
+        .is_some, .is_none => 0,
+        .map, .and_then, .unwrap_or => 1
+    };
+    if (arguments.len != expected) {
+        return self.runtimeError(span, "{s} expects {} argument(s), found {}", .{
+            @tagName(method.kind),
+            expected,
+            arguments.len
+        });
+    }
+
+    // The value held by Some, null for None
+    const held: ?Value = switch (method.receiver.variant.fields) {
+        .tuple => |values| values[0],
+        else => null
+    };
+
+    return switch (method.kind) {
+        .is_some => .{ .boolean = held != null },
+        .is_none => .{ .boolean = held == null },
+        .unwrap_or => held orelse arguments[0],
+        .map => {
+            const value = held orelse return method.receiver.*;
+
+            const values = try self.env.valueAllocator().alloc(Value, 1);
+            values[0] = try self.call(span, arguments[0], &.{value});
+
+            return .{ .variant = .{
+                .@"enum" = "Option",
+                .name = "Some",
+                .fields = .{ .tuple = values }
+            }};
+        },
+        .and_then => if (held) |value| try self.call(span, arguments[0], &.{value}) else method.receiver.*
+    };
+}
+
+// Evaluates the body of a function in a new frame, binding its parameters to
+// arguments and, for closures, the names it captured to their values
+fn invoke(
+    self: *Interpreter,
+    span: Span,
+    name: []const u8,
+    params: []const Node.Parameter,
+    body: *const Node,
+    captured: ?*const std.StringHashMap(Value),
+    arguments: []const Value
+) EvalError!Value {
+    if (arguments.len != params.len) {
+        return self.runtimeError(span, "{s} expects {} argument(s), found {}", .{
+            name,
+            params.len,
+            arguments.len
+        });
+    }
+
+    if (self.depth == max_depth) return self.runtimeError(span, "calls nested deeper than {}", .{max_depth});
+    self.depth = self.depth + 1;
+    defer self.depth = self.depth - 1;
+
+    const base = try self.env.enterFrame();
+    defer self.env.exitFrame(base);
+
+    if (captured) |values| {
+        var iterator = values.iterator();
+        while (iterator.next()) |entry| try self.env.define(entry.key_ptr.*, entry.value_ptr.*);
+    }
+
+    for (params, arguments) |param, argument| try self.env.define(param.name, coerce(param.annotation, argument));
+
+    return self.evaluate(body) catch |err| switch (err) {
+        error.Return => self.returned,
+        // Loops outside of the function can't be broken out of from within it
+        error.Break, error.Continue => self.runtimeError(span, "break or continue outside of a loop", .{}),
+        else => |e| return e
+    };
+}
+
+fn evalPrefix(self: *Interpreter, node: *const Node, prefix: Node.Prefix) EvalError!Value {
+    const operand = try self.evaluate(prefix.operand);
+
+    return switch (prefix.operator) {
+        .negate => switch (operand) {
+            .integer => |integer| .{ .integer = try self.checked(node.span, @subWithOverflow(0, integer)) },
+            .float => |float| .{ .float = -float },
+            else => self.invalidOperands(node.span)
+        },
+        .not => switch (operand) {
+            .boolean => |boolean| .{ .boolean = !boolean },
+            else => self.invalidOperands(node.span)
+        },
+        .bit_not => switch (operand) {
+            .integer => |integer| .{ .integer = ~integer },
+            else => self.invalidOperands(node.span)
+        }
+    };
+}
+
+fn invalidOperands(self: *Interpreter, span: Span) EvalError {
+    return self.runtimeError(span, "invalid operand types for operator", .{});
+}
+
+// Unwraps the result of an overflowing operation, failing if it overflowed
+fn checked(self: *Interpreter, span: Span, result: anytype) EvalError!i64 {
+    if (result[1] != 0) return self.runtimeError(span, "integer overflow", .{});
+    return result[0];
+}
+
+fn evalInfix(self: *Interpreter, node: *const Node, infix: Node.Infix) EvalError!Value {
+    switch (infix.operator) {
+        // The right operand is only evaluated when it decides the result
+        .@"and", .@"or" => {
+            const lhs = try self.evalCondition(infix.lhs);
+            if (lhs == (infix.operator == .@"or")) return .{ .boolean = lhs };
+
+            return .{ .boolean = try self.evalCondition(infix.rhs) };
+        },
+        .forward_app => {
+            const callee = try self.evaluate(infix.lhs);
+            return try self.call(node.span, callee, &.{try self.evaluate(infix.rhs)});
+        },
+        .reverse_app => {
+            const argument = try self.evaluate(infix.lhs);
+            return try self.call(node.span, try self.evaluate(infix.rhs), &.{argument});
+        },
+        else => {}
+    }
+
+    const lhs = try self.evaluate(infix.lhs);
+    const rhs = try self.evaluate(infix.rhs);
+
+    switch (infix.operator) {
+        .equal => return .{ .boolean = lhs.eql(rhs) },
+        .not_equal => return .{ .boolean = !lhs.eql(rhs) },
+        .concat => {
+            if (lhs != .string or rhs != .string) return self.invalidOperands(node.span);
+            return .{ .string = try std.mem.concat(self.env.valueAllocator(), u8, &.{lhs.string, rhs.string}) };
+        },
+        .range_exc, .range_inc => {
+            if (lhs != .integer or rhs != .integer) return self.invalidOperands(node.span);
+            return .{ .range = .{
+                .start = lhs.integer,
+                .end = rhs.integer,
+                .inclusive = infix.operator == .range_inc
+            }};
+        },
+        else => {}
+    }
+
+    if (lhs == .integer and rhs == .integer) return try self.evalInteger(node.span, infix.operator, lhs.integer, rhs.integer);
+
+    // Integers mixed with floats are promoted, as the type checker coerces their literals
+    const l = toFloat(lhs) orelse return self.invalidOperands(node.span);
+    const r = toFloat(rhs) orelse return self.invalidOperands(node.span);
+
+    return switch (infix.operator) {
+        .add => .{ .float = l + r },
+        .subtract => .{ .float = l - r },
+        .multiply => .{ .float = l * r },
+        .divide => .{ .float = l / r },
+        .modulo => .{ .float = @rem(l, r) },
+        .exponent => .{ .float = std.math.pow(f64, l, r) },
+        .lesser => .{ .boolean = l < r },
+        .lesser_eq => .{ .boolean = l <= r },
+        .greater => .{ .boolean = l > r },
+        .greater_eq => .{ .boolean = l >= r },
+        else => self.invalidOperands(node.span)
+    };
+}
+
+fn toFloat(value: Value) ?f64 {
+    return switch (value) {
+        .integer => |integer| @floatFromInt(integer),
+        .float => |float| float,
+        else => null
+    };
+}
+
+// Casts a number to the numeric type called target as rust's as does, integers
+// wrap to narrower types and floats are truncated, saturating at the bounds of
+// the type and casting nan to 0
+fn castValue(value: Value, target: []const u8) Value {
+    const Numeric = enum { @"i8", @"i16", @"i32", @"i64", @"f32", @"f64" };
+    const to = std.meta.stringToEnum(Numeric, target) orelse return value;
+
+    return switch (to) {
+        .@"f32" => .{ .float = @floatCast(@as(f32, @floatCast(toFloat(value) orelse return value))) },
+        .@"f64" => .{ .float = toFloat(value) orelse return value },
+        .@"i8" => .{ .integer = castInteger(i8, value) orelse return value },
+        .@"i16" => .{ .integer = castInteger(i16, value) orelse return value },
+        .@"i32" => .{ .integer = castInteger(i32, value) orelse return value },
+        .@"i64" => .{ .integer = castInteger(i64, value) orelse return value }
+    };
+}
+
+fn castInteger(comptime T: type, value: Value) ?i64 {
+    return switch (value) {
+        .integer => |integer| @as(T, @truncate(integer)),
+        .float => |float| std.math.lossyCast(T, float),
+        else => null
+    };
+}
+
+fn evalInteger(self: *Interpreter, span: Span, operator: Node.Infix.Operator, lhs: i64, rhs: i64) EvalError!Value {
+    return switch (operator) {
+        .add => .{ .integer = try self.checked(span, @addWithOverflow(lhs, rhs)) },
+        .subtract => .{ .integer = try self.checked(span, @subWithOverflow(lhs, rhs)) },
+        .multiply => .{ .integer = try self.checked(span, @mulWithOverflow(lhs, rhs)) },
+        .divide, .modulo => {
+            if (rhs == 0) return self.runtimeError(span, "division by zero", .{});
+            if (lhs == std.math.minInt(i64) and rhs == -1) return self.runtimeError(span, "integer overflow", .{});
+
+            return .{ .integer = if (operator == .divide) @divTrunc(lhs, rhs) else @rem(lhs, rhs) };
+        },
+        .exponent => .{ .integer = try self.power(span, lhs, rhs) },
+        .bit_and => .{ .integer = lhs & rhs },
+        .bit_or => .{ .integer = lhs | rhs },
+        .bit_xor => .{ .integer = lhs ^ rhs },
+        .lshift, .rshift => {
+            if (rhs < 0 or rhs >= 64) return self.runtimeError(span, "shift amount {} is out of range", .{rhs});
+
+            const amount: u6 = @intCast(rhs);
+            return .{ .integer = if (operator == .lshift) lhs << amount else lhs >> amount };
+        },
+        .lesser => .{ .boolean = lhs < rhs },
+        .lesser_eq => .{ .boolean = lhs <= rhs },
+        .greater => .{ .boolean = lhs > rhs },
+        .greater_eq => .{ .boolean = lhs >= rhs },
+        else => self.invalidOperands(span)
+    };
+}
+
+// Negative exponents truncate towards zero, matching integer division
+fn power(self: *Interpreter, span: Span, base: i64, exponent: i64) EvalError!i64 {
+    if (exponent < 0) {
+        if (base == 0) return self.runtimeError(span, "division by zero", .{});
+        if (base == 1) return 1;
+        if (base == -1) return if (@rem(exponent, 2) == 0) 1 else -1;
+        return 0;
+    }
+
+    return std.math.powi(i64, base, exponent) catch return self.runtimeError(span, "integer overflow", .{});
+}
+
 test "interpreter modules" {
     _ = tests;
+    _ = Environment;
+    _ = Executor;
 }
 
 const tests = struct {
+    const testing = std.testing;
+    const AstBuilder = ruka.AstBuilder;
+
+    // Interprets source in a fresh environment, expecting it to evaluate to expected
+    fn expectValue(expected: Value, source: []const u8) !void {
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        const value = try unit.interpret(env);
+        if (!expected.eql(value)) {
+            std.debug.print("expected {}, found {}\n", .{expected, value});
+            return error.TestExpectedEqual;
+        }
+    }
+
+    test "bindings and arithmetic" {
+        try expectValue(.{ .integer = 7 },
+            \\let x = 2
+            \\let y = x * 3 + 1
+            \\y
+        );
+        try expectValue(.{ .float = 3.0 }, "1.5 * 2");
+        try expectValue(.{ .float = 2.0 }, "let f: f64 = 2\nf");
+        try expectValue(.{ .integer = -2 }, "7 - (9 % (4 ** 2))");
+        try expectValue(.{ .boolean = true }, "(1 < 2) and (3 >= 3)");
+        try expectValue(.unit, "let x = 1");
+    }
+
+    test "strings and ranges" {
+        try expectValue(.{ .string = "hello world" }, "\"hello\" <> \" \" <> \"world\"");
+        try expectValue(.{ .boolean = true }, "(\"a\" == \"a\") and (\"a\" != \"b\")");
+        try expectValue(.{ .range = .{ .start = 0, .end = 10, .inclusive = true } }, "0..=10");
+    }
+
+    test "breaking and continuing loops" {
+        try expectValue(.{ .integer = 5 },
+            \\var i = 0
+            \\while true {
+            \\    i = i + 1
+            \\    if i == 5 { break }
+            \\}
+            \\i
+        );
+        // Only the innermost loop is broken out of
+        try expectValue(.{ .integer = 6 },
+            \\var count = 0
+            \\for i in 0..3 {
+            \\    for j in 0..10 {
+            \\        if j == 2 { break }
+            \\        count = count + 1
+            \\    }
+            \\}
+            \\count
+        );
+        try expectValue(.{ .integer = 25 },
+            \\var total = 0
+            \\for i in 0..10 {
+            \\    if i % 2 == 0 { continue }
+            \\    total = total + i
+            \\}
+            \\total
+        );
+    }
+
+    test "match expressions" {
+        const source =
+            \\fn describe(n) {
+            \\    match n {
+            \\        0 => 0
+            \\        1 | 2 | 3 => 1
+            \\        m if m < 0 => -1
+            \\        m => m * 10
+            \\    }
+            \\}
+            \\
+        ;
+        try expectValue(.{ .integer = 0 }, source ++ "describe(0)");
+        try expectValue(.{ .integer = 1 }, source ++ "describe(2)");
+        try expectValue(.{ .integer = -1 }, source ++ "describe(-5)");
+        try expectValue(.{ .integer = 40 }, source ++ "describe(4)");
+        try expectValue(.{ .string = "yes" }, "match true { false => \"no\", true => \"yes\" }");
+    }
+
+    test "structs and methods" {
+        const source =
+            \\struct Point { x: i64, y: i64 }
+            \\impl Point {
+            \\    fn origin() -> Point { Point { x: 0, y: 0 } }
+            \\    fn sum(self: Point) -> i64 { self.x + self.y }
+            \\}
+            \\let p = Point { x: 3, y: 4 }
+            \\
+        ;
+        try expectValue(.{ .integer = 7 }, source ++ "p.sum()");
+        try expectValue(.{ .integer = 0 }, source ++ "Point.origin().x");
+        try expectValue(.{ .boolean = true }, source ++ "p == Point { y: 4, x: 3 }");
+        try expectValue(.{ .integer = 4 }, source ++ "match p { Point { x: 0 } => 0, Point { x: 3, y } => y, _ => 1 }");
+    }
+
+    test "methods taking self by value or by reference" {
+        const source =
+            \\struct Point { x: i64, y: i64 }
+            \\impl Point {
+            \\    fn translate(self, dx: i64, dy: i64) -> Point { Point { x: self.x + dx, y: self.y + dy } }
+            \\}
+            \\impl Point {
+            \\    fn sum(&self) -> i64 { self.x + self.y }
+            \\    fn twice(&self) -> i64 { self.sum() * 2 }
+            \\}
+            \\fn translate(x: i64) -> i64 { x + 100 }
+            \\var p = Point { x: 1, y: 2 }
+            \\
+        ;
+        try expectValue(.{ .integer = 4 }, source ++ "p.translate(1, 1).translate(2, 2).y - 1");
+        try expectValue(.{ .integer = 3 }, source ++ "p.sum()");
+        try expectValue(.{ .integer = 6 }, source ++ "p.twice()");
+        try expectValue(.{ .integer = 3 }, source ++ "Point.sum(&p)");
+        try expectValue(.{ .integer = 6 }, source ++ "let r = &p\nr.twice()");
+        try expectValue(.{ .integer = 101 }, source ++ "translate(1)");
+    }
+
+    test "traits and their defaults" {
+        const source =
+            \\trait Shape {
+            \\    fn area(self) -> i64
+            \\    fn doubled(&self) -> i64 { self.area() * 2 }
+            \\}
+            \\struct Square { side: i64 }
+            \\struct Rect { w: i64, h: i64 }
+            \\impl Shape for Square {
+            \\    fn area(self) -> i64 { self.side * self.side }
+            \\}
+            \\impl Shape for Rect {
+            \\    fn area(self) -> i64 { self.w * self.h }
+            \\    fn doubled(&self) -> i64 { 0 }
+            \\}
+            \\fn area_of(s: impl Shape) -> i64 { s.area() }
+            \\var sq = Square { side: 3 }
+            \\var r = Rect { w: 2, h: 5 }
+            \\
+        ;
+        try expectValue(.{ .integer = 9 }, source ++ "area_of(sq)");
+        try expectValue(.{ .integer = 10 }, source ++ "area_of(r)");
+        try expectValue(.{ .integer = 18 }, source ++ "sq.doubled()");
+        try expectValue(.{ .integer = 0 }, source ++ "r.doubled()");
+    }
+
+    test "async functions run by the executor" {
+        const source =
+            \\async fn add(a: i32, b: i32) -> i32 { a + b }
+            \\async fn sum_of(n: i32) -> i32 {
+            \\    var total = 0
+            \\    for i in 0..n { total = total + await add(i, 1) }
+            \\    total
+            \\}
+            \\
+        ;
+        try expectValue(.{ .integer = 3 }, source ++ "block_on(add(1, 2))");
+        try expectValue(.{ .integer = 6 }, source ++ "block_on(sum_of(3))");
+        try expectValue(.{ .integer = 7 }, source ++ "async fn main() -> i32 { await add(3, 4) }");
+    }
+
+    test "enums and matching on variants" {
+        const source =
+            \\enum Shape { Circle(i64), Rect(i64, i64), Empty }
+            \\enum Event { KeyPress { key: i64 } }
+            \\fn area(shape: Shape) -> i64 {
+            \\    match shape {
+            \\        Shape::Circle(r) => 3 * r * r
+            \\        Shape::Rect(w, h) => w * h
+            \\        Shape::Empty => 0
+            \\    }
+            \\}
+            \\
+        ;
+        try expectValue(.{ .integer = 12 }, source ++ "area(Shape::Circle(2))");
+        try expectValue(.{ .integer = 6 }, source ++ "area(Shape::Rect(2, 3))");
+        try expectValue(.{ .integer = 0 }, source ++ "area(Shape::Empty)");
+        try expectValue(.{ .boolean = true }, source ++ "Shape::Rect(1, 2) == Shape::Rect(1, 2)");
+        try expectValue(.{ .boolean = false }, source ++ "Shape::Circle(1) == Shape::Empty");
+        try expectValue(.{ .integer = 65 }, source ++ "match (Event::KeyPress { key: 65 }) { Event::KeyPress { key } => key }");
+    }
+
+    test "closures" {
+        // Capturing immutable bindings
+        try expectValue(.{ .integer = 5 },
+            \\fn adder(n) { fn(x) { x + n } }
+            \\let add = adder(2)
+            \\add(3)
+        );
+        try expectValue(.{ .integer = 6 },
+            \\fn outer(a) {
+            \\    let g = fn(b) { fn(c) { a + b + c } }
+            \\    g(2)(3)
+            \\}
+            \\outer(1)
+        );
+        // Mutable bindings are copied when the closure is evaluated
+        try expectValue(.{ .integer = 15 },
+            \\fn counter() {
+            \\    var n = 1
+            \\    let get = fn() { n }
+            \\    n = 5
+            \\    get() * 10 + n
+            \\}
+            \\counter()
+        );
+        // Top level bindings aren't captured, so closures see their current values
+        try expectValue(.{ .integer = 7 },
+            \\var total = 0
+            \\let bump = fn(by) { total = total + by }
+            \\bump(3)
+            \\bump(4)
+            \\total
+        );
+    }
+
+    test "functions are values" {
+        try expectValue(.{ .integer = 12 },
+            \\fn double(x) { x * 2 }
+            \\fn sum(n, f) {
+            \\    var total = 0
+            \\    for i in 0..n { total = total + f(i) }
+            \\    total
+            \\}
+            \\sum(3, double) + sum(3, fn(x) { x + 1 })
+        );
+        try expectValue(.{ .integer = 8 },
+            \\fn double(x) { x * 2 }
+            \\let f = double
+            \\f(4)
+        );
+    }
+
+    test "? unwraps ok values and returns errors" {
+        const source =
+            \\fn parse(n) { if n < 0 { Err("negative") } else { Ok(n) } }
+            \\fn double(n) { Ok(parse(n)? * 2) }
+            \\fn quad(n) { Ok(double(n)? * 2) }
+            \\
+        ;
+
+        try expectValue(.{ .integer = 12 }, source ++ "match quad(3) { Ok(v) => v, Err(_) => 0 }");
+        try expectValue(.{ .integer = -1 }, source ++ "match quad(-3) { Ok(v) => v, Err(_) => -1 }");
+        try expectValue(.{ .boolean = true }, source ++ "quad(-3) == Err(\"negative\")");
+    }
+
+    test "option values, their methods and ?" {
+        const source =
+            \\fn half(n) { if n % 2 == 0 { Some(n / 2) } else { None } }
+            \\fn eighth(n) { Some(half(half(half(n)?)?)?) }
+            \\
+        ;
+
+        try expectValue(.{ .integer = 2 }, source ++ "eighth(16).unwrap_or(0)");
+        try expectValue(.{ .integer = -1 }, source ++ "eighth(12).unwrap_or(-1)");
+        try expectValue(.{ .integer = 3 }, source ++ "half(4).map(fn(x) { x + 1 }).unwrap_or(0)");
+        try expectValue(.{ .integer = 1 }, source ++ "half(4).and_then(half).unwrap_or(0)");
+        try expectValue(.{ .boolean = true }, source ++ "half(3).is_none() and half(2).is_some()");
+        try expectValue(.{ .boolean = true }, source ++ "half(3).map(fn(x) { x + 1 }) == None");
+    }
+
+    test "functions of the built in modules" {
+        try expectValue(.{ .integer = 2 }, "math.min(3, math.abs(-2))");
+        try expectValue(.{ .float = 3.0 }, "math.floor(math.pow(2.0, 0.5) + math.sqrt(4.0))");
+        try expectValue(.{ .boolean = true }, "let f = math.max\nf(1, 2) == 2");
+    }
+
+    test "runtime errors are reported" {
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        var input = std.io.fixedBufferStream("let x = 10\nx / (x - 10)");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.EvaluationFailed, unit.interpret(env));
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("division by zero", unit.diagnostics.items[0].message);
+    }
+
+    test "failing asserts are reported" {
+        try expectValue(.unit, "assert(1 < 2)");
+
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        var input = std.io.fixedBufferStream("let x = 3\nassert(x > 5, \"x is small\")");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        // Units compiling their input alone write the condition back out, as they don't keep the source
+        try testing.expectError(error.EvaluationFailed, unit.interpret(env));
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("assertion failed: (x > 5), x is small", unit.diagnostics.items[0].message);
+    }
+
+    test "strings are formatted by fmt" {
+        try expectValue(.{ .string = "no arguments" }, "fmt(\"no arguments\")");
+        try expectValue(.{ .string = "x is 3" }, "let x = 3\nfmt(\"x is {}\", x)");
+        try expectValue(.{ .string = "ruka, true and 1.5" }, "fmt(\"{}, {} and {}\", \"ruka\", 1 < 2, 1.5)");
+        try expectValue(.{ .string = "ff is 3.14" }, "fmt(\"{:x} is {:.2}\", 255, 3.14159)");
+        try expectValue(.{ .string = "{7}" }, "fmt!(\"{{{}}}\", 7)");
+    }
+
+    test "arrays, indexes and slices" {
+        const elements = [_]Value{.{ .integer = 1 }, .{ .integer = 2 }, .{ .integer = 3 }};
+
+        try expectValue(.{ .array = &elements }, "[1, 2, 3]");
+        try expectValue(.{ .integer = 5 }, "let a = [1, 2, 3]\na[1] + a[2]");
+        try expectValue(.{ .array = elements[1..] }, "let a = [1, 2, 3]\na[1..3]");
+        try expectValue(.{ .integer = 2 }, "let a = [1, 2, 3]\na[0..=1][1]");
+
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        var input = std.io.fixedBufferStream("let a = [1, 2, 3]\na[3]");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.EvaluationFailed, unit.interpret(env));
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("index 3 is out of bounds for an array of length 3", unit.diagnostics.items[0].message);
+    }
+
+    test "tuples and their elements" {
+        const pair = [_]Value{.{ .integer = 1 }, .{ .string = "x" }};
+        const single = [_]Value{.{ .integer = 42 }};
+
+        try expectValue(.{ .tuple = &pair }, "(1, \"x\")");
+        try expectValue(.{ .tuple = &single }, "(42,)");
+        try expectValue(.{ .integer = 42 }, "(42)");
+        try expectValue(.unit, "()");
+        try expectValue(.{ .boolean = true }, "let t = (1, (\"a\", true))\nt.1.1");
+        try expectValue(.{ .integer = 3 }, "let t = (1, (2, 0))\nmatch t { (a, (b, _)) => a + b }");
+    }
+
+    test "casts wrap integers and truncate floats" {
+        try expectValue(.{ .integer = 44 }, "let big: i64 = 300\nbig as i8");
+        try expectValue(.{ .integer = -56 }, "200 as i8");
+        try expectValue(.{ .integer = -1 }, "65535 as i16 as i64");
+        try expectValue(.{ .integer = 2 }, "2.75 as i64");
+        try expectValue(.{ .integer = -3 }, "-3.5 as i32");
+        try expectValue(.{ .integer = 127 }, "1000.0 as i8");
+        try expectValue(.{ .integer = 0 }, "(0.0 / 0.0) as i64");
+        try expectValue(.{ .float = 7.0 }, "7 as f64");
+        try expectValue(.{ .float = 16777216.0 }, "16777217 as f32 as f64");
+    }
+
+    test "defers run in reverse as their block is left" {
+        // Each file closed appends its number to closed, the loop's file is closed
+        // at the end of each iteration and before the function's as it returns
+        try expectValue(.{ .integer = 12231 },
+            \\var closed = 0
+            \\fn close(file: i64) { closed = closed * 10 + file }
+            \\fn find(target: i64) -> i64 {
+            \\    defer close(1)
+            \\    defer close(3)
+            \\    for i in 0..10 {
+            \\        defer close(2)
+            \\        if i == target { return i }
+            \\    }
+            \\    0
+            \\}
+            \\let found = find(1)
+            \\found * 10000 + closed
+        );
+        try expectValue(.{ .integer = 3 },
+            \\var n = 0
+            \\fn f() -> i64 {
+            \\    defer n = n + 1
+            \\    n
+            \\}
+            \\f() + f() + n
+        );
+    }
+
+    test "assigning through references changes what they refer to" {
+        try expectValue(.{ .integer = 3 },
+            \\fn bump(r: &i64) { *r = *r + 1 }
+            \\var n = 1
+            \\bump(&n)
+            \\bump(&n)
+            \\n
+        );
+        try expectValue(.{ .integer = 810 },
+            \\struct Point { x: i64, y: i64 }
+            \\fn double(r: &i64) { *r = *r * 2 }
+            \\var p = Point { x: 3, y: 4 }
+            \\var pairs = [(1, 2), (5, 6)]
+            \\double(&p.y)
+            \\double(&pairs[1].0)
+            \\let r = &p
+            \\(*r).y * 100 + pairs[1].0
+        );
+    }
+
+    test "destructuring bindings" {
+        try expectValue(.{ .integer = 3 }, "let (a, (b, _)) = (1, (2, 0))\na + b");
+        try expectValue(.{ .integer = 7 },
+            \\struct Point { x: i64, y: i64 }
+            \\let Point { x: px, y: py } = Point { x: 3, y: 4 }
+            \\px + py
+        );
+    }
+
+    test "functions, recursion and loops" {
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        var input = std.io.fixedBufferStream("");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var program = try Ast.init(testing.allocator, "test source");
+        defer program.deinit();
+
+        const b = AstBuilder.init(program);
+
+        // fn fact(n) { if n <= 1 { return 1 }; n * fact(n - 1) }
+        const fact = try b.function("fact", &.{.{"n", null}}, null, try b.block(&.{
+            try b.conditional(
+                try b.infix(.lesser_eq, try b.identifier("n"), try b.integer(1)),
+                try b.block(&.{try b.node(.{ .@"return" = try b.integer(1) })}),
+                null
+            ),
+            try b.infix(.multiply, try b.identifier("n"),
+                try b.call("fact", &.{try b.infix(.subtract, try b.identifier("n"), try b.integer(1))}))
+        }));
+
+        // fn main() { var total = 0; for i in 1..=4 { total = total + fact(i) }; total }
+        const main = try b.function("main", &.{}, null, try b.block(&.{
+            try b.binding("total", true, null, try b.integer(0)),
+            try b.node(.{ .@"for" = .{
+                .variable = "i",
+                .iterable = try b.infix(.range_inc, try b.integer(1), try b.integer(4)),
+                .body = try b.block(&.{
+                    try b.assignment("total", try b.infix(.add, try b.identifier("total"), try b.call("fact", &.{try b.identifier("i")})))
+                })
+            }}),
+            try b.identifier("total")
+        }));
+
+        try b.module(&.{main, fact});
+
+        var interpreter = try Interpreter.init(unit, env);
+        defer interpreter.deinit();
+
+        try testing.expectEqual(Value{ .integer = 33 }, try interpreter.eval(program.root));
+        try testing.expectEqual(Value{ .integer = 3_628_800 }, try interpreter.eval(try b.call("fact", &.{try b.integer(10)})));
+
+        // Locals of a call are gone once it returns
+        try testing.expectEqual(null, env.lookup("total"));
+        try testing.expectEqual(1, env.scopes.items.len);
+    }
 
+    test "formatting values" {
+        try testing.expectFmt("3.0 2.5 \"hi\" 'c' () 0..=3", "{} {} {} {} {} {}", .{
+            Value{ .float = 3.0 },
+            Value{ .float = 2.5 },
+            Value{ .string = "hi" },
+            Value{ .character = 'c' },
+            Value.unit,
+            Value{ .range = .{ .start = 0, .end = 3, .inclusive = true } }
+        });
+        try testing.expectFmt("Point { x: 1, y: 2.0 } Empty {}", "{} {}", .{
+            Value{ .@"struct" = .{ .name = "Point", .fields = &.{
+                .{ .name = "x", .value = .{ .integer = 1 } },
+                .{ .name = "y", .value = .{ .float = 2.0 } }
+            }}},
+            Value{ .@"struct" = .{ .name = "Empty", .fields = &.{} } }
+        });
+        try testing.expectFmt("Shape::Rect(1, 2) Color::Red Event::KeyPress { key: 'a' }", "{} {} {}", .{
+            Value{ .variant = .{ .@"enum" = "Shape", .name = "Rect", .fields = .{ .tuple = &.{
+                .{ .integer = 1 },
+                .{ .integer = 2 }
+            }}}},
+            Value{ .variant = .{ .@"enum" = "Color", .name = "Red", .fields = .unit } },
+            Value{ .variant = .{ .@"enum" = "Event", .name = "KeyPress", .fields = .{ .@"struct" = &.{
+                .{ .name = "key", .value = .{ .character = 'a' } }
+            }}}}
+        });
+    }
 };
@@ -0,0 +1,728 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! A language server, speaking json-rpc over the standard streams, which checks
+//! the documents an editor opens and publishes their diagnostics as they change
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const AnyReader = std.io.AnyReader;
+const AnyWriter = std.io.AnyWriter;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+const Value = std.json.Value;
+
+const ruka = @import("prelude.zig");
+const Compiler = ruka.Compiler;
+const Diagnostic = ruka.Diagnostic;
+const Node = ruka.Node;
+const Span = ruka.Span;
+const Token = ruka.Token;
+const Transport = ruka.Transport;
+const Unit = ruka.Unit;
+const constants = ruka.constants;
+
+/// The open documents keyed by their uris, which are owned by the server
+documents: StringHashMap(Document),
+
+input: *Transport,
+output: *Transport,
+
+status: enum {running, exiting},
+/// Set by the shutdown request, after which only exit is expected
+shut_down: bool,
+
+allocator: Allocator,
+
+const LanguageServer = @This();
+
+const log = std.log.scoped(.lsp);
+
+/// An open document, its text and the result of its last successful check
+const Document = struct {
+    /// The file the document's uri names, which the files it imports are found relative to
+    path: []const u8,
+    text: []const u8,
+    /// Kept while the document has errors, so requests about its nodes can still
+    /// be answered as it is edited
+    checked: ?Checked = null,
+
+    const Checked = struct {
+        /// Owns the text checked, and the files it imports, which spans refer to
+        compiler: *Compiler,
+        result: Unit.Result,
+
+        fn text(self: Checked) []const u8 {
+            return self.compiler.sources.items[0].contents;
+        }
+
+        fn deinit(self: Checked) void {
+            self.result.deinit();
+            self.compiler.deinit();
+        }
+    };
+
+    fn deinit(self: Document, allocator: Allocator) void {
+        allocator.free(self.path);
+        allocator.free(self.text);
+        if (self.checked) |checked| checked.deinit();
+    }
+};
+
+/// The node a request's position is on
+const Located = struct {
+    checked: Document.Checked,
+    node: *const Node
+};
+
+// The error codes defined by json-rpc and the protocol
+const ErrorCode = enum(i32) {
+    parse_error = -32700,
+    invalid_request = -32600,
+    method_not_found = -32601,
+    invalid_params = -32602
+};
+
+/// Creates a server reading messages from reader and writing to writer
+pub fn init(allocator: Allocator, reader: AnyReader, writer: AnyWriter) !*LanguageServer {
+    const input = try Transport.init(allocator, reader, null);
+    errdefer input.deinit();
+
+    const output = try Transport.init(allocator, null, writer);
+    errdefer output.deinit();
+
+    return try create(allocator, input, output);
+}
+
+/// Creates a server over stdin and stdout
+pub fn initStdio(allocator: Allocator) !*LanguageServer {
+    const input = try Transport.initWithFile(allocator, std.io.getStdIn());
+    errdefer input.deinit();
+
+    const output = try Transport.initWithFile(allocator, std.io.getStdOut());
+    errdefer output.deinit();
+
+    return try create(allocator, input, output);
+}
+
+fn create(allocator: Allocator, input: *Transport, output: *Transport) !*LanguageServer {
+    const server = try allocator.create(LanguageServer);
+    server.* = .{
+        .documents = .init(allocator),
+        .input = input,
+        .output = output,
+        .status = .running,
+        .shut_down = false,
+        .allocator = allocator
+    };
+
+    return server;
+}
+
+pub fn deinit(self: *LanguageServer) void {
+    var documents = self.documents.iterator();
+    while (documents.next()) |entry| {
+        self.allocator.free(entry.key_ptr.*);
+        entry.value_ptr.deinit(self.allocator);
+    }
+
+    self.documents.deinit();
+    self.input.deinit();
+    self.output.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Handles messages until the input ends or the client sends exit. Returns
+/// whether the client shut the server down first, as the protocol expects
+pub fn run(self: *LanguageServer) !bool {
+    while (self.status != .exiting) {
+        const body = try self.readMessage() orelse break;
+        defer self.allocator.free(body);
+
+        try self.handleMessage(body);
+    }
+
+    return self.shut_down;
+}
+
+// Reads the body of the next message, framed by a content length header. The
+// caller owns the returned memory, which is null at the end of the input
+fn readMessage(self: *LanguageServer) !?[]u8 {
+    var length: ?usize = null;
+
+    while (true) {
+        const line = try self.input.readLine(self.allocator) orelse return null;
+        defer self.allocator.free(line);
+
+        const header = std.mem.trimRight(u8, line, "\r");
+        if (header.len == 0) break;
+
+        const separator = std.mem.indexOfScalar(u8, header, ':') orelse return error.InvalidHeader;
+        if (std.ascii.eqlIgnoreCase(header[0..separator], "content-length")) {
+            length = try std.fmt.parseInt(usize, std.mem.trim(u8, header[separator + 1..], " "), 10);
+        }
+    }
+
+    const body = try self.allocator.alloc(u8, length orelse return error.MissingContentLength);
+    errdefer self.allocator.free(body);
+
+    try self.input.readNoEof(body);
+    return body;
+}
+
+fn writeMessage(self: *LanguageServer, body: []const u8) !void {
+    try self.output.print("Content-Length: {}\r\n\r\n{s}", .{body.len, body});
+}
+
+/// Handles a single message, requests are those with an id and are answered
+pub fn handleMessage(self: *LanguageServer, body: []const u8) !void {
+    const parsed = std.json.parseFromSlice(Value, self.allocator, body, .{}) catch |err| switch (err) {
+        error.OutOfMemory => return error.OutOfMemory,
+        else => return try self.respondError(null, .parse_error, "the message isn't valid json")
+    };
+    defer parsed.deinit();
+
+    const id = field(parsed.value, "id");
+    const method = string(field(parsed.value, "method")) orelse {
+        return try self.respondError(id, .invalid_request, "the message has no method");
+    };
+    const params = field(parsed.value, "params");
+
+    log.debug("received {s}", .{method});
+
+    if (id) |request| {
+        try self.handleRequest(request, method, params);
+    } else {
+        try self.handleNotification(method, params);
+    }
+}
+
+fn handleRequest(self: *LanguageServer, id: Value, method: []const u8, params: ?Value) !void {
+    if (self.shut_down) {
+        return try self.respondError(id, .invalid_request, "the server is shutting down");
+    }
+
+    if (std.mem.eql(u8, method, "initialize")) {
+        // Documents are synchronized by sending their full text on each change
+        return try self.respond(id, std.fmt.comptimePrint(
+            \\{{"capabilities":{{"textDocumentSync":1,"hoverProvider":true,"definitionProvider":true}},"serverInfo":{{"name":"ruka","version":"{s}"}}}}
+        , .{constants.version_str}));
+    }
+
+    if (std.mem.eql(u8, method, "textDocument/hover")) return try self.hover(id, params);
+    if (std.mem.eql(u8, method, "textDocument/definition")) return try self.definition(id, params);
+
+    if (std.mem.eql(u8, method, "shutdown")) {
+        self.shut_down = true;
+        return try self.respond(id, "null");
+    }
+
+    try self.respondError(id, .method_not_found, "the method isn't supported");
+}
+
+fn handleNotification(self: *LanguageServer, method: []const u8, params: ?Value) !void {
+    if (std.mem.eql(u8, method, "exit")) {
+        self.status = .exiting;
+        return;
+    }
+
+    const document = field(params, "textDocument");
+    const uri = string(field(document, "uri")) orelse return;
+
+    if (std.mem.eql(u8, method, "textDocument/didOpen")) {
+        const text = string(field(document, "text")) orelse return;
+        try self.update(uri, text);
+    } else if (std.mem.eql(u8, method, "textDocument/didChange")) {
+        // With full synchronization the last change holds the whole text
+        const changes = field(params, "contentChanges") orelse return;
+        if (changes != .array or changes.array.items.len == 0) return;
+
+        const text = string(field(changes.array.getLast(), "text")) orelse return;
+        try self.update(uri, text);
+    } else if (std.mem.eql(u8, method, "textDocument/didClose")) {
+        if (self.documents.fetchRemove(uri)) |entry| {
+            self.allocator.free(entry.key);
+            entry.value.deinit(self.allocator);
+        }
+
+        // Clears the diagnostics shown for the document
+        try self.publishDiagnostics(uri, &.{}, "");
+    }
+}
+
+// Replaces the text of the document at uri, then checks it
+fn update(self: *LanguageServer, uri: []const u8, text: []const u8) !void {
+    const entry = try self.documents.getOrPut(uri);
+    if (!entry.found_existing) {
+        errdefer self.documents.removeByPtr(entry.key_ptr);
+
+        entry.key_ptr.* = try self.allocator.dupe(u8, uri);
+        errdefer self.allocator.free(entry.key_ptr.*);
+
+        entry.value_ptr.* = .{ .path = try pathOf(self.allocator, uri), .text = "" };
+    }
+
+    const owned = try self.allocator.dupe(u8, text);
+    self.allocator.free(entry.value_ptr.text);
+    entry.value_ptr.text = owned;
+
+    try self.check(entry.key_ptr.*, entry.value_ptr);
+}
+
+// Checks the document along with the files it imports, keeping the result if it succeeds
+fn check(self: *LanguageServer, uri: []const u8, document: *Document) !void {
+    const compiler = try Compiler.initSource(self.allocator, .{
+        .path = document.path,
+        .contents = document.text
+    }, .{ .check_only = true, .color = .never });
+    var kept = false;
+    defer if (!kept) compiler.deinit();
+
+    const result = compiler.compile() catch |err| block: {
+        // Failures without errors to show aren't caused by the document
+        if (!hasErrors(compiler.diagnostics.items)) return err;
+        break :block null;
+    };
+    errdefer if (result) |checked| checked.deinit();
+
+    // Only the diagnostics of the document itself are published, not those of its imports
+    var diagnostics = ArrayList(Diagnostic).init(self.allocator);
+    defer diagnostics.deinit();
+
+    for (compiler.diagnostics.items) |diagnostic| {
+        if (std.mem.eql(u8, diagnostic.span.file, document.path)) try diagnostics.append(diagnostic);
+    }
+
+    try self.publishDiagnostics(uri, diagnostics.items, document.text);
+
+    if (result) |checked| {
+        if (document.checked) |previous| previous.deinit();
+
+        document.checked = .{ .compiler = compiler, .result = checked };
+        kept = true;
+    }
+}
+
+fn hasErrors(diagnostics: []const Diagnostic) bool {
+    for (diagnostics) |diagnostic| if (diagnostic.severity == .@"error") return true;
+    return false;
+}
+
+// Sends the diagnostics of the document at uri, whose spans refer to text
+fn publishDiagnostics(self: *LanguageServer, uri: []const u8, diagnostics: []const Diagnostic, text: []const u8) !void {
+    var body = ArrayList(u8).init(self.allocator);
+    defer body.deinit();
+
+    const writer = body.writer();
+    try writer.writeAll("{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{\"uri\":");
+    try std.json.encodeJsonString(uri, .{}, writer);
+    try writer.writeAll(",\"diagnostics\":[");
+
+    for (diagnostics, 0..) |diagnostic, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writeDiagnostic(writer, diagnostic, text);
+    }
+
+    try writer.writeAll("]}}");
+    try self.writeMessage(body.items);
+}
+
+// Finds the innermost node at the position params names, within the document's
+// last successful check. Null if there is none, or the position is on whitespace
+// or a keyword
+fn locate(self: *LanguageServer, params: ?Value) error{InvalidParams}!?Located {
+    const uri = string(field(field(params, "textDocument"), "uri"));
+    const position = field(params, "position");
+    const line = integer(field(position, "line"));
+    const character = integer(field(position, "character"));
+
+    if (uri == null or line == null or character == null) return error.InvalidParams;
+
+    const document = self.documents.get(uri.?) orelse return null;
+    const checked = document.checked orelse return null;
+
+    const text = checked.text();
+    const offset = offsetOf(text, line.?, character.?) orelse return null;
+    if (!describable(text, offset)) return null;
+
+    return .{
+        .checked = checked,
+        .node = checked.result.ast.nodeAt(document.path, offset) orelse return null
+    };
+}
+
+// Answers with the type of the node at the position
+fn hover(self: *LanguageServer, id: Value, params: ?Value) !void {
+    const located = (self.locate(params) catch {
+        return try self.respondError(id, .invalid_params, "expected a document and a position");
+    }) orelse return try self.respond(id, "null");
+
+    const @"type" = located.checked.result.types.get(located.node.id) orelse return try self.respond(id, "null");
+
+    var result = ArrayList(u8).init(self.allocator);
+    defer result.deinit();
+
+    const writer = result.writer();
+    const contents = try std.fmt.allocPrint(self.allocator, "```ruka\n{}\n```", .{@"type"});
+    defer self.allocator.free(contents);
+
+    try writer.writeAll("{\"contents\":{\"kind\":\"markdown\",\"value\":");
+    try std.json.encodeJsonString(contents, .{}, writer);
+    try writer.writeAll("},\"range\":");
+    try writeRange(writer, located.node.span, located.checked.text());
+    try writer.writeByte('}');
+
+    try self.respond(id, result.items);
+}
+
+// Answers with the location of the definition the identifier at the position
+// refers to, which may be in a file the document imports. Names defined by the
+// prelude have no location
+fn definition(self: *LanguageServer, id: Value, params: ?Value) !void {
+    const located = (self.locate(params) catch {
+        return try self.respondError(id, .invalid_params, "expected a document and a position");
+    }) orelse return try self.respond(id, "null");
+
+    const span = located.checked.result.types.getDefinition(located.node.id) orelse return try self.respond(id, "null");
+    const text = located.checked.compiler.findSource(span.file) orelse return try self.respond(id, "null");
+
+    var result = ArrayList(u8).init(self.allocator);
+    defer result.deinit();
+
+    const writer = result.writer();
+    try writer.writeAll("{\"uri\":");
+    try writeUri(writer, span.file);
+    try writer.writeAll(",\"range\":");
+    try writeRange(writer, span, text);
+    try writer.writeByte('}');
+
+    try self.respond(id, result.items);
+}
+
+const file_scheme = "file://";
+
+// The path of the file uri names, or uri itself if it doesn't name a file. The
+// caller owns the returned memory
+fn pathOf(allocator: Allocator, uri: []const u8) ![]u8 {
+    if (!std.mem.startsWith(u8, uri, file_scheme)) return try allocator.dupe(u8, uri);
+
+    var path = ArrayList(u8).init(allocator);
+    errdefer path.deinit();
+
+    // Percent encoded bytes are decoded, `%20` is a space
+    var i: usize = file_scheme.len;
+    while (i < uri.len) {
+        if (uri[i] == '%' and i + 3 <= uri.len) {
+            if (std.fmt.parseInt(u8, uri[i + 1..i + 3], 16)) |byte| {
+                try path.append(byte);
+                i = i + 3;
+                continue;
+            } else |_| {}
+        }
+
+        try path.append(uri[i]);
+        i = i + 1;
+    }
+
+    return try path.toOwnedSlice();
+}
+
+/// Writes the uri of the file at path as a json string, the reverse of pathOf.
+/// Relative paths are written as they are
+pub fn writeUri(writer: anytype, path: []const u8) !void {
+    if (!std.fs.path.isAbsolute(path)) return try std.json.encodeJsonString(path, .{}, writer);
+
+    try writer.writeAll("\"" ++ file_scheme);
+    for (path) |byte| switch (byte) {
+        'a'...'z', 'A'...'Z', '0'...'9', '/', '-', '_', '.', '~' => try writer.writeByte(byte),
+        else => try writer.print("%{X:0>2}", .{byte})
+    };
+    try writer.writeByte('"');
+}
+
+// The offset of the zero based line and character in text, null if text has no
+// such position
+fn offsetOf(text: []const u8, line: usize, character: usize) ?usize {
+    var start: usize = 0;
+    for (0..line) |_| {
+        const newline = std.mem.indexOfScalarPos(u8, text, start, '\n') orelse return null;
+        start = newline + 1;
+    }
+
+    const end = std.mem.indexOfScalarPos(u8, text, start, '\n') orelse text.len;
+    if (start + character >= end) return null;
+
+    return start + character;
+}
+
+// Whether the byte at offset is worth describing, rather than whitespace or part
+// of a keyword
+fn describable(text: []const u8, offset: usize) bool {
+    if (std.ascii.isWhitespace(text[offset])) return false;
+
+    var start = offset;
+    while (start > 0 and ruka.isAlphanumerical(text[start - 1])) start = start - 1;
+
+    var end = offset;
+    while (end < text.len and ruka.isAlphanumerical(text[end])) end = end + 1;
+
+    return Token.Kind.tryKeyword(text[start..end]) == null;
+}
+
+fn writeDiagnostic(writer: anytype, diagnostic: Diagnostic, text: []const u8) !void {
+    try writer.writeAll("{\"range\":");
+    try writeRange(writer, diagnostic.span, text);
+
+    const severity: u8 = switch (diagnostic.severity) {
+        .@"error" => 1,
+        .warning => 2,
+        .note => 3,
+        .help => 4
+    };
+
+    try writer.print(",\"severity\":{},\"source\":\"ruka\",\"message\":", .{severity});
+    try std.json.encodeJsonString(diagnostic.message, .{}, writer);
+    try writer.writeByte('}');
+}
+
+// Lines and characters are zero based in the protocol, but one based in spans
+fn writeRange(writer: anytype, span: Span, text: []const u8) !void {
+    const start = span.pos;
+    const end = span.endPos(text);
+
+    try writer.print("{{\"start\":{{\"line\":{},\"character\":{}}},\"end\":{{\"line\":{},\"character\":{}}}}}", .{
+        start.line -| 1,
+        start.col -| 1,
+        end.line -| 1,
+        end.col -| 1
+    });
+}
+
+fn respond(self: *LanguageServer, id: Value, result: []const u8) !void {
+    var body = ArrayList(u8).init(self.allocator);
+    defer body.deinit();
+
+    const writer = body.writer();
+    try writer.writeAll("{\"jsonrpc\":\"2.0\",\"id\":");
+    try std.json.stringify(id, .{}, writer);
+    try writer.print(",\"result\":{s}}}", .{result});
+
+    try self.writeMessage(body.items);
+}
+
+fn respondError(self: *LanguageServer, id: ?Value, code: ErrorCode, message: []const u8) !void {
+    var body = ArrayList(u8).init(self.allocator);
+    defer body.deinit();
+
+    const writer = body.writer();
+    try writer.writeAll("{\"jsonrpc\":\"2.0\",\"id\":");
+    try std.json.stringify(id orelse .null, .{}, writer);
+    try writer.print(",\"error\":{{\"code\":{},\"message\":", .{@intFromEnum(code)});
+    try std.json.encodeJsonString(message, .{}, writer);
+    try writer.writeAll("}}");
+
+    try self.writeMessage(body.items);
+}
+
+// The member name of value, null if value isn't an object or has no such member
+fn field(value: ?Value, name: []const u8) ?Value {
+    const object = value orelse return null;
+    if (object != .object) return null;
+
+    return object.object.get(name);
+}
+
+fn string(value: ?Value) ?[]const u8 {
+    const str = value orelse return null;
+    if (str != .string) return null;
+
+    return str.string;
+}
+
+fn integer(value: ?Value) ?usize {
+    const int = value orelse return null;
+    if (int != .integer or int.integer < 0) return null;
+
+    return @intCast(int.integer);
+}
+
+test "language server modules" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const expectContains = ruka.testing.expectContains;
+    const File = std.fs.File;
+
+    // Drives a server as a client would, by framing messages and writing them to
+    // a pipe the server reads, returning everything written to the other pipe
+    fn session(messages: []const []const u8) ![]u8 {
+        const requests = try std.posix.pipe();
+        const responses = try std.posix.pipe();
+
+        const client_input: File = .{ .handle = responses[0] };
+        defer client_input.close();
+
+        {
+            const client_output: File = .{ .handle = requests[1] };
+            defer client_output.close();
+
+            for (messages) |message| {
+                try client_output.writer().print("Content-Length: {}\r\n\r\n{s}", .{message.len, message});
+            }
+        }
+
+        {
+            const server_input: File = .{ .handle = requests[0] };
+            defer server_input.close();
+            const server_output: File = .{ .handle = responses[1] };
+            defer server_output.close();
+
+            var server = try LanguageServer.init(testing.allocator, server_input.reader().any(), server_output.writer().any());
+            defer server.deinit();
+
+            try testing.expect(try server.run());
+        }
+
+        return try client_input.readToEndAlloc(testing.allocator, std.math.maxInt(usize));
+    }
+
+    test "diagnostics are published as documents change" {
+        const output = try session(&.{
+            \\{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"capabilities":{}}}
+            ,
+            \\{"jsonrpc":"2.0","method":"initialized","params":{}}
+            ,
+            \\{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///main.ruka","languageId":"ruka","version":1,"text":"fn main() {\n    y\n}\n"}}}
+            ,
+            \\{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///main.ruka","version":2},"contentChanges":[{"text":"fn main() {}\n"}]}}
+            ,
+            \\{"jsonrpc":"2.0","id":2,"method":"shutdown"}
+            ,
+            \\{"jsonrpc":"2.0","method":"exit"}
+        });
+        defer testing.allocator.free(output);
+
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"capabilities\":{\"textDocumentSync\":1,\"hoverProvider\":true,\"definitionProvider\":true}");
+        try expectContains(output,
+            \\"uri":"file:///main.ruka","diagnostics":[{"range":{"start":{"line":1,"character":4},"end":{"line":1,"character":5}},"severity":1,"source":"ruka","message":"undefined name"}
+        );
+        try expectContains(output, "\"uri\":\"file:///main.ruka\",\"diagnostics\":[]");
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":null}");
+    }
+
+    test "hovering shows the types of nodes from the last successful check" {
+        const output = try session(&.{
+            \\{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///add.ruka","text":"fn add(x: i32, y: i32) -> i32 { x + y }\nfn main() {\n    let sum = add(1, 2)\n}\n"}}}
+            ,
+            \\{"jsonrpc":"2.0","id":1,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///add.ruka"},"position":{"line":2,"character":15}}}
+            ,
+            \\{"jsonrpc":"2.0","id":2,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///add.ruka"},"position":{"line":2,"character":5}}}
+            ,
+            \\{"jsonrpc":"2.0","id":3,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///add.ruka"},"position":{"line":2,"character":2}}}
+            ,
+            \\{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///add.ruka"},"contentChanges":[{"text":"fn add(x: i32, y: i32) -> i32 {"}]}}
+            ,
+            \\{"jsonrpc":"2.0","id":4,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///add.ruka"},"position":{"line":2,"character":14}}}
+            ,
+            \\{"jsonrpc":"2.0","id":5,"method":"shutdown"}
+            ,
+            \\{"jsonrpc":"2.0","method":"exit"}
+        });
+        defer testing.allocator.free(output);
+
+        const callee =
+            \\"result":{"contents":{"kind":"markdown","value":"```ruka\nfn(i32, i32) -> i32\n```"},"range":{"start":{"line":2,"character":14},"end":{"line":2,"character":17}}}
+        ;
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":1," ++ callee);
+        // Over a keyword and whitespace
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":null}");
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":3,\"result\":null}");
+        // The document no longer parses, so the previous check is used
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":4," ++ callee);
+    }
+
+    test "definitions are found in the files a document imports" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.writeFile(.{ .sub_path = "math.ruka", .data = "pub fn add(x: i32, y: i32) -> i32 { x + y }\n" });
+
+        const root = try tmp.dir.realpathAlloc(testing.allocator, ".");
+        defer testing.allocator.free(root);
+
+        const open = try std.fmt.allocPrint(testing.allocator,
+            \\{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"file://{s}/main.ruka","text":"import math\nfn main() {{\n    let sum = add(1, 2)\n    io.println(\"\")\n}}\n"}}}}}}
+        , .{root});
+        defer testing.allocator.free(open);
+
+        // On the call of add
+        const call = try std.fmt.allocPrint(testing.allocator,
+            \\{{"jsonrpc":"2.0","id":1,"method":"textDocument/definition","params":{{"textDocument":{{"uri":"file://{s}/main.ruka"}},"position":{{"line":2,"character":15}}}}}}
+        , .{root});
+        defer testing.allocator.free(call);
+
+        // On the built in println
+        const builtin = try std.fmt.allocPrint(testing.allocator,
+            \\{{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{{"textDocument":{{"uri":"file://{s}/main.ruka"}},"position":{{"line":3,"character":8}}}}}}
+        , .{root});
+        defer testing.allocator.free(builtin);
+
+        const output = try session(&.{
+            open,
+            call,
+            builtin,
+            \\{"jsonrpc":"2.0","id":3,"method":"shutdown"}
+            ,
+            \\{"jsonrpc":"2.0","method":"exit"}
+        });
+        defer testing.allocator.free(output);
+
+        const location = try std.fmt.allocPrint(testing.allocator,
+            \\{{"jsonrpc":"2.0","id":1,"result":{{"uri":"file://{s}/math.ruka","range":{{"start":{{"line":0,
+        , .{root});
+        defer testing.allocator.free(location);
+
+        try expectContains(output, location);
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":null}");
+    }
+
+    test "uris name files" {
+        const path = try pathOf(testing.allocator, "file:///home/user/my%20project/main.ruka");
+        defer testing.allocator.free(path);
+        try testing.expectEqualStrings("/home/user/my project/main.ruka", path);
+
+        const untitled = try pathOf(testing.allocator, "untitled:Untitled-1");
+        defer testing.allocator.free(untitled);
+        try testing.expectEqualStrings("untitled:Untitled-1", untitled);
+
+        var uri = ArrayList(u8).init(testing.allocator);
+        defer uri.deinit();
+        try writeUri(uri.writer(), path);
+        try testing.expectEqualStrings("\"file:///home/user/my%20project/main.ruka\"", uri.items);
+    }
+
+    test "messages are framed by their content length" {
+        const output = try session(&.{
+            \\{"jsonrpc":"2.0","id":"a","method":"hover"}
+            ,
+            \\not json
+            ,
+            \\{"jsonrpc":"2.0","id":3,"method":"shutdown"}
+            ,
+            \\{"jsonrpc":"2.0","id":4,"method":"initialize"}
+            ,
+            \\{"jsonrpc":"2.0","method":"exit"}
+        });
+        defer testing.allocator.free(output);
+
+        const method_not_found =
+            \\{"jsonrpc":"2.0","id":"a","error":{"code":-32601,"message":"the method isn't supported"}}
+        ;
+        try testing.expect(std.mem.startsWith(u8, output, std.fmt.comptimePrint("Content-Length: {}\r\n\r\n", .{method_not_found.len}) ++ method_not_found));
+
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":null,\"error\":{\"code\":-32700,");
+        try expectContains(output, "{\"jsonrpc\":\"2.0\",\"id\":4,\"error\":{\"code\":-32600,\"message\":\"the server is shutting down\"}}");
+    }
+};
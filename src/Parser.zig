@@ -6,25 +6,68 @@ const Allocator = std.mem.Allocator;
 const ArrayList = std.ArrayList;
 
 const ruka = @import("prelude.zig");
-const Scanner = ruka.Scanner;
+const Diagnostic = ruka.Diagnostic;
+const Symbol = ruka.Symbol;
+const SymbolTable = ruka.SymbolTable;
+const Token = ruka.Token;
 const Unit = ruka.Unit;
 
-scanner: *Scanner,
+tokens: []const Token,
+index: usize,
+
+ast: *Ast,
 unit: *Unit,
+/// The bindings, functions, type parameters and parameters declared in the
+/// scopes being parsed, so names declared twice in the same scope are reported
+symbols: SymbolTable,
+/// Whether an identifier followed by a brace is parsed as a struct literal, which
+/// it isn't before the block of a condition, until a delimiter is opened
+struct_literals: bool,
+/// Whether the `>>` ending the type just parsed closed the type arguments enclosing
+/// it as well as its own, as in `Option<Vec<i32>>`
+closed_type_arguments: bool,
+/// The number of blocks, expressions, types and patterns enclosing the one being
+/// parsed, see max_depth
+depth: usize,
 
 allocator: std.mem.Allocator,
 
 const Parser = @This();
 
 pub const Ast = @import("parser/Ast.zig");
+pub const AstBuilder = @import("parser/AstBuilder.zig");
+pub const AstReader = @import("parser/AstReader.zig");
+pub const CaptureAnalyzer = @import("parser/CaptureAnalyzer.zig");
+pub const bincode = @import("parser/bincode.zig");
+pub const visitor = @import("parser/visitor.zig");
+const Node = Ast.Node;
+const Precedence = Node.Precedence;
+
+const log = std.log.scoped(.parser);
+
+/// How deeply constructs may be nested, deeper input is reported rather than
+/// parsed so it can't overflow the stack of this or any later pass
+pub const max_depth = 256;
+
+// Explicit so the mutually recursive parsing functions can resolve their error sets
+const ParseError = error{UnexpectedToken} || Allocator.Error;
+
+/// Creates a parser over tokens, which must end with an eof token
+pub fn init(unit: *Unit, tokens: []const Token) !*Parser {
+    std.debug.assert(tokens.len > 0 and tokens[tokens.len - 1].kind == .eof);
 
-pub fn init(unit: *Unit, scanner: *Scanner) !*Parser {
     const parser = try unit.allocator.create(Parser);
-    errdefer parser.deinit();
+    errdefer unit.allocator.destroy(parser);
 
     parser.* = .{
+        .tokens = tokens,
+        .index = 0,
+        .ast = undefined,
         .unit = unit,
-        .scanner = scanner,
+        .symbols = try .init(unit.allocator),
+        .struct_literals = true,
+        .closed_type_arguments = false,
+        .depth = 0,
         .allocator = unit.allocator
     };
 
@@ -32,40 +75,3294 @@ pub fn init(unit: *Unit, scanner: *Scanner) !*Parser {
 }
 
 pub fn deinit(self: *Parser) void {
+    self.symbols.deinit();
     self.allocator.destroy(self);
 }
 
-
+/// Parses the tokens into an ast. Parsing continues past errors so all of them
+/// are recorded in the unit, but fails if any were encountered
 pub fn parse(self: *Parser) !*Ast {
-    const ast = try Ast.init(self.allocator);
-    errdefer ast.deinit();
+    const error_count = self.unit.errorCount();
 
-    var output = ArrayList(u8).init(self.allocator);
-    defer output.deinit();
+    self.ast = try Ast.init(self.allocator, self.unit.input);
+    errdefer self.ast.deinit();
 
-    const writer = output.writer();
+    var items = ArrayList(*Node).init(self.arena());
 
-    try writer.print("\t{s}:\n", .{self.unit.input});
+    self.skipDocComments();
+    self.skipSeparators();
+    while (self.current().kind != .eof) {
+        const statement = self.parseStatement() catch |err| switch (err) {
+            error.UnexpectedToken => {
+                self.synchronize();
+                self.skipSeparators();
+                continue;
+            },
+            else => return err
+        };
 
-    var token = try self.scanner.nextToken();
+        try items.append(statement);
 
-    while(token.kind != .eof): (token = try self.scanner.nextToken()) {
-        try writer.print("{s}: {s}\n", .{@tagName(token.kind) , try token.kind.toStr(self.allocator)});
-        token.deinit();
+        if (!self.isSeparator()) {
+            self.createError("expected a newline or ';' after statement") catch |err| switch (err) {
+                error.UnexpectedToken => {},
+                else => return err
+            };
+            self.synchronize();
+        }
+
+        self.skipSeparators();
     }
 
-    try writer.print("eof: \\x00\n", .{});
+    self.ast.root.kind.module.items = try items.toOwnedSlice();
+    self.ast.root.span = self.spanFrom(self.tokenSpan(&self.tokens[0]));
+
+    // Closures refer to the bindings of the scopes enclosing them by name alone
+    var analyzer = try CaptureAnalyzer.init(self.unit);
+    defer analyzer.deinit();
+    try analyzer.analyze(self.ast);
 
-    std.debug.print("{s}\n", .{output.items});
+    const parse_errors = self.unit.errorCount() - error_count;
+    if (parse_errors > 0) {
+        log.err("{s}: parsing failed with {} error(s)", .{self.unit.input, parse_errors});
+        return error.ParsingFailed;
+    }
 
-    return ast;
+    return self.ast;
 }
 
-test "parser modules" {
-    _ = tests;
-    _ = Ast;
+fn arena(self: *Parser) Allocator {
+    return self.ast.arena.allocator();
 }
 
-const tests = struct {
+fn current(self: *Parser) *const Token {
+    return &self.tokens[self.index];
+}
+
+fn previous(self: *Parser) *const Token {
+    var index = self.index;
+    while (index > 0) {
+        index = index - 1;
+        if (self.tokens[index].kind != .doc_comment) return &self.tokens[index];
+    }
+
+    return &self.tokens[0];
+}
+
+// The token after the current one, skipping doc comments as advancing does
+fn peek(self: *Parser) *const Token {
+    var index = self.index + 1;
+    while (index < self.tokens.len and self.tokens[index].kind == .doc_comment) index = index + 1;
+
+    return &self.tokens[@min(index, self.tokens.len - 1)];
+}
+
+fn advance(self: *Parser) void {
+    if (self.index + 1 < self.tokens.len) self.index = self.index + 1;
+    self.skipDocComments();
+}
+
+// Doc comments are only read by documentation tools, so the parser passes over them
+fn skipDocComments(self: *Parser) void {
+    while (self.current().kind == .doc_comment) self.index = self.index + 1;
+}
+
+/// The span of token, referencing the ast's copy of the file name
+fn tokenSpan(self: *Parser, token: *const Token) ruka.Span {
+    var span = token.span;
+    span.file = self.ast.file;
+
+    return span;
+}
+
+/// A span from start to the end of the last consumed token
+fn spanFrom(self: *Parser, start: ruka.Span) ruka.Span {
+    return start.merge(self.tokenSpan(self.previous()));
+}
+
+/// Records an error at the current token and fails with UnexpectedToken
+fn createError(self: *Parser, msg: []const u8) ParseError!void {
+    try self.unit.createError(msg, self.current().span);
+    return error.UnexpectedToken;
+}
+
+// Enters a nested construct, each call must be followed by leave
+fn enter(self: *Parser) ParseError!void {
+    if (self.depth == max_depth) try self.createError("nested too deeply to parse");
+    self.depth = self.depth + 1;
+}
+
+fn leave(self: *Parser) void {
+    self.depth = self.depth - 1;
+}
+
+fn expect(self: *Parser, kind: std.meta.Tag(Token.Kind), msg: []const u8) ParseError!void {
+    if (std.meta.activeTag(self.current().kind) != kind) try self.createError(msg);
+}
+
+/// Expects the current token to be kind, consuming it
+fn consume(self: *Parser, kind: std.meta.Tag(Token.Kind), msg: []const u8) ParseError!void {
+    try self.expect(kind, msg);
+    self.advance();
+}
+
+// Newlines within delimiters don't end statements
+fn skipNewlines(self: *Parser) void {
+    while (self.current().kind == .newline) self.advance();
+}
+
+fn isKeyword(self: *Parser, keyword: Token.Keyword) bool {
+    return switch (self.current().kind) {
+        .keyword => |current_keyword| current_keyword == keyword,
+        else => false
+    };
+}
+
+fn isSeparator(self: *Parser) bool {
+    return switch (self.current().kind) {
+        .newline, .semicolon, .eof => true,
+        else => false
+    };
+}
+
+fn skipSeparators(self: *Parser) void {
+    while (self.current().kind == .newline or self.current().kind == .semicolon) {
+        self.advance();
+    }
+}
+
+/// Skips tokens until the end of the current statement
+fn synchronize(self: *Parser) void {
+    while (!self.isSeparator()) self.advance();
+}
+
+fn createNode(self: *Parser, kind: Node.Kind, span: ruka.Span) ParseError!*Node {
+    return try self.ast.createNode(kind, span);
+}
+
+/// Binds the name of the identifier token in the innermost scope, reporting it if
+/// it is already bound there. Bindings may rebind the names of earlier bindings,
+/// which is only warned about as the new binding shadows the old one
+fn declare(self: *Parser, token: *const Token, kind: Symbol.Kind, msg: []const u8) ParseError!void {
+    const span = self.tokenSpan(token);
+    const id = token.kind.identifier;
+    const name = self.unit.interner.get(id);
+
+    if (self.symbols.lookupLocal(id)) |existing| {
+        if (!isBinding(existing.kind) or !isBinding(kind)) {
+            return try self.unit.createErrorFmt(span, "{s} {s} is already defined in this scope", .{msg, name});
+        }
+
+        try self.unit.report(.initLint(.shadowing, try std.fmt.allocPrint(
+            self.unit.arena.allocator(),
+            "{s} {s} is already defined in this scope, and is shadowed",
+            .{msg, name}
+        ), span));
+    }
+
+    try self.symbols.redefine(id, .init(kind, span));
+}
+
+fn isBinding(kind: Symbol.Kind) bool {
+    return kind == .variable or kind == .constant;
+}
+
+/// Sets whether struct literals can be parsed, returning the previous setting to restore
+fn allowStructLiterals(self: *Parser, allowed: bool) bool {
+    const outer = self.struct_literals;
+    self.struct_literals = allowed;
+
+    return outer;
+}
+
+/// Copies the current identifier into the ast, consuming it
+fn parseName(self: *Parser, msg: []const u8) ParseError![]const u8 {
+    try self.expect(.identifier, msg);
+
+    const name = try self.arena().dupe(u8, self.unit.interner.get(self.current().kind.identifier));
+    self.advance();
+
+    return name;
+}
+
+fn parseStatement(self: *Parser) ParseError!*Node {
+    return switch (self.current().kind) {
+        .keyword => |keyword| switch (keyword) {
+            .let, .@"var", .@"const" => try self.parseBinding(),
+            // Closures begin with fn too, but have no name
+            .@"fn" => if (self.peek().kind == .lparen) try self.parseExpression() else try self.parseFunction(null),
+            .@"async" => try self.parseAsync(null),
+            .@"struct" => try self.parseStruct(),
+            .@"union" => try self.parseUnion(),
+            .@"enum" => try self.parseEnum(),
+            .impl => try self.parseImpl(),
+            .trait => try self.parseTrait(),
+            .@"return" => try self.parseReturn(),
+            .@"while" => try self.parseWhile(),
+            .@"for" => try self.parseFor(),
+            .@"break" => try self.parseBreak(),
+            .@"continue" => try self.parseContinue(),
+            .@"defer" => try self.parseDefer(),
+            .goto => try self.parseGoto(),
+            .@"extern" => try self.parseExtern(),
+            .import => try self.parseImport(),
+            .@"pub" => try self.parseExport(),
+            else => try self.parseExpression()
+        },
+        .pound => try self.parseAttributed(),
+        .identifier => if (self.peek().kind == .colon) try self.parseLabel() else try self.parseExpression(),
+        else => try self.parseExpression()
+    };
+}
+
+// Parses the attributes written before a declaration and the declaration, which
+// they are stored on. The lints they allow or deny are set from the attributes to
+// the end of the declaration before it is parsed, so they apply to the warnings
+// reported while parsing it
+fn parseAttributed(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+
+    var attributes = ArrayList(Node.Attribute).init(self.arena());
+    while (self.current().kind == .pound) {
+        try attributes.append(try self.parseAttribute());
+        self.skipNewlines();
+    }
+
+    const first_level = self.unit.lint_levels.items.len;
+    var open = start;
+    open.end = std.math.maxInt(usize);
+    try self.unit.setLintLevels(attributes.items, open);
+    const last_level = self.unit.lint_levels.items.len;
+
+    const declaration = try self.parseStatement();
+    for (self.unit.lint_levels.items[first_level..last_level]) |*level| level.span.end = declaration.span.end;
+
+    switch (declaration.unwrapExport().kind) {
+        .fn_def, .struct_def, .union_def, .enum_def, .impl, .trait_def, .var_decl, .const_decl => {
+            declaration.attributes = try attributes.toOwnedSlice();
+        },
+        else => try self.unit.createError("attributes can only be applied to declarations", start)
+    }
+
+    for (declaration.attributes) |attribute| {
+        if (!std.mem.eql(u8, attribute.name, "test") or declaration.unwrapExport().kind == .fn_def) continue;
+        try self.unit.createError("the test attribute can only be applied to functions", attribute.span);
+    }
+
+    return declaration;
+}
+
+// Parses `#[name]` or `#[name(arg, ...)]`, reporting names which aren't allow,
+// deny or test and arguments which aren't lints
+fn parseAttribute(self: *Parser) ParseError!Node.Attribute {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+    try self.consume(.lbracket, "expected '[' after '#'");
+
+    const name_span = self.tokenSpan(self.current());
+    // test is a keyword, so isn't read as a name
+    const is_test = self.current().kind == .keyword and self.current().kind.keyword == .@"test";
+    const name = if (is_test) block: {
+        self.advance();
+        break :block "test";
+    } else try self.parseName("expected an attribute name");
+
+    const is_lint_level = std.mem.eql(u8, name, "allow") or std.mem.eql(u8, name, "deny");
+    if (!is_lint_level and !is_test) {
+        try self.unit.createErrorFmt(name_span, "unknown attribute {s}, expected allow, deny or test", .{name});
+    }
+
+    var args = ArrayList([]const u8).init(self.arena());
+    if (self.current().kind == .lparen) {
+        self.advance();
+        while (self.current().kind != .rparen) {
+            const arg_span = self.tokenSpan(self.current());
+            const arg = try self.parseName("expected a lint name");
+            if (is_lint_level and Diagnostic.Lint.fromName(arg) == null) {
+                try self.unit.createErrorFmt(arg_span, "unknown lint {s}", .{arg});
+            }
+            try args.append(arg);
+
+            if (self.current().kind != .comma) break;
+            self.advance();
+        }
+        try self.consume(.rparen, "expected ')' to close the attribute's arguments");
+    }
+    try self.consume(.rbracket, "expected ']' to close the attribute");
+
+    if (is_test and args.items.len > 0) try self.unit.createError("the test attribute takes no arguments", self.spanFrom(start));
+
+    return .{
+        .name = name,
+        .args = try args.toOwnedSlice(),
+        .span = self.spanFrom(start)
+    };
+}
+
+fn parseImport(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    var path = ArrayList([]const u8).init(self.arena());
+    try path.append(try self.parseName("expected a module name after import"));
+    while (self.current().kind == .dot) {
+        self.advance();
+        try path.append(try self.parseName("expected a module name after '.'"));
+    }
+
+    var import: Node.Import = .{
+        .path = try path.toOwnedSlice(),
+        .alias = null
+    };
+
+    if (self.isKeyword(.as)) {
+        self.advance();
+        import.alias = try self.parseName("expected an alias after as");
+    }
+
+    return try self.createNode(.{ .import = import }, self.spanFrom(start));
+}
+
+fn parseExport(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const declaration = switch (self.current().kind) {
+        .keyword => |keyword| switch (keyword) {
+            .let, .@"var", .@"const" => try self.parseBinding(),
+            .@"fn" => try self.parseFunction(null),
+            .@"async" => try self.parseAsync(null),
+            .@"extern" => try self.parseExtern(),
+            .@"struct" => try self.parseStruct(),
+            .@"union" => try self.parseUnion(),
+            .@"enum" => try self.parseEnum(),
+            .trait => try self.parseTrait(),
+            else => null
+        },
+        else => null
+    } orelse {
+        try self.createError("expected a declaration after pub");
+        unreachable;
+    };
+
+    return try self.createNode(.{ .@"export" = declaration }, self.spanFrom(start));
+}
+
+// Functions in impls and traits may write their first parameter as `self`, of the
+// receiver's type
+fn parseFunction(self: *Parser, receiver: ?Node.Kind) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    var function: Node.FunctionDef = .{
+        .name = try self.parseName("expected a function name after fn"),
+        .params = &.{},
+        .return_type = null,
+        .body = undefined
+    };
+    try self.declare(self.previous(), .function, "function");
+
+    // Type parameters and parameters are only visible within the function
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    function.type_params = try self.parseTypeParams();
+    try self.consume(.lparen, "expected '(' after function name");
+    function.params = try self.parseParams(receiver);
+
+    // Functions without a return type return unit
+    if (self.current().kind == .arrow) {
+        self.advance();
+        function.return_type = try self.parseAnnotation();
+    }
+
+    function.body = try self.parseBlock();
+    return try self.createNode(.{ .fn_def = function }, self.spanFrom(start));
+}
+
+// `async fn name(...) -> T`, whose calls return a Future<T> rather than running it
+fn parseAsync(self: *Parser, receiver: ?Node.Kind) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    if (!self.isKeyword(.@"fn")) try self.createError("expected fn after async");
+    const function = try self.parseFunction(receiver);
+
+    function.kind.fn_def.@"async" = true;
+    function.span = self.spanFrom(start);
+    return function;
+}
+
+// `extern fn name(a: T, ...) -> R`, a function defined outside the program. Its
+// parameters must be annotated, as there is no body to infer their types from,
+// and a `...` after them accepts any number of arguments more
+fn parseExtern(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    if (!self.isKeyword(.@"fn")) try self.createError("expected fn after extern");
+    self.advance();
+
+    var function: Node.ExternFn = .{
+        .name = try self.parseName("expected a function name after fn"),
+        .params = &.{},
+        .variadic = false,
+        .return_type = null
+    };
+    try self.declare(self.previous(), .extern_function, "function");
+
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    try self.consume(.lparen, "expected '(' after function name");
+    self.skipNewlines();
 
+    var params = ArrayList(Node.Parameter).init(self.arena());
+    while (self.current().kind != .rparen) {
+        if (self.current().kind == .ellipsis) {
+            self.advance();
+            function.variadic = true;
+
+            self.skipNewlines();
+            if (self.current().kind != .rparen) try self.createError("expected ')' after '...', it must be the last parameter");
+            break;
+        }
+
+        const param_start = self.tokenSpan(self.current());
+        const name = try self.parseName("expected a parameter name");
+        try self.declare(self.previous(), .parameter, "parameter");
+        try self.consume(.colon, "expected ':' after parameter name, extern function parameters must be annotated");
+
+        try params.append(.{
+            .name = name,
+            .annotation = try self.parseAnnotation(),
+            .span = self.spanFrom(param_start)
+        });
+
+        self.skipNewlines();
+        if (self.current().kind != .comma) break;
+        self.advance();
+        self.skipNewlines();
+    }
+
+    try self.consume(.rparen, "expected ')' to close parameters");
+    function.params = try params.toOwnedSlice();
+
+    if (self.current().kind == .arrow) {
+        self.advance();
+        function.return_type = try self.parseAnnotation();
+    }
+
+    return try self.createNode(.{ .extern_fn = function }, self.spanFrom(start));
+}
+
+// The parameters following the '(' of a function, declared in the current scope
+fn parseParams(self: *Parser, receiver: ?Node.Kind) ParseError![]Node.Parameter {
+    self.skipNewlines();
+
+    var params = ArrayList(Node.Parameter).init(self.arena());
+    while (self.current().kind != .rparen) {
+        const shorthand = if (receiver != null and params.items.len == 0) try self.parseSelf(receiver.?) else null;
+        try params.append(shorthand orelse try self.parseParam());
+
+        self.skipNewlines();
+        if (self.current().kind != .comma) break;
+        self.advance();
+        self.skipNewlines();
+    }
+
+    try self.consume(.rparen, "expected ')' to close parameters");
+    return try params.toOwnedSlice();
+}
+
+fn parseParam(self: *Parser) ParseError!Node.Parameter {
+    const start = self.tokenSpan(self.current());
+    const name = try self.parseName("expected a parameter name");
+    try self.declare(self.previous(), .parameter, "parameter");
+
+    var annotation: ?*Node = null;
+    if (self.current().kind == .colon) {
+        self.advance();
+        annotation = try self.parseAnnotation();
+    }
+
+    return .{ .name = name, .annotation = annotation, .span = self.spanFrom(start) };
+}
+
+// `self` or `&self`, short for `self: Receiver` and `self: &Receiver`. Null when
+// the parameter isn't written either way, such as an annotated self
+fn parseSelf(self: *Parser, receiver: Node.Kind) ParseError!?Node.Parameter {
+    const start = self.tokenSpan(self.current());
+    const reference = self.current().kind == .ampersand;
+
+    const token = if (reference) self.peek() else self.current();
+    if (token.kind != .identifier or !std.mem.eql(u8, self.unit.interner.get(token.kind.identifier), "self")) return null;
+    if (!reference and self.peek().kind == .colon) return null;
+
+    if (reference) self.advance();
+    const name_span = self.tokenSpan(self.current());
+    try self.declare(self.current(), .parameter, "parameter");
+    self.advance();
+
+    const receiver_type = try self.createNode(receiver, name_span);
+    return .{
+        .name = "self",
+        .annotation = if (reference) try self.createNode(.{ .type_expr = .{ .reference = receiver_type } }, self.spanFrom(start)) else receiver_type,
+        .span = self.spanFrom(start),
+        .shorthand = true
+    };
+}
+
+// A function without a name, written where a value is expected. Its captures are
+// found once the whole ast is parsed
+fn parseClosure(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    // Parameters are only visible within the closure
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    try self.consume(.lparen, "expected '(' after fn");
+    var closure: Node.Closure = .{
+        .params = try self.parseParams(null),
+        .return_type = null,
+        .body = undefined
+    };
+
+    // Closures without a return type return the type of their body
+    if (self.current().kind == .arrow) {
+        self.advance();
+        closure.return_type = try self.parseAnnotation();
+    }
+
+    closure.body = try self.parseBlock();
+    return try self.createNode(.{ .closure = closure }, self.spanFrom(start));
+}
+
+fn parseStruct(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const name = try self.parseName("expected a struct name after struct");
+    try self.declare(self.previous(), .@"type", "struct");
+
+    // Type parameters are only visible within the struct
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    const type_params = try self.parseTypeParams();
+    try self.consume(.lsquirly, "expected '{' after struct name");
+
+    const fields = try self.parseFieldDefinitions("struct");
+    try self.consume(.rsquirly, "expected '}' to close struct");
+
+    return try self.createNode(.{ .struct_def = .{
+        .name = name,
+        .type_params = type_params,
+        .fields = fields
+    }}, self.spanFrom(start));
+}
+
+fn parseUnion(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const name = try self.parseName("expected a union name after union");
+    try self.declare(self.previous(), .@"type", "union");
+    try self.consume(.lsquirly, "expected '{' after union name");
+
+    const fields = try self.parseFieldDefinitions("union");
+    try self.consume(.rsquirly, "expected '}' to close union");
+
+    return try self.createNode(.{ .union_def = .{
+        .name = name,
+        .fields = fields
+    }}, self.spanFrom(start));
+}
+
+// The type parameters of a generic function or struct, declaring them in the
+// current scope. Definitions without any have no angle brackets
+fn parseTypeParams(self: *Parser) ParseError![]const []const u8 {
+    if (self.current().kind != .lesser) return &.{};
+    self.advance();
+
+    var type_params = ArrayList([]const u8).init(self.arena());
+    while (true) {
+        try type_params.append(try self.parseName("expected a type parameter name"));
+        try self.declare(self.previous(), .@"type", "type parameter");
+
+        if (self.current().kind != .comma) break;
+        self.advance();
+    }
+
+    try self.consume(.greater, "expected '>' to close type parameters");
+    return try type_params.toOwnedSlice();
+}
+
+// The fields of a struct or struct style variant up to the closing brace. Fields are
+// separated like statements, or by commas
+fn parseFieldDefinitions(self: *Parser, owner: []const u8) ParseError![]Node.StructDef.Field {
+    var fields = ArrayList(Node.StructDef.Field).init(self.arena());
+
+    self.skipSeparators();
+    while (self.current().kind != .rsquirly and self.current().kind != .eof) {
+        const field_start = self.tokenSpan(self.current());
+        const field_name = try self.parseName("expected a field name");
+
+        for (fields.items) |field| if (std.mem.eql(u8, field.name, field_name)) {
+            try self.unit.createErrorFmt(field_start, "field {s} is already defined in this {s}", .{field_name, owner});
+            break;
+        };
+
+        try self.consume(.colon, "expected ':' after field name");
+        const annotation = try self.parseAnnotation();
+        try fields.append(.{ .name = field_name, .annotation = annotation, .span = self.spanFrom(field_start) });
+
+        if (self.current().kind == .comma) {
+            self.advance();
+        } else if (!self.isSeparator() and self.current().kind != .rsquirly) {
+            try self.createError("expected a newline or ',' after field");
+        }
+
+        self.skipSeparators();
+    }
+
+    return try fields.toOwnedSlice();
+}
+
+// Variants are separated like statements, or by commas
+fn parseEnum(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const name = try self.parseName("expected an enum name after enum");
+    try self.declare(self.previous(), .@"type", "enum");
+    try self.consume(.lsquirly, "expected '{' after enum name");
+
+    var variants = ArrayList(Node.EnumDef.Variant).init(self.arena());
+
+    self.skipSeparators();
+    while (self.current().kind != .rsquirly and self.current().kind != .eof) {
+        const variant_start = self.tokenSpan(self.current());
+        const variant_name = try self.parseName("expected a variant name");
+
+        for (variants.items) |variant| if (std.mem.eql(u8, variant.name, variant_name)) {
+            try self.unit.createErrorFmt(variant_start, "variant {s} is already defined in this enum", .{variant_name});
+            break;
+        };
+
+        const fields: Node.EnumDef.Variant.Fields = switch (self.current().kind) {
+            .lparen => .{ .tuple = try self.parseVariantTypes() },
+            .lsquirly => block: {
+                self.advance();
+
+                const fields = try self.parseFieldDefinitions("variant");
+                try self.consume(.rsquirly, "expected '}' to close variant fields");
+
+                break :block .{ .@"struct" = fields };
+            },
+            else => .unit
+        };
+        try variants.append(.{ .name = variant_name, .fields = fields, .span = self.spanFrom(variant_start) });
+
+        if (self.current().kind == .comma) {
+            self.advance();
+        } else if (!self.isSeparator() and self.current().kind != .rsquirly) {
+            try self.createError("expected a newline or ',' after enum variant");
+        }
+
+        self.skipSeparators();
+    }
+
+    try self.consume(.rsquirly, "expected '}' to close enum");
+
+    return try self.createNode(.{ .enum_def = .{
+        .name = name,
+        .variants = try variants.toOwnedSlice()
+    }}, self.spanFrom(start));
+}
+
+// The types of the values held by a tuple style variant, `(f64, f64)`
+fn parseVariantTypes(self: *Parser) ParseError![]*Node {
+    self.advance();
+    self.skipNewlines();
+
+    var annotations = ArrayList(*Node).init(self.arena());
+    while (self.current().kind != .rparen) {
+        try annotations.append(try self.parseAnnotation());
+
+        self.skipNewlines();
+        if (self.current().kind != .comma) break;
+        self.advance();
+        self.skipNewlines();
+    }
+
+    try self.consume(.rparen, "expected ')' to close variant fields");
+    return try annotations.toOwnedSlice();
+}
+
+// The functions of an impl are scoped to it, they are found through the struct
+fn parseImpl(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    // `impl Trait for Struct` defines the trait's methods for the struct
+    var trait: ?[]const u8 = null;
+    var name = try self.parseName("expected a struct name after impl");
+    if (self.isKeyword(.@"for")) {
+        self.advance();
+        trait = name;
+        name = try self.parseName("expected a struct name after for");
+    }
+    try self.consume(.lsquirly, "expected '{' after impl name");
+
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    var functions = ArrayList(*Node).init(self.arena());
+
+    self.skipSeparators();
+    while (self.current().kind != .rsquirly and self.current().kind != .eof) {
+        if (self.isKeyword(.@"async")) {
+            try functions.append(try self.parseAsync(.{ .identifier = name }));
+        } else {
+            if (!self.isKeyword(.@"fn")) try self.createError("expected a function in impl block");
+            try functions.append(try self.parseFunction(.{ .identifier = name }));
+        }
+
+        if (!self.isSeparator() and self.current().kind != .rsquirly) {
+            try self.createError("expected a newline or ';' after function");
+        }
+
+        self.skipSeparators();
+    }
+
+    try self.consume(.rsquirly, "expected '}' to close impl block");
+
+    return try self.createNode(.{ .impl = .{
+        .name = name,
+        .functions = try functions.toOwnedSlice(),
+        .trait = trait
+    }}, self.spanFrom(start));
+}
+
+// `trait Name { fn method(&self) -> T }`, the methods given bodies are the defaults
+// of the implementations which don't define them. Their self parameters are of any
+// type implementing the trait
+fn parseTrait(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const name = try self.parseName("expected a trait name after trait");
+    try self.declare(self.previous(), .@"type", "trait");
+    try self.consume(.lsquirly, "expected '{' after trait name");
+
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    var methods = ArrayList(Node.TraitDef.Method).init(self.arena());
+
+    self.skipSeparators();
+    while (self.current().kind != .rsquirly and self.current().kind != .eof) {
+        if (!self.isKeyword(.@"fn")) try self.createError("expected a method in trait");
+        try methods.append(try self.parseMethod(name));
+
+        if (!self.isSeparator() and self.current().kind != .rsquirly) {
+            try self.createError("expected a newline or ';' after method");
+        }
+
+        self.skipSeparators();
+    }
+
+    try self.consume(.rsquirly, "expected '}' to close trait");
+
+    return try self.createNode(.{ .trait_def = .{
+        .name = name,
+        .methods = try methods.toOwnedSlice()
+    }}, self.spanFrom(start));
+}
+
+// A method of trait, a default is defined when it is given a body
+fn parseMethod(self: *Parser, trait: []const u8) ParseError!Node.TraitDef.Method {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const name = try self.parseName("expected a method name after fn");
+    try self.declare(self.previous(), .function, "method");
+
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    try self.consume(.lparen, "expected '(' after method name");
+    var method: Node.TraitDef.Method = .{
+        .name = name,
+        .params = try self.parseParams(.{ .type_expr = .{ .impl = trait } }),
+        .return_type = null,
+        .default = null,
+        .span = undefined
+    };
+
+    if (self.current().kind == .arrow) {
+        self.advance();
+        method.return_type = try self.parseAnnotation();
+    }
+
+    if (self.current().kind == .lsquirly) {
+        const body = try self.parseBlock();
+        method.default = try self.createNode(.{ .fn_def = .{
+            .name = name,
+            .params = method.params,
+            .return_type = method.return_type,
+            .body = body
+        }}, self.spanFrom(start));
+    }
+
+    method.span = self.spanFrom(start);
+    return method;
+}
+
+/// Parses statements between braces. Errors within the block are recovered from
+/// at the end of the statement, so those after it are still parsed
+fn parseBlock(self: *Parser) ParseError!*Node {
+    try self.enter();
+    defer self.leave();
+
+    const start = self.tokenSpan(self.current());
+    try self.consume(.lsquirly, "expected '{' to begin block");
+
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    const outer = self.allowStructLiterals(true);
+    defer self.struct_literals = outer;
+
+    var statements = ArrayList(*Node).init(self.arena());
+
+    self.skipSeparators();
+    while (self.current().kind != .rsquirly and self.current().kind != .eof) {
+        const statement = self.parseStatement() catch |err| switch (err) {
+            error.UnexpectedToken => {
+                self.synchronizeBlock();
+                self.skipSeparators();
+                continue;
+            },
+            else => return err
+        };
+
+        try statements.append(statement);
+
+        if (!self.isSeparator() and self.current().kind != .rsquirly) {
+            self.createError("expected a newline or ';' after statement") catch |err| switch (err) {
+                error.UnexpectedToken => {},
+                else => return err
+            };
+            self.synchronizeBlock();
+        }
+
+        self.skipSeparators();
+    }
+
+    try self.consume(.rsquirly, "expected '}' to close block");
+    return try self.createNode(.{ .block = .{
+        .statements = try statements.toOwnedSlice()
+    }}, self.spanFrom(start));
+}
+
+/// Skips tokens until the end of the current statement or block
+fn synchronizeBlock(self: *Parser) void {
+    while (!self.isSeparator() and self.current().kind != .rsquirly) self.advance();
+}
+
+fn parseReturn(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    return try self.createNode(.{ .@"return" = try self.parseOptionalValue() }, self.spanFrom(start));
+}
+
+fn parseBreak(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    return try self.createNode(.{ .@"break" = try self.parseOptionalValue() }, self.spanFrom(start));
+}
+
+fn parseContinue(self: *Parser) ParseError!*Node {
+    const span = self.tokenSpan(self.current());
+    self.advance();
+
+    return try self.createNode(.@"continue", span);
+}
+
+fn parseDefer(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const body = if (self.current().kind == .lsquirly) try self.parseBlock()
+        else try self.parseExpression();
+
+    return try self.createNode(.{ .@"defer" = body }, self.spanFrom(start));
+}
+
+// Labels are written `name:` as a statement of their own, marking where gotos
+// naming them jump to
+fn parseLabel(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    const name = try self.parseName("expected a label name");
+    self.advance();
+
+    return try self.createNode(.{ .label = name }, self.spanFrom(start));
+}
+
+fn parseGoto(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const target = try self.parseName("expected the name of the label to go to");
+    return try self.createNode(.{ .goto = target }, self.spanFrom(start));
+}
+
+// `sizeof(T)`, whose parentheses hold a type rather than an expression
+fn parseSizeof(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    try self.consume(.lparen, "expected '(' after sizeof");
+    const annotation = try self.parseAnnotation();
+    try self.consume(.rparen, "expected ')' to close sizeof");
+
+    return try self.createNode(.{ .sizeof = annotation }, self.spanFrom(start));
+}
+
+// `comptime { ... }`, a block evaluated while the program is checked. comptime is
+// scanned as a parameter mode
+fn parseComptime(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    return try self.createNode(.{ .@"comptime" = try self.parseBlock() }, self.spanFrom(start));
+}
+
+// `unsafe { ... }`, a block within which extern functions can be called
+fn parseUnsafe(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    return try self.createNode(.{ .@"unsafe" = try self.parseBlock() }, self.spanFrom(start));
+}
+
+// The value of a return or break, which is left out when the statement ends
+fn parseOptionalValue(self: *Parser) ParseError!?*Node {
+    if (self.isSeparator() or self.current().kind == .rsquirly) return null;
+    return try self.parseExpression();
+}
+
+fn parseWhile(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const loop: Node.While = .{
+        .condition = try self.parseCondition(),
+        .body = try self.parseBlock()
+    };
+
+    return try self.createNode(.{ .@"while" = loop }, self.spanFrom(start));
+}
+
+fn parseFor(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const variable_token = self.current();
+    const variable = try self.parseName("expected a loop variable after for");
+
+    if (!self.isKeyword(.in)) try self.createError("expected in after the loop variable");
+    self.advance();
+
+    const iterable = try self.parseCondition();
+
+    // The loop variable is only visible within the body
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    try self.declare(variable_token, .constant, "loop variable");
+
+    const loop: Node.For = .{
+        .variable = variable,
+        .iterable = iterable,
+        .body = try self.parseBlock()
+    };
+
+    return try self.createNode(.{ .@"for" = loop }, self.spanFrom(start));
+}
+
+// Else ifs are parsed as an if nested in the else block, so later passes only
+// handle a single kind of conditional
+fn parseIf(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    var conditional: Node.If = .{
+        .condition = try self.parseCondition(),
+        .then_block = try self.parseBlock(),
+        .else_block = null
+    };
+
+    if (self.isKeyword(.@"else")) {
+        self.advance();
+        conditional.else_block = if (self.isKeyword(.@"if")) try self.parseIf()
+            else try self.parseBlock();
+    }
+
+    return try self.createNode(.{ .@"if" = conditional }, self.spanFrom(start));
+}
+
+fn parseMatch(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    self.advance();
+
+    const subject = try self.parseCondition();
+    try self.consume(.lsquirly, "expected '{' to begin match arms");
+
+    const outer = self.allowStructLiterals(true);
+    defer self.struct_literals = outer;
+
+    var arms = ArrayList(Node.Match.Arm).init(self.arena());
+
+    self.skipSeparators();
+    while (self.current().kind != .rsquirly and self.current().kind != .eof) {
+        try arms.append(try self.parseArm());
+
+        // Arms are separated like statements, or by commas
+        if (self.current().kind == .comma) {
+            self.advance();
+        } else if (!self.isSeparator() and self.current().kind != .rsquirly) {
+            try self.createError("expected a newline or ',' after match arm");
+        }
+
+        self.skipSeparators();
+    }
+
+    try self.consume(.rsquirly, "expected '}' to end match arms");
+
+    return try self.createNode(.{ .match = .{
+        .subject = subject,
+        .arms = try arms.toOwnedSlice()
+    }}, self.spanFrom(start));
+}
+
+fn parseArm(self: *Parser) ParseError!Node.Match.Arm {
+    // The names a pattern binds are only visible within its arm
+    try self.symbols.enterScope();
+    defer self.symbols.exitScope();
+
+    var arm: Node.Match.Arm = .{
+        .pattern = try self.parsePattern(),
+        .guard = null,
+        .body = undefined
+    };
+
+    if (self.isKeyword(.@"if")) {
+        self.advance();
+        arm.guard = try self.parseExpression();
+    }
+
+    try self.consume(.wide_arrow, "expected '=>' after match pattern");
+    arm.body = if (self.current().kind == .lsquirly) try self.parseBlock()
+        else try self.parseExpression();
+
+    return arm;
+}
+
+fn parsePattern(self: *Parser) ParseError!Node.Pattern {
+    try self.enter();
+    defer self.leave();
+
+    const start = self.tokenSpan(self.current());
+    const first = try self.parsePatternAlternative();
+    if (self.current().kind != .pipe) return first;
+
+    var alternatives = ArrayList(Node.Pattern).init(self.arena());
+    try alternatives.append(first);
+    while (self.current().kind == .pipe) {
+        self.advance();
+        try alternatives.append(try self.parsePatternAlternative());
+    }
+
+    return .{
+        .kind = .{ .@"or" = try alternatives.toOwnedSlice() },
+        .span = self.spanFrom(start)
+    };
+}
+
+// Comma separated patterns up to the closing token, returning whether a comma
+// followed the last pattern
+fn parsePatternList(
+    self: *Parser,
+    patterns: *ArrayList(Node.Pattern),
+    close: std.meta.Tag(Token.Kind),
+    msg: []const u8
+) ParseError!bool {
+    var trailing = false;
+
+    self.skipNewlines();
+    while (std.meta.activeTag(self.current().kind) != close) {
+        try patterns.append(try self.parsePattern());
+        self.skipNewlines();
+
+        trailing = self.current().kind == .comma;
+        if (!trailing) break;
+        self.advance();
+        self.skipNewlines();
+    }
+
+    try self.consume(close, msg);
+    return trailing;
+}
+
+fn parsePatternAlternative(self: *Parser) ParseError!Node.Pattern {
+    const token = self.current();
+    const start = self.tokenSpan(token);
+
+    const kind: Node.Pattern.Kind = switch (token.kind) {
+        .identifier => |id| block: {
+            if (std.mem.eql(u8, self.unit.interner.get(id), "_")) {
+                self.advance();
+                break :block .wildcard;
+            }
+
+            var path = ArrayList([]const u8).init(self.arena());
+            try path.append(try self.parseName("expected a pattern"));
+            while (self.current().kind == .double_colon) {
+                self.advance();
+                try path.append(try self.parseName("expected a variant name after '::'"));
+            }
+
+            switch (self.current().kind) {
+                .lparen => {
+                    self.advance();
+
+                    var fields = ArrayList(Node.Pattern).init(self.arena());
+                    _ = try self.parsePatternList(&fields, .rparen, "expected ')' to close variant pattern");
+
+                    break :block .{ .variant = .{
+                        .path = try path.toOwnedSlice(),
+                        .fields = .{ .tuple = try fields.toOwnedSlice() }
+                    }};
+                },
+                .lsquirly => {
+                    const fields = try self.parseFieldPatterns();
+                    if (path.items.len == 1) break :block .{ .@"struct" = .{
+                        .name = path.items[0],
+                        .fields = fields
+                    }};
+
+                    break :block .{ .variant = .{
+                        .path = try path.toOwnedSlice(),
+                        .fields = .{ .@"struct" = fields }
+                    }};
+                },
+                else => {}
+            }
+
+            // Paths and the names of the prelude's variants name variants, other
+            // single names are bound
+            const prelude = Node.VariantLiteral.prelude.has(path.items[0]);
+            if (path.items.len > 1 or prelude) break :block .{ .variant = .{
+                .path = try path.toOwnedSlice(),
+                .fields = .unit
+            }};
+
+            try self.declare(token, .constant, "binding");
+            break :block .{ .identifier = path.items[0] };
+        },
+        .lparen => block: {
+            self.advance();
+
+            var elements = ArrayList(Node.Pattern).init(self.arena());
+            const trailing = try self.parsePatternList(&elements, .rparen, "expected ')' to close tuple pattern");
+
+            // Parentheses around a single pattern only group it
+            if (elements.items.len == 0) break :block .{ .literal = .unit };
+            if (elements.items.len == 1 and !trailing) return elements.items[0];
+
+            break :block .{ .tuple = try elements.toOwnedSlice() };
+        },
+        .minus => block: {
+            self.advance();
+
+            const literal: Node.Literal = switch (self.current().kind) {
+                .integer => |integer| .{ .integer = -integer },
+                .float => |float| .{ .float = -float },
+                else => {
+                    try self.createError("expected a number after '-' in pattern");
+                    unreachable;
+                }
+            };
+            self.advance();
+
+            break :block .{ .literal = literal };
+        },
+        else => .{ .literal = try self.parseLiteralPattern() }
+    };
+
+    return .{ .kind = kind, .span = self.spanFrom(start) };
+}
+
+fn parseLiteralPattern(self: *Parser) ParseError!Node.Literal {
+    const literal: Node.Literal = switch (self.current().kind) {
+        .integer => |integer| .{ .integer = integer },
+        .float => |float| .{ .float = float },
+        .string => |string| .{ .string = try self.arena().dupe(u8, string.items) },
+        .character => |character| .{ .character = character },
+        .keyword => |keyword| switch (keyword) {
+            .true => .{ .boolean = true },
+            .false => .{ .boolean = false },
+            else => {
+                try self.createError("expected a pattern");
+                unreachable;
+            }
+        },
+        else => {
+            try self.createError("expected a pattern");
+            unreachable;
+        }
+    };
+
+    self.advance();
+    return literal;
+}
+
+// Fields without a pattern bind the field to its own name
+fn parseFieldPatterns(self: *Parser) ParseError![]Node.Pattern.StructPattern.Field {
+    self.advance();
+
+    var fields = ArrayList(Node.Pattern.StructPattern.Field).init(self.arena());
+
+    self.skipNewlines();
+    while (self.current().kind != .rsquirly) {
+        const field_token = self.current();
+        const field_start = self.tokenSpan(field_token);
+        const field_name = try self.parseName("expected a field name in struct pattern");
+
+        var pattern: Node.Pattern = undefined;
+        if (self.current().kind == .colon) {
+            self.advance();
+            pattern = try self.parsePattern();
+        } else {
+            try self.declare(field_token, .constant, "binding");
+            pattern = .{ .kind = .{ .identifier = field_name }, .span = self.spanFrom(field_start) };
+        }
+
+        try fields.append(.{ .name = field_name, .pattern = pattern });
+        self.skipNewlines();
+
+        if (self.current().kind != .comma) break;
+        self.advance();
+        self.skipNewlines();
+    }
+
+    try self.consume(.rsquirly, "expected '}' to close struct pattern");
+    return try fields.toOwnedSlice();
+}
+
+fn parseBinding(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    const keyword = self.current().kind.keyword;
+    self.advance();
+
+    if (keyword == .let and self.startsDestructure()) return try self.parseDestructure(start);
+
+    const name_token = self.current();
+    var binding: Node.Binding = .{
+        .name = try self.parseName("expected an identifier after binding keyword"),
+        .mutable = keyword == .@"var",
+        .annotation = null,
+        .value = null
+    };
+
+    if (self.current().kind == .colon) {
+        self.advance();
+        binding.annotation = try self.parseAnnotation();
+    }
+
+    // Annotated bindings may be left without a value, except for constants
+    if (keyword == .@"const") {
+        try self.consume(.assign, "expected '=' after constant identifier, constants must have a value");
+        binding.value = try self.parseExpression();
+    } else if (binding.annotation == null or self.current().kind == .assign) {
+        try self.consume(.assign, "expected '=' after binding identifier");
+        binding.value = try self.parseExpression();
+    }
+
+    // Declared after the value, which can't refer to the binding itself
+    try self.declare(name_token, if (binding.mutable) .variable else .constant, "binding");
+
+    const kind: Node.Kind = switch (keyword) {
+        .@"const" => .{ .const_decl = binding },
+        else => .{ .var_decl = binding }
+    };
+
+    return try self.createNode(kind, self.spanFrom(start));
+}
+
+// Patterns other than a single name, `(a, b)`, `Point { x, y }` or `Some(x)`
+fn startsDestructure(self: *Parser) bool {
+    return switch (self.current().kind) {
+        .lparen => true,
+        .identifier => switch (self.peek().kind) {
+            .lparen, .lsquirly, .double_colon => true,
+            else => false
+        },
+        else => false
+    };
+}
+
+// The names of the pattern are bound as it is parsed, every destructuring binding
+// must have a value
+fn parseDestructure(self: *Parser, start: ruka.Span) ParseError!*Node {
+    const pattern = try self.parsePattern();
+
+    var annotation: ?*Node = null;
+    if (self.current().kind == .colon) {
+        self.advance();
+        annotation = try self.parseAnnotation();
+    }
+
+    try self.consume(.assign, "expected '=' after binding pattern");
+
+    return try self.createNode(.{ .destructure = .{
+        .pattern = pattern,
+        .annotation = annotation,
+        .value = try self.parseExpression()
+    }}, self.spanFrom(start));
+}
+
+fn parseExpression(self: *Parser) ParseError!*Node {
+    return try self.parseBinary(.assignment);
+}
+
+// Parses the expression before the block of an if, while, for or match, where a
+// brace begins the block rather than a struct literal
+fn parseCondition(self: *Parser) ParseError!*Node {
+    const outer = self.allowStructLiterals(false);
+    defer self.struct_literals = outer;
+
+    return try self.parseExpression();
+}
+
+// A type which isn't within type arguments, so a `>>` ending it closes nothing
+fn parseAnnotation(self: *Parser) ParseError!*Node {
+    const annotation = try self.parseType();
+    if (self.closed_type_arguments) {
+        self.closed_type_arguments = false;
+        try self.unit.createError("unexpected '>' after type", self.previous().span);
+        return error.UnexpectedToken;
+    }
+
+    return annotation;
+}
+
+// Names of types are identifiers and unit is the unit literal, every other type
+// is a type expression. Types which end with another type, like references, leave
+// closing any type arguments to it
+fn parseType(self: *Parser) ParseError!*Node {
+    try self.enter();
+    defer self.leave();
+
+    const token = self.current();
+    const start = self.tokenSpan(token);
+
+    const type_expr: Node.TypeExpr = switch (token.kind) {
+        .identifier => |id| block: {
+            const name = try self.arena().dupe(u8, self.unit.interner.get(id));
+            self.advance();
+
+            if (self.current().kind != .lesser) return try self.createNode(.{ .identifier = name }, start);
+            break :block .{ .generic = .{
+                .name = name,
+                .arguments = try self.parseTypeArguments()
+            }};
+        },
+        .lbracket => block: {
+            self.advance();
+
+            const element = try self.parseAnnotation();
+            if (self.current().kind != .semicolon) {
+                try self.consume(.rbracket, "expected ']' to close slice type");
+                break :block .{ .slice = element };
+            }
+            self.advance();
+
+            const len = try self.parseExpression();
+            try self.consume(.rbracket, "expected ']' to close array type");
+
+            break :block .{ .array = .{
+                .element = element,
+                .len = len
+            }};
+        },
+        .ampersand => block: {
+            self.advance();
+            break :block .{ .reference = try self.parseType() };
+        },
+        // Only valid as the last parameter of a function type, which is checked
+        // when the type is resolved
+        .ellipsis => block: {
+            self.advance();
+            break :block .variadic;
+        },
+        .lparen => block: {
+            self.advance();
+            self.skipNewlines();
+
+            if (self.current().kind == .rparen) {
+                self.advance();
+                return try self.createNode(.{ .literal = .unit }, self.spanFrom(start));
+            }
+
+            var elements = ArrayList(*Node).init(self.arena());
+            var trailing_comma = false;
+            while (self.current().kind != .rparen) {
+                try elements.append(try self.parseAnnotation());
+                trailing_comma = false;
+
+                self.skipNewlines();
+                if (self.current().kind != .comma) break;
+                self.advance();
+                self.skipNewlines();
+                trailing_comma = true;
+            }
+
+            try self.consume(.rparen, "expected ')' to close tuple type");
+
+            // A single type without a trailing comma is only parenthesized
+            if (elements.items.len == 1 and !trailing_comma) return elements.items[0];
+            break :block .{ .tuple = try elements.toOwnedSlice() };
+        },
+        .keyword => |keyword| block: {
+            // `impl Trait`, any type implementing the trait
+            if (keyword == .impl) {
+                self.advance();
+                break :block .{ .impl = try self.parseName("expected a trait name after impl") };
+            }
+            if (keyword != .@"fn") {
+                try self.createError("expected a type");
+                unreachable;
+            }
+            self.advance();
+
+            try self.consume(.lparen, "expected '(' after fn in function type");
+            self.skipNewlines();
+
+            var params = ArrayList(*Node).init(self.arena());
+            while (self.current().kind != .rparen) {
+                try params.append(try self.parseAnnotation());
+
+                self.skipNewlines();
+                if (self.current().kind != .comma) break;
+                self.advance();
+                self.skipNewlines();
+            }
+
+            try self.consume(.rparen, "expected ')' to close function type parameters");
+
+            var return_type: ?*Node = null;
+            if (self.current().kind == .arrow) {
+                self.advance();
+                return_type = try self.parseType();
+            }
+
+            break :block .{ .function = .{
+                .params = try params.toOwnedSlice(),
+                .return_type = return_type
+            }};
+        },
+        else => {
+            try self.createError("expected a type");
+            unreachable;
+        }
+    };
+
+    return try self.createNode(.{ .type_expr = type_expr }, self.spanFrom(start));
+}
+
+// The types between angle brackets following the name of a generic type. A `>>`
+// is scanned as one token, so where type arguments nest it closes both lists
+fn parseTypeArguments(self: *Parser) ParseError![]*Node {
+    self.advance();
+
+    var arguments = ArrayList(*Node).init(self.arena());
+    while (true) {
+        try arguments.append(try self.parseType());
+
+        if (self.closed_type_arguments) {
+            self.closed_type_arguments = false;
+            return try arguments.toOwnedSlice();
+        }
+
+        if (self.current().kind != .comma) break;
+        self.advance();
+    }
+
+    switch (self.current().kind) {
+        .greater => self.advance(),
+        .rshift => {
+            self.advance();
+            self.closed_type_arguments = true;
+        },
+        else => try self.createError("expected '>' to close type arguments")
+    }
+
+    return try arguments.toOwnedSlice();
+}
+
+/// Parses an expression whose operators bind at least as tightly as min, by
+/// precedence climbing
+fn parseBinary(self: *Parser, min: Precedence) ParseError!*Node {
+    var lhs = try self.parsePrefix();
+
+    while (true) {
+        if (self.current().kind == .assign) {
+            if (Precedence.assignment.lessThan(min)) break;
+            self.advance();
+
+            // Right associative, so `a = b = 1` assigns 1 to b first
+            const rhs = try self.parseBinary(.assignment);
+            lhs = try self.createNode(.{ .assignment = .{
+                .lhs = lhs,
+                .rhs = rhs
+            }}, lhs.span.merge(rhs.span));
+            continue;
+        }
+
+        // Casts bind tighter than the infix operators, so `a * b as i64` is a * (b as i64)
+        if (self.isKeyword(.as)) {
+            if (Precedence.cast.lessThan(min)) break;
+            self.advance();
+
+            const target = try self.parseAnnotation();
+            lhs = try self.createNode(.{ .cast = .{
+                .value = lhs,
+                .target = target
+            }}, lhs.span.merge(target.span));
+            continue;
+        }
+
+        const operator = Node.Infix.Operator.fromKind(self.current().kind) orelse break;
+        const precedence = operator.precedence();
+        if (precedence.lessThan(min)) break;
+        self.advance();
+
+        const associativity = operator.associativity();
+        const rhs = try self.parseBinary(if (associativity == .right) precedence else precedence.next());
+        lhs = try self.createNode(.{ .infix = .{
+            .operator = operator,
+            .lhs = lhs,
+            .rhs = rhs
+        }}, lhs.span.merge(rhs.span));
+
+        if (associativity == .none) {
+            const chained = Node.Infix.Operator.fromKind(self.current().kind) orelse continue;
+            if (chained.precedence() == precedence) {
+                try self.createError("operators which can't be chained need parentheses");
+            }
+        }
+    }
+
+    return lhs;
+}
+
+fn parsePrefix(self: *Parser) ParseError!*Node {
+    try self.enter();
+    defer self.leave();
+
+    const start = self.tokenSpan(self.current());
+
+    // References and dereferences bind as the prefix operators do, so `*r.x` is *(r.x)
+    const kind = self.current().kind;
+    if (kind == .ampersand or kind == .asterisk) {
+        self.advance();
+
+        const operand = try self.parseBinary(Precedence.prefix.next());
+        const node: Node.Kind = if (kind == .ampersand) .{ .reference = operand } else .{ .dereference = operand };
+        return try self.createNode(node, start.merge(operand.span));
+    }
+
+    // So do awaits, `await f() + 1` is (await f()) + 1
+    if (self.isKeyword(.@"await")) {
+        self.advance();
+
+        const operand = try self.parseBinary(Precedence.prefix.next());
+        return try self.createNode(.{ .@"await" = operand }, start.merge(operand.span));
+    }
+
+    const operator = Node.Prefix.Operator.fromKind(kind) orelse return try self.parseCall();
+    self.advance();
+
+    // Binds tighter than every infix operator except exponentiation, so `-2 ** 2` is -(2 ** 2)
+    const operand = try self.parseBinary(Precedence.prefix.next());
+    return try self.createNode(.{ .prefix = .{
+        .operator = operator,
+        .operand = operand
+    }}, start.merge(operand.span));
+}
+
+// Parses a primary followed by any calls, indexes, field accesses and ?s of it
+fn parseCall(self: *Parser) ParseError!*Node {
+    var expression = try self.parsePrimary();
+
+    while (true) switch (self.current().kind) {
+        .lparen => expression = try self.parseArguments(expression),
+        .lbracket => expression = try self.parseIndex(expression),
+        .question => {
+            self.advance();
+            expression = try self.createNode(.{ .@"try" = expression }, self.spanFrom(expression.span));
+        },
+        .dot => {
+            self.advance();
+
+            // The elements of tuples are accessed by their position, `t.0`
+            if (self.current().kind == .integer) {
+                // Integer literals are never negative, the sign is an operator
+                const position: usize = @intCast(self.current().kind.integer);
+                self.advance();
+
+                expression = try self.createNode(.{ .tuple_index = .{
+                    .object = expression,
+                    .index = position
+                }}, self.spanFrom(expression.span));
+                continue;
+            }
+
+            const field = try self.parseName("expected a field name after '.'");
+            expression = try self.createNode(.{ .field_access = .{
+                .object = expression,
+                .field = field
+            }}, self.spanFrom(expression.span));
+        },
+        else => break
+    };
+
+    return expression;
+}
+
+fn parseArguments(self: *Parser, callee: *Node) ParseError!*Node {
+    const arguments = try self.parseValues(.rparen, "expected ')' to close arguments");
+
+    return try self.createNode(.{ .fn_call = .{
+        .callee = callee,
+        .arguments = arguments
+    }}, self.spanFrom(callee.span));
+}
+
+fn parseIndex(self: *Parser, object: *Node) ParseError!*Node {
+    self.advance();
+    self.skipNewlines();
+
+    const outer = self.allowStructLiterals(true);
+    defer self.struct_literals = outer;
+
+    const index = try self.parseExpression();
+    self.skipNewlines();
+    try self.consume(.rbracket, "expected ']' to close index");
+
+    return try self.createNode(.{ .index = .{
+        .object = object,
+        .index = index
+    }}, self.spanFrom(object.span));
+}
+
+// Comma separated expressions between parentheses, or the brackets of arrays
+fn parseValues(self: *Parser, close: std.meta.Tag(Token.Kind), msg: []const u8) ParseError![]*Node {
+    self.advance();
+    self.skipNewlines();
+
+    const outer = self.allowStructLiterals(true);
+    defer self.struct_literals = outer;
+
+    var values = ArrayList(*Node).init(self.arena());
+    while (self.current().kind != close) {
+        try values.append(try self.parseExpression());
+
+        self.skipNewlines();
+        if (self.current().kind != .comma) break;
+        self.advance();
+        self.skipNewlines();
+    }
+
+    try self.consume(close, msg);
+    return try values.toOwnedSlice();
+}
+
+fn parseStructLiteral(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    const name = try self.parseName("expected a struct name");
+    const fields = try self.parseFieldValues();
+
+    return try self.createNode(.{ .struct_literal = .{
+        .name = name,
+        .fields = fields
+    }}, self.spanFrom(start));
+}
+
+// Struct style fields are only given where struct literals are allowed, so the
+// brace of a block following a condition isn't read as beginning them
+fn parseVariantLiteral(self: *Parser) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    const enum_name = try self.parseName("expected an enum name");
+    try self.consume(.double_colon, "expected '::' after enum name");
+    const variant = try self.parseName("expected a variant name after '::'");
+
+    return try self.parseVariantFields(start, enum_name, variant, true);
+}
+
+// The variants of the prelude's enums are written without their enum, `Ok(x)`
+fn parsePreludeVariant(self: *Parser, enum_name: []const u8) ParseError!*Node {
+    const start = self.tokenSpan(self.current());
+    const variant = try self.parseName("expected a variant name");
+
+    return try self.parseVariantFields(start, enum_name, variant, false);
+}
+
+fn parseVariantFields(
+    self: *Parser,
+    start: ruka.Span,
+    enum_name: []const u8,
+    variant: []const u8,
+    qualified: bool
+) ParseError!*Node {
+    const fields: Node.VariantLiteral.Fields = switch (self.current().kind) {
+        .lparen => .{ .tuple = try self.parseValues(.rparen, "expected ')' to close variant values") },
+        .lsquirly => if (self.struct_literals) .{ .@"struct" = try self.parseFieldValues() } else .unit,
+        else => .unit
+    };
+
+    return try self.createNode(.{ .variant_literal = .{
+        .@"enum" = enum_name,
+        .variant = variant,
+        .fields = fields,
+        .qualified = qualified
+    }}, self.spanFrom(start));
+}
+
+fn parseFieldValues(self: *Parser) ParseError![]Node.StructLiteral.Field {
+    self.advance();
+
+    const outer = self.allowStructLiterals(true);
+    defer self.struct_literals = outer;
+
+    var fields = ArrayList(Node.StructLiteral.Field).init(self.arena());
+
+    self.skipNewlines();
+    while (self.current().kind != .rsquirly) {
+        const field_start = self.tokenSpan(self.current());
+        const field_name = try self.parseName("expected a field name in struct literal");
+        try self.consume(.colon, "expected ':' after field name");
+
+        const value = try self.parseExpression();
+        try fields.append(.{ .name = field_name, .value = value, .span = self.spanFrom(field_start) });
+
+        self.skipNewlines();
+        if (self.current().kind != .comma) break;
+        self.advance();
+        self.skipNewlines();
+    }
+
+    try self.consume(.rsquirly, "expected '}' to close struct literal");
+    return try fields.toOwnedSlice();
+}
+
+fn parsePrimary(self: *Parser) ParseError!*Node {
+    const token = self.current();
+    const span = self.tokenSpan(token);
+
+    const kind: Node.Kind = switch (token.kind) {
+        .identifier => |id| block: {
+            switch (self.peek().kind) {
+                .double_colon => return try self.parseVariantLiteral(),
+                .lsquirly => if (self.struct_literals) return try self.parseStructLiteral(),
+                else => {}
+            }
+
+            if (Node.VariantLiteral.prelude.get(self.unit.interner.get(id))) |enum_name| {
+                return try self.parsePreludeVariant(enum_name);
+            }
+
+            break :block .{ .identifier = try self.arena().dupe(u8, self.unit.interner.get(id)) };
+        },
+        .integer => |integer| .{ .literal = .{ .integer = integer } },
+        .float => |float| .{ .literal = .{ .float = float } },
+        .string => |string| .{ .literal = .{ .string = try self.arena().dupe(u8, string.items) } },
+        .character => |character| .{ .literal = .{ .character = character } },
+        .keyword => |keyword| switch (keyword) {
+            .true => .{ .literal = .{ .boolean = true } },
+            .false => .{ .literal = .{ .boolean = false } },
+            .@"if" => return try self.parseIf(),
+            .match => return try self.parseMatch(),
+            .@"fn" => return try self.parseClosure(),
+            .@"unsafe" => return try self.parseUnsafe(),
+            .sizeof => return try self.parseSizeof(),
+            else => {
+                try self.createError("expected an expression");
+                unreachable;
+            }
+        },
+        .lparen => {
+            self.advance();
+
+            const outer = self.allowStructLiterals(true);
+            defer self.struct_literals = outer;
+
+            // The empty tuple is the unit value
+            if (self.current().kind == .rparen) {
+                self.advance();
+                return try self.createNode(.{ .literal = .unit }, self.spanFrom(span));
+            }
+
+            const expression = try self.parseExpression();
+            if (self.current().kind != .comma) {
+                try self.consume(.rparen, "expected ')' to close grouped expression");
+                return expression;
+            }
+
+            // A trailing comma makes a tuple of a single value, `(1,)`
+            var elements = ArrayList(*Node).init(self.arena());
+            try elements.append(expression);
+            while (self.current().kind == .comma) {
+                self.advance();
+                self.skipNewlines();
+                if (self.current().kind == .rparen) break;
+
+                try elements.append(try self.parseExpression());
+                self.skipNewlines();
+            }
+
+            try self.consume(.rparen, "expected ')' to close tuple");
+            return try self.createNode(.{ .tuple_literal = try elements.toOwnedSlice() }, self.spanFrom(span));
+        },
+        .mode => |mode| switch (mode) {
+            .@"comptime" => return try self.parseComptime(),
+            else => {
+                try self.createError("expected an expression");
+                unreachable;
+            }
+        },
+        .lbracket => {
+            const elements = try self.parseValues(.rbracket, "expected ']' to close array");
+            return try self.createNode(.{ .array_literal = elements }, self.spanFrom(span));
+        },
+        else => {
+            try self.createError("expected an expression");
+            unreachable;
+        }
+    };
+
+    self.advance();
+    return try self.createNode(kind, span);
+}
+
+test "parser modules" {
+    _ = tests;
+    _ = Ast;
+    _ = AstBuilder;
+    _ = AstReader;
+    _ = CaptureAnalyzer;
+    _ = bincode;
+    _ = visitor;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    fn parseSource(unit: *Unit) !*Ast {
+        var scanner = try ruka.Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        var parser = try Parser.init(unit, tokens.items);
+        defer parser.deinit();
+
+        return try parser.parse();
+    }
+
+    test "bindings and expressions" {
+        const source =
+            \\let x = 1 + 2
+            \\
+            \\const y = (x); x * y
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        var out = ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        try ast.write(out.writer().any());
+        try testing.expectEqualStrings(
+            \\let x = (1 + 2)
+            \\const y = x
+            \\(x * y)
+            \\
+        , out.items);
+    }
+
+    test "tree snapshot" {
+        const source =
+            \\let x = 1_000 + 2.5
+            \\var s = "hi\\n"
+            \\const y = (x) * true
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        var out = ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        try out.writer().print("{}", .{ast});
+        try testing.expectEqualStrings(
+            \\module test source
+            \\  var_decl x
+            \\    infix +
+            \\      integer 1000
+            \\      float 2.5
+            \\  var_decl s mutable
+            \\    string "hi\\n"
+            \\  const_decl y
+            \\    infix *
+            \\      identifier x
+            \\      boolean true
+            \\
+        , out.items);
+    }
+
+    test "imports and exports" {
+        const source =
+            \\import std.math as m
+            \\import util
+            \\pub const x = 1
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        var out = ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        try out.writer().print("{}", .{ast});
+        try testing.expectEqualStrings(
+            \\module test source
+            \\  import std.math as m
+            \\  import util
+            \\  export
+            \\    const_decl x
+            \\      integer 1
+            \\
+        , out.items);
+    }
+
+    test "doc comments are passed over" {
+        const source =
+            \\/// The answer
+            \\let x = 42 /// trailing
+            \\///
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        const items = ast.root.kind.module.items;
+        try testing.expectEqual(1, items.len);
+        try testing.expectEqual(10, items[0].span.end - items[0].span.start);
+    }
+
+    test "pub must precede a declaration" {
+        const source =
+            \\pub 1 + 2
+            \\import
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("expected a declaration after pub", unit.diagnostics.items[0].message);
+    }
+
+    test "written source parses to the same tree" {
+        const source =
+            \\let x = 1 + 2 * 3
+            \\var c = 'c'; const y = (x - 1.25) == 4
+            \\struct P { x: f64 }; let p = P { x: 1.0 }.x; struct Q<T> { t: T }
+            \\if (P { x: 2.0 }).x == p {}
+            \\enum E { A, B(i64), C { x: i64 } }; let e = E::C { x: 1 }
+            \\fn f(g: fn(i64, (f64, bool)) -> &[u8], o: Option<Vec<(i64,)>>) -> fn() {}
+            \\let r = Ok(-f()?.y); let s = Err(r?)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        var written = ArrayList(u8).init(testing.allocator);
+        defer written.deinit();
+        try ast.write(written.writer().any());
+
+        var reinput = std.io.fixedBufferStream(written.items);
+
+        var reunit = try Unit.init(.testing(reinput.reader().any(), output.writer().any()));
+        defer reunit.deinit();
+
+        const reparsed = try parseSource(reunit);
+        defer reparsed.deinit();
+
+        var expected = ArrayList(u8).init(testing.allocator);
+        defer expected.deinit();
+        try expected.writer().print("{}", .{ast});
+
+        var actual = ArrayList(u8).init(testing.allocator);
+        defer actual.deinit();
+        try actual.writer().print("{}", .{reparsed});
+
+        try testing.expectEqualStrings(expected.items, actual.items);
+    }
+
+    // Parses source, expecting the tree of its module to be expected
+    fn expectTree(source: []const u8, expected: []const u8) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        var out = ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        try out.writer().print("{}", .{ast});
+        try testing.expectEqualStrings(expected, out.items);
+    }
+
+    test "operator precedence" {
+        try expectTree("1 + 2 * 3",
+            \\module test source
+            \\  infix +
+            \\    integer 1
+            \\    infix *
+            \\      integer 2
+            \\      integer 3
+            \\
+        );
+        try expectTree("a or b and c | d ^ e & f == g < h << i - j % k",
+            \\module test source
+            \\  infix or
+            \\    identifier a
+            \\    infix and
+            \\      identifier b
+            \\      infix |
+            \\        identifier c
+            \\        infix ^
+            \\          identifier d
+            \\          infix &
+            \\            identifier e
+            \\            infix ==
+            \\              identifier f
+            \\              infix <
+            \\                identifier g
+            \\                infix <<
+            \\                  identifier h
+            \\                  infix -
+            \\                    identifier i
+            \\                    infix %
+            \\                      identifier j
+            \\                      identifier k
+            \\
+        );
+    }
+
+    test "operator associativity" {
+        try expectTree("1 - 2 - 3",
+            \\module test source
+            \\  infix -
+            \\    infix -
+            \\      integer 1
+            \\      integer 2
+            \\    integer 3
+            \\
+        );
+        try expectTree("2 ** 3 ** 2",
+            \\module test source
+            \\  infix **
+            \\    integer 2
+            \\    infix **
+            \\      integer 3
+            \\      integer 2
+            \\
+        );
+        try expectTree("a = b = 1",
+            \\module test source
+            \\  assignment
+            \\    identifier a
+            \\    assignment
+            \\      identifier b
+            \\      integer 1
+            \\
+        );
+    }
+
+    test "prefix operators" {
+        try expectTree("!-x",
+            \\module test source
+            \\  prefix !
+            \\    prefix -
+            \\      identifier x
+            \\
+        );
+        try expectTree("-2 ** 2 * 3",
+            \\module test source
+            \\  infix *
+            \\    prefix -
+            \\      infix **
+            \\        integer 2
+            \\        integer 2
+            \\    integer 3
+            \\
+        );
+    }
+
+    test "comparisons can't be chained" {
+        var input = std.io.fixedBufferStream("1 < 2 < 3\n(1 < 2) < 3");
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqual(ruka.Position.init(1, 7), unit.diagnostics.items[0].span.pos);
+    }
+
+    test "functions" {
+        try expectTree(
+            \\fn add(a: i64, b: i64) -> i64 {
+            \\    return a + b
+            \\}
+            \\fn nothing() {}
+        ,
+            \\module test source
+            \\  fn_def add
+            \\    parameter a
+            \\      identifier i64
+            \\    parameter b
+            \\      identifier i64
+            \\    returns
+            \\      identifier i64
+            \\    block
+            \\      return
+            \\        infix +
+            \\          identifier a
+            \\          identifier b
+            \\  fn_def nothing
+            \\    block
+            \\
+        );
+    }
+
+    test "recursive functions" {
+        try expectTree("fn fact(n: i64) -> i64 { n * fact(n - 1) }",
+            \\module test source
+            \\  fn_def fact
+            \\    parameter n
+            \\      identifier i64
+            \\    returns
+            \\      identifier i64
+            \\    block
+            \\      infix *
+            \\        identifier n
+            \\        fn_call
+            \\          identifier fact
+            \\          infix -
+            \\            identifier n
+            \\            integer 1
+            \\
+        );
+    }
+
+    test "generic functions and parameter lists spanning lines" {
+        try expectTree(
+            \\pub fn pick<T, U>(
+            \\    first: T,
+            \\    second: U
+            \\) -> T {
+            \\    let x = first; x
+            \\}
+        ,
+            \\module test source
+            \\  export
+            \\    fn_def pick
+            \\      type_param T
+            \\      type_param U
+            \\      parameter first
+            \\        identifier T
+            \\      parameter second
+            \\        identifier U
+            \\      returns
+            \\        identifier T
+            \\      block
+            \\        var_decl x
+            \\          identifier first
+            \\        identifier x
+            \\
+        );
+    }
+
+    test "names defined twice in the same scope" {
+        const source =
+            \\fn f() {}
+            \\fn f(x, x) {}
+            \\fn g() { fn g() {} }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("function f is already defined in this scope", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("parameter x is already defined in this scope", unit.diagnostics.items[1].message);
+        try testing.expectEqual(ruka.Position.init(2, 9), unit.diagnostics.items[1].span.pos);
+    }
+
+    test "bindings" {
+        try expectTree(
+            \\let x: i32 = 1
+            \\var y = x
+            \\var z: f64
+            \\const w = 2
+        ,
+            \\module test source
+            \\  var_decl x
+            \\    type
+            \\      identifier i32
+            \\    integer 1
+            \\  var_decl y mutable
+            \\    identifier x
+            \\  var_decl z mutable
+            \\    type
+            \\      identifier f64
+            \\  const_decl w
+            \\    integer 2
+            \\
+        );
+    }
+
+    test "bindings require a value unless annotated" {
+        const source =
+            \\let x
+            \\const y: i32
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("expected '=' after binding identifier", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings(
+            "expected '=' after constant identifier, constants must have a value",
+            unit.diagnostics.items[1].message
+        );
+    }
+
+    test "bindings shadow earlier bindings" {
+        const source =
+            \\let x = 1
+            \\fn f(x) {
+            \\    let x = x + 1
+            \\    fn g() { let x = 2; x }
+            \\    x
+            \\}
+            \\var x = x
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        // Only rebinding x in the same scope is warned about
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqual(.warning, unit.diagnostics.items[0].severity);
+        try testing.expectEqualStrings(
+            "binding x is already defined in this scope, and is shadowed",
+            unit.diagnostics.items[0].message
+        );
+        try testing.expectEqual(ruka.Position.init(7, 5), unit.diagnostics.items[0].span.pos);
+    }
+
+    test "bindings can't rebind other names" {
+        const source =
+            \\fn f() {}
+            \\let f = 1
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqualStrings("binding f is already defined in this scope", unit.diagnostics.items[0].message);
+    }
+
+    test "if expressions" {
+        try expectTree(
+            \\let x = if a { 1 } else { 2 }
+            \\if b { f() }
+        ,
+            \\module test source
+            \\  var_decl x
+            \\    if
+            \\      identifier a
+            \\      block
+            \\        integer 1
+            \\      else
+            \\        block
+            \\          integer 2
+            \\  if
+            \\    identifier b
+            \\    block
+            \\      fn_call
+            \\        identifier f
+            \\
+        );
+    }
+
+    test "else ifs are nested ifs" {
+        const source = "if a { 1 } else if b { 2 } else { 3 }";
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        const outer = ast.root.kind.module.items[0];
+        try testing.expectEqual(0, outer.span.start);
+        try testing.expectEqual(source.len, outer.span.end);
+
+        const inner = outer.kind.@"if".else_block.?;
+        try testing.expect(inner.kind == .@"if");
+        try testing.expectEqual(16, inner.span.start);
+        try testing.expect(inner.kind.@"if".else_block.?.kind == .block);
+    }
+
+    test "loops" {
+        try expectTree(
+            \\while true { break }
+            \\for i in 0..10 {
+            \\    continue
+            \\    break i
+            \\}
+        ,
+            \\module test source
+            \\  while
+            \\    boolean true
+            \\    block
+            \\      break
+            \\  for i
+            \\    infix ..
+            \\      integer 0
+            \\      integer 10
+            \\    block
+            \\      continue
+            \\      break
+            \\        identifier i
+            \\
+        );
+    }
+
+    test "defers" {
+        try expectTree(
+            \\fn f() {
+            \\    defer close(file)
+            \\    defer { flush() }
+            \\}
+        ,
+            \\module test source
+            \\  fn_def f
+            \\    block
+            \\      defer
+            \\        fn_call
+            \\          identifier close
+            \\          identifier file
+            \\      defer
+            \\        block
+            \\          fn_call
+            \\            identifier flush
+            \\
+        );
+    }
+
+    test "labels and gotos" {
+        try expectTree(
+            \\fn f() {
+            \\    again:
+            \\    x = 1; done:
+            \\    goto again
+            \\}
+        ,
+            \\module test source
+            \\  fn_def f
+            \\    block
+            \\      label again
+            \\      assignment
+            \\        identifier x
+            \\        integer 1
+            \\      label done
+            \\      goto again
+            \\
+        );
+    }
+
+    test "for loops require in" {
+        var input = std.io.fixedBufferStream("for i 0..10 {}");
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("expected in after the loop variable", unit.diagnostics.items[0].message);
+    }
+
+    test "match expressions" {
+        try expectTree(
+            \\let size = match n {
+            \\    0 => "none"
+            \\    1 | 2 => "few", -3 => "few"
+            \\    m if m > 10 => { "many" }
+            \\    _ => "some"
+            \\}
+        ,
+            \\module test source
+            \\  var_decl size
+            \\    match
+            \\      identifier n
+            \\      arm 0
+            \\        string "none"
+            \\      arm 1 | 2
+            \\        string "few"
+            \\      arm -3
+            \\        string "few"
+            \\      arm m
+            \\        guard
+            \\          infix >
+            \\            identifier m
+            \\            integer 10
+            \\        block
+            \\          string "many"
+            \\      arm _
+            \\        string "some"
+            \\
+        );
+    }
+
+    test "nested patterns" {
+        try expectTree(
+            \\match shape {
+            \\    Shape::Circle(0.0) => 0
+            \\    Shape::Rect(w, (h, _)) => w
+            \\    Point { x: 0, y } => y
+            \\    (Some(a), (b,), ()) => a
+            \\    Shape::Empty => 1
+            \\    Event::KeyPress { key: 'a', modifier } => 2
+            \\}
+        ,
+            \\module test source
+            \\  match
+            \\    identifier shape
+            \\    arm Shape::Circle(0.0)
+            \\      integer 0
+            \\    arm Shape::Rect(w, (h, _))
+            \\      identifier w
+            \\    arm Point { x: 0, y }
+            \\      identifier y
+            \\    arm (Some(a), (b,), ())
+            \\      identifier a
+            \\    arm Shape::Empty
+            \\      integer 1
+            \\    arm Event::KeyPress { key: 'a', modifier }
+            \\      integer 2
+            \\
+        );
+    }
+
+    test "pattern bindings are scoped to their arm" {
+        const source =
+            \\let x = 1
+            \\match x {
+            \\    x => x
+            \\    (y, y) => y
+            \\}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqual(ruka.Position.init(4, 9), unit.diagnostics.items[0].span.pos);
+    }
+
+    test "structs and impls" {
+        try expectTree(
+            \\struct Point { x: f64, y: f64 }
+            \\impl Point {
+            \\    fn norm(self: Point) -> f64 { self.x }
+            \\}
+        ,
+            \\module test source
+            \\  struct_def Point
+            \\    field x
+            \\      identifier f64
+            \\    field y
+            \\      identifier f64
+            \\  impl Point
+            \\    fn_def norm
+            \\      parameter self
+            \\        identifier Point
+            \\      returns
+            \\        identifier f64
+            \\      block
+            \\        field_access x
+            \\          identifier self
+            \\
+        );
+    }
+
+    test "self parameters of impl functions" {
+        try expectTree(
+            \\impl Point {
+            \\    fn moved(self, dx: f64) {}
+            \\    fn len(&self) {}
+            \\}
+            \\fn free(self) {}
+        ,
+            \\module test source
+            \\  impl Point
+            \\    fn_def moved
+            \\      parameter self
+            \\        identifier Point
+            \\      parameter dx
+            \\        identifier f64
+            \\      block
+            \\    fn_def len
+            \\      parameter self
+            \\        type_expr reference
+            \\          identifier Point
+            \\      block
+            \\  fn_def free
+            \\    parameter self
+            \\    block
+            \\
+        );
+    }
+
+    test "traits" {
+        try expectTree(
+            \\trait Printable { fn print(&self); fn description(&self) -> str { "default" } }
+            \\impl Printable for Point {
+            \\    fn print(&self) {}
+            \\}
+            \\fn show(x: impl Printable) {}
+        ,
+            \\module test source
+            \\  trait_def Printable
+            \\    method print
+            \\      parameter self
+            \\        type_expr reference
+            \\          type_expr impl Printable
+            \\    fn_def description
+            \\      parameter self
+            \\        type_expr reference
+            \\          type_expr impl Printable
+            \\      returns
+            \\        identifier str
+            \\      block
+            \\        string "default"
+            \\  impl Printable for Point
+            \\    fn_def print
+            \\      parameter self
+            \\        type_expr reference
+            \\          identifier Point
+            \\      block
+            \\  fn_def show
+            \\    parameter x
+            \\      type_expr impl Printable
+            \\    block
+            \\
+        );
+    }
+
+    test "async functions and awaits" {
+        try expectTree(
+            \\async fn add(a: i32, b: i32) -> i32 { a + b }
+            \\async fn twice() -> i32 { await add(1, 2) * 2 }
+        ,
+            \\module test source
+            \\  fn_def add async
+            \\    parameter a
+            \\      identifier i32
+            \\    parameter b
+            \\      identifier i32
+            \\    returns
+            \\      identifier i32
+            \\    block
+            \\      infix +
+            \\        identifier a
+            \\        identifier b
+            \\  fn_def twice async
+            \\    returns
+            \\      identifier i32
+            \\    block
+            \\      infix *
+            \\        await
+            \\          fn_call
+            \\            identifier add
+            \\            integer 1
+            \\            integer 2
+            \\        integer 2
+            \\
+        );
+    }
+
+    test "unions" {
+        try expectTree(
+            \\union Bits { i: i32, f: f32 }
+            \\let b = Bits { f: 1.0 }
+        ,
+            \\module test source
+            \\  union_def Bits
+            \\    field i
+            \\      identifier i32
+            \\    field f
+            \\      identifier f32
+            \\  var_decl b
+            \\    struct_literal Bits
+            \\      field f
+            \\        float 1.0
+            \\
+        );
+    }
+
+    test "generic structs" {
+        try expectTree(
+            \\struct Pair<A, B> { first: A, second: B }
+            \\fn swap<T>(p: Pair<T, T>) -> Pair<T, T> { Pair { first: p.second, second: p.first } }
+        ,
+            \\module test source
+            \\  struct_def Pair
+            \\    type_param A
+            \\    type_param B
+            \\    field first
+            \\      identifier A
+            \\    field second
+            \\      identifier B
+            \\  fn_def swap
+            \\    type_param T
+            \\    parameter p
+            \\      type_expr generic Pair
+            \\        identifier T
+            \\        identifier T
+            \\    returns
+            \\      type_expr generic Pair
+            \\        identifier T
+            \\        identifier T
+            \\    block
+            \\      struct_literal Pair
+            \\        field first
+            \\          field_access second
+            \\            identifier p
+            \\        field second
+            \\          field_access first
+            \\            identifier p
+            \\
+        );
+    }
+
+    test "struct literals and field accesses" {
+        try expectTree(
+            \\let d = Line { start: Point { x: 1.0, y: 2.0 }, end: p }.start.x
+            \\if a.b.c { Point {} }
+        ,
+            \\module test source
+            \\  var_decl d
+            \\    field_access x
+            \\      field_access start
+            \\        struct_literal Line
+            \\          field start
+            \\            struct_literal Point
+            \\              field x
+            \\                float 1.0
+            \\              field y
+            \\                float 2.0
+            \\          field end
+            \\            identifier p
+            \\  if
+            \\    field_access c
+            \\      field_access b
+            \\        identifier a
+            \\    block
+            \\      struct_literal Point
+            \\
+        );
+    }
+
+    test "struct fields are named once and impls only hold functions" {
+        const source =
+            \\struct P { x: i64, x: f64 }
+            \\impl P { let x = 1 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("field x is already defined in this struct", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("expected a function in impl block", unit.diagnostics.items[1].message);
+    }
+
+    test "enums and their variants" {
+        try expectTree(
+            \\enum Color { Red, Green, Blue }
+            \\enum Shape {
+            \\    Circle(f64)
+            \\    Rect(f64, f64)
+            \\}
+            \\enum Event { KeyPress { key: u8 } }
+            \\let s = Shape::Circle(1.0)
+            \\let e = Event::KeyPress { key: 65 }
+            \\if c == Color::Red { 1 }
+        ,
+            \\module test source
+            \\  enum_def Color
+            \\    variant Red
+            \\    variant Green
+            \\    variant Blue
+            \\  enum_def Shape
+            \\    variant Circle
+            \\      identifier f64
+            \\    variant Rect
+            \\      identifier f64
+            \\      identifier f64
+            \\  enum_def Event
+            \\    variant KeyPress
+            \\      field key
+            \\        identifier u8
+            \\  var_decl s
+            \\    variant_literal Shape::Circle
+            \\      float 1.0
+            \\  var_decl e
+            \\    variant_literal Event::KeyPress
+            \\      field key
+            \\        integer 65
+            \\  if
+            \\    infix ==
+            \\      identifier c
+            \\      variant_literal Color::Red
+            \\    block
+            \\      integer 1
+            \\
+        );
+    }
+
+    test "the ? operator and the prelude's variants" {
+        try expectTree(
+            \\fn parse(s: str) -> Result<i64, str> { Err(s) }
+            \\let x = -parse("1")?.value
+            \\let y = Ok(x)
+            \\let z = match y { Some(v) => v?, None => None }
+        ,
+            \\module test source
+            \\  fn_def parse
+            \\    parameter s
+            \\      identifier str
+            \\    returns
+            \\      type_expr generic Result
+            \\        identifier i64
+            \\        identifier str
+            \\    block
+            \\      variant_literal Result::Err
+            \\        identifier s
+            \\  var_decl x
+            \\    prefix -
+            \\      field_access value
+            \\        try
+            \\          fn_call
+            \\            identifier parse
+            \\            string "1"
+            \\  var_decl y
+            \\    variant_literal Result::Ok
+            \\      identifier x
+            \\  var_decl z
+            \\    match
+            \\      identifier y
+            \\      arm Some(v)
+            \\        try
+            \\          identifier v
+            \\      arm None
+            \\        variant_literal Option::None
+            \\
+        );
+    }
+
+    test "enum variants are named once" {
+        const source =
+            \\enum E { A, B(i64), A }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("variant A is already defined in this enum", unit.diagnostics.items[0].message);
+    }
+
+    test "type expressions" {
+        try expectTree(
+            \\let f: fn(i32, f64) -> bool = g
+            \\let r: Result<Vec<i32>, str> = x
+            \\let b: &[u8] = y
+            \\let a: [i64; 3] = z
+        ,
+            \\module test source
+            \\  var_decl f
+            \\    type
+            \\      type_expr function
+            \\        identifier i32
+            \\        identifier f64
+            \\        returns
+            \\          identifier bool
+            \\    identifier g
+            \\  var_decl r
+            \\    type
+            \\      type_expr generic Result
+            \\        type_expr generic Vec
+            \\          identifier i32
+            \\        identifier str
+            \\    identifier x
+            \\  var_decl b
+            \\    type
+            \\      type_expr reference
+            \\        type_expr slice
+            \\          identifier u8
+            \\    identifier y
+            \\  var_decl a
+            \\    type
+            \\      type_expr array
+            \\        identifier i64
+            \\        len
+            \\          integer 3
+            \\    identifier z
+            \\
+        );
+    }
+
+    test "array literals and indexes" {
+        try expectTree("let a = [1, 2,\n    3]\na[0] + a[1..3][0]",
+            \\module test source
+            \\  var_decl a
+            \\    array_literal
+            \\      integer 1
+            \\      integer 2
+            \\      integer 3
+            \\  infix +
+            \\    index
+            \\      identifier a
+            \\      integer 0
+            \\    index
+            \\      index
+            \\        identifier a
+            \\        infix ..
+            \\          integer 1
+            \\          integer 3
+            \\      integer 0
+            \\
+        );
+    }
+
+    test "tuple literals and positions" {
+        try expectTree("let t = (1, (\"a\", true))\nt.1.0\n(42,)\n(42)\n()",
+            \\module test source
+            \\  var_decl t
+            \\    tuple_literal
+            \\      integer 1
+            \\      tuple_literal
+            \\        string "a"
+            \\        boolean true
+            \\  tuple_index 0
+            \\    tuple_index 1
+            \\      identifier t
+            \\  tuple_literal
+            \\    integer 42
+            \\  integer 42
+            \\  unit
+            \\
+        );
+    }
+
+    test "references and dereferences" {
+        try expectTree("let r = &x\n*r = *r * 2\nf(&p.x, *q.y)",
+            \\module test source
+            \\  var_decl r
+            \\    reference
+            \\      identifier x
+            \\  assignment
+            \\    dereference
+            \\      identifier r
+            \\    infix *
+            \\      dereference
+            \\        identifier r
+            \\      integer 2
+            \\  fn_call
+            \\    identifier f
+            \\    reference
+            \\      field_access x
+            \\        identifier p
+            \\    dereference
+            \\      field_access y
+            \\        identifier q
+            \\
+        );
+    }
+
+    test "extern functions and unsafe blocks" {
+        try expectTree("extern fn printf(fmt: &str, ...) -> i32\nextern fn abort()\nlet f: fn(str, ...) = g\nunsafe { abort() }",
+            \\module test source
+            \\  extern_fn printf variadic
+            \\    parameter fmt
+            \\      type_expr reference
+            \\        identifier str
+            \\    returns
+            \\      identifier i32
+            \\  extern_fn abort
+            \\  var_decl f
+            \\    type
+            \\      type_expr function
+            \\        identifier str
+            \\        type_expr variadic
+            \\    identifier g
+            \\  unsafe
+            \\    block
+            \\      fn_call
+            \\        identifier abort
+            \\
+        );
+    }
+
+    test "comptime blocks" {
+        try expectTree("let s = comptime { sizeof(i64) } * 2",
+            \\module test source
+            \\  var_decl s
+            \\    infix *
+            \\      comptime
+            \\        block
+            \\          sizeof
+            \\            identifier i64
+            \\      integer 2
+            \\
+        );
+    }
+
+    test "casts" {
+        try expectTree("let c = -x as i32 * 2 + y as f64 as i64",
+            \\module test source
+            \\  var_decl c
+            \\    infix +
+            \\      infix *
+            \\        cast
+            \\          prefix -
+            \\            identifier x
+            \\          as
+            \\            identifier i32
+            \\        integer 2
+            \\      cast
+            \\        cast
+            \\          identifier y
+            \\          as
+            \\            identifier f64
+            \\        as
+            \\          identifier i64
+            \\
+        );
+    }
+
+    test "sizeof" {
+        try expectTree("let s = sizeof(i64) + sizeof(&[u8])",
+            \\module test source
+            \\  var_decl s
+            \\    infix +
+            \\      sizeof
+            \\        identifier i64
+            \\      sizeof
+            \\        type_expr reference
+            \\          type_expr slice
+            \\            identifier u8
+            \\
+        );
+    }
+
+    test "destructuring bindings" {
+        try expectTree("let (a, _) = (1, 2)\nlet Point { x: px, y }: Point = p\nlet Some(v) = o\nlet f = g(1)",
+            \\module test source
+            \\  destructure (a, _)
+            \\    tuple_literal
+            \\      integer 1
+            \\      integer 2
+            \\  destructure Point { x: px, y }
+            \\    type
+            \\      identifier Point
+            \\    identifier p
+            \\  destructure Some(v)
+            \\    identifier o
+            \\  var_decl f
+            \\    fn_call
+            \\      identifier g
+            \\      integer 1
+            \\
+        );
+    }
+
+    test "nested type arguments and parenthesized types" {
+        try expectTree("fn f(a: Option<Vec<i32>>, b: (i64,), c: (i64), d: ()) {}",
+            \\module test source
+            \\  fn_def f
+            \\    parameter a
+            \\      type_expr generic Option
+            \\        type_expr generic Vec
+            \\          identifier i32
+            \\    parameter b
+            \\      type_expr tuple
+            \\        identifier i64
+            \\    parameter c
+            \\      identifier i64
+            \\    parameter d
+            \\      unit
+            \\    block
+            \\
+        );
+    }
+
+    test "type expressions must be closed" {
+        const source =
+            \\let a: Vec<i32>> = x
+            \\let b: [i32 = y
+            \\let c: Option<i32 = z
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("unexpected '>' after type", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("expected ']' to close slice type", unit.diagnostics.items[1].message);
+        try testing.expectEqualStrings("expected '>' to close type arguments", unit.diagnostics.items[2].message);
+    }
+
+    test "errors within blocks are recovered from" {
+        const source =
+            \\fn f() {
+            \\    let = 1
+            \\    let y = 2 3
+            \\    y
+            \\}
+            \\let z = 1
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+    }
+
+    test "errors are accumulated" {
+        const source =
+            \\let = 1
+            \\let y 2
+            \\let z = 3 4
+            \\z
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+        try testing.expectEqual(ruka.Position.init(1, 5), unit.diagnostics.items[0].span.pos);
+    }
+
+    test "attributes" {
+        var input = std.io.fixedBufferStream(
+            \\#[allow(dead_code)]
+            \\#[deny(shadowing, overflow)]
+            \\pub fn f() {}
+        );
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try parseSource(unit);
+        defer ast.deinit();
+
+        const attributes = ast.root.kind.module.items[0].attributes;
+        try testing.expectEqual(2, attributes.len);
+        try testing.expectEqualStrings("allow", attributes[0].name);
+        try testing.expectEqualStrings("dead_code", attributes[0].args[0]);
+        try testing.expectEqualStrings("deny", attributes[1].name);
+        try testing.expectEqual(2, attributes[1].args.len);
+        try testing.expectEqual(3, unit.lint_levels.items.len);
+    }
+
+    test "unknown attributes and those not applied to declarations" {
+        var input = std.io.fixedBufferStream(
+            \\#[always_inline]
+            \\fn f() {}
+            \\#[allow(everything)]
+            \\let x = 1
+            \\#[allow(dead_code)]
+            \\x + 1
+        );
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+
+        const diagnostics = unit.diagnostics.items;
+        try testing.expectEqual(3, diagnostics.len);
+        try testing.expectEqualStrings("unknown attribute always_inline, expected allow, deny or test", diagnostics[0].message);
+        try testing.expectEqualStrings("unknown lint everything", diagnostics[1].message);
+        try testing.expectEqualStrings("attributes can only be applied to declarations", diagnostics[2].message);
+        try testing.expectEqual(ruka.Position.init(5, 1), diagnostics[2].span.pos);
+    }
+
+    test "test attributes" {
+        {
+            var input = std.io.fixedBufferStream(
+                \\#[test]
+                \\fn adds() {}
+            );
+
+            var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+            defer unit.deinit();
+
+            const ast = try parseSource(unit);
+            defer ast.deinit();
+
+            const attributes = ast.root.kind.module.items[0].attributes;
+            try testing.expectEqual(1, attributes.len);
+            try testing.expectEqualStrings("test", attributes[0].name);
+            try testing.expectEqual(0, attributes[0].args.len);
+        }
+
+        var input = std.io.fixedBufferStream(
+            \\#[test]
+            \\let x = 1
+            \\#[test(slow)]
+            \\fn f() {}
+        );
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+
+        const diagnostics = unit.diagnostics.items;
+        try testing.expectEqual(2, diagnostics.len);
+        try testing.expectEqualStrings("the test attribute can only be applied to functions", diagnostics[0].message);
+        try testing.expectEqualStrings("the test attribute takes no arguments", diagnostics[1].message);
+    }
+
+    test "deeply nested input is reported rather than parsed" {
+        const depth = max_depth + 10;
+        const source = "let x = " ++ "(" ** depth ++ "1" ++ ")" ** depth ++ "\n" ++
+            "let y = " ++ "- " ** depth ++ "1\n" ++
+            "let z: " ++ "[" ** depth ++ "i64" ++ "]" ** depth ++ " = 1\n";
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ParsingFailed, parseSource(unit));
+
+        var reported: usize = 0;
+        for (unit.diagnostics.items) |diagnostic| {
+            if (std.mem.eql(u8, diagnostic.message, "nested too deeply to parse")) reported = reported + 1;
+        }
+        try testing.expectEqual(3, reported);
+    }
 };
@@ -3,335 +3,427 @@
 
 const std = @import("std");
 const Allocator = std.mem.Allocator;
+const AnyReader = std.io.AnyReader;
 const AnyWriter = std.io.AnyWriter;
+const ArrayList = std.ArrayList;
 const Termios = std.posix.termios;
-const builtin = @import("builtin");
 
 const ruka = @import("prelude.zig");
+const Diagnostic = ruka.Diagnostic;
+const Environment = ruka.Environment;
 const Transport = ruka.Transport;
+const Unit = ruka.Unit;
+const constants = ruka.constants;
 
-original: Termios = undefined,
-raw: Termios = undefined,
+/// The lines entered, oldest first
+history: ArrayList([]const u8),
+/// Holds the bindings of every line evaluated since the last reset
+env: *Environment,
 
-utf8_supported: bool,
-status: enum {uninitialized, initialized, running, exiting_success, exiting_failure},
+input: *Transport,
+output: *Transport,
+/// Set when the input is a terminal, which is put in raw mode so lines can be edited
+terminal: ?Terminal,
+/// Whether diagnostics are colored
+color: bool,
+
+status: enum {running, exiting},
 
 allocator: Allocator,
 
 const Repl = @This();
 
-var transport: *Transport = undefined;
-var size: Size = undefined;
-var position: Position = undefined;
+const prompt = "> ";
+
+const help =
+    \\Enter statements to evaluate them, their bindings persist between lines
+    \\
+    \\    commands:
+    \\        :help  : Displays this message
+    \\        :reset : Discards every binding
+    \\        :quit  : Exits the repl
+    \\
+;
+
+const Command = enum {
+    help,
+    reset,
+    quit
+};
 
-pub fn init(allocator: Allocator) !*Repl {
-    const terminal = try allocator.create(Repl);
-    errdefer allocator.destroy(terminal);
+const commandsMap = std.StaticStringMap(Command).initComptime(.{
+    .{"help", .help},
+    .{"reset", .reset},
+    .{"quit", .quit},
+    .{"q", .quit}
+});
 
-    const stdin = std.io.getStdIn();
-    transport = try Transport.initWithFile(allocator, stdin);
-    errdefer transport.deinit();
-
-    terminal.* = .{
-        .original = undefined,
-        .raw = undefined,
-        .utf8_supported = try is_utf8_supported(),
-        .status = .uninitialized,
-        .allocator = allocator
-    };
+const Terminal = struct {
+    original: Termios,
+    raw: Termios
+};
 
-    try terminal.tty_setup();
+const Key = enum {
+    up,
+    down,
+    left,
+    right,
+    other
+};
 
-    return terminal;
-}
+// Control characters, as read from a terminal in raw mode
+const ctrl_a = 'a' & '\x1F';
+const ctrl_c = 'c' & '\x1F';
+const ctrl_d = 'd' & '\x1F';
+const ctrl_e = 'e' & '\x1F';
+const ctrl_h = 'h' & '\x1F';
+const delete = '\x7F';
 
-pub fn deinit(self: *Repl) void {
-    self.allocator.destroy(transport);
-    self.allocator.destroy(self);
-}
+/// Creates a repl reading lines from reader and writing results to writer
+pub fn init(allocator: Allocator, reader: AnyReader, writer: AnyWriter) !*Repl {
+    const input = try Transport.init(allocator, reader, null);
+    errdefer input.deinit();
 
-fn tty_setup(self: *Repl) !void {
-    self.original = try std.posix.tcgetattr(transport.getHandle());
-    self.raw = self.original;
+    const output = try Transport.init(allocator, null, writer);
+    errdefer output.deinit();
 
-    self.raw.lflag.ECHO = false;
-    self.raw.lflag.ICANON = false;
-    self.raw.lflag.ISIG = false;
-    self.raw.lflag.IEXTEN = false;
+    return try create(allocator, input, output);
+}
 
-    self.raw.iflag.IXON = false;
-    self.raw.iflag.ICRNL = false;
-    self.raw.iflag.BRKINT = false;
-    self.raw.iflag.INPCK = false;
-    self.raw.iflag.ISTRIP = false;
+/// Creates a repl over stdin and stdout. Lines can be edited, and the history
+/// browsed with the arrow keys, when stdin is a terminal
+pub fn initTerminal(allocator: Allocator) !*Repl {
+    const stdin = std.io.getStdIn();
+    const stdout = std.io.getStdOut();
 
-    self.raw.oflag.OPOST = false;
+    const terminal = if (std.posix.isatty(stdin.handle)) try rawMode(stdin.handle) else null;
 
-    self.raw.cflag.CSIZE = .CS8;
+    const input = try Transport.initWithFile(allocator, stdin);
+    errdefer input.deinit();
 
-    self.raw.cc[@intFromEnum(std.posix.system.V.TIME)] = 0;
-    self.raw.cc[@intFromEnum(std.posix.system.V.MIN)] = 1;
+    const output = try Transport.initWithFile(allocator, stdout);
+    errdefer output.deinit();
 
-    size = try resize();
-    position = .{.row = 1, .col = 1};
+    const repl = try create(allocator, input, output);
+    repl.terminal = terminal;
+    repl.color = Diagnostic.ColorChoice.auto.enabled(stdout);
 
-    self.status = .initialized;
+    return repl;
 }
 
-const Size = struct {
-    width: usize,
-    height: usize
-};
+fn create(allocator: Allocator, input: *Transport, output: *Transport) !*Repl {
+    const repl = try allocator.create(Repl);
+    errdefer allocator.destroy(repl);
+
+    repl.* = .{
+        .history = .init(allocator),
+        .env = try .init(allocator),
+        .input = input,
+        .output = output,
+        .terminal = null,
+        .color = false,
+        .status = .running,
+        .allocator = allocator
+    };
 
-const Position = struct {
-    row: usize,
-    col: usize,
+    return repl;
+}
 
-    pub fn move_up(self: *Position) void {
-        if (self.row <= 1) return;
-        self.row = std.math.clamp(self.row - 1, 1, size.height - 2);
-    }
+pub fn deinit(self: *Repl) void {
+    for (self.history.items) |line| self.allocator.free(line);
+    self.history.deinit();
+    self.env.deinit();
+    self.input.deinit();
+    self.output.deinit();
+    self.allocator.destroy(self);
+}
 
-    pub fn move_left(self: *Position) void {
-        if (self.col <= 1) return;
-        self.col = std.math.clamp(self.col - 1, 1, size.width - 1);
-    }
+// Echoing and line buffering are disabled so lines can be edited as they are typed
+fn rawMode(handle: std.fs.File.Handle) !Terminal {
+    const original = try std.posix.tcgetattr(handle);
+    var raw = original;
 
-    pub fn move_down(self: *Position) void {
-        self.row = std.math.clamp(self.row + 1, 1, size.height - 2);
-    }
+    raw.lflag.ECHO = false;
+    raw.lflag.ICANON = false;
+    raw.lflag.ISIG = false;
+    raw.lflag.IEXTEN = false;
 
-    pub fn move_right(self: *Position) void {
-        self.col = std.math.clamp(self.col + 1, 1, size.width - 1);
-    }
-};
+    raw.iflag.IXON = false;
+    raw.iflag.ICRNL = false;
+    raw.iflag.BRKINT = false;
+    raw.iflag.INPCK = false;
+    raw.iflag.ISTRIP = false;
 
-const Job = union(enum) {
-    input: u8,
-    tick
-};
+    raw.cflag.CSIZE = .CS8;
 
-pub fn run(self: *Repl) !void {
-    if (self.status == .uninitialized) return error.UninitializedTTY;
+    raw.cc[@intFromEnum(std.posix.system.V.TIME)] = 0;
+    raw.cc[@intFromEnum(std.posix.system.V.MIN)] = 1;
 
-    try self.uncook();
-    defer self.cook() catch {};
+    return .{
+        .original = original,
+        .raw = raw
+    };
+}
 
-    var buffer: [1]u8 = undefined;
+/// Evaluates lines until the input ends or :quit is entered
+pub fn run(self: *Repl) !void {
+    try self.output.print("ruka {s}, enter :help for help\n", .{constants.version_str});
 
-    std.posix.sigaction(std.posix.SIG.WINCH, &std.posix.Sigaction{
-        .handler = .{ .handler = handle_sig_winch },
-        .mask = std.posix.empty_sigset,
-        .flags = 0
-    }, null);
+    if (self.terminal) |terminal| try std.posix.tcsetattr(self.input.getHandle(), .FLUSH, terminal.raw);
+    defer if (self.terminal) |terminal| std.posix.tcsetattr(self.input.getHandle(), .FLUSH, terminal.original) catch {};
 
     self.status = .running;
+    while (self.status == .running) {
+        const line = try self.readLine() orelse break;
+        defer self.allocator.free(line);
 
-    outer: while(true) {
-        try render();
-        _ = try transport.read(&buffer);
-
-        switch (buffer[0]) {
-            'q' => break,
-            // Escape sequences
-            '\x1B' => {
-                self.raw.cc[@intFromEnum(std.posix.system.V.TIME)] = 1;
-                self.raw.cc[@intFromEnum(std.posix.system.V.MIN)] = 0;
-                try std.posix.tcsetattr(transport.getHandle(), .NOW, self.raw);
-
-                var esc_buffer: [8]u8 = undefined;
-                const esc_read = try transport.read(&esc_buffer);
-
-                self.raw.cc[@intFromEnum(std.posix.system.V.TIME)] = 0;
-                self.raw.cc[@intFromEnum(std.posix.system.V.MIN)] = 1;
-                try std.posix.tcsetattr(transport.getHandle(), .FLUSH, self.raw);
-
-                if (esc_read == 0) {
-                    // Escape
-                    continue;
-                } else if (std.mem.eql(u8, esc_buffer[0..esc_read], "[A")) {
-                    position.move_up();
-                } else if (std.mem.eql(u8, esc_buffer[0..esc_read], "[D")) {
-                    position.move_left();
-                } else if (std.mem.eql(u8, esc_buffer[0..esc_read], "[B")) {
-                    position.move_down();
-                } else if (std.mem.eql(u8, esc_buffer[0..esc_read], "[C")) {
-                    position.move_right();
-                }
-            },
-            'w', 'k' => position.move_up(),
-            'a', 'h' => position.move_left(),
-            's', 'j' => position.move_down(),
-            'd', 'l' => position.move_right(),
-            else => {
-                // Check for Ctrl-{ch} characters
-                for ('a'..'z' + 1) |char| {
-                    if (buffer[0] == char & '\x1F') {
-                        // is a Ctrl-{ch}
-                        continue: outer;
-                    }
-                }
-            }
-        }
+        try self.handleLine(line);
     }
 }
 
-fn handle_sig_winch(_: c_int) callconv(.C) void {
-    size = resize() catch return;
-    render() catch return;
-}
+/// Runs line if it is a command, otherwise evaluates it and prints its value
+pub fn handleLine(self: *Repl, line: []const u8) !void {
+    const trimmed = std.mem.trim(u8, line, " \t\r");
+    if (trimmed.len == 0) return;
 
-fn uncook(self: *Repl) !void {
-    errdefer self.cook() catch {};
+    const entry = try self.allocator.dupe(u8, trimmed);
+    errdefer self.allocator.free(entry);
+    try self.history.append(entry);
 
-    try std.posix.tcsetattr(transport.getHandle(), .FLUSH, self.raw);
+    if (trimmed[0] == ':') return try self.runCommand(trimmed[1..]);
 
-    try enable_alt_buffer();
-    try hide_cursor();
-    try save_cursor();
-    try save_screen();
-    try clear();
+    try self.evaluate(trimmed);
+}
+
+fn runCommand(self: *Repl, name: []const u8) !void {
+    const command = commandsMap.get(name) orelse {
+        return try self.output.print("unknown command :{s}, enter :help for the commands\n", .{name});
+    };
 
-    try transport.flush();
+    switch (command) {
+        .help => try self.output.writeAll(help),
+        .reset => {
+            self.env.reset();
+            try self.output.writeAll("discarded every binding\n");
+        },
+        .quit => self.status = .exiting
+    }
 }
 
-fn cook(self: *Repl) !void {
-    try std.posix.tcsetattr(transport.getHandle(), .FLUSH, self.original);
+fn evaluate(self: *Repl, source: []const u8) !void {
+    var input = std.io.fixedBufferStream(source);
+    const reader = input.reader();
+
+    var unit = try Unit.init(.{
+        .input = "repl",
+        .output = "repl",
+        .reader = reader.any(),
+        .writer = std.io.null_writer.any(),
+        .allocator = self.allocator
+    });
+    defer unit.deinit();
+
+    const value = unit.interpret(self.env) catch |err| {
+        // Failures without errors to show aren't caused by the line
+        if (unit.errorCount() == 0) return err;
+        return try self.reportDiagnostics(unit, source);
+    };
 
-    try clear();
-    try reset_attribute();
-    try restore_screen();
-    try restore_cursor();
-    try show_cursor();
-    try disable_alt_buffer();
+    try self.reportDiagnostics(unit, source);
 
-    try transport.flush();
+    // Bindings evaluate to (), which isn't worth printing
+    if (value != .unit) try self.output.print("{}\n", .{value});
 }
 
-fn render() !void {
-    try clear();
-    try draw_border();
-    try draw_cursor();
+fn reportDiagnostics(self: *Repl, unit: *Unit, source: []const u8) !void {
+    if (unit.diagnostics.items.len == 0) return;
 
-    try transport.flush();
-}
+    var rendered = ArrayList(u8).init(self.allocator);
+    defer rendered.deinit();
 
-fn draw_border() !void {
-    for (0..size.height - 1) |i| {
-        try move_cursor(i, 0);
-        try transport.writeAllNoFlush("\u{2502}");
+    const writer = rendered.writer();
+    var terminal = Diagnostic.TerminalEmitter.init(writer.any(), self.color);
+    for (unit.diagnostics.items) |*diagnostic| try terminal.emit(diagnostic, source);
 
-        try move_cursor(i, size.width);
-        try transport.writeAllNoFlush("\u{2502}");
+    try self.output.writeAll(rendered.items);
+}
+
+// Reads the next line, the caller owns the returned memory which is null at the
+// end of the input
+fn readLine(self: *Repl) !?[]u8 {
+    if (self.terminal == null) {
+        try self.output.writeAll(prompt);
+        return try self.input.readLine(self.allocator);
     }
 
-    for (0..size.width) |i| {
-        try move_cursor(0, i);
-        try transport.writeAllNoFlush("\u{2500}");
+    var line = ArrayList(u8).init(self.allocator);
+    defer line.deinit();
 
-        try move_cursor(size.height - 1, i);
-        try transport.writeAllNoFlush("\u{2500}");
-    }
+    var cursor: usize = 0;
+    // The entry of the history being edited, past the end for a new line
+    var entry = self.history.items.len;
 
-    try move_cursor(0, 0);
-    try transport.writeAllNoFlush("\u{250c}");
-    try move_cursor(0, size.width);
-    try transport.writeAllNoFlush("\u{2510}");
-    try move_cursor(size.height - 1, 0);
-    try transport.writeAllNoFlush("\u{2514}");
-    try move_cursor(size.height - 1, size.width);
-    try transport.writeAllNoFlush("\u{2518}");
+    while (true) {
+        try self.redraw(line.items, cursor);
 
-    try move_cursor(size.height, 0);
-    try transport.writeAllNoFlush("  q: Quit");
+        const byte = self.input.readByte() catch |err| switch (err) {
+            error.EndOfStream => return null,
+            else => return err
+        };
 
-    try move_cursor(0, 2);
-    try transport.writeAllNoFlush("Menu");
+        switch (byte) {
+            '\r', '\n' => {
+                try self.output.writeAll("\r\n");
+                return try line.toOwnedSlice();
+            },
+            // Ends the session, but only from an empty line so it isn't pressed by accident
+            ctrl_d => if (line.items.len == 0) {
+                try self.output.writeAll("\r\n");
+                return null;
+            },
+            // Discards the line
+            ctrl_c => {
+                try self.output.writeAll("^C\r\n");
+                line.clearRetainingCapacity();
+                cursor = 0;
+                entry = self.history.items.len;
+            },
+            delete, ctrl_h => if (cursor > 0) {
+                _ = line.orderedRemove(cursor - 1);
+                cursor = cursor - 1;
+            },
+            ctrl_a => cursor = 0,
+            ctrl_e => cursor = line.items.len,
+            '\x1B' => switch (try self.readEscape()) {
+                .up => if (entry > 0) {
+                    entry = entry - 1;
+                    try replaceLine(&line, self.history.items[entry]);
+                    cursor = line.items.len;
+                },
+                .down => if (entry < self.history.items.len) {
+                    entry = entry + 1;
+                    try replaceLine(&line, if (entry < self.history.items.len) self.history.items[entry] else "");
+                    cursor = line.items.len;
+                },
+                .left => cursor = cursor -| 1,
+                .right => cursor = @min(cursor + 1, line.items.len),
+                .other => {}
+            },
+            else => if (std.ascii.isPrint(byte)) {
+                try line.insert(cursor, byte);
+                cursor = cursor + 1;
+            }
+        }
+    }
 }
 
-fn draw_cursor() !void {
-    try move_cursor(position.row, position.col);
-    try white_background();
-    try transport.writeAllNoFlush(" ");
-    try reset_attribute();
+fn replaceLine(line: *ArrayList(u8), contents: []const u8) !void {
+    line.clearRetainingCapacity();
+    try line.appendSlice(contents);
 }
 
-fn blue_background() !void {
-    try transport.writeAllNoFlush("\x1B[44m");
+// Rewrites the line being edited, leaving the terminal's cursor at cursor
+fn redraw(self: *Repl, line: []const u8, cursor: usize) !void {
+    try self.output.printNoFlush("\r\x1B[K{s}{s}", .{prompt, line});
+    if (cursor < line.len) try self.output.printNoFlush("\x1B[{}D", .{line.len - cursor});
+    try self.output.flush();
 }
 
-fn white_background() !void {
-    try transport.writeAllNoFlush("\x1B[47m");
-}
+// Reads the rest of an escape sequence, waiting briefly for it so a lone escape
+// isn't mistaken for the start of one
+fn readEscape(self: *Repl) !Key {
+    const handle = self.input.getHandle();
 
-fn move_cursor(row: usize, col: usize) !void {
-    try transport.printNoFlush("\x1B[{};{}H", .{row + 1, col + 1});
-}
+    var waiting = self.terminal.?.raw;
+    waiting.cc[@intFromEnum(std.posix.system.V.TIME)] = 1;
+    waiting.cc[@intFromEnum(std.posix.system.V.MIN)] = 0;
+    try std.posix.tcsetattr(handle, .NOW, waiting);
+    defer std.posix.tcsetattr(handle, .NOW, self.terminal.?.raw) catch {};
 
-fn hide_cursor() !void {
-    try transport.writeAllNoFlush("\x1B[?25l"); // Hide the cursor.
-}
+    var buffer: [8]u8 = undefined;
+    const sequence = buffer[0..try self.input.read(&buffer)];
 
-fn show_cursor() !void {
-    try transport.writeAllNoFlush("\x1B[?25h"); // Shows the cursor.
-}
+    if (std.mem.eql(u8, sequence, "[A")) return .up;
+    if (std.mem.eql(u8, sequence, "[B")) return .down;
+    if (std.mem.eql(u8, sequence, "[C")) return .right;
+    if (std.mem.eql(u8, sequence, "[D")) return .left;
 
-fn save_cursor() !void {
-    try transport.writeAllNoFlush("\x1B[s"); // Save cursor position.
+    return .other;
 }
 
-fn restore_cursor() !void {
-    try transport.writeAllNoFlush("\x1B[u"); // Restore cursor position.
+test "repl modules" {
+    _ = tests;
 }
 
-fn save_screen() !void {
-    try transport.writeAllNoFlush("\x1B[?47h"); // Save screen.
-}
+const tests = struct {
+    const testing = std.testing;
+    const expectContains = ruka.testing.expectContains;
 
-fn restore_screen() !void {
-    try transport.writeAllNoFlush("\x1B[?47l"); // Restore screen.
-}
+    // Runs a repl over the lines of input, returning everything it wrote
+    fn session(input: []const u8) ![]u8 {
+        var stream = std.io.fixedBufferStream(input);
 
-fn enable_alt_buffer() !void {
-    try transport.writeAllNoFlush("\x1B[?1049h"); // Enable alternative buffer.
-}
+        var output = ArrayList(u8).init(testing.allocator);
+        errdefer output.deinit();
 
-fn disable_alt_buffer() !void {
-    try transport.writeAllNoFlush("\x1B[?1049l"); // Disable alternative buffer.
-}
+        var repl = try Repl.init(testing.allocator, stream.reader().any(), output.writer().any());
+        defer repl.deinit();
 
-fn clear() !void {
-    try transport.writeAllNoFlush("\x1B[H\x1B[J"); // Clear.
-}
+        try repl.run();
 
-fn reset_attribute() !void {
-    try transport.writeAllNoFlush("\x1B[0m"); // Attribute reset.
-}
+        return try output.toOwnedSlice();
+    }
 
-fn resize() !Size {
-    var win_size = std.mem.zeroes(std.posix.winsize);
-    const err = std.posix.system.ioctl(transport.getHandle(), std.posix.system.T.IOCGWINSZ, @intFromPtr(&win_size));
-    if (std.posix.errno(err) != .SUCCESS) {
-        return std.posix.unexpectedErrno(@enumFromInt(err));
+    test "bindings persist between lines" {
+        const output = try session(
+            \\let x = 2
+            \\let greeting = "hello"
+            \\x * 21
+            \\greeting <> " world"
+            \\
+        );
+        defer testing.allocator.free(output);
+
+        try expectContains(output, "> 42\n");
+        try expectContains(output, "> \"hello world\"\n");
     }
 
-    return Size {
-        .height = std.math.clamp(win_size.row - 1, 10, 1000),
-        .width = std.math.clamp(win_size.col - 1, 10, 1000)
-    };
-}
+    test "commands" {
+        const output = try session(
+            \\:help
+            \\let x = 1
+            \\:reset
+            \\x
+            \\:nothing
+            \\:quit
+            \\x
+            \\
+        );
+        defer testing.allocator.free(output);
+
+        try expectContains(output, help);
+        try expectContains(output, "discarded every binding\n");
+        try expectContains(output, "error: x is not defined\n");
+        try expectContains(output, "unknown command :nothing");
+        // Nothing after :quit is read
+        try testing.expect(std.mem.endsWith(output, "> "));
+    }
 
-fn is_utf8_supported() !bool {
-    var lang: [15]u8 = undefined;
-    _ = std.ascii.lowerString(&lang, std.posix.getenv("LANG") orelse return false);
+    test "history" {
+        var stream = std.io.fixedBufferStream("");
 
-    return std.mem.containsAtLeast(u8, &lang, 1, "utf-8");
-}
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
 
-test "repl modules" {
-    _ = tests;
-}
+        var repl = try Repl.init(testing.allocator, stream.reader().any(), output.writer().any());
+        defer repl.deinit();
 
-const tests = struct {
+        try repl.handleLine("  let x = 1  ");
+        try repl.handleLine("");
+        try repl.handleLine(":help");
 
-};
\ No newline at end of file
+        try testing.expectEqual(2, repl.history.items.len);
+        try testing.expectEqualStrings("let x = 1", repl.history.items[0]);
+        try testing.expectEqualStrings(":help", repl.history.items[1]);
+    }
+};
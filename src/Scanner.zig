@@ -10,6 +10,8 @@ const ruka = @import("prelude.zig");
 const Compiler = ruka.Compiler;
 const Position = ruka.Position;
 
+const log = std.log.scoped(.scanner);
+
 prev_char: u8,
 read_char: u8,
 peek_char: u8,
@@ -17,10 +19,16 @@ peep_char: u8,
 
 current_pos: Position,
 token_pos: Position,
+token_index: usize,
 index: usize,
+/// Whether the last token was a '.', integers following one are the positions
+/// of tuple elements, so `t.0.1` isn't read as t, '.' and 0.1
+after_dot: bool,
 
 unit: *Compiler.Unit,
 
+pub const MacroExpander = @import("scanner/MacroExpander.zig");
+pub const ParallelScanner = @import("scanner/ParallelScanner.zig");
 pub const Token = @import("scanner/Token.zig");
 
 const Scanner = @This();
@@ -36,7 +44,9 @@ pub fn init(unit: *Compiler.Unit) !*Scanner {
 
         .current_pos = .init(1, 1),
         .token_pos = .init(1, 1),
+        .token_index = 0,
         .index = 0,
+        .after_dot = false,
 
         .unit = unit,
     };
@@ -53,6 +63,10 @@ pub fn deinit(self: *Scanner) void {
 pub fn nextToken(self: *Scanner) !Token {
     self.skipWhitespace();
     self.token_pos = self.current_pos;
+    self.token_index = self.index;
+
+    const after_dot = self.after_dot;
+    self.after_dot = false;
 
     const byte = self.read();
     const token = switch(byte) {
@@ -60,7 +74,8 @@ pub fn nextToken(self: *Scanner) !Token {
         '"' => block: {
             break :block switch (self.peek()) {
                 '|' => try self.readMultiString(),
-                else => try self.readSingleString()
+                '"' => if (self.peep() == '"') try self.readTripleString(false) else try self.readSingleString(false),
+                else => try self.readSingleString(false)
             };
         },
         // Characters and Enum Literals
@@ -69,13 +84,21 @@ pub fn nextToken(self: *Scanner) !Token {
         '/' => block: {
             switch (self.peek()) {
                 '/' => {
+                    if (self.peep() == '/') break :block try self.readDocComment();
+
                     self.skipSingleComment();
+                    try self.recordComment();
                     break :block self.nextToken();
                 },
                 '*' => {
                     try self.skipMultiComment();
+                    try self.recordComment();
                     break :block self.nextToken();
                 },
+                '=' => {
+                    self.advance(2);
+                    break :block self.createToken(.assign_div);
+                },
                 else => {
                     self.advance(1);
                     break :block self.createToken(.slash);
@@ -98,6 +121,7 @@ pub fn nextToken(self: *Scanner) !Token {
         },
         ':' => block: {
             var kind = try self.tryCompoundOperator(.{
+                .{2, "::", Token.Kind.double_colon},
                 .{2, ":=", Token.Kind.assign_exp}
             });
 
@@ -110,6 +134,7 @@ pub fn nextToken(self: *Scanner) !Token {
         },
         '>' => block: {
             var kind = try self.tryCompoundOperator(.{
+                .{3, ">>=", Token.Kind.assign_rshift},
                 .{2, ">=", Token.Kind.greater_eq},
                 .{2, ">>", Token.Kind.rshift}
             });
@@ -123,6 +148,7 @@ pub fn nextToken(self: *Scanner) !Token {
         },
         '<' => block: {
             var kind = try self.tryCompoundOperator(.{
+                .{3, "<<=", Token.Kind.assign_lshift},
                 .{2, "<=", Token.Kind.lesser_eq},
                 .{2, "<<", Token.Kind.lshift},
                 .{2, "<|", Token.Kind.forward_app},
@@ -139,7 +165,8 @@ pub fn nextToken(self: *Scanner) !Token {
         '-' => block: {
             var kind = try self.tryCompoundOperator(.{
                 .{2, "->", Token.Kind.arrow},
-                .{2, "--", Token.Kind.decrement}
+                .{2, "--", Token.Kind.decrement},
+                .{2, "-=", Token.Kind.assign_sub}
             });
 
             if (kind == null) {
@@ -151,7 +178,8 @@ pub fn nextToken(self: *Scanner) !Token {
         },
         '+' => block: {
             var kind = try self.tryCompoundOperator(.{
-                .{2, "++", Token.Kind.increment}
+                .{2, "++", Token.Kind.increment},
+                .{2, "+=", Token.Kind.assign_add}
             });
 
             if (kind == null) {
@@ -163,7 +191,8 @@ pub fn nextToken(self: *Scanner) !Token {
         },
         '*' => block: {
             var kind = try self.tryCompoundOperator(.{
-                .{2, "**", Token.Kind.square}
+                .{2, "**", Token.Kind.square},
+                .{2, "*=", Token.Kind.assign_mul}
             });
 
             if (kind == null) {
@@ -175,6 +204,7 @@ pub fn nextToken(self: *Scanner) !Token {
         },
         '.' => block: {
             var kind = try self.tryCompoundOperator(.{
+                .{3, "...", Token.Kind.ellipsis},
                 .{3, "..=", Token.Kind.range_inc},
                 .{2, "..", Token.Kind.range_exc}
             });
@@ -182,6 +212,7 @@ pub fn nextToken(self: *Scanner) !Token {
             if (kind == null) {
                 self.advance(1);
                 kind = Token.Kind.dot;
+                self.after_dot = true;
             }
 
             break :block self.createToken(kind.?);
@@ -200,7 +231,9 @@ pub fn nextToken(self: *Scanner) !Token {
         },
         '|' => block: {
             var kind = try self.tryCompoundOperator(.{
-                .{2, "|>", Token.Kind.reverse_app}
+                .{2, "|>", Token.Kind.reverse_app},
+                .{2, "||", Token.Kind.logical_or},
+                .{2, "|=", Token.Kind.assign_or}
             });
 
             if (kind == null) {
@@ -210,24 +243,108 @@ pub fn nextToken(self: *Scanner) !Token {
 
             break :block self.createToken(kind.?);
         },
+        '&' => block: {
+            var kind = try self.tryCompoundOperator(.{
+                .{2, "&&", Token.Kind.logical_and},
+                .{2, "&=", Token.Kind.assign_and}
+            });
+
+            if (kind == null) {
+                self.advance(1);
+                kind = Token.Kind.ampersand;
+            }
+
+            break :block self.createToken(kind.?);
+        },
+        '^' => block: {
+            var kind = try self.tryCompoundOperator(.{
+                .{2, "^=", Token.Kind.assign_xor}
+            });
+
+            if (kind == null) {
+                self.advance(1);
+                kind = Token.Kind.caret;
+            }
+
+            break :block self.createToken(kind.?);
+        },
+        '%' => block: {
+            var kind = try self.tryCompoundOperator(.{
+                .{2, "%=", Token.Kind.assign_mod}
+            });
+
+            if (kind == null) {
+                self.advance(1);
+                kind = Token.Kind.percent;
+            }
+
+            break :block self.createToken(kind.?);
+        },
         '\x00' => self.createToken(Token.Kind.eof),
         // Single characters, identifiers, keywords, modes, numbers
         else => block: {
-            if (ruka.isAlphabetical(byte)) {
+            // Raw strings, the token starts at the r
+            if (byte == 'r' and self.peek() == '"') {
+                self.advance(1);
+                break :block switch (self.peek()) {
+                    '"' => if (self.peep() == '"') try self.readTripleString(true) else try self.readSingleString(true),
+                    else => try self.readSingleString(true)
+                };
+            }
+
+            // Identifiers may begin with an underscore, which alone is the wildcard pattern
+            if (ruka.isAlphabetical(byte) or byte == '_') {
                 break :block try self.readIdentifierKeywordMode();
             } else if (ruka.isIntegral(byte)) {
-                break :block try self.readIntegerFloat();
+                break :block try self.readIntegerFloat(!after_dot);
             }
 
             // Single character
+            const kind = Token.Kind.fromByte(byte);
+            if (kind == .illegal) {
+                // Record the unknown character and continue scanning past it
+                try self.createError("unknown character");
+                self.advance(1);
+                break :block self.nextToken();
+            }
+
             self.advance(1);
-            break :block self.createToken(Token.Kind.fromByte(byte));
+            break :block self.createToken(kind);
         }
     };
 
     return token;
 }
 
+/// Scans the entire file, returning every token including the trailing eof,
+/// the caller owns the returned tokens. Scanning continues past errors so all
+/// of them are recorded in the unit, but fails if any were encountered
+pub fn scan(self: *Scanner) !ArrayList(Token) {
+    const error_count = self.unit.errorCount();
+
+    var tokens = ArrayList(Token).init(self.unit.allocator);
+    errdefer {
+        for (tokens.items) |token| token.deinit();
+        tokens.deinit();
+    }
+
+    var token = try self.nextToken();
+    while (token.kind != .eof): (token = try self.nextToken()) {
+        errdefer token.deinit();
+        try tokens.append(token);
+    }
+
+    try tokens.append(token);
+
+    const scan_errors = self.unit.errorCount() - error_count;
+    if (scan_errors > 0) {
+        log.err("{s}: scanning failed with {} error(s)", .{self.unit.input, scan_errors});
+        return error.ScanningFailed;
+    }
+
+    return tokens;
+}
+
 fn read(self: Scanner) u8 {
     return self.read_char;
 }
@@ -263,29 +380,24 @@ fn advance(self: *Scanner, count: usize) void {
 
 
 fn createToken(self: *Scanner, kind: Token.Kind) Token {
-    return Token.init(
-        kind,
+    return Token.initSpan(kind, .init(
         self.unit.input,
+        self.token_index,
+        self.index,
         self.token_pos
-    );
+    ));
 }
 
-fn createError(self: *Scanner, msg: []const u8) !void {
-    try self.unit.createError(self, "scanner error", msg);
+// The span of the byte currently being scanned
+fn currentSpan(self: *Scanner) ruka.Span {
+    return .init(self.unit.input, self.index, self.index + 1, self.current_pos);
 }
 
-fn createEscapeError(self: *Scanner, i: usize, slice: []const u8) !void {
-    if (i + 1 > slice.len) {
-        return try self.createError("unterminated escape character");
-    }
-
-    var buf = [_]u8{0} ** 40;
-    try self.createError(try std.fmt.bufPrint(&buf,
-        "unrecognized escape character: //{}",
-        .{slice[i + 1]}
-    ));
+fn createError(self: *Scanner, msg: []const u8) !void {
+    try self.unit.createError(msg, self.currentSpan());
 }
 
+
 fn skipWhitespace(self: *Scanner) void {
     switch (self.read()) {
         inline ' ', '\t' => {
@@ -296,6 +408,11 @@ fn skipWhitespace(self: *Scanner) void {
     }
 }
 
+// Records the span of the comment just read in the unit, so the formatter can write it back
+fn recordComment(self: *Scanner) !void {
+    try self.unit.comments.append(.init(self.unit.input, self.token_index, self.index, self.token_pos));
+}
+
 fn skipSingleComment(self: *Scanner) void {
     switch (self.read()) {
         '\n', '\x00' => {},
@@ -306,21 +423,47 @@ fn skipSingleComment(self: *Scanner) void {
     }
 }
 
+// Skips a comment delimited by /* and */, which may contain nested comments so
+// code containing them can be commented out. Unterminated comments are reported
+// at their start
 fn skipMultiComment(self: *Scanner) !void {
-    var next = self.peek();
+    const span = self.currentSpan();
+    self.advance(2);
 
-    while (self.read() != '\x00'): (next = self.peek()) {
-        if (self.read() == '*' and next == '/') {
-            self.advance(2);
-            break;
+    var depth: usize = 1;
+    while (depth > 0) {
+        switch (self.read()) {
+            '\x00' => return try self.unit.createError("unterminated multiline comment", span),
+            '/' => if (self.peek() == '*') {
+                depth = depth + 1;
+                self.advance(2);
+                continue;
+            },
+            '*' => if (self.peek() == '/') {
+                depth = depth - 1;
+                self.advance(2);
+                continue;
+            },
+            else => {}
         }
 
         self.advance(1);
     }
+}
+
+// Reads a comment starting with ///, up to the end of the line
+fn readDocComment(self: *Scanner) !Token {
+    var string = ArrayList(u8).init(self.unit.allocator);
+    errdefer string.deinit();
 
-    if (next != '/') {
-        try self.createError("unterminated multiline comment");
+    self.advance(3);
+    while (self.read() != '\n' and self.read() != '\x00') {
+        try string.append(self.read());
+        self.advance(1);
     }
+
+    try self.recordComment();
+    return self.createToken(.{ .doc_comment = string });
 }
 
 fn readCharacterEnum(self: *Scanner) !Token {
@@ -335,24 +478,25 @@ fn readCharacterEnum(self: *Scanner) !Token {
         return try self.readEnumLiteral();
     }
 
-    while (self.peek() != '\'' and self.peek() != '\x00') {
-        try string.append(self.peek());
-        self.advance(1);
+    self.advance(1);
+    while (self.read() != '\'' and self.read() != '\x00') {
+        if (self.read() == '\\') {
+            try self.readEscape(&string);
+        } else {
+            try string.append(self.read());
+            self.advance(1);
+        }
     }
 
-    string = try self.handleEscapeCharacters(
-        try string.toOwnedSlice(),
-        self.unit.allocator
-    );
-
     if (string.items.len > 1) {
         try self.createError("too many characters in character literal");
     } else if (string.items.len < 1) {
         try self.createError("character literal is empty");
     }
 
-    self.advance(2);
-    return self.createToken(.{ .character = string.items[0] });
+    self.advance(1);
+    const character = if (string.items.len > 0) string.items[0] else '\x00';
+    return self.createToken(.{ .character = character });
 }
 
 fn readEnumLiteral(self: *Scanner) !Token {
@@ -396,12 +540,6 @@ fn tryCompoundOperator(self: *Scanner, comptime matches: anytype) !?Token.Kind {
     return null;
 }
 
-/// Checks if a string represents an escape character, if it does return that character
-fn tryEscapeChar(str: []const u8) ?u8 {
-    // Check for \u{xxxxxx} and \x{xx}
-    return escapes.get(str);
-}
-
 // Map representing escape sequences and their string representation
 const escapes = std.StaticStringMap(u8).initComptime(.{
     .{"\\n", '\n'},
@@ -414,46 +552,70 @@ const escapes = std.StaticStringMap(u8).initComptime(.{
     .{"\\0", '\x00'}
 });
 
-// Replaces escape characters
-fn handleEscapeCharacters(
-    self: *Scanner,
-    slice: [] const u8,
-    allocator: Allocator
-) !std.ArrayList(u8) {
-    defer self.unit.allocator.free(slice);
-    var string = ArrayList(u8).init(allocator);
-    errdefer string.deinit();
+// Reads the escape sequence starting at the current backslash into string,
+// unrecognized escapes are reported and kept as written
+fn readEscape(self: *Scanner, string: *ArrayList(u8)) !void {
+    const span = self.currentSpan();
+    self.advance(1);
+
+    const byte = self.read();
+    switch (byte) {
+        '\x00', '\n' => return try self.unit.createError("unterminated escape character", span),
+        'u' => return try self.readUnicodeEscape(string, span),
+        else => {}
+    }
 
-    var i: usize = 0;
-    while (i < slice.len) {
-        switch (slice[i]) {
-            '\\' => {
-                // Adjust to check for hex and unicode escape characters
-                const esc_ch = tryEscapeChar(slice[i..i+2]);
+    self.advance(1);
+    if (escapes.get(&.{'\\', byte})) |escaped| {
+        try string.append(escaped);
+    } else {
+        try self.unit.createErrorFmt(span, "unrecognized escape character: \\{c}", .{byte});
+        try string.appendSlice(&.{'\\', byte});
+    }
+}
 
-                if (esc_ch) |esc| {
-                    i = i + 2;
-                    try string.append(esc);
-                } else {
-                    try self.createEscapeError(i, slice);
+// Reads a unicode escape of the form \u{XXXXXX}, where X is one to six hex
+// digits, into string as utf-8
+fn readUnicodeEscape(self: *Scanner, string: *ArrayList(u8), span: ruka.Span) !void {
+    self.advance(1);
+    if (self.read() != '{') {
+        return try self.unit.createError("expected '{' after unicode escape", span);
+    }
+    self.advance(1);
 
-                    i = i + 1;
-                    try string.append('\\');
-                }
-            },
-            else => |ch| {
-                i = i + 1;
-                try string.append(ch);
-            }
-        }
+    var value: u32 = 0;
+    var digits: usize = 0;
+    while (std.fmt.charToDigit(self.read(), 16) catch null) |digit| : (self.advance(1)) {
+        // Values past six digits are out of range, so stop growing them
+        if (digits < 6) value = value * 16 + digit;
+        digits = digits + 1;
+    }
+
+    if (self.read() != '}') {
+        return try self.unit.createError("unterminated unicode escape", span);
+    }
+    self.advance(1);
+
+    if (digits == 0) {
+        return try self.unit.createError("unicode escape has no digits", span);
+    }
+
+    if (digits > 6 or value > 0x10FFFF) {
+        return try self.unit.createError("unicode escape is larger than 10FFFF", span);
+    }
+
+    if (value >= 0xD800 and value <= 0xDFFF) {
+        return try self.unit.createError("unicode escape is a surrogate code point", span);
     }
 
-    return string;
+    var buf: [4]u8 = undefined;
+    const len = std.unicode.utf8Encode(@intCast(value), &buf) catch unreachable;
+    try string.appendSlice(buf[0..len]);
 }
 
 fn readIdentifierKeywordMode(self: *Scanner) !Token {
     var string = ArrayList(u8).init(self.unit.allocator);
-    errdefer string.deinit();
+    defer string.deinit();
 
     var byte = self.read();
     while (ruka.isAlphanumerical(byte)) {
@@ -463,87 +625,177 @@ fn readIdentifierKeywordMode(self: *Scanner) !Token {
     }
 
     var kind = Token.Kind.tryMode(string.items);
-    if (kind) |k| {
-        string.deinit();
-        return self.createToken(k);
-    }
+    if (kind) |k| return self.createToken(k);
 
     kind = Token.Kind.tryKeyword(string.items);
-    if (kind) |k| {
-        string.deinit();
-        return self.createToken(k);
-    }
+    if (kind) |k| return self.createToken(k);
 
-    kind = .{ .identifier = string };
+    // Identifiers are interned so later passes compare them as integers
+    kind = .{ .identifier = try self.unit.interner.intern(string.items) };
 
     return self.createToken(kind.?);
 }
 
-fn readIntegerFloat(self: *Scanner) !Token {
+// Only integers are read when not fractional, as the positions of tuple elements are
+fn readIntegerFloat(self: *Scanner, fractional: bool) !Token {
+    if (self.read() == '0') {
+        switch (self.peek()) {
+            'x', 'X' => return try self.readPrefixedInteger(16),
+            'o', 'O' => return try self.readPrefixedInteger(8),
+            'b', 'B' => return try self.readPrefixedInteger(2),
+            else => {}
+        }
+    }
+
+    // The digits read, without separators, so floats can be parsed from them
     var string = ArrayList(u8).init(self.unit.allocator);
-    errdefer string.deinit();
+    defer string.deinit();
 
-    // Iterate while self.read() is numeric, if self.read() is a '.',
-    // read only integer values afterwards
-    var float = false;
-    var byte = self.read();
-    while (ruka.isNumeric(byte)) {
-        if (byte == '.') {
-            try string.append(byte);
-            try self.readMantissa(&string);
-            float = true;
-            break;
-        }
+    var value: ?i64 = 0;
+    while (ruka.isIntegral(self.read())): (self.advance(1)) {
+        const byte = self.read();
+        if (byte == '_') continue;
 
+        value = accumulateDigit(value, byte - '0', 10);
         try string.append(byte);
+    }
+
+    // Only separators were read
+    if (string.items.len == 0) {
+        const token = self.createToken(.{ .integer = 0 });
+        try self.unit.createError("invalid integer literal", token.span);
+
+        return token;
+    }
+
+    var float = false;
+
+    // A second '.' makes a range, such as 0..10
+    if (fractional and self.read() == '.' and self.peek() != '.') {
+        float = true;
+        try string.append('.');
         self.advance(1);
-        byte = self.read();
+
+        // A bare trailing '.' is read as '.0'
+        if (!try self.readDigits(&string)) try string.append('0');
     }
 
-    const kind: Token.Kind = switch (float) {
-        false => .{ .integer = string },
-        true  => .{ .float = string }
-    };
+    if (fractional and self.isExponent()) {
+        float = true;
+        try string.append('e');
+        self.advance(1);
 
-    return self.createToken(kind);
+        if (self.read() == '+' or self.read() == '-') {
+            try string.append(self.read());
+            self.advance(1);
+        }
+
+        _ = try self.readDigits(&string);
+    }
+
+    if (!float) return try self.createIntegerToken(value);
+
+    // The digits are always well formed, so the only failure is a float too large to represent
+    const token = self.createToken(.{ .float = std.fmt.parseFloat(f64, string.items) catch unreachable });
+    if (std.math.isInf(token.kind.float)) {
+        try self.unit.report(.initLint(.overflow, "float literal is too large and becomes infinity", token.span));
+    }
+
+    return token;
 }
 
-fn readMantissa(self: *Scanner, string: *ArrayList(u8)) !void {
-    self.advance(1);
+// Whether an exponent with at least one digit follows a float's digits, so `1e`
+// followed by something else is an integer followed by an identifier
+fn isExponent(self: *Scanner) bool {
+    if (self.read() != 'e' and self.read() != 'E') return false;
 
-    var byte = self.read();
+    return switch (self.peek()) {
+        '0'...'9' => true,
+        '+', '-' => std.ascii.isDigit(self.peep()),
+        else => false
+    };
+}
+
+// Reads digits and separators into string, returning whether any digits were read
+fn readDigits(self: *Scanner, string: *ArrayList(u8)) !bool {
+    var found = false;
+    while (ruka.isIntegral(self.read())): (self.advance(1)) {
+        if (self.read() == '_') continue;
 
-    if (!ruka.isIntegral(byte)) {
-        try string.append('0');
-        return;
+        try string.append(self.read());
+        found = true;
     }
 
-    while (ruka.isIntegral(byte)) {
-        try string.append(byte);
+    return found;
+}
+
+// Reads an integer written in base after its prefix, such as 0xff. Digits outside
+// of base are recorded as errors and skipped
+fn readPrefixedInteger(self: *Scanner, base: u8) !Token {
+    self.advance(2);
+
+    var value: ?i64 = 0;
+    var digits: usize = 0;
+    var invalid = false;
+
+    var byte = self.read();
+    while (ruka.isAlphanumerical(byte)): ({
         self.advance(1);
         byte = self.read();
+    }) {
+        if (byte == '_') continue;
+
+        const digit = std.fmt.charToDigit(byte, base) catch {
+            try self.unit.createErrorFmt(self.currentSpan(), "invalid digit '{c}' in base {} integer literal", .{byte, base});
+            invalid = true;
+            continue;
+        };
+
+        value = accumulateDigit(value, digit, base);
+        digits = digits + 1;
     }
+
+    if (digits == 0 and !invalid) try self.createError("expected digits after integer literal prefix");
+
+    return try self.createIntegerToken(value);
+}
+
+// Appends digit to the integer value accumulated so far, null once it no longer
+// fits in an i64
+fn accumulateDigit(value: ?i64, digit: u8, base: u8) ?i64 {
+    const shifted = std.math.mul(i64, value orelse return null, base) catch return null;
+    return std.math.add(i64, shifted, digit) catch null;
+}
+
+fn createIntegerToken(self: *Scanner, value: ?i64) !Token {
+    const token = self.createToken(.{ .integer = value orelse 0 });
+    if (value == null) try self.unit.createError("integer literal is too large to fit in 64 bits", token.span);
+
+    return token;
 }
 
-fn readSingleString(self: *Scanner) !Token {
+// Reads a string delimited by ", escapes are kept as written when raw
+fn readSingleString(self: *Scanner, raw: bool) !Token {
     var string = ArrayList(u8).init(self.unit.allocator);
     errdefer string.deinit();
 
-    while (self.peek() != '"' and self.peek() != '\x00') {
-        try string.append(self.peek());
-        self.advance(1);
+    self.advance(1);
+    while (self.read() != '"' and self.read() != '\n' and self.read() != '\x00') {
+        if (!raw and self.read() == '\\') {
+            try self.readEscape(&string);
+        } else {
+            try string.append(self.read());
+            self.advance(1);
+        }
     }
 
-    self.advance(2);
-
-    if (self.prev() != '"') {
+    if (self.read() == '"') {
+        self.advance(1);
+    } else {
+        // Close the string at the end of the line so scanning can continue
         try self.createError("unterminated string literal");
     }
 
-    string = try self.handleEscapeCharacters(
-        try string.toOwnedSlice(),
-        self.unit.allocator
-    );
     return self.createToken(.{ .string = string });
 }
 
@@ -551,45 +803,88 @@ fn readMultiString(self: *Scanner) !Token {
     var string = ArrayList(u8).init(self.unit.allocator);
     errdefer string.deinit();
 
-    self.advance(1);
-    while (self.peek() != '"' and self.peek() != '\x00') {
-        switch (self.peek()) {
+    self.advance(2);
+    while (self.read() != '"') {
+        switch (self.read()) {
+            '\x00' => {
+                try self.createError("unterminated string literal");
+                break;
+            },
             '\n' => {
                 try string.append('\n');
-                self.advance(2);
+                self.advance(1);
                 self.skipWhitespace();
 
-                switch (self.read()) {
-                    '|' => {
-                        switch (self.peek()) {
-                            '"' => break,
-                            else => |ch| try string.append(ch)
-                        }
-                    },
-                    else => try self.createError("missing start of line delimiter '|'")
+                if (self.read() == '|') {
+                    self.advance(1);
+                } else {
+                    try self.createError("missing start of line delimiter '|'");
                 }
             },
-            else => |ch| try string.append(ch)
+            '\\' => try self.readEscape(&string),
+            else => |byte| {
+                try string.append(byte);
+                self.advance(1);
+            }
         }
+    } else self.advance(1);
+
+    return self.createToken(.{ .string = string });
+}
+
+// Reads a string delimited by """, which may span lines. A newline directly after
+// the opening quotes isn't included, and each following line has its leading
+// whitespace stripped up to the column of the opening quotes, which follow the r of
+// raw strings. Escapes are kept as written when raw
+fn readTripleString(self: *Scanner, raw: bool) !Token {
+    var string = ArrayList(u8).init(self.unit.allocator);
+    errdefer string.deinit();
+
+    const indentation = self.current_pos.col - 1;
+    self.advance(3);
 
+    if (self.read() == '\n') {
         self.advance(1);
+        self.skipIndentation(indentation);
     }
 
-    self.advance(2);
+    var terminated = false;
+    while (self.read() != '\x00') {
+        if (self.read() == '"' and self.peek() == '"' and self.peep() == '"') {
+            self.advance(3);
+            terminated = true;
+            break;
+        }
 
-    if (self.prev() != '"') {
-        try self.createError("unterminated string literal");
+        // Escaped quotes don't close the string
+        if (!raw and self.read() == '\\') {
+            try self.readEscape(&string);
+            continue;
+        }
+
+        const byte = self.read();
+        try string.append(byte);
+        self.advance(1);
+
+        if (byte == '\n') self.skipIndentation(indentation);
     }
 
-    string = try self.handleEscapeCharacters(
-        try string.toOwnedSlice(),
-        self.unit.allocator
-    );
+    if (!terminated) try self.createError("unterminated string literal");
+
     return self.createToken(.{ .string = string });
 }
 
+// Skips at most count spaces or tabs
+fn skipIndentation(self: *Scanner, count: usize) void {
+    for (0..count) |_| {
+        if (self.read() != ' ' and self.read() != '\t') return;
+        self.advance(1);
+    }
+}
+
 test "scanner modules" {
     _ = tests;
+    _ = MacroExpander;
     _ = Token;
 }
 
@@ -600,10 +895,6 @@ const tests = struct {
 
     fn compareTokens(expected_token: *const Token, actual_token: *const Token) !void {
         switch (expected_token.kind) {
-            .identifier => |e_identifier| switch (actual_token.kind) {
-                .identifier => |a_identifier| try expectEqualStrings(e_identifier.items, a_identifier.items),
-                else => try expectEqual(expected_token.kind, actual_token.kind)
-            },
             .@"enum" => |e_enum_literal| switch (actual_token.kind) {
                 .@"enum" => |a_enum_literal| try expectEqualStrings(e_enum_literal.items, a_enum_literal.items),
                 else => try expectEqual(expected_token.kind, actual_token.kind)
@@ -617,11 +908,11 @@ const tests = struct {
                 else => try expectEqual(expected_token.kind, actual_token.kind)
             },
             .integer => |e_integer| switch (actual_token.kind) {
-                .integer => |a_integer| try expectEqualStrings(e_integer.items, a_integer.items),
+                .integer => |a_integer| try expectEqual(e_integer, a_integer),
                 else => try expectEqual(expected_token.kind, actual_token.kind)
             },
             .float => |e_float| switch (actual_token.kind) {
-                .float => |a_float| try expectEqualStrings(e_float.items, a_float.items),
+                .float => |a_float| try expectEqual(e_float, a_float),
                 else => try expectEqual(expected_token.kind, actual_token.kind)
             },
             .keyword => |e_keyword| switch (actual_token.kind) {
@@ -637,8 +928,8 @@ const tests = struct {
             }
         }
 
-        try expectEqualStrings(expected_token.file, actual_token.file);
-        try expectEqual(expected_token.pos, actual_token.pos);
+        try expectEqualStrings(expected_token.span.file, actual_token.span.file);
+        try expectEqual(expected_token.span.pos, actual_token.span.pos);
     }
 
     fn checkResults(scanner: *Scanner, e: []const Token) !void {
@@ -655,6 +946,30 @@ const tests = struct {
         try expectEqual(e.len, i + 1);
     }
 
+    fn checkKinds(source: []const u8, e: []const std.meta.Tag(Token.Kind)) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(e.len, tokens.items.len);
+        for (e, tokens.items) |expected_kind, token| {
+            try expectEqual(expected_kind, std.meta.activeTag(token.kind));
+        }
+    }
+
     test "next token" {
         const source = "let x = 12_000 12_000.50 '\\n'";
         var input = std.io.fixedBufferStream(source);
@@ -668,16 +983,12 @@ const tests = struct {
         var scanner = try Scanner.init(unit);
         defer scanner.deinit();
 
-        var arena = std.heap.ArenaAllocator.init(unit.allocator);
-        defer arena.deinit();
-        const allocator = arena.allocator();
-
         const expected = [_]Token{
             .init(.{ .keyword = .let }, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7)),
-            .init(try .initInteger("12_000", allocator), "test source", .init(1, 9)),
-            .init(try .initFloat("12_000.50", allocator), "test source", .init(1, 16)),
+            .init(.{ .integer = 12_000 }, "test source", .init(1, 9)),
+            .init(.{ .float = 12_000.5 }, "test source", .init(1, 16)),
             .init(.{ .character = '\n' }, "test source", .init(1, 26)),
             .init(.eof, "test source", .init(1, 30)),
         };
@@ -722,6 +1033,29 @@ const tests = struct {
         try checkResults(scanner, &expected);
     }
 
+    test "assignment operators" {
+        const source = "+= -= *= /= %= &= |= ^= <<= >>=";
+        try checkKinds(source, &.{
+            .assign_add, .assign_sub, .assign_mul, .assign_div, .assign_mod, .assign_and,
+            .assign_or, .assign_xor, .assign_lshift, .assign_rshift, .eof
+        });
+    }
+
+    test "logical operators and delimiters" {
+        try checkKinds("&& || ! :: ... ? @", &.{
+            .logical_and, .logical_or, .bang, .double_colon, .ellipsis, .question, .address, .eof
+        });
+    }
+
+    test "longest operators are scanned first" {
+        try checkKinds("===", &.{.equal, .assign, .eof});
+        try checkKinds("<<==", &.{.assign_lshift, .assign, .eof});
+        try checkKinds("....", &.{.ellipsis, .dot, .eof});
+        try checkKinds("&&&", &.{.logical_and, .ampersand, .eof});
+        try checkKinds("x/=y//=z", &.{.identifier, .assign_div, .identifier, .eof});
+        try checkKinds(":::=", &.{.double_colon, .assign_exp, .eof});
+    }
+
     test "string reading" {
         const source = "let x = \"Hello, world!\"";
         var input = std.io.fixedBufferStream(source);
@@ -741,7 +1075,7 @@ const tests = struct {
 
         const expected = [_]Token{
             .init(.{ .keyword = .let }, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7)),
             .init(try .initString("Hello, world!", allocator), "test source", .init(1, 9)),
             .init(.eof, "test source", .init(1, 24)),
@@ -772,7 +1106,7 @@ const tests = struct {
 
         const expected = [_]Token{
             .init(.{ .keyword = .let }, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7)),
             .init(try .initString("\n Hello, world!\n", allocator), "test source", .init(1, 9)),
             .init(.eof, "test source", .init(3, 12)),
@@ -800,7 +1134,7 @@ const tests = struct {
 
         const expected = [_]Token{
             .init(.{ .keyword = .let }, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7)),
             .init(try .initString("Hello, \n\\sworld!", allocator), "test source", .init(1, 9)),
             .init(.eof, "test source", .init(1, 28)),
@@ -822,13 +1156,9 @@ const tests = struct {
         var scanner = try Scanner.init(unit);
         defer scanner.deinit();
 
-        var arena = std.heap.ArenaAllocator.init(unit.allocator);
-        defer arena.deinit();
-        const allocator = arena.allocator();
-
         const expected = [_]Token{
             .init(.{ .keyword = .let }, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7)),
             .init(.{ .character = '\n' }, "test source", .init(1, 9)),
             .init(.eof, "test source", .init(1, 13)),
@@ -856,7 +1186,7 @@ const tests = struct {
 
         const expected = [_]Token{
             .init(.{ .keyword = .let }, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7)),
             .init(.lsquirly, "test source", .init(1, 9)),
             .init(.{ .character = 'b' }, "test source", .init(1, 10)),
@@ -884,15 +1214,11 @@ const tests = struct {
         var scanner = try Scanner.init(unit);
         defer scanner.deinit();
 
-        var arena = std.heap.ArenaAllocator.init(unit.allocator);
-        defer arena.deinit();
-        const allocator = arena.allocator();
-
         const expected = [_]Token{
             .init(.{ .keyword = .let}, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7)),
-            .init(try .initIdentifier("hello", allocator), "test source", .init(1, 9)),
+            .init(try .initIdentifier("hello", &unit.interner), "test source", .init(1, 9)),
             .init(.lparen, "test source", .init(1, 14)),
             .init(.rparen, "test source", .init(1, 15)),
             .init(.eof, "test source", .init(1, 16))
@@ -914,13 +1240,9 @@ const tests = struct {
         var scanner = try Scanner.init(unit);
         defer scanner.deinit();
 
-        var arena = std.heap.ArenaAllocator.init(unit.allocator);
-        defer arena.deinit();
-        const allocator = arena.allocator();
-
         const expected = [_]Token{
             .init(.{ .keyword = .let }, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7)),
             .init(.eof, "test source", .init(1, 27))
         };
@@ -944,17 +1266,850 @@ const tests = struct {
         var scanner = try Scanner.init(unit);
         defer scanner.deinit();
 
-        var arena = std.heap.ArenaAllocator.init(unit.allocator);
-        defer arena.deinit();
-        const allocator = arena.allocator();
-
         const expected = [_]Token{
             .init(.{ .keyword = .let}, "test source", .init(1, 1)),
-            .init(try .initIdentifier("x", allocator), "test source", .init(1, 5)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
             .init(.assign, "test source", .init(1, 7 )),
             .init(.eof, "test source", .init(3, 3))
         };
 
         try checkResults(scanner, &expected);
     }
+
+    test "nested multi comments" {
+        try checkKinds("x /* outer /* inner */ still outer */ y", &.{.identifier, .identifier, .eof});
+        try checkKinds("/* /* */ */ /* */ x", &.{.identifier, .eof});
+        try checkKinds("/*/ x */ y", &.{.identifier, .eof});
+    }
+
+    test "unterminated nested multi comments" {
+        var input = std.io.fixedBufferStream("x\n  /* /* */ y");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        try testing.expectError(error.ScanningFailed, scanner.scan());
+        try expectEqual(1, unit.diagnostics.items.len);
+        try expectEqualStrings("unterminated multiline comment", unit.diagnostics.items[0].message);
+        try expectEqual(Position.init(2, 3), unit.diagnostics.items[0].span.pos);
+    }
+
+    test "doc comments" {
+        var input = std.io.fixedBufferStream("/// Adds one\n// not docs\nx /// after");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        const expected = [_]std.meta.Tag(Token.Kind){.doc_comment, .newline, .newline, .identifier, .doc_comment, .eof};
+        try expectEqual(expected.len, tokens.items.len);
+        for (expected, tokens.items) |expected_kind, token| {
+            try expectEqual(expected_kind, std.meta.activeTag(token.kind));
+        }
+
+        try expectEqualStrings(" Adds one", tokens.items[0].kind.doc_comment.items);
+        try expectEqualStrings(" after", tokens.items[4].kind.doc_comment.items);
+        try expectEqual(Position.init(3, 3), tokens.items[4].span.pos);
+    }
+
+    test "comments are recorded" {
+        const source = "x // line\n/* block /* nested */ */ y /// docs";
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        const expected = [_][]const u8{"// line", "/* block /* nested */ */", "/// docs"};
+        try expectEqual(expected.len, unit.comments.items.len);
+        for (expected, unit.comments.items) |text, span| {
+            try expectEqualStrings(text, source[span.start..span.end]);
+        }
+        try expectEqual(Position.init(2, 1), unit.comments.items[1].pos);
+    }
+
+    test "empty input" {
+        try checkKinds("", &.{.eof});
+    }
+
+    test "whitespace only input" {
+        try checkKinds("  \t    \t", &.{.eof});
+    }
+
+    test "newlines" {
+        try checkKinds("x\n\ny", &.{.identifier, .newline, .newline, .identifier, .eof});
+    }
+
+    test "single character tokens" {
+        const source = "= . , ( ) [ ] { } ` \\ : ; @ $ # ! ? + - * / % & | ^ ~ < >";
+        try checkKinds(source, &.{
+            .assign, .dot, .comma, .lparen, .rparen, .lbracket, .rbracket, .lsquirly,
+            .rsquirly, .backtick, .backslash, .colon, .semicolon, .address, .cash, .pound,
+            .bang, .question, .plus, .minus, .asterisk, .slash, .percent, .ampersand,
+            .pipe, .caret, .tilde, .lesser, .greater, .eof
+        });
+    }
+
+    test "unknown characters are skipped" {
+        const source = "\x01x";
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        const token = try scanner.nextToken();
+        defer token.deinit();
+
+        try expectEqual(.identifier, std.meta.activeTag(token.kind));
+        try expectEqual(1, unit.diagnostics.items.len);
+        try expectEqual(Position.init(1, 1), unit.diagnostics.items[0].span.pos);
+    }
+
+    test "literals" {
+        try checkKinds("x 'a' 'b \"c\" 1 1.5", &.{
+            .identifier, .character, .@"enum", .string, .integer, .float, .eof
+        });
+    }
+
+    // Scans source, which must hold a single integer literal without errors
+    fn scanInteger(source: []const u8) !i64 {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(2, tokens.items.len);
+        return tokens.items[0].kind.integer;
+    }
+
+    // Scans source, expecting a single error at column with message
+    fn expectScanError(source: []const u8, message: []const u8, column: usize) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        try testing.expectError(error.ScanningFailed, scanner.scan());
+        try expectEqual(1, unit.diagnostics.items.len);
+        try expectEqualStrings(message, unit.diagnostics.items[0].message);
+        try expectEqual(Position.init(1, column), unit.diagnostics.items[0].span.pos);
+    }
+
+    test "decimal integers" {
+        try expectEqual(0, try scanInteger("0"));
+        try expectEqual(1234, try scanInteger("1234"));
+    }
+
+    test "hexadecimal integers" {
+        try expectEqual(255, try scanInteger("0xff"));
+        try expectEqual(0xABCDEF, try scanInteger("0XAbCdEf"));
+    }
+
+    test "octal integers" {
+        try expectEqual(8, try scanInteger("0o10"));
+        try expectEqual(0o777, try scanInteger("0O777"));
+    }
+
+    test "binary integers" {
+        try expectEqual(5, try scanInteger("0b101"));
+        try expectEqual(0, try scanInteger("0B0"));
+    }
+
+    test "digit separators" {
+        try expectEqual(0xFFFF, try scanInteger("0xFF_FF"));
+        try expectEqual(0b1010_1010, try scanInteger("0b1010_1010"));
+        try expectEqual(1_000_000, try scanInteger("1_000_000"));
+    }
+
+    test "leading zeros" {
+        try expectEqual(7, try scanInteger("007"));
+        try expectEqual(1, try scanInteger("0x0001"));
+    }
+
+    test "largest integers" {
+        try expectEqual(std.math.maxInt(i64), try scanInteger("9223372036854775807"));
+        try expectEqual(std.math.maxInt(i64), try scanInteger("0x7FFF_FFFF_FFFF_FFFF"));
+        try expectEqual(std.math.maxInt(i64), try scanInteger("0o777777777777777777777"));
+    }
+
+    test "overflowing integers" {
+        try expectScanError("9223372036854775808", "integer literal is too large to fit in 64 bits", 1);
+        try expectScanError("0x8000000000000000", "integer literal is too large to fit in 64 bits", 1);
+        try expectScanError("0b" ++ "1" ** 64, "integer literal is too large to fit in 64 bits", 1);
+    }
+
+    test "invalid binary digits" {
+        try expectScanError("0b102", "invalid digit '2' in base 2 integer literal", 5);
+    }
+
+    test "invalid octal digits" {
+        try expectScanError("0o78", "invalid digit '8' in base 8 integer literal", 4);
+    }
+
+    test "invalid hexadecimal digits" {
+        try expectScanError("0xfg", "invalid digit 'g' in base 16 integer literal", 4);
+    }
+
+    test "prefixes without digits" {
+        try expectScanError("0x", "expected digits after integer literal prefix", 3);
+        try expectScanError("0b_", "expected digits after integer literal prefix", 4);
+    }
+
+    test "every invalid digit is reported" {
+        var input = std.io.fixedBufferStream("0b1239");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        try testing.expectError(error.ScanningFailed, scanner.scan());
+        try expectEqual(3, unit.diagnostics.items.len);
+        try expectEqual(Position.init(1, 6), unit.diagnostics.items[2].span.pos);
+    }
+
+    test "prefixed integers end at non alphanumerics" {
+        try checkKinds("0x1f+0b1 0o7)", &.{.integer, .plus, .integer, .integer, .rparen, .eof});
+    }
+
+    test "zero alone and before other tokens" {
+        try checkKinds("0 0.5 0+1", &.{.integer, .float, .integer, .plus, .integer, .eof});
+    }
+
+    test "prefixed integers are parsed as literals" {
+        var input = std.io.fixedBufferStream("let x = 0x10 + 0b11");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        const sum = ast.root.kind.module.items[0].kind.var_decl.value.?.kind.infix;
+        try expectEqual(16, sum.lhs.kind.literal.integer);
+        try expectEqual(3, sum.rhs.kind.literal.integer);
+    }
+
+    // Scans source, which must hold a single float literal and no errors, along with its warnings
+    fn scanFloat(source: []const u8, warnings: usize) !f64 {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(2, tokens.items.len);
+        try expectEqual(warnings, unit.diagnostics.items.len);
+        return tokens.items[0].kind.float;
+    }
+
+    test "floats" {
+        try expectEqual(0.0, try scanFloat("0.0", 0));
+        try expectEqual(2.5, try scanFloat("2.5", 0));
+        try expectEqual(1_000.5, try scanFloat("1_000.5", 0));
+    }
+
+    test "float exponents" {
+        try expectEqual(1.0e-5, try scanFloat("1.0e-5", 0));
+        try expectEqual(1e10, try scanFloat("1e10", 0));
+        try expectEqual(2.5e3, try scanFloat("2.5E+3", 0));
+    }
+
+    test "bare decimal points" {
+        try expectEqual(1.0, try scanFloat("1.", 0));
+        try checkKinds("1. + 2", &.{.float, .plus, .integer, .eof});
+    }
+
+    test "overflowing floats" {
+        try expectEqual(1e308, try scanFloat("1e308", 0));
+        try testing.expect(std.math.isInf(try scanFloat("1e309", 1)));
+    }
+
+    test "integers and floats are distinguished" {
+        try checkKinds("1 1e10 1.5 1e", &.{.integer, .float, .float, .integer, .identifier, .eof});
+        try checkKinds("0..10", &.{.integer, .range_exc, .integer, .eof});
+    }
+
+    test "integers after a dot are tuple positions" {
+        try checkKinds("t.0.1", &.{.identifier, .dot, .integer, .dot, .integer, .eof});
+        try checkKinds("t.1e5 1.5", &.{.identifier, .dot, .integer, .identifier, .float, .eof});
+    }
+
+    // Scans source, which must hold a single string literal without errors
+    fn expectString(expected: []const u8, source: []const u8) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(2, tokens.items.len);
+        try expectEqualStrings(expected, tokens.items[0].kind.string.items);
+    }
+
+    test "triple quoted strings" {
+        try expectString("one\ntwo", "\"\"\"one\ntwo\"\"\"");
+        try expectString("", "\"\"\"\"\"\"");
+        try expectString("", "\"\"");
+    }
+
+    test "triple quoted strings strip indentation" {
+        const source =
+            \\    """
+            \\    first
+            \\      second
+            \\  third
+            \\    """
+        ;
+
+        try expectString("first\n  second\nthird\n", source);
+    }
+
+    test "triple quoted strings hold quotes" {
+        try expectString("say \"hi\" or \"\" here", "\"\"\"say \"hi\" or \"\" here\"\"\"");
+        try expectString("a\"\"\"b", "\"\"\"a\\\"\"\"b\"\"\"");
+    }
+
+    test "raw strings" {
+        try expectString("\\n", "r\"\\n\"");
+        try expectString("\\", "r\"\\\"");
+        try expectString("\\\\", "r\"\\\\\"");
+        try expectString("C:\\path\\to", "r\"C:\\path\\to\"");
+    }
+
+    test "raw strings differ from escaped strings" {
+        var input = std.io.fixedBufferStream("r\"\\n\" \"\\n\"");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        const raw = tokens.items[0].kind.string.items;
+        const escaped = tokens.items[1].kind.string.items;
+        try testing.expect(!std.mem.eql(u8, raw, escaped));
+        try expectEqualStrings("\n", escaped);
+        try checkKinds("r r2 rx r\"\"", &.{.identifier, .identifier, .identifier, .string, .eof});
+    }
+
+    test "raw triple quoted strings" {
+        try expectString("line\\n\n  \\t", "r\"\"\"line\\n\n  \\t\"\"\"");
+    }
+
+    test "raw triple quoted strings strip indentation to their quotes" {
+        const source =
+            \\    r"""
+            \\     first\n
+            \\       second
+            \\    """
+        ;
+
+        try expectString("first\\n\n  second\n", source);
+    }
+
+    test "unterminated triple quoted strings" {
+        var input = std.io.fixedBufferStream("\"\"\"never\nclosed\"\"");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        try testing.expectError(error.ScanningFailed, scanner.scan());
+        try expectEqual(1, unit.diagnostics.items.len);
+        try expectEqualStrings("unterminated string literal", unit.diagnostics.items[0].message);
+    }
+
+    test "escape sequences" {
+        try expectString("\n\t\r\\\"'\x00", "\"\\n\\t\\r\\\\\\\"\\'\\0\"");
+        try expectString("a\"b", "\"\"\"a\\\"b\"\"\"");
+    }
+
+    test "unicode escapes" {
+        try expectString("A", "\"\\u{41}\"");
+        try expectString("\u{e9}", "\"\\u{e9}\"");
+        try expectString("\u{10FFFF}", "\"\\u{10FFFF}\"");
+        try expectString("\u{1F600}", "\"\\u{01F600}\"");
+    }
+
+    test "invalid unicode escapes" {
+        try expectScanError("\"ab\\u{D800}\"", "unicode escape is a surrogate code point", 4);
+        try expectScanError("\"\\u{110000}\"", "unicode escape is larger than 10FFFF", 2);
+        try expectScanError("\"\\u{1000000}\"", "unicode escape is larger than 10FFFF", 2);
+        try expectScanError("\"\\u{}\"", "unicode escape has no digits", 2);
+        try expectScanError("\"\\u41\"", "expected '{' after unicode escape", 2);
+        try expectScanError("\"\\u{41\"", "unterminated unicode escape", 2);
+    }
+
+    test "unrecognized escapes are reported at the backslash" {
+        try expectScanError("\"abc\\q\"", "unrecognized escape character: \\q", 5);
+        try expectString("\\q", "r\"\\q\"");
+    }
+
+    test "every keyword" {
+        for (std.enums.values(Token.Keyword)) |keyword| {
+            var input = std.io.fixedBufferStream(keyword.toStr());
+
+            var buf: [10]u8 = undefined;
+            var output = std.io.fixedBufferStream(&buf);
+
+            var unit = try Compiler.Unit.init(.testing(input.reader().any(), output.writer().any()));
+            defer unit.deinit();
+
+            var scanner = try Scanner.init(unit);
+            defer scanner.deinit();
+
+            const token = try scanner.nextToken();
+            defer token.deinit();
+
+            try expectEqual(Token.Kind{ .keyword = keyword }, token.kind);
+        }
+    }
+
+    test "keywords are only matched by whole identifiers" {
+        var input = std.io.fixedBufferStream("fn fns lets return1 trait");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(6, tokens.items.len);
+        try expectEqual(Token.Kind{ .keyword = .@"fn" }, tokens.items[0].kind);
+        for (tokens.items[1..4], [_][]const u8{"fns", "lets", "return1"}) |token, name| {
+            try expectEqualStrings(name, unit.interner.get(token.kind.identifier));
+        }
+        try expectEqual(Token.Kind{ .keyword = .trait }, tokens.items[4].kind);
+    }
+
+    test "identifiers beginning with underscores" {
+        var input = std.io.fixedBufferStream("_ _unused __x1");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(4, tokens.items.len);
+        for (tokens.items[0..3], [_][]const u8{"_", "_unused", "__x1"}) |token, name| {
+            try expectEqualStrings(name, unit.interner.get(token.kind.identifier));
+        }
+    }
+
+    test "every mode" {
+        for (std.enums.values(Token.Mode)) |mode| {
+            var input = std.io.fixedBufferStream(mode.toStr());
+
+            var buf: [10]u8 = undefined;
+            var output = std.io.fixedBufferStream(&buf);
+
+            var unit = try Compiler.Unit.init(.testing(input.reader().any(), output.writer().any()));
+            defer unit.deinit();
+
+            var scanner = try Scanner.init(unit);
+            defer scanner.deinit();
+
+            const token = try scanner.nextToken();
+            defer token.deinit();
+
+            try expectEqual(Token.Kind{ .mode = mode }, token.kind);
+        }
+    }
+
+    test "scan" {
+        const source = "let x = 1";
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        const expected = [_]Token{
+            .init(.{ .keyword = .let }, "test source", .init(1, 1)),
+            .init(try .initIdentifier("x", &unit.interner), "test source", .init(1, 5)),
+            .init(.assign, "test source", .init(1, 7)),
+            .init(.{ .integer = 1 }, "test source", .init(1, 9)),
+            .init(.eof, "test source", .init(1, 10))
+        };
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(expected.len, tokens.items.len);
+        for (&expected, tokens.items) |*e, *token| {
+            try compareTokens(e, token);
+        }
+    }
+
+    test "token spans" {
+        const source = "let x\n  = \"hi\" 12.5";
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        const expected = [_][]const u8{"let", "x", "\n", "=", "\"hi\"", "12.5", ""};
+
+        try expectEqual(expected.len, tokens.items.len);
+        for (expected, tokens.items) |slice, token| {
+            try expectEqualStrings(slice, token.span.slice(source));
+            try expectEqual(token.span.locate(source), token.span.pos);
+        }
+    }
+
+    test "error recovery" {
+        const source = "let \x01 = \x02\n\"unterminated\nx \x03 \x04";
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        try testing.expectError(error.ScanningFailed, scanner.scan());
+        try expectEqual(5, unit.diagnostics.items.len);
+        try expectEqualStrings("unterminated string literal", unit.diagnostics.items[2].message);
+    }
+
+    test "unterminated string closes at end of line" {
+        const source = "\"abc\nx";
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        const expected = [_]std.meta.Tag(Token.Kind){.string, .newline, .identifier, .eof};
+        for (expected) |expected_kind| {
+            const token = try scanner.nextToken();
+            defer token.deinit();
+
+            try expectEqual(expected_kind, std.meta.activeTag(token.kind));
+        }
+
+        try expectEqual(1, unit.diagnostics.items.len);
+    }
+
+    test "identifiers" {
+        var input = std.io.fixedBufferStream("x abc_1 camelCase X9");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(5, tokens.items.len);
+        for (tokens.items[0..4], [_][]const u8{"x", "abc_1", "camelCase", "X9"}) |token, name| {
+            try expectEqualStrings(name, unit.interner.get(token.kind.identifier));
+        }
+    }
+
+    test "identifiers are interned once" {
+        var input = std.io.fixedBufferStream("x y x");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(tokens.items[0].kind.identifier, tokens.items[2].kind.identifier);
+        try testing.expect(tokens.items[0].kind.identifier != tokens.items[1].kind.identifier);
+    }
+
+    test "comparison operators" {
+        try checkKinds("< <= > >= == !=", &.{
+            .lesser, .lesser_eq, .greater, .greater_eq, .equal, .not_equal, .eof
+        });
+    }
+
+    test "bitwise operators" {
+        try checkKinds("& | ^ ~ << >>", &.{.ampersand, .pipe, .caret, .tilde, .lshift, .rshift, .eof});
+    }
+
+    test "arithmetic operators" {
+        try checkKinds("+ - * / % ++ -- **", &.{
+            .plus, .minus, .asterisk, .slash, .percent, .increment, .decrement, .square, .eof
+        });
+    }
+
+    test "punctuation without whitespace" {
+        try checkKinds("f(a,b)[0]{}", &.{
+            .identifier, .lparen, .identifier, .comma, .identifier, .rparen, .lbracket,
+            .integer, .rbracket, .lsquirly, .rsquirly, .eof
+        });
+    }
+
+    test "keywords and modes next to punctuation" {
+        try checkKinds("let(mut)", &.{.keyword, .lparen, .mode, .rparen, .eof});
+        try checkKinds("fn{loc}", &.{.keyword, .lsquirly, .mode, .rsquirly, .eof});
+    }
+
+    test "eof is returned repeatedly" {
+        var input = std.io.fixedBufferStream("x");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        const token = try scanner.nextToken();
+        defer token.deinit();
+        try expectEqual(.identifier, std.meta.activeTag(token.kind));
+
+        for (0..3) |_| {
+            const eof = try scanner.nextToken();
+            try expectEqual(.eof, std.meta.activeTag(eof.kind));
+            try expectEqual(Position.init(1, 2), eof.span.pos);
+        }
+    }
+
+    test "newline positions" {
+        var input = std.io.fixedBufferStream("a\n  b\n\nc");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        const expected = [_]Position{
+            .init(1, 1), .init(1, 2), .init(2, 3), .init(2, 4), .init(3, 1), .init(4, 1), .init(4, 2)
+        };
+
+        try expectEqual(expected.len, tokens.items.len);
+        for (expected, tokens.items) |pos, token| try expectEqual(pos, token.span.pos);
+    }
+
+    test "character literal escapes" {
+        var input = std.io.fixedBufferStream("'\\t' '\\\\' '\\''");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        var tokens = try scanner.scan();
+        defer {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        }
+
+        try expectEqual(4, tokens.items.len);
+        for (tokens.items[0..3], "\t\\'") |token, character| {
+            try expectEqual(character, token.kind.character);
+        }
+    }
+
+    test "too many characters in character literals" {
+        try expectScanError("'\\nx'", "too many characters in character literal", 5);
+    }
+
+    test "enum literals end at non alphanumerics" {
+        try checkKinds("'ok.'err", &.{.@"enum", .dot, .@"enum", .eof});
+        try checkKinds("'a1_b+'c", &.{.@"enum", .plus, .@"enum", .eof});
+    }
+
+    test "unterminated strings at the end of input" {
+        try expectScanError("\"abc", "unterminated string literal", 5);
+    }
+
+    test "unterminated multi strings" {
+        try expectScanError("\"|abc", "unterminated string literal", 6);
+    }
+
+    test "multi strings missing a line delimiter" {
+        var input = std.io.fixedBufferStream("\"|a\nb\"");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        try testing.expectError(error.ScanningFailed, scanner.scan());
+        try expectEqual(1, unit.diagnostics.items.len);
+        try expectEqualStrings("missing start of line delimiter '|'", unit.diagnostics.items[0].message);
+        try expectEqual(Position.init(2, 1), unit.diagnostics.items[0].span.pos);
+    }
+
+    test "escapes in multi strings" {
+        try expectString("a\tb|c", "\"|a\\tb\\|c\"");
+    }
+
+    test "unterminated escapes" {
+        var input = std.io.fixedBufferStream("\"abc\\");
+
+        var unit = try Compiler.Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        // The string is unterminated as well, as the input ends within it
+        try testing.expectError(error.ScanningFailed, scanner.scan());
+        try expectEqual(2, unit.diagnostics.items.len);
+        try expectEqualStrings("unterminated escape character", unit.diagnostics.items[0].message);
+        try expectEqual(Position.init(1, 5), unit.diagnostics.items[0].span.pos);
+        try expectEqualStrings("unterminated string literal", unit.diagnostics.items[1].message);
+    }
+
+    test "integers followed by identifiers" {
+        try checkKinds("12abc", &.{.integer, .identifier, .eof});
+        try checkKinds("0b1 b", &.{.integer, .identifier, .eof});
+    }
+
+    test "trailing digit separators" {
+        try expectEqual(1000, try scanInteger("1_000_"));
+        try expectEqual(255, try scanInteger("0x_ff_"));
+    }
+
+    test "negative numbers are a minus and a literal" {
+        try checkKinds("-1 -2.5", &.{.minus, .integer, .minus, .float, .eof});
+        try checkKinds("x-1", &.{.identifier, .minus, .integer, .eof});
+    }
+
+    test "digit separators in fractions and exponents" {
+        try expectEqual(1.0005, try scanFloat("1.000_5", 0));
+        try expectEqual(1e10, try scanFloat("1e1_0", 0));
+    }
+
+    test "floats before ranges and fields" {
+        try checkKinds("1.5..2", &.{.float, .range_exc, .integer, .eof});
+        try checkKinds("1..=5", &.{.integer, .range_inc, .integer, .eof});
+        try checkKinds("1.5.0", &.{.float, .dot, .integer, .eof});
+    }
+
+    test "comments at the end of input" {
+        try checkKinds("x // trailing", &.{.identifier, .eof});
+        try checkKinds("x /", &.{.identifier, .slash, .eof});
+        try checkKinds("///", &.{.doc_comment, .eof});
+        try checkKinds("//// x", &.{.doc_comment, .eof});
+    }
 };
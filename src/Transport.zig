@@ -69,6 +69,31 @@ pub fn read(self: *Transport, buffer: []u8) !usize {
     return try self.br.?.reader().read(buffer);
 }
 
+/// Reads the rest of the input, the caller owns the returned memory
+pub fn readAll(self: *Transport, allocator: Allocator) ![]u8 {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    return try self.br.?.reader().readAllAlloc(allocator, std.math.maxInt(usize));
+}
+
+/// Reads up to the next newline, which is discarded. The caller owns the returned
+/// memory, which is null at the end of the input
+pub fn readLine(self: *Transport, allocator: Allocator) !?[]u8 {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    return try self.br.?.reader().readUntilDelimiterOrEofAlloc(allocator, '\n', std.math.maxInt(usize));
+}
+
+/// Fills buffer, failing if the input ends first
+pub fn readNoEof(self: *Transport, buffer: []u8) !void {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    try self.br.?.reader().readNoEof(buffer);
+}
+
 pub fn readByte(self: *Transport) !u8 {
     self.mutex.lock();
     defer self.mutex.unlock();
@@ -89,7 +114,7 @@ pub fn writeAll(self: *Transport, msg: []const u8) !void {
     defer self.mutex.unlock();
 
     try self.bw.?.writer().writeAll(msg);
-    try self.bw.flush();
+    try self.bw.?.flush();
 }
 
 pub fn writeAllNoFlush(self: *Transport, msg: []const u8) !void {
@@ -0,0 +1,4981 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("prelude.zig");
+const Ast = ruka.Ast;
+const Diagnostic = ruka.Diagnostic;
+const Node = ruka.Node;
+const Span = ruka.Span;
+const stdlib = ruka.stdlib;
+const Symbol = ruka.Symbol;
+const Target = ruka.Target;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+ast: *Ast,
+unit: *Unit,
+types: *TypeTable,
+/// The return type of the function currently being checked
+return_type: ?Type,
+/// The number of loops enclosing the node being checked, within the current function
+loops: usize,
+/// Whether the node being checked is within the body of a defer, within the current function
+deferring: bool,
+/// Whether the node being checked is within an unsafe block, within the current function
+in_unsafe: bool,
+/// Whether the node being checked is within a comptime block
+in_comptime: bool,
+/// Whether the node being checked is within the body of an async function, where
+/// futures can be awaited
+in_async: bool,
+/// The labels of the function or closure being checked
+labels: ?*const Labels,
+/// The depth of the scope the innermost closure enclosing the node being checked
+/// is defined in, within the current function. The bindings of that scope and
+/// those enclosing it, other than the top level, are captured
+closure_depth: ?usize,
+/// The enums every program can refer to, which are defined before it is checked
+prelude: Prelude,
+/// The instances of generic structs created while the fields of the definitions
+/// in a scope are resolved, their fields are substituted once all of those are
+deferred_instances: ?*ArrayList(*Type.Struct),
+/// The type variables of bindings declared without an annotation
+inference: Inference,
+/// The values of the const declarations checked so far
+consts: ConstEval,
+/// The nodes whose types refer to type variables which aren't resolved yet
+unresolved: ArrayList(Node.Id),
+/// The names of top level bindings whose types are inferred, their symbols are
+/// updated once the variables are resolved
+inferred_bindings: ArrayList([]const u8),
+
+allocator: Allocator,
+
+const TypeChecker = @This();
+
+pub const ConstEval = @import("typechecker/ConstEval.zig");
+pub const ExhaustivenessChecker = @import("typechecker/ExhaustivenessChecker.zig");
+pub const TypeTable = @import("typechecker/TypeTable.zig");
+pub const Inference = @import("typechecker/Inference.zig");
+pub const Labels = @import("typechecker/Labels.zig");
+
+const log = std.log.scoped(.typechecker);
+
+// Dispatches each node to the visit method for its kind
+const Walk = Visitor(TypeChecker, CheckError, Type);
+
+// Explicit so the mutually recursive checking functions can resolve their error sets
+const CheckError = Allocator.Error;
+
+/// The types substituted for the type parameters of a generic function or struct, by name
+const Substitution = std.StringHashMap(Type);
+
+/// Represents the type of a value
+pub const Type = union(enum) {
+    @"i8",
+    @"i16",
+    @"i32",
+    @"i64",
+    @"f32",
+    @"f64",
+    @"bool",
+    str,
+    char,
+    unit,
+    function: Function,
+    range: *const Type,
+    array: Array,
+    /// A view of some of the elements of an array, `arr[a..b]`
+    slice: *const Type,
+    /// A reference to a var binding or a field or element of one, `&x`
+    reference: *const Type,
+    /// The types of the elements of tuples, in order. The empty tuple is unit
+    tuple: []const Type,
+    @"struct": *const Struct,
+    @"enum": *const Enum,
+    /// Any type implementing the trait, `impl Printable`. Only the trait's methods
+    /// can be called on its values
+    trait: *const Trait,
+    /// What calling an async function returns, `Future<T>`, which gives the T the
+    /// function returns once awaited
+    future: *const Type,
+    /// A type parameter of the generic function or struct being checked, it is
+    /// only compatible with itself as it stands for any type
+    parameter: []const u8,
+    /// The type of a binding declared without an annotation, which is inferred
+    /// from how the binding is used
+    variable: Inference.TypeVar,
+    /// The type of expressions which failed to check, it is compatible with
+    /// every type so a single mistake is only reported once
+    invalid,
+
+    /// Generic functions are instantiated where they are called, substituting the
+    /// types of the arguments for their type parameters
+    pub const Function = struct {
+        type_params: []const []const u8 = &.{},
+        params: []const Type,
+        ret: *const Type,
+        /// Whether any number of arguments more than params can be passed, as
+        /// extern functions like printf take
+        variadic: bool = false
+    };
+
+    /// Arrays hold a number of elements known when they are checked, `[T; N]`
+    pub const Array = struct {
+        element: *const Type,
+        len: usize
+    };
+
+    /// Structs are nominal, each definition is a distinct type. Generic structs
+    /// are definitions of types, each of their instances is a distinct type
+    pub const Struct = struct {
+        name: []const u8,
+        type_params: []const []const u8 = &.{},
+        fields: []const Field,
+        /// The generic struct this is an instance of, and the types substituted for
+        /// its type parameters
+        generic: ?*const Struct = null,
+        arguments: []const Type = &.{},
+        /// Whether the fields overlay the same memory, as a union's do, so only
+        /// the field last written holds a value
+        overlaid: bool = false,
+
+        pub const Field = struct {
+            name: []const u8,
+            @"type": Type
+        };
+
+        pub fn fieldIndex(self: *const Struct, name: []const u8) ?usize {
+            return indexOfField(self.fields, name);
+        }
+
+        pub fn indexOfField(fields: []const Field, name: []const u8) ?usize {
+            for (fields, 0..) |field, i| if (std.mem.eql(u8, field.name, name)) return i;
+            return null;
+        }
+    };
+
+    /// Enums are nominal like structs, their values are one of the variants
+    pub const Enum = struct {
+        name: []const u8,
+        type_params: []const []const u8 = &.{},
+        variants: []const Variant,
+        /// The generic enum this is an instance of, and the types substituted for
+        /// its type parameters
+        generic: ?*const Enum = null,
+        arguments: []const Type = &.{},
+
+        pub const Variant = struct {
+            name: []const u8,
+            fields: Fields,
+
+            pub const Fields = union(enum) {
+                unit,
+                tuple: []const Type,
+                @"struct": []const Struct.Field
+            };
+        };
+
+        pub fn variantIndex(self: *const Enum, name: []const u8) ?usize {
+            for (self.variants, 0..) |variant, i| if (std.mem.eql(u8, variant.name, name)) return i;
+            return null;
+        }
+    };
+
+    /// Traits are nominal like structs, a struct implements one when an impl of it
+    /// is written for the struct
+    pub const Trait = struct {
+        name: []const u8,
+        methods: []const Method,
+
+        pub const Method = struct {
+            name: []const u8,
+            /// Taking the trait or a reference to it as its first parameter, which
+            /// the struct takes the place of in each impl
+            function: Function,
+            /// Whether the trait defines the method for impls which don't
+            default: bool
+        };
+
+        pub fn methodIndex(self: *const Trait, name: []const u8) ?usize {
+            for (self.methods, 0..) |method, i| if (std.mem.eql(u8, method.name, name)) return i;
+            return null;
+        }
+    };
+
+    /// Finds the builtin type called name
+    pub fn fromName(name: []const u8) ?Type {
+        return builtins.get(name);
+    }
+
+    pub fn eql(self: Type, other: Type) bool {
+        if (std.meta.activeTag(self) != std.meta.activeTag(other)) return false;
+
+        return switch (self) {
+            .function => |function| {
+                if (function.type_params.len != other.function.type_params.len) return false;
+                if (function.params.len != other.function.params.len) return false;
+                if (function.variadic != other.function.variadic) return false;
+                for (function.params, other.function.params) |param, other_param| {
+                    if (!param.eql(other_param)) return false;
+                }
+
+                return function.ret.eql(other.function.ret.*);
+            },
+            .range => |element| element.eql(other.range.*),
+            .array => |array| array.len == other.array.len and array.element.eql(other.array.element.*),
+            .slice => |element| element.eql(other.slice.*),
+            .reference => |element| element.eql(other.reference.*),
+            .tuple => |elements| {
+                if (elements.len != other.tuple.len) return false;
+                for (elements, other.tuple) |element, other_element| {
+                    if (!element.eql(other_element)) return false;
+                }
+
+                return true;
+            },
+            .@"struct" => |structure| structure == other.@"struct",
+            .@"enum" => |enumeration| enumeration == other.@"enum",
+            .trait => |trait| trait == other.trait,
+            .future => |value| value.eql(other.future.*),
+            .parameter => |name| std.mem.eql(u8, name, other.parameter),
+            .variable => |variable| variable == other.variable,
+            else => true
+        };
+    }
+
+    pub fn isInteger(self: Type) bool {
+        return switch (self) {
+            .@"i8", .@"i16", .@"i32", .@"i64" => true,
+            else => false
+        };
+    }
+
+    pub fn isFloat(self: Type) bool {
+        return switch (self) {
+            .@"f32", .@"f64" => true,
+            else => false
+        };
+    }
+
+    pub fn isNumeric(self: Type) bool {
+        return self.isInteger() or self.isFloat();
+    }
+
+    /// The size and alignment of values of a type, in bytes
+    pub const Layout = struct {
+        size: u64,
+        alignment: u64,
+
+        fn scalar(size: u64) Layout {
+            return .{ .size = size, .alignment = size };
+        }
+    };
+
+    /// How C lays out values of the type on a target whose pointers are
+    /// pointer_size bytes, null for types without a fixed layout. Scalars are
+    /// aligned to their size
+    pub fn layout(self: Type, pointer_size: u8) ?Layout {
+        return switch (self) {
+            .@"i8", .@"bool", .char => Layout.scalar(1),
+            .@"i16" => Layout.scalar(2),
+            .@"i32", .@"f32" => Layout.scalar(4),
+            .@"i64", .@"f64" => Layout.scalar(8),
+            .str, .reference => Layout.scalar(pointer_size),
+            .array => |array| {
+                if (array.len == 0) return null;
+
+                const element = array.element.layout(pointer_size) orelse return null;
+                return .{ .size = element.size * array.len, .alignment = element.alignment };
+            },
+            .tuple => |elements| {
+                var aggregate: Aggregate = .{};
+                for (elements) |element| aggregate.add(element.layout(pointer_size) orelse return null);
+
+                return aggregate.finish();
+            },
+            .@"struct" => |structure| {
+                if (structure.overlaid) return overlay(structure.fields, pointer_size);
+
+                var aggregate: Aggregate = .{};
+                for (structure.fields) |field| aggregate.add(field.@"type".layout(pointer_size) orelse return null);
+
+                return aggregate.finish();
+            },
+            else => null
+        };
+    }
+
+    // Each field is placed at the first offset aligned for it, and the whole is
+    // padded to a multiple of the largest alignment, so arrays of it stay aligned
+    const Aggregate = struct {
+        size: u64 = 0,
+        alignment: u64 = 1,
+        fields: usize = 0,
+
+        fn add(self: *Aggregate, field: Layout) void {
+            self.size = std.mem.alignForward(u64, self.size, field.alignment) + field.size;
+            self.alignment = @max(self.alignment, field.alignment);
+            self.fields = self.fields + 1;
+        }
+
+        // C has no empty structs
+        fn finish(self: Aggregate) ?Layout {
+            if (self.fields == 0) return null;
+            return .{ .size = std.mem.alignForward(u64, self.size, self.alignment), .alignment = self.alignment };
+        }
+    };
+
+    // The fields of a union share its storage, so it is as large as the largest of
+    // them and aligned for the most aligned, padded like structs
+    fn overlay(fields: []const Struct.Field, pointer_size: u8) ?Layout {
+        // C has no empty unions
+        if (fields.len == 0) return null;
+
+        var size: u64 = 0;
+        var alignment: u64 = 1;
+        for (fields) |field| {
+            const field_layout = field.@"type".layout(pointer_size) orelse return null;
+            size = @max(size, field_layout.size);
+            alignment = @max(alignment, field_layout.alignment);
+        }
+
+        return .{ .size = std.mem.alignForward(u64, size, alignment), .alignment = alignment };
+    }
+
+    pub fn format(
+        self: Type,
+        comptime _: []const u8,
+        _: std.fmt.FormatOptions,
+        writer: anytype
+    ) @TypeOf(writer).Error!void {
+        switch (self) {
+            .function => |function| {
+                try writer.writeAll("fn");
+                if (function.type_params.len > 0) {
+                    try writer.writeByte('<');
+                    for (function.type_params, 0..) |type_param, i| {
+                        if (i > 0) try writer.writeAll(", ");
+                        try writer.writeAll(type_param);
+                    }
+                    try writer.writeByte('>');
+                }
+                try writer.writeByte('(');
+                for (function.params, 0..) |param, i| {
+                    if (i > 0) try writer.writeAll(", ");
+                    try writer.print("{}", .{param});
+                }
+                if (function.variadic) try writer.writeAll(if (function.params.len > 0) ", ..." else "...");
+                try writer.print(") -> {}", .{function.ret.*});
+            },
+            .range => |element| try writer.print("range({})", .{element.*}),
+            .array => |array| try writer.print("[{}; {}]", .{array.element.*, array.len}),
+            .slice => |element| try writer.print("[{}]", .{element.*}),
+            .reference => |element| try writer.print("&{}", .{element.*}),
+            .future => |value| try writer.print("Future<{}>", .{value.*}),
+            .tuple => |elements| {
+                try writer.writeByte('(');
+                for (elements, 0..) |element, i| {
+                    if (i > 0) try writer.writeAll(", ");
+                    try writer.print("{}", .{element});
+                }
+                if (elements.len == 1) try writer.writeByte(',');
+                try writer.writeByte(')');
+            },
+            .@"struct" => |structure| {
+                try writer.writeAll(structure.name);
+                try formatArguments(structure.arguments, writer);
+            },
+            .parameter => |name| try writer.writeAll(name),
+            .variable => try writer.writeAll("_"),
+            .@"enum" => |enumeration| {
+                try writer.writeAll(enumeration.name);
+                try formatArguments(enumeration.arguments, writer);
+            },
+            .trait => |trait| try writer.print("impl {s}", .{trait.name}),
+            .unit => try writer.writeAll("()"),
+            else => try writer.writeAll(@tagName(self))
+        }
+    }
+
+    fn formatArguments(arguments: []const Type, writer: anytype) @TypeOf(writer).Error!void {
+        if (arguments.len == 0) return;
+
+        try writer.writeByte('<');
+        for (arguments, 0..) |argument, i| {
+            if (i > 0) try writer.writeAll(", ");
+            try writer.print("{}", .{argument});
+        }
+        try writer.writeByte('>');
+    }
+};
+
+// Map representing the builtin types and their names
+const builtins = std.StaticStringMap(Type).initComptime(.{
+    .{"i8", .@"i8"},
+    .{"i16", .@"i16"},
+    .{"i32", .@"i32"},
+    .{"i64", .@"i64"},
+    .{"f32", .@"f32"},
+    .{"f64", .@"f64"},
+    .{"bool", .@"bool"},
+    .{"str", .str},
+    .{"char", .char},
+    .{"unit", .unit}
+});
+
+pub fn init(unit: *Unit, ast: *Ast) !*TypeChecker {
+    const checker = try unit.allocator.create(TypeChecker);
+
+    checker.* = .{
+        .ast = ast,
+        .unit = unit,
+        .types = undefined,
+        .return_type = null,
+        .loops = 0,
+        .deferring = false,
+        .in_unsafe = false,
+        .in_comptime = false,
+        .in_async = false,
+        .labels = null,
+        .closure_depth = null,
+        .prelude = undefined,
+        .deferred_instances = null,
+        .consts = undefined,
+        .inference = .init(unit.allocator),
+        .unresolved = .init(unit.allocator),
+        .inferred_bindings = .init(unit.allocator),
+        .allocator = unit.allocator
+    };
+
+    return checker;
+}
+
+pub fn deinit(self: *TypeChecker) void {
+    self.inference.deinit();
+    self.unresolved.deinit();
+    self.inferred_bindings.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Finds the type of every node in the ast. Checking continues past errors so all
+/// of them are recorded in the unit, but fails if any were encountered
+pub fn check(self: *TypeChecker) !*TypeTable {
+    const error_count = self.unit.errorCount();
+
+    self.types = try TypeTable.init(self.allocator);
+    errdefer self.types.deinit();
+
+    self.consts = .init(self.unit, self.types);
+    defer self.consts.deinit();
+
+    try self.declarePrelude();
+    _ = try self.checkNode(self.ast.root);
+    try self.resolveInferred(0);
+    try self.redefineInferred();
+
+    // Which values a match covers is only known once the type of its subject is
+    var exhaustiveness = try ExhaustivenessChecker.init(self.unit, self.types);
+    defer exhaustiveness.deinit();
+    try exhaustiveness.check(self.ast);
+
+    const type_errors = self.unit.errorCount() - error_count;
+    if (type_errors > 0) {
+        log.err("{s}: type checking failed with {} error(s)", .{self.unit.input, type_errors});
+        return error.TypeCheckingFailed;
+    }
+
+    return self.types;
+}
+
+fn createError(self: *TypeChecker, span: Span, msg: []const u8) CheckError!void {
+    try self.unit.createError(msg, span);
+}
+
+const Prelude = struct {
+    /// `enum Result<T, E> { Ok(T), Err(E) }`, the result of functions which can fail
+    result: *const Type.Enum,
+    /// `enum Option<T> { Some(T), None }`, a value which may be missing
+    option: *const Type.Enum
+};
+
+// The prelude's enums are bound in the top level scope, so programs can't define
+// types of the same names
+fn declarePrelude(self: *TypeChecker) CheckError!void {
+    const result = try self.types.genericEnumeration("Result", &.{"T", "E"});
+    try self.types.setVariants(result, &.{
+        .{ .name = "Ok", .fields = .{ .tuple = &.{try self.types.parameter("T")} } },
+        .{ .name = "Err", .fields = .{ .tuple = &.{try self.types.parameter("E")} } }
+    });
+
+    const option = try self.types.genericEnumeration("Option", &.{"T"});
+    try self.types.setVariants(option, &.{
+        .{ .name = "Some", .fields = .{ .tuple = &.{try self.types.parameter("T")} } },
+        .{ .name = "None", .fields = .unit }
+    });
+
+    self.prelude = .{ .result = result, .option = option };
+
+    try self.declarePreludeSymbol(result.name, .@"type", .{ .@"enum" = result });
+    try self.declarePreludeSymbol(option.name, .@"type", .{ .@"enum" = option });
+
+    // assert may also be given a message and fmt any number of arguments, which
+    // calls of them are checked for
+    try self.declarePreludeSymbol("assert", .function, try self.types.function(&.{.@"bool"}, .unit));
+    try self.declarePreludeSymbol("fmt", .function, try self.types.function(&.{.str}, .str));
+
+    // block_on runs a future to completion outside of async functions, giving the
+    // value it produces
+    const produced = try self.types.parameter("T");
+    try self.declarePreludeSymbol("block_on", .function, try self.types.genericFunction(&.{"T"}, &.{try self.types.future(produced)}, produced));
+
+    // The functions of the built in modules are defined as `module.function`, like
+    // those of impls
+    for (stdlib.modules) |module| {
+        try self.declarePreludeSymbol(module.name, .module, null);
+
+        for (module.functions) |*function| {
+            const name = try std.fmt.allocPrint(self.allocator, "{s}.{s}", .{module.name, function.name});
+            defer self.allocator.free(name);
+
+            try self.declarePreludeSymbol(name, .function, try self.builtinType(function));
+        }
+    }
+}
+
+fn declarePreludeSymbol(self: *TypeChecker, name: []const u8, kind: Symbol.Kind, @"type": ?Type) CheckError!void {
+    const id = try self.unit.interner.intern(name);
+    const symbol: Symbol = .{
+        .kind = kind,
+        .span = self.ast.root.span,
+        .@"type" = @"type"
+    };
+
+    // Units checked more than once define the prelude each time
+    self.unit.symbols.define(id, symbol) catch |err| switch (err) {
+        error.DuplicateSymbol => try self.unit.symbols.redefine(id, symbol),
+        else => |e| return e
+    };
+}
+
+// Functions taking numbers are generic over their type, which is checked to be
+// numeric once it is inferred for a call
+fn builtinType(self: *TypeChecker, function: *const stdlib.Function) CheckError!Type {
+    const number = try self.types.parameter("T");
+
+    const params = try self.allocator.alloc(Type, function.params.len);
+    defer self.allocator.free(params);
+
+    var generic = false;
+    for (params, function.params) |*param, kind| param.* = switch (kind) {
+        .number => block: {
+            generic = true;
+            break :block number;
+        },
+        .float => .@"f64",
+        .str => .str
+    };
+
+    const ret: Type = switch (function.ret) {
+        .number => number,
+        .float => .@"f64",
+        .unit => .unit,
+        .line => try self.instantiateEnum(self.prelude.result, &.{.str, .str})
+    };
+
+    return try self.types.genericFunction(if (generic) &.{"T"} else &.{}, params, ret);
+}
+
+/// The function of a built in module callee refers to, null if it refers to
+/// something else
+fn builtinCalled(self: *const TypeChecker, callee: *const Node) ?*const stdlib.Function {
+    const access = switch (callee.kind) {
+        .field_access => |access| access,
+        else => return null
+    };
+    if (access.object.kind != .identifier or !self.isModule(access.object.kind.identifier)) return null;
+
+    return stdlib.find(access.object.kind.identifier, access.field);
+}
+
+/// Whether callee refers to the prelude's function called name, rather than a
+/// binding shadowing it
+fn isPreludeFunction(self: *const TypeChecker, callee: *const Node, name: []const u8) bool {
+    if (callee.kind != .identifier or !std.mem.eql(u8, callee.kind.identifier, name)) return false;
+
+    // The prelude's symbols span the root, which covers nothing
+    const symbol = self.lookup(name) orelse return false;
+    return symbol.kind == .function and symbol.span.end <= symbol.span.start;
+}
+
+/// Whether name is bound to a built in module, which bindings can shadow
+fn isModule(self: *const TypeChecker, name: []const u8) bool {
+    const symbol = self.lookup(name) orelse return false;
+    return symbol.kind == .module;
+}
+
+/// The type arguments of type if it is an instance of the generic enum definition,
+/// which for Result are the type of its values then its error
+fn instanceArguments(definition: *const Type.Enum, @"type": Type) ?[]const Type {
+    return switch (@"type") {
+        .@"enum" => |enumeration| if (enumeration.generic == definition) enumeration.arguments else null,
+        else => null
+    };
+}
+
+fn define(
+    self: *TypeChecker,
+    span: Span,
+    name: []const u8,
+    kind: Symbol.Kind,
+    @"type": Type
+) CheckError!void {
+    const id = try self.unit.interner.intern(name);
+    const symbol: Symbol = .{
+        .kind = kind,
+        .span = span,
+        .@"type" = @"type"
+    };
+
+    self.unit.symbols.define(id, symbol) catch |err| switch (err) {
+        // Bindings shadow earlier bindings in the same scope, which the parser warns about
+        error.DuplicateSymbol => if (isBinding(kind) and isBinding(self.unit.symbols.lookupLocal(id).?.kind)) {
+            try self.unit.symbols.redefine(id, symbol);
+        } else {
+            try self.createError(span, "name is already defined in this scope");
+        },
+        else => |e| return e
+    };
+}
+
+fn isBinding(kind: Symbol.Kind) bool {
+    return kind == .variable or kind == .constant;
+}
+
+/// Finds the symbol bound to name, names which were never interned can't be bound
+fn lookup(self: *const TypeChecker, name: []const u8) ?*const Symbol {
+    const id = self.unit.interner.find(name) orelse return null;
+    return self.unit.symbols.lookup(id);
+}
+
+fn checkNode(self: *TypeChecker, node: *const Node) CheckError!Type {
+    // Type variables are replaced by what has been inferred for them so far
+    const @"type" = self.inference.resolve(try Walk.visit(self, node));
+
+    try self.record(node.id, @"type");
+    return @"type";
+}
+
+fn record(self: *TypeChecker, id: Node.Id, @"type": Type) CheckError!void {
+    try self.types.put(id, @"type");
+    if (containsVariable(@"type")) try self.unresolved.append(id);
+}
+
+fn containsVariable(@"type": Type) bool {
+    return switch (@"type") {
+        .variable => true,
+        .range, .slice, .reference, .future => |element| containsVariable(element.*),
+        .array => |array| containsVariable(array.element.*),
+        .tuple => |elements| {
+            for (elements) |element| if (containsVariable(element)) return true;
+            return false;
+        },
+        .function => |function| {
+            for (function.params) |param| if (containsVariable(param)) return true;
+            return containsVariable(function.ret.*);
+        },
+        .@"struct" => |structure| {
+            for (structure.arguments) |argument| if (containsVariable(argument)) return true;
+            return false;
+        },
+        .@"enum" => |enumeration| {
+            for (enumeration.arguments) |argument| if (containsVariable(argument)) return true;
+            return false;
+        },
+        else => false
+    };
+}
+
+/// Replaces the type variables in type with the types inferred for them
+fn substituteVariables(self: *TypeChecker, @"type": Type) CheckError!Type {
+    if (!containsVariable(@"type")) return @"type";
+
+    switch (@"type") {
+        .variable => {
+            const resolved = self.inference.resolve(@"type");
+            return if (resolved == .variable) resolved else try self.substituteVariables(resolved);
+        },
+        .range => |element| return try self.types.range(try self.substituteVariables(element.*)),
+        .array => |array| return try self.types.array(try self.substituteVariables(array.element.*), array.len),
+        .slice => |element| return try self.types.slice(try self.substituteVariables(element.*)),
+        .reference => |element| return try self.types.reference(try self.substituteVariables(element.*)),
+        .future => |value| return try self.types.future(try self.substituteVariables(value.*)),
+        .tuple => |elements| {
+            const types = try self.allocator.alloc(Type, elements.len);
+            defer self.allocator.free(types);
+
+            for (types, elements) |*@"type", element| @"type".* = try self.substituteVariables(element);
+            return try self.types.tuple(types);
+        },
+        .function => |function| {
+            const params = try self.allocator.alloc(Type, function.params.len);
+            defer self.allocator.free(params);
+
+            for (params, function.params) |*param, inferred| param.* = try self.substituteVariables(inferred);
+
+            const ret = try self.substituteVariables(function.ret.*);
+            var substituted = try self.types.genericFunction(function.type_params, params, ret);
+            substituted.function.variadic = function.variadic;
+            return substituted;
+        },
+        .@"struct" => |structure| {
+            const arguments = try self.allocator.alloc(Type, structure.arguments.len);
+            defer self.allocator.free(arguments);
+
+            for (arguments, structure.arguments) |*argument, inferred| argument.* = try self.substituteVariables(inferred);
+            return try self.instantiateStruct(structure.generic.?, arguments);
+        },
+        .@"enum" => |enumeration| {
+            const arguments = try self.allocator.alloc(Type, enumeration.arguments.len);
+            defer self.allocator.free(arguments);
+
+            for (arguments, enumeration.arguments) |*argument, inferred| argument.* = try self.substituteVariables(inferred);
+            return try self.instantiateEnum(enumeration.generic.?, arguments);
+        },
+        else => unreachable
+    }
+}
+
+// Settles the type variables created since mark, those which are only known to be
+// numeric take the default type of their literals. The types of the nodes
+// referring to them are then updated
+fn resolveInferred(self: *TypeChecker, mark: usize) CheckError!void {
+    var index = mark;
+    while (index < self.inference.count()) : (index = index + 1) {
+        const variable: Inference.TypeVar = @intCast(index);
+        if (self.inference.resolve(.{ .variable = variable }) != .variable) continue;
+
+        const span = self.inference.spanOf(variable);
+        const inferred = self.inference.classOf(variable).default() orelse block: {
+            try self.unit.createErrorFmt(span, "the type of {s} can't be inferred", .{self.inference.nameOf(variable)});
+            break :block .invalid;
+        };
+
+        _ = self.inference.constrain(variable, .{ .@"type" = inferred }, span);
+    }
+
+    var bindings = self.types.bindings.valueIterator();
+    while (bindings.next()) |@"type"| @"type".* = try self.substituteVariables(@"type".*);
+
+    var captures = self.types.captures.valueIterator();
+    while (captures.next()) |types| for (types.*) |*@"type"| {
+        @"type".* = try self.substituteVariables(@"type".*);
+    };
+
+    var i: usize = 0;
+    while (i < self.unresolved.items.len) {
+        const id = self.unresolved.items[i];
+        const resolved = try self.substituteVariables(self.types.get(id).?);
+        try self.types.put(id, resolved);
+
+        if (containsVariable(resolved)) {
+            i = i + 1;
+        } else {
+            _ = self.unresolved.swapRemove(i);
+        }
+    }
+}
+
+// Top level bindings outlive checking, so their symbols are given the types inferred for them
+fn redefineInferred(self: *TypeChecker) CheckError!void {
+    for (self.inferred_bindings.items) |name| {
+        const id = self.unit.interner.find(name) orelse continue;
+        const symbol = self.unit.symbols.lookupLocal(id) orelse continue;
+
+        var inferred = symbol.*;
+        inferred.@"type" = try self.substituteVariables(symbol.@"type" orelse continue);
+        try self.unit.symbols.redefine(id, inferred);
+    }
+}
+
+// Generic structs are only types once the types substituted for their type
+// parameters are given, `Pair<i64, bool>`
+fn resolveNamed(self: *TypeChecker, node: *const Node, name: []const u8, arguments: []const *Node) CheckError!Type {
+    // Futures are built in rather than defined by the prelude, unless a program
+    // defines a type called Future of its own
+    if (std.mem.eql(u8, name, "Future") and self.typeNamed(name) == null) {
+        if (arguments.len != 1) {
+            try self.unit.createErrorFmt(node.span, "Future takes 1 type argument(s), found {}", .{arguments.len});
+            return .invalid;
+        }
+
+        return try self.types.future(try self.resolveType(arguments[0]));
+    }
+
+    const named = Type.fromName(name) orelse self.typeNamed(name) orelse {
+        try self.createError(node.span, "unknown type");
+        return .invalid;
+    };
+
+    // The values a trait accepts are of the types implementing it, written `impl Trait`
+    if (named == .trait) {
+        try self.unit.createErrorFmt(node.span, "{s} is a trait, values of the types implementing it are of type impl {s}", .{name, name});
+        return .invalid;
+    }
+
+    const type_params = switch (named) {
+        .@"struct" => |structure| structure.type_params,
+        .@"enum" => |enumeration| enumeration.type_params,
+        else => &.{}
+    };
+
+    if (arguments.len != type_params.len) {
+        if (type_params.len == 0) {
+            try self.unit.createErrorFmt(node.span, "{s} isn't generic", .{name});
+        } else {
+            try self.unit.createErrorFmt(node.span, "{s} takes {} type argument(s), found {}", .{
+                name,
+                type_params.len,
+                arguments.len
+            });
+        }
+        return .invalid;
+    }
+
+    if (type_params.len == 0) return named;
+
+    const resolved = try self.allocator.alloc(Type, arguments.len);
+    defer self.allocator.free(resolved);
+
+    for (resolved, arguments) |*@"type", argument| @"type".* = try self.resolveType(argument);
+    return switch (named) {
+        .@"struct" => |structure| try self.instantiateStruct(structure, resolved),
+        .@"enum" => |enumeration| try self.instantiateEnum(enumeration, resolved),
+        else => unreachable
+    };
+}
+
+/// The instance of the generic struct definition substituting arguments for its
+/// type parameters
+fn instantiateStruct(self: *TypeChecker, definition: *const Type.Struct, arguments: []const Type) CheckError!Type {
+    if (self.types.findInstance(definition, arguments)) |instance| return .{ .@"struct" = instance };
+
+    // Created before its fields are substituted, so fields of its own type find it
+    const instance = try self.types.instance(definition, arguments);
+    if (self.deferred_instances) |deferred| {
+        try deferred.append(instance);
+    } else {
+        try self.substituteFields(instance);
+    }
+
+    return .{ .@"struct" = instance };
+}
+
+fn substituteFields(self: *TypeChecker, instance: *Type.Struct) CheckError!void {
+    const definition = instance.generic.?;
+
+    var substitution = Substitution.init(self.allocator);
+    defer substitution.deinit();
+
+    for (definition.type_params, instance.arguments) |name, argument| try substitution.put(name, argument);
+
+    const fields = try self.allocator.alloc(Type.Struct.Field, definition.fields.len);
+    defer self.allocator.free(fields);
+
+    for (fields, definition.fields) |*field, generic| field.* = .{
+        .name = generic.name,
+        .@"type" = try self.substitute(generic.@"type", &substitution)
+    };
+
+    try self.types.setFields(instance, fields);
+}
+
+/// The instance of the generic enum definition substituting arguments for its
+/// type parameters. The variants of generic enums are resolved before they are
+/// instantiated, as only the prelude defines them
+fn instantiateEnum(self: *TypeChecker, definition: *const Type.Enum, arguments: []const Type) CheckError!Type {
+    if (self.types.findEnumInstance(definition, arguments)) |instance| return .{ .@"enum" = instance };
+
+    const instance = try self.types.enumInstance(definition, arguments);
+
+    var substitution = Substitution.init(self.allocator);
+    defer substitution.deinit();
+
+    for (definition.type_params, arguments) |name, argument| try substitution.put(name, argument);
+
+    var variants = ArrayList(Type.Enum.Variant).init(self.allocator);
+    defer {
+        for (variants.items) |variant| switch (variant.fields) {
+            .unit => {},
+            .tuple => |types| self.allocator.free(types),
+            .@"struct" => |fields| self.allocator.free(fields)
+        };
+        variants.deinit();
+    }
+
+    for (definition.variants) |variant| {
+        try variants.ensureUnusedCapacity(1);
+
+        const fields: Type.Enum.Variant.Fields = switch (variant.fields) {
+            .unit => .unit,
+            .tuple => |generic| block: {
+                const types = try self.allocator.alloc(Type, generic.len);
+                errdefer self.allocator.free(types);
+
+                for (types, generic) |*@"type", field| @"type".* = try self.substitute(field, &substitution);
+                break :block .{ .tuple = types };
+            },
+            .@"struct" => |generic| block: {
+                const fields = try self.allocator.alloc(Type.Struct.Field, generic.len);
+                errdefer self.allocator.free(fields);
+
+                for (fields, generic) |*field, generic_field| field.* = .{
+                    .name = generic_field.name,
+                    .@"type" = try self.substitute(generic_field.@"type", &substitution)
+                };
+                break :block .{ .@"struct" = fields };
+            }
+        };
+
+        variants.appendAssumeCapacity(.{ .name = variant.name, .fields = fields });
+    }
+
+    try self.types.setVariants(instance, variants.items);
+    return .{ .@"enum" = instance };
+}
+
+/// Replaces the type parameters in type with the types substituted for them.
+/// Functions stay generic over the type parameters which aren't substituted
+fn substitute(self: *TypeChecker, @"type": Type, substitution: *const Substitution) CheckError!Type {
+    switch (@"type") {
+        .parameter => |name| return substitution.get(name) orelse @"type",
+        .function => |function| {
+            var type_params = ArrayList([]const u8).init(self.allocator);
+            defer type_params.deinit();
+
+            for (function.type_params) |name| if (!substitution.contains(name)) try type_params.append(name);
+
+            const params = try self.allocator.alloc(Type, function.params.len);
+            defer self.allocator.free(params);
+
+            for (params, function.params) |*param, generic| param.* = try self.substitute(generic, substitution);
+
+            const ret = try self.substitute(function.ret.*, substitution);
+            var substituted = try self.types.genericFunction(type_params.items, params, ret);
+            substituted.function.variadic = function.variadic;
+            return substituted;
+        },
+        .range => |element| return try self.types.range(try self.substitute(element.*, substitution)),
+        .array => |array| return try self.types.array(try self.substitute(array.element.*, substitution), array.len),
+        .slice => |element| return try self.types.slice(try self.substitute(element.*, substitution)),
+        .reference => |element| return try self.types.reference(try self.substitute(element.*, substitution)),
+        .future => |value| return try self.types.future(try self.substitute(value.*, substitution)),
+        .tuple => |elements| {
+            const types = try self.allocator.alloc(Type, elements.len);
+            defer self.allocator.free(types);
+
+            for (types, elements) |*@"type", element| @"type".* = try self.substitute(element, substitution);
+            return try self.types.tuple(types);
+        },
+        .@"struct" => |structure| {
+            const definition = structure.generic orelse return @"type";
+
+            const arguments = try self.allocator.alloc(Type, structure.arguments.len);
+            defer self.allocator.free(arguments);
+
+            for (arguments, structure.arguments) |*argument, generic| argument.* = try self.substitute(generic, substitution);
+            return try self.instantiateStruct(definition, arguments);
+        },
+        .@"enum" => |enumeration| {
+            const definition = enumeration.generic orelse return @"type";
+
+            const arguments = try self.allocator.alloc(Type, enumeration.arguments.len);
+            defer self.allocator.free(arguments);
+
+            for (arguments, enumeration.arguments) |*argument, generic| argument.* = try self.substitute(generic, substitution);
+            return try self.instantiateEnum(definition, arguments);
+        },
+        else => return @"type"
+    }
+}
+
+/// Infers the types substituted for type_params from the type of a value, actual,
+/// given where a value of type expected is. Type parameters inferred as two
+/// different types are reported at span
+fn inferParams(
+    self: *TypeChecker,
+    span: Span,
+    type_params: []const []const u8,
+    expected: Type,
+    actual: Type,
+    substitution: *Substitution
+) CheckError!void {
+    if (actual == .invalid) return;
+
+    switch (expected) {
+        .parameter => |name| {
+            for (type_params) |type_param| {
+                if (std.mem.eql(u8, type_param, name)) break;
+            } else return;
+
+            const entry = try substitution.getOrPut(name);
+            if (!entry.found_existing) {
+                entry.value_ptr.* = actual;
+                return;
+            }
+
+            const inferred = entry.value_ptr.*;
+            if (inferred == .invalid or inferred.eql(actual)) return;
+
+            try self.unit.createErrorFmt(span, "type parameter {s} is inferred as both {} and {}", .{name, inferred, actual});
+            entry.value_ptr.* = .invalid;
+        },
+        .function => |function| {
+            if (actual != .function or actual.function.params.len != function.params.len) return;
+
+            for (function.params, actual.function.params) |param, actual_param| {
+                try self.inferParams(span, type_params, param, actual_param, substitution);
+            }
+            try self.inferParams(span, type_params, function.ret.*, actual.function.ret.*, substitution);
+        },
+        .range => |element| if (actual == .range) {
+            try self.inferParams(span, type_params, element.*, actual.range.*, substitution);
+        },
+        .array => |array| if (actual == .array) {
+            try self.inferParams(span, type_params, array.element.*, actual.array.element.*, substitution);
+        },
+        .slice => |element| if (actual == .slice) {
+            try self.inferParams(span, type_params, element.*, actual.slice.*, substitution);
+        },
+        .reference => |element| if (actual == .reference) {
+            try self.inferParams(span, type_params, element.*, actual.reference.*, substitution);
+        },
+        .future => |value| if (actual == .future) {
+            try self.inferParams(span, type_params, value.*, actual.future.*, substitution);
+        },
+        .tuple => |elements| if (actual == .tuple and actual.tuple.len == elements.len) {
+            for (elements, actual.tuple) |element, actual_element| {
+                try self.inferParams(span, type_params, element, actual_element, substitution);
+            }
+        },
+        .@"struct" => |structure| {
+            const definition = structure.generic orelse return;
+            if (actual != .@"struct" or actual.@"struct".generic != definition) return;
+
+            for (structure.arguments, actual.@"struct".arguments) |argument, actual_argument| {
+                try self.inferParams(span, type_params, argument, actual_argument, substitution);
+            }
+        },
+        .@"enum" => |enumeration| {
+            const definition = enumeration.generic orelse return;
+            if (actual != .@"enum" or actual.@"enum".generic != definition) return;
+
+            for (enumeration.arguments, actual.@"enum".arguments) |argument, actual_argument| {
+                try self.inferParams(span, type_params, argument, actual_argument, substitution);
+            }
+        },
+        else => {}
+    }
+}
+
+/// The types inferred for type_params in order, reporting those nothing was
+/// inferred for. The caller owns the returned slice
+fn inferredArguments(
+    self: *TypeChecker,
+    span: Span,
+    type_params: []const []const u8,
+    substitution: *Substitution
+) CheckError![]Type {
+    const arguments = try self.allocator.alloc(Type, type_params.len);
+    errdefer self.allocator.free(arguments);
+
+    for (arguments, type_params) |*argument, name| {
+        argument.* = substitution.get(name) orelse block: {
+            try self.unit.createErrorFmt(span, "the type of type parameter {s} can't be inferred", .{name});
+            try substitution.put(name, .invalid);
+            break :block .invalid;
+        };
+    }
+
+    return arguments;
+}
+
+/// Defines the type parameters of a generic function or struct in the current scope
+fn defineTypeParams(self: *TypeChecker, span: Span, type_params: []const []const u8) CheckError!void {
+    for (type_params) |name| try self.define(span, name, .@"type", try self.types.parameter(name));
+}
+
+/// Resolves a type annotation
+fn resolveType(self: *TypeChecker, node: *const Node) CheckError!Type {
+    const @"type": Type = switch (node.kind) {
+        .identifier => |name| try self.resolveNamed(node, name, &.{}),
+        .literal => |literal| switch (literal) {
+            .unit => .unit,
+            else => block: {
+                try self.createError(node.span, "expected a type");
+                break :block .invalid;
+            }
+        },
+        .type_expr => |type_expr| switch (type_expr) {
+            .function => |function| block: {
+                var params = ArrayList(Type).init(self.allocator);
+                defer params.deinit();
+
+                // A trailing `...` makes the function variadic rather than being a parameter
+                var variadic = false;
+                for (function.params, 0..) |param, i| {
+                    if (param.kind == .type_expr and param.kind.type_expr == .variadic) {
+                        if (i + 1 == function.params.len) {
+                            variadic = true;
+                        } else {
+                            try self.createError(param.span, "... can only be the last parameter of a function type");
+                        }
+                        continue;
+                    }
+                    try params.append(try self.resolveType(param));
+                }
+                const ret: Type = if (function.return_type) |return_type| try self.resolveType(return_type)
+                    else .unit;
+
+                var resolved = try self.types.function(params.items, ret);
+                resolved.function.variadic = variadic;
+                break :block resolved;
+            },
+            .generic => |generic| try self.resolveNamed(node, generic.name, generic.arguments),
+            .array => |array| block: {
+                const element = try self.resolveType(array.element);
+                const len = switch (array.len.kind) {
+                    .literal => |literal| switch (literal) {
+                        .integer => |len| if (len >= 0) len else null,
+                        else => null
+                    },
+                    else => null
+                } orelse {
+                    try self.createError(array.len.span, "array lengths must be non-negative integer literals");
+                    break :block .invalid;
+                };
+
+                break :block try self.types.array(element, @intCast(len));
+            },
+            .slice => |element| try self.types.slice(try self.resolveType(element)),
+            .tuple => |elements| block: {
+                const types = try self.allocator.alloc(Type, elements.len);
+                defer self.allocator.free(types);
+
+                for (types, elements) |*@"type", element| @"type".* = try self.resolveType(element);
+                break :block try self.types.tuple(types);
+            },
+            .reference => |element| try self.types.reference(try self.resolveType(element)),
+            .impl => |name| block: {
+                const trait = self.traitNamed(name) orelse {
+                    try self.unit.createErrorFmt(node.span, "{s} is not a trait", .{name});
+                    break :block .invalid;
+                };
+
+                break :block .{ .trait = trait };
+            },
+            .variadic => block: {
+                try self.createError(node.span, "... can only be the last parameter of a function type");
+                break :block .invalid;
+            }
+        },
+        else => block: {
+            try self.createError(node.span, "expected a type");
+            break :block .invalid;
+        }
+    };
+
+    try self.types.put(node.id, @"type");
+    return @"type";
+}
+
+// Finds the literal an expression evaluates to, looking through arithmetic and blocks
+fn literalOf(node: *const Node) ?Node.Literal {
+    return switch (node.kind) {
+        .literal => |literal| literal,
+        .prefix => |prefix| switch (prefix.operator) {
+            .negate, .bit_not => literalOf(prefix.operand),
+            .not => null
+        },
+        .infix => |infix| switch (infix.operator) {
+            .add, .subtract, .multiply, .divide, .modulo, .exponent,
+            .bit_and, .bit_or, .bit_xor, .lshift, .rshift => {
+                const lhs = literalOf(infix.lhs) orelse return null;
+                const rhs = literalOf(infix.rhs) orelse return null;
+
+                return if (std.meta.activeTag(lhs) == std.meta.activeTag(rhs)) lhs else null;
+            },
+            else => null
+        },
+        .block => |block| if (block.statements.len > 0)
+            literalOf(block.statements[block.statements.len - 1])
+            else null,
+        .@"comptime" => |body| literalOf(body),
+        else => null
+    };
+}
+
+// Changes the recorded type of a literal expression and the nodes it evaluates through
+fn retype(self: *TypeChecker, node: *const Node, @"type": Type) CheckError!void {
+    try self.record(node.id, @"type");
+
+    switch (node.kind) {
+        .prefix => |prefix| try self.retype(prefix.operand, @"type"),
+        .infix => |infix| {
+            try self.retype(infix.lhs, @"type");
+            try self.retype(infix.rhs, @"type");
+        },
+        .block => |block| if (block.statements.len > 0) {
+            try self.retype(block.statements[block.statements.len - 1], @"type");
+        },
+        .@"comptime" => |body| try self.retype(body, @"type"),
+        else => {}
+    }
+}
+
+/// Checks if node, of type actual, can be used where expected is required.
+/// Numeric literals take on the expected type when it is of the same family, and
+/// bindings whose types are inferred take on the type they are used as
+fn coerce(self: *TypeChecker, node: *const Node, actual: Type, expected: Type) CheckError!bool {
+    if (actual == .invalid or expected == .invalid) return true;
+
+    // Conflicts are reported with where the binding's type was inferred, rather
+    // than by the caller
+    if (self.inferredVariable(node)) |variable| {
+        try self.constrain(node, variable, .{ .@"type" = expected });
+        return true;
+    }
+
+    const value = self.inference.resolve(actual);
+    const target = self.inference.resolve(expected);
+
+    switch (target) {
+        // Literals keep the variable as their type, so they become whichever
+        // type it is inferred as
+        .variable => |variable| {
+            const class = numericClass(node);
+            if (class == .any) {
+                try self.constrain(node, variable, .{ .@"type" = value });
+            } else {
+                try self.retype(node, target);
+                try self.constrain(node, variable, .{ .class = class });
+            }
+
+            return true;
+        },
+        else => {}
+    }
+
+    if (value == .variable) {
+        try self.constrain(node, value.variable, .{ .@"type" = target });
+        return true;
+    }
+
+    if (value.eql(target)) return true;
+
+    // Values of the types implementing a trait are accepted as it, and references
+    // to them as references to it
+    if (target == .trait) return self.types.implements(value, target.trait);
+    if (value == .reference and target == .reference and target.reference.* == .trait) {
+        return self.types.implements(value.reference.*, target.reference.trait);
+    }
+
+    if (value == .array and target == .array) return try self.coerceArray(node, value.array, target.array);
+    if (value == .function and target == .function) return try self.unifyTypes(node, value, target);
+    if (value == .slice and target == .slice) return try self.unifyTypes(node, value, target);
+    if (value == .reference and target == .reference) return try self.unifyTypes(node, value, target);
+    if (value == .tuple and target == .tuple) return try self.coerceTuple(node, value.tuple, target.tuple);
+    if (sameGeneric(value, target)) return try self.unifyTypes(node, value, target);
+
+    const literal = literalOf(node) orelse return false;
+    const compatible = switch (literal) {
+        .integer => target.isInteger(),
+        .float => target.isFloat(),
+        else => false
+    };
+
+    if (compatible) try self.retype(node, target);
+    return compatible;
+}
+
+// The elements of array literals are coerced to the element type, so numeric
+// literals take it on
+fn coerceArray(self: *TypeChecker, node: *const Node, actual: Type.Array, expected: Type.Array) CheckError!bool {
+    if (actual.len != expected.len) return false;
+
+    const elements = switch (node.kind) {
+        .array_literal => |elements| elements,
+        else => return try self.unifyTypes(node, actual.element.*, expected.element.*)
+    };
+    // The elements of empty arrays are inferred as those expected
+    if (elements.len == 0) return try self.unifyTypes(node, actual.element.*, expected.element.*);
+
+    for (elements) |element| {
+        const @"type" = self.types.get(element.id) orelse actual.element.*;
+        if (!try self.coerce(element, @"type", expected.element.*)) return false;
+    }
+
+    try self.record(node.id, .{ .array = expected });
+    return true;
+}
+
+// The elements of tuple literals are coerced to the types expected of them, like
+// those of arrays
+fn coerceTuple(self: *TypeChecker, node: *const Node, actual: []const Type, expected: []const Type) CheckError!bool {
+    if (actual.len != expected.len) return false;
+
+    const elements = switch (node.kind) {
+        .tuple_literal => |elements| elements,
+        else => return try self.unifyTypes(node, .{ .tuple = actual }, .{ .tuple = expected })
+    };
+
+    for (elements, actual, expected) |element, @"type", expected_type| {
+        if (!try self.coerce(element, @"type", expected_type)) return false;
+    }
+
+    try self.record(node.id, .{ .tuple = expected });
+    return true;
+}
+
+// Whether values of type a can be used where b is required, constraining the
+// type variables in either by the types they correspond to in the other. Used
+// for function types whose parameters or results are inferred
+fn unifyTypes(self: *TypeChecker, node: *const Node, a: Type, b: Type) CheckError!bool {
+    const lhs = self.inference.resolve(a);
+    const rhs = self.inference.resolve(b);
+    if (lhs == .invalid or rhs == .invalid) return true;
+
+    // Conflicts are reported by constrain
+    if (lhs == .variable) {
+        try self.constrain(node, lhs.variable, .{ .@"type" = rhs });
+        return true;
+    }
+    if (rhs == .variable) {
+        try self.constrain(node, rhs.variable, .{ .@"type" = lhs });
+        return true;
+    }
+
+    if (sameGeneric(lhs, rhs)) {
+        for (lhs.@"enum".arguments, rhs.@"enum".arguments) |argument, other| {
+            if (!try self.unifyTypes(node, argument, other)) return false;
+        }
+
+        return true;
+    }
+
+    if (lhs == .array and rhs == .array) {
+        return lhs.array.len == rhs.array.len and try self.unifyTypes(node, lhs.array.element.*, rhs.array.element.*);
+    }
+    if (lhs == .slice and rhs == .slice) return try self.unifyTypes(node, lhs.slice.*, rhs.slice.*);
+    if (lhs == .reference and rhs == .reference) return try self.unifyTypes(node, lhs.reference.*, rhs.reference.*);
+    if (lhs == .tuple and rhs == .tuple) {
+        if (lhs.tuple.len != rhs.tuple.len) return false;
+        for (lhs.tuple, rhs.tuple) |element, other| {
+            if (!try self.unifyTypes(node, element, other)) return false;
+        }
+
+        return true;
+    }
+
+    if (lhs != .function or rhs != .function) return lhs.eql(rhs);
+
+    const function = lhs.function;
+    const other = rhs.function;
+    if (function.type_params.len > 0 or other.type_params.len > 0) return lhs.eql(rhs);
+    if (function.params.len != other.params.len or function.variadic != other.variadic) return false;
+
+    for (function.params, other.params) |param, other_param| {
+        if (!try self.unifyTypes(node, param, other_param)) return false;
+    }
+
+    return try self.unifyTypes(node, function.ret.*, other.ret.*);
+}
+
+// Whether a and b are instances of the same generic enum, whose arguments may be
+// inferred from the values of other variants
+fn sameGeneric(a: Type, b: Type) bool {
+    if (a != .@"enum" or b != .@"enum") return false;
+
+    const generic = a.@"enum".generic orelse return false;
+    return generic == b.@"enum".generic;
+}
+
+/// Finds the type both operands can be used as
+fn unify(
+    self: *TypeChecker,
+    lhs: *const Node,
+    lhs_type: Type,
+    rhs: *const Node,
+    rhs_type: Type
+) CheckError!?Type {
+    // Literals take on the type of the other operand, rather than constraining it
+    if (literalOf(lhs) != null and try self.coerce(lhs, lhs_type, rhs_type)) return rhs_type;
+
+    if (try self.coerce(rhs, rhs_type, lhs_type)) return lhs_type;
+    if (try self.coerce(lhs, lhs_type, rhs_type)) return rhs_type;
+
+    return null;
+}
+
+// The family of types the numeric literal node could be, any if it isn't one
+fn numericClass(node: *const Node) Inference.Class {
+    const literal = literalOf(node) orelse return .any;
+    return switch (literal) {
+        .integer => .integer,
+        .float => .float,
+        else => .any
+    };
+}
+
+// The type variable of the binding node refers to, if its type is inferred
+fn inferredVariable(self: *const TypeChecker, node: *const Node) ?Inference.TypeVar {
+    const name = switch (node.kind) {
+        .identifier => |name| name,
+        else => return null
+    };
+
+    const symbol = self.lookup(name) orelse return null;
+    return switch (symbol.@"type" orelse return null) {
+        .variable => |variable| variable,
+        else => null
+    };
+}
+
+// Constrains a type variable by its use at node, reporting a conflict with what
+// was inferred for it earlier along with where that was
+fn constrain(
+    self: *TypeChecker,
+    node: *const Node,
+    variable: Inference.TypeVar,
+    constraint: Inference.Constraint
+) CheckError!void {
+    const conflict = self.inference.constrain(variable, constraint, node.span) orelse return;
+
+    const arena = self.unit.arena.allocator();
+    // Bindings of other inferred bindings share their variable
+    const name = switch (node.kind) {
+        .identifier => |identifier| identifier,
+        else => self.inference.nameOf(variable)
+    };
+
+    var diagnostic: Diagnostic = .init(
+        .@"error",
+        try std.fmt.allocPrint(arena, "{s} is inferred as both {} and {}", .{name, conflict.previous, constraint}),
+        node.span
+    );
+    diagnostic.children = try arena.dupe(Diagnostic, &[_]Diagnostic{.init(
+        .note,
+        try std.fmt.allocPrint(arena, "{s} is inferred as {} here", .{name, conflict.previous}),
+        conflict.origin
+    )});
+
+    try self.unit.report(diagnostic);
+}
+
+// The type of values of type, defaulting the type variables only known to be
+// numeric so far. Used where the type must be known to check a node
+fn known(self: *TypeChecker, node: *const Node, @"type": Type) CheckError!Type {
+    const variable = switch (self.inference.resolve(@"type")) {
+        .variable => |variable| variable,
+        else => |resolved| return resolved
+    };
+
+    const inferred = self.inference.classOf(variable).default() orelse return @"type";
+    try self.constrain(node, variable, .{ .@"type" = inferred });
+
+    return self.inference.resolve(@"type");
+}
+
+// Whether values of type are integers, including bindings of integer literals
+// whose types aren't inferred yet
+fn isInteger(self: *TypeChecker, @"type": Type) bool {
+    return switch (@"type") {
+        .variable => |variable| self.inference.classOf(variable) == .integer,
+        else => @"type".isInteger()
+    };
+}
+
+fn isNumeric(self: *TypeChecker, @"type": Type) bool {
+    return switch (@"type") {
+        .variable => |variable| self.inference.classOf(variable) != .any,
+        else => @"type".isNumeric()
+    };
+}
+
+fn expectCondition(self: *TypeChecker, node: *const Node) CheckError!void {
+    const @"type" = try self.checkNode(node);
+    if (!try self.coerce(node, @"type", .@"bool")) {
+        try self.createError(node.span, "expected a bool condition");
+    }
+}
+
+fn functionType(self: *TypeChecker, node: *const Node, fn_def: Node.FunctionDef) CheckError!Type {
+    // Type parameters are only visible within the function
+    try self.unit.symbols.enterScope();
+    defer self.unit.symbols.exitScope();
+
+    try self.defineTypeParams(node.span, fn_def.type_params);
+
+    var params = ArrayList(Type).init(self.allocator);
+    defer params.deinit();
+
+    for (fn_def.params) |param| {
+        const annotation = param.annotation orelse {
+            try self.createError(param.span, "parameters require a type annotation");
+            try params.append(.invalid);
+            continue;
+        };
+
+        try params.append(try self.resolveType(annotation));
+    }
+
+    const ret: Type = if (fn_def.return_type) |return_type| try self.resolveType(return_type)
+        else .unit;
+
+    // Calling an async function gives a future of what its body returns
+    const result = if (fn_def.@"async") try self.types.future(ret) else ret;
+    return try self.types.genericFunction(fn_def.type_params, params.items, result);
+}
+
+// The declared types of an extern function are trusted, there is no body to check
+// them against
+fn externType(self: *TypeChecker, extern_fn: Node.ExternFn) CheckError!Type {
+    var params = ArrayList(Type).init(self.allocator);
+    defer params.deinit();
+
+    for (extern_fn.params) |param| try params.append(try self.resolveType(param.annotation.?));
+
+    const ret: Type = if (extern_fn.return_type) |return_type| try self.resolveType(return_type)
+        else .unit;
+
+    var function = try self.types.function(params.items, ret);
+    function.function.variadic = extern_fn.variadic;
+    return function;
+}
+
+// Defines the types and functions in statements before checking them, so they
+// can be referenced anywhere in their scope. Every struct and enum is defined
+// before the fields of any are resolved, so they can refer to each other
+fn declareDefinitions(self: *TypeChecker, statements: []const *Node) CheckError!void {
+    var structs = ArrayList(*Type.Struct).init(self.allocator);
+    defer structs.deinit();
+
+    var enums = ArrayList(*Type.Enum).init(self.allocator);
+    defer enums.deinit();
+
+    var traits = ArrayList(*Type.Trait).init(self.allocator);
+    defer traits.deinit();
+
+    for (statements) |item| {
+        const statement = item.unwrapExport();
+        switch (statement.kind) {
+            .struct_def => |struct_def| {
+                const structure = try self.types.genericStructure(struct_def.name, struct_def.type_params);
+                try structs.append(structure);
+                try self.define(statement.span, struct_def.name, .@"type", .{ .@"struct" = structure });
+            },
+            // Unions are structs whose fields overlay each other
+            .union_def => |union_def| {
+                const overlaid = try self.types.structure(union_def.name);
+                overlaid.overlaid = true;
+                try structs.append(overlaid);
+                try self.define(statement.span, union_def.name, .@"type", .{ .@"struct" = overlaid });
+            },
+            .enum_def => |enum_def| {
+                const enumeration = try self.types.enumeration(enum_def.name);
+                try enums.append(enumeration);
+                try self.define(statement.span, enum_def.name, .@"type", .{ .@"enum" = enumeration });
+            },
+            .trait_def => |trait_def| {
+                const trait = try self.types.trait(trait_def.name);
+                try traits.append(trait);
+                try self.define(statement.span, trait_def.name, .@"type", .{ .trait = trait });
+            },
+            else => {}
+        }
+    }
+
+    var deferred = ArrayList(*Type.Struct).init(self.allocator);
+    defer deferred.deinit();
+
+    self.deferred_instances = &deferred;
+    defer self.deferred_instances = null;
+
+    var resolved_structs: usize = 0;
+    var resolved_enums: usize = 0;
+    var resolved_traits: usize = 0;
+    for (statements) |item| {
+        const statement = item.unwrapExport();
+        switch (statement.kind) {
+            .struct_def => |struct_def| {
+                try self.resolveFields(statement, structs.items[resolved_structs], struct_def);
+                resolved_structs = resolved_structs + 1;
+            },
+            .union_def => |union_def| {
+                try self.resolveFields(statement, structs.items[resolved_structs], .{
+                    .name = union_def.name,
+                    .fields = union_def.fields
+                });
+                resolved_structs = resolved_structs + 1;
+            },
+            .enum_def => |enum_def| {
+                try self.resolveVariants(enums.items[resolved_enums], enum_def);
+                resolved_enums = resolved_enums + 1;
+            },
+            .trait_def => |trait_def| {
+                try self.resolveMethods(statement, traits.items[resolved_traits], trait_def);
+                resolved_traits = resolved_traits + 1;
+            },
+            else => {}
+        }
+    }
+
+    self.deferred_instances = null;
+    for (deferred.items) |instance| try self.substituteFields(instance);
+
+    for (statements) |item| {
+        const statement = item.unwrapExport();
+        switch (statement.kind) {
+            .fn_def => |fn_def| {
+                const function = try self.functionType(statement, fn_def);
+                try self.types.put(statement.id, function);
+                try self.define(statement.span, fn_def.name, .function, function);
+            },
+            .extern_fn => |extern_fn| {
+                const function = try self.externType(extern_fn);
+                try self.types.put(statement.id, function);
+                try self.define(statement.span, extern_fn.name, .extern_function, function);
+            },
+            .impl => |impl| try self.declareMethods(statement, impl),
+            else => {}
+        }
+    }
+}
+
+fn resolveFields(self: *TypeChecker, node: *const Node, structure: *Type.Struct, struct_def: Node.StructDef) CheckError!void {
+    // Type parameters are only visible within the struct
+    try self.unit.symbols.enterScope();
+    defer self.unit.symbols.exitScope();
+
+    try self.defineTypeParams(node.span, struct_def.type_params);
+
+    const fields = try self.resolveFieldTypes(struct_def.fields);
+    defer self.allocator.free(fields);
+
+    try self.types.setFields(structure, fields);
+}
+
+// The methods of a trait take self or &self, of any type implementing the trait.
+// Those given bodies are checked as the functions they define
+fn resolveMethods(self: *TypeChecker, node: *const Node, trait: *Type.Trait, trait_def: Node.TraitDef) CheckError!void {
+    var methods = ArrayList(Type.Trait.Method).init(self.allocator);
+    defer methods.deinit();
+
+    for (trait_def.methods) |method| {
+        const @"type" = if (method.default) |default| block: {
+            const resolved = try self.functionType(default, default.kind.fn_def);
+            try self.types.put(default.id, resolved);
+            break :block resolved;
+        } else try self.functionType(node, .{
+            .name = method.name,
+            .params = method.params,
+            .return_type = method.return_type,
+            .body = undefined
+        });
+
+        const function = @"type".function;
+        const first = if (function.params.len > 0) function.params[0] else Type.unit;
+        const receiver = if (first == .reference) first.reference.* else first;
+        if (receiver != .trait or receiver.trait != trait) {
+            try self.unit.createErrorFmt(method.span, "{s}.{s} must take self or &self as its first parameter", .{trait.name, method.name});
+            continue;
+        }
+
+        try methods.append(.{ .name = method.name, .function = function, .default = method.default != null });
+    }
+
+    try self.types.setMethods(trait, methods.items);
+}
+
+// The table copies the variants, so the types resolved for their fields are freed
+fn resolveVariants(self: *TypeChecker, enumeration: *Type.Enum, enum_def: Node.EnumDef) CheckError!void {
+    var variants = ArrayList(Type.Enum.Variant).init(self.allocator);
+    defer {
+        for (variants.items) |variant| switch (variant.fields) {
+            .unit => {},
+            .tuple => |types| self.allocator.free(types),
+            .@"struct" => |fields| self.allocator.free(fields)
+        };
+        variants.deinit();
+    }
+
+    for (enum_def.variants) |variant| {
+        try variants.ensureUnusedCapacity(1);
+
+        const fields: Type.Enum.Variant.Fields = switch (variant.fields) {
+            .unit => .unit,
+            .tuple => |annotations| block: {
+                const types = try self.allocator.alloc(Type, annotations.len);
+                errdefer self.allocator.free(types);
+
+                for (types, annotations) |*@"type", annotation| @"type".* = try self.resolveType(annotation);
+                break :block .{ .tuple = types };
+            },
+            .@"struct" => |fields| .{ .@"struct" = try self.resolveFieldTypes(fields) }
+        };
+
+        variants.appendAssumeCapacity(.{ .name = variant.name, .fields = fields });
+    }
+
+    try self.types.setVariants(enumeration, variants.items);
+}
+
+/// Resolves the annotations of fields, the caller owns the returned slice
+fn resolveFieldTypes(self: *TypeChecker, fields: []const Node.StructDef.Field) CheckError![]Type.Struct.Field {
+    const resolved = try self.allocator.alloc(Type.Struct.Field, fields.len);
+    errdefer self.allocator.free(resolved);
+
+    for (resolved, fields) |*field, annotated| field.* = .{
+        .name = annotated.name,
+        .@"type" = try self.resolveType(annotated.annotation)
+    };
+
+    return resolved;
+}
+
+// The functions of an impl are defined as `Struct.function`, which can't clash
+// with other names as identifiers can't contain dots
+fn declareMethods(self: *TypeChecker, node: *const Node, impl: Node.Impl) CheckError!void {
+    const structure = self.structNamed(impl.name) orelse {
+        return try self.unit.createErrorFmt(node.span, "{s} is not a struct", .{impl.name});
+    };
+
+    for (impl.functions) |function| {
+        const fn_def = function.kind.fn_def;
+        const @"type" = try self.functionType(function, fn_def);
+        try self.types.put(function.id, @"type");
+
+        const name = try std.fmt.allocPrint(self.allocator, "{s}.{s}", .{structure.name, fn_def.name});
+        defer self.allocator.free(name);
+
+        try self.define(function.span, name, .function, @"type");
+    }
+
+    if (impl.trait) |trait| try self.implementTrait(node, impl, structure, trait);
+}
+
+// The functions of an impl of a trait are its methods, with the signatures the
+// trait gives them taking the struct in its place. The methods the impl doesn't
+// define are defined as the trait's defaults, those without any are reported
+fn implementTrait(self: *TypeChecker, node: *const Node, impl: Node.Impl, structure: *const Type.Struct, name: []const u8) CheckError!void {
+    const trait = self.traitNamed(name) orelse {
+        return try self.unit.createErrorFmt(node.span, "{s} is not a trait", .{name});
+    };
+
+    const receiver: Type = .{ .@"struct" = structure };
+    if (self.types.implements(receiver, trait)) {
+        return try self.unit.createErrorFmt(node.span, "{s} is already implemented for {s}", .{trait.name, structure.name});
+    }
+    try self.types.implement(trait, structure);
+
+    for (impl.functions) |function| {
+        const fn_def = function.kind.fn_def;
+        const index = trait.methodIndex(fn_def.name) orelse {
+            try self.unit.createErrorFmt(function.span, "{s} is not a method of {s}", .{fn_def.name, trait.name});
+            continue;
+        };
+
+        const expected = try self.methodFor(trait.methods[index].function, trait, receiver);
+        const actual = self.types.get(function.id).?;
+        if (actual == .function and !actual.eql(expected)) {
+            try self.unit.createErrorFmt(function.span, "{s}.{s} doesn't match the signature of {s}.{s}, expected {} but found {}", .{
+                structure.name,
+                fn_def.name,
+                trait.name,
+                fn_def.name,
+                expected,
+                actual
+            });
+        }
+    }
+
+    var missing = ArrayList(u8).init(self.allocator);
+    defer missing.deinit();
+
+    for (trait.methods) |method| {
+        const defined = for (impl.functions) |function| {
+            if (std.mem.eql(u8, function.kind.fn_def.name, method.name)) break true;
+        } else false;
+        if (defined) continue;
+
+        if (!method.default) {
+            if (missing.items.len > 0) try missing.appendSlice(", ");
+            try missing.appendSlice(method.name);
+            continue;
+        }
+
+        const qualified = try std.fmt.allocPrint(self.allocator, "{s}.{s}", .{structure.name, method.name});
+        defer self.allocator.free(qualified);
+
+        try self.define(node.span, qualified, .function, try self.methodFor(method.function, trait, receiver));
+    }
+
+    if (missing.items.len > 0) {
+        try self.unit.createErrorFmt(node.span, "{s} doesn't implement {s}, it is missing {s}", .{structure.name, trait.name, missing.items});
+    }
+}
+
+/// The type of a method of trait, taking receiver in place of the trait
+fn methodFor(self: *TypeChecker, function: Type.Function, trait: *const Type.Trait, receiver: Type) CheckError!Type {
+    const params = try self.allocator.alloc(Type, function.params.len);
+    defer self.allocator.free(params);
+
+    for (params, function.params) |*param, trait_param| param.* = try self.receiving(trait_param, trait, receiver);
+    return try self.types.function(params, try self.receiving(function.ret.*, trait, receiver));
+}
+
+// Replaces trait with receiver in type, and in the types it references
+fn receiving(self: *TypeChecker, @"type": Type, trait: *const Type.Trait, receiver: Type) CheckError!Type {
+    return switch (@"type") {
+        .trait => |other| if (other == trait) receiver else @"type",
+        .reference => |element| try self.types.reference(try self.receiving(element.*, trait, receiver)),
+        else => @"type"
+    };
+}
+
+/// The type defined with name, null if name isn't bound to a type
+fn typeNamed(self: *const TypeChecker, name: []const u8) ?Type {
+    const symbol = self.lookup(name) orelse return null;
+    if (symbol.kind != .@"type") return null;
+
+    return symbol.@"type";
+}
+
+/// The struct name is bound to, null if it isn't bound to one
+fn structNamed(self: *const TypeChecker, name: []const u8) ?*const Type.Struct {
+    return switch (self.typeNamed(name) orelse return null) {
+        .@"struct" => |structure| structure,
+        else => null
+    };
+}
+
+/// The trait name is bound to, null if it isn't bound to one
+fn traitNamed(self: *const TypeChecker, name: []const u8) ?*const Type.Trait {
+    return switch (self.typeNamed(name) orelse return null) {
+        .trait => |trait| trait,
+        else => null
+    };
+}
+
+/// The enum name is bound to, null if it isn't bound to one
+fn enumNamed(self: *const TypeChecker, name: []const u8) ?*const Type.Enum {
+    return switch (self.typeNamed(name) orelse return null) {
+        .@"enum" => |enumeration| enumeration,
+        else => null
+    };
+}
+
+/// The type of the function named name defined for structure by an impl
+fn findMethod(self: *TypeChecker, structure: *const Type.Struct, name: []const u8) CheckError!?Type {
+    const qualified = try std.fmt.allocPrint(self.allocator, "{s}.{s}", .{structure.name, name});
+    defer self.allocator.free(qualified);
+
+    const symbol = self.lookup(qualified) orelse return null;
+    return symbol.@"type";
+}
+
+// The methods nodes are dispatched to, each returns the type of the node it checks
+
+pub fn visitModule(self: *TypeChecker, _: *const Node, module: Node.Module) CheckError!Type {
+    try self.declareDefinitions(module.items);
+    for (module.items) |item| _ = try self.checkNode(item);
+
+    return .unit;
+}
+
+pub fn visitFnDef(self: *TypeChecker, node: *const Node, fn_def: Node.FunctionDef) CheckError!Type {
+    const @"type" = self.types.get(node.id) orelse try self.functionType(node, fn_def);
+    const function = @"type".function;
+
+    try self.unit.symbols.enterScope();
+    defer self.unit.symbols.exitScope();
+
+    try self.defineTypeParams(node.span, fn_def.type_params);
+    for (fn_def.params, function.params) |param, param_type| {
+        try self.define(param.span, param.name, .parameter, param_type);
+    }
+
+    // The body of an async function returns the type its future gives
+    const ret = if (fn_def.@"async") function.ret.future.* else function.ret.*;
+
+    const outer = self.return_type;
+    self.return_type = ret;
+    defer self.return_type = outer;
+
+    const outer_async = self.in_async;
+    self.in_async = fn_def.@"async";
+    defer self.in_async = outer_async;
+
+    // Loops outside of the function can't be broken out of from within it
+    const outer_loops = self.loops;
+    self.loops = 0;
+    defer self.loops = outer_loops;
+
+    const outer_deferring = self.deferring;
+    self.deferring = false;
+    defer self.deferring = outer_deferring;
+
+    const outer_unsafe = self.in_unsafe;
+    self.in_unsafe = false;
+    defer self.in_unsafe = outer_unsafe;
+
+    var labels = try Labels.find(self.unit, fn_def.body);
+    defer labels.deinit();
+
+    const outer_labels = self.labels;
+    self.labels = &labels;
+    defer self.labels = outer_labels;
+
+    const outer_closure = self.closure_depth;
+    self.closure_depth = null;
+    defer self.closure_depth = outer_closure;
+
+    // The bindings of the function are inferred from their uses within it
+    const mark = self.inference.count();
+
+    const body = try self.checkNode(fn_def.body);
+    if (!try self.coerce(fn_def.body, body, ret)) {
+        try self.createError(fn_def.body.span, "function body does not match its return type");
+    }
+
+    // The parameters go out of scope as the function returns, as its body's bindings do
+    if (fn_def.body.kind == .block and fn_def.body.kind.block.statements.len > 0) {
+        const statements = fn_def.body.kind.block.statements;
+        try self.checkEscape(statements[statements.len - 1], self.unit.symbols.depth() - 1);
+    }
+
+    try self.resolveInferred(mark);
+    return @"type";
+}
+
+// Structs and the functions of impls are declared before the statements of their scope are checked
+pub fn visitStructDef(_: *TypeChecker, _: *const Node, _: Node.StructDef) CheckError!Type {
+    return .unit;
+}
+
+pub fn visitUnionDef(_: *TypeChecker, _: *const Node, _: Node.UnionDef) CheckError!Type {
+    return .unit;
+}
+
+pub fn visitEnumDef(_: *TypeChecker, _: *const Node, _: Node.EnumDef) CheckError!Type {
+    return .unit;
+}
+
+pub fn visitImpl(self: *TypeChecker, _: *const Node, impl: Node.Impl) CheckError!Type {
+    for (impl.functions) |function| _ = try self.checkNode(function);
+    return .unit;
+}
+
+// The defaults of a trait are checked as functions taking any type implementing it
+pub fn visitTraitDef(self: *TypeChecker, _: *const Node, trait_def: Node.TraitDef) CheckError!Type {
+    for (trait_def.methods) |method| if (method.default) |default| {
+        _ = try self.checkNode(default);
+    };
+
+    return .unit;
+}
+
+pub fn visitVarDecl(self: *TypeChecker, node: *const Node, binding: Node.Binding) CheckError!Type {
+    return try self.checkBinding(node, binding);
+}
+
+pub fn visitConstDecl(self: *TypeChecker, node: *const Node, binding: Node.Binding) CheckError!Type {
+    return try self.checkBinding(node, binding);
+}
+
+fn checkBinding(self: *TypeChecker, node: *const Node, binding: Node.Binding) CheckError!Type {
+    const kind: Symbol.Kind = if (binding.mutable) .variable else .constant;
+
+    const annotation: ?Type = if (binding.annotation) |annotation| try self.resolveType(annotation)
+        else null;
+
+    const @"type": Type = block: {
+        const value = binding.value orelse {
+            if (annotation) |expected| break :block expected;
+
+            // Inferred from the values later assigned to the binding
+            const inferred = try self.inference.fresh(binding.name, node.span, .any, node.span);
+            try self.types.putBinding(node.id, inferred);
+            break :block inferred;
+        };
+
+        const actual = try self.checkNode(value);
+        const expected = annotation orelse {
+            // Numeric literals take on the type the binding is used as
+            const class = numericClass(value);
+            if (class == .any) break :block actual;
+
+            const inferred = try self.inference.fresh(binding.name, node.span, class, value.span);
+            try self.retype(value, inferred);
+            break :block inferred;
+        };
+        if (!try self.coerce(value, actual, expected)) {
+            try self.createError(value.span, "value does not match the binding's type");
+        }
+
+        break :block expected;
+    };
+
+    if (node.kind == .const_decl) {
+        try self.evaluateConst(node, binding);
+    } else {
+        self.consts.forget(binding.name);
+    }
+
+    if (self.unit.symbols.depth() == 1 and containsVariable(@"type")) try self.inferred_bindings.append(binding.name);
+    try self.define(node.span, binding.name, kind, @"type");
+    return .unit;
+}
+
+// The values of consts must be known before the program runs, those whose values
+// failed to check aren't evaluated
+fn evaluateConst(self: *TypeChecker, node: *const Node, binding: Node.Binding) CheckError!void {
+    const value = binding.value orelse {
+        try self.consts.invalidate(binding.name);
+        return try self.createError(node.span, "const declarations must be given a value");
+    };
+
+    if (self.types.get(value.id)) |@"type"| if (@"type" == .invalid) return try self.consts.invalidate(binding.name);
+
+    _ = self.consts.declare(binding.name, value) catch |err| switch (err) {
+        error.NotConstant => try self.createError(value.span, "const expression must be evaluable at compile time"),
+        // Consts referring to consts which were already reported
+        error.InvalidConstant => {},
+        error.OutOfMemory => return error.OutOfMemory
+    };
+}
+
+// Only patterns matching every value can be bound by let, others need the arms of
+// a match for the values they don't match
+pub fn visitDestructure(self: *TypeChecker, _: *const Node, destructure: Node.Destructure) CheckError!Type {
+    const value = destructure.value;
+    const actual = try self.checkNode(value);
+
+    const @"type": Type = if (destructure.annotation) |annotation| block: {
+        const expected = try self.resolveType(annotation);
+        if (!try self.coerce(value, actual, expected)) {
+            try self.createError(value.span, "value does not match the binding's type");
+        }
+
+        break :block expected;
+    } else try self.known(value, actual);
+
+    const pattern = destructure.pattern;
+    if (!pattern.isIrrefutable()) {
+        try self.createError(pattern.span, "let bindings can't use refutable patterns, use match instead");
+    }
+
+    try self.checkPattern(pattern, @"type");
+    return .unit;
+}
+
+// Imports are resolved by the compiler before checking, which merges the imported
+// modules into the ast
+pub fn visitImport(self: *TypeChecker, node: *const Node, _: Node.Import) CheckError!Type {
+    if (self.unit.symbols.depth() > 1) try self.createError(node.span, "imports must be at the top level");
+    return .unit;
+}
+
+pub fn visitExport(self: *TypeChecker, node: *const Node, declaration: *Node) CheckError!Type {
+    if (self.unit.symbols.depth() > 1) try self.createError(node.span, "only top level declarations can be exported");
+    return try self.checkNode(declaration);
+}
+
+pub fn visitBlock(self: *TypeChecker, _: *const Node, block: Node.Block) CheckError!Type {
+    try self.unit.symbols.enterScope();
+    defer self.unit.symbols.exitScope();
+
+    try self.declareDefinitions(block.statements);
+
+    // A block evaluates to its final statement
+    var @"type": Type = .unit;
+    for (block.statements) |statement| @"type" = try self.checkNode(statement);
+
+    if (block.statements.len > 0) {
+        try self.checkEscape(block.statements[block.statements.len - 1], self.unit.symbols.depth() - 1);
+    }
+
+    return @"type";
+}
+
+pub fn visitIf(self: *TypeChecker, _: *const Node, conditional: Node.If) CheckError!Type {
+    try self.expectCondition(conditional.condition);
+
+    const then_type = try self.checkNode(conditional.then_block);
+    const else_block = conditional.else_block orelse return .unit;
+    const else_type = try self.checkNode(else_block);
+
+    return try self.unify(conditional.then_block, then_type, else_block, else_type) orelse {
+        try self.createError(else_block.span, "if and else branches have different types");
+        return .invalid;
+    };
+}
+
+pub fn visitWhile(self: *TypeChecker, _: *const Node, loop: Node.While) CheckError!Type {
+    try self.expectCondition(loop.condition);
+    try self.checkLoopBody(loop.body);
+
+    return .unit;
+}
+
+pub fn visitFor(self: *TypeChecker, node: *const Node, loop: Node.For) CheckError!Type {
+    const element: Type = switch (try self.known(loop.iterable, try self.checkNode(loop.iterable))) {
+        .range => |element| element.*,
+        .invalid => .invalid,
+        else => block: {
+            try self.createError(loop.iterable.span, "only ranges can be iterated over");
+            break :block .invalid;
+        }
+    };
+
+    try self.unit.symbols.enterScope();
+    defer self.unit.symbols.exitScope();
+
+    try self.define(node.span, loop.variable, .constant, element);
+    try self.checkLoopBody(loop.body);
+
+    return .unit;
+}
+
+// A match evaluates to the type of its arms, which must all have the same type
+pub fn visitMatch(self: *TypeChecker, node: *const Node, match: Node.Match) CheckError!Type {
+    const subject = try self.known(match.subject, try self.checkNode(match.subject));
+
+    var result: Type = .unit;
+    var first: ?*const Node = null;
+    for (match.arms) |arm| {
+        try self.unit.symbols.enterScope();
+        defer self.unit.symbols.exitScope();
+
+        try self.checkPattern(arm.pattern, subject);
+        if (arm.guard) |guard| try self.expectCondition(guard);
+
+        const body = try self.checkNode(arm.body);
+        const previous = first orelse {
+            first = arm.body;
+            result = body;
+            continue;
+        };
+
+        result = try self.unify(previous, result, arm.body, body) orelse {
+            try self.createError(arm.body.span, "match arms have different types");
+            return .invalid;
+        };
+    }
+
+    return result;
+}
+
+/// Checks pattern can match values of type expected, binding the names it binds
+fn checkPattern(self: *TypeChecker, pattern: Node.Pattern, expected: Type) CheckError!void {
+    switch (pattern.kind) {
+        .literal => |literal| {
+            if (expected == .invalid) return;
+
+            const matches = switch (literal) {
+                .integer => expected.isInteger(),
+                .float => expected.isFloat(),
+                .boolean => expected == .@"bool",
+                .string => expected == .str,
+                .character => expected == .char,
+                .unit => expected == .unit
+            };
+            if (!matches) try self.createError(pattern.span, "pattern does not match the type of the matched value");
+        },
+        .identifier => |name| try self.define(pattern.span, name, .constant, expected),
+        .wildcard => {},
+        // Only one alternative matches, so names bound by the others would be left unbound
+        .@"or" => |alternatives| for (alternatives) |alternative| {
+            if (alternative.binds()) {
+                try self.createError(alternative.span, "or patterns can't bind names");
+            } else {
+                try self.checkPattern(alternative, expected);
+            }
+        },
+        .@"struct" => |structure_pattern| switch (expected) {
+            .@"struct" => |structure| try self.checkStructPattern(pattern, structure_pattern, structure),
+            else => try self.invalidPattern(pattern, expected)
+        },
+        .variant => |variant_pattern| switch (expected) {
+            .@"enum" => |enumeration| try self.checkVariantPattern(pattern, variant_pattern, enumeration),
+            else => try self.invalidPattern(pattern, expected)
+        },
+        .tuple => |elements| switch (expected) {
+            .tuple => |types| if (elements.len == types.len) {
+                for (elements, types) |element, @"type"| try self.checkPattern(element, @"type");
+            } else {
+                try self.unit.createErrorFmt(pattern.span, "pattern has {} elements but the tuple has {}", .{elements.len, types.len});
+                for (elements) |element| try self.checkPattern(element, .invalid);
+            },
+            else => {
+                try self.invalidPattern(pattern, expected);
+                for (elements) |element| try self.checkPattern(element, .invalid);
+            }
+        }
+    }
+}
+
+// Variants are named by their enum and name, or by their name alone as the enum
+// is known from the matched value
+fn checkVariantPattern(
+    self: *TypeChecker,
+    pattern: Node.Pattern,
+    variant_pattern: Node.Pattern.VariantPattern,
+    enumeration: *const Type.Enum
+) CheckError!void {
+    const path = variant_pattern.path;
+    const name = path[path.len - 1];
+
+    const index = block: {
+        if (path.len > 2 or (path.len == 2 and !std.mem.eql(u8, path[0], enumeration.name))) {
+            try self.createError(pattern.span, "pattern does not match the type of the matched value");
+            break :block null;
+        }
+
+        break :block enumeration.variantIndex(name) orelse {
+            try self.unit.createErrorFmt(pattern.span, "{s} has no variant named {s}", .{enumeration.name, name});
+            break :block null;
+        };
+    } orelse return try self.bindInvalid(variant_pattern.fields);
+
+    const variant = enumeration.variants[index];
+    const matches = switch (variant_pattern.fields) {
+        .unit => variant.fields == .unit,
+        .tuple => |fields| variant.fields == .tuple and fields.len == variant.fields.tuple.len,
+        .@"struct" => variant.fields == .@"struct"
+    };
+
+    if (!matches) {
+        try self.unit.createErrorFmt(pattern.span, "pattern does not match the fields of {s}::{s}", .{
+            enumeration.name,
+            variant.name
+        });
+        return try self.bindInvalid(variant_pattern.fields);
+    }
+
+    switch (variant_pattern.fields) {
+        .unit => {},
+        .tuple => |fields| for (fields, variant.fields.tuple) |field, @"type"| try self.checkPattern(field, @"type"),
+        .@"struct" => |fields| for (fields) |field| {
+            const field_index = Type.Struct.indexOfField(variant.fields.@"struct", field.name) orelse {
+                try self.unit.createErrorFmt(field.pattern.span, "{s}::{s} has no field named {s}", .{
+                    enumeration.name,
+                    variant.name,
+                    field.name
+                });
+                try self.checkPattern(field.pattern, .invalid);
+                continue;
+            };
+
+            try self.checkPattern(field.pattern, variant.fields.@"struct"[field_index].@"type");
+        }
+    }
+}
+
+// Binds the names in the fields of a variant pattern which failed to check, so
+// uses of them aren't reported as undefined
+fn bindInvalid(self: *TypeChecker, fields: Node.Pattern.VariantPattern.Fields) CheckError!void {
+    switch (fields) {
+        .unit => {},
+        .tuple => |patterns| for (patterns) |field| try self.checkPattern(field, .invalid),
+        .@"struct" => |patterns| for (patterns) |field| try self.checkPattern(field.pattern, .invalid)
+    }
+}
+
+fn checkStructPattern(
+    self: *TypeChecker,
+    pattern: Node.Pattern,
+    structure_pattern: Node.Pattern.StructPattern,
+    structure: *const Type.Struct
+) CheckError!void {
+    if (!std.mem.eql(u8, structure_pattern.name, structure.name)) {
+        return try self.createError(pattern.span, "pattern does not match the type of the matched value");
+    }
+
+    if (structure_pattern.fields.len > 0) try self.checkUnionAccess(pattern.span, structure);
+
+    for (structure_pattern.fields) |field| {
+        const index = structure.fieldIndex(field.name) orelse {
+            try self.unit.createErrorFmt(field.pattern.span, "{s} has no field named {s}", .{structure.name, field.name});
+            continue;
+        };
+
+        try self.checkPattern(field.pattern, structure.fields[index].@"type");
+    }
+}
+
+fn invalidPattern(self: *TypeChecker, pattern: Node.Pattern, expected: Type) CheckError!void {
+    if (expected == .invalid) return;
+
+    try self.unit.createErrorFmt(pattern.span, "{s} patterns can't match values of type {}", .{
+        @tagName(pattern.kind),
+        expected
+    });
+}
+
+fn checkLoopBody(self: *TypeChecker, body: *const Node) CheckError!void {
+    self.loops = self.loops + 1;
+    defer self.loops = self.loops - 1;
+
+    _ = try self.checkNode(body);
+}
+
+// Loops evaluate to (), so only breaks without a value or with a () value match them
+pub fn visitBreak(self: *TypeChecker, node: *const Node, value: ?*Node) CheckError!Type {
+    const actual: Type = if (value) |v| try self.checkNode(v) else .unit;
+
+    if (self.loops == 0) {
+        try self.createError(node.span, if (self.deferring) "defers can't break out of the loops enclosing them"
+            else "break outside of a loop");
+    } else if (!try self.coerce(value orelse node, actual, .unit)) {
+        try self.createError(node.span, "break value does not match the loop's type, loops evaluate to ()");
+    }
+
+    return .unit;
+}
+
+pub fn visitContinue(self: *TypeChecker, node: *const Node, _: void) CheckError!Type {
+    if (self.loops == 0) {
+        try self.createError(node.span, if (self.deferring) "defers can't continue the loops enclosing them"
+            else "continue outside of a loop");
+    }
+
+    return .unit;
+}
+
+pub fn visitLabel(_: *TypeChecker, _: *const Node, _: []const u8) CheckError!Type {
+    return .unit;
+}
+
+// Gotos jump to a label of the function they are written in, and only within
+// unsafe blocks or where the goto lint is allowed. Jumping past the declaration of
+// a binding which is still in scope at the label leaves it without its value
+pub fn visitGoto(self: *TypeChecker, node: *const Node, target: []const u8) CheckError!Type {
+    if (!self.in_unsafe and !self.unit.isAllowed(.goto, node.span)) {
+        try self.createError(node.span, "gotos can only be written within unsafe blocks, or where #[allow(goto)] applies");
+    }
+
+    const labels = self.labels orelse {
+        try self.createError(node.span, "goto outside of a function");
+        return .unit;
+    };
+
+    if (self.deferring) {
+        try self.createError(node.span, "defers can't jump out of the blocks enclosing them");
+    } else if (labels.defers) {
+        try self.createError(node.span, "gotos can't be written in functions with defers, which they would skip");
+    }
+
+    const label = labels.get(target) orelse {
+        try self.unit.createErrorFmt(node.span, "goto target {s} isn't a label within this function", .{target});
+        return .unit;
+    };
+
+    if (labels.skippedBinding(node, label)) |binding| {
+        try self.unit.report(.init(.warning, try std.fmt.allocPrint(
+            self.unit.arena.allocator(),
+            "goto {s} jumps over the initialization of {s}",
+            .{target, binding.name}
+        ), node.span));
+    }
+
+    return .unit;
+}
+
+// Defers run as their block is left, however it is left, so they can't leave the
+// block themselves. Their bodies are checked where they are written, so they can
+// only refer to the bindings declared before them
+pub fn visitDefer(self: *TypeChecker, node: *const Node, body: *Node) CheckError!Type {
+    if (self.return_type == null) try self.createError(node.span, "defer outside of a function");
+
+    const outer_loops = self.loops;
+    self.loops = 0;
+    defer self.loops = outer_loops;
+
+    const outer_deferring = self.deferring;
+    self.deferring = true;
+    defer self.deferring = outer_deferring;
+
+    _ = try self.checkNode(body);
+    return .unit;
+}
+
+/// Sizes are those of the target compiled for, types without a fixed layout have none
+pub fn visitSizeof(self: *TypeChecker, node: *const Node, annotation: *Node) CheckError!Type {
+    const @"type" = try self.resolveType(annotation);
+    try self.record(annotation.id, @"type");
+    if (@"type" != .invalid and @"type".layout(Target.pointerSizeOf(self.unit.options.target)) == null) {
+        try self.unit.createErrorFmt(node.span, "values of type {} have no fixed size", .{@"type"});
+    }
+
+    return .@"i64";
+}
+
+/// Casts convert between numeric types, unsafe blocks may also cast between
+/// reference types. Narrowing integer casts warn unless the value is a literal
+/// which fits the type cast to
+pub fn visitCast(self: *TypeChecker, node: *const Node, cast: Node.Cast) CheckError!Type {
+    const value = try self.known(cast.value, try self.checkNode(cast.value));
+    const target = try self.resolveType(cast.target);
+    try self.record(cast.target.id, target);
+    if (value == .invalid or target == .invalid or value.eql(target)) return target;
+
+    if (value.isNumeric() and target.isNumeric()) {
+        if (value.isInteger() and target.isInteger() and !fitsCast(cast.value, value, target)) {
+            try self.unit.report(.initLint(.truncation, try std.fmt.allocPrint(
+                self.unit.arena.allocator(),
+                "casting {} to {} may truncate it",
+                .{value, target}
+            ), node.span));
+        }
+    } else if (value == .reference and target == .reference) {
+        if (!self.in_unsafe) try self.createError(node.span, "pointer casts can only be written within unsafe blocks");
+    } else {
+        try self.unit.createErrorFmt(node.span, "values of type {} can't be cast to {}", .{value, target});
+    }
+
+    return target;
+}
+
+// Whether every value of an integer cast fits the integer type it is cast to
+fn fitsCast(value: *const Node, from: Type, to: Type) bool {
+    const pointer_size = Target.pointerSizeOf(null);
+    if (from.layout(pointer_size).?.size <= to.layout(pointer_size).?.size) return true;
+
+    const integer = switch (value.kind) {
+        .literal => |literal| if (literal == .integer) literal.integer else return false,
+        .prefix => |prefix| if (prefix.operator == .negate and prefix.operand.kind == .literal and prefix.operand.kind.literal == .integer)
+            -%prefix.operand.kind.literal.integer
+            else return false,
+        else => return false
+    };
+
+    return switch (to) {
+        .@"i8" => std.math.cast(i8, integer) != null,
+        .@"i16" => std.math.cast(i16, integer) != null,
+        .@"i32" => std.math.cast(i32, integer) != null,
+        else => true
+    };
+}
+
+/// Extern functions are declared before the statements of their scope are checked
+pub fn visitExternFn(_: *TypeChecker, _: *const Node, _: Node.ExternFn) CheckError!Type {
+    return .unit;
+}
+
+/// Unsafe blocks have the type of their body, within which extern functions can be called
+pub fn visitUnsafe(self: *TypeChecker, _: *const Node, body: *Node) CheckError!Type {
+    const outer_unsafe = self.in_unsafe;
+    self.in_unsafe = true;
+    defer self.in_unsafe = outer_unsafe;
+
+    return try self.checkNode(body);
+}
+
+/// Comptime blocks are evaluated once checked, their bodies replaced by the
+/// literals they evaluate to. The target being compiled for is known within them
+pub fn visitComptime(self: *TypeChecker, node: *const Node, body: *Node) CheckError!Type {
+    // Those within another comptime block are evaluated with the bindings of the
+    // blocks enclosing them
+    const outer_comptime = self.in_comptime;
+    self.in_comptime = true;
+    defer self.in_comptime = outer_comptime;
+
+    try self.unit.symbols.enterScope();
+    defer self.unit.symbols.exitScope();
+
+    try self.define(node.span, "TARGET_BITS", .constant, .@"i64");
+    try self.define(node.span, "TARGET_OS", .constant, .str);
+    try self.define(node.span, "TARGET_ARCH", .constant, .str);
+
+    const error_count = self.unit.errorCount();
+    const @"type" = try self.checkNode(body);
+    if (outer_comptime or self.unit.errorCount() > error_count) return @"type";
+
+    const literal = self.consts.evalComptime(body) catch |err| switch (err) {
+        error.NotConstant => {
+            try self.createError(node.span, "comptime blocks must be evaluable at compile time");
+            return @"type";
+        },
+        error.InvalidConstant => return @"type",
+        error.OutOfMemory => return error.OutOfMemory
+    };
+
+    body.kind = .{ .literal = literal };
+    return @"type";
+}
+
+// Returns have the enclosing function's return type, so blocks ending in one
+// match the function they return from
+pub fn visitReturn(self: *TypeChecker, node: *const Node, value: ?*Node) CheckError!Type {
+    const actual: Type = if (value) |v| try self.checkNode(v) else .unit;
+    if (self.deferring) {
+        try self.createError(node.span, "defers can't return from the function enclosing them");
+        return .invalid;
+    }
+
+    const expected = self.return_type orelse {
+        try self.createError(node.span, "return outside of a function");
+        return .invalid;
+    };
+
+    if (!try self.coerce(value orelse node, actual, expected)) {
+        try self.createError(node.span, "returned value does not match the function's return type");
+    }
+
+    // Only references to top level bindings outlive the function
+    if (value) |v| try self.checkEscape(v, 1);
+
+    return expected;
+}
+
+pub fn visitAssignment(self: *TypeChecker, node: *const Node, assignment: Node.Assignment) CheckError!Type {
+    const target = try self.checkNode(assignment.lhs);
+    const value = try self.checkNode(assignment.rhs);
+
+    switch (assignment.lhs.kind) {
+        .identifier => |name| if (self.lookup(name)) |symbol| {
+            if (symbol.kind != .variable) {
+                try self.createError(assignment.lhs.span, "cannot assign to an immutable binding");
+            } else if (self.isCaptured(name)) {
+                try self.createError(assignment.lhs.span, "cannot assign to a captured binding, closures hold a copy of its value");
+            }
+
+            const id = self.unit.interner.find(name).?;
+            try self.checkEscape(assignment.rhs, self.unit.symbols.depthOf(id).?);
+        },
+        // Assigns to what the reference refers to
+        .dereference => {},
+        else => try self.createError(assignment.lhs.span, "invalid assignment target")
+    }
+
+    if (!try self.coerce(assignment.rhs, value, target)) {
+        try self.createError(node.span, "value does not match the assigned binding's type");
+    }
+
+    return .unit;
+}
+
+// Whether name refers to a binding captured by the innermost closure enclosing
+// the node being checked, rather than one of its own or a top level one
+fn isCaptured(self: *const TypeChecker, name: []const u8) bool {
+    const closure_depth = self.closure_depth orelse return false;
+    const id = self.unit.interner.find(name) orelse return false;
+    const depth = self.unit.symbols.depthOf(id) orelse return false;
+
+    return depth > 1 and depth <= closure_depth;
+}
+
+/// Only places can be referenced, var bindings and the fields and elements of
+/// them, or what a reference refers to
+pub fn visitReference(self: *TypeChecker, _: *const Node, operand: *const Node) CheckError!Type {
+    const @"type" = try self.checkNode(operand);
+    if (@"type" == .invalid) return .invalid;
+    if (!try self.checkReferenceable(operand, @"type")) return .invalid;
+
+    return try self.types.reference(@"type");
+}
+
+// Only places can be referenced, of var bindings which closures haven't captured.
+// Returns false if operand, of type, isn't a place
+fn checkReferenceable(self: *TypeChecker, operand: *const Node, @"type": Type) CheckError!bool {
+    // Methods are accessed like fields and slices are indexed like elements, but
+    // neither is stored in the value
+    const place = isPlace(operand)
+        and !(operand.kind == .field_access and @"type" == .function)
+        and !(operand.kind == .index and @"type" == .slice);
+    if (!place) {
+        try self.createError(operand.span, "only bindings, fields and elements can be referenced");
+        return false;
+    }
+
+    if (placeRoot(operand)) |name| {
+        if (self.lookup(name)) |symbol| {
+            if (symbol.kind != .variable) {
+                try self.unit.createErrorFmt(operand.span, "{s} is immutable, only var bindings can be referenced", .{name});
+            } else if (self.isCaptured(name)) {
+                try self.createError(operand.span, "cannot reference a captured binding, closures hold a copy of its value");
+            }
+        }
+    }
+
+    return true;
+}
+
+pub fn visitDereference(self: *TypeChecker, node: *const Node, operand: *const Node) CheckError!Type {
+    const @"type" = try self.known(operand, try self.checkNode(operand));
+
+    return switch (@"type") {
+        .reference => |element| element.*,
+        .invalid => .invalid,
+        else => block: {
+            try self.unit.createErrorFmt(node.span, "values of type {} can't be dereferenced", .{@"type"});
+            break :block .invalid;
+        }
+    };
+}
+
+/// Awaiting a future gives the value its async function returns
+pub fn visitAwait(self: *TypeChecker, node: *const Node, operand: *const Node) CheckError!Type {
+    const @"type" = try self.known(operand, try self.checkNode(operand));
+    if (!self.in_async) try self.createError(node.span, "await can only be used within async functions");
+
+    return switch (@"type") {
+        .future => |value| value.*,
+        .invalid => .invalid,
+        else => block: {
+            try self.unit.createErrorFmt(operand.span, "only futures can be awaited, found {}", .{@"type"});
+            break :block .invalid;
+        }
+    };
+}
+
+fn isPlace(node: *const Node) bool {
+    return switch (node.kind) {
+        .identifier, .dereference => true,
+        .field_access => |access| isPlace(access.object),
+        .index => |index| isPlace(index.object),
+        .tuple_index => |access| isPlace(access.object),
+        else => false
+    };
+}
+
+// The binding a place is a field or element of, places reached through a
+// dereference are rooted wherever the reference refers to
+fn placeRoot(node: *const Node) ?[]const u8 {
+    return switch (node.kind) {
+        .identifier => |name| name,
+        .field_access => |access| placeRoot(access.object),
+        .index => |index| placeRoot(index.object),
+        .tuple_index => |access| placeRoot(access.object),
+        else => null
+    };
+}
+
+// Warns when value is a reference to a binding declared in a scope deeper than
+// depth, which goes out of scope before the reference does
+fn checkEscape(self: *TypeChecker, value: *const Node, depth: usize) CheckError!void {
+    if (value.kind != .reference) return;
+
+    const name = placeRoot(value.kind.reference) orelse return;
+    const id = self.unit.interner.find(name) orelse return;
+    const declared = self.unit.symbols.depthOf(id) orelse return;
+    if (declared <= depth) return;
+
+    try self.unit.report(.initLint(.dangling_reference, try std.fmt.allocPrint(
+        self.unit.arena.allocator(),
+        "reference to {s} may outlive it, as {s} goes out of scope first",
+        .{name, name}
+    ), value.span));
+}
+
+pub fn visitPrefix(self: *TypeChecker, node: *const Node, prefix: Node.Prefix) CheckError!Type {
+    const operand = try self.checkNode(prefix.operand);
+    if (operand == .invalid) return .invalid;
+
+    const valid = switch (prefix.operator) {
+        .negate => self.isNumeric(operand),
+        .not => try self.coerce(prefix.operand, operand, .@"bool"),
+        .bit_not => self.isInteger(operand)
+    };
+
+    if (!valid) {
+        try self.createError(node.span, "invalid operand type for operator");
+        return .invalid;
+    }
+
+    return operand;
+}
+
+pub fn visitInfix(self: *TypeChecker, node: *const Node, infix: Node.Infix) CheckError!Type {
+    const lhs = try self.checkNode(infix.lhs);
+    const rhs = try self.checkNode(infix.rhs);
+
+    switch (infix.operator) {
+        .forward_app => {
+            try self.checkUnsafeCall(node, infix.lhs);
+            try self.checkComptimeCall(node, infix.lhs);
+            return try self.apply(node, lhs, &.{infix.rhs}, &.{rhs});
+        },
+        .reverse_app => {
+            try self.checkUnsafeCall(node, infix.rhs);
+            try self.checkComptimeCall(node, infix.rhs);
+            return try self.apply(node, rhs, &.{infix.lhs}, &.{lhs});
+        },
+        else => {}
+    }
+
+    if (lhs == .invalid or rhs == .invalid) return .invalid;
+
+    const unified = try self.unify(infix.lhs, lhs, infix.rhs, rhs) orelse {
+        try self.createError(node.span, "mismatched operand types");
+        return .invalid;
+    };
+    const operand = self.inference.resolve(unified);
+
+    const valid = switch (infix.operator) {
+        .add, .subtract, .multiply, .divide, .modulo, .exponent,
+        .lesser, .lesser_eq, .greater, .greater_eq => self.isNumeric(operand),
+        .bit_and, .bit_or, .bit_xor, .lshift, .rshift,
+        .range_exc, .range_inc => self.isInteger(operand),
+        .equal, .not_equal => operand != .function,
+        .@"and", .@"or" => try self.coerce(infix.lhs, operand, .@"bool"),
+        .concat => try self.coerce(infix.lhs, operand, .str),
+        .forward_app, .reverse_app => unreachable
+    };
+
+    if (!valid) {
+        try self.createError(node.span, "invalid operand types for operator");
+        return .invalid;
+    }
+
+    return switch (infix.operator) {
+        .lesser, .lesser_eq, .greater, .greater_eq,
+        .equal, .not_equal => .@"bool",
+        .range_exc, .range_inc => try self.types.range(operand),
+        else => operand
+    };
+}
+
+pub fn visitFnCall(self: *TypeChecker, node: *const Node, call: Node.Call) CheckError!Type {
+    const callee = try self.checkNode(call.callee);
+    if (self.isPreludeFunction(call.callee, "assert")) return try self.checkAssert(node, call.arguments);
+    if (self.isPreludeFunction(call.callee, "fmt")) return try self.checkFmt(node, call.arguments);
+
+    var arguments = ArrayList(Type).init(self.allocator);
+    defer arguments.deinit();
+
+    for (call.arguments) |argument| try arguments.append(try self.checkNode(argument));
+
+    try self.checkUnsafeCall(node, call.callee);
+    try self.checkComptimeCall(node, call.callee);
+    const @"type" = try self.apply(node, callee, call.arguments, arguments.items);
+
+    const builtin = self.builtinCalled(call.callee) orelse return @"type";
+    if (builtin.ret != .number) return @"type";
+
+    const number = try self.known(node, @"type");
+    if (number != .invalid and !number.isNumeric()) {
+        try self.unit.createErrorFmt(node.span, "{s}.{s} expects integers or floats, found {}", .{
+            builtin.module,
+            builtin.name,
+            number
+        });
+        return .invalid;
+    }
+
+    return number;
+}
+
+// `assert(condition)` or `assert(condition, message)`
+fn checkAssert(self: *TypeChecker, node: *const Node, arguments: []const *Node) CheckError!Type {
+    if (arguments.len == 0 or arguments.len > 2) {
+        try self.createError(node.span, "assert expects a condition and optionally a message");
+    }
+
+    for (arguments, 0..) |argument, i| {
+        const expected: Type = if (i == 0) .@"bool" else .str;
+        if (!try self.coerce(argument, try self.checkNode(argument), expected)) {
+            try self.createError(argument.span, if (i == 0) "assert's condition must be a bool" else "assert's message must be a str");
+        }
+    }
+
+    return .unit;
+}
+
+// `fmt(template, arguments...)`, the template is a string literal so its
+// placeholders are counted against the arguments and checked against their types
+fn checkFmt(self: *TypeChecker, node: *const Node, arguments: []const *Node) CheckError!Type {
+    if (arguments.len == 0) {
+        try self.createError(node.span, "fmt expects a template and its arguments");
+        return .str;
+    }
+
+    const template_node = arguments[0];
+    const values = arguments[1..];
+    _ = try self.checkNode(template_node);
+
+    const template = switch (template_node.kind) {
+        .literal => |literal| if (literal == .string) literal.string else null,
+        else => null
+    } orelse {
+        for (values) |value| _ = try self.checkNode(value);
+
+        try self.createError(template_node.span, "fmt's template must be a string literal");
+        return .str;
+    };
+
+    const placeholders = stdlib.fmt.countPlaceholders(template) catch |err| {
+        for (values) |value| _ = try self.checkNode(value);
+
+        try self.createError(template_node.span, stdlib.fmt.describe(err));
+        return .str;
+    };
+
+    if (placeholders != values.len) {
+        for (values) |value| _ = try self.checkNode(value);
+
+        try self.unit.createErrorFmt(node.span, "fmt's template has {} placeholder(s) but {} argument(s) were given", .{
+            placeholders,
+            values.len
+        });
+        return .str;
+    }
+
+    var next: usize = 0;
+    var pieces = stdlib.fmt.Iterator.init(template);
+    while (pieces.next() catch unreachable) |piece| {
+        const specifier = switch (piece) {
+            .text => continue,
+            .placeholder => |specifier| specifier
+        };
+
+        const value = values[next];
+        next = next + 1;
+
+        const @"type" = try self.known(value, try self.checkNode(value));
+        switch (specifier) {
+            .hex => if (@"type" != .invalid and !@"type".isInteger()) {
+                try self.unit.createErrorFmt(value.span, "{{:x}} formats integers, found {}", .{@"type"});
+            },
+            .precision => if (@"type" != .invalid and !@"type".isFloat()) {
+                try self.unit.createErrorFmt(value.span, "{{:.N}} formats floats, found {}", .{@"type"});
+            },
+            .default => switch (@"type") {
+                .@"bool", .str, .char, .invalid => {},
+                else => if (!@"type".isNumeric()) {
+                    try self.unit.createErrorFmt(value.span, "values of type {} can't be formatted", .{@"type"});
+                }
+            }
+        }
+    }
+
+    return .str;
+}
+
+// Comptime blocks are run by the compiler, so can't read or write anything outside
+// of the program being compiled
+fn checkComptimeCall(self: *TypeChecker, node: *const Node, callee: *const Node) CheckError!void {
+    if (!self.in_comptime) return;
+
+    if (self.builtinCalled(callee)) |builtin| if (std.mem.eql(u8, builtin.module, "io")) {
+        return try self.createError(node.span, "comptime blocks can't perform io");
+    };
+
+    if (callee.kind != .identifier) return;
+    const symbol = self.lookup(callee.kind.identifier) orelse return;
+    if (symbol.kind == .extern_function) {
+        try self.createError(node.span, "comptime blocks can't call extern functions");
+    }
+}
+
+// Extern functions can do anything, so calls of them are only allowed where the
+// program says it knows, within unsafe blocks
+fn checkUnsafeCall(self: *TypeChecker, node: *const Node, callee: *const Node) CheckError!void {
+    if (self.in_unsafe or callee.kind != .identifier) return;
+
+    const symbol = self.lookup(callee.kind.identifier) orelse return;
+    if (symbol.kind == .extern_function) {
+        try self.createError(node.span, "extern functions can only be called within unsafe blocks");
+    }
+}
+
+/// Checks calling a value of type callee with arguments
+fn apply(
+    self: *TypeChecker,
+    node: *const Node,
+    callee: Type,
+    arguments: []const *Node,
+    argument_types: []const Type
+) CheckError!Type {
+    const function = switch (callee) {
+        .function => |function| function,
+        .invalid => return .invalid,
+        else => {
+            try self.createError(node.span, "called value is not a function");
+            return .invalid;
+        }
+    };
+
+    const arity_matches = if (function.variadic) arguments.len >= function.params.len
+        else arguments.len == function.params.len;
+    if (!arity_matches) {
+        try self.createError(node.span, "wrong number of arguments");
+        return if (function.type_params.len > 0) .invalid else function.ret.*;
+    }
+
+    const instance = if (function.type_params.len > 0)
+        try self.instantiate(node, function, arguments, argument_types)
+        else function;
+
+    const count = instance.params.len;
+    for (arguments[0..count], argument_types[0..count], instance.params) |argument, argument_type, param| {
+        if (!try self.coerce(argument, argument_type, param)) {
+            try self.createError(argument.span, "argument does not match the parameter's type");
+        }
+    }
+
+    // The arguments passed for `...` take whichever types they have
+    for (arguments[count..], argument_types[count..]) |argument, argument_type| {
+        _ = try self.known(argument, argument_type);
+    }
+
+    return instance.ret.*;
+}
+
+// Infers the types substituted for the type parameters of a generic function from
+// the arguments of a call, recording them so the backends can generate a function
+// for each distinct instantiation
+fn instantiate(
+    self: *TypeChecker,
+    node: *const Node,
+    function: Type.Function,
+    arguments: []const *Node,
+    argument_types: []const Type
+) CheckError!Type.Function {
+    var substitution = Substitution.init(self.allocator);
+    defer substitution.deinit();
+
+    // Literals can be coerced to the types inferred from the other arguments, so
+    // they are only inferred from when nothing else is
+    for ([_]bool{false, true}) |literals| {
+        for (arguments, argument_types, function.params) |argument, argument_type, param| {
+            if ((literalOf(argument) != null) != literals) continue;
+            if (literals and param == .parameter and substitution.contains(param.parameter)) continue;
+
+            const inferred = try self.known(argument, argument_type);
+            try self.inferParams(argument.span, function.type_params, param, inferred, &substitution);
+        }
+    }
+
+    const type_arguments = try self.inferredArguments(node.span, function.type_params, &substitution);
+    defer self.allocator.free(type_arguments);
+
+    try self.types.putInstantiation(node.id, type_arguments);
+    return (try self.substitute(.{ .function = function }, &substitution)).function;
+}
+
+/// Checks a struct literal gives each of the struct's fields exactly once
+pub fn visitStructLiteral(self: *TypeChecker, node: *const Node, literal: Node.StructLiteral) CheckError!Type {
+    const structure = self.structNamed(literal.name) orelse {
+        try self.unit.createErrorFmt(node.span, "{s} is not a struct", .{literal.name});
+        for (literal.fields) |field| _ = try self.checkNode(field.value);
+
+        return .invalid;
+    };
+
+    if (structure.overlaid) {
+        try self.checkUnionValue(node, structure, literal.fields);
+        return .{ .@"struct" = structure };
+    }
+
+    if (structure.type_params.len == 0) {
+        try self.checkFieldValues(node, structure.name, structure.fields, literal.fields, null);
+        return .{ .@"struct" = structure };
+    }
+
+    // The instance of a generic struct is inferred from the values of its fields
+    var substitution = Substitution.init(self.allocator);
+    defer substitution.deinit();
+
+    try self.checkFieldValues(node, structure.name, structure.fields, literal.fields, .{
+        .type_params = structure.type_params,
+        .substitution = &substitution
+    });
+
+    const arguments = try self.inferredArguments(node.span, structure.type_params, &substitution);
+    defer self.allocator.free(arguments);
+
+    return try self.instantiateStruct(structure, arguments);
+}
+
+// Union literals write a single field, which holds the union's value
+fn checkUnionValue(
+    self: *TypeChecker,
+    node: *const Node,
+    overlaid: *const Type.Struct,
+    fields: []const Node.StructLiteral.Field
+) CheckError!void {
+    for (fields) |field| {
+        const value = try self.checkNode(field.value);
+
+        const index = overlaid.fieldIndex(field.name) orelse {
+            try self.unit.createErrorFmt(field.span, "{s} has no field named {s}", .{overlaid.name, field.name});
+            continue;
+        };
+
+        if (!try self.coerce(field.value, value, overlaid.fields[index].@"type")) {
+            try self.unit.createErrorFmt(field.value.span, "value does not match the type of field {s}", .{field.name});
+        }
+    }
+
+    if (fields.len != 1) {
+        try self.unit.createErrorFmt(node.span, "{s} literals give exactly one field, unions hold one at a time", .{overlaid.name});
+    }
+}
+
+// Only the field of a union last written holds a value, so reading one is unsafe
+fn checkUnionAccess(self: *TypeChecker, span: Span, structure: *const Type.Struct) CheckError!void {
+    if (!structure.overlaid or self.in_unsafe) return;
+    try self.unit.createErrorFmt(span, "the fields of union {s} can only be accessed within unsafe blocks", .{structure.name});
+}
+
+/// The type parameters whose types are inferred from the values given for them
+const ParamInference = struct {
+    type_params: []const []const u8,
+    substitution: *Substitution
+};
+
+// Checks fields are given once each, with values of their types. Name is what the
+// fields are described as belonging to
+fn checkFieldValues(
+    self: *TypeChecker,
+    node: *const Node,
+    name: []const u8,
+    expected: []const Type.Struct.Field,
+    fields: []const Node.StructLiteral.Field,
+    inference: ?ParamInference
+) CheckError!void {
+    const given = try self.allocator.alloc(bool, expected.len);
+    defer self.allocator.free(given);
+    @memset(given, false);
+
+    for (fields) |field| {
+        const value = try self.checkNode(field.value);
+
+        const index = Type.Struct.indexOfField(expected, field.name) orelse {
+            try self.unit.createErrorFmt(field.span, "{s} has no field named {s}", .{name, field.name});
+            continue;
+        };
+
+        if (given[index]) {
+            try self.unit.createErrorFmt(field.span, "field {s} is given more than once", .{field.name});
+            continue;
+        }
+        given[index] = true;
+
+        var field_type = expected[index].@"type";
+        if (inference) |inferring| {
+            const inferred = try self.known(field.value, value);
+            try self.inferParams(field.value.span, inferring.type_params, field_type, inferred, inferring.substitution);
+            field_type = try self.substitute(field_type, inferring.substitution);
+        }
+
+        if (!try self.coerce(field.value, value, field_type)) {
+            try self.unit.createErrorFmt(field.value.span, "value does not match the type of field {s}", .{field.name});
+        }
+    }
+
+    for (expected, given) |field, was_given| if (!was_given) {
+        try self.unit.createErrorFmt(node.span, "{s} literal is missing field {s}", .{name, field.name});
+    };
+}
+
+/// Checks a variant literal names a variant of the enum, giving its fields in the
+/// style the variant declares them
+pub fn visitVariantLiteral(self: *TypeChecker, node: *const Node, literal: Node.VariantLiteral) CheckError!Type {
+    const definition = self.enumNamed(literal.@"enum") orelse {
+        try self.unit.createErrorFmt(node.span, "{s} is not an enum", .{literal.@"enum"});
+        try self.checkVariantValues(literal.fields);
+
+        return .invalid;
+    };
+    const enumeration = if (definition.type_params.len > 0) try self.freshInstance(node, definition) else definition;
+
+    const index = enumeration.variantIndex(literal.variant) orelse {
+        try self.unit.createErrorFmt(node.span, "{s} has no variant named {s}", .{enumeration.name, literal.variant});
+        try self.checkVariantValues(literal.fields);
+
+        return .invalid;
+    };
+    const variant = enumeration.variants[index];
+
+    const name = try std.fmt.allocPrint(self.allocator, "{s}::{s}", .{enumeration.name, variant.name});
+    defer self.allocator.free(name);
+
+    if (std.meta.activeTag(literal.fields) != std.meta.activeTag(variant.fields)) {
+        try self.unit.createErrorFmt(node.span, "{s} is {s}", .{name, switch (variant.fields) {
+            .unit => "a variant without fields",
+            .tuple => "a tuple variant, its values are given in parentheses",
+            .@"struct" => "a struct variant, its fields are given in braces"
+        }});
+        try self.checkVariantValues(literal.fields);
+
+        return .{ .@"enum" = enumeration };
+    }
+
+    switch (literal.fields) {
+        .unit => {},
+        .tuple => |values| {
+            const types = variant.fields.tuple;
+            if (values.len != types.len) {
+                try self.unit.createErrorFmt(node.span, "{s} holds {} value(s), found {}", .{name, types.len, values.len});
+                try self.checkVariantValues(literal.fields);
+            } else for (values, types) |value, @"type"| {
+                const actual = try self.checkNode(value);
+                if (!try self.coerce(value, actual, @"type")) {
+                    try self.unit.createErrorFmt(value.span, "value does not match the type held by {s}", .{name});
+                }
+            }
+        },
+        .@"struct" => |fields| try self.checkFieldValues(node, name, variant.fields.@"struct", fields, null)
+    }
+
+    return .{ .@"enum" = enumeration };
+}
+
+// The type arguments of a generic enum's variants are inferred, from the values
+// they hold and from where they are used, so each is given a type variable
+fn freshInstance(self: *TypeChecker, node: *const Node, definition: *const Type.Enum) CheckError!*const Type.Enum {
+    const arguments = try self.allocator.alloc(Type, definition.type_params.len);
+    defer self.allocator.free(arguments);
+
+    for (arguments, definition.type_params) |*argument, name| {
+        const description = try std.fmt.allocPrint(self.unit.arena.allocator(), "{s}'s {s}", .{definition.name, name});
+        argument.* = try self.inference.fresh(description, node.span, .any, node.span);
+    }
+
+    return (try self.instantiateEnum(definition, arguments)).@"enum";
+}
+
+// Err and None values are returned from the enclosing function, so it must return
+// the same enum, a Result whose error type the value's can be used as. Ok and Some
+// values are unwrapped
+pub fn visitTry(self: *TypeChecker, node: *const Node, operand: *const Node) CheckError!Type {
+    const @"type" = try self.known(operand, try self.checkNode(operand));
+    if (@"type" == .invalid) return .invalid;
+
+    if (self.deferring) {
+        try self.createError(node.span, "? can't be used within a defer, which can't return from the function enclosing it");
+        return .invalid;
+    }
+
+    const definition = for ([_]*const Type.Enum{self.prelude.result, self.prelude.option}) |prelude| {
+        if (instanceArguments(prelude, @"type") != null) break prelude;
+    } else {
+        try self.unit.createErrorFmt(operand.span, "? can only be applied to Result or Option values, found {}", .{@"type"});
+        return .invalid;
+    };
+    const arguments = instanceArguments(definition, @"type").?;
+
+    const return_type = self.inference.resolve(self.return_type orelse .unit);
+    const expected = instanceArguments(definition, return_type) orelse switch (return_type) {
+        // Closures whose result isn't annotated return the enum of the value, of
+        // its error for Results
+        .variable => |variable| block: {
+            const value = try self.inference.fresh("the closure's value", node.span, .any, node.span);
+            const instance = if (definition == self.prelude.result)
+                try self.instantiateEnum(definition, &.{value, arguments[1]})
+                else try self.instantiateEnum(definition, &.{value});
+            try self.constrain(node, variable, .{ .@"type" = instance });
+
+            break :block instance.@"enum".arguments;
+        },
+        .invalid => return arguments[0],
+        else => {
+            try self.unit.createErrorFmt(node.span, "? can only be used in functions returning {s}", .{definition.name});
+            return arguments[0];
+        }
+    };
+
+    if (definition == self.prelude.result and !try self.unifyTypes(node, arguments[1], expected[1])) {
+        try self.unit.createErrorFmt(node.span, "the value's error type {} doesn't match the function's, {}", .{
+            arguments[1],
+            expected[1]
+        });
+    }
+
+    return arguments[0];
+}
+
+// Checks the values of a variant literal which can't be checked against the variant
+fn checkVariantValues(self: *TypeChecker, fields: Node.VariantLiteral.Fields) CheckError!void {
+    switch (fields) {
+        .unit => {},
+        .tuple => |values| for (values) |value| {
+            _ = try self.checkNode(value);
+        },
+        .@"struct" => |values| for (values) |field| {
+            _ = try self.checkNode(field.value);
+        }
+    }
+}
+
+/// Checks the field or method accessed is defined for the struct. Methods take the
+/// value they are accessed through as their first argument, the other functions
+/// of the struct are accessed through its name
+pub fn visitFieldAccess(self: *TypeChecker, node: *const Node, access: Node.FieldAccess) CheckError!Type {
+    if (access.object.kind == .identifier) {
+        const name = access.object.kind.identifier;
+        if (self.structNamed(name)) |structure| {
+            return try self.findMethod(structure, access.field) orelse {
+                try self.unit.createErrorFmt(node.span, "{s} has no function named {s}", .{structure.name, access.field});
+                return .invalid;
+            };
+        }
+
+        if (self.isModule(name)) {
+            const qualified = try std.fmt.allocPrint(self.allocator, "{s}.{s}", .{name, access.field});
+            defer self.allocator.free(qualified);
+
+            const symbol = self.lookup(qualified) orelse {
+                try self.unit.createErrorFmt(node.span, "{s} has no function named {s}", .{name, access.field});
+                return .invalid;
+            };
+            return symbol.@"type".?;
+        }
+    }
+
+    const object = try self.known(access.object, try self.checkNode(access.object));
+    if (instanceArguments(self.prelude.option, object)) |arguments| {
+        return try self.optionMethod(node, access.field, arguments[0]);
+    }
+
+    if (object == .trait) return try self.traitMethod(node, access, object.trait, false);
+    if (object == .reference and object.reference.* == .trait) {
+        return try self.traitMethod(node, access, object.reference.trait, true);
+    }
+
+    // Fields and methods are accessed through references to structs as through the
+    // structs themselves
+    const through_reference = object == .reference and object.reference.* == .@"struct";
+    const structure = switch (if (through_reference) object.reference.* else object) {
+        .@"struct" => |structure| structure,
+        .invalid => return .invalid,
+        else => {
+            try self.unit.createErrorFmt(access.object.span, "values of type {} have no fields", .{object});
+            return .invalid;
+        }
+    };
+
+    if (structure.fieldIndex(access.field)) |index| {
+        try self.checkUnionAccess(node.span, structure);
+        return structure.fields[index].@"type";
+    }
+
+    const method = try self.findMethod(structure, access.field) orelse {
+        try self.unit.createErrorFmt(node.span, "{s} has no field or method named {s}", .{structure.name, access.field});
+        return .invalid;
+    };
+
+    const receiver: Type = .{ .@"struct" = structure };
+    const borrowed = try self.types.reference(receiver);
+
+    // The type parameters of generic methods are first inferred from the value
+    // they are accessed through
+    var function = method.function;
+    if (function.type_params.len > 0 and function.params.len > 0) {
+        var substitution = Substitution.init(self.allocator);
+        defer substitution.deinit();
+
+        const accessed = if (function.params[0] == .reference) borrowed else receiver;
+        try self.inferParams(access.object.span, function.type_params, function.params[0], accessed, &substitution);
+        function = (try self.substitute(method, &substitution)).function;
+    }
+
+    const borrows = function.params.len > 0 and function.params[0].eql(borrowed);
+    if (!borrows and (function.params.len == 0 or !function.params[0].eql(receiver))) {
+        try self.unit.createErrorFmt(node.span, "{s}.{s} is not a method, its first parameter isn't a {s} or a &{s}", .{
+            structure.name,
+            access.field,
+            structure.name,
+            structure.name
+        });
+        return .invalid;
+    }
+
+    // Methods taking a reference are given one to the value they're accessed through,
+    // unless it already is one
+    if (borrows and !through_reference) _ = try self.checkReferenceable(access.object, receiver);
+
+    return try self.types.genericFunction(function.type_params, function.params[1..], function.ret.*);
+}
+
+// Only the methods of a trait can be called on its values, which of the functions
+// defined for them is called is found once the program runs
+fn traitMethod(
+    self: *TypeChecker,
+    node: *const Node,
+    access: Node.FieldAccess,
+    trait: *const Type.Trait,
+    through_reference: bool
+) CheckError!Type {
+    const index = trait.methodIndex(access.field) orelse {
+        try self.unit.createErrorFmt(node.span, "{s} has no method named {s}", .{trait.name, access.field});
+        return .invalid;
+    };
+
+    // Each method takes self or &self, which is checked as its trait is declared
+    const function = trait.methods[index].function;
+    if (function.params[0] == .reference and !through_reference) {
+        _ = try self.checkReferenceable(access.object, .{ .trait = trait });
+    }
+
+    return try self.types.function(function.params[1..], function.ret.*);
+}
+
+// Impls can't be written for generic enums, so the methods of Option are defined
+// by the checker and the interpreter. The values its map and and_then methods
+// produce are inferred from the functions they are given
+fn optionMethod(self: *TypeChecker, node: *const Node, name: []const u8, value: Type) CheckError!Type {
+    if (std.mem.eql(u8, name, "is_some") or std.mem.eql(u8, name, "is_none")) {
+        return try self.types.function(&.{}, .@"bool");
+    }
+    if (std.mem.eql(u8, name, "unwrap_or")) return try self.types.function(&.{value}, value);
+
+    const maps = std.mem.eql(u8, name, "map");
+    if (!maps and !std.mem.eql(u8, name, "and_then")) {
+        try self.unit.createErrorFmt(node.span, "Option has no method named {s}", .{name});
+        return .invalid;
+    }
+
+    const mapped = try self.inference.fresh("the mapped value", node.span, .any, node.span);
+    const option = try self.instantiateEnum(self.prelude.option, &.{mapped});
+    const function = try self.types.function(&.{value}, if (maps) mapped else option);
+
+    return try self.types.function(&.{function}, option);
+}
+
+/// The elements of an array literal must have the same type. Numeric literals
+/// take on the type of the first element which isn't one, the type of an empty
+/// array's elements is inferred from its uses
+pub fn visitArrayLiteral(self: *TypeChecker, node: *const Node, elements: []*Node) CheckError!Type {
+    if (elements.len == 0) {
+        const element = try self.inference.fresh("the array's elements", node.span, .any, node.span);
+        return try self.types.array(element, 0);
+    }
+
+    const types = try self.allocator.alloc(Type, elements.len);
+    defer self.allocator.free(types);
+
+    var first: usize = 0;
+    for (types, elements, 0..) |*@"type", element, i| {
+        @"type".* = try self.checkNode(element);
+        if (literalOf(elements[first]) != null and literalOf(element) == null) first = i;
+    }
+
+    const element = types[first];
+    for (types, elements) |@"type", value| {
+        if (!try self.coerce(value, @"type", element)) {
+            try self.unit.createErrorFmt(value.span, "array elements must have the same type, expected {} but found {}", .{element, @"type"});
+        }
+    }
+
+    return try self.types.array(element, elements.len);
+}
+
+/// Indexing an array or slice by an integer gives one of its elements, by a range
+/// a slice of them. Indexes are checked against the length when evaluated
+pub fn visitIndex(self: *TypeChecker, node: *const Node, index: Node.Index) CheckError!Type {
+    const object = try self.known(index.object, try self.checkNode(index.object));
+    const position = try self.known(index.index, try self.checkNode(index.index));
+
+    const element: Type = switch (object) {
+        .array => |array| array.element.*,
+        .slice => |slice| slice.*,
+        .invalid => .invalid,
+        else => block: {
+            try self.unit.createErrorFmt(node.span, "values of type {} can't be indexed", .{object});
+            break :block .invalid;
+        }
+    };
+
+    switch (position) {
+        .range => return if (element == .invalid) .invalid else try self.types.slice(element),
+        .invalid => {},
+        else => if (!self.isInteger(position)) {
+            try self.unit.createErrorFmt(index.index.span, "array indexes must be integers, found {}", .{position});
+        }
+    }
+
+    return element;
+}
+
+pub fn visitTupleLiteral(self: *TypeChecker, _: *const Node, elements: []*Node) CheckError!Type {
+    const types = try self.allocator.alloc(Type, elements.len);
+    defer self.allocator.free(types);
+
+    for (types, elements) |*@"type", element| @"type".* = try self.checkNode(element);
+    return try self.types.tuple(types);
+}
+
+pub fn visitTupleIndex(self: *TypeChecker, node: *const Node, access: Node.TupleIndex) CheckError!Type {
+    const object = try self.known(access.object, try self.checkNode(access.object));
+
+    const elements = switch (object) {
+        .tuple => |elements| elements,
+        .invalid => return .invalid,
+        else => {
+            try self.unit.createErrorFmt(access.object.span, "values of type {} have no positional fields", .{object});
+            return .invalid;
+        }
+    };
+
+    if (access.index >= elements.len) {
+        try self.unit.createErrorFmt(node.span, "{} has no element {}, it has {}", .{object, access.index, elements.len});
+        return .invalid;
+    }
+
+    return elements[access.index];
+}
+
+/// Closures have function types, the types of the parameters and result they
+/// aren't annotated with are inferred from their uses
+pub fn visitClosure(self: *TypeChecker, node: *const Node, closure: Node.Closure) CheckError!Type {
+    // The captured bindings were found by the parser, the backends are given
+    // their types so they can copy their values into the closure
+    var captures = ArrayList(Type).init(self.allocator);
+    defer captures.deinit();
+
+    for (closure.captures) |name| {
+        const symbol = self.lookup(name) orelse {
+            try captures.append(.invalid);
+            continue;
+        };
+
+        try captures.append(self.inference.resolve(symbol.@"type" orelse .invalid));
+    }
+    try self.types.putCaptures(node.id, captures.items);
+
+    const outer_closure = self.closure_depth;
+    self.closure_depth = self.unit.symbols.depth();
+    defer self.closure_depth = outer_closure;
+
+    try self.unit.symbols.enterScope();
+    defer self.unit.symbols.exitScope();
+
+    var params = ArrayList(Type).init(self.allocator);
+    defer params.deinit();
+
+    for (closure.params) |param| {
+        const @"type" = if (param.annotation) |annotation| try self.resolveType(annotation)
+            else try self.inference.fresh(param.name, param.span, .any, param.span);
+
+        try params.append(@"type");
+        try self.define(param.span, param.name, .parameter, @"type");
+    }
+
+    const ret = if (closure.return_type) |return_type| try self.resolveType(return_type)
+        else try self.inference.fresh("the closure's result", node.span, .any, node.span);
+
+    const outer = self.return_type;
+    self.return_type = ret;
+    defer self.return_type = outer;
+
+    const outer_loops = self.loops;
+    self.loops = 0;
+    defer self.loops = outer_loops;
+
+    const outer_deferring = self.deferring;
+    self.deferring = false;
+    defer self.deferring = outer_deferring;
+
+    // Closures run when they are called, rather than as part of the future of the
+    // async function they are defined in
+    const outer_async = self.in_async;
+    self.in_async = false;
+    defer self.in_async = outer_async;
+
+    var labels = try Labels.find(self.unit, closure.body);
+    defer labels.deinit();
+
+    const outer_labels = self.labels;
+    self.labels = &labels;
+    defer self.labels = outer_labels;
+
+    const body = try self.checkNode(closure.body);
+    if (body == .invalid and ret == .variable) {
+        // Bodies which failed to check were reported already
+        try self.constrain(closure.body, ret.variable, .{ .@"type" = .invalid });
+    } else if (!try self.coerce(closure.body, body, ret)) {
+        try self.createError(closure.body.span, "closure body does not match its return type");
+    }
+
+    return try self.types.function(params.items, ret);
+}
+
+pub fn visitIdentifier(self: *TypeChecker, node: *const Node, name: []const u8) CheckError!Type {
+    const symbol = self.lookup(name) orelse {
+        try self.createError(node.span, "undefined name");
+        return .invalid;
+    };
+
+    if (symbol.kind == .@"type") {
+        try self.createError(node.span, "types can't be used as values");
+        return .invalid;
+    }
+
+    // The prelude's symbols span the root, which covers nothing
+    if (symbol.span.end > symbol.span.start) try self.types.putDefinition(node.id, symbol.span);
+
+    return symbol.@"type" orelse .invalid;
+}
+
+// Types are only written in annotations, which are resolved rather than visited
+pub fn visitTypeExpr(self: *TypeChecker, node: *const Node, _: Node.TypeExpr) CheckError!Type {
+    try self.createError(node.span, "types can't be used as values");
+    return .invalid;
+}
+
+pub fn visitLiteral(_: *TypeChecker, _: *const Node, literal: Node.Literal) CheckError!Type {
+    return switch (literal) {
+        .integer => .@"i64",
+        .float => .@"f64",
+        .boolean => .@"bool",
+        .string => .str,
+        .character => .char,
+        .unit => .unit
+    };
+}
+
+test "type checker modules" {
+    _ = tests;
+    _ = ConstEval;
+    _ = Labels;
+    _ = ExhaustivenessChecker;
+    _ = TypeTable;
+    _ = Inference;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    // The type of the binding named name
+    fn symbolType(unit: *Unit, name: []const u8) Type {
+        return unit.symbols.lookup(unit.interner.find(name).?).?.@"type".?;
+    }
+
+    test "binding and expression types" {
+        const source =
+            \\let x: i32 = 1 + 2
+            \\var s = "a" <> "b"
+            \\let y = x * 3 < 10
+            \\let z = 1.5
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        const types = result.types;
+
+        try testing.expectEqual(Type.@"i32", unit.symbols.lookup(unit.interner.find("x").?).?.@"type".?);
+        try testing.expectEqual(Type.str, unit.symbols.lookup(unit.interner.find("s").?).?.@"type".?);
+        try testing.expectEqual(Type.@"bool", unit.symbols.lookup(unit.interner.find("y").?).?.@"type".?);
+        try testing.expectEqual(Type.@"f64", unit.symbols.lookup(unit.interner.find("z").?).?.@"type".?);
+
+        // The module, then `let x: i32 = 1 + 2` and its children in creation order
+        try testing.expectEqual(Type.unit, types.get(0).?);
+        try testing.expectEqual(Type.@"i32", types.get(1).?);
+        try testing.expectEqual(Type.@"i32", types.get(2).?);
+        try testing.expectEqual(Type.@"i32", types.get(4).?);
+    }
+
+    test "if expressions" {
+        const source =
+            \\let x = if true { 1 } else if false { 2 } else { 3 }
+            \\let y = if true { 1 }
+            \\let z = if x > 1 { 1 } else { "one" }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(Type.@"i64", unit.symbols.lookup(unit.interner.find("x").?).?.@"type".?);
+        try testing.expectEqual(Type.unit, unit.symbols.lookup(unit.interner.find("y").?).?.@"type".?);
+
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("if and else branches have different types", unit.diagnostics.items[0].message);
+    }
+
+    test "match expressions" {
+        const source =
+            \\let n = 5
+            \\let a = match n { 0 => 1, m if m > 3 => m, _ => 2 }
+            \\let b = match n { 1 | 2 => "x", _ => 3 }
+            \\let c = match n { "one" => 1, Point { x } => 0, y | 2 => 0, _ => 0 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(Type.@"i64", unit.symbols.lookup(unit.interner.find("a").?).?.@"type".?);
+
+        try testing.expectEqual(5, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("match arms have different types", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("pattern does not match the type of the matched value", unit.diagnostics.items[1].message);
+        try testing.expectEqualStrings("struct patterns can't match values of type i64", unit.diagnostics.items[2].message);
+        try testing.expectEqualStrings("or patterns can't bind names", unit.diagnostics.items[3].message);
+        // y matches every value, so the wildcard after it can't be reached
+        try testing.expectEqual(.warning, unit.diagnostics.items[4].severity);
+    }
+
+    test "structs" {
+        const source =
+            \\struct Point { x: f64, y: f64 }
+            \\struct Line { start: Point, end: Point }
+            \\
+            \\impl Point {
+            \\    fn origin() -> Point { Point { x: 0.0, y: 0.0 } }
+            \\    fn norm(self: Point) -> f64 { self.x * self.x + self.y * self.y }
+            \\}
+            \\
+            \\let line = Line { start: Point.origin(), end: Point { x: 1.0, y: 2.0 } }
+            \\let y = line.end.y
+            \\let n = line.end.norm()
+            \\let x = match line.start { Point { x: 0.0, y } => y, _ => 1.0 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [16]u8 = undefined;
+        const line = unit.symbols.lookup(unit.interner.find("line").?).?.@"type".?;
+        try testing.expectEqualStrings("Line", try std.fmt.bufPrint(&buf, "{}", .{line}));
+
+        try testing.expectEqual(Type.@"f64", unit.symbols.lookup(unit.interner.find("y").?).?.@"type".?);
+        try testing.expectEqual(Type.@"f64", unit.symbols.lookup(unit.interner.find("n").?).?.@"type".?);
+        try testing.expectEqual(Type.@"f64", unit.symbols.lookup(unit.interner.find("x").?).?.@"type".?);
+    }
+
+    test "struct literals and field accesses are checked against the struct" {
+        const source =
+            \\struct Point { x: f64, y: f64 }
+            \\impl Point { fn origin() -> Point { Point { x: 0.0, y: 0.0 } } }
+            \\let a = Point { x: 1.0 }
+            \\let b = Point { x: 1.0, y: 2.0, z: 3.0 }
+            \\let c = Point { x: 1.0, x: 2.0, y: 1.0 }
+            \\let d = Point { x: "one", y: 2.0 }
+            \\let e = b.z
+            \\let f = a.x.y
+            \\let g = Shape { x: 1.0 }
+            \\let h = Point
+            \\let i = a.origin()
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "Point literal is missing field y",
+            "Point has no field named z",
+            "field x is given more than once",
+            "value does not match the type of field x",
+            "Point has no field or method named z",
+            "values of type f64 have no fields",
+            "Shape is not a struct",
+            "types can't be used as values",
+            "Point.origin is not a method, its first parameter isn't a Point or a &Point"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "methods take self by value or by reference" {
+        const source =
+            \\struct Point { x: f64, y: f64 }
+            \\impl Point {
+            \\    fn translate(self, dx: f64, dy: f64) -> Point { Point { x: self.x + dx, y: self.y + dy } }
+            \\}
+            \\impl Point {
+            \\    fn scale(self, k: f64) -> Point { Point { x: self.x * k, y: self.y * k } }
+            \\    fn length_squared(&self) -> f64 { self.x * self.x + self.y * self.y }
+            \\    fn doubled(&self) -> f64 { self.length_squared() * 2.0 }
+            \\}
+            \\fn translate(x: f64) -> f64 { x + 1.0 }
+            \\var p = Point { x: 1.0, y: 2.0 }
+            \\let moved = p.translate(1.0, 2.0).scale(2.0)
+            \\let squared = p.length_squared()
+            \\let direct = Point.length_squared(&p)
+            \\let shifted = translate(1.0)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [16]u8 = undefined;
+        try testing.expectEqualStrings("Point", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "moved")}));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "squared"));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "direct"));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "shifted"));
+    }
+
+    test "methods taking references are called through places" {
+        const source =
+            \\struct Point { x: f64 }
+            \\impl Point { fn get(&self) -> f64 { self.x } }
+            \\impl Point { fn get(self) -> f64 { self.x } }
+            \\let p = Point { x: 1.0 }
+            \\let a = p.get()
+            \\let b = (Point { x: 2.0 }).get()
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "name is already defined in this scope",
+            "p is immutable, only var bindings can be referenced",
+            "only bindings, fields and elements can be referenced"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "traits" {
+        const source =
+            \\trait Printable {
+            \\    fn name(self) -> str
+            \\    fn description(&self) -> str { self.name() }
+            \\}
+            \\struct Point { x: f64 }
+            \\impl Printable for Point {
+            \\    fn name(self) -> str { "point" }
+            \\}
+            \\fn show(x: impl Printable) -> str { x.name() }
+            \\fn label(x: &impl Printable) -> str { x.description() }
+            \\var p = Point { x: 1.0 }
+            \\let shown = show(p)
+            \\let labelled = label(&p)
+            \\let described = p.description()
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        try testing.expectEqual(Type.str, symbolType(unit, "shown"));
+        try testing.expectEqual(Type.str, symbolType(unit, "labelled"));
+        try testing.expectEqual(Type.str, symbolType(unit, "described"));
+    }
+
+    test "async functions return futures" {
+        const source =
+            \\async fn add(a: i32, b: i32) -> i32 { a + b }
+            \\async fn twice(a: i32) -> i32 { await add(a, a) * 2 }
+            \\let pending: Future<i32> = twice(1)
+            \\let sum = block_on(add(1, 2))
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        const pending = symbolType(unit, "pending");
+        try testing.expect(pending == .future and pending.future.* == .@"i32");
+        try testing.expectEqual(Type.@"i32", symbolType(unit, "sum"));
+    }
+
+    test "awaits outside async functions and of values other than futures are reported" {
+        const source =
+            \\async fn one() -> i32 { 1 }
+            \\fn f() -> i32 { await one() }
+            \\async fn g() -> i32 { await 1 }
+            \\async fn h() -> i32 { let c = fn() -> i32 { await one() }; c() }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "await can only be used within async functions",
+            "only futures can be awaited, found i64",
+            "await can only be used within async functions"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "impls of traits define each method the trait requires" {
+        const source =
+            \\trait Shape {
+            \\    fn area(self) -> f64
+            \\    fn perimeter(self) -> f64
+            \\    fn name(self) -> str { "shape" }
+            \\}
+            \\struct Square { side: f64 }
+            \\struct Circle { r: f64 }
+            \\impl Shape for Square {
+            \\    fn area(self) -> i64 { 1 }
+            \\    fn sides(self) -> i64 { 4 }
+            \\}
+            \\fn total(s: impl Shape) -> f64 { s.area() }
+            \\fn bad(s: Shape) {}
+            \\let t = total(Circle { r: 1.0 })
+            \\let n = (Square { side: 1.0 }).name()
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "Square.area doesn't match the signature of Shape.area, expected fn(Square) -> f64 but found fn(Square) -> i64",
+            "sides is not a method of Shape",
+            "Square doesn't implement Shape, it is missing perimeter",
+            "Shape is a trait, values of the types implementing it are of type impl Shape",
+            "argument does not match the parameter's type"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "enums" {
+        const source =
+            \\enum Shape { Circle(f64), Rect(f64, f64), Empty }
+            \\enum Event { KeyPress { key: i64 }, Quit }
+            \\
+            \\fn area(shape: Shape) -> f64 {
+            \\    match shape {
+            \\        Shape::Circle(r) => 3.0 * r * r
+            \\        Shape::Rect(w, h) => w * h
+            \\        Shape::Empty => 0.0
+            \\    }
+            \\}
+            \\
+            \\let a = area(Shape::Rect(2.0, 3.0))
+            \\let e = Event::KeyPress { key: 65 }
+            \\let k = match e { Event::KeyPress { key } => key, Event::Quit => 0 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [16]u8 = undefined;
+        const e = unit.symbols.lookup(unit.interner.find("e").?).?.@"type".?;
+        try testing.expectEqualStrings("Event", try std.fmt.bufPrint(&buf, "{}", .{e}));
+
+        try testing.expectEqual(Type.@"f64", unit.symbols.lookup(unit.interner.find("a").?).?.@"type".?);
+        try testing.expectEqual(Type.@"i64", unit.symbols.lookup(unit.interner.find("k").?).?.@"type".?);
+    }
+
+    test "variant literals and patterns are checked against the enum" {
+        const source =
+            \\enum Shape { Circle(f64), Rect(f64, f64), Empty }
+            \\enum Event { KeyPress { key: i64 } }
+            \\let a = Shape::Square(1.0)
+            \\let b = Shape::Rect(1.0)
+            \\let c = Shape::Circle("one")
+            \\let d = Shape::Empty(1.0)
+            \\let e = Event::KeyPress { code: 1 }
+            \\let f = Color::Red
+            \\let s = Shape::Empty
+            \\let g = match s { Shape::Circle(r) => r, Shape::Rect(w, h) => w }
+            \\let h = match s { Shape::Circle(r) if r > 1.0 => r, Shape::Rect(w, 0.0) => w, Shape::Empty => 0.0 }
+            \\let i = match s { Event::KeyPress { key } => key, _ => 0 }
+            \\let j = match s { Shape::Circle => 0.0, Shape::Rect(w, _) => w, _ => 0.0 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "Shape has no variant named Square",
+            "Shape::Rect holds 2 value(s), found 1",
+            "value does not match the type held by Shape::Circle",
+            "Shape::Empty is a variant without fields",
+            "Event::KeyPress has no field named code",
+            "Event::KeyPress literal is missing field key",
+            "Color is not an enum",
+            "pattern does not match the type of the matched value",
+            "pattern does not match the fields of Shape::Circle",
+            "match does not cover Shape::Empty",
+            "match does not cover Shape::Circle, Shape::Rect"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "function types" {
+        const source =
+            \\fn twice(f: fn(i64) -> i64, x: i64) -> i64 { f(f(x)) }
+            \\fn inc(x: i64) -> i64 { x + 1 }
+            \\
+            \\let y = twice(inc, 1)
+            \\let g: fn(i64) -> i64 = inc
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [32]u8 = undefined;
+        const g = unit.symbols.lookup(unit.interner.find("g").?).?.@"type".?;
+        try testing.expectEqualStrings("fn(i64) -> i64", try std.fmt.bufPrint(&buf, "{}", .{g}));
+
+        try testing.expectEqual(Type.@"i64", unit.symbols.lookup(unit.interner.find("y").?).?.@"type".?);
+    }
+
+    test "closures" {
+        const source =
+            \\fn twice(f: fn(i64) -> i64, x: i64) -> i64 { f(f(x)) }
+            \\fn adder(n: i64) -> i64 {
+            \\    let add = fn(x) { x + n }
+            \\    twice(add, 1)
+            \\}
+            \\
+            \\let square = fn(x) { x * 2.0 }
+            \\let negate: fn(bool) -> bool = fn(b) { !b }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [32]u8 = undefined;
+        try testing.expectEqualStrings("fn(f64) -> f64", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "square")}));
+        try testing.expectEqualStrings("fn(bool) -> bool", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "negate")}));
+    }
+
+    test "closures can't assign to the bindings they capture" {
+        const source =
+            \\fn f() {
+            \\    var n = 1
+            \\    let set = fn() { n = 2 }
+            \\    let get = fn() -> i64 { n }
+            \\    n = 3
+            \\    let unused = fn(x) { 1 }
+            \\}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("cannot assign to a captured binding, closures hold a copy of its value", unit.diagnostics.items[0].message);
+        try testing.expectEqual(3, unit.diagnostics.items[0].span.pos.line);
+        try testing.expectEqualStrings("the type of x can't be inferred", unit.diagnostics.items[1].message);
+    }
+
+    test "functions are values" {
+        const source =
+            \\fn double(x: i32) -> i32 { x * 2 }
+            \\fn sum(n: i32, f: fn(i32) -> i32) -> i32 {
+            \\    var total: i32 = 0
+            \\    for i in 0..n { total = total + f(i) }
+            \\    total
+            \\}
+            \\
+            \\let f: fn(i32) -> i32 = double
+            \\let a = sum(3, f)
+            \\let b = sum(3, fn(x) { x + 1 })
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [32]u8 = undefined;
+        try testing.expectEqual(0, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("i32", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "b")}));
+    }
+
+    test "functions of the wrong type are reported" {
+        const source =
+            \\fn invert(b: bool) -> bool { !b }
+            \\fn apply(f: fn(i32) -> i32, x: i32) -> i32 { f(x) }
+            \\
+            \\let a = apply(invert, 1)
+            \\let g: fn(i32) -> i32 = invert
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("argument does not match the parameter's type", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("value does not match the binding's type", unit.diagnostics.items[1].message);
+    }
+
+    test "? propagates the errors of Result values" {
+        const source =
+            \\fn parse(n: i64) -> Result<i64, str> { if n < 0 { Err("negative") } else { Ok(n) } }
+            \\fn double(n: i64) -> Result<i64, str> { Ok(parse(n)? * 2) }
+            \\fn quad(n: i64) -> Result<i64, str> { Ok(double(n)? * 2) }
+            \\
+            \\let a = quad(3)
+            \\let b = match a { Ok(v) => v, Err(_) => 0 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [32]u8 = undefined;
+        try testing.expectEqual(0, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("Result<i64, str>", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "a")}));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "b"));
+    }
+
+    test "? outside of functions returning a matching Result is reported" {
+        const source =
+            \\fn parse(n: i64) -> Result<i64, str> { Ok(n) }
+            \\fn f(n: i64) -> i64 { parse(n)? }
+            \\fn g(n: i64) -> Result<i64, bool> { Ok(parse(n)?) }
+            \\fn h(n: i64) -> Result<i64, str> { Ok(n?) }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("? can only be used in functions returning Result", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("the value's error type str doesn't match the function's, bool", unit.diagnostics.items[1].message);
+        try testing.expectEqualStrings("? can only be applied to Result or Option values, found i64", unit.diagnostics.items[2].message);
+    }
+
+    test "option values and their methods" {
+        const source =
+            \\fn half(n: i64) -> Option<i64> { if n % 2 == 0 { Some(n / 2) } else { None } }
+            \\fn eighth(n: i64) -> Option<i64> { Some(half(half(half(n)?)?)?) }
+            \\
+            \\let x: Option<i32> = None
+            \\let a = eighth(16).unwrap_or(0)
+            \\let b = half(4).map(fn(n) { n > 1 })
+            \\let c = half(4).and_then(half).is_some()
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [32]u8 = undefined;
+        try testing.expectEqual(0, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("Option<i32>", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "x")}));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "a"));
+        try testing.expectEqualStrings("Option<bool>", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "b")}));
+        try testing.expectEqual(Type.@"bool", symbolType(unit, "c"));
+    }
+
+    test "misused option values are reported" {
+        const source =
+            \\fn first(o: Option<i64>) -> i64 { o? }
+            \\let y: Option<i64> = Some(true)
+            \\let z = y.unwrap()
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("? can only be used in functions returning Option", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("value does not match the binding's type", unit.diagnostics.items[1].message);
+        try testing.expectEqualStrings("Option has no method named unwrap", unit.diagnostics.items[2].message);
+    }
+
+    test "references and dereferences" {
+        const source =
+            \\struct Point { x: i64, y: f64 }
+            \\fn bump(r: &i64) { *r = *r + 1 }
+            \\var n: i64 = 1
+            \\var p = Point { x: 1, y: 2.5 }
+            \\var t: (i64, [bool; 2]) = (1, [true, false])
+            \\let a = &n
+            \\let b = &p.y
+            \\let c: &bool = &t.1[0]
+            \\let d = *a + 1
+            \\bump(&p.x)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [32]u8 = undefined;
+        try testing.expectEqualStrings("&i64 &f64 &bool", try std.fmt.bufPrint(&buf, "{} {} {}", .{symbolType(unit, "a"), symbolType(unit, "b"), symbolType(unit, "c")}));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "d"));
+        try testing.expectEqual(0, unit.diagnostics.items.len);
+    }
+
+    test "invalid references and dereferences are reported" {
+        const source =
+            \\let x = 1
+            \\var y = 2
+            \\let a = &x
+            \\let b = &(y + 1)
+            \\let c = *y
+            \\let d: &i64 = 1
+            \\let e: fn() = 1
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "x is immutable, only var bindings can be referenced",
+            "only bindings, fields and elements can be referenced",
+            "values of type i64 can't be dereferenced",
+            "value does not match the binding's type",
+            "value does not match the binding's type"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "references outliving their bindings are warned about" {
+        const source =
+            \\var global: i64 = 0
+            \\var r = &global
+            \\fn leak() -> &i64 {
+            \\    var local: i64 = 1
+            \\    &local
+            \\}
+            \\fn leak_early(early: bool) -> &i64 {
+            \\    var local: i64 = 2
+            \\    if early { return &local }
+            \\    &global
+            \\}
+            \\fn keep() {
+            \\    var inner: i64 = 3
+            \\    r = &inner
+            \\    var also = &inner
+            \\}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        const expected = [_][]const u8{
+            "reference to local may outlive it, as local goes out of scope first",
+            "reference to local may outlive it, as local goes out of scope first",
+            "reference to inner may outlive it, as inner goes out of scope first"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+            try testing.expectEqual(.dangling_reference, diagnostic.lint.?);
+        }
+    }
+
+    test "sizes of types" {
+        const source =
+            \\struct Point { x: i32, y: i64 }
+            \\struct Pixel { on: bool, color: (i8, i16) }
+            \\union Value { i: i64, bytes: [i8; 12], pair: (i8, i16) }
+            \\let a = sizeof(i64) == 8
+            \\let p = sizeof(Point)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        try testing.expectEqual(Type.@"bool", symbolType(unit, "a"));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "p"));
+
+        // Fields are aligned to their size, and the struct to its largest field
+        try testing.expectEqual(Type.Layout{ .size = 16, .alignment = 8 }, symbolType(unit, "Point").layout(8).?);
+        try testing.expectEqual(Type.Layout{ .size = 6, .alignment = 2 }, symbolType(unit, "Pixel").layout(8).?);
+        // Unions are as large as their largest field, padded to their most aligned
+        try testing.expectEqual(Type.Layout{ .size = 16, .alignment = 8 }, symbolType(unit, "Value").layout(8).?);
+
+        const padded: Type = .{ .tuple = &.{.@"i8", .@"i32", .@"i8"} };
+        try testing.expectEqual(Type.Layout{ .size = 12, .alignment = 4 }, padded.layout(4).?);
+        try testing.expectEqual(Type.Layout{ .size = 4, .alignment = 4 }, Type.str.layout(4).?);
+    }
+
+    test "union fields are only accessed within unsafe blocks" {
+        const source =
+            \\union Bits { i: i32, f: f32 }
+            \\let b = Bits { f: 1.0 }
+            \\let i = unsafe { b.i }
+            \\let f = b.f
+            \\let none = Bits {}
+            \\let both = Bits { i: 1, f: 2.0 }
+            \\let wrong = Bits { i: true }
+            \\let matched = match b { Bits { i } => i }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "the fields of union Bits can only be accessed within unsafe blocks",
+            "Bits literals give exactly one field, unions hold one at a time",
+            "Bits literals give exactly one field, unions hold one at a time",
+            "value does not match the type of field i",
+            "the fields of union Bits can only be accessed within unsafe blocks"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+        try testing.expectEqual(Type.@"i32", symbolType(unit, "i"));
+    }
+
+    test "types without a fixed size can't be sized" {
+        const source =
+            \\enum E { A, B }
+            \\let e = sizeof(E)
+            \\let u = sizeof(())
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "values of type E have no fixed size",
+            "values of type () have no fixed size"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "numeric casts" {
+        const source =
+            \\let big: i64 = 300
+            \\let wide = 7 as i64
+            \\let narrow = big as i8
+            \\let small = -128 as i8
+            \\let whole = 2.75 as i32
+            \\let real = big as f64
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "wide"));
+        try testing.expectEqual(Type.@"i8", symbolType(unit, "narrow"));
+        try testing.expectEqual(Type.@"i8", symbolType(unit, "small"));
+        try testing.expectEqual(Type.@"i32", symbolType(unit, "whole"));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "real"));
+
+        // Only the narrowing cast of a value which may not fit warns
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("casting i64 to i8 may truncate it", unit.diagnostics.items[0].message);
+        try testing.expectEqual(.truncation, unit.diagnostics.items[0].lint.?);
+    }
+
+    test "casts between other types" {
+        const source =
+            \\var x: i64 = 1
+            \\let p = &x as &i8
+            \\let q = unsafe { &x as &i8 }
+            \\let s = "1" as i64
+            \\let b = true as i8
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "pointer casts can only be written within unsafe blocks",
+            "values of type str can't be cast to i64",
+            "values of type bool can't be cast to i8"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "extern functions are called within unsafe blocks" {
+        const source =
+            \\extern fn printf(fmt: str, ...) -> i32
+            \\extern fn malloc(size: i64) -> &i8
+            \\extern fn free(ptr: &i8)
+            \\fn main() {
+            \\    let written = unsafe {
+            \\        let p = malloc(8)
+            \\        free(p)
+            \\        printf("%d %s\\n", 1, "two")
+            \\    }
+            \\}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buf: [64]u8 = undefined;
+        try testing.expectEqualStrings("fn(str, ...) -> i32", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "printf")}));
+        try testing.expectEqualStrings("fn(&i8) -> ()", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "free")}));
+    }
+
+    test "invalid extern function calls are reported" {
+        const source =
+            \\extern fn puts(s: str) -> i32
+            \\extern fn printf(fmt: str, ...) -> i32
+            \\let a = puts("outside")
+            \\let b = "piped" |> puts
+            \\let c = unsafe { printf() }
+            \\let d: fn(..., i64) = puts
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "extern functions can only be called within unsafe blocks",
+            "extern functions can only be called within unsafe blocks",
+            "wrong number of arguments",
+            "... can only be the last parameter of a function type",
+            "value does not match the binding's type"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "comptime blocks are replaced by their values" {
+        const source =
+            \\let ptr_size = comptime { sizeof(i64) }
+            \\let library = comptime {
+            \\    if TARGET_OS == "linux" { "libc.so.6" } else { "msvcrt.dll" }
+            \\}
+            \\let small: i8 = comptime { 1 + 2 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var options = Unit.UnitOptions.testing(input.reader().any(), std.io.null_writer.any());
+        options.options.target = "x86_64-linux-gnu";
+
+        var unit = try Unit.init(options);
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        const items = result.ast.root.kind.module.items;
+        const expected = [_]Node.Literal{ .{ .integer = 8 }, .{ .string = "libc.so.6" }, .{ .integer = 3 } };
+        for (expected, items) |literal, item| {
+            const body = item.kind.var_decl.value.?.kind.@"comptime";
+            try testing.expectEqualDeep(literal, body.kind.literal);
+        }
+
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "ptr_size"));
+        try testing.expectEqual(Type.str, symbolType(unit, "library"));
+        try testing.expectEqual(Type.@"i8", symbolType(unit, "small"));
+    }
+
+    test "comptime blocks can't perform io or call extern functions" {
+        const source =
+            \\extern fn getpid() -> i32
+            \\fn two() -> i64 { 2 }
+            \\let a = comptime { io.println("hello") }
+            \\let b = comptime { unsafe { getpid() } }
+            \\let c = comptime { two() }
+            \\let d = TARGET_BITS
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        // The target is only known within comptime blocks
+        const expected = [_][]const u8{
+            "comptime blocks can't perform io",
+            "comptime blocks can't call extern functions",
+            "comptime blocks must be evaluable at compile time",
+            "undefined name"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "generic functions and structs" {
+        const source =
+            \\struct Pair<A, B> { first: A, second: B }
+            \\fn identity<T>(x: T) -> T { x }
+            \\fn swap<T>(p: Pair<T, T>) -> Pair<T, T> { Pair { first: p.second, second: p.first } }
+            \\fn repeat<T>(x: T, n: i64) -> T { if n <= 1 { x } else { repeat(x, n - 1) } }
+            \\
+            \\let a = identity(1)
+            \\let b = identity("b")
+            \\let p = Pair { first: 1, second: true }
+            \\let c = p.second
+            \\let s = swap(Pair { first: 1.5, second: 2.5 }).first
+            \\let r = repeat('r', 3)
+            \\let q: Pair<i64, bool> = p
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "a"));
+        try testing.expectEqual(Type.str, symbolType(unit, "b"));
+        try testing.expectEqual(Type.@"bool", symbolType(unit, "c"));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "s"));
+        try testing.expectEqual(Type.char, symbolType(unit, "r"));
+
+        var buf: [32]u8 = undefined;
+        try testing.expectEqualStrings("Pair<i64, bool>", try std.fmt.bufPrint(&buf, "{}", .{symbolType(unit, "p")}));
+        try testing.expect(symbolType(unit, "p").eql(symbolType(unit, "q")));
+
+        // Each call to a generic function is instantiated, including the recursive one
+        try testing.expectEqual(5, result.types.instantiations.count());
+    }
+
+    test "type parameters must be inferred consistently" {
+        const source =
+            \\fn same<T>(a: T, b: T) -> T { a }
+            \\fn make<T>() -> i64 { 1 }
+            \\struct Box<T> { value: T }
+            \\
+            \\let i = 1
+            \\let t = true
+            \\let x = same(i, t)
+            \\let y = make()
+            \\let z: Box = Box { value: 1 }
+            \\let w: Box<i64, i64> = Box { value: 1 }
+            \\let v: i64<bool> = 1
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(5, unit.diagnostics.items.len);
+        try testing.expectEqualStrings(
+            "type parameter T is inferred as both i64 and bool",
+            unit.diagnostics.items[0].message
+        );
+        try testing.expectEqualStrings(
+            "the type of type parameter T can't be inferred",
+            unit.diagnostics.items[1].message
+        );
+        try testing.expectEqualStrings("Box takes 1 type argument(s), found 0", unit.diagnostics.items[2].message);
+        try testing.expectEqualStrings("Box takes 1 type argument(s), found 2", unit.diagnostics.items[3].message);
+        try testing.expectEqualStrings("i64 isn't generic", unit.diagnostics.items[4].message);
+    }
+
+    test "bindings without annotations are inferred from their uses" {
+        const source =
+            \\fn half(x: i32) -> i32 { x / 2 }
+            \\fn pick(c: bool) -> i32 {
+            \\    let n = 10
+            \\    let m = if c { n } else { 5 }
+            \\    half(m)
+            \\}
+            \\
+            \\var count
+            \\count = 1
+            \\let total = count + 2
+            \\let byte = 7
+            \\let b: i8 = byte
+            \\var ratio
+            \\ratio = 0.5
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        // Bindings only known to be numeric take the default type of their literals
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "count"));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "total"));
+        try testing.expectEqual(Type.@"i8", symbolType(unit, "byte"));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "ratio"));
+
+        // The literals bound to n and m are retyped as the parameter they're passed to
+        const pick = result.ast.root.kind.module.items[1].kind.fn_def;
+        const statements = pick.body.kind.block.statements;
+        const n = statements[0].kind.var_decl.value.?;
+        const m = statements[1].kind.var_decl.value.?;
+        try testing.expectEqual(Type.@"i32", result.types.get(n.id).?);
+        try testing.expectEqual(Type.@"i32", result.types.get(m.id).?);
+        try testing.expectEqual(Type.@"i32", result.types.get(m.kind.@"if".else_block.?.id).?);
+    }
+
+    test "conflicting uses of inferred bindings are reported" {
+        const source =
+            \\fn wide(x: i64) -> i64 { x }
+            \\fn flag(b: bool) -> bool { b }
+            \\fn g() {
+            \\    let n = 1
+            \\    wide(n)
+            \\    flag(n)
+            \\    var unknown
+            \\}
+            \\let r = 2.5
+            \\let i: i32 = r
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+
+        // Each conflict is reported at the use, with a note at where the type was inferred
+        const conflict = unit.diagnostics.items[0];
+        try testing.expectEqualStrings("n is inferred as both i64 and bool", conflict.message);
+        try testing.expectEqual(6, conflict.span.pos.line);
+        try testing.expectEqual(1, conflict.children.len);
+        try testing.expectEqualStrings("n is inferred as i64 here", conflict.children[0].message);
+        try testing.expectEqual(5, conflict.children[0].span.pos.line);
+
+        try testing.expectEqualStrings("the type of unknown can't be inferred", unit.diagnostics.items[1].message);
+
+        const literal = unit.diagnostics.items[2];
+        try testing.expectEqualStrings("r is inferred as both a float and i32", literal.message);
+        try testing.expectEqualStrings("r is inferred as a float here", literal.children[0].message);
+        try testing.expectEqual(9, literal.children[0].span.pos.line);
+    }
+
+    test "defers can't leave their block or refer to later bindings" {
+        const source =
+            \\fn f(n: i64) -> i64 {
+            \\    defer later
+            \\    let later = 1
+            \\    for i in 0..n {
+            \\        defer if i > 1 { continue }
+            \\        defer for j in 0..i { break }
+            \\    }
+            \\    defer { return 1 }
+            \\    n
+            \\}
+            \\defer f(1)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "undefined name",
+            "defers can't continue the loops enclosing them",
+            "defers can't return from the function enclosing them",
+            "defer outside of a function"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "gotos jump to the labels of their function within unsafe blocks" {
+        const source =
+            \\fn a() {
+            \\    unsafe {
+            \\        goto done
+            \\        let x = 1
+            \\        done:
+            \\    }
+            \\}
+            \\fn b() { goto nowhere }
+            \\#[allow(goto)]
+            \\fn c() {
+            \\    top:
+            \\    goto top
+            \\}
+            \\fn d() {
+            \\    twice:
+            \\    twice:
+            \\}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "goto done jumps over the initialization of x",
+            "gotos can only be written within unsafe blocks, or where #[allow(goto)] applies",
+            "goto target nowhere isn't a label within this function",
+            "label twice is defined more than once in this function"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+        try testing.expectEqual(.warning, unit.diagnostics.items[0].severity);
+    }
+
+    test "break and continue are only valid within loops" {
+        const source =
+            \\while true { break }
+            \\for i in 0..10 { if i > 5 { continue } }
+            \\break
+            \\while true { fn f() { continue } }
+            \\while true { break 1 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("break outside of a loop", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("continue outside of a loop", unit.diagnostics.items[1].message);
+        try testing.expectEqualStrings(
+            "break value does not match the loop's type, loops evaluate to ()",
+            unit.diagnostics.items[2].message
+        );
+    }
+
+    test "fmt templates are checked against their arguments" {
+        const source =
+            \\let a: str = fmt("{} and {:x} and {:.1}", true, 255, 1.5)
+            \\let b = fmt("{} {}", 1)
+            \\let c = fmt("{:x}", 1.5)
+            \\let d = fmt("{:.2}", "s")
+            \\let t = "{}"
+            \\let e = fmt(t, 1)
+            \\let f = fmt("{:y}")
+            \\let g = fmt("{}", 0..3)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "fmt's template has 2 placeholder(s) but 1 argument(s) were given",
+            "{:x} formats integers, found f64",
+            "{:.N} formats floats, found str",
+            "fmt's template must be a string literal",
+            "unknown placeholder in template, expected {}, {:x} or {:.N}",
+            "values of type range(i64) can't be formatted"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "arrays and their indexes" {
+        const source =
+            \\let a = [1, 2, 3]
+            \\let b: [i8; 2] = [4, 5]
+            \\let c = a[1]
+            \\let d = a[0..2]
+            \\let e: [f64; 0] = []
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buffer: [16]u8 = undefined;
+        try testing.expectEqualStrings("[i64; 3]", try std.fmt.bufPrint(&buffer, "{}", .{symbolType(unit, "a")}));
+        try testing.expectEqualStrings("[i8; 2]", try std.fmt.bufPrint(&buffer, "{}", .{symbolType(unit, "b")}));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "c"));
+        try testing.expectEqualStrings("[i64]", try std.fmt.bufPrint(&buffer, "{}", .{symbolType(unit, "d")}));
+        try testing.expectEqualStrings("[f64; 0]", try std.fmt.bufPrint(&buffer, "{}", .{symbolType(unit, "e")}));
+    }
+
+    test "tuples and their elements" {
+        const source =
+            \\let t = (1, ("a", true))
+            \\let a = t.0
+            \\let b = t.1.1
+            \\let c: (i8, f64) = (1, 2.5)
+            \\let d = (42,)
+            \\let e = match t { (n, (_, flag)) => if flag { n } else { 0 } }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        var buffer: [32]u8 = undefined;
+        try testing.expectEqualStrings("(i64, (str, bool))", try std.fmt.bufPrint(&buffer, "{}", .{symbolType(unit, "t")}));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "a"));
+        try testing.expectEqual(Type.@"bool", symbolType(unit, "b"));
+        try testing.expectEqualStrings("(i8, f64)", try std.fmt.bufPrint(&buffer, "{}", .{symbolType(unit, "c")}));
+        try testing.expectEqualStrings("(i64,)", try std.fmt.bufPrint(&buffer, "{}", .{symbolType(unit, "d")}));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "e"));
+    }
+
+    test "invalid tuple positions and patterns are reported" {
+        const source =
+            \\let t = (1, "a")
+            \\let a = t.2
+            \\let b = 1.0
+            \\let c = b.0
+            \\let d = match t { (x, y, z) => 1 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "(i64, str) has no element 2, it has 2",
+            "values of type f64 have no positional fields",
+            "pattern has 3 elements but the tuple has 2"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "destructuring bindings" {
+        const source =
+            \\struct Point { x: i64, y: f64 }
+            \\let p = Point { x: 1, y: 2.5 }
+            \\let Point { x: px, y: py } = p
+            \\let Point { x, y } = p
+            \\let (a, (b, _)) = (true, ("s", 3))
+            \\let (c, d): (i8, f64) = (1, 2.5)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "px"));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "py"));
+        try testing.expectEqual(Type.@"i64", symbolType(unit, "x"));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "y"));
+        try testing.expectEqual(Type.@"bool", symbolType(unit, "a"));
+        try testing.expectEqual(Type.str, symbolType(unit, "b"));
+        try testing.expectEqual(Type.@"i8", symbolType(unit, "c"));
+        try testing.expectEqual(Type.@"f64", symbolType(unit, "d"));
+    }
+
+    test "refutable and mismatched destructuring patterns are reported" {
+        const source =
+            \\let o = Some(1)
+            \\let Some(v) = o
+            \\let (a, b) = (1, 2, 3)
+            \\let (c, true) = (1, false)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "let bindings can't use refutable patterns, use match instead",
+            "pattern has 2 elements but the tuple has 3",
+            "let bindings can't use refutable patterns, use match instead"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "arrays of mixed types and invalid indexes are reported" {
+        const source =
+            \\let a = [1, true, 3]
+            \\let b = [1, 2][true]
+            \\let c = 1[0]
+            \\let d: [i64; 2] = [1, 2, 3]
+            \\let e: [i64; n] = [1]
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        const expected = [_][]const u8{
+            "array elements must have the same type, expected i64 but found bool",
+            "array indexes must be integers, found bool",
+            "values of type i64 can't be indexed",
+            "value does not match the binding's type",
+            "array lengths must be non-negative integer literals"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+
+    test "type errors are accumulated" {
+        const source =
+            \\let x: bool = 1
+            \\let y = true + 1
+            \\let z: thing = w
+            \\let x = 1
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(5, unit.diagnostics.items.len);
+        // Rebinding x is only warned about by the parser
+        try testing.expectEqual(.warning, unit.diagnostics.items[0].severity);
+        try testing.expectEqualStrings("value does not match the binding's type", unit.diagnostics.items[1].message);
+        try testing.expectEqualStrings("mismatched operand types", unit.diagnostics.items[2].message);
+        try testing.expectEqualStrings("unknown type", unit.diagnostics.items[3].message);
+        try testing.expectEqualStrings("undefined name", unit.diagnostics.items[4].message);
+    }
+
+    test "bindings rebind names in the same scope" {
+        const source =
+            \\let x = 1
+            \\let x = "one"
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqual(.warning, unit.diagnostics.items[0].severity);
+        try testing.expectEqual(Type.str, unit.symbols.lookup(unit.interner.find("x").?).?.@"type".?);
+    }
+};
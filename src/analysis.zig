@@ -0,0 +1,97 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Analyses of how control and values flow through the bodies of functions, built
+//! once and shared by the passes using them
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const AutoArrayHashMap = std.AutoArrayHashMap;
+
+const ruka = @import("prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+pub const ControlFlowGraph = @import("analysis/ControlFlowGraph.zig");
+pub const DeadAssignmentChecker = @import("analysis/DeadAssignmentChecker.zig");
+pub const InitializationChecker = @import("analysis/InitializationChecker.zig");
+pub const Liveness = @import("analysis/Liveness.zig");
+pub const ReachingDefinitions = @import("analysis/ReachingDefinitions.zig");
+
+/// Runs the analyses of the bodies of functions over a checked ast, recording
+/// their diagnostics in unit. The graphs of the functions are built once, and
+/// shared by each analysis. Dead assignments are only warned about in optimized
+/// builds
+pub fn analyze(unit: *Unit, ast: *const Ast) !void {
+    const error_count = unit.errorCount();
+
+    var graphs = try Graphs.build(unit.allocator, ast);
+    defer graphs.deinit();
+
+    var initialization = try InitializationChecker.init(unit);
+    defer initialization.deinit();
+
+    try initialization.check(&graphs);
+
+    if (unit.options.optimization_level != .debug) {
+        var dead_assignments = try DeadAssignmentChecker.init(unit);
+        defer dead_assignments.deinit();
+
+        try dead_assignments.check(&graphs);
+    }
+
+    if (unit.errorCount() > error_count) return error.AnalysisFailed;
+}
+
+/// The graphs of every function and closure in an ast, keyed by the ids of their
+/// nodes and in the order they appear
+pub const Graphs = struct {
+    map: AutoArrayHashMap(Node.Id, ControlFlowGraph),
+
+    const Walk = Visitor(Graphs, Allocator.Error, void);
+
+    pub fn build(allocator: Allocator, ast: *const Ast) !Graphs {
+        var graphs = Graphs{ .map = .init(allocator) };
+        errdefer graphs.deinit();
+
+        try Walk.visit(&graphs, ast.root);
+        return graphs;
+    }
+
+    pub fn deinit(self: *Graphs) void {
+        for (self.map.values()) |*graph| graph.deinit();
+        self.map.deinit();
+    }
+
+    /// The graph of function, a fn_def or closure in the ast the graphs were built from
+    pub fn get(self: *const Graphs, function: *const Node) ?*const ControlFlowGraph {
+        return self.map.getPtr(function.id);
+    }
+
+    pub fn visitFnDef(self: *Graphs, node: *const Node, _: Node.FunctionDef) Allocator.Error!void {
+        try self.add(node);
+    }
+
+    pub fn visitClosure(self: *Graphs, node: *const Node, _: Node.Closure) Allocator.Error!void {
+        try self.add(node);
+    }
+
+    // Functions nested within function get graphs of their own
+    fn add(self: *Graphs, function: *const Node) Allocator.Error!void {
+        var graph = try ControlFlowGraph.build(self.map.allocator, function);
+        errdefer graph.deinit();
+
+        try self.map.put(function.id, graph);
+        try Walk.visitChildren(self, function);
+    }
+};
+
+test "analysis modules" {
+    _ = ControlFlowGraph;
+    _ = DeadAssignmentChecker;
+    _ = InitializationChecker;
+    _ = Liveness;
+    _ = ReachingDefinitions;
+}
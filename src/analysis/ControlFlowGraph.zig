@@ -0,0 +1,507 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The basic blocks of a function's body and the edges control takes between them.
+//!
+//! Blocks hold the statements of the body which run one after another, the body
+//! is split at every if, while, for, match, return, break, continue, label and
+//! goto, each label starting the block its gotos jump to. Branches
+//! and loops hold the nodes they evaluate rather than themselves, an if's block
+//! holds its condition and a match's its subject, so analyses can treat every
+//! statement as running in full. The header of a for loop holds the loop itself,
+//! standing for binding its variable to the next element. Control flow within the
+//! expressions of other statements, such as an if initializing a binding, stays
+//! within the statement's block
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const Node = ruka.Node;
+
+/// The fn_def or closure whose body the graph is of
+function: *const Node,
+/// The id of each block is its index, the entry is the first block and the exit,
+/// which holds no statements, the second
+blocks: ArrayList(BasicBlock),
+edges: ArrayList(Edge),
+
+allocator: Allocator,
+
+const ControlFlowGraph = @This();
+
+pub const entry = 0;
+pub const exit = 1;
+
+pub const BasicBlock = struct {
+    id: usize,
+    statements: ArrayList(*const Node)
+};
+
+pub const Edge = struct {
+    from: usize,
+    to: usize,
+    kind: Kind,
+
+    pub const Kind = enum {
+        unconditional,
+        /// Taken when the condition ending the block is true, or the pattern of a
+        /// match arm matches
+        if_true,
+        if_false,
+        /// From the end of a loop's body, or a continue, to its header
+        loop_back,
+        @"return"
+    };
+};
+
+/// Builds the graph of the body of function, a fn_def or closure
+pub fn build(allocator: Allocator, function: *const Node) !ControlFlowGraph {
+    const body = switch (function.kind) {
+        .fn_def => |fn_def| fn_def.body,
+        .closure => |closure| closure.body,
+        else => unreachable
+    };
+
+    var graph = ControlFlowGraph{
+        .function = function,
+        .blocks = .init(allocator),
+        .edges = .init(allocator),
+        .allocator = allocator
+    };
+    errdefer graph.deinit();
+
+    _ = try graph.addBlock();
+    _ = try graph.addBlock();
+
+    var builder = Builder{
+        .graph = &graph,
+        .current = entry,
+        .loops = .init(allocator),
+        .labels = .init(allocator)
+    };
+    defer builder.loops.deinit();
+    defer builder.labels.deinit();
+
+    try builder.statement(body);
+
+    // Falling off the end of the body returns its value
+    if (builder.current) |current| try graph.addEdge(current, exit, .unconditional);
+
+    return graph;
+}
+
+pub fn deinit(self: *ControlFlowGraph) void {
+    for (self.blocks.items) |*block| block.statements.deinit();
+    self.blocks.deinit();
+    self.edges.deinit();
+}
+
+/// The blocks control can reach block from, in the order their edges were added,
+/// the caller owns the slice
+pub fn predecessors(self: *const ControlFlowGraph, allocator: Allocator, block: usize) ![]usize {
+    var found = ArrayList(usize).init(allocator);
+    for (self.edges.items) |edge| if (edge.to == block) try found.append(edge.from);
+
+    return try found.toOwnedSlice();
+}
+
+/// The blocks control can reach from block, in the order their edges were added,
+/// the caller owns the slice
+pub fn successors(self: *const ControlFlowGraph, allocator: Allocator, block: usize) ![]usize {
+    var found = ArrayList(usize).init(allocator);
+    for (self.edges.items) |edge| if (edge.from == block) try found.append(edge.to);
+
+    return try found.toOwnedSlice();
+}
+
+/// Writes the graph in graphviz's dot language, each block labelled with the kinds
+/// and positions of its statements
+pub fn writeDot(self: *const ControlFlowGraph, writer: anytype, name: []const u8) @TypeOf(writer).Error!void {
+    try writer.writeAll("digraph ");
+    try std.json.encodeJsonString(name, .{}, writer);
+    try writer.writeAll(" {\n    node [shape=box];\n");
+
+    for (self.blocks.items) |block| {
+        try writer.print("    b{} [label=\"", .{block.id});
+        switch (block.id) {
+            entry => try writer.writeAll("entry"),
+            exit => try writer.writeAll("exit"),
+            else => try writer.print("b{}", .{block.id})
+        }
+
+        for (block.statements.items) |statement| {
+            try writer.print("\\l{s} {}:{}", .{@tagName(statement.kind), statement.span.pos.line, statement.span.pos.col});
+        }
+
+        try writer.writeAll("\\l\"];\n");
+    }
+
+    for (self.edges.items) |edge| {
+        try writer.print("    b{} -> b{}", .{edge.from, edge.to});
+        if (edge.kind != .unconditional) try writer.print(" [label=\"{s}\"]", .{@tagName(edge.kind)});
+        try writer.writeAll(";\n");
+    }
+
+    try writer.writeAll("}\n");
+}
+
+fn addBlock(self: *ControlFlowGraph) !usize {
+    const id = self.blocks.items.len;
+    try self.blocks.append(.{ .id = id, .statements = .init(self.allocator) });
+
+    return id;
+}
+
+fn addEdge(self: *ControlFlowGraph, from: usize, to: usize, kind: Edge.Kind) !void {
+    try self.edges.append(.{ .from = from, .to = to, .kind = kind });
+}
+
+const Builder = struct {
+    graph: *ControlFlowGraph,
+    /// The block statements are added to, null after a jump until the next
+    /// statement, which starts a block nothing reaches
+    current: ?usize,
+    /// The loops being built, innermost last
+    loops: ArrayList(Loop),
+    /// The block each label starts, added by the label or the first goto to it
+    labels: StringHashMap(usize),
+
+    const Loop = struct {
+        header: usize,
+        after: usize
+    };
+
+    fn statement(self: *Builder, node: *const Node) Allocator.Error!void {
+        switch (node.kind) {
+            .block => |block| for (block.statements) |child| try self.statement(child),
+            .@"unsafe" => |body| try self.statement(body),
+            .@"if" => |@"if"| {
+                const condition = try self.append(@"if".condition);
+
+                _ = try self.branch(condition, .if_true);
+                try self.statement(@"if".then_block);
+                const then_end = self.current;
+
+                // Without an else, control goes straight from the condition to the join
+                var else_end: End = .{ .from = condition, .kind = .if_false };
+                if (@"if".else_block) |else_block| {
+                    _ = try self.branch(condition, .if_false);
+                    try self.statement(else_block);
+                    else_end = .{ .from = self.current, .kind = .unconditional };
+                }
+
+                try self.join(&.{.{ .from = then_end, .kind = .unconditional }, else_end});
+            },
+            .@"while" => |@"while"| {
+                const header = try self.graph.addBlock();
+                if (self.current) |current| try self.graph.addEdge(current, header, .unconditional);
+                self.current = header;
+                _ = try self.append(@"while".condition);
+
+                try self.loop(header, @"while".body);
+            },
+            .@"for" => |@"for"| {
+                // The iterable is evaluated once, before the first iteration
+                _ = try self.append(@"for".iterable);
+
+                const header = try self.graph.addBlock();
+                if (self.current) |current| try self.graph.addEdge(current, header, .unconditional);
+                self.current = header;
+                _ = try self.append(node);
+
+                try self.loop(header, @"for".body);
+            },
+            .match => |match| {
+                var tested: ?usize = try self.append(match.subject);
+                var ends = ArrayList(End).init(self.graph.allocator);
+                defer ends.deinit();
+
+                for (match.arms, 0..) |arm, i| {
+                    const test_block = tested orelse break;
+                    const arm_block = try self.branch(test_block, .if_true);
+                    tested = null;
+
+                    // A failing guard moves on to the next arm, as a pattern which
+                    // doesn't match does
+                    if (arm.guard) |guard| {
+                        _ = try self.append(guard);
+                        _ = try self.branch(arm_block, .if_true);
+                    }
+
+                    try self.statement(arm.body);
+                    try ends.append(.{ .from = self.current, .kind = .unconditional });
+
+                    // Matches are exhaustive, so the last arm's pattern always matches
+                    if (arm.guard != null or i + 1 < match.arms.len) {
+                        const next = try self.graph.addBlock();
+                        try self.graph.addEdge(test_block, next, .if_false);
+                        if (arm.guard != null) try self.graph.addEdge(arm_block, next, .if_false);
+                        tested = next;
+                    }
+                }
+
+                // Only reached when the last arm is guarded
+                if (tested) |test_block| try ends.append(.{ .from = test_block, .kind = .unconditional });
+
+                try self.join(ends.items);
+            },
+            .@"return" => {
+                const current = try self.append(node);
+                try self.graph.addEdge(current, exit, .@"return");
+                self.current = null;
+            },
+            .@"break" => {
+                const current = try self.append(node);
+                if (self.innermost()) |innermost| try self.graph.addEdge(current, innermost.after, .unconditional);
+                self.current = null;
+            },
+            .@"continue" => {
+                const current = try self.append(node);
+                if (self.innermost()) |innermost| try self.graph.addEdge(current, innermost.header, .loop_back);
+                self.current = null;
+            },
+            .label => |name| {
+                const block = try self.labelBlock(name);
+                if (self.current) |current| try self.graph.addEdge(current, block, .unconditional);
+                self.current = block;
+                _ = try self.append(node);
+            },
+            .goto => |target| {
+                const current = try self.append(node);
+                try self.graph.addEdge(current, try self.labelBlock(target), .unconditional);
+                self.current = null;
+            },
+            else => _ = try self.append(node)
+        }
+    }
+
+    const End = struct {
+        from: ?usize,
+        kind: Edge.Kind
+    };
+
+    // Adds node to the current block, starting one if the last statement jumped
+    fn append(self: *Builder, node: *const Node) !usize {
+        const current = self.current orelse try self.graph.addBlock();
+        try self.graph.blocks.items[current].statements.append(node);
+        self.current = current;
+
+        return current;
+    }
+
+    // Starts a block reached from block by an edge of kind
+    fn branch(self: *Builder, from: usize, kind: Edge.Kind) !usize {
+        const to = try self.graph.addBlock();
+        try self.graph.addEdge(from, to, kind);
+        self.current = to;
+
+        return to;
+    }
+
+    // Starts the block reached from the end of each branch, or none if every branch jumped
+    fn join(self: *Builder, ends: []const End) !void {
+        var reached = false;
+        for (ends) |end| reached = reached or end.from != null;
+
+        if (!reached) {
+            self.current = null;
+            return;
+        }
+
+        const joined = try self.graph.addBlock();
+        for (ends) |end| if (end.from) |from| try self.graph.addEdge(from, joined, end.kind);
+        self.current = joined;
+    }
+
+    // Builds the body of the loop whose header is the current block, leaving the
+    // block after the loop current
+    fn loop(self: *Builder, header: usize, body: *const Node) !void {
+        const after = try self.graph.addBlock();
+        try self.graph.addEdge(header, after, .if_false);
+
+        try self.loops.append(.{ .header = header, .after = after });
+        _ = try self.branch(header, .if_true);
+        try self.statement(body);
+        self.loops.items.len = self.loops.items.len - 1;
+
+        if (self.current) |current| try self.graph.addEdge(current, header, .loop_back);
+        self.current = after;
+    }
+
+    fn labelBlock(self: *Builder, name: []const u8) !usize {
+        if (self.labels.get(name)) |block| return block;
+
+        const block = try self.graph.addBlock();
+        try self.labels.put(name, block);
+        return block;
+    }
+
+    fn innermost(self: *const Builder) ?Loop {
+        const loops = self.loops.items;
+        return if (loops.len == 0) null else loops[loops.len - 1];
+    }
+};
+
+test "control flow graph" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const Unit = ruka.Unit;
+
+    const Built = struct {
+        unit: *Unit,
+        ast: *ruka.Ast,
+        graph: ControlFlowGraph,
+
+        fn deinit(self: *Built) void {
+            self.graph.deinit();
+            self.ast.deinit();
+            self.unit.deinit();
+        }
+    };
+
+    // Parses source, a single function, and builds its graph
+    fn buildFrom(source: []const u8) !Built {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        errdefer unit.deinit();
+
+        const ast = try unit.parse();
+        errdefer ast.deinit();
+
+        return .{
+            .unit = unit,
+            .ast = ast,
+            .graph = try build(testing.allocator, ast.root.kind.module.items[0])
+        };
+    }
+
+    fn expectEdge(graph: *const ControlFlowGraph, from: usize, to: usize, kind: Edge.Kind) !void {
+        for (graph.edges.items) |edge| {
+            if (edge.from == from and edge.to == to and edge.kind == kind) return;
+        }
+
+        return error.TestExpectedEdge;
+    }
+
+    test "ifs branch and join" {
+        var built = try buildFrom(
+            \\fn f(x: i64) -> i64 {
+            \\    let y = x + 1
+            \\    if y > 2 { return y }
+            \\    y * 2
+            \\}
+        );
+        defer built.deinit();
+
+        const graph = &built.graph;
+
+        // entry, exit, then and the block after the if
+        try testing.expectEqual(4, graph.blocks.items.len);
+        try testing.expectEqual(2, graph.blocks.items[entry].statements.items.len);
+        try testing.expectEqual(0, graph.blocks.items[exit].statements.items.len);
+
+        try expectEdge(graph, entry, 2, .if_true);
+        try expectEdge(graph, 2, exit, .@"return");
+        try expectEdge(graph, entry, 3, .if_false);
+        try expectEdge(graph, 3, exit, .unconditional);
+        try testing.expectEqual(4, graph.edges.items.len);
+    }
+
+    test "loops lead back to their headers" {
+        var built = try buildFrom(
+            \\fn f() -> i64 {
+            \\    var total = 0
+            \\    for i in 0..10 {
+            \\        if i == 5 { break }
+            \\        if i % 2 == 0 { continue }
+            \\        total = total + i
+            \\    }
+            \\    total
+            \\}
+        );
+        defer built.deinit();
+
+        const graph = &built.graph;
+        const header = 2;
+        const after = 3;
+
+        try expectEdge(graph, entry, header, .unconditional);
+        try testing.expect(graph.blocks.items[header].statements.items[0].kind == .@"for");
+        try expectEdge(graph, header, after, .if_false);
+
+        var breaks: usize = 0;
+        var loop_backs: usize = 0;
+        for (graph.edges.items) |edge| {
+            if (edge.to == after and edge.kind == .unconditional) breaks = breaks + 1;
+            if (edge.to == header and edge.kind == .loop_back) loop_backs = loop_backs + 1;
+        }
+
+        try testing.expectEqual(1, breaks);
+        // The continue and the end of the body
+        try testing.expectEqual(2, loop_backs);
+        try expectEdge(graph, after, exit, .unconditional);
+    }
+
+    test "gotos lead to the blocks their labels start" {
+        var built = try buildFrom(
+            \\fn f(n: i64) {
+            \\    var i = 0
+            \\    unsafe {
+            \\        again:
+            \\        i = i + 1
+            \\        if i < n { goto again }
+            \\    }
+            \\}
+        );
+        defer built.deinit();
+
+        const graph = &built.graph;
+        const again = 2;
+
+        try testing.expect(graph.blocks.items[again].statements.items[0].kind == .label);
+        try expectEdge(graph, entry, again, .unconditional);
+        try expectEdge(graph, again, 3, .if_true);
+        try expectEdge(graph, 3, again, .unconditional);
+        try expectEdge(graph, again, 4, .if_false);
+        try expectEdge(graph, 4, exit, .unconditional);
+        try testing.expectEqual(5, graph.edges.items.len);
+    }
+
+    test "graphs are written in dot" {
+        var built = try buildFrom(
+            \\fn f(x: bool) -> i64 {
+            \\    while x { return 1 }
+            \\    0
+            \\}
+        );
+        defer built.deinit();
+
+        var dot = ArrayList(u8).init(testing.allocator);
+        defer dot.deinit();
+
+        try built.graph.writeDot(dot.writer(), "f");
+        try testing.expectEqualStrings(
+            \\digraph "f" {
+            \\    node [shape=box];
+            \\    b0 [label="entry\l"];
+            \\    b1 [label="exit\l"];
+            \\    b2 [label="b2\lidentifier 2:11\l"];
+            \\    b3 [label="b3\lliteral 3:5\l"];
+            \\    b4 [label="b4\lreturn 2:15\l"];
+            \\    b0 -> b2;
+            \\    b2 -> b3 [label="if_false"];
+            \\    b2 -> b4 [label="if_true"];
+            \\    b4 -> b1 [label="return"];
+            \\    b3 -> b1;
+            \\}
+            \\
+        , dot.items);
+    }
+};
@@ -0,0 +1,290 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Warns about values given to local variables, by assignments and var bindings,
+//! which are never used as the variable isn't live afterwards. Variables whose
+//! names start with an underscore are left alone, as are those bound more than
+//! once in a function, whose liveness mixes that of each binding, and those
+//! referenced, which may be used through the reference
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const analysis = ruka.analysis;
+const ControlFlowGraph = ruka.ControlFlowGraph;
+const Liveness = analysis.Liveness;
+const Node = ruka.Node;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+unit: *Unit,
+allocator: Allocator,
+
+const DeadAssignmentChecker = @This();
+
+pub fn init(unit: *Unit) !*DeadAssignmentChecker {
+    const checker = try unit.allocator.create(DeadAssignmentChecker);
+
+    checker.* = .{
+        .unit = unit,
+        .allocator = unit.allocator
+    };
+
+    return checker;
+}
+
+pub fn deinit(self: *DeadAssignmentChecker) void {
+    self.allocator.destroy(self);
+}
+
+/// Warns about the dead assignments in each graph
+pub fn check(self: *DeadAssignmentChecker, graphs: *const analysis.Graphs) !void {
+    for (graphs.map.values()) |*graph| try self.checkGraph(graph);
+}
+
+fn checkGraph(self: *DeadAssignmentChecker, graph: *const ControlFlowGraph) !void {
+    // The number of times each local is bound, those of the top level may be used
+    // by other functions after any assignment
+    var locals = StringHashMap(usize).init(self.allocator);
+    defer locals.deinit();
+
+    const params = switch (graph.function.kind) {
+        .fn_def => |fn_def| fn_def.params,
+        .closure => |closure| closure.params,
+        else => unreachable
+    };
+    for (params) |param| try bind(&locals, param.name);
+
+    for (graph.blocks.items) |block| for (block.statements.items) |statement| switch (statement.kind) {
+        .var_decl, .const_decl => |binding| try bind(&locals, binding.name),
+        .@"for" => |loop| try bind(&locals, loop.variable),
+        else => {}
+    };
+
+    var referenced = StringHashMap(void).init(self.allocator);
+    defer referenced.deinit();
+
+    var collector = ReferenceCollector{ .names = &referenced };
+    for (graph.blocks.items) |block| {
+        for (block.statements.items) |statement| try ReferenceCollector.Walk.visit(&collector, statement);
+    }
+
+    var liveness = try Liveness.compute(self.allocator, graph);
+    defer liveness.deinit();
+
+    for (graph.blocks.items) |block| {
+        var live = try liveness.blocks[block.id].live_out.clone();
+        defer live.deinit();
+
+        // Walked backwards, so live holds the variables live after each statement
+        var i = block.statements.items.len;
+        while (i > 0) {
+            i = i - 1;
+
+            const statement = block.statements.items[i];
+            if (assigned(statement)) |name| {
+                const once = (locals.get(name) orelse 0) == 1;
+                if (!live.contains(name) and once and !referenced.contains(name) and !std.mem.startsWith(u8, name, "_")) {
+                    try self.unit.report(.initLint(.unused_variable, "value assigned but never used", statement.span));
+                }
+            }
+
+            try Liveness.transfer(&live, statement);
+        }
+    }
+}
+
+// The variable statement gives a value to, let and const bindings hold the value
+// they are bound to however they are used so aren't assignments
+fn assigned(statement: *const Node) ?[]const u8 {
+    return switch (statement.kind) {
+        .var_decl => |binding| if (binding.mutable and binding.value != null) binding.name else null,
+        .assignment => Liveness.definedBy(statement),
+        else => null
+    };
+}
+
+fn bind(locals: *StringHashMap(usize), name: []const u8) !void {
+    const count = try locals.getOrPut(name);
+    count.value_ptr.* = if (count.found_existing) count.value_ptr.* + 1 else 1;
+}
+
+// Collects the variables whose fields, elements or selves are referenced
+const ReferenceCollector = struct {
+    names: *StringHashMap(void),
+
+    const Walk = Visitor(ReferenceCollector, Allocator.Error, void);
+
+    pub fn visitReference(self: *ReferenceCollector, _: *const Node, operand: *const Node) Allocator.Error!void {
+        var place = operand;
+        while (true) {
+            place = switch (place.kind) {
+                .field_access => |access| access.object,
+                .index => |index| index.object,
+                .tuple_index => |access| access.object,
+                .identifier => |name| return try self.names.put(name, {}),
+                else => return
+            };
+        }
+    }
+};
+
+test "dead assignment checker" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const Compiler = ruka.Compiler;
+    const Position = ruka.Position;
+
+    // Checks source with the optimizations which enable the checker, expecting a
+    // dead assignment warning at each of positions
+    fn expectWarnings(source: []const u8, positions: []const Position) !void {
+        const compiler = try Compiler.initSource(testing.allocator, .{
+            .path = "test.ruka",
+            .contents = source
+        }, .{ .check_only = true, .optimization_level = .release_fast });
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        var warnings = std.ArrayList(Position).init(testing.allocator);
+        defer warnings.deinit();
+
+        for (compiler.diagnostics.items) |diagnostic| {
+            try testing.expectEqual(.warning, diagnostic.severity);
+            try testing.expectEqualStrings("value assigned but never used", diagnostic.message);
+            try warnings.append(diagnostic.span.pos);
+        }
+
+        try testing.expectEqualSlices(Position, positions, warnings.items);
+    }
+
+    test "values overwritten before they are used" {
+        try expectWarnings(
+            \\fn f() -> i64 {
+            \\    var x = 1
+            \\    x = 2
+            \\    x
+            \\}
+        , &.{.init(2, 5)});
+    }
+
+    test "values assigned after their last use" {
+        try expectWarnings(
+            \\fn f() -> i64 {
+            \\    var x = 1
+            \\    let y = x + 1
+            \\    x = 5
+            \\    y
+            \\}
+        , &.{.init(4, 5)});
+    }
+
+    test "values assigned in one branch and overwritten after it" {
+        try expectWarnings(
+            \\fn f(c: bool) -> i64 {
+            \\    var x = 0
+            \\    if c {
+            \\        x = 1
+            \\    }
+            \\    x = 2
+            \\    x
+            \\}
+        , &.{.init(2, 5), .init(4, 9)});
+    }
+
+    test "values assigned by the last iteration of a loop" {
+        try expectWarnings(
+            \\fn f(n: i64) -> i64 {
+            \\    var total = 0
+            \\    var last = 0
+            \\    for i in 0..n {
+            \\        total = total + i
+            \\        last = i
+            \\    }
+            \\    total
+            \\}
+        , &.{.init(3, 5), .init(6, 9)});
+    }
+
+    test "values assigned before returning" {
+        try expectWarnings(
+            \\fn f(c: bool) -> i64 {
+            \\    var x = 1
+            \\    if c {
+            \\        x = 2
+            \\        return 0
+            \\    }
+            \\    x
+            \\}
+        , &.{.init(4, 9)});
+    }
+
+    test "values of referenced variables aren't warned about" {
+        try expectWarnings(
+            \\fn f() -> i64 {
+            \\    var x = 1
+            \\    let r = &x
+            \\    x = 2
+            \\    *r
+            \\}
+        , &.{});
+    }
+
+    test "values allowed to be unused by attributes" {
+        try expectWarnings(
+            \\fn f() -> i64 {
+            \\    #[allow(unused_variable)]
+            \\    var x = 1
+            \\    x = 2
+            \\    var y = 1
+            \\    y = 2
+            \\    x + y
+            \\}
+        , &.{.init(5, 5)});
+    }
+
+    test "values denied being unused by attributes" {
+        const compiler = try Compiler.initSource(testing.allocator, .{
+            .path = "test.ruka",
+            .contents =
+                \\#[deny(unused_variable)]
+                \\fn f() -> i64 {
+                \\    var x = 1
+                \\    x = 2
+                \\    x
+                \\}
+        }, .{ .check_only = true, .optimization_level = .release_fast });
+        defer compiler.deinit();
+
+        try testing.expectError(error.AnalysisFailed, compiler.compile());
+
+        const diagnostics = compiler.diagnostics.items;
+        try testing.expectEqual(1, diagnostics.len);
+        try testing.expectEqual(.@"error", diagnostics[0].severity);
+        try testing.expectEqual(Position.init(3, 5), diagnostics[0].span.pos);
+    }
+
+    test "values used on any path aren't warned about" {
+        try expectWarnings(
+            \\var count = 0
+            \\fn f(c: bool) -> i64 {
+            \\    var x = 0
+            \\    if c {
+            \\        x = x + 1
+            \\    } else {
+            \\        x = 2
+            \\    }
+            \\    var _unused = 3
+            \\    _unused = 4
+            \\    count = count + 1
+            \\    x
+            \\}
+        , &.{});
+    }
+};
@@ -0,0 +1,156 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Reports uses of variables which some path from the start of the function
+//! reaches without giving them a value, such as those bound without one and
+//! assigned in only one branch of an if
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("../prelude.zig");
+const analysis = ruka.analysis;
+const ControlFlowGraph = ruka.ControlFlowGraph;
+const Liveness = analysis.Liveness;
+const ReachingDefinitions = analysis.ReachingDefinitions;
+const Unit = ruka.Unit;
+
+unit: *Unit,
+allocator: Allocator,
+
+const InitializationChecker = @This();
+
+pub fn init(unit: *Unit) !*InitializationChecker {
+    const checker = try unit.allocator.create(InitializationChecker);
+
+    checker.* = .{
+        .unit = unit,
+        .allocator = unit.allocator
+    };
+
+    return checker;
+}
+
+pub fn deinit(self: *InitializationChecker) void {
+    self.allocator.destroy(self);
+}
+
+/// Reports the uses of possibly uninitialized variables in each graph
+pub fn check(self: *InitializationChecker, graphs: *const analysis.Graphs) !void {
+    for (graphs.map.values()) |*graph| try self.checkGraph(graph);
+}
+
+fn checkGraph(self: *InitializationChecker, graph: *const ControlFlowGraph) !void {
+    var reaching = try ReachingDefinitions.compute(self.allocator, graph);
+    defer reaching.deinit();
+
+    var uses = ArrayList(Liveness.Use).init(self.allocator);
+    defer uses.deinit();
+
+    for (graph.blocks.items) |block| {
+        var defs = try reaching.blocks[block.id].reaching_in.clone();
+        defer defs.deinit();
+
+        for (block.statements.items) |statement| {
+            uses.clearRetainingCapacity();
+            try Liveness.collectUses(&uses, statement);
+
+            for (uses.items) |use| if (reachedUninitialized(&defs, use.name)) {
+                try self.unit.createError("use of possibly uninitialized variable", use.span);
+            };
+
+            try ReachingDefinitions.transfer(&defs, statement);
+        }
+    }
+}
+
+// Whether a binding of name without a value is among defs
+fn reachedUninitialized(defs: *const ReachingDefinitions.DefSet, name: []const u8) bool {
+    var definitions = defs.keyIterator();
+    while (definitions.next()) |definition| {
+        if (!ReachingDefinitions.isUninitialized(definition.*)) continue;
+        if (std.mem.eql(u8, Liveness.definedBy(definition.*).?, name)) return true;
+    }
+
+    return false;
+}
+
+test "initialization checker" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const Compiler = ruka.Compiler;
+    const Position = ruka.Position;
+
+    // Checks source, expecting an error at each of positions
+    fn expectErrors(source: []const u8, positions: []const Position) !void {
+        const compiler = try Compiler.initSource(testing.allocator, .{
+            .path = "test.ruka",
+            .contents = source
+        }, .{ .check_only = true });
+        defer compiler.deinit();
+
+        if (positions.len == 0) {
+            const result = (try compiler.compile()).?;
+            result.deinit();
+        } else {
+            try testing.expectError(error.AnalysisFailed, compiler.compile());
+        }
+
+        var errors = ArrayList(Position).init(testing.allocator);
+        defer errors.deinit();
+
+        for (compiler.diagnostics.items) |diagnostic| {
+            try testing.expectEqual(.@"error", diagnostic.severity);
+            try testing.expectEqualStrings("use of possibly uninitialized variable", diagnostic.message);
+            try errors.append(diagnostic.span.pos);
+        }
+
+        try testing.expectEqualSlices(Position, positions, errors.items);
+    }
+
+    test "variables assigned on only some paths" {
+        try expectErrors(
+            \\fn f(c: bool) -> i64 {
+            \\    var x: i64
+            \\    if c {
+            \\        x = 1
+            \\    }
+            \\    x + 1
+            \\}
+        , &.{.init(6, 5)});
+    }
+
+    test "variables assigned on every path" {
+        try expectErrors(
+            \\fn f(c: bool) -> i64 {
+            \\    var x: i64
+            \\    if c {
+            \\        x = 1
+            \\    } else {
+            \\        x = 2
+            \\    }
+            \\    x + 1
+            \\}
+        , &.{});
+    }
+
+    test "variables assigned within loops" {
+        // Each iteration uses the total of the last, but the loop may not run at all
+        try expectErrors(
+            \\fn f(n: i64) -> i64 {
+            \\    var total: i64
+            \\    total = 0
+            \\    var last: i64
+            \\    for i in 0..n {
+            \\        last = i
+            \\        total = total + last
+            \\    }
+            \\    total + last
+            \\}
+        , &.{.init(9, 13)});
+    }
+};
@@ -0,0 +1,295 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Finds the variables live at the entry and exit of each block of a control flow
+//! graph, those whose current values may still be used, by iterating
+//! `live_in = uses ∪ (live_out - definitions)` backwards to a fixed point.
+//!
+//! Variables are identified by name, so a binding shadowing another in a nested
+//! block is treated as the same variable. Uses within nested control flow or
+//! closures are counted wherever the enclosing statement runs, keeping variables
+//! live for longer than they may be
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const ControlFlowGraph = ruka.ControlFlowGraph;
+const Node = ruka.Node;
+const Span = ruka.Span;
+const Visitor = ruka.Visitor;
+
+/// The sets of each block, indexed by its id
+blocks: []Sets,
+
+allocator: Allocator,
+
+const Liveness = @This();
+
+/// The names of live variables
+pub const LiveSet = StringHashMap(void);
+
+pub const Sets = struct {
+    live_in: LiveSet,
+    live_out: LiveSet
+};
+
+/// A variable's value being used, by an identifier or a closure capturing it
+pub const Use = struct {
+    name: []const u8,
+    span: Span
+};
+
+/// Computes the variables live at the entry and exit of each block of graph
+pub fn compute(allocator: Allocator, graph: *const ControlFlowGraph) !Liveness {
+    const blocks = graph.blocks.items;
+
+    var liveness = Liveness{
+        .blocks = try allocator.alloc(Sets, blocks.len),
+        .allocator = allocator
+    };
+    for (liveness.blocks) |*sets| sets.* = .{ .live_in = .init(allocator), .live_out = .init(allocator) };
+    errdefer liveness.deinit();
+
+    // The uses and definitions of each block, summarising its statements
+    const summaries = try allocator.alloc(Summary, blocks.len);
+    for (summaries) |*summary| summary.* = .{ .uses = .init(allocator), .definitions = .init(allocator) };
+    defer {
+        for (summaries) |*summary| summary.deinit();
+        allocator.free(summaries);
+    }
+
+    for (blocks, summaries) |block, *summary| try summary.build(block.statements.items);
+
+    // Blocks are worked backwards from the end, so most are only visited once
+    // outside of loops
+    var worklist = ArrayList(usize).init(allocator);
+    defer worklist.deinit();
+
+    const queued = try allocator.alloc(bool, blocks.len);
+    defer allocator.free(queued);
+    @memset(queued, true);
+
+    for (0..blocks.len) |id| try worklist.append(id);
+
+    while (worklist.items.len > 0) {
+        const id = worklist.items[worklist.items.len - 1];
+        worklist.items.len = worklist.items.len - 1;
+        queued[id] = false;
+
+        const sets = &liveness.blocks[id];
+        for (graph.edges.items) |edge| if (edge.from == id) {
+            try unionInto(&sets.live_out, &liveness.blocks[edge.to].live_in);
+        };
+
+        const summary = &summaries[id];
+        const count = sets.live_in.count();
+
+        try unionInto(&sets.live_in, &summary.uses);
+        var live_out = sets.live_out.keyIterator();
+        while (live_out.next()) |name| {
+            if (!summary.definitions.contains(name.*)) try sets.live_in.put(name.*, {});
+        }
+
+        // Sets only grow, so live_in changed if its count did
+        if (sets.live_in.count() == count) continue;
+        for (graph.edges.items) |edge| if (edge.to == id and !queued[edge.from]) {
+            queued[edge.from] = true;
+            try worklist.append(edge.from);
+        };
+    }
+
+    return liveness;
+}
+
+pub fn deinit(self: *Liveness) void {
+    for (self.blocks) |*sets| {
+        sets.live_in.deinit();
+        sets.live_out.deinit();
+    }
+    self.allocator.free(self.blocks);
+}
+
+/// Moves live, the variables live after statement, to before it
+pub fn transfer(live: *LiveSet, statement: *const Node) !void {
+    if (definedBy(statement)) |name| _ = live.remove(name);
+
+    var uses = ArrayList(Use).init(live.allocator);
+    defer uses.deinit();
+
+    try collectUses(&uses, statement);
+    for (uses.items) |use| try live.put(use.name, {});
+}
+
+/// The variable statement gives a new value, null if it doesn't. Assignments to the
+/// fields of a variable only change part of its value, so don't define it
+pub fn definedBy(statement: *const Node) ?[]const u8 {
+    return switch (statement.kind) {
+        .var_decl, .const_decl => |binding| binding.name,
+        .assignment => |assignment| switch (assignment.lhs.kind) {
+            .identifier => |name| name,
+            else => null
+        },
+        .@"for" => |loop| loop.variable,
+        else => null
+    };
+}
+
+/// Adds the uses of variables by statement to uses, in source order
+pub fn collectUses(uses: *ArrayList(Use), statement: *const Node) !void {
+    var collector = UseCollector{ .uses = uses };
+
+    switch (statement.kind) {
+        .var_decl, .const_decl => |binding| if (binding.value) |value| try UseCollector.Walk.visit(&collector, value),
+        .assignment => |assignment| {
+            if (definedBy(statement) == null) try UseCollector.Walk.visit(&collector, assignment.lhs);
+            try UseCollector.Walk.visit(&collector, assignment.rhs);
+        },
+        // Stands for binding the variable, the iterable and body are in other blocks
+        .@"for" => {},
+        else => try UseCollector.Walk.visit(&collector, statement)
+    }
+}
+
+fn unionInto(set: *LiveSet, other: *const LiveSet) !void {
+    var names = other.keyIterator();
+    while (names.next()) |name| try set.put(name.*, {});
+}
+
+const Summary = struct {
+    /// Used before they are defined within the block
+    uses: LiveSet,
+    definitions: LiveSet,
+
+    fn deinit(self: *Summary) void {
+        self.uses.deinit();
+        self.definitions.deinit();
+    }
+
+    fn build(self: *Summary, statements: []const *const Node) !void {
+        var i = statements.len;
+        while (i > 0) {
+            i = i - 1;
+
+            const statement = statements[i];
+            if (definedBy(statement)) |name| try self.definitions.put(name, {});
+            try transfer(&self.uses, statement);
+        }
+    }
+};
+
+const UseCollector = struct {
+    uses: *ArrayList(Use),
+
+    const Walk = Visitor(UseCollector, Allocator.Error, void);
+
+    pub fn visitIdentifier(self: *UseCollector, node: *const Node, name: []const u8) Allocator.Error!void {
+        try self.uses.append(.{ .name = name, .span = node.span });
+    }
+
+    // Annotations name types rather than using variables
+    pub fn visitVarDecl(self: *UseCollector, _: *const Node, binding: Node.Binding) Allocator.Error!void {
+        if (binding.value) |value| try Walk.visit(self, value);
+    }
+
+    pub fn visitConstDecl(self: *UseCollector, node: *const Node, binding: Node.Binding) Allocator.Error!void {
+        try self.visitVarDecl(node, binding);
+    }
+
+    pub fn visitDestructure(self: *UseCollector, _: *const Node, destructure: Node.Destructure) Allocator.Error!void {
+        try Walk.visit(self, destructure.value);
+    }
+
+    pub fn visitTypeExpr(_: *UseCollector, _: *const Node, _: Node.TypeExpr) Allocator.Error!void {}
+
+    // Closures use the variables they capture where they are created
+    pub fn visitClosure(self: *UseCollector, node: *const Node, closure: Node.Closure) Allocator.Error!void {
+        for (closure.captures) |capture| try self.uses.append(.{ .name = capture, .span = node.span });
+    }
+
+    // Functions can't refer to the variables of those enclosing them
+    pub fn visitFnDef(_: *UseCollector, _: *const Node, _: Node.FunctionDef) Allocator.Error!void {}
+};
+
+test "liveness" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const Unit = ruka.Unit;
+
+    // Parses source, a single function, and computes the liveness of its graph
+    fn expectLiveness(source: []const u8, expected: []const struct { usize, []const []const u8, []const []const u8 }) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        var graph = try ControlFlowGraph.build(testing.allocator, ast.root.kind.module.items[0]);
+        defer graph.deinit();
+
+        var liveness = try compute(testing.allocator, &graph);
+        defer liveness.deinit();
+
+        for (expected) |block| {
+            const id, const live_in, const live_out = block;
+            try expectNames(live_in, &liveness.blocks[id].live_in);
+            try expectNames(live_out, &liveness.blocks[id].live_out);
+        }
+    }
+
+    fn expectNames(expected: []const []const u8, set: *const LiveSet) !void {
+        for (expected) |name| {
+            if (!set.contains(name)) return error.TestExpectedLive;
+        }
+
+        try testing.expectEqual(expected.len, set.count());
+    }
+
+    test "overwritten values aren't live" {
+        // x = 1 is overwritten on every path before x is used
+        const entry = ControlFlowGraph.entry;
+        try expectLiveness(
+            \\fn f(c: bool) -> i64 {
+            \\    var x = 1
+            \\    if c {
+            \\        x = 2
+            \\    }
+            \\    x = 3
+            \\    x
+            \\}
+        , &.{
+            .{entry, &.{"c"}, &.{}},
+            .{2, &.{}, &.{}},
+            .{3, &.{}, &.{}},
+            .{ControlFlowGraph.exit, &.{}, &.{}}
+        });
+    }
+
+    test "values used by later iterations stay live around loops" {
+        const header = 2;
+        const body = 4;
+        try expectLiveness(
+            \\fn f(n: i64) -> i64 {
+            \\    var total = 0
+            \\    var i = 0
+            \\    while i < n {
+            \\        total = total + i
+            \\        i = i + 1
+            \\    }
+            \\    total
+            \\}
+        , &.{
+            .{ControlFlowGraph.entry, &.{"n"}, &.{"i", "n", "total"}},
+            .{header, &.{"i", "n", "total"}, &.{"i", "n", "total"}},
+            .{body, &.{"i", "n", "total"}, &.{"i", "n", "total"}},
+            .{3, &.{"total"}, &.{}}
+        });
+    }
+};
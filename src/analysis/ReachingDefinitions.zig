@@ -0,0 +1,178 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Finds the definitions of variables which may reach the entry and exit of each
+//! block of a control flow graph, those which may have given the variables their
+//! current values, by iterating `out = gen ∪ (in - kill)` forwards to a fixed point.
+//!
+//! A binding without a value is a definition leaving its variable uninitialized,
+//! so the uses it reaches may read a variable which was never given a value.
+//! Variables are identified by name as in Liveness
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const AutoHashMap = std.AutoHashMap;
+
+const ruka = @import("../prelude.zig");
+const ControlFlowGraph = ruka.ControlFlowGraph;
+const Liveness = ruka.analysis.Liveness;
+const Node = ruka.Node;
+
+/// The sets of each block, indexed by its id
+blocks: []Sets,
+
+allocator: Allocator,
+
+const ReachingDefinitions = @This();
+
+/// The statements defining variables, see Liveness.definedBy
+pub const DefSet = AutoHashMap(*const Node, void);
+
+pub const Sets = struct {
+    reaching_in: DefSet,
+    reaching_out: DefSet
+};
+
+/// Computes the definitions reaching the entry and exit of each block of graph
+pub fn compute(allocator: Allocator, graph: *const ControlFlowGraph) !ReachingDefinitions {
+    const blocks = graph.blocks.items;
+
+    var reaching = ReachingDefinitions{
+        .blocks = try allocator.alloc(Sets, blocks.len),
+        .allocator = allocator
+    };
+    for (reaching.blocks) |*sets| sets.* = .{ .reaching_in = .init(allocator), .reaching_out = .init(allocator) };
+    errdefer reaching.deinit();
+
+    // Blocks are worked forwards from the entry, which is popped first
+    var worklist = ArrayList(usize).init(allocator);
+    defer worklist.deinit();
+
+    const queued = try allocator.alloc(bool, blocks.len);
+    defer allocator.free(queued);
+    @memset(queued, true);
+
+    var id = blocks.len;
+    while (id > 0) {
+        id = id - 1;
+        try worklist.append(id);
+    }
+
+    while (worklist.items.len > 0) {
+        const current = worklist.items[worklist.items.len - 1];
+        worklist.items.len = worklist.items.len - 1;
+        queued[current] = false;
+
+        const sets = &reaching.blocks[current];
+        for (graph.edges.items) |edge| if (edge.to == current) {
+            try unionInto(&sets.reaching_in, &reaching.blocks[edge.from].reaching_out);
+        };
+
+        const changed = block: {
+            var out = try sets.reaching_in.clone();
+            errdefer out.deinit();
+
+            for (blocks[current].statements.items) |statement| try transfer(&out, statement);
+
+            // Sets only grow, so reaching_out changed if its count did
+            const grew = out.count() != sets.reaching_out.count();
+            sets.reaching_out.deinit();
+            sets.reaching_out = out;
+
+            break :block grew;
+        };
+
+        if (!changed) continue;
+        for (graph.edges.items) |edge| if (edge.from == current and !queued[edge.to]) {
+            queued[edge.to] = true;
+            try worklist.append(edge.to);
+        };
+    }
+
+    return reaching;
+}
+
+pub fn deinit(self: *ReachingDefinitions) void {
+    for (self.blocks) |*sets| {
+        sets.reaching_in.deinit();
+        sets.reaching_out.deinit();
+    }
+    self.allocator.free(self.blocks);
+}
+
+/// Moves defs, the definitions reaching statement, to after it
+pub fn transfer(defs: *DefSet, statement: *const Node) !void {
+    const name = Liveness.definedBy(statement) orelse return;
+
+    var killed = ArrayList(*const Node).init(defs.allocator);
+    defer killed.deinit();
+
+    var definitions = defs.keyIterator();
+    while (definitions.next()) |definition| {
+        if (std.mem.eql(u8, Liveness.definedBy(definition.*).?, name)) try killed.append(definition.*);
+    }
+
+    for (killed.items) |definition| _ = defs.remove(definition);
+    try defs.put(statement, {});
+}
+
+/// Whether definition is a binding without a value, leaving its variable uninitialized
+pub fn isUninitialized(definition: *const Node) bool {
+    return switch (definition.kind) {
+        .var_decl, .const_decl => |binding| binding.value == null,
+        else => false
+    };
+}
+
+fn unionInto(set: *DefSet, other: *const DefSet) !void {
+    var definitions = other.keyIterator();
+    while (definitions.next()) |definition| try set.put(definition.*, {});
+}
+
+test "reaching definitions" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const Unit = ruka.Unit;
+
+    test "definitions on either branch reach their join" {
+        var input = std.io.fixedBufferStream(
+            \\fn f(c: bool) -> i64 {
+            \\    var x: i64
+            \\    if c {
+            \\        x = 1
+            \\    }
+            \\    x
+            \\}
+        );
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        var graph = try ControlFlowGraph.build(testing.allocator, ast.root.kind.module.items[0]);
+        defer graph.deinit();
+
+        var reaching = try compute(testing.allocator, &graph);
+        defer reaching.deinit();
+
+        // entry, exit, then and the join
+        const declaration = graph.blocks.items[ControlFlowGraph.entry].statements.items[0];
+        const assignment = graph.blocks.items[2].statements.items[0];
+
+        const joined = &reaching.blocks[3].reaching_in;
+        try testing.expectEqual(2, joined.count());
+        try testing.expect(joined.contains(declaration));
+        try testing.expect(joined.contains(assignment));
+        try testing.expect(isUninitialized(declaration));
+
+        const assigned = &reaching.blocks[2].reaching_out;
+        try testing.expectEqual(1, assigned.count());
+        try testing.expect(assigned.contains(assignment));
+    }
+};
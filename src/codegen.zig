@@ -0,0 +1,16 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Backends translating type checked asts into other languages
+
+pub const C = @import("codegen/C.zig");
+pub const LLVM = @import("codegen/LLVM.zig");
+pub const SourceMap = @import("codegen/SourceMap.zig");
+pub const Wasm = @import("codegen/Wasm.zig");
+
+test "codegen modules" {
+    _ = C;
+    _ = LLVM;
+    _ = SourceMap;
+    _ = Wasm;
+}
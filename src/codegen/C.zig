@@ -0,0 +1,3219 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const AutoHashMap = std.AutoHashMap;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const SourceMap = ruka.codegen.SourceMap;
+const Span = ruka.Span;
+const stdlib = ruka.stdlib;
+const Target = ruka.Target;
+const Type = ruka.Type;
+const TypeTable = ruka.TypeTable;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+ast: *Ast,
+types: *const TypeTable,
+unit: *Unit,
+
+/// The generated source
+output: ArrayList(u8),
+/// The temporaries holding the values of hoisted nodes, keyed by the node's id
+temporaries: AutoHashMap(Node.Id, Name),
+temporary_count: usize,
+depth: usize,
+/// The return type of the function currently being generated
+return_type: Type,
+/// The instantiations of generic functions called by the program, each generated
+/// as a separate function
+instances: ArrayList(Instance),
+/// The index of the instance currently being generated
+instance: ?usize,
+/// The generic functions defined at the top level, keyed by name
+generics: std.StringHashMap(*const Node),
+/// The names of the functions defined at the top level, which are called directly
+functions: std.StringHashMap(void),
+/// The names of the extern functions declared at the top level, which are called
+/// by the names C knows them by
+externs: std.StringHashMap(void),
+/// The names of the parameters and bindings visible in the function being
+/// generated, which shadow the top level functions
+locals: ArrayList([]const u8),
+/// The bodies of the defers of the blocks being generated, which are written
+/// again wherever control leaves their block
+defers: ArrayList(*const Node),
+/// The index in defers of the first defer of the function being generated, those
+/// before it were deferred by the function a closure is created in
+function_defers: usize,
+/// The index in defers of the first defer within the innermost loop
+loop_defers: usize,
+/// The function types of the values the program uses, each represented by a
+/// struct holding a pointer to the function to call
+function_types: ArrayList(Type),
+/// The array types of the values the program uses, each represented by a struct
+/// holding the elements so arrays are copied like other values
+array_types: ArrayList(Type),
+/// The tuple types of the values the program uses, each represented by a struct
+/// with a field for each element
+tuple_types: ArrayList(Type),
+/// The union types of the values the program uses, each represented by a C union
+/// with the same fields
+union_types: ArrayList(Type),
+/// The structs of the array and tuple types and the unions of the union types in
+/// the order they are declared, the types of their elements first
+aggregates: ArrayList(Name),
+/// The structs and constructors of the closures generated so far
+closure_declarations: ArrayList(u8),
+/// The functions the closures generated so far call, written after main
+closure_definitions: ArrayList(u8),
+closure_count: usize,
+/// The top level functions used as values so far, each of which is given a
+/// struct to point to
+references: std.StringHashMap(void),
+/// The generic functions of the built in modules called so far and the types
+/// they were called with, a function is written for each
+builtins: ArrayList(Builtin),
+/// Maps the statements of the generated source to the source
+source_map: SourceMap,
+/// The mappings of the statements of the closures generated so far, relative to
+/// closure_definitions
+closure_mappings: SourceMap,
+
+allocator: Allocator,
+
+const C = @This();
+
+const log = std.log.scoped(.codegen);
+
+// Explicit so the mutually recursive generating functions can resolve their error sets
+const GenerateError = Allocator.Error;
+
+// Declared rather than included, so the names headers declare don't collide with bindings
+const prelude =
+    \\#include <stdarg.h>
+    \\#include <stdbool.h>
+    \\#include <stddef.h>
+    \\#include <stdint.h>
+    \\
+    \\void *malloc(size_t);
+    \\double pow(double, double);
+    \\float powf(float, float);
+    \\double fmod(double, double);
+    \\float fmodf(float, float);
+    \\int strcmp(const char *, const char *);
+    \\int printf(const char *, ...);
+    \\int dprintf(int, const char *, ...);
+    \\int vsnprintf(char *, size_t, const char *, va_list);
+    \\double sqrt(double);
+    \\double floor(double);
+    \\double ceil(double);
+    \\void exit(int);
+    \\
+    \\static void (*ruka_fail)(void) = NULL;
+    \\
+    \\static inline void ruka_assert(bool condition, const char *expression, const char *message, const char *location) {
+    \\    if (condition) return;
+    \\
+    \\    if (message) {
+    \\        dprintf(2, "%s: assertion failed: %s, %s\n", location, expression, message);
+    \\    } else {
+    \\        dprintf(2, "%s: assertion failed: %s\n", location, expression);
+    \\    }
+    \\
+    \\    if (ruka_fail) ruka_fail();
+    \\    exit(1);
+    \\}
+    \\
+    \\static inline const char *ruka_fmt(const char *format, ...) {
+    \\    va_list arguments;
+    \\    va_start(arguments, format);
+    \\    int length = vsnprintf(NULL, 0, format, arguments);
+    \\    va_end(arguments);
+    \\
+    \\    char *formatted = malloc(length + 1);
+    \\    va_start(arguments, format);
+    \\    vsnprintf(formatted, length + 1, format, arguments);
+    \\    va_end(arguments);
+    \\
+    \\    return formatted;
+    \\}
+    \\
+    \\static inline int64_t ruka_check_index(int64_t index, int64_t length, const char *location) {
+    \\    if (index < 0 || index >= length) {
+    \\        dprintf(2, "%s: index %lld is out of bounds for an array of length %lld\n", location, (long long)index, (long long)length);
+    \\        if (ruka_fail) ruka_fail();
+    \\        exit(1);
+    \\    }
+    \\
+    \\    return index;
+    \\}
+    \\
+    \\static inline int64_t ruka_ipow(int64_t base, int64_t exponent) {
+    \\    if (exponent < 0) {
+    \\        if (base == 1) return 1;
+    \\        if (base == -1) return exponent % 2 == 0 ? 1 : -1;
+    \\        return 0;
+    \\    }
+    \\
+    \\    uint64_t result = 1;
+    \\    uint64_t factor = (uint64_t)base;
+    \\    while (exponent > 0) {
+    \\        if (exponent & 1) result = result * factor;
+    \\        factor = factor * factor;
+    \\        exponent = exponent >> 1;
+    \\    }
+    \\
+    \\    return (int64_t)result;
+    \\}
+    \\
+    \\
+;
+
+// Written after the prelude when compiling tests. Failing asserts jump back to
+// the test's runner, which reports it rather than ending the program
+const test_prelude =
+    \\#include <setjmp.h>
+    \\
+    \\static jmp_buf ruka_test_jump;
+    \\
+    \\static void ruka_test_fail(void) {
+    \\    longjmp(ruka_test_jump, 1);
+    \\}
+    \\
+    \\static int ruka_run_test(const char *name, void (*test)(void)) {
+    \\    ruka_fail = ruka_test_fail;
+    \\    if (setjmp(ruka_test_jump) == 0) {
+    \\        test();
+    \\        printf("test %s ... ok\n", name);
+    \\        return 0;
+    \\    }
+    \\
+    \\    printf("test %s ... FAILED\n", name);
+    \\    return 1;
+    \\}
+    \\
+    \\
+;
+
+// Bindings with these names are prefixed, as they are C keywords or names the
+// generated code relies on
+const reserved = std.StaticStringMap(void).initComptime(.{
+    .{"auto"}, .{"break"}, .{"case"}, .{"char"}, .{"const"}, .{"continue"},
+    .{"default"}, .{"do"}, .{"double"}, .{"else"}, .{"enum"}, .{"extern"},
+    .{"float"}, .{"for"}, .{"goto"}, .{"if"}, .{"inline"}, .{"int"},
+    .{"long"}, .{"register"}, .{"restrict"}, .{"return"}, .{"short"}, .{"signed"},
+    .{"sizeof"}, .{"static"}, .{"struct"}, .{"switch"}, .{"typedef"}, .{"union"},
+    .{"unsigned"}, .{"void"}, .{"volatile"}, .{"while"}, .{"alignas"}, .{"alignof"},
+    .{"bool"}, .{"constexpr"}, .{"false"}, .{"nullptr"}, .{"static_assert"}, .{"thread_local"},
+    .{"true"}, .{"typeof"}, .{"typeof_unqual"}, .{"_Alignas"}, .{"_Alignof"}, .{"_Atomic"},
+    .{"_Bool"}, .{"_Complex"}, .{"_Generic"}, .{"_Imaginary"}, .{"_Noreturn"}, .{"_Static_assert"},
+    .{"_Thread_local"}, .{"main"}, .{"pow"}, .{"powf"}, .{"fmod"}, .{"fmodf"},
+    .{"strcmp"}, .{"malloc"}, .{"size_t"}, .{"printf"}, .{"dprintf"}, .{"sqrt"},
+    .{"floor"}, .{"ceil"}, .{"exit"}, .{"setjmp"}, .{"longjmp"}, .{"jmp_buf"},
+    .{"vsnprintf"}, .{"va_list"}, .{"va_start"}, .{"va_end"}
+});
+
+/// A name in the generated source. Names beginning with ruka_ are reserved
+/// for the generated code
+const Name = union(enum) {
+    binding: []const u8,
+    temporary: usize,
+    instance: struct {
+        name: []const u8,
+        index: usize
+    },
+    /// The struct representing values of a function type
+    function_type: usize,
+    /// The struct holding the elements of values of an array type
+    array_type: usize,
+    /// The struct holding the elements of values of a tuple type
+    tuple_type: usize,
+    /// The field of a tuple's struct holding one of its elements, which can't
+    /// collide with bindings so isn't prefixed
+    tuple_element: usize,
+    /// The C union representing values of a union type
+    union_type: usize,
+    /// The struct holding a closure's captured values
+    closure: usize,
+    /// The function a closure calls
+    closure_call: usize,
+    /// The struct values of a top level function's type point to
+    reference: []const u8,
+    /// The function the struct values of a top level function's type point to calls
+    reference_call: []const u8,
+    /// The function written for a generic function of a built in module
+    builtin: Builtin,
+
+    pub fn format(self: Name, comptime _: []const u8, _: std.fmt.FormatOptions, writer: anytype) !void {
+        switch (self) {
+            .binding => |name| {
+                if (reserved.has(name)) try writer.writeAll("ruka_");
+                try writer.writeAll(name);
+            },
+            .temporary => |index| try writer.print("ruka_tmp{}", .{index}),
+            .instance => |instance| try writer.print("ruka_{s}_{}", .{instance.name, instance.index}),
+            .function_type => |index| try writer.print("ruka_fn{}", .{index}),
+            .array_type => |index| try writer.print("ruka_array{}", .{index}),
+            .tuple_type => |index| try writer.print("ruka_tuple{}", .{index}),
+            .tuple_element => |index| try writer.print("f{}", .{index}),
+            .union_type => |index| try writer.print("ruka_union{}", .{index}),
+            .closure => |index| try writer.print("ruka_closure{}", .{index}),
+            .closure_call => |index| try writer.print("ruka_closure{}_call", .{index}),
+            .reference => |name| try writer.print("ruka_ref_{s}", .{name}),
+            .reference_call => |name| try writer.print("ruka_ref_{s}_call", .{name}),
+            .builtin => |builtin| try writer.print("ruka_{s}_{s}_{}", .{
+                builtin.function.module,
+                builtin.function.name,
+                builtin.@"type"
+            })
+        }
+    }
+};
+
+/// A generic function and the types substituted for its type parameters
+const Instance = struct {
+    node: *const Node,
+    arguments: []const Type
+};
+
+/// A generic function of a built in module and the type it is called with
+const Builtin = struct {
+    function: *const stdlib.Function,
+    @"type": Type
+};
+
+/// Where the value of a statement is stored
+const Destination = union(enum) {
+    discard,
+    assign: Name,
+    /// Returned from the current function
+    @"return"
+};
+
+pub fn init(unit: *Unit, ast: *Ast, types: *const TypeTable) !*C {
+    const generator = try unit.allocator.create(C);
+
+    generator.* = .{
+        .ast = ast,
+        .types = types,
+        .unit = unit,
+        .output = .init(unit.allocator),
+        .temporaries = .init(unit.allocator),
+        .temporary_count = 0,
+        .depth = 0,
+        .return_type = .unit,
+        .instances = .init(unit.allocator),
+        .instance = null,
+        .generics = .init(unit.allocator),
+        .functions = .init(unit.allocator),
+        .externs = .init(unit.allocator),
+        .locals = .init(unit.allocator),
+        .defers = .init(unit.allocator),
+        .function_defers = 0,
+        .loop_defers = 0,
+        .function_types = .init(unit.allocator),
+        .array_types = .init(unit.allocator),
+        .tuple_types = .init(unit.allocator),
+        .union_types = .init(unit.allocator),
+        .aggregates = .init(unit.allocator),
+        .closure_declarations = .init(unit.allocator),
+        .closure_definitions = .init(unit.allocator),
+        .closure_count = 0,
+        .references = .init(unit.allocator),
+        .builtins = .init(unit.allocator),
+        .source_map = .init(unit.allocator, unit.output),
+        .closure_mappings = .init(unit.allocator, unit.output),
+        .allocator = unit.allocator
+    };
+
+    return generator;
+}
+
+pub fn deinit(self: *C) void {
+    self.output.deinit();
+    self.temporaries.deinit();
+    for (self.instances.items) |instance| self.allocator.free(instance.arguments);
+    self.instances.deinit();
+    self.generics.deinit();
+    self.functions.deinit();
+    self.externs.deinit();
+    self.locals.deinit();
+    self.defers.deinit();
+    self.function_types.deinit();
+    self.array_types.deinit();
+    self.tuple_types.deinit();
+    self.union_types.deinit();
+    self.aggregates.deinit();
+    self.closure_declarations.deinit();
+    self.closure_definitions.deinit();
+    self.references.deinit();
+    self.builtins.deinit();
+    self.source_map.deinit();
+    self.closure_mappings.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Generates the C source of the ast, the caller owns the returned memory.
+/// Generation continues past constructs C can't represent so all of them are
+/// recorded in the unit, but fails if any were encountered
+pub fn generate(self: *C) ![]u8 {
+    const error_count = self.unit.errorCount();
+
+    try self.generateModule(self.ast.root.kind.module);
+
+    // The function types and closures the program uses are only known once the
+    // rest of it is generated
+    var declarations = ArrayList(u8).init(self.allocator);
+    defer declarations.deinit();
+
+    try self.writeDeclarations(&declarations);
+    try self.output.insertSlice(prelude.len, declarations.items);
+    self.source_map.shift(0, declarations.items.len);
+
+    self.closure_mappings.shift(0, self.output.items.len);
+    try self.source_map.mappings.appendSlice(self.closure_mappings.mappings.items);
+    try self.output.appendSlice(self.closure_definitions.items);
+
+    const codegen_errors = self.unit.errorCount() - error_count;
+    if (codegen_errors > 0) {
+        log.err("{s}: code generation failed with {} error(s)", .{self.unit.input, codegen_errors});
+        return error.CodegenFailed;
+    }
+
+    return try self.output.toOwnedSlice();
+}
+
+fn write(self: *C, bytes: []const u8) GenerateError!void {
+    try self.output.appendSlice(bytes);
+}
+
+fn print(self: *C, comptime fmt: []const u8, args: anytype) GenerateError!void {
+    try self.output.writer().print(fmt, args);
+}
+
+fn indent(self: *C) GenerateError!void {
+    try self.output.appendNTimes(' ', self.depth * 4);
+}
+
+fn unsupported(self: *C, span: Span, comptime construct: []const u8) GenerateError!void {
+    try self.unit.createError(construct ++ " are not supported by the c backend", span);
+}
+
+fn unsupportedType(self: *C, span: Span, @"type": Type) GenerateError!void {
+    try self.unit.createErrorFmt(span, "values of type {} are not supported by the c backend", .{@"type"});
+}
+
+fn typeOf(self: *const C, node: *const Node) Type {
+    return self.concrete(self.types.get(node.id) orelse .invalid);
+}
+
+// Substitutes the arguments of the instance being generated for its type parameters
+fn concrete(self: *const C, @"type": Type) Type {
+    const name = switch (@"type") {
+        .parameter => |name| name,
+        else => return @"type"
+    };
+    const instance = self.instances.items[self.instance orelse return @"type"];
+
+    for (instance.node.kind.fn_def.type_params, instance.arguments) |type_param, argument| {
+        if (std.mem.eql(u8, type_param, name)) return argument;
+    }
+
+    return @"type";
+}
+
+fn bindingType(self: *const C, node: *const Node, binding: Node.Binding) Type {
+    if (binding.annotation) |annotation| return self.typeOf(annotation);
+    if (binding.value) |value| return self.typeOf(value);
+
+    // Inferred from the values later assigned to the binding
+    return self.concrete(self.types.getBinding(node.id) orelse .invalid);
+}
+
+fn isMain(fn_def: Node.FunctionDef) bool {
+    return std.mem.eql(u8, fn_def.name, "main");
+}
+
+fn isExpression(node: *const Node) bool {
+    return switch (node.kind) {
+        .prefix, .infix, .fn_call, .array_literal, .index, .tuple_literal, .tuple_index,
+        .struct_literal, .field_access, .reference, .dereference, .sizeof, .cast, .closure,
+        .@"comptime", .identifier, .literal => true,
+        else => false
+    };
+}
+
+// Whether evaluating an expression requires statements, which must be hoisted out of it
+fn containsStatement(node: *const Node) bool {
+    return switch (node.kind) {
+        .prefix => |prefix| containsStatement(prefix.operand),
+        .infix => |infix| containsStatement(infix.lhs) or containsStatement(infix.rhs),
+        .fn_call => |call| {
+            if (containsStatement(call.callee)) return true;
+            for (call.arguments) |argument| if (containsStatement(argument)) return true;
+
+            return false;
+        },
+        .array_literal, .tuple_literal => |elements| {
+            for (elements) |element| if (containsStatement(element)) return true;
+            return false;
+        },
+        .index => |index| containsStatement(index.object) or containsStatement(index.index),
+        .tuple_index => |access| containsStatement(access.object),
+        .struct_literal => |literal| {
+            for (literal.fields) |field| if (containsStatement(field.value)) return true;
+            return false;
+        },
+        .field_access => |access| containsStatement(access.object),
+        .reference, .dereference => |operand| containsStatement(operand),
+        .cast => |cast| containsStatement(cast.value),
+        // The bodies of closures are generated as separate functions, and those of
+        // comptime blocks are replaced by their values once checked
+        .sizeof, .closure, .@"comptime", .identifier, .literal => false,
+        else => true
+    };
+}
+
+// The C spelling of a type, null for types C can't represent
+fn cType(@"type": Type) ?[]const u8 {
+    return switch (@"type") {
+        .@"i8" => "int8_t",
+        .@"i16" => "int16_t",
+        .@"i32" => "int32_t",
+        .@"i64" => "int64_t",
+        .@"f32" => "float",
+        .@"f64" => "double",
+        .@"bool" => "bool",
+        .str => "const char *",
+        .char => "char",
+        .unit => "void",
+        .function, .range, .array, .slice, .reference, .tuple, .@"struct", .@"enum", .trait, .future, .parameter, .variable, .invalid => null
+    };
+}
+
+// Whether C can represent values of type. Functions are represented by pointers
+// to a struct holding the function to call
+fn isRepresentable(@"type": Type) bool {
+    return switch (@"type") {
+        .function => |function| {
+            if (function.type_params.len > 0) return false;
+            for (function.params) |param| if (param == .unit or !isRepresentable(param)) return false;
+
+            return isRepresentable(function.ret.*);
+        },
+        // C has no empty arrays
+        .array => |array| array.len > 0 and array.element.* != .unit and isRepresentable(array.element.*),
+        .tuple => |elements| {
+            for (elements) |element| if (element == .unit or !isRepresentable(element)) return false;
+            return true;
+        },
+        // References are represented by pointers
+        .reference => |element| element.* != .unit and isRepresentable(element.*),
+        // Only unions, whose fields C lays out as the checker sizes them
+        .@"struct" => |structure| {
+            if (!structure.overlaid or structure.fields.len == 0) return false;
+            for (structure.fields) |field| if (field.@"type" == .unit or !isRepresentable(field.@"type")) return false;
+
+            return true;
+        },
+        else => cType(@"type") != null
+    };
+}
+
+// Writes the C spelling of type, returning false if C can't represent it
+fn writeType(self: *C, @"type": Type) GenerateError!bool {
+    if (!isRepresentable(@"type")) return false;
+
+    switch (@"type") {
+        .function => try self.print("{} *", .{Name{ .function_type = try self.functionTypeIndex(@"type") }}),
+        .array => try self.print("{}", .{Name{ .array_type = try self.arrayTypeIndex(@"type") }}),
+        .tuple => try self.print("{}", .{Name{ .tuple_type = try self.tupleTypeIndex(@"type") }}),
+        .@"struct" => try self.print("{}", .{Name{ .union_type = try self.unionTypeIndex(@"type") }}),
+        .reference => |element| {
+            _ = try self.writeType(element.*);
+            try self.write(" *");
+        },
+        else => try self.write(cType(@"type").?)
+    }
+
+    return true;
+}
+
+// Writes a declaration of name, returning false if C can't represent its type
+fn writeDeclaration(self: *C, @"type": Type, name: Name) GenerateError!bool {
+    if (!try self.writeType(@"type")) return false;
+
+    // Pointer declarators are written against the name
+    if (self.output.items[self.output.items.len - 1] != '*') try self.write(" ");
+    try self.print("{}", .{name});
+
+    return true;
+}
+
+fn declare(self: *C, @"type": Type, name: Name, span: Span) GenerateError!void {
+    if (!try self.writeDeclaration(@"type", name)) try self.unsupportedType(span, @"type");
+}
+
+fn writeSignature(self: *C, node: *const Node, fn_def: Node.FunctionDef, name: Name) GenerateError!void {
+    const function = self.typeOf(node).function;
+
+    _ = try self.writeDeclaration(self.concrete(function.ret.*), name);
+    try self.write("(");
+    if (fn_def.params.len == 0) try self.write("void");
+    try self.writeParams(function, fn_def.params);
+    try self.write(")");
+}
+
+// `extern R name(T a, ...);`, the declared types are trusted so they are written as given
+fn writeExtern(self: *C, node: *const Node, extern_fn: Node.ExternFn) GenerateError!void {
+    const function = self.typeOf(node).function;
+
+    for (extern_fn.params, function.params) |param, param_type| {
+        if (param_type == .unit or !isRepresentable(param_type)) try self.unsupportedType(param.span, param_type);
+    }
+    if (!isRepresentable(function.ret.*)) try self.unsupportedType(node.span, function.ret.*);
+
+    try self.write("extern ");
+    _ = try self.writeDeclaration(function.ret.*, .{ .binding = extern_fn.name });
+    try self.write("(");
+    if (extern_fn.params.len == 0 and !extern_fn.variadic) try self.write("void");
+    try self.writeParams(function, extern_fn.params);
+    if (extern_fn.variadic) try self.write(if (extern_fn.params.len > 0) ", ..." else "...");
+    try self.write(");\n");
+}
+
+fn writeParams(self: *C, function: Type.Function, params: []const Node.Parameter) GenerateError!void {
+    for (params, function.params, 0..) |param, param_type, i| {
+        if (i > 0) try self.write(", ");
+        _ = try self.writeDeclaration(self.concrete(param_type), .{ .binding = param.name });
+    }
+}
+
+// Reports the parameter and return types of a function C can't represent
+fn checkSignature(self: *C, node: *const Node, fn_def: Node.FunctionDef) GenerateError!void {
+    if (fn_def.@"async") return try self.unsupported(node.span, "async functions");
+
+    const function = self.typeOf(node).function;
+
+    for (fn_def.params, function.params) |param, declared| {
+        const param_type = self.concrete(declared);
+        if (param_type == .unit or !isRepresentable(param_type)) try self.unsupportedType(param.span, param_type);
+    }
+
+    const ret = self.concrete(function.ret.*);
+    if (!isRepresentable(ret)) try self.unsupportedType(node.span, ret);
+}
+
+fn instanceName(self: *const C, index: usize) Name {
+    return .{ .instance = .{
+        .name = self.instances.items[index].node.kind.fn_def.name,
+        .index = index
+    }};
+}
+
+// The generic function defined at the top level which callee names, if it names one
+fn genericCallee(self: *const C, callee: *const Node) ?*const Node {
+    return switch (callee.kind) {
+        .identifier => |name| self.generics.get(name),
+        else => null
+    };
+}
+
+// The types the type checker inferred for the type parameters of the function
+// called by node, with those of the instance being generated substituted.
+// The caller owns the returned memory
+fn concreteArguments(self: *const C, type_arguments: []const Type) GenerateError![]Type {
+    const arguments = try self.allocator.alloc(Type, type_arguments.len);
+    for (arguments, type_arguments) |*argument, type_argument| argument.* = self.concrete(type_argument);
+
+    return arguments;
+}
+
+// The first of arguments which can't be passed to a function in C
+fn unrepresentable(arguments: []const Type) ?Type {
+    for (arguments) |argument| {
+        if (argument == .unit or !isRepresentable(argument)) return argument;
+    }
+
+    return null;
+}
+
+// The index of the instance of definition with arguments, which is added if
+// it hasn't been already
+fn instanceOf(self: *C, definition: *const Node, arguments: []const Type) GenerateError!usize {
+    search: for (self.instances.items, 0..) |instance, i| {
+        if (instance.node != definition) continue;
+        for (instance.arguments, arguments) |a, b| if (!a.eql(b)) continue :search;
+
+        return i;
+    }
+
+    try self.instances.ensureUnusedCapacity(1);
+    self.instances.appendAssumeCapacity(.{
+        .node = definition,
+        .arguments = try self.allocator.dupe(Type, arguments)
+    });
+
+    return self.instances.items.len - 1;
+}
+
+// Adds the instance called by node if C can represent it, those it can't are
+// reported when the call is generated
+fn collectInstance(self: *C, node: *const Node, callee: *const Node) GenerateError!void {
+    const type_arguments = self.types.getInstantiation(node.id) orelse return;
+    const definition = self.genericCallee(callee) orelse return;
+
+    const arguments = try self.concreteArguments(type_arguments);
+    defer self.allocator.free(arguments);
+
+    if (unrepresentable(arguments) == null) _ = try self.instanceOf(definition, arguments);
+}
+
+// Finds the instances of generic functions the program calls, including those
+// called from within other instances
+fn collectInstances(self: *C) GenerateError!void {
+    var collector: InstanceCollector = .{ .generator = self };
+    try InstanceCollector.Walk.visit(&collector, self.ast.root);
+
+    // Instances found while walking are appended, so are walked in turn until
+    // none are left. Calls which recurse with the same arguments find their own instance
+    var i: usize = 0;
+    while (i < self.instances.items.len) : (i = i + 1) {
+        self.instance = i;
+        try InstanceCollector.Walk.visit(&collector, self.instances.items[i].node.kind.fn_def.body);
+    }
+
+    self.instance = null;
+}
+
+const InstanceCollector = struct {
+    generator: *C,
+
+    const Walk = Visitor(InstanceCollector, GenerateError, void);
+
+    // Generic functions are only walked as the instances the program calls
+    pub fn visitFnDef(self: *InstanceCollector, node: *const Node, fn_def: Node.FunctionDef) GenerateError!void {
+        if (fn_def.type_params.len == 0) try Walk.visitChildren(self, node);
+    }
+
+    pub fn visitFnCall(self: *InstanceCollector, node: *const Node, call: Node.Call) GenerateError!void {
+        try Walk.visitChildren(self, node);
+        try self.generator.collectInstance(node, call.callee);
+    }
+
+    pub fn visitInfix(self: *InstanceCollector, node: *const Node, infix: Node.Infix) GenerateError!void {
+        try Walk.visitChildren(self, node);
+        switch (infix.operator) {
+            .forward_app => try self.generator.collectInstance(node, infix.lhs),
+            .reverse_app => try self.generator.collectInstance(node, infix.rhs),
+            else => {}
+        }
+    }
+};
+
+// C can't declare a name twice in the same scope, so bindings which rebind a
+// name bound earlier in their block aren't supported
+fn checkRebindings(self: *C, statements: []const *Node) GenerateError!void {
+    var names = std.StringHashMap(void).init(self.allocator);
+    defer names.deinit();
+
+    for (statements) |exported| {
+        const statement = exported.unwrapExport();
+        switch (statement.kind) {
+            .var_decl, .const_decl => |binding| {
+                const entry = try names.getOrPut(binding.name);
+                if (entry.found_existing) try self.unsupported(statement.span, "bindings redefined in the same scope");
+            },
+            else => {}
+        }
+    }
+}
+
+fn generateModule(self: *C, module: Node.Module) GenerateError!void {
+    try self.write(prelude);
+    if (self.unit.options.tests) try self.write(test_prelude);
+    try self.checkRebindings(module.items);
+
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def => |fn_def| {
+                try self.functions.put(fn_def.name, {});
+                if (fn_def.type_params.len > 0) try self.generics.put(fn_def.name, item);
+            },
+            .extern_fn => |extern_fn| try self.externs.put(extern_fn.name, {}),
+            else => {}
+        }
+    }
+    try self.collectInstances();
+
+    // The prelude already declares the C functions the generated code relies on,
+    // so externs of those aren't declared again
+    var externs: usize = 0;
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .extern_fn => |extern_fn| if (!reserved.has(extern_fn.name)) {
+                try self.writeExtern(item, extern_fn);
+                externs = externs + 1;
+            },
+            else => {}
+        }
+    }
+    if (externs > 0) try self.write("\n");
+
+    // Top level bindings become globals, so functions can refer to them
+    var globals: usize = 0;
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .var_decl, .const_decl => |binding| {
+                const @"type" = self.bindingType(item, binding);
+                if (@"type" == .unit) continue;
+
+                try self.write("static ");
+                try self.declare(@"type", .{ .binding = binding.name }, item.span);
+                try self.write(";\n");
+                globals = globals + 1;
+            },
+            else => {}
+        }
+    }
+    if (globals > 0) try self.write("\n");
+
+    // Prototypes let functions be called before they are defined
+    var main: ?*const Node = null;
+    var test_functions = ArrayList(*const Node).init(self.allocator);
+    defer test_functions.deinit();
+
+    var functions: usize = 0;
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def => |fn_def| {
+                if (exported.hasAttribute("test")) try test_functions.append(item);
+
+                // Only the instances of generic functions are generated
+                if (fn_def.type_params.len > 0) continue;
+                if (isMain(fn_def)) main = item;
+
+                try self.writeSignature(item, fn_def, .{ .binding = fn_def.name });
+                try self.write(";\n");
+                functions = functions + 1;
+            },
+            else => {}
+        }
+    }
+    for (self.instances.items, 0..) |instance, i| {
+        self.instance = i;
+        try self.writeSignature(instance.node, instance.node.kind.fn_def, self.instanceName(i));
+        try self.write(";\n");
+        functions = functions + 1;
+    }
+    self.instance = null;
+    if (functions > 0) try self.write("\n");
+
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def => |fn_def| if (fn_def.type_params.len == 0) {
+                try self.generateFunction(item, fn_def, .{ .binding = fn_def.name });
+            },
+            else => {}
+        }
+    }
+    for (self.instances.items, 0..) |instance, i| {
+        self.instance = i;
+        try self.generateFunction(instance.node, instance.node.kind.fn_def, self.instanceName(i));
+    }
+    self.instance = null;
+
+    try self.generateMain(module, main, test_functions.items);
+}
+
+// C's main assigns the top level bindings in order, then calls the program's
+// main function if it has one, or runs the tests when compiling them
+fn generateMain(self: *C, module: Node.Module, main: ?*const Node, test_functions: []const *const Node) GenerateError!void {
+    try self.write("int main(void) {\n");
+    self.depth = 1;
+    defer self.depth = 0;
+
+    self.return_type = .unit;
+    self.locals.clearRetainingCapacity();
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def, .extern_fn, .import => {},
+            .var_decl, .const_decl => |binding| {
+                const value = binding.value orelse continue;
+
+                try self.generateMapped(item.span, value, if (self.bindingType(item, binding) == .unit) .discard
+                    else .{ .assign = .{ .binding = binding.name } });
+            },
+            else => try self.generateMapped(item.span, item, .discard)
+        }
+    }
+
+    try self.indent();
+    if (self.unit.options.tests) {
+        try self.generateTestRuns(test_functions);
+    } else if (main) |node| {
+        const function = self.typeOf(node).function;
+        if (function.params.len > 0 or !(function.ret.* == .unit or function.ret.isInteger())) {
+            try self.unit.createError("main must take no parameters and return () or an integer", node.span);
+        }
+
+        const entry: Name = .{ .binding = node.kind.fn_def.name };
+        if (function.ret.* == .unit) {
+            try self.print("{}();\n", .{entry});
+            try self.indent();
+            try self.write("return 0;\n");
+        } else {
+            try self.print("return {}();\n", .{entry});
+        }
+    } else {
+        try self.write("return 0;\n");
+    }
+
+    try self.write("}\n");
+}
+
+// Runs each test in order, printing whether it passed, then a summary. The
+// program fails if any test did
+fn generateTestRuns(self: *C, test_functions: []const *const Node) GenerateError!void {
+    try self.write("int ruka_failed = 0;\n");
+    for (test_functions) |node| {
+        const fn_def = node.kind.fn_def;
+        const function = self.typeOf(node).function;
+        if (fn_def.type_params.len > 0 or function.params.len > 0 or function.ret.* != .unit) {
+            try self.unit.createError("tests must take no parameters or type parameters and return ()", node.span);
+            continue;
+        }
+
+        try self.indent();
+        try self.write("ruka_failed = ruka_failed + ruka_run_test(");
+        try self.writeQuoted(fn_def.name);
+        try self.print(", {});\n", .{Name{ .binding = fn_def.name }});
+    }
+
+    try self.indent();
+    try self.print(
+        "printf(\"\\ntest result: %s. %d passed; %d failed\\n\", ruka_failed > 0 ? \"FAILED\" : \"ok\", {} - ruka_failed, ruka_failed);\n",
+        .{test_functions.len}
+    );
+    try self.indent();
+    try self.write("return ruka_failed > 0;\n");
+}
+
+fn generateFunction(self: *C, node: *const Node, fn_def: Node.FunctionDef, name: Name) GenerateError!void {
+    try self.checkSignature(node, fn_def);
+
+    // The instances of a generic function share its nodes, so temporaries are
+    // only kept for the function they were created in
+    self.temporaries.clearRetainingCapacity();
+    self.return_type = self.concrete(self.typeOf(node).function.ret.*);
+
+    self.locals.clearRetainingCapacity();
+    for (fn_def.params) |param| try self.locals.append(param.name);
+
+    self.defers.clearRetainingCapacity();
+    self.function_defers = 0;
+    self.loop_defers = 0;
+
+    try self.writeSignature(node, fn_def, name);
+    try self.write(" ");
+    try self.generateBlock(fn_def.body, if (self.return_type == .unit) .discard else .@"return");
+    try self.write("\n\n");
+}
+
+// Writes node as a braced block, without ending the line of the closing brace
+fn generateBlock(self: *C, node: *const Node, dest: Destination) GenerateError!void {
+    try self.write("{\n");
+    self.depth = self.depth + 1;
+    try self.generateStatements(node, dest);
+    self.depth = self.depth - 1;
+    try self.indent();
+    try self.write("}");
+}
+
+// Writes the statements of a block, or node itself if it isn't one. The last
+// statement produces the block's value
+fn generateStatements(self: *C, node: *const Node, dest: Destination) GenerateError!void {
+    const statements = switch (node.kind) {
+        .block => |block| block.statements,
+        else => return try self.generateStatement(node, dest)
+    };
+
+    try self.checkRebindings(statements);
+
+    // The bindings of a block are only visible within it
+    const locals = self.locals.items.len;
+    defer self.locals.shrinkRetainingCapacity(locals);
+
+    const defers = self.defers.items.len;
+    defer self.defers.shrinkRetainingCapacity(defers);
+
+    for (statements, 0..) |statement, i| {
+        try self.generateMapped(statement.span, statement, if (i + 1 == statements.len) dest else .discard);
+    }
+
+    // Blocks left by a return, break or continue ran their defers as they left
+    const left = dest == .@"return" or (statements.len > 0 and switch (statements[statements.len - 1].kind) {
+        .@"return", .@"break", .@"continue", .goto => true,
+        else => false
+    });
+    if (!left) try self.generateDefers(defers);
+}
+
+// Writes the bodies deferred after mark, the last deferred first, as control
+// leaves the blocks they were deferred in
+fn generateDefers(self: *C, mark: usize) GenerateError!void {
+    var i = self.defers.items.len;
+    while (i > mark) {
+        i = i - 1;
+
+        const body = self.defers.items[i];
+        try self.generateMapped(body.span, body, .discard);
+    }
+}
+
+// Generates a statement, mapping the code generated for it to span
+fn generateMapped(self: *C, span: Span, node: *const Node, dest: Destination) GenerateError!void {
+    try self.writeLine(span);
+
+    const index = self.source_map.mappings.items.len;
+    try self.source_map.mappings.append(.{
+        .gen_start = self.output.items.len,
+        .gen_end = self.output.items.len,
+        .orig_span = span
+    });
+
+    try self.generateStatement(node, dest);
+    self.source_map.mappings.items[index].gen_end = self.output.items.len;
+}
+
+// Maps the lines following to the line span starts on, so debuggers step through
+// the source rather than the generated c. Nodes built without a position have
+// no line to map to
+fn writeLine(self: *C, span: Span) GenerateError!void {
+    if (!self.unit.options.debug_info or span.pos.line == 0) return;
+
+    try self.print("#line {} \"", .{span.pos.line});
+    for (span.file) |c| {
+        if (c == '"' or c == '\\') try self.output.append('\\');
+        try self.output.append(c);
+    }
+    try self.write("\"\n");
+}
+
+/// Writes node as whole lines of statements, storing its value in dest
+fn generateStatement(self: *C, node: *const Node, dest: Destination) GenerateError!void {
+    switch (node.kind) {
+        // Imports and exports are only valid at the top level, which the checker ensures
+        .module, .import, .@"export" => unreachable,
+        // Only written in annotations, which aren't generated as statements
+        .type_expr => unreachable,
+        .fn_def => try self.unsupported(node.span, "nested functions"),
+        .struct_def, .impl => try self.unsupported(node.span, "structs"),
+        .trait_def => try self.unsupported(node.span, "traits"),
+        .@"await" => try self.unsupported(node.span, "async functions"),
+        // Declared with the types of the program's values, as they are used
+        .union_def => {},
+        .enum_def, .variant_literal => try self.unsupported(node.span, "enums"),
+        .@"try" => try self.unsupported(node.span, "? operators"),
+        .destructure => try self.unsupported(node.span, "destructuring bindings"),
+        .var_decl, .const_decl => |binding| {
+            try self.generateBinding(node, binding);
+            try self.locals.append(binding.name);
+        },
+        .block => {
+            try self.indent();
+            try self.generateBlock(node, dest);
+            try self.write("\n");
+        },
+        .@"if" => |conditional| {
+            try self.hoist(conditional.condition);
+            try self.indent();
+            try self.generateIf(conditional, dest);
+            try self.write("\n");
+        },
+        .@"while" => |loop| try self.generateWhile(loop),
+        .@"for" => |loop| try self.generateFor(loop),
+        .match => try self.unsupported(node.span, "match expressions"),
+        .@"return" => |value| try self.generateReturn(value),
+        .@"break" => |value| try self.generateBreak(value),
+        .@"continue" => {
+            try self.generateDefers(self.loop_defers);
+            try self.indent();
+            try self.write("continue;\n");
+        },
+        .@"defer" => |body| try self.defers.append(body),
+        // Labels are statements of their own, as C labels must precede one
+        .label => |name| {
+            try self.indent();
+            try self.print("{}: ;\n", .{Name{ .binding = name }});
+        },
+        // The checker ensures gotos aren't written in functions with defers
+        .goto => |target| {
+            try self.indent();
+            try self.print("goto {};\n", .{Name{ .binding = target }});
+        },
+        // Declared before the functions of the program
+        .extern_fn => {},
+        .@"unsafe" => |body| try self.generateStatement(body, dest),
+        .assignment => |assignment| try self.generateAssignment(assignment),
+        .prefix, .infix, .fn_call, .array_literal, .index, .tuple_literal, .tuple_index,
+        .struct_literal, .field_access, .reference, .dereference, .sizeof, .cast, .closure,
+        .@"comptime", .identifier, .literal => switch (dest) {
+            .discard => try self.generateDiscard(node),
+            .assign => |name| {
+                try self.hoist(node);
+                try self.indent();
+                try self.print("{} = ", .{name});
+                try self.generateUnwrapped(node);
+                try self.write(";\n");
+            },
+            .@"return" => {
+                try self.hoist(node);
+                if (self.defers.items.len == self.function_defers) {
+                    try self.indent();
+                    try self.write("return ");
+                    try self.generateUnwrapped(node);
+                    return try self.write(";\n");
+                }
+
+                // The value is computed before the defers run, which may change what it's computed from
+                const temporary = self.createTemporary();
+                try self.indent();
+                try self.declare(self.return_type, temporary, node.span);
+                try self.write(" = ");
+                try self.generateUnwrapped(node);
+                try self.write(";\n");
+
+                try self.generateDefers(self.function_defers);
+                try self.indent();
+                try self.print("return {};\n", .{temporary});
+            }
+        }
+    }
+}
+
+// Evaluates an expression for its effects alone
+fn generateDiscard(self: *C, node: *const Node) GenerateError!void {
+    if (node.kind == .identifier or node.kind == .literal) return;
+
+    try self.hoist(node);
+    try self.indent();
+
+    // Cast so C doesn't warn about the unused value
+    if (node.kind != .fn_call and self.typeOf(node) != .unit) {
+        try self.write("(void)");
+        try self.generateExpression(node);
+    } else {
+        try self.generateUnwrapped(node);
+    }
+
+    try self.write(";\n");
+}
+
+fn generateBinding(self: *C, node: *const Node, binding: Node.Binding) GenerateError!void {
+    const @"type" = self.bindingType(node, binding);
+    const name: Name = .{ .binding = binding.name };
+
+    // Unit values have no representation, only their effects are kept
+    if (@"type" == .unit) {
+        if (binding.value) |value| try self.generateStatement(value, .discard);
+        return;
+    }
+
+    if (binding.value) |value| {
+        if (isExpression(value)) {
+            try self.hoist(value);
+            try self.indent();
+            try self.declare(@"type", name, node.span);
+            try self.write(" = ");
+            try self.generateUnwrapped(value);
+            return try self.write(";\n");
+        }
+    }
+
+    try self.indent();
+    try self.declare(@"type", name, node.span);
+    try self.write(";\n");
+
+    if (binding.value) |value| try self.generateStatement(value, .{ .assign = name });
+}
+
+fn generateIf(self: *C, conditional: Node.If, dest: Destination) GenerateError!void {
+    try self.write("if (");
+    try self.generateUnwrapped(conditional.condition);
+    try self.write(") ");
+    try self.generateBlock(conditional.then_block, dest);
+
+    const else_block = conditional.else_block orelse return;
+    try self.write(" else ");
+
+    // Chains of else ifs stay flat, unless a condition has statements to hoist
+    switch (else_block.kind) {
+        .@"if" => |chained| if (!containsStatement(chained.condition)) {
+            return try self.generateIf(chained, dest);
+        },
+        else => {}
+    }
+
+    try self.generateBlock(else_block, dest);
+}
+
+fn generateWhile(self: *C, loop: Node.While) GenerateError!void {
+    // Breaking or continuing the loop runs the defers of the blocks within it
+    const loop_defers = self.loop_defers;
+    self.loop_defers = self.defers.items.len;
+    defer self.loop_defers = loop_defers;
+
+    try self.indent();
+
+    if (!containsStatement(loop.condition)) {
+        try self.write("while (");
+        try self.generateUnwrapped(loop.condition);
+        try self.write(") ");
+        try self.generateBlock(loop.body, .discard);
+        return try self.write("\n");
+    }
+
+    // The condition's statements must run before every iteration
+    try self.write("while (true) {\n");
+    self.depth = self.depth + 1;
+
+    try self.hoist(loop.condition);
+    try self.indent();
+    try self.write("if (!");
+    try self.generateExpression(loop.condition);
+    try self.write(") break;\n");
+    try self.generateStatements(loop.body, .discard);
+
+    self.depth = self.depth - 1;
+    try self.indent();
+    try self.write("}\n");
+}
+
+fn generateFor(self: *C, loop: Node.For) GenerateError!void {
+    const range: ?Node.Infix = switch (loop.iterable.kind) {
+        .infix => |infix| switch (infix.operator) {
+            .range_exc, .range_inc => infix,
+            else => null
+        },
+        else => null
+    };
+    const bounds = range orelse return try self.unsupported(loop.iterable.span, "iterables other than ranges");
+
+    const loop_defers = self.loop_defers;
+    self.loop_defers = self.defers.items.len;
+    defer self.loop_defers = loop_defers;
+
+    try self.hoist(bounds.lhs);
+    try self.hoist(bounds.rhs);
+
+    const variable: Name = .{ .binding = loop.variable };
+    // The end of the range is only evaluated once
+    const end = self.createTemporary();
+
+    try self.indent();
+    try self.write("for (");
+    try self.declare(self.typeOf(loop.iterable).range.*, variable, loop.iterable.span);
+    try self.write(" = ");
+    try self.generateUnwrapped(bounds.lhs);
+    try self.print(", {} = ", .{end});
+    try self.generateUnwrapped(bounds.rhs);
+    try self.print("; {} {s} {}; {} = {} + 1) ", .{
+        variable,
+        if (bounds.operator == .range_inc) "<=" else "<",
+        end,
+        variable,
+        variable
+    });
+    try self.generateBlock(loop.body, .discard);
+    try self.write("\n");
+}
+
+fn generateReturn(self: *C, value: ?*Node) GenerateError!void {
+    if (self.return_type != .unit) return try self.generateStatement(value.?, .@"return");
+
+    if (value) |v| try self.generateStatement(v, .discard);
+    try self.generateDefers(self.function_defers);
+    try self.indent();
+    try self.write("return;\n");
+}
+
+// Loops evaluate to (), so the value is only evaluated for its effects
+fn generateBreak(self: *C, value: ?*Node) GenerateError!void {
+    if (value) |v| try self.generateStatement(v, .discard);
+    try self.generateDefers(self.loop_defers);
+    try self.indent();
+    try self.write("break;\n");
+}
+
+fn generateAssignment(self: *C, assignment: Node.Assignment) GenerateError!void {
+    if (self.typeOf(assignment.lhs) == .unit) return try self.generateStatement(assignment.rhs, .discard);
+
+    // Assignments through references store the value where the reference points
+    if (assignment.lhs.kind == .dereference) {
+        try self.hoist(assignment.lhs);
+        try self.hoist(assignment.rhs);
+        try self.indent();
+        try self.generateUnwrapped(assignment.lhs);
+        try self.write(" = ");
+        try self.generateUnwrapped(assignment.rhs);
+        return try self.write(";\n");
+    }
+
+    try self.generateStatement(assignment.rhs, .{ .assign = .{ .binding = assignment.lhs.kind.identifier } });
+}
+
+fn createTemporary(self: *C) Name {
+    defer self.temporary_count = self.temporary_count + 1;
+    return .{ .temporary = self.temporary_count };
+}
+
+// Writes the statements nested within an expression ahead of it, storing their
+// values in temporaries the expression refers to in their place
+fn hoist(self: *C, node: *const Node) GenerateError!void {
+    switch (node.kind) {
+        .prefix => |prefix| try self.hoist(prefix.operand),
+        .infix => |infix| {
+            const short_circuits = infix.operator == .@"and" or infix.operator == .@"or";
+            if (short_circuits and containsStatement(infix.rhs)) return try self.hoistShortCircuit(node, infix);
+
+            try self.hoist(infix.lhs);
+            try self.hoist(infix.rhs);
+        },
+        .fn_call => |call| {
+            if (self.builtinCalled(call.callee) == null) try self.hoist(call.callee);
+            for (call.arguments) |argument| try self.hoist(argument);
+        },
+        .array_literal, .tuple_literal => |elements| for (elements) |element| try self.hoist(element),
+        .index => |index| {
+            try self.hoist(index.object);
+            try self.hoist(index.index);
+        },
+        .tuple_index => |access| try self.hoist(access.object),
+        .struct_literal => |literal| for (literal.fields) |field| try self.hoist(field.value),
+        .field_access => |access| try self.hoist(access.object),
+        .reference, .dereference => |operand| try self.hoist(operand),
+        .cast => |cast| try self.hoist(cast.value),
+        .sizeof, .closure, .@"comptime", .identifier, .literal => {},
+        .variant_literal => try self.unsupported(node.span, "enums"),
+        .@"try" => try self.unsupported(node.span, "? operators"),
+        else => {
+            const @"type" = self.typeOf(node);
+            if (@"type" == .unit) return try self.generateStatement(node, .discard);
+
+            const temporary = self.createTemporary();
+            try self.temporaries.put(node.id, temporary);
+
+            try self.indent();
+            try self.declare(@"type", temporary, node.span);
+            try self.write(";\n");
+            try self.generateStatement(node, .{ .assign = temporary });
+        }
+    }
+}
+
+// The statements of the right operand only run when it decides the result
+fn hoistShortCircuit(self: *C, node: *const Node, infix: Node.Infix) GenerateError!void {
+    const temporary = self.createTemporary();
+    try self.temporaries.put(node.id, temporary);
+
+    try self.hoist(infix.lhs);
+    try self.indent();
+    try self.print("bool {} = ", .{temporary});
+    try self.generateUnwrapped(infix.lhs);
+    try self.write(";\n");
+
+    try self.indent();
+    try self.print("if ({s}{}) {{\n", .{if (infix.operator == .@"or") "!" else "", temporary});
+    self.depth = self.depth + 1;
+    try self.generateStatement(infix.rhs, .{ .assign = temporary });
+    self.depth = self.depth - 1;
+    try self.indent();
+    try self.write("}\n");
+}
+
+// Writes an expression which can be used as an operand
+fn generateExpression(self: *C, node: *const Node) GenerateError!void {
+    if (self.temporaries.get(node.id)) |temporary| return try self.print("{}", .{temporary});
+
+    switch (node.kind) {
+        .prefix, .infix, .cast => {
+            try self.write("(");
+            try self.generateUnwrapped(node);
+            try self.write(")");
+        },
+        else => try self.generateUnwrapped(node)
+    }
+}
+
+// Writes an expression without the parentheses operators are otherwise wrapped in
+fn generateUnwrapped(self: *C, node: *const Node) GenerateError!void {
+    if (self.temporaries.get(node.id)) |temporary| return try self.print("{}", .{temporary});
+
+    switch (node.kind) {
+        .prefix => |prefix| {
+            try self.write(prefix.operator.toStr());
+            try self.generateExpression(prefix.operand);
+        },
+        .infix => |infix| try self.generateInfix(node, infix),
+        .fn_call => |call| try self.generateCall(node, call.callee, call.arguments),
+        .array_literal => |elements| try self.generateArray(node, elements),
+        .index => |index| try self.generateIndex(node, index),
+        .tuple_literal => |elements| try self.generateTuple(node, elements),
+        .tuple_index => |access| {
+            try self.generateExpression(access.object);
+            try self.print(".{}", .{Name{ .tuple_element = access.index }});
+        },
+        .struct_literal => |literal| try self.generateUnion(node, literal),
+        .field_access => |access| {
+            const object = self.typeOf(access.object);
+            if (object != .@"struct" or !object.@"struct".overlaid) return try self.unsupported(node.span, "structs");
+
+            try self.generateExpression(access.object);
+            try self.print(".{}", .{Name{ .binding = access.field }});
+        },
+        .reference, .dereference => |operand| {
+            try self.write(if (node.kind == .reference) "&(" else "*(");
+            try self.generateUnwrapped(operand);
+            try self.write(")");
+        },
+        // C lays out the types it represents as the checker sizes them
+        .sizeof => |annotation| {
+            const @"type" = self.typeOf(annotation);
+            if (!isRepresentable(@"type")) return try self.unsupportedType(node.span, @"type");
+
+            try self.write("(int64_t)sizeof(");
+            _ = try self.writeType(@"type");
+            try self.write(")");
+        },
+        .cast => |cast| {
+            const @"type" = self.typeOf(cast.target);
+            if (!isRepresentable(@"type")) return try self.unsupportedType(node.span, @"type");
+
+            try self.write("(");
+            _ = try self.writeType(@"type");
+            try self.write(")(");
+            try self.generateUnwrapped(cast.value);
+            try self.write(")");
+        },
+        .closure => |closure| try self.generateClosure(node, closure),
+        .identifier => |name| {
+            if (self.typeOf(node) == .unit) return try self.write("((void)0)");
+            if (self.isDirect(name)) return try self.generateReference(node, name);
+            if (self.isExtern(name)) return try self.unsupported(node.span, "extern functions used as values");
+            try self.print("{}", .{Name{ .binding = name }});
+        },
+        .literal => |literal| try self.generateLiteral(node, literal),
+        .@"comptime" => |body| try self.generateUnwrapped(body),
+        // Statements are hoisted out of expressions, those left have no value
+        else => try self.write("((void)0)")
+    }
+}
+
+fn generateInfix(self: *C, node: *const Node, infix: Node.Infix) GenerateError!void {
+    const operand = self.typeOf(infix.lhs);
+
+    switch (infix.operator) {
+        .forward_app => return try self.generateCall(node, infix.lhs, &.{infix.rhs}),
+        .reverse_app => return try self.generateCall(node, infix.rhs, &.{infix.lhs}),
+        .exponent => return switch (operand) {
+            .@"f32" => try self.generateBuiltin("powf", infix),
+            .@"f64" => try self.generateBuiltin("pow", infix),
+            else => {
+                try self.print("({s})", .{cType(operand).?});
+                try self.generateBuiltin("ruka_ipow", infix);
+            }
+        },
+        .modulo => switch (operand) {
+            .@"f32" => return try self.generateBuiltin("fmodf", infix),
+            .@"f64" => return try self.generateBuiltin("fmod", infix),
+            else => {}
+        },
+        .equal, .not_equal => switch (operand) {
+            .str => {
+                try self.generateBuiltin("strcmp", infix);
+                return try self.print(" {s} 0", .{infix.operator.toStr()});
+            },
+            .unit => return try self.unsupported(node.span, "comparisons of () values"),
+            else => {}
+        },
+        .concat => return try self.unsupported(node.span, "string concatenations"),
+        .range_exc, .range_inc => return try self.unsupported(node.span, "ranges outside of for loops"),
+        else => {}
+    }
+
+    try self.generateExpression(infix.lhs);
+    try self.print(" {s} ", .{switch (infix.operator) {
+        .@"and" => "&&",
+        .@"or" => "||",
+        else => infix.operator.toStr()
+    }});
+    try self.generateExpression(infix.rhs);
+}
+
+// Arrays are written as compound literals of their struct
+fn generateArray(self: *C, node: *const Node, elements: []const *Node) GenerateError!void {
+    const @"type" = self.typeOf(node);
+    if (!isRepresentable(@"type")) return try self.unsupportedType(node.span, @"type");
+
+    try self.print("({}){{{{", .{Name{ .array_type = try self.arrayTypeIndex(@"type") }});
+    for (elements, 0..) |element, i| {
+        if (i > 0) try self.write(", ");
+        try self.generateUnwrapped(element);
+    }
+    try self.write("}}");
+}
+
+// Tuples are written as compound literals of their struct, like arrays
+fn generateTuple(self: *C, node: *const Node, elements: []const *Node) GenerateError!void {
+    const @"type" = self.typeOf(node);
+    if (!isRepresentable(@"type")) return try self.unsupportedType(node.span, @"type");
+
+    try self.print("({}){{", .{Name{ .tuple_type = try self.tupleTypeIndex(@"type") }});
+    for (elements, 0..) |element, i| {
+        if (i > 0) try self.write(", ");
+        try self.generateUnwrapped(element);
+    }
+    try self.write("}");
+}
+
+// Unions are written as compound literals of their union, designating the field
+// given, as only structs which are unions are represented
+fn generateUnion(self: *C, node: *const Node, literal: Node.StructLiteral) GenerateError!void {
+    const @"type" = self.typeOf(node);
+    if (@"type" != .@"struct" or !@"type".@"struct".overlaid) return try self.unsupported(node.span, "structs");
+    if (!isRepresentable(@"type")) return try self.unsupportedType(node.span, @"type");
+
+    const field = literal.fields[0];
+    try self.print("({}){{.{} = ", .{Name{ .union_type = try self.unionTypeIndex(@"type") }, Name{ .binding = field.name }});
+    try self.generateUnwrapped(field.value);
+    try self.write("}");
+}
+
+// Indexes are checked against the array's length when generating debug info,
+// the program exits reporting where an index was out of bounds
+fn generateIndex(self: *C, node: *const Node, index: Node.Index) GenerateError!void {
+    if (self.typeOf(index.index) == .range) return try self.unsupported(node.span, "slices");
+
+    const object = self.typeOf(index.object);
+    if (object != .array) return try self.unsupportedType(index.object.span, object);
+
+    try self.generateExpression(index.object);
+    try self.write(".items[");
+
+    if (!self.unit.options.debug_info) {
+        try self.generateUnwrapped(index.index);
+        return try self.write("]");
+    }
+
+    try self.write("ruka_check_index(");
+    try self.generateUnwrapped(index.index);
+    try self.print(", {}, ", .{object.array.len});
+
+    const location = try std.fmt.allocPrint(self.allocator, "{s}:{}:{}", .{node.span.file, node.span.pos.line, node.span.pos.col});
+    defer self.allocator.free(location);
+
+    try self.writeQuoted(location);
+    try self.write(")]");
+}
+
+// Writes an operator implemented by a function in C
+fn generateBuiltin(self: *C, name: []const u8, infix: Node.Infix) GenerateError!void {
+    try self.print("{s}(", .{name});
+    try self.generateUnwrapped(infix.lhs);
+    try self.write(", ");
+    try self.generateUnwrapped(infix.rhs);
+    try self.write(")");
+}
+
+// Functions defined at the top level are called directly, other values of function
+// types through the call function of their type, which is given the value
+fn generateCall(self: *C, node: *const Node, callee: *const Node, arguments: []const *Node) GenerateError!void {
+    if (self.builtinCalled(callee)) |function| return try self.generateBuiltinCall(node, function, arguments);
+    if (self.isPreludeFunction(callee, "assert")) return try self.generateAssert(node, arguments);
+    if (self.isPreludeFunction(callee, "fmt")) return try self.generateFmt(arguments);
+    if (self.isPreludeFunction(callee, "block_on")) return try self.unsupported(node.span, "async functions");
+
+    const callee_type = self.typeOf(callee);
+    const direct = callee.kind == .identifier and self.isDirect(callee.kind.identifier);
+
+    if (self.types.getInstantiation(node.id)) |type_arguments| {
+        try self.generateInstanceName(node, callee, type_arguments);
+        try self.write("(");
+    } else if (direct) {
+        try self.print("{}(", .{Name{ .binding = callee.kind.identifier }});
+    } else if (callee.kind == .identifier and self.isExtern(callee.kind.identifier)) {
+        try self.print("{s}(", .{callee.kind.identifier});
+    } else if (callee_type == .function and isRepresentable(callee_type)) {
+        try self.print("{}_call(", .{Name{ .function_type = try self.functionTypeIndex(callee_type) }});
+        try self.generateUnwrapped(callee);
+        if (arguments.len > 0) try self.write(", ");
+    } else {
+        try self.generateExpression(callee);
+        try self.write("(");
+    }
+
+    for (arguments, 0..) |argument, i| {
+        if (i > 0) try self.write(", ");
+        try self.generateUnwrapped(argument);
+    }
+    try self.write(")");
+}
+
+// The functions of the built in modules are translated to the C functions they
+// correspond to. abs, min and max are generic, so a function is written for each
+// type they are called with
+fn generateBuiltinCall(
+    self: *C,
+    node: *const Node,
+    function: *const stdlib.Function,
+    arguments: []const *Node
+) GenerateError!void {
+    if (function.ret == .line) return try self.unsupported(node.span, "io.read_line calls");
+
+    if (std.mem.eql(u8, function.module, "io")) {
+        const to_errors = std.mem.startsWith(u8, function.name, "e");
+        const newline = std.mem.endsWith(u8, function.name, "ln");
+
+        try self.print("{s}\"%s{s}\", ", .{if (to_errors) "dprintf(2, " else "printf(", if (newline) "\\n" else ""});
+        try self.generateUnwrapped(arguments[0]);
+        return try self.write(")");
+    }
+
+    if (function.ret == .number) {
+        const builtin: Builtin = .{ .function = function, .@"type" = self.typeOf(node) };
+        for (self.builtins.items) |written| {
+            if (written.function == function and written.@"type".eql(builtin.@"type")) break;
+        } else try self.builtins.append(builtin);
+
+        try self.print("{}(", .{Name{ .builtin = builtin }});
+    } else {
+        try self.print("{s}(", .{function.name});
+    }
+
+    for (arguments, 0..) |argument, i| {
+        if (i > 0) try self.write(", ");
+        try self.generateUnwrapped(argument);
+    }
+    try self.write(")");
+}
+
+// Asserts are passed the source of their condition and where they are, which
+// they report if the condition doesn't hold
+fn generateAssert(self: *C, node: *const Node, arguments: []const *Node) GenerateError!void {
+    try self.write("ruka_assert(");
+    try self.generateUnwrapped(arguments[0]);
+    try self.write(", ");
+    try self.writeQuoted(try self.unit.sourceText(arguments[0]));
+    try self.write(", ");
+    if (arguments.len > 1) try self.generateUnwrapped(arguments[1]) else try self.write("NULL");
+    try self.write(", ");
+
+    const location = try std.fmt.allocPrint(self.allocator, "{s}:{}:{}", .{node.span.file, node.span.pos.line, node.span.pos.col});
+    defer self.allocator.free(location);
+
+    try self.writeQuoted(location);
+    try self.write(")");
+}
+
+// Templates are translated to printf formats, their placeholders' conversions
+// chosen by the types of the arguments, which are cast to the types the
+// conversions expect. The strings formatted are never freed, as no string is
+fn generateFmt(self: *C, arguments: []const *Node) GenerateError!void {
+    const values = arguments[1..];
+
+    var specifiers = ArrayList(stdlib.fmt.Specifier).init(self.allocator);
+    defer specifiers.deinit();
+
+    try self.write("ruka_fmt(\"");
+    var pieces = stdlib.fmt.Iterator.init(arguments[0].kind.literal.string);
+    while (pieces.next() catch unreachable) |piece| {
+        const specifier = switch (piece) {
+            .text => |text| {
+                for (text) |byte| {
+                    if (byte == '%') try self.write("%%") else try self.writeEscaped(byte);
+                }
+                continue;
+            },
+            .placeholder => |specifier| specifier
+        };
+
+        const @"type" = self.typeOf(values[specifiers.items.len]);
+        try specifiers.append(specifier);
+
+        switch (specifier) {
+            .hex => try self.write("%llx"),
+            .precision => |precision| try self.print("%.{}f", .{precision}),
+            .default => try self.write(switch (@"type") {
+                .@"bool", .str => "%s",
+                .char => "%c",
+                else => if (@"type".isFloat()) "%g" else "%lld"
+            })
+        }
+    }
+    try self.write("\"");
+
+    for (values, specifiers.items) |value, specifier| {
+        try self.write(", ");
+
+        const @"type" = self.typeOf(value);
+        const cast: []const u8 = switch (specifier) {
+            .hex => "(unsigned long long)",
+            .precision => "(double)",
+            .default => switch (@"type") {
+                .@"bool", .str, .char => "",
+                else => if (@"type".isFloat()) "(double)" else "(long long)"
+            }
+        };
+
+        try self.print("{s}(", .{cast});
+        try self.generateUnwrapped(value);
+        try self.write(if (@"type" == .@"bool" and specifier == .default) ") ? \"true\" : \"false\"" else ")");
+    }
+    try self.write(")");
+}
+
+// Whether callee refers to the prelude's function called name, rather than a
+// binding shadowing it
+fn isPreludeFunction(self: *const C, callee: *const Node, name: []const u8) bool {
+    if (callee.kind != .identifier or !std.mem.eql(u8, callee.kind.identifier, name)) return false;
+    for (self.locals.items) |local| if (std.mem.eql(u8, local, name)) return false;
+
+    return true;
+}
+
+// The function of a built in module callee refers to, null if it refers to
+// something else
+fn builtinCalled(self: *const C, callee: *const Node) ?*const stdlib.Function {
+    const access = switch (callee.kind) {
+        .field_access => |access| access,
+        else => return null
+    };
+    if (access.object.kind != .identifier) return null;
+
+    const name = access.object.kind.identifier;
+    for (self.locals.items) |local| if (std.mem.eql(u8, local, name)) return null;
+
+    return stdlib.find(name, access.field);
+}
+
+// Whether name refers to a function defined at the top level, rather than a
+// binding shadowing it
+fn isDirect(self: *const C, name: []const u8) bool {
+    if (!self.functions.contains(name)) return false;
+    for (self.locals.items) |local| if (std.mem.eql(u8, local, name)) return false;
+
+    return true;
+}
+
+// Whether name refers to an extern function declared at the top level, rather
+// than a binding shadowing it
+fn isExtern(self: *const C, name: []const u8) bool {
+    if (!self.externs.contains(name)) return false;
+    for (self.locals.items) |local| if (std.mem.eql(u8, local, name)) return false;
+
+    return true;
+}
+
+// The index of the struct representing values of a function type, which is added
+// along with those of the function types it refers to if it hasn't been already
+fn functionTypeIndex(self: *C, @"type": Type) GenerateError!usize {
+    for (self.function_types.items, 0..) |function_type, i| if (function_type.eql(@"type")) return i;
+
+    const function = @"type".function;
+    for (function.params) |param| if (param == .function) {
+        _ = try self.functionTypeIndex(param);
+    };
+    if (function.ret.* == .function) _ = try self.functionTypeIndex(function.ret.*);
+    for (function.params) |param| if (param == .array or param == .tuple or param == .@"struct") {
+        try self.addElementType(param);
+    };
+    const ret = function.ret.*;
+    if (ret == .array or ret == .tuple or ret == .@"struct") try self.addElementType(ret);
+
+    try self.function_types.append(@"type");
+    return self.function_types.items.len - 1;
+}
+
+// The index of the struct representing values of an array type, which is added
+// along with those of the types of its elements if it hasn't been already
+fn arrayTypeIndex(self: *C, @"type": Type) GenerateError!usize {
+    for (self.array_types.items, 0..) |array_type, i| if (array_type.eql(@"type")) return i;
+
+    try self.addElementType(@"type".array.element.*);
+
+    try self.aggregates.ensureUnusedCapacity(1);
+    try self.array_types.append(@"type");
+    self.aggregates.appendAssumeCapacity(.{ .array_type = self.array_types.items.len - 1 });
+
+    return self.array_types.items.len - 1;
+}
+
+// The index of the struct representing values of a tuple type, which is added
+// like those of arrays
+fn tupleTypeIndex(self: *C, @"type": Type) GenerateError!usize {
+    for (self.tuple_types.items, 0..) |tuple_type, i| if (tuple_type.eql(@"type")) return i;
+
+    for (@"type".tuple) |element| try self.addElementType(element);
+
+    try self.aggregates.ensureUnusedCapacity(1);
+    try self.tuple_types.append(@"type");
+    self.aggregates.appendAssumeCapacity(.{ .tuple_type = self.tuple_types.items.len - 1 });
+
+    return self.tuple_types.items.len - 1;
+}
+
+// The index of the C union representing values of a union type, which is added
+// like the structs of tuples
+fn unionTypeIndex(self: *C, @"type": Type) GenerateError!usize {
+    for (self.union_types.items, 0..) |union_type, i| if (union_type.eql(@"type")) return i;
+
+    for (@"type".@"struct".fields) |field| try self.addElementType(field.@"type");
+
+    try self.aggregates.ensureUnusedCapacity(1);
+    try self.union_types.append(@"type");
+    self.aggregates.appendAssumeCapacity(.{ .union_type = self.union_types.items.len - 1 });
+
+    return self.union_types.items.len - 1;
+}
+
+// Adds the struct of the type of an array's, tuple's or union's elements, which
+// must be declared before the struct holding them
+fn addElementType(self: *C, element: Type) GenerateError!void {
+    switch (element) {
+        .array => _ = try self.arrayTypeIndex(element),
+        .tuple => _ = try self.tupleTypeIndex(element),
+        .@"struct" => |structure| if (structure.overlaid) {
+            _ = try self.unionTypeIndex(element);
+        },
+        .function => _ = try self.functionTypeIndex(element),
+        else => {}
+    }
+}
+
+/// Writes a closure as a call to the constructor of its struct, which copies
+/// the values of the bindings it captures. The struct is allocated and never
+/// freed, as closures can outlive the function creating them
+fn generateClosure(self: *C, node: *const Node, closure: Node.Closure) GenerateError!void {
+    const @"type" = self.typeOf(node);
+    if (!isRepresentable(@"type")) return try self.unsupportedType(node.span, @"type");
+
+    const captures = self.types.getCaptures(node.id) orelse &.{};
+    for (captures) |capture| {
+        if (capture == .unit or !isRepresentable(capture)) return try self.unsupportedType(node.span, capture);
+    }
+
+    const index = self.closure_count;
+    self.closure_count = self.closure_count + 1;
+
+    std.mem.swap(ArrayList(u8), &self.output, &self.closure_declarations);
+    const declared = self.writeClosureDeclaration(index, @"type", closure, captures);
+    std.mem.swap(ArrayList(u8), &self.output, &self.closure_declarations);
+    try declared;
+
+    // Closures within the body are generated while it is, so each function is
+    // written to a buffer of its own
+    var definition = ArrayList(u8).init(self.allocator);
+    defer definition.deinit();
+
+    // As are the mappings of its statements, until it's placed after the others
+    var mappings = SourceMap.init(self.allocator, self.source_map.file);
+    defer mappings.deinit();
+
+    std.mem.swap(ArrayList(u8), &self.output, &definition);
+    std.mem.swap(SourceMap, &self.source_map, &mappings);
+    const defined = self.writeClosureDefinition(index, @"type".function, closure, captures);
+    std.mem.swap(SourceMap, &self.source_map, &mappings);
+    std.mem.swap(ArrayList(u8), &self.output, &definition);
+    try defined;
+
+    mappings.shift(0, self.closure_definitions.items.len);
+    try self.closure_mappings.mappings.appendSlice(mappings.mappings.items);
+    try self.closure_definitions.appendSlice(definition.items);
+
+    try self.print("{}_new(", .{Name{ .closure = index }});
+    for (closure.captures, 0..) |name, i| {
+        if (i > 0) try self.write(", ");
+        try self.print("{}", .{Name{ .binding = name }});
+    }
+    try self.write(")");
+}
+
+// Writes the struct holding the captured values, the prototype of the function
+// the closure calls and the constructor of the struct
+fn writeClosureDeclaration(
+    self: *C,
+    index: usize,
+    @"type": Type,
+    closure: Node.Closure,
+    captures: []const Type
+) GenerateError!void {
+    const function_type: Name = .{ .function_type = try self.functionTypeIndex(@"type") };
+    const name: Name = .{ .closure = index };
+
+    try self.print("struct {} {{\n    {} ruka_header;\n", .{name, function_type});
+    for (closure.captures, captures) |capture, capture_type| {
+        try self.write("    ");
+        _ = try self.writeDeclaration(capture_type, .{ .binding = capture });
+        try self.write(";\n");
+    }
+    try self.write("};\n\n");
+
+    try self.writeClosureSignature(index, @"type".function, closure);
+    try self.write(";\n\n");
+
+    try self.print("static {} *{}_new(", .{function_type, name});
+    if (captures.len == 0) try self.write("void");
+    for (closure.captures, captures, 0..) |capture, capture_type, i| {
+        if (i > 0) try self.write(", ");
+        _ = try self.writeDeclaration(capture_type, .{ .binding = capture });
+    }
+    try self.write(") {\n");
+
+    try self.print("    struct {} *ruka_closure = malloc(sizeof(struct {}));\n", .{name, name});
+    try self.print("    ruka_closure->ruka_header.call = {};\n", .{Name{ .closure_call = index }});
+    for (closure.captures) |capture| try self.print("    ruka_closure->{} = {};\n", .{
+        Name{ .binding = capture },
+        Name{ .binding = capture }
+    });
+    try self.write("    return &ruka_closure->ruka_header;\n}\n\n");
+}
+
+fn writeClosureSignature(self: *C, index: usize, function: Type.Function, closure: Node.Closure) GenerateError!void {
+    try self.write("static ");
+    _ = try self.writeDeclaration(function.ret.*, .{ .closure_call = index });
+    try self.print("({} *ruka_self", .{Name{ .function_type = try self.functionTypeIndex(.{ .function = function }) }});
+    if (closure.params.len > 0) try self.write(", ");
+    try self.writeParams(function, closure.params);
+    try self.write(")");
+}
+
+// Writes the function the closure calls, which is given the closure's struct
+// first and loads the captured values from it
+fn writeClosureDefinition(
+    self: *C,
+    index: usize,
+    function: Type.Function,
+    closure: Node.Closure,
+    captures: []const Type
+) GenerateError!void {
+    const depth = self.depth;
+    defer self.depth = depth;
+
+    const return_type = self.return_type;
+    defer self.return_type = return_type;
+
+    const locals = self.locals.items.len;
+    defer self.locals.shrinkRetainingCapacity(locals);
+
+    // The closure's function returns to its caller, running only its own defers
+    const function_defers = self.function_defers;
+    const loop_defers = self.loop_defers;
+    self.function_defers = self.defers.items.len;
+    self.loop_defers = self.defers.items.len;
+    defer {
+        self.function_defers = function_defers;
+        self.loop_defers = loop_defers;
+    }
+
+    self.depth = 1;
+    self.return_type = function.ret.*;
+    for (closure.params) |param| try self.locals.append(param.name);
+    for (closure.captures) |capture| try self.locals.append(capture);
+
+    try self.write("\n");
+    try self.writeClosureSignature(index, function, closure);
+    try self.write(" {\n");
+
+    const name: Name = .{ .closure = index };
+    if (captures.len > 0) try self.print("    struct {} *ruka_closure = (struct {} *)ruka_self;\n", .{name, name});
+    for (closure.captures, captures) |capture, capture_type| {
+        try self.indent();
+        _ = try self.writeDeclaration(capture_type, .{ .binding = capture });
+        try self.print(" = ruka_closure->{};\n", .{Name{ .binding = capture }});
+    }
+
+    try self.generateStatements(closure.body, if (self.return_type == .unit) .discard else .@"return");
+    try self.write("}\n");
+}
+
+/// Writes a top level function used as a value, as a pointer to a struct whose
+/// call function calls it. The struct is shared by every use of the function
+fn generateReference(self: *C, node: *const Node, name: []const u8) GenerateError!void {
+    const @"type" = self.typeOf(node);
+    if (!isRepresentable(@"type")) return try self.unsupportedType(node.span, @"type");
+
+    if (!self.references.contains(name)) {
+        try self.references.put(name, {});
+
+        std.mem.swap(ArrayList(u8), &self.output, &self.closure_declarations);
+        const declared = self.writeReferenceDeclaration(name, @"type".function);
+        std.mem.swap(ArrayList(u8), &self.output, &self.closure_declarations);
+        try declared;
+
+        std.mem.swap(ArrayList(u8), &self.output, &self.closure_definitions);
+        const defined = self.writeReferenceDefinition(name, @"type".function);
+        std.mem.swap(ArrayList(u8), &self.output, &self.closure_definitions);
+        try defined;
+    }
+
+    try self.print("&{}", .{Name{ .reference = name }});
+}
+
+fn writeReferenceSignature(self: *C, name: []const u8, function: Type.Function) GenerateError!void {
+    try self.write("static ");
+    _ = try self.writeDeclaration(function.ret.*, .{ .reference_call = name });
+    try self.print("({} *ruka_self", .{Name{ .function_type = try self.functionTypeIndex(.{ .function = function }) }});
+    for (function.params, 0..) |param, i| {
+        try self.write(", ");
+        _ = try self.writeDeclaration(param, .{ .temporary = i });
+    }
+    try self.write(")");
+}
+
+fn writeReferenceDeclaration(self: *C, name: []const u8, function: Type.Function) GenerateError!void {
+    try self.writeReferenceSignature(name, function);
+    try self.write(";\n\n");
+    try self.print("static {} {} = {{ {} }};\n\n", .{
+        Name{ .function_type = try self.functionTypeIndex(.{ .function = function }) },
+        Name{ .reference = name },
+        Name{ .reference_call = name }
+    });
+}
+
+// The call function ignores the struct it is given, as the function it calls
+// captures nothing
+fn writeReferenceDefinition(self: *C, name: []const u8, function: Type.Function) GenerateError!void {
+    try self.write("\n");
+    try self.writeReferenceSignature(name, function);
+    try self.print(" {{\n    {s}{}(", .{if (function.ret.* == .unit) "" else "return ", Name{ .binding = name }});
+    for (0..function.params.len) |i| {
+        if (i > 0) try self.write(", ");
+        try self.print("{}", .{Name{ .temporary = i }});
+    }
+    try self.write(");\n}\n");
+}
+
+// Writes the structs representing the function types the program uses, each
+// with a function calling the function its values point to
+// Stops the source compiling for a target whose pointers or byte order differ from
+// the one it was generated for
+fn writeTargetGuards(self: *C, target: *const Target) GenerateError!void {
+    try self.print("#if UINTPTR_MAX != {s}\n", .{target.maxPointer()});
+    try self.print("#error \"generated for {s}, whose pointers are {} bytes\"\n", .{target.triple, target.pointer_size});
+    try self.write("#endif\n");
+
+    const order = if (target.endian == .little) "__ORDER_LITTLE_ENDIAN__" else "__ORDER_BIG_ENDIAN__";
+    try self.print("#if defined(__BYTE_ORDER__) && __BYTE_ORDER__ != {s}\n", .{order});
+    try self.print("#error \"generated for {s}, which is {s} endian\"\n", .{target.triple, @tagName(target.endian)});
+    try self.write("#endif\n\n");
+}
+
+fn writeDeclarations(self: *C, declarations: *ArrayList(u8)) GenerateError!void {
+    std.mem.swap(ArrayList(u8), &self.output, declarations);
+    defer std.mem.swap(ArrayList(u8), &self.output, declarations);
+
+    if (self.unit.options.target) |triple| {
+        if (Target.find(triple)) |target| try self.writeTargetGuards(target);
+    }
+
+    for (0..self.function_types.items.len) |i| {
+        try self.print("typedef struct {} {};\n", .{Name{ .function_type = i }, Name{ .function_type = i }});
+    }
+    if (self.function_types.items.len > 0) try self.write("\n");
+
+    // Written before the function types' structs, whose call functions may take
+    // or return arrays and tuples
+    for (self.aggregates.items) |name| {
+        try self.write(if (name == .union_type) "typedef union {\n" else "typedef struct {\n");
+        switch (name) {
+            .array_type => |i| {
+                const array = self.array_types.items[i].array;
+
+                try self.write("    ");
+                _ = try self.writeDeclaration(array.element.*, .{ .binding = "items" });
+                try self.print("[{}];\n", .{array.len});
+            },
+            .tuple_type => |i| for (self.tuple_types.items[i].tuple, 0..) |element, j| {
+                try self.write("    ");
+                _ = try self.writeDeclaration(element, .{ .tuple_element = j });
+                try self.write(";\n");
+            },
+            .union_type => |i| for (self.union_types.items[i].@"struct".fields) |field| {
+                try self.write("    ");
+                _ = try self.writeDeclaration(field.@"type", .{ .binding = field.name });
+                try self.write(";\n");
+            },
+            else => unreachable
+        }
+        try self.print("}} {};\n\n", .{name});
+    }
+
+    for (self.function_types.items, 0..) |function_type, i| {
+        const function = function_type.function;
+        const name: Name = .{ .function_type = i };
+
+        try self.print("struct {} {{\n    ", .{name});
+        _ = try self.writeType(function.ret.*);
+        try self.print("{s}(*call)({} *", .{if (function.ret.* == .function) "" else " ", name});
+        for (function.params) |param| {
+            try self.write(", ");
+            _ = try self.writeType(param);
+        }
+        try self.write(");\n};\n\n");
+
+        try self.write("static inline ");
+        _ = try self.writeDeclaration(function.ret.*, name);
+        try self.print("_call({} *ruka_self", .{name});
+        for (function.params, 0..) |param, j| {
+            try self.write(", ");
+            _ = try self.writeDeclaration(param, .{ .temporary = j });
+        }
+        try self.print(") {{\n    {s}ruka_self->call(ruka_self", .{if (function.ret.* == .unit) "" else "return "});
+        for (0..function.params.len) |j| try self.print(", {}", .{Name{ .temporary = j }});
+        try self.write(");\n}\n\n");
+    }
+
+    for (self.builtins.items) |builtin| {
+        const c_type = cType(builtin.@"type").?;
+        const name: Name = .{ .builtin = builtin };
+
+        if (std.mem.eql(u8, builtin.function.name, "abs")) {
+            try self.print("static inline {s} {}({s} x) {{\n    return x < 0 ? -x : x;\n}}\n\n", .{c_type, name, c_type});
+        } else {
+            const operator = if (std.mem.eql(u8, builtin.function.name, "max")) ">" else "<";
+            try self.print("static inline {s} {}({s} a, {s} b) {{\n    return a {s} b ? a : b;\n}}\n\n", .{
+                c_type,
+                name,
+                c_type,
+                c_type,
+                operator
+            });
+        }
+    }
+
+    try self.write(self.closure_declarations.items);
+}
+
+// Writes the name of the instance of the generic function a call is to
+fn generateInstanceName(self: *C, node: *const Node, callee: *const Node, type_arguments: []const Type) GenerateError!void {
+    const definition = self.genericCallee(callee)
+        orelse return try self.unsupported(callee.span, "generic functions used as values");
+
+    const arguments = try self.concreteArguments(type_arguments);
+    defer self.allocator.free(arguments);
+
+    if (unrepresentable(arguments)) |argument| return try self.unsupportedType(node.span, argument);
+    try self.print("{}", .{self.instanceName(try self.instanceOf(definition, arguments))});
+}
+
+fn generateLiteral(self: *C, node: *const Node, literal: Node.Literal) GenerateError!void {
+    switch (literal) {
+        .integer => |integer| try self.print("{}", .{integer}),
+        .float => |float| try self.writeFloat(float, self.typeOf(node) == .@"f32"),
+        .boolean => |boolean| try self.write(if (boolean) "true" else "false"),
+        .string => |string| try self.writeQuoted(string),
+        .character => |character| {
+            try self.write("'");
+            try self.writeEscaped(character);
+            try self.write("'");
+        },
+        .unit => try self.write("((void)0)")
+    }
+}
+
+// Floats always include a decimal point so C reads them as floats
+fn writeFloat(self: *C, float: f64, single: bool) GenerateError!void {
+    if (std.math.isInf(float)) return try self.write(if (single) "(1.0f / 0.0f)" else "(1.0 / 0.0)");
+    if (std.math.isNan(float)) return try self.write(if (single) "(0.0f / 0.0f)" else "(0.0 / 0.0)");
+
+    // Large enough for the longest decimal representation of an f64
+    var buf: [512]u8 = undefined;
+    const str = std.fmt.bufPrint(&buf, "{d}", .{float}) catch unreachable;
+    try self.write(str);
+
+    if (std.mem.indexOfAny(u8, str, ".e") == null) try self.write(".0");
+    if (single) try self.write("f");
+}
+
+fn writeQuoted(self: *C, string: []const u8) GenerateError!void {
+    try self.write("\"");
+    for (string) |byte| try self.writeEscaped(byte);
+    try self.write("\"");
+}
+
+// Octal escapes are used for unprintable bytes, as unlike hex escapes they can't
+// run into the characters that follow
+fn writeEscaped(self: *C, byte: u8) GenerateError!void {
+    switch (byte) {
+        '\\', '"', '\'', '?' => try self.print("\\{c}", .{byte}),
+        '\n' => try self.write("\\n"),
+        '\t' => try self.write("\\t"),
+        '\r' => try self.write("\\r"),
+        else => if (std.ascii.isPrint(byte)) {
+            try self.output.append(byte);
+        } else {
+            try self.print("\\{o:0>3}", .{byte});
+        }
+    }
+}
+
+test "c modules" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const AstBuilder = ruka.AstBuilder;
+
+    // Compiles source, returning the generated C
+    fn generateSource(source: []const u8) ![]u8 {
+        var input = std.io.fixedBufferStream(source);
+
+        var output = ArrayList(u8).init(testing.allocator);
+        errdefer output.deinit();
+
+        // Without line directives, which are tested on their own
+        var options = Unit.UnitOptions.testing(input.reader().any(), output.writer().any());
+        options.options.debug_info = false;
+
+        var unit = try Unit.init(options);
+        defer unit.deinit();
+
+        const result = try unit.compile();
+        result.deinit();
+
+        return try output.toOwnedSlice();
+    }
+
+    test "bindings and expressions" {
+        const generated = try generateSource(
+            \\let x: i32 = 1 + 2
+            \\var s = "hello world"
+            \\let big = x * 3 < 10 and true
+            \\let f = 1.5 ** 2.0
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(prelude, generated[0..prelude.len]);
+        try testing.expectEqualStrings(
+            \\static int32_t x;
+            \\static const char *s;
+            \\static bool big;
+            \\static double f;
+            \\
+            \\int main(void) {
+            \\    x = 1 + 2;
+            \\    s = "hello world";
+            \\    big = ((x * 3) < 10) && true;
+            \\    f = pow(1.5, 2.0);
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "breaking and continuing loops" {
+        const generated = try generateSource(
+            \\var n = 0
+            \\while true {
+            \\    n = n + 1
+            \\    if n > 3 { break }
+            \\}
+            \\for i in 0..10 { continue }
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t n;
+            \\
+            \\int main(void) {
+            \\    n = 0;
+            \\    while (true) {
+            \\        n = n + 1;
+            \\        if (n > 3) {
+            \\            break;
+            \\        }
+            \\    }
+            \\    for (int64_t i = 0, ruka_tmp0 = 10; i < ruka_tmp0; i = i + 1) {
+            \\        continue;
+            \\    }
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "labels and gotos are written as C's" {
+        const generated = try generateSource(
+            \\fn count(n: i64) -> i64 {
+            \\    var i = 0
+            \\    unsafe {
+            \\        again:
+            \\        if i == n { goto done }
+            \\        i = i + 1
+            \\        goto again
+            \\        done:
+            \\    }
+            \\    i
+            \\}
+            \\let counted = count(3)
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t counted;
+            \\
+            \\int64_t count(int64_t n);
+            \\
+            \\int64_t count(int64_t n) {
+            \\    int64_t i = 0;
+            \\    {
+            \\        again: ;
+            \\        if (i == n) {
+            \\            goto done;
+            \\        }
+            \\        i = i + 1;
+            \\        goto again;
+            \\        done: ;
+            \\    }
+            \\    return i;
+            \\}
+            \\
+            \\int main(void) {
+            \\    counted = count(3);
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "references are pointers to what they refer to" {
+        const generated = try generateSource(
+            \\var counter = 1
+            \\fn bump(r: &i64) { *r = *r + 1 }
+            \\let ref = &counter
+            \\bump(ref)
+            \\*ref = *ref * 2
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t counter;
+            \\static int64_t *ref;
+            \\
+            \\void bump(int64_t *r);
+            \\
+            \\void bump(int64_t *r) {
+            \\    *(r) = *(r) + 1;
+            \\}
+            \\
+            \\int main(void) {
+            \\    counter = 1;
+            \\    ref = &(counter);
+            \\    bump(ref);
+            \\    *(ref) = *(ref) * 2;
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "sizes are those C gives the types" {
+        const generated = try generateSource(
+            \\let a = sizeof(i64) * 2
+            \\let b = sizeof(&f64)
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t a;
+            \\static int64_t b;
+            \\
+            \\int main(void) {
+            \\    a = (int64_t)sizeof(int64_t) * 2;
+            \\    b = (int64_t)sizeof(double *);
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "comptime blocks are generated as the values they evaluate to" {
+        const generated = try generateSource(
+            \\let a = comptime { sizeof(i32) * 2 }
+            \\let wide = comptime {
+            \\    let bits = TARGET_BITS
+            \\    if bits >= 32 { true } else { false }
+            \\}
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t a;
+            \\static bool wide;
+            \\
+            \\int main(void) {
+            \\    a = 8;
+            \\    wide = true;
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "casts are written as C casts" {
+        const generated = try generateSource(
+            \\let big: i64 = 300
+            \\let a = big as i8
+            \\let b = 2.5 as i32 * 2
+            \\let c = (big + 1) as f64
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t big;
+            \\static int8_t a;
+            \\static int32_t b;
+            \\static double c;
+            \\
+            \\int main(void) {
+            \\    big = 300;
+            \\    a = (int8_t)(big);
+            \\    b = ((int32_t)(2.5)) * 2;
+            \\    c = (double)(big + 1);
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "extern functions are declared and called by their own names" {
+        const generated = try generateSource(
+            \\extern fn puts(s: str) -> i32
+            \\extern fn malloc(size: i64) -> &i8
+            \\extern fn free(ptr: &i8)
+            \\fn main() {
+            \\    unsafe {
+            \\        let p = malloc(8)
+            \\        puts("allocated")
+            \\        free(p)
+            \\    }
+            \\}
+        );
+        defer testing.allocator.free(generated);
+
+        // malloc is declared by the prelude
+        try testing.expectEqualStrings(
+            \\extern int32_t puts(const char *s);
+            \\extern void free(int8_t *ptr);
+            \\
+            \\void ruka_main(void);
+            \\
+            \\void ruka_main(void) {
+            \\    {
+            \\        int8_t *p = malloc(8);
+            \\        puts("allocated");
+            \\        free(p);
+            \\    }
+            \\}
+            \\
+            \\int main(void) {
+            \\    ruka_main();
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "defers are written again wherever their block is left" {
+        const generated = try generateSource(
+            \\var released = 0
+            \\fn release(file: i64) { released = released * 10 + file }
+            \\fn find(target: i64) -> i64 {
+            \\    defer release(1)
+            \\    for i in 0..10 {
+            \\        defer release(2)
+            \\        if i == target { return i }
+            \\    }
+            \\    0
+            \\}
+            \\let found = find(1)
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t released;
+            \\static int64_t found;
+            \\
+            \\void release(int64_t file);
+            \\int64_t find(int64_t target);
+            \\
+            \\void release(int64_t file) {
+            \\    released = (released * 10) + file;
+            \\}
+            \\
+            \\int64_t find(int64_t target) {
+            \\    for (int64_t i = 0, ruka_tmp0 = 10; i < ruka_tmp0; i = i + 1) {
+            \\        if (i == target) {
+            \\            int64_t ruka_tmp1 = i;
+            \\            release(2);
+            \\            release(1);
+            \\            return ruka_tmp1;
+            \\        }
+            \\        release(2);
+            \\    }
+            \\    int64_t ruka_tmp2 = 0;
+            \\    release(1);
+            \\    return ruka_tmp2;
+            \\}
+            \\
+            \\int main(void) {
+            \\    released = 0;
+            \\    found = find(1);
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "functions and control flow" {
+        var input = std.io.fixedBufferStream("");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var program = try Ast.init(testing.allocator, "test source");
+        defer program.deinit();
+
+        const b = AstBuilder.init(program);
+
+        // fn square(x: i32) -> i32 { x * x }
+        const square = try b.function("square", &.{.{"x", try b.identifier("i32")}}, try b.identifier("i32"), try b.block(&.{
+            try b.infix(.multiply, try b.identifier("x"), try b.identifier("x"))
+        }));
+
+        const call = try b.call("square", &.{try b.identifier("i")});
+
+        // while i < 10 { total = total + square(i); i = i + 1 }
+        const loop = try b.node(.{ .@"while" = .{
+            .condition = try b.infix(.lesser, try b.identifier("i"), try b.integer(10)),
+            .body = try b.block(&.{
+                try b.assignment("total", try b.infix(.add, try b.identifier("total"), call)),
+                try b.assignment("i", try b.infix(.add, try b.identifier("i"), try b.integer(1)))
+            })
+        }});
+
+        // let parity = if total % 2 == 0 { 0 } else { 1 }
+        const parity = try b.binding("parity", false, null, try b.conditional(
+            try b.infix(
+                .equal,
+                try b.infix(.modulo, try b.identifier("total"), try b.integer(2)),
+                try b.integer(0)
+            ),
+            try b.block(&.{try b.integer(0)}),
+            try b.block(&.{try b.integer(1)})
+        ));
+
+        const main = try b.function("main", &.{}, null, try b.block(&.{
+            try b.binding("total", true, try b.identifier("i32"), try b.integer(0)),
+            try b.binding("i", true, try b.identifier("i32"), try b.integer(0)),
+            loop,
+            parity
+        }));
+
+        try b.module(&.{square, main});
+
+        var checker = try ruka.TypeChecker.init(unit, program);
+        defer checker.deinit();
+
+        const types = try checker.check();
+        defer types.deinit();
+
+        var generator = try C.init(unit, program, types);
+        defer generator.deinit();
+
+        const generated = try generator.generate();
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\int32_t square(int32_t x);
+            \\void ruka_main(void);
+            \\
+            \\int32_t square(int32_t x) {
+            \\    return x * x;
+            \\}
+            \\
+            \\void ruka_main(void) {
+            \\    int32_t total = 0;
+            \\    int32_t i = 0;
+            \\    while (i < 10) {
+            \\        total = total + square(i);
+            \\        i = i + 1;
+            \\    }
+            \\    int64_t parity;
+            \\    if ((total % 2) == 0) {
+            \\        parity = 0;
+            \\    } else {
+            \\        parity = 1;
+            \\    }
+            \\}
+            \\
+            \\int main(void) {
+            \\    ruka_main();
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "generic functions are generated for each instance" {
+        const generated = try generateSource(
+            \\fn identity<T>(x: T) -> T { x }
+            \\fn repeat<T>(x: T, n: i64) -> T { if n <= 1 { x } else { repeat(x, n - 1) } }
+            \\let a = identity(1)
+            \\let b = identity(true)
+            \\let c = repeat('c', 3)
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t a;
+            \\static bool b;
+            \\static char c;
+            \\
+            \\int64_t ruka_identity_0(int64_t x);
+            \\bool ruka_identity_1(bool x);
+            \\char ruka_repeat_2(char x, int64_t n);
+            \\
+            \\int64_t ruka_identity_0(int64_t x) {
+            \\    return x;
+            \\}
+            \\
+            \\bool ruka_identity_1(bool x) {
+            \\    return x;
+            \\}
+            \\
+            \\char ruka_repeat_2(char x, int64_t n) {
+            \\    if (n <= 1) {
+            \\        return x;
+            \\    } else {
+            \\        return ruka_repeat_2(x, n - 1);
+            \\    }
+            \\}
+            \\
+            \\int main(void) {
+            \\    a = ruka_identity_0(1);
+            \\    b = ruka_identity_1(true);
+            \\    c = ruka_repeat_2('c', 3);
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "closures are generated as structs holding their captures" {
+        const generated = try generateSource(
+            \\fn twice(f: fn(i64) -> i64, x: i64) -> i64 { f(f(x)) }
+            \\fn main() -> i64 {
+            \\    let n = 2
+            \\    let add = fn(x: i64) -> i64 { x + n }
+            \\    twice(add, 1)
+            \\}
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\typedef struct ruka_fn0 ruka_fn0;
+            \\
+            \\struct ruka_fn0 {
+            \\    int64_t (*call)(ruka_fn0 *, int64_t);
+            \\};
+            \\
+            \\static inline int64_t ruka_fn0_call(ruka_fn0 *ruka_self, int64_t ruka_tmp0) {
+            \\    return ruka_self->call(ruka_self, ruka_tmp0);
+            \\}
+            \\
+            \\struct ruka_closure0 {
+            \\    ruka_fn0 ruka_header;
+            \\    int64_t n;
+            \\};
+            \\
+            \\static int64_t ruka_closure0_call(ruka_fn0 *ruka_self, int64_t x);
+            \\
+            \\static ruka_fn0 *ruka_closure0_new(int64_t n) {
+            \\    struct ruka_closure0 *ruka_closure = malloc(sizeof(struct ruka_closure0));
+            \\    ruka_closure->ruka_header.call = ruka_closure0_call;
+            \\    ruka_closure->n = n;
+            \\    return &ruka_closure->ruka_header;
+            \\}
+            \\
+            \\int64_t twice(ruka_fn0 *f, int64_t x);
+            \\int64_t ruka_main(void);
+            \\
+            \\int64_t twice(ruka_fn0 *f, int64_t x) {
+            \\    return ruka_fn0_call(f, ruka_fn0_call(f, x));
+            \\}
+            \\
+            \\int64_t ruka_main(void) {
+            \\    int64_t n = 2;
+            \\    ruka_fn0 *add = ruka_closure0_new(n);
+            \\    return twice(add, 1);
+            \\}
+            \\
+            \\int main(void) {
+            \\    return ruka_main();
+            \\}
+            \\
+            \\static int64_t ruka_closure0_call(ruka_fn0 *ruka_self, int64_t x) {
+            \\    struct ruka_closure0 *ruka_closure = (struct ruka_closure0 *)ruka_self;
+            \\    int64_t n = ruka_closure->n;
+            \\    return x + n;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "functions used as values point to a struct calling them" {
+        const generated = try generateSource(
+            \\fn double(x: i64) -> i64 { x * 2 }
+            \\fn apply(f: fn(i64) -> i64, x: i64) -> i64 { f(x) }
+            \\let a = apply(double, 3)
+            \\let f: fn(i64) -> i64 = double
+            \\let b = f(a)
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\typedef struct ruka_fn0 ruka_fn0;
+            \\
+            \\struct ruka_fn0 {
+            \\    int64_t (*call)(ruka_fn0 *, int64_t);
+            \\};
+            \\
+            \\static inline int64_t ruka_fn0_call(ruka_fn0 *ruka_self, int64_t ruka_tmp0) {
+            \\    return ruka_self->call(ruka_self, ruka_tmp0);
+            \\}
+            \\
+            \\static int64_t ruka_ref_double_call(ruka_fn0 *ruka_self, int64_t ruka_tmp0);
+            \\
+            \\static ruka_fn0 ruka_ref_double = { ruka_ref_double_call };
+            \\
+            \\static int64_t a;
+            \\static ruka_fn0 *f;
+            \\static int64_t b;
+            \\
+            \\int64_t ruka_double(int64_t x);
+            \\int64_t apply(ruka_fn0 *f, int64_t x);
+            \\
+            \\int64_t ruka_double(int64_t x) {
+            \\    return x * 2;
+            \\}
+            \\
+            \\int64_t apply(ruka_fn0 *f, int64_t x) {
+            \\    return ruka_fn0_call(f, x);
+            \\}
+            \\
+            \\int main(void) {
+            \\    a = apply(&ruka_ref_double, 3);
+            \\    f = &ruka_ref_double;
+            \\    b = ruka_fn0_call(f, a);
+            \\    return 0;
+            \\}
+            \\
+            \\static int64_t ruka_ref_double_call(ruka_fn0 *ruka_self, int64_t ruka_tmp0) {
+            \\    return ruka_double(ruka_tmp0);
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "functions of the built in modules are translated to C functions" {
+        const generated = try generateSource(
+            \\let m = math.max(math.abs(-3), 2)
+            \\let r = math.sqrt(2.0) + math.floor(1.5)
+            \\let n = math.min(m, 4)
+            \\io.println("done")
+            \\io.eprint("!")
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static inline int64_t ruka_math_max_i64(int64_t a, int64_t b) {
+            \\    return a > b ? a : b;
+            \\}
+            \\
+            \\static inline int64_t ruka_math_abs_i64(int64_t x) {
+            \\    return x < 0 ? -x : x;
+            \\}
+            \\
+            \\static inline int64_t ruka_math_min_i64(int64_t a, int64_t b) {
+            \\    return a < b ? a : b;
+            \\}
+            \\
+            \\static int64_t m;
+            \\static double r;
+            \\static int64_t n;
+            \\
+            \\int main(void) {
+            \\    m = ruka_math_max_i64(ruka_math_abs_i64(-3), 2);
+            \\    r = sqrt(2.0) + floor(1.5);
+            \\    n = ruka_math_min_i64(m, 4);
+            \\    printf("%s\n", "done");
+            \\    dprintf(2, "%s", "!");
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "sources generated for a target check its pointer size and byte order" {
+        var input = std.io.fixedBufferStream("let x = 1");
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var options = Unit.UnitOptions.testing(input.reader().any(), output.writer().any());
+        options.options.target = "s390x-linux-gnu";
+        options.options.debug_info = false;
+
+        var unit = try Unit.init(options);
+        defer unit.deinit();
+
+        const result = try unit.compile();
+        defer result.deinit();
+
+        try testing.expectEqualStrings(
+            \\#if UINTPTR_MAX != 0xffffffffffffffff
+            \\#error "generated for s390x-linux-gnu, whose pointers are 8 bytes"
+            \\#endif
+            \\#if defined(__BYTE_ORDER__) && __BYTE_ORDER__ != __ORDER_BIG_ENDIAN__
+            \\#error "generated for s390x-linux-gnu, which is big endian"
+            \\#endif
+            \\
+            \\static int64_t x;
+            \\
+            \\int main(void) {
+            \\    x = 1;
+            \\    return 0;
+            \\}
+            \\
+        , output.items[prelude.len..]);
+    }
+
+    test "statements are mapped to their lines in the source when debug info is enabled" {
+        const source =
+            \\let x = 1
+            \\fn f(n: i64) -> i64 {
+            \\    let y = n * 2
+            \\
+            \\    y + x
+            \\}
+            \\let z = f(x)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var options = Unit.UnitOptions.testing(input.reader().any(), output.writer().any());
+        options.input = "src/\"main\".ruka";
+        options.options.debug_info = true;
+
+        var unit = try Unit.init(options);
+        defer unit.deinit();
+
+        const result = try unit.compile();
+        defer result.deinit();
+
+        try testing.expectEqualStrings(
+            \\static int64_t x;
+            \\static int64_t z;
+            \\
+            \\int64_t f(int64_t n);
+            \\
+            \\int64_t f(int64_t n) {
+            \\#line 3 "src/\"main\".ruka"
+            \\    int64_t y = n * 2;
+            \\#line 5 "src/\"main\".ruka"
+            \\    return y + x;
+            \\}
+            \\
+            \\int main(void) {
+            \\#line 1 "src/\"main\".ruka"
+            \\    x = 1;
+            \\#line 7 "src/\"main\".ruka"
+            \\    z = f(x);
+            \\    return 0;
+            \\}
+            \\
+        , output.items[prelude.len..]);
+    }
+
+    test "unsupported constructs are reported" {
+        const source =
+            \\let s = "a" <> "b"
+            \\let r = 0..10
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.CodegenFailed, unit.compile());
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+        try testing.expectEqualStrings(
+            "values of type range(i64) are not supported by the c backend",
+            unit.diagnostics.items[0].message
+        );
+        try testing.expectEqualStrings(
+            "string concatenations are not supported by the c backend",
+            unit.diagnostics.items[1].message
+        );
+        try testing.expectEqualStrings(
+            "ranges outside of for loops are not supported by the c backend",
+            unit.diagnostics.items[2].message
+        );
+        try testing.expectEqual(0, output.items.len);
+    }
+
+    test "rebinding in the same scope is reported" {
+        const source =
+            \\let x = 1
+            \\let x = 2
+            \\fn f() -> i64 { let y = x; let y = y + 1; y }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        // The parser warns about both rebindings first
+        try testing.expectError(error.CodegenFailed, unit.compile());
+        try testing.expectEqual(4, unit.diagnostics.items.len);
+        try testing.expectEqual(.warning, unit.diagnostics.items[1].severity);
+        try testing.expectEqual(2, unit.diagnostics.items[2].span.pos.line);
+        try testing.expectEqual(3, unit.diagnostics.items[3].span.pos.line);
+        try testing.expectEqualStrings(
+            "bindings redefined in the same scope are not supported by the c backend",
+            unit.diagnostics.items[3].message
+        );
+    }
+
+    test "asserts are passed the source of their condition and where they are" {
+        const generated = try generateSource(
+            \\let x = 3
+            \\assert(x > 0)
+            \\assert(x < 5, "x is large")
+        );
+        defer testing.allocator.free(generated);
+
+        // Units compiled on their own print the condition, having no source to slice
+        try testing.expectEqualStrings(
+            \\static int64_t x;
+            \\
+            \\int main(void) {
+            \\    x = 3;
+            \\    ruka_assert(x > 0, "(x > 0)", NULL, "test source:2:1");
+            \\    ruka_assert(x < 5, "(x < 5)", "x is large", "test source:3:1");
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "fmt templates are translated to printf formats" {
+        const generated = try generateSource(
+            \\let x = 3
+            \\let s = fmt("{} is {:x}, {:.2}% {}", x, 255, 1.5, true)
+            \\let c = fmt("{} {}", 'c', s)
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\static int64_t x;
+            \\static const char *s;
+            \\static const char *c;
+            \\
+            \\int main(void) {
+            \\    x = 3;
+            \\    s = ruka_fmt("%lld is %llx, %.2f%% %s", (long long)(x), (unsigned long long)(255), (double)(1.5), (true) ? "true" : "false");
+            \\    c = ruka_fmt("%c %s", ('c'), (s));
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "arrays are generated as structs holding their elements" {
+        const generated = try generateSource(
+            \\fn second(a: [i64; 3]) -> i64 { a[1] }
+            \\let a = [1, 2, 3]
+            \\let b = second(a) + [4, 5][0]
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\typedef struct {
+            \\    int64_t items[3];
+            \\} ruka_array0;
+            \\
+            \\typedef struct {
+            \\    int64_t items[2];
+            \\} ruka_array1;
+            \\
+            \\static ruka_array0 a;
+            \\static int64_t b;
+            \\
+            \\int64_t second(ruka_array0 a);
+            \\
+            \\int64_t second(ruka_array0 a) {
+            \\    return a.items[1];
+            \\}
+            \\
+            \\int main(void) {
+            \\    a = (ruka_array0){{1, 2, 3}};
+            \\    b = second(a) + (ruka_array1){{4, 5}}.items[0];
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "unions are generated as C unions" {
+        const generated = try generateSource(
+            \\union Bits { i: i32, f: f32 }
+            \\fn bits_of(x: f32) -> i32 {
+            \\    let b = Bits { f: x }
+            \\    unsafe { b.i }
+            \\}
+            \\let one = bits_of(1.0)
+            \\let size = sizeof(Bits)
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\typedef union {
+            \\    int32_t i;
+            \\    float f;
+            \\} ruka_union0;
+            \\
+            \\static int32_t one;
+            \\static int64_t size;
+            \\
+            \\int32_t bits_of(float x);
+            \\
+            \\int32_t bits_of(float x) {
+            \\    ruka_union0 b = (ruka_union0){.f = x};
+            \\    {
+            \\        return b.i;
+            \\    }
+            \\}
+            \\
+            \\int main(void) {
+            \\    one = bits_of(1.0);
+            \\    size = (int64_t)sizeof(ruka_union0);
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "tuples are generated as structs with a field for each element" {
+        const generated = try generateSource(
+            \\fn swap(p: (i64, bool)) -> (bool, i64) { (p.1, p.0) }
+            \\let t = swap((1, true))
+            \\let n = t.1
+        );
+        defer testing.allocator.free(generated);
+
+        try testing.expectEqualStrings(
+            \\typedef struct {
+            \\    bool f0;
+            \\    int64_t f1;
+            \\} ruka_tuple0;
+            \\
+            \\typedef struct {
+            \\    int64_t f0;
+            \\    bool f1;
+            \\} ruka_tuple1;
+            \\
+            \\static ruka_tuple0 t;
+            \\static int64_t n;
+            \\
+            \\ruka_tuple0 swap(ruka_tuple1 p);
+            \\
+            \\ruka_tuple0 swap(ruka_tuple1 p) {
+            \\    return (ruka_tuple0){p.f1, p.f0};
+            \\}
+            \\
+            \\int main(void) {
+            \\    t = swap((ruka_tuple1){1, true});
+            \\    n = t.f1;
+            \\    return 0;
+            \\}
+            \\
+        , generated[prelude.len..]);
+    }
+
+    test "indexes are checked against the array's length when debug info is enabled" {
+        var input = std.io.fixedBufferStream("let a = [1, 2, 3]\nlet b = a[2]");
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var options = Unit.UnitOptions.testing(input.reader().any(), output.writer().any());
+        options.options.debug_info = true;
+
+        var unit = try Unit.init(options);
+        defer unit.deinit();
+
+        const result = try unit.compile();
+        defer result.deinit();
+
+        try testing.expect(std.mem.indexOf(u8, output.items, "b = a.items[ruka_check_index(2, 3, \"test source:2:9\")];") != null);
+    }
+
+    test "compiling tests generates a main running each of them" {
+        const source =
+            \\#[test]
+            \\fn adds() {
+            \\    assert(1 + 1 == 2)
+            \\}
+            \\#[test]
+            \\fn subtracts() {
+            \\    assert(2 - 1 == 1)
+            \\}
+            \\fn main() {}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var options = Unit.UnitOptions.testing(input.reader().any(), output.writer().any());
+        options.options.debug_info = false;
+        options.options.tests = true;
+
+        var unit = try Unit.init(options);
+        defer unit.deinit();
+
+        const result = try unit.compile();
+        result.deinit();
+
+        try testing.expectEqualStrings(prelude ++ test_prelude, output.items[0..prelude.len + test_prelude.len]);
+        try testing.expectStringEndsWith(output.items,
+            \\int main(void) {
+            \\    int ruka_failed = 0;
+            \\    ruka_failed = ruka_failed + ruka_run_test("adds", adds);
+            \\    ruka_failed = ruka_failed + ruka_run_test("subtracts", subtracts);
+            \\    printf("\\ntest result: %s. %d passed; %d failed\\n", ruka_failed > 0 ? "FAILED" : "ok", 2 - ruka_failed, ruka_failed);
+            \\    return ruka_failed > 0;
+            \\}
+            \\
+        );
+    }
+
+    test "tests taking parameters are reported" {
+        const source =
+            \\#[test]
+            \\fn takes(x: i64) {}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var options = Unit.UnitOptions.testing(input.reader().any(), output.writer().any());
+        options.options.tests = true;
+
+        var unit = try Unit.init(options);
+        defer unit.deinit();
+
+        try testing.expectError(error.CodegenFailed, unit.compile());
+        try testing.expectEqualStrings(
+            "tests must take no parameters or type parameters and return ()",
+            unit.diagnostics.items[0].message
+        );
+    }
+
+    test "generated source compiles and runs" {
+        try expectCompilesAndRuns(
+            \\let greeting = "hello world"
+            \\const length = 11
+            \\let matches = (length == 11) and (greeting == "hello world")
+        );
+    }
+
+    test "the fields of unions alias the same memory" {
+        try expectCompilesAndRuns(
+            \\union Bits { i: i32, f: f32 }
+            \\fn bits_of(x: f32) -> i32 {
+            \\    let b = Bits { f: x }
+            \\    unsafe { b.i }
+            \\}
+            \\assert(bits_of(1.0) == 1065353216)
+            \\assert(bits_of(2.0) == 1073741824)
+            \\assert(sizeof(Bits) == 4)
+        );
+    }
+
+    // Compiles the C generated for source with the system's C compiler, expecting
+    // the program to exit successfully
+    fn expectCompilesAndRuns(source: []const u8) !void {
+        const generated = try generateSource(source);
+        defer testing.allocator.free(generated);
+
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.writeFile(.{ .sub_path = "hello.c", .data = generated });
+
+        const cc = std.process.Child.run(.{
+            .allocator = testing.allocator,
+            .argv = &.{"cc", "-o", "hello", "hello.c"},
+            .cwd_dir = tmp.dir
+        }) catch |err| switch (err) {
+            // Only checked where a C compiler is installed
+            error.FileNotFound => return error.SkipZigTest,
+            else => return err
+        };
+        defer testing.allocator.free(cc.stdout);
+        defer testing.allocator.free(cc.stderr);
+
+        try testing.expectEqual(std.process.Child.Term{ .Exited = 0 }, cc.term);
+
+        const path = try tmp.dir.realpathAlloc(testing.allocator, "hello");
+        defer testing.allocator.free(path);
+
+        const run = try std.process.Child.run(.{
+            .allocator = testing.allocator,
+            .argv = &.{path}
+        });
+        defer testing.allocator.free(run.stdout);
+        defer testing.allocator.free(run.stderr);
+
+        try testing.expectEqual(std.process.Child.Term{ .Exited = 0 }, run.term);
+    }
+};
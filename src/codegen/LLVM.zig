@@ -0,0 +1,686 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+const ArrayList = std.ArrayList;
+
+const build_options = @import("options");
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Span = ruka.Span;
+const Type = ruka.Type;
+const TypeTable = ruka.TypeTable;
+const Unit = ruka.Unit;
+
+/// Whether the compiler was built with -Dllvm-backend, nothing else in this
+/// file may be used otherwise
+pub const enabled = build_options.llvm_backend;
+
+// The headers are only imported when the backend is enabled, so the compiler
+// builds without libLLVM installed
+const llvm = if (enabled) @cImport({
+    @cInclude("llvm-c/Analysis.h");
+    @cInclude("llvm-c/Core.h");
+    @cInclude("llvm-c/Target.h");
+    @cInclude("llvm-c/TargetMachine.h");
+}) else struct {
+    pub const LLVMContextRef = ?*opaque {};
+    pub const LLVMModuleRef = ?*opaque {};
+    pub const LLVMBuilderRef = ?*opaque {};
+    pub const LLVMTypeRef = ?*opaque {};
+    pub const LLVMValueRef = ?*opaque {};
+};
+
+context: llvm.LLVMContextRef,
+module: llvm.LLVMModuleRef,
+builder: llvm.LLVMBuilderRef,
+
+ast: *Ast,
+types: *const TypeTable,
+unit: *Unit,
+
+/// The bindings in scope, later ones shadow earlier ones with the same name
+locals: ArrayList(Local),
+/// The return type of the function currently being generated
+return_type: Type,
+/// Whether the function being generated is a main returning (), which returns
+/// an exit code instead
+in_main: bool,
+
+/// Owns the null terminated names passed to llvm
+arena: ArenaAllocator,
+allocator: Allocator,
+
+const LLVM = @This();
+
+const log = std.log.scoped(.codegen);
+
+// Explicit so the mutually recursive generating functions can resolve their error sets.
+// UnsupportedNode abandons the current function, after the construct has been reported
+const GenerateError = error{UnsupportedNode} || Allocator.Error;
+
+/// The stack slot of a binding
+const Local = struct {
+    name: []const u8,
+    pointer: llvm.LLVMValueRef,
+    @"type": llvm.LLVMTypeRef
+};
+
+pub fn init(unit: *Unit, ast: *Ast, types: *const TypeTable) !*LLVM {
+    const generator = try unit.allocator.create(LLVM);
+    errdefer unit.allocator.destroy(generator);
+
+    var arena = ArenaAllocator.init(unit.allocator);
+    errdefer arena.deinit();
+
+    const module_name = try arena.allocator().dupeZ(u8, unit.input);
+    const context = llvm.LLVMContextCreate();
+
+    generator.* = .{
+        .context = context,
+        .module = llvm.LLVMModuleCreateWithNameInContext(module_name, context),
+        .builder = llvm.LLVMCreateBuilderInContext(context),
+        .ast = ast,
+        .types = types,
+        .unit = unit,
+        .locals = .init(unit.allocator),
+        .return_type = .unit,
+        .in_main = false,
+        .arena = arena,
+        .allocator = unit.allocator
+    };
+
+    return generator;
+}
+
+pub fn deinit(self: *LLVM) void {
+    llvm.LLVMDisposeBuilder(self.builder);
+    llvm.LLVMDisposeModule(self.module);
+    llvm.LLVMContextDispose(self.context);
+    self.locals.deinit();
+    self.arena.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Generates the textual llvm ir of the ast, the caller owns the returned memory.
+/// Generation continues past constructs the backend doesn't support yet so all
+/// of them are recorded in the unit, but fails if any were encountered
+pub fn generate(self: *LLVM) ![]u8 {
+    const error_count = self.unit.errorCount();
+
+    if (self.unit.options.target) |triple| try self.setTarget(triple);
+    try self.generateModule(self.ast.root.kind.module);
+
+    const codegen_errors = self.unit.errorCount() - error_count;
+    if (codegen_errors > 0) {
+        log.err("{s}: code generation failed with {} error(s)", .{self.unit.input, codegen_errors});
+        return error.CodegenFailed;
+    }
+
+    // Ir llvm rejects is a bug in the backend rather than the program
+    var message: [*c]u8 = null;
+    defer if (message != null) llvm.LLVMDisposeMessage(message);
+    if (llvm.LLVMVerifyModule(self.module, llvm.LLVMReturnStatusAction, &message) != 0) {
+        log.err("{s}: generated invalid llvm ir: {s}", .{self.unit.input, message});
+        return error.CodegenFailed;
+    }
+
+    const ir = llvm.LLVMPrintModuleToString(self.module);
+    defer llvm.LLVMDisposeMessage(ir);
+
+    return try self.allocator.dupe(u8, std.mem.span(ir));
+}
+
+// Sets the module's triple and the data layout of a machine for it, so the ir is
+// generated for the target rather than the host
+fn setTarget(self: *LLVM, triple: []const u8) !void {
+    const triple_z = try self.arena.allocator().dupeZ(u8, triple);
+    llvm.LLVMSetTarget(self.module, triple_z);
+
+    llvm.LLVMInitializeAllTargetInfos();
+    llvm.LLVMInitializeAllTargets();
+    llvm.LLVMInitializeAllTargetMCs();
+
+    var target: llvm.LLVMTargetRef = null;
+    var message: [*c]u8 = null;
+    defer if (message != null) llvm.LLVMDisposeMessage(message);
+    if (llvm.LLVMGetTargetFromTriple(triple_z, &target, &message) != 0) {
+        return try self.unit.createErrorFmt(self.ast.root.span, "llvm can't generate code for {s}: {s}", .{triple, message});
+    }
+
+    const machine = llvm.LLVMCreateTargetMachine(
+        target,
+        triple_z,
+        "generic",
+        "",
+        llvm.LLVMCodeGenLevelDefault,
+        llvm.LLVMRelocDefault,
+        llvm.LLVMCodeModelDefault
+    );
+    defer llvm.LLVMDisposeTargetMachine(machine);
+
+    const layout = llvm.LLVMCreateTargetDataLayout(machine);
+    defer llvm.LLVMDisposeTargetData(layout);
+    llvm.LLVMSetModuleDataLayout(self.module, layout);
+}
+
+fn unsupported(self: *LLVM, span: Span, comptime construct: []const u8) GenerateError {
+    self.unit.createError(construct ++ " are not supported by the llvm backend", span) catch |err| return err;
+    return error.UnsupportedNode;
+}
+
+fn unsupportedType(self: *LLVM, span: Span, @"type": Type) GenerateError {
+    self.unit.createErrorFmt(
+        span,
+        "values of type {} are not supported by the llvm backend",
+        .{@"type"}
+    ) catch |err| return err;
+    return error.UnsupportedNode;
+}
+
+// Unsupported constructs only abandon the item containing them, so the rest are still checked
+fn recover(result: GenerateError!void) Allocator.Error!void {
+    result catch |err| switch (err) {
+        error.UnsupportedNode => {},
+        else => |e| return e
+    };
+}
+
+fn typeOf(self: *const LLVM, node: *const Node) Type {
+    return self.types.get(node.id) orelse .invalid;
+}
+
+fn bindingType(self: *const LLVM, node: *const Node, binding: Node.Binding) Type {
+    if (binding.annotation) |annotation| return self.typeOf(annotation);
+    if (binding.value) |value| return self.typeOf(value);
+
+    // Inferred from the values later assigned to the binding
+    return self.types.getBinding(node.id) orelse .invalid;
+}
+
+fn isMain(fn_def: Node.FunctionDef) bool {
+    return std.mem.eql(u8, fn_def.name, "main");
+}
+
+// Llvm only takes null terminated names
+fn cString(self: *LLVM, name: []const u8) GenerateError![*:0]const u8 {
+    return try self.arena.allocator().dupeZ(u8, name);
+}
+
+// The llvm type values of a type are represented by, null for types the backend doesn't support
+fn lowerType(self: *LLVM, @"type": Type) llvm.LLVMTypeRef {
+    return switch (@"type") {
+        .@"i8", .char => llvm.LLVMInt8TypeInContext(self.context),
+        .@"i16" => llvm.LLVMInt16TypeInContext(self.context),
+        .@"i32" => llvm.LLVMInt32TypeInContext(self.context),
+        .@"i64" => llvm.LLVMInt64TypeInContext(self.context),
+        .@"f32" => llvm.LLVMFloatTypeInContext(self.context),
+        .@"f64" => llvm.LLVMDoubleTypeInContext(self.context),
+        .@"bool" => llvm.LLVMInt1TypeInContext(self.context),
+        .unit => llvm.LLVMVoidTypeInContext(self.context),
+        .str, .function, .range, .array, .slice, .reference, .tuple, .@"struct", .@"enum", .trait, .future, .parameter, .variable, .invalid => null
+    };
+}
+
+fn valueType(self: *LLVM, span: Span, @"type": Type) GenerateError!llvm.LLVMTypeRef {
+    return self.lowerType(@"type") orelse return self.unsupportedType(span, @"type");
+}
+
+fn generateModule(self: *LLVM, module: Node.Module) GenerateError!void {
+    // Declared first so functions can call those defined after them
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def => |fn_def| try recover(self.declareFunction(item, fn_def)),
+            else => {}
+        }
+    }
+
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def => |fn_def| try recover(self.generateFunction(item, fn_def)),
+            .import => {},
+            .struct_def, .impl => try recover(self.unsupported(item.span, "structs")),
+            .union_def => try recover(self.unsupported(item.span, "unions")),
+            .trait_def => try recover(self.unsupported(item.span, "traits")),
+            .enum_def => try recover(self.unsupported(item.span, "enums")),
+            .extern_fn => try recover(self.unsupported(item.span, "extern functions")),
+            else => try recover(self.unsupported(item.span, "top level statements"))
+        }
+    }
+}
+
+fn declareFunction(self: *LLVM, node: *const Node, fn_def: Node.FunctionDef) GenerateError!void {
+    if (fn_def.type_params.len > 0) return self.unsupported(node.span, "generic functions");
+    if (fn_def.@"async") return self.unsupported(node.span, "async functions");
+
+    const function = self.typeOf(node).function;
+
+    const main = isMain(fn_def);
+    if (main and (function.params.len > 0 or !(function.ret.* == .unit or function.ret.isInteger()))) {
+        try self.unit.createError("main must take no parameters and return () or an integer", node.span);
+        return error.UnsupportedNode;
+    }
+
+    const params = try self.arena.allocator().alloc(llvm.LLVMTypeRef, function.params.len);
+    for (params, fn_def.params, function.params) |*param, definition, param_type| {
+        if (param_type == .unit) return self.unsupportedType(definition.span, param_type);
+        param.* = try self.valueType(definition.span, param_type);
+    }
+
+    // A main returning () still returns an exit code to the system
+    const ret = if (main and function.ret.* == .unit) llvm.LLVMInt32TypeInContext(self.context)
+        else try self.valueType(node.span, function.ret.*);
+
+    const fn_type = llvm.LLVMFunctionType(ret, params.ptr, @intCast(params.len), 0);
+    _ = llvm.LLVMAddFunction(self.module, try self.cString(fn_def.name), fn_type);
+}
+
+fn generateFunction(self: *LLVM, node: *const Node, fn_def: Node.FunctionDef) GenerateError!void {
+    // Functions missing from the module had unsupported signatures, which were
+    // reported when declaring them
+    const function = llvm.LLVMGetNamedFunction(self.module, try self.cString(fn_def.name))
+        orelse return error.UnsupportedNode;
+    const checked = self.typeOf(node).function;
+
+    self.return_type = checked.ret.*;
+    self.in_main = isMain(fn_def) and self.return_type == .unit;
+
+    const entry = llvm.LLVMAppendBasicBlockInContext(self.context, function, "entry");
+    llvm.LLVMPositionBuilderAtEnd(self.builder, entry);
+
+    self.locals.clearRetainingCapacity();
+    for (fn_def.params, checked.params, 0..) |param, param_type, i| {
+        try self.declareLocal(param.name, param_type, llvm.LLVMGetParam(function, @intCast(i)), param.span);
+    }
+
+    const value = try self.generateBlock(fn_def.body);
+
+    // The body's value is returned, unless it ended by returning
+    if (llvm.LLVMGetBasicBlockTerminator(llvm.LLVMGetInsertBlock(self.builder)) == null) {
+        // Only the code following a return has no value where one is expected
+        if (value == null and self.return_type != .unit) {
+            _ = llvm.LLVMBuildUnreachable(self.builder);
+        } else {
+            self.buildReturn(value);
+        }
+    }
+}
+
+// Returns value from the current function, it is null for () values
+fn buildReturn(self: *LLVM, value: llvm.LLVMValueRef) void {
+    if (self.return_type != .unit) {
+        _ = llvm.LLVMBuildRet(self.builder, value);
+    } else if (self.in_main) {
+        _ = llvm.LLVMBuildRet(self.builder, llvm.LLVMConstInt(llvm.LLVMInt32TypeInContext(self.context), 0, 0));
+    } else {
+        _ = llvm.LLVMBuildRetVoid(self.builder);
+    }
+}
+
+// Stores value in a new stack slot for name, value is null for bindings without one
+fn declareLocal(self: *LLVM, name: []const u8, @"type": Type, value: llvm.LLVMValueRef, span: Span) GenerateError!void {
+    if (@"type" == .unit) return self.unsupportedType(span, @"type");
+
+    const slot_type = try self.valueType(span, @"type");
+    const pointer = llvm.LLVMBuildAlloca(self.builder, slot_type, try self.cString(name));
+    if (value != null) _ = llvm.LLVMBuildStore(self.builder, value, pointer);
+
+    try self.locals.append(.{ .name = name, .pointer = pointer, .@"type" = slot_type });
+}
+
+fn lookup(self: *const LLVM, name: []const u8) ?Local {
+    var i = self.locals.items.len;
+    while (i > 0) {
+        i = i - 1;
+        if (std.mem.eql(u8, self.locals.items[i].name, name)) return self.locals.items[i];
+    }
+
+    return null;
+}
+
+// Generates the statements of a block, or node itself if it isn't one, returning
+// the value of the last statement
+fn generateBlock(self: *LLVM, node: *const Node) GenerateError!llvm.LLVMValueRef {
+    const statements = switch (node.kind) {
+        .block => |block| block.statements,
+        else => return try self.generateNode(node)
+    };
+
+    // Bindings go out of scope at the end of their block
+    const scope = self.locals.items.len;
+    defer self.locals.shrinkRetainingCapacity(scope);
+
+    var value: llvm.LLVMValueRef = null;
+    for (statements) |statement| value = try self.generateNode(statement);
+
+    return value;
+}
+
+/// Generates node, returning its value or null for () values
+fn generateNode(self: *LLVM, node: *const Node) GenerateError!llvm.LLVMValueRef {
+    return switch (node.kind) {
+        // Imports and exports are only valid at the top level, which the checker ensures
+        .module, .import, .@"export" => unreachable,
+        // Only written in annotations, whose types are known from the checker
+        .type_expr => unreachable,
+        .fn_def => self.unsupported(node.span, "nested functions"),
+        .struct_def, .impl, .struct_literal, .field_access => self.unsupported(node.span, "structs"),
+        .union_def => self.unsupported(node.span, "unions"),
+        .trait_def => self.unsupported(node.span, "traits"),
+        .enum_def, .variant_literal => self.unsupported(node.span, "enums"),
+        .@"try" => self.unsupported(node.span, "? operators"),
+        .array_literal, .index => self.unsupported(node.span, "arrays"),
+        .tuple_literal, .tuple_index => self.unsupported(node.span, "tuples"),
+        .destructure => self.unsupported(node.span, "destructuring bindings"),
+        .@"defer" => self.unsupported(node.span, "defers"),
+        .extern_fn => self.unsupported(node.span, "extern functions"),
+        .@"unsafe", .@"comptime" => |body| try self.generateNode(body),
+        .label, .goto => self.unsupported(node.span, "gotos"),
+        .reference, .dereference => self.unsupported(node.span, "references"),
+        .@"await" => self.unsupported(node.span, "async functions"),
+        .sizeof => self.unsupported(node.span, "sizeof of types other than the built in types"),
+        .cast => self.unsupported(node.span, "casts"),
+        .closure => self.unsupported(node.span, "closures"),
+        .var_decl, .const_decl => |binding| try self.generateBinding(node, binding),
+        .block => try self.generateBlock(node),
+        .@"if", .@"while", .@"for", .match, .@"break", .@"continue" => self.unsupported(node.span, "control flow expressions"),
+        .@"return" => |value| try self.generateReturn(value),
+        .assignment => |assignment| try self.generateAssignment(assignment),
+        .prefix => |prefix| try self.generatePrefix(node, prefix),
+        .infix => |infix| try self.generateInfix(node, infix),
+        .fn_call => |call| try self.generateCall(call.callee, call.arguments),
+        .identifier => |name| try self.generateIdentifier(node, name),
+        .literal => |literal| try self.generateLiteral(node, literal)
+    };
+}
+
+fn generateBinding(self: *LLVM, node: *const Node, binding: Node.Binding) GenerateError!llvm.LLVMValueRef {
+    const value = if (binding.value) |v| try self.generateNode(v) else null;
+
+    // Unit values have no representation, only their effects are kept
+    const @"type" = self.bindingType(node, binding);
+    if (@"type" != .unit) try self.declareLocal(binding.name, @"type", value, node.span);
+
+    return null;
+}
+
+fn generateReturn(self: *LLVM, value: ?*Node) GenerateError!llvm.LLVMValueRef {
+    self.buildReturn(if (value) |v| try self.generateNode(v) else null);
+
+    // The code following a return is unreachable, but still needs a block to be generated into
+    const function = llvm.LLVMGetBasicBlockParent(llvm.LLVMGetInsertBlock(self.builder));
+    const block = llvm.LLVMAppendBasicBlockInContext(self.context, function, "unreachable");
+    llvm.LLVMPositionBuilderAtEnd(self.builder, block);
+
+    return null;
+}
+
+fn generateAssignment(self: *LLVM, assignment: Node.Assignment) GenerateError!llvm.LLVMValueRef {
+    if (assignment.lhs.kind == .dereference) return self.unsupported(assignment.lhs.span, "references");
+
+    const value = try self.generateNode(assignment.rhs);
+    if (self.typeOf(assignment.lhs) == .unit) return null;
+
+    const local = self.lookup(assignment.lhs.kind.identifier)
+        orelse return self.unsupported(assignment.lhs.span, "assignments to top level bindings");
+    _ = llvm.LLVMBuildStore(self.builder, value, local.pointer);
+
+    return null;
+}
+
+fn generateIdentifier(self: *LLVM, node: *const Node, name: []const u8) GenerateError!llvm.LLVMValueRef {
+    if (self.typeOf(node) == .unit) return null;
+
+    const local = self.lookup(name)
+        orelse return self.unsupported(node.span, "references to top level bindings and functions");
+
+    return llvm.LLVMBuildLoad2(self.builder, local.@"type", local.pointer, try self.cString(name));
+}
+
+fn generateLiteral(self: *LLVM, node: *const Node, literal: Node.Literal) GenerateError!llvm.LLVMValueRef {
+    return switch (literal) {
+        .integer => |integer| llvm.LLVMConstInt(try self.valueType(node.span, self.typeOf(node)), @bitCast(integer), 1),
+        .float => |float| llvm.LLVMConstReal(try self.valueType(node.span, self.typeOf(node)), float),
+        .boolean => |boolean| llvm.LLVMConstInt(llvm.LLVMInt1TypeInContext(self.context), @intFromBool(boolean), 0),
+        .character => |character| llvm.LLVMConstInt(llvm.LLVMInt8TypeInContext(self.context), character, 0),
+        .string => self.unsupported(node.span, "strings"),
+        .unit => null
+    };
+}
+
+fn generatePrefix(self: *LLVM, node: *const Node, prefix: Node.Prefix) GenerateError!llvm.LLVMValueRef {
+    const operand = try self.generateNode(prefix.operand);
+
+    return switch (prefix.operator) {
+        .negate => if (self.typeOf(node).isFloat()) llvm.LLVMBuildFNeg(self.builder, operand, "")
+            else llvm.LLVMBuildNeg(self.builder, operand, ""),
+        .not, .bit_not => llvm.LLVMBuildNot(self.builder, operand, "")
+    };
+}
+
+fn generateInfix(self: *LLVM, node: *const Node, infix: Node.Infix) GenerateError!llvm.LLVMValueRef {
+    const operand = self.typeOf(infix.lhs);
+
+    switch (infix.operator) {
+        .forward_app => return try self.generateCall(infix.lhs, &.{infix.rhs}),
+        .reverse_app => return try self.generateCall(infix.rhs, &.{infix.lhs}),
+        .@"and", .@"or" => return self.unsupported(node.span, "short circuiting operators"),
+        .concat => return self.unsupported(node.span, "string concatenations"),
+        .range_exc, .range_inc => return self.unsupported(node.span, "ranges"),
+        .exponent => if (operand.isInteger()) return self.unsupported(node.span, "integer exponents"),
+        else => {}
+    }
+
+    if (operand == .unit) return self.unsupported(node.span, "comparisons of () values");
+
+    const lhs = try self.generateNode(infix.lhs);
+    const rhs = try self.generateNode(infix.rhs);
+    const b = self.builder;
+
+    if (operand.isFloat()) return switch (infix.operator) {
+        .add => llvm.LLVMBuildFAdd(b, lhs, rhs, ""),
+        .subtract => llvm.LLVMBuildFSub(b, lhs, rhs, ""),
+        .multiply => llvm.LLVMBuildFMul(b, lhs, rhs, ""),
+        .divide => llvm.LLVMBuildFDiv(b, lhs, rhs, ""),
+        .modulo => llvm.LLVMBuildFRem(b, lhs, rhs, ""),
+        .exponent => self.generatePow(self.lowerType(operand), lhs, rhs),
+        .lesser => llvm.LLVMBuildFCmp(b, llvm.LLVMRealOLT, lhs, rhs, ""),
+        .lesser_eq => llvm.LLVMBuildFCmp(b, llvm.LLVMRealOLE, lhs, rhs, ""),
+        .greater => llvm.LLVMBuildFCmp(b, llvm.LLVMRealOGT, lhs, rhs, ""),
+        .greater_eq => llvm.LLVMBuildFCmp(b, llvm.LLVMRealOGE, lhs, rhs, ""),
+        .equal => llvm.LLVMBuildFCmp(b, llvm.LLVMRealOEQ, lhs, rhs, ""),
+        .not_equal => llvm.LLVMBuildFCmp(b, llvm.LLVMRealUNE, lhs, rhs, ""),
+        // Bitwise operators are rejected for floats by the type checker
+        else => unreachable
+    };
+
+    // Booleans and characters are compared unsigned
+    const signed = operand.isInteger();
+
+    return switch (infix.operator) {
+        .add => llvm.LLVMBuildAdd(b, lhs, rhs, ""),
+        .subtract => llvm.LLVMBuildSub(b, lhs, rhs, ""),
+        .multiply => llvm.LLVMBuildMul(b, lhs, rhs, ""),
+        .divide => llvm.LLVMBuildSDiv(b, lhs, rhs, ""),
+        .modulo => llvm.LLVMBuildSRem(b, lhs, rhs, ""),
+        .bit_and => llvm.LLVMBuildAnd(b, lhs, rhs, ""),
+        .bit_or => llvm.LLVMBuildOr(b, lhs, rhs, ""),
+        .bit_xor => llvm.LLVMBuildXor(b, lhs, rhs, ""),
+        .lshift => llvm.LLVMBuildShl(b, lhs, rhs, ""),
+        .rshift => llvm.LLVMBuildAShr(b, lhs, rhs, ""),
+        .lesser => llvm.LLVMBuildICmp(b, if (signed) llvm.LLVMIntSLT else llvm.LLVMIntULT, lhs, rhs, ""),
+        .lesser_eq => llvm.LLVMBuildICmp(b, if (signed) llvm.LLVMIntSLE else llvm.LLVMIntULE, lhs, rhs, ""),
+        .greater => llvm.LLVMBuildICmp(b, if (signed) llvm.LLVMIntSGT else llvm.LLVMIntUGT, lhs, rhs, ""),
+        .greater_eq => llvm.LLVMBuildICmp(b, if (signed) llvm.LLVMIntSGE else llvm.LLVMIntUGE, lhs, rhs, ""),
+        .equal => llvm.LLVMBuildICmp(b, llvm.LLVMIntEQ, lhs, rhs, ""),
+        .not_equal => llvm.LLVMBuildICmp(b, llvm.LLVMIntNE, lhs, rhs, ""),
+        else => unreachable
+    };
+}
+
+// Float exponents are calls of llvm's pow intrinsic, as there's no instruction for them
+fn generatePow(self: *LLVM, operand: llvm.LLVMTypeRef, lhs: llvm.LLVMValueRef, rhs: llvm.LLVMValueRef) llvm.LLVMValueRef {
+    const name = "llvm.pow";
+    const id = llvm.LLVMLookupIntrinsicID(name, name.len);
+
+    var overloads = [_]llvm.LLVMTypeRef{operand};
+    const function = llvm.LLVMGetIntrinsicDeclaration(self.module, id, &overloads, overloads.len);
+    const fn_type = llvm.LLVMIntrinsicGetType(self.context, id, &overloads, overloads.len);
+
+    var arguments = [_]llvm.LLVMValueRef{lhs, rhs};
+    return llvm.LLVMBuildCall2(self.builder, fn_type, function, &arguments, arguments.len, "");
+}
+
+fn generateCall(self: *LLVM, callee: *const Node, arguments: []const *Node) GenerateError!llvm.LLVMValueRef {
+    const name = switch (callee.kind) {
+        .identifier => |identifier| identifier,
+        else => return self.unsupported(callee.span, "calls of values other than functions")
+    };
+
+    // Functions missing from the module had unsupported signatures, which were
+    // reported when declaring them, or are the prelude's assert, fmt or block_on
+    const function = llvm.LLVMGetNamedFunction(self.module, try self.cString(name)) orelse {
+        if (std.mem.eql(u8, name, "assert")) return self.unsupported(callee.span, "asserts");
+        if (std.mem.eql(u8, name, "fmt")) return self.unsupported(callee.span, "fmt calls");
+        if (std.mem.eql(u8, name, "block_on")) return self.unsupported(callee.span, "async functions");
+        return error.UnsupportedNode;
+    };
+    const fn_type = llvm.LLVMGlobalGetValueType(function);
+
+    const values = try self.arena.allocator().alloc(llvm.LLVMValueRef, arguments.len);
+    for (values, arguments) |*value, argument| value.* = try self.generateNode(argument);
+
+    const ret = llvm.LLVMGetReturnType(fn_type);
+    if (llvm.LLVMGetTypeKind(ret) == llvm.LLVMVoidTypeKind) {
+        _ = llvm.LLVMBuildCall2(self.builder, fn_type, function, values.ptr, @intCast(values.len), "");
+        return null;
+    }
+
+    return llvm.LLVMBuildCall2(self.builder, fn_type, function, values.ptr, @intCast(values.len), "");
+}
+
+test "llvm modules" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const AstBuilder = ruka.AstBuilder;
+
+    // Type checks program and generates its ir
+    fn generateIr(unit: *Unit, program: *Ast) ![]u8 {
+        var checker = try ruka.TypeChecker.init(unit, program);
+        defer checker.deinit();
+
+        const types = try checker.check();
+        defer types.deinit();
+
+        var generator = try LLVM.init(unit, program, types);
+        defer generator.deinit();
+
+        return try generator.generate();
+    }
+
+    test "straight line arithmetic functions" {
+        if (!enabled) return error.SkipZigTest;
+
+        var input = std.io.fixedBufferStream("");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var program = try Ast.init(testing.allocator, "test source");
+        defer program.deinit();
+
+        const b = AstBuilder.init(program);
+
+        // fn add(a: i64, b: i64) -> i64 { let sum = a + b; sum * 2 }
+        const add = try b.function("add", &.{
+            .{"a", try b.identifier("i64")},
+            .{"b", try b.identifier("i64")}
+        }, try b.identifier("i64"), try b.block(&.{
+            try b.constant("sum", null, try b.infix(.add, try b.identifier("a"), try b.identifier("b"))),
+            try b.infix(.multiply, try b.identifier("sum"), try b.integer(2))
+        }));
+
+        // fn twice() -> i64 { add(1, 2) - 3 }
+        const twice = try b.function("twice", &.{}, try b.identifier("i64"), try b.block(&.{
+            try b.infix(.subtract, try b.call("add", &.{try b.integer(1), try b.integer(2)}), try b.integer(3))
+        }));
+
+        try b.module(&.{add, twice});
+
+        const ir = try generateIr(unit, program);
+        defer testing.allocator.free(ir);
+
+        try testing.expect(std.mem.indexOf(u8, ir, "define i64 @add(i64 %0, i64 %1)") != null);
+        try testing.expect(std.mem.indexOf(u8, ir, "add i64") != null);
+        try testing.expect(std.mem.indexOf(u8, ir, "mul i64") != null);
+        try testing.expect(std.mem.indexOf(u8, ir, "define i64 @twice()") != null);
+        try testing.expect(std.mem.indexOf(u8, ir, "call i64 @add(i64 1, i64 2)") != null);
+        try testing.expect(std.mem.indexOf(u8, ir, "sub i64") != null);
+        try testing.expect(std.mem.indexOf(u8, ir, "ret i64") != null);
+    }
+
+    test "unsupported constructs are reported" {
+        if (!enabled) return error.SkipZigTest;
+
+        var input = std.io.fixedBufferStream("");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var program = try Ast.init(testing.allocator, "test source");
+        defer program.deinit();
+
+        const b = AstBuilder.init(program);
+
+        // fn pick(a: i64) -> i64 { if a < 0 { 0 } else { a } }
+        const pick = try b.function("pick", &.{.{"a", try b.identifier("i64")}}, try b.identifier("i64"), try b.block(&.{
+            try b.conditional(
+                try b.infix(.lesser, try b.identifier("a"), try b.integer(0)),
+                try b.block(&.{try b.integer(0)}),
+                try b.block(&.{try b.identifier("a")})
+            )
+        }));
+
+        try b.module(&.{pick});
+
+        try testing.expectError(error.CodegenFailed, generateIr(unit, program));
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqualStrings(
+            "control flow expressions are not supported by the llvm backend",
+            unit.diagnostics.items[0].message
+        );
+    }
+
+    test "emitting without the backend fails" {
+        if (enabled) return error.SkipZigTest;
+
+        var input = std.io.fixedBufferStream("let x = 1");
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var opts: Unit.UnitOptions = .testing(input.reader().any(), output.writer().any());
+        opts.options.emit = .llvm_ir;
+
+        var unit = try Unit.init(opts);
+        defer unit.deinit();
+
+        try testing.expectError(error.BackendUnavailable, unit.compile());
+        try testing.expectEqual(0, output.items.len);
+    }
+};
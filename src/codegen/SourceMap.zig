@@ -0,0 +1,132 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Maps ranges of generated code back to the spans of the source they were
+//! generated from, so tools given positions in the generated code, such as the
+//! errors of a c compiler, can find them in the source
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("../prelude.zig");
+const Span = ruka.Span;
+
+/// The generated file the ranges are within
+file: []const u8,
+/// Ordered by the start of their ranges, a statement's mapping precedes those of
+/// the statements nested within it
+mappings: ArrayList(Mapping),
+
+const SourceMap = @This();
+
+pub const Mapping = struct {
+    /// The range of bytes in the generated code, the end is exclusive
+    gen_start: usize,
+    gen_end: usize,
+    orig_span: Span
+};
+
+pub fn init(allocator: Allocator, file: []const u8) SourceMap {
+    return SourceMap {
+        .file = file,
+        .mappings = .init(allocator)
+    };
+}
+
+pub fn deinit(self: *SourceMap) void {
+    self.mappings.deinit();
+}
+
+/// Moves the ranges of the mappings from index on by offset, after the code they
+/// map was moved
+pub fn shift(self: *SourceMap, index: usize, offset: usize) void {
+    for (self.mappings.items[index..]) |*mapping| {
+        mapping.gen_start = mapping.gen_start + offset;
+        mapping.gen_end = mapping.gen_end + offset;
+    }
+}
+
+/// The span of the innermost mapping containing the byte at offset in the
+/// generated code, null if no mapping does
+pub fn find(self: *const SourceMap, offset: usize) ?Span {
+    var found: ?Span = null;
+    for (self.mappings.items) |mapping| {
+        if (mapping.gen_start > offset) break;
+        if (offset < mapping.gen_end) found = mapping.orig_span;
+    }
+
+    return found;
+}
+
+/// Writes the source map as a json object holding the file and the mappings,
+/// each span is an object like those of the ast's json
+pub fn writeJson(self: *const SourceMap, writer: anytype) @TypeOf(writer).Error!void {
+    try writer.writeAll("{\"file\":");
+    try std.json.encodeJsonString(self.file, .{}, writer);
+    try writer.writeAll(",\"mappings\":[");
+
+    for (self.mappings.items, 0..) |mapping, i| {
+        if (i > 0) try writer.writeByte(',');
+
+        const span = mapping.orig_span;
+        try writer.print("{{\"gen_start\":{},\"gen_end\":{},\"orig_span\":{{\"file\":", .{mapping.gen_start, mapping.gen_end});
+        try std.json.encodeJsonString(span.file, .{}, writer);
+        try writer.print(",\"start\":{},\"end\":{},\"line\":{},\"column\":{}}}}}", .{
+            span.start,
+            span.end,
+            span.pos.line,
+            span.pos.col
+        });
+    }
+
+    try writer.writeAll("]}\n");
+}
+
+test "source map" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "the innermost mapping containing an offset is found" {
+        var map = SourceMap.init(testing.allocator, "main.c");
+        defer map.deinit();
+
+        try map.mappings.appendSlice(&.{
+            .{ .gen_start = 0, .gen_end = 20, .orig_span = .{ .file = "main.ruka", .pos = .init(1, 1) } },
+            .{ .gen_start = 5, .gen_end = 10, .orig_span = .{ .file = "main.ruka", .pos = .init(2, 5) } },
+            .{ .gen_start = 30, .gen_end = 40, .orig_span = .{ .file = "main.ruka", .pos = .init(4, 1) } }
+        });
+
+        try testing.expectEqual(1, map.find(2).?.pos.line);
+        try testing.expectEqual(2, map.find(7).?.pos.line);
+        try testing.expectEqual(1, map.find(12).?.pos.line);
+        try testing.expectEqual(null, map.find(25));
+
+        map.shift(1, 100);
+        try testing.expectEqual(1, map.find(7).?.pos.line);
+        try testing.expectEqual(4, map.find(135).?.pos.line);
+    }
+
+    test "source maps are written as json" {
+        var map = SourceMap.init(testing.allocator, "main.c");
+        defer map.deinit();
+
+        try map.mappings.append(.{
+            .gen_start = 3,
+            .gen_end = 9,
+            .orig_span = .{ .file = "main.ruka", .start = 4, .end = 9, .pos = .init(2, 1) }
+        });
+
+        var json = ArrayList(u8).init(testing.allocator);
+        defer json.deinit();
+
+        try map.writeJson(json.writer());
+        try testing.expectEqualStrings(
+            \\{"file":"main.c","mappings":[{"gen_start":3,"gen_end":9,"orig_span":{"file":"main.ruka","start":4,"end":9,"line":2,"column":1}}]}
+            \\
+        , json.items);
+    }
+};
@@ -0,0 +1,976 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+
+const build_options = @import("options");
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Span = ruka.Span;
+const Type = ruka.Type;
+const TypeTable = ruka.TypeTable;
+const Unit = ruka.Unit;
+
+/// Whether the compiler was built with -Dwasm-backend
+pub const enabled = build_options.wasm_backend;
+
+ast: *Ast,
+types: *const TypeTable,
+unit: *Unit,
+
+/// The functions of the module, in the order of their indices
+functions: ArrayList(Function),
+/// The indices of the functions, keyed by name
+indices: StringHashMap(u32),
+/// The instructions of the function being generated
+code: ArrayList(u8),
+/// The bindings in scope, later ones shadow earlier ones with the same name
+locals: ArrayList(Local),
+/// The types of the locals of the function being generated, indexed by local,
+/// starting with its parameters
+local_types: ArrayList(Valtype),
+/// The number of blocks enclosing the instruction being generated, which
+/// branches count outwards from
+depth: u32,
+/// The depths of the enclosing loops' labels, innermost last
+loops: ArrayList(u32),
+
+/// Owns the signatures and bodies of the functions
+arena: ArenaAllocator,
+allocator: Allocator,
+
+const Wasm = @This();
+
+const log = std.log.scoped(.codegen);
+
+// Explicit so the mutually recursive generating functions can resolve their error sets.
+// UnsupportedNode abandons the current function, after the construct has been reported
+const GenerateError = error{UnsupportedNode} || Allocator.Error;
+
+const magic = "\x00asm";
+const version = [_]u8{1, 0, 0, 0};
+
+/// The types of wasm values
+const Valtype = enum(u8) {
+    i32 = 0x7f,
+    i64 = 0x7e,
+    f32 = 0x7d,
+    f64 = 0x7c
+};
+
+const SectionId = enum(u8) {
+    type = 1,
+    function = 3,
+    @"export" = 7,
+    code = 10
+};
+
+// The block type of blocks leaving no value
+const empty_block = 0x40;
+
+const Opcode = enum(u8) {
+    @"unreachable" = 0x00,
+    block = 0x02,
+    loop = 0x03,
+    @"if" = 0x04,
+    @"else" = 0x05,
+    end = 0x0b,
+    br = 0x0c,
+    br_if = 0x0d,
+    @"return" = 0x0f,
+    call = 0x10,
+    drop = 0x1a,
+    local_get = 0x20,
+    local_set = 0x21,
+    i32_const = 0x41,
+    i64_const = 0x42,
+    f32_const = 0x43,
+    f64_const = 0x44,
+    i32_eqz = 0x45,
+    i32_eq = 0x46,
+    i32_ne = 0x47,
+    i32_lt_s = 0x48,
+    i32_lt_u = 0x49,
+    i32_gt_s = 0x4a,
+    i32_gt_u = 0x4b,
+    i32_le_s = 0x4c,
+    i32_le_u = 0x4d,
+    i32_ge_s = 0x4e,
+    i32_ge_u = 0x4f,
+    i64_eq = 0x51,
+    i64_ne = 0x52,
+    i64_lt_s = 0x53,
+    i64_lt_u = 0x54,
+    i64_gt_s = 0x55,
+    i64_gt_u = 0x56,
+    i64_le_s = 0x57,
+    i64_le_u = 0x58,
+    i64_ge_s = 0x59,
+    i64_ge_u = 0x5a,
+    f32_eq = 0x5b,
+    f32_ne = 0x5c,
+    f32_lt = 0x5d,
+    f32_gt = 0x5e,
+    f32_le = 0x5f,
+    f32_ge = 0x60,
+    f64_eq = 0x61,
+    f64_ne = 0x62,
+    f64_lt = 0x63,
+    f64_gt = 0x64,
+    f64_le = 0x65,
+    f64_ge = 0x66,
+    i32_add = 0x6a,
+    i32_sub = 0x6b,
+    i32_mul = 0x6c,
+    i32_div_s = 0x6d,
+    i32_rem_s = 0x6f,
+    i32_and = 0x71,
+    i32_or = 0x72,
+    i32_xor = 0x73,
+    i32_shl = 0x74,
+    i32_shr_s = 0x75,
+    i64_add = 0x7c,
+    i64_sub = 0x7d,
+    i64_mul = 0x7e,
+    i64_div_s = 0x7f,
+    i64_rem_s = 0x81,
+    i64_and = 0x83,
+    i64_or = 0x84,
+    i64_xor = 0x85,
+    i64_shl = 0x86,
+    i64_shr_s = 0x87,
+    f32_neg = 0x8c,
+    f32_add = 0x92,
+    f32_sub = 0x93,
+    f32_mul = 0x94,
+    f32_div = 0x95,
+    f64_neg = 0x9a,
+    f64_add = 0xa0,
+    f64_sub = 0xa1,
+    f64_mul = 0xa2,
+    f64_div = 0xa3,
+    i32_extend8_s = 0xc0,
+    i32_extend16_s = 0xc1
+};
+
+/// A function defined at the top level, which the module holds under its index
+const Function = struct {
+    fn_def: Node.FunctionDef,
+    params: []const Valtype,
+    /// Null for functions returning ()
+    ret: ?Valtype,
+    /// Whether it was declared pub, exporting it from the module
+    exported: bool,
+    /// Its locals and instructions, encoded as the code section holds them
+    body: []const u8 = &.{}
+};
+
+/// A binding and the local holding its value
+const Local = struct {
+    name: []const u8,
+    index: u32
+};
+
+pub fn init(unit: *Unit, ast: *Ast, types: *const TypeTable) !*Wasm {
+    const generator = try unit.allocator.create(Wasm);
+
+    generator.* = .{
+        .ast = ast,
+        .types = types,
+        .unit = unit,
+        .functions = .init(unit.allocator),
+        .indices = .init(unit.allocator),
+        .code = .init(unit.allocator),
+        .locals = .init(unit.allocator),
+        .local_types = .init(unit.allocator),
+        .depth = 0,
+        .loops = .init(unit.allocator),
+        .arena = .init(unit.allocator),
+        .allocator = unit.allocator
+    };
+
+    return generator;
+}
+
+pub fn deinit(self: *Wasm) void {
+    self.functions.deinit();
+    self.indices.deinit();
+    self.code.deinit();
+    self.locals.deinit();
+    self.local_types.deinit();
+    self.loops.deinit();
+    self.arena.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Generates the binary wasm module of the ast, the caller owns the returned
+/// memory. Generation continues past constructs the backend doesn't support yet
+/// so all of them are recorded in the unit, but fails if any were encountered
+pub fn generate(self: *Wasm) ![]u8 {
+    const error_count = self.unit.errorCount();
+
+    try self.generateModule(self.ast.root.kind.module);
+
+    const codegen_errors = self.unit.errorCount() - error_count;
+    if (codegen_errors > 0) {
+        log.err("{s}: code generation failed with {} error(s)", .{self.unit.input, codegen_errors});
+        return error.CodegenFailed;
+    }
+
+    return try self.writeModule();
+}
+
+fn unsupported(self: *Wasm, span: Span, comptime construct: []const u8) GenerateError {
+    self.unit.createError(construct ++ " are not supported by the wasm backend", span) catch |err| return err;
+    return error.UnsupportedNode;
+}
+
+fn unsupportedType(self: *Wasm, span: Span, @"type": Type) GenerateError {
+    self.unit.createErrorFmt(
+        span,
+        "values of type {} are not supported by the wasm backend",
+        .{@"type"}
+    ) catch |err| return err;
+    return error.UnsupportedNode;
+}
+
+// Unsupported constructs only abandon the item containing them, so the rest are still checked
+fn recover(result: GenerateError!void) Allocator.Error!void {
+    result catch |err| switch (err) {
+        error.UnsupportedNode => {},
+        else => |e| return e
+    };
+}
+
+fn typeOf(self: *const Wasm, node: *const Node) Type {
+    return self.types.get(node.id) orelse .invalid;
+}
+
+fn bindingType(self: *const Wasm, node: *const Node, binding: Node.Binding) Type {
+    if (binding.annotation) |annotation| return self.typeOf(annotation);
+    if (binding.value) |value| return self.typeOf(value);
+
+    // Inferred from the values later assigned to the binding
+    return self.types.getBinding(node.id) orelse .invalid;
+}
+
+// The wasm type values of a type are represented by, null for types the backend
+// doesn't support. Integers narrower than 32 bits are held in i32s
+fn lowerType(@"type": Type) ?Valtype {
+    return switch (@"type") {
+        .@"i8", .@"i16", .@"i32", .@"bool", .char => .i32,
+        .@"i64" => .i64,
+        .@"f32" => .f32,
+        .@"f64" => .f64,
+        .str, .unit, .function, .range, .array, .slice, .reference, .tuple, .@"struct", .@"enum", .trait, .future, .parameter, .variable, .invalid => null
+    };
+}
+
+fn valueType(self: *Wasm, span: Span, @"type": Type) GenerateError!Valtype {
+    return lowerType(@"type") orelse return self.unsupportedType(span, @"type");
+}
+
+// Whether generating node leaves a value on the stack, () values have no representation
+fn pushes(self: *const Wasm, node: *const Node) bool {
+    return switch (node.kind) {
+        .var_decl, .const_decl, .assignment, .@"return", .@"while", .@"break", .@"continue" => false,
+        .block => |block| block.statements.len > 0 and self.pushes(block.statements[block.statements.len - 1]),
+        else => self.typeOf(node) != .unit
+    };
+}
+
+fn emit(self: *Wasm, opcode: Opcode) GenerateError!void {
+    try self.code.append(@intFromEnum(opcode));
+}
+
+fn emitUnsigned(self: *Wasm, value: u64) GenerateError!void {
+    try std.leb.writeUleb128(self.code.writer(), value);
+}
+
+fn emitSigned(self: *Wasm, value: i64) GenerateError!void {
+    try std.leb.writeIleb128(self.code.writer(), value);
+}
+
+// Opens a block, loop or if, whose branches are counted from the innermost
+fn emitBlock(self: *Wasm, opcode: Opcode, result: ?Valtype) GenerateError!void {
+    try self.emit(opcode);
+    try self.code.append(if (result) |valtype| @intFromEnum(valtype) else empty_block);
+    self.depth = self.depth + 1;
+}
+
+fn emitEnd(self: *Wasm) GenerateError!void {
+    try self.emit(.end);
+    self.depth = self.depth - 1;
+}
+
+fn generateModule(self: *Wasm, module: Node.Module) GenerateError!void {
+    // Declared first so functions can call those defined after them
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def => |fn_def| try recover(self.declareFunction(item, fn_def, exported.kind == .@"export")),
+            else => {}
+        }
+    }
+
+    for (module.items) |exported| {
+        const item = exported.unwrapExport();
+        switch (item.kind) {
+            .fn_def => |fn_def| if (self.indices.get(fn_def.name)) |index| {
+                try recover(self.generateFunction(&self.functions.items[index]));
+            },
+            .import => {},
+            .struct_def, .impl => try recover(self.unsupported(item.span, "structs")),
+            .union_def => try recover(self.unsupported(item.span, "unions")),
+            .trait_def => try recover(self.unsupported(item.span, "traits")),
+            .enum_def => try recover(self.unsupported(item.span, "enums")),
+            .extern_fn => try recover(self.unsupported(item.span, "extern functions")),
+            else => try recover(self.unsupported(item.span, "top level statements"))
+        }
+    }
+}
+
+fn declareFunction(self: *Wasm, node: *const Node, fn_def: Node.FunctionDef, exported: bool) GenerateError!void {
+    if (fn_def.type_params.len > 0) return self.unsupported(node.span, "generic functions");
+    if (fn_def.@"async") return self.unsupported(node.span, "async functions");
+
+    const function = self.typeOf(node).function;
+
+    const params = try self.arena.allocator().alloc(Valtype, function.params.len);
+    for (params, fn_def.params, function.params) |*param, definition, param_type| {
+        param.* = try self.valueType(definition.span, param_type);
+    }
+
+    const ret = if (function.ret.* == .unit) null else try self.valueType(node.span, function.ret.*);
+
+    try self.indices.put(fn_def.name, @intCast(self.functions.items.len));
+    try self.functions.append(.{
+        .fn_def = fn_def,
+        .params = params,
+        .ret = ret,
+        .exported = exported
+    });
+}
+
+fn generateFunction(self: *Wasm, function: *Function) GenerateError!void {
+    self.code.clearRetainingCapacity();
+    self.locals.clearRetainingCapacity();
+    self.local_types.clearRetainingCapacity();
+    self.loops.clearRetainingCapacity();
+    self.depth = 0;
+
+    for (function.fn_def.params, function.params, 0..) |param, valtype, i| {
+        try self.locals.append(.{ .name = param.name, .index = @intCast(i) });
+        try self.local_types.append(valtype);
+    }
+
+    const body = function.fn_def.body;
+    try self.generateBlock(body);
+
+    // The body's value is returned, code following a return has no value where
+    // one is expected
+    if (self.pushes(body) and function.ret == null) try self.emit(.drop);
+    if (!self.pushes(body) and function.ret != null) try self.emit(.@"unreachable");
+    try self.emit(.end);
+
+    // Each local after the parameters is declared on its own
+    var encoded = ArrayList(u8).init(self.arena.allocator());
+    const writer = encoded.writer();
+
+    const declared = self.local_types.items[function.params.len..];
+    try std.leb.writeUleb128(writer, declared.len);
+    for (declared) |valtype| {
+        try std.leb.writeUleb128(writer, @as(u32, 1));
+        try writer.writeByte(@intFromEnum(valtype));
+    }
+    try encoded.appendSlice(self.code.items);
+
+    function.body = encoded.items;
+}
+
+// Creates a local for name, which holds values of type
+fn declareLocal(self: *Wasm, name: []const u8, @"type": Type, span: Span) GenerateError!u32 {
+    const index: u32 = @intCast(self.local_types.items.len);
+    try self.local_types.append(try self.valueType(span, @"type"));
+    try self.locals.append(.{ .name = name, .index = index });
+
+    return index;
+}
+
+fn lookup(self: *const Wasm, name: []const u8) ?Local {
+    var i = self.locals.items.len;
+    while (i > 0) {
+        i = i - 1;
+        if (std.mem.eql(u8, self.locals.items[i].name, name)) return self.locals.items[i];
+    }
+
+    return null;
+}
+
+// Generates the statements of a block, or node itself if it isn't one, leaving
+// the value of the last statement on the stack
+fn generateBlock(self: *Wasm, node: *const Node) GenerateError!void {
+    const statements = switch (node.kind) {
+        .block => |block| block.statements,
+        else => return try self.generateNode(node)
+    };
+
+    // Bindings go out of scope at the end of their block
+    const scope = self.locals.items.len;
+    defer self.locals.shrinkRetainingCapacity(scope);
+
+    for (statements, 0..) |statement, i| {
+        try self.generateNode(statement);
+        if (i + 1 < statements.len and self.pushes(statement)) try self.emit(.drop);
+    }
+}
+
+// Generates a branch of an if or a loop's body, keeping its value only if the
+// construct has one
+fn generateBranch(self: *Wasm, node: *const Node, keep: bool) GenerateError!void {
+    try self.generateBlock(node);
+    if (!keep and self.pushes(node)) try self.emit(.drop);
+}
+
+fn generateNode(self: *Wasm, node: *const Node) GenerateError!void {
+    return switch (node.kind) {
+        // Imports and exports are only valid at the top level, which the checker ensures
+        .module, .import, .@"export" => unreachable,
+        // Only written in annotations, whose types are known from the checker
+        .type_expr => unreachable,
+        .fn_def => self.unsupported(node.span, "nested functions"),
+        .struct_def, .impl, .struct_literal, .field_access => self.unsupported(node.span, "structs"),
+        .union_def => self.unsupported(node.span, "unions"),
+        .trait_def => self.unsupported(node.span, "traits"),
+        .enum_def, .variant_literal => self.unsupported(node.span, "enums"),
+        .@"try" => self.unsupported(node.span, "? operators"),
+        .array_literal, .index => self.unsupported(node.span, "arrays"),
+        .tuple_literal, .tuple_index => self.unsupported(node.span, "tuples"),
+        .destructure => self.unsupported(node.span, "destructuring bindings"),
+        .@"defer" => self.unsupported(node.span, "defers"),
+        .extern_fn => self.unsupported(node.span, "extern functions"),
+        .@"unsafe", .@"comptime" => |body| try self.generateNode(body),
+        .label, .goto => self.unsupported(node.span, "gotos"),
+        .reference, .dereference => self.unsupported(node.span, "references"),
+        .@"await" => self.unsupported(node.span, "async functions"),
+        .sizeof => self.unsupported(node.span, "sizeof of types other than the built in types"),
+        .cast => self.unsupported(node.span, "casts"),
+        .closure => self.unsupported(node.span, "closures"),
+        .@"for" => self.unsupported(node.span, "for loops"),
+        .match => self.unsupported(node.span, "match expressions"),
+        .var_decl, .const_decl => |binding| try self.generateBinding(node, binding),
+        .block => try self.generateBlock(node),
+        .@"if" => |conditional| try self.generateIf(node, conditional),
+        .@"while" => |loop| try self.generateWhile(loop),
+        .@"break" => |value| try self.generateBreak(node, value),
+        .@"continue" => try self.generateContinue(),
+        .@"return" => |value| try self.generateReturn(value),
+        .assignment => |assignment| try self.generateAssignment(assignment),
+        .prefix => |prefix| try self.generatePrefix(node, prefix),
+        .infix => |infix| try self.generateInfix(node, infix),
+        .fn_call => |call| try self.generateCall(call.callee, call.arguments),
+        .identifier => |name| try self.generateIdentifier(node, name),
+        .literal => |literal| try self.generateLiteral(node, literal)
+    };
+}
+
+fn generateBinding(self: *Wasm, node: *const Node, binding: Node.Binding) GenerateError!void {
+    if (binding.value) |value| try self.generateNode(value);
+
+    // Unit values have no representation, only their effects are kept
+    const @"type" = self.bindingType(node, binding);
+    if (@"type" == .unit) return;
+
+    const index = try self.declareLocal(binding.name, @"type", node.span);
+    if (binding.value != null) {
+        try self.emit(.local_set);
+        try self.emitUnsigned(index);
+    }
+}
+
+fn generateIf(self: *Wasm, node: *const Node, conditional: Node.If) GenerateError!void {
+    try self.generateNode(conditional.condition);
+
+    const result = if (self.pushes(node)) try self.valueType(node.span, self.typeOf(node)) else null;
+    try self.emitBlock(.@"if", result);
+
+    try self.generateBranch(conditional.then_block, result != null);
+    if (conditional.else_block) |else_block| {
+        try self.emit(.@"else");
+        try self.generateBranch(else_block, result != null);
+    }
+
+    try self.emitEnd();
+}
+
+// Loops are a block to break out of around a loop to continue, which exits once
+// the condition is false
+fn generateWhile(self: *Wasm, loop: Node.While) GenerateError!void {
+    try self.emitBlock(.block, null);
+    try self.emitBlock(.loop, null);
+
+    try self.loops.append(self.depth);
+    defer _ = self.loops.orderedRemove(self.loops.items.len - 1);
+
+    try self.generateNode(loop.condition);
+    try self.emit(.i32_eqz);
+    try self.emit(.br_if);
+    try self.emitUnsigned(1);
+
+    try self.generateBranch(loop.body, false);
+    try self.emit(.br);
+    try self.emitUnsigned(0);
+
+    try self.emitEnd();
+    try self.emitEnd();
+}
+
+fn generateBreak(self: *Wasm, node: *const Node, value: ?*Node) GenerateError!void {
+    if (value != null) return self.unsupported(node.span, "breaks with values");
+
+    // The block around the innermost loop
+    try self.emit(.br);
+    try self.emitUnsigned(self.depth - self.loops.items[self.loops.items.len - 1] + 1);
+}
+
+fn generateContinue(self: *Wasm) GenerateError!void {
+    try self.emit(.br);
+    try self.emitUnsigned(self.depth - self.loops.items[self.loops.items.len - 1]);
+}
+
+fn generateReturn(self: *Wasm, value: ?*Node) GenerateError!void {
+    if (value) |v| try self.generateNode(v);
+    try self.emit(.@"return");
+}
+
+fn generateAssignment(self: *Wasm, assignment: Node.Assignment) GenerateError!void {
+    const name = switch (assignment.lhs.kind) {
+        .identifier => |identifier| identifier,
+        .dereference => return self.unsupported(assignment.lhs.span, "references"),
+        else => return self.unsupported(assignment.lhs.span, "structs")
+    };
+
+    try self.generateNode(assignment.rhs);
+    if (self.typeOf(assignment.lhs) == .unit) return;
+
+    const local = self.lookup(name)
+        orelse return self.unsupported(assignment.lhs.span, "assignments to top level bindings");
+    try self.emit(.local_set);
+    try self.emitUnsigned(local.index);
+}
+
+fn generateIdentifier(self: *Wasm, node: *const Node, name: []const u8) GenerateError!void {
+    if (self.typeOf(node) == .unit) return;
+
+    const local = self.lookup(name)
+        orelse return self.unsupported(node.span, "references to top level bindings and functions");
+    try self.emit(.local_get);
+    try self.emitUnsigned(local.index);
+}
+
+fn generateLiteral(self: *Wasm, node: *const Node, literal: Node.Literal) GenerateError!void {
+    const writer = self.code.writer();
+
+    switch (literal) {
+        .integer => |integer| switch (try self.valueType(node.span, self.typeOf(node))) {
+            .i64 => {
+                try self.emit(.i64_const);
+                try self.emitSigned(integer);
+            },
+            .f32, .f64 => unreachable,
+            .i32 => {
+                try self.emit(.i32_const);
+                try self.emitSigned(@as(i32, @truncate(integer)));
+            }
+        },
+        .float => |float| switch (try self.valueType(node.span, self.typeOf(node))) {
+            .f32 => {
+                try self.emit(.f32_const);
+                try writer.writeInt(u32, @bitCast(@as(f32, @floatCast(float))), .little);
+            },
+            .f64 => {
+                try self.emit(.f64_const);
+                try writer.writeInt(u64, @bitCast(float), .little);
+            },
+            .i32, .i64 => unreachable
+        },
+        .boolean => |boolean| {
+            try self.emit(.i32_const);
+            try self.emitSigned(if (boolean) 1 else 0);
+        },
+        .character => |character| {
+            try self.emit(.i32_const);
+            try self.emitSigned(character);
+        },
+        .string => return self.unsupported(node.span, "strings"),
+        .unit => {}
+    }
+}
+
+// Results of arithmetic on integers narrower than the i32 holding them are
+// sign extended, so they wrap as they would in a register of their own size
+fn wrap(self: *Wasm, @"type": Type) GenerateError!void {
+    switch (@"type") {
+        .@"i8" => try self.emit(.i32_extend8_s),
+        .@"i16" => try self.emit(.i32_extend16_s),
+        else => {}
+    }
+}
+
+fn generatePrefix(self: *Wasm, node: *const Node, prefix: Node.Prefix) GenerateError!void {
+    const @"type" = self.typeOf(node);
+    const valtype = try self.valueType(node.span, @"type");
+
+    switch (prefix.operator) {
+        .negate => switch (valtype) {
+            .f32, .f64 => {
+                try self.generateNode(prefix.operand);
+                try self.emit(if (valtype == .f32) .f32_neg else .f64_neg);
+            },
+            // Subtracted from zero, which is pushed first
+            .i32, .i64 => {
+                try self.emit(if (valtype == .i32) .i32_const else .i64_const);
+                try self.emitSigned(0);
+                try self.generateNode(prefix.operand);
+                try self.emit(if (valtype == .i32) .i32_sub else .i64_sub);
+                try self.wrap(@"type");
+            }
+        },
+        .not => {
+            try self.generateNode(prefix.operand);
+            try self.emit(.i32_eqz);
+        },
+        .bit_not => {
+            try self.generateNode(prefix.operand);
+            try self.emit(if (valtype == .i32) .i32_const else .i64_const);
+            try self.emitSigned(-1);
+            try self.emit(if (valtype == .i32) .i32_xor else .i64_xor);
+        }
+    }
+}
+
+// The opcode of an operation for values of the type, narrow is the i32 or f32
+// form of the instruction and wide the i64 or f64 form
+fn sized(valtype: Valtype, narrow: Opcode, wide: Opcode) Opcode {
+    return if (valtype == .i32 or valtype == .f32) narrow else wide;
+}
+
+fn generateInfix(self: *Wasm, node: *const Node, infix: Node.Infix) GenerateError!void {
+    const operand = self.typeOf(infix.lhs);
+
+    switch (infix.operator) {
+        .forward_app => return try self.generateCall(infix.lhs, &.{infix.rhs}),
+        .reverse_app => return try self.generateCall(infix.rhs, &.{infix.lhs}),
+        .@"and", .@"or" => return try self.generateShortCircuit(infix),
+        .concat => return self.unsupported(node.span, "string concatenations"),
+        .range_exc, .range_inc => return self.unsupported(node.span, "ranges"),
+        .exponent => return self.unsupported(node.span, "exponents"),
+        .modulo => if (operand.isFloat()) return self.unsupported(node.span, "float remainders"),
+        else => {}
+    }
+
+    if (operand == .unit) return self.unsupported(node.span, "comparisons of () values");
+
+    const valtype = try self.valueType(infix.lhs.span, operand);
+    try self.generateNode(infix.lhs);
+    try self.generateNode(infix.rhs);
+
+    if (operand.isFloat()) return try self.emit(switch (infix.operator) {
+        .add => sized(valtype, .f32_add, .f64_add),
+        .subtract => sized(valtype, .f32_sub, .f64_sub),
+        .multiply => sized(valtype, .f32_mul, .f64_mul),
+        .divide => sized(valtype, .f32_div, .f64_div),
+        .lesser => sized(valtype, .f32_lt, .f64_lt),
+        .lesser_eq => sized(valtype, .f32_le, .f64_le),
+        .greater => sized(valtype, .f32_gt, .f64_gt),
+        .greater_eq => sized(valtype, .f32_ge, .f64_ge),
+        .equal => sized(valtype, .f32_eq, .f64_eq),
+        .not_equal => sized(valtype, .f32_ne, .f64_ne),
+        // Bitwise operators are rejected for floats by the type checker
+        else => unreachable
+    });
+
+    // Booleans and characters are compared unsigned
+    const signed = operand.isInteger();
+
+    try self.emit(switch (infix.operator) {
+        .add => sized(valtype, .i32_add, .i64_add),
+        .subtract => sized(valtype, .i32_sub, .i64_sub),
+        .multiply => sized(valtype, .i32_mul, .i64_mul),
+        .divide => sized(valtype, .i32_div_s, .i64_div_s),
+        .modulo => sized(valtype, .i32_rem_s, .i64_rem_s),
+        .bit_and => sized(valtype, .i32_and, .i64_and),
+        .bit_or => sized(valtype, .i32_or, .i64_or),
+        .bit_xor => sized(valtype, .i32_xor, .i64_xor),
+        .lshift => sized(valtype, .i32_shl, .i64_shl),
+        .rshift => sized(valtype, .i32_shr_s, .i64_shr_s),
+        .lesser => if (signed) sized(valtype, .i32_lt_s, .i64_lt_s)
+            else sized(valtype, .i32_lt_u, .i64_lt_u),
+        .lesser_eq => if (signed) sized(valtype, .i32_le_s, .i64_le_s)
+            else sized(valtype, .i32_le_u, .i64_le_u),
+        .greater => if (signed) sized(valtype, .i32_gt_s, .i64_gt_s)
+            else sized(valtype, .i32_gt_u, .i64_gt_u),
+        .greater_eq => if (signed) sized(valtype, .i32_ge_s, .i64_ge_s)
+            else sized(valtype, .i32_ge_u, .i64_ge_u),
+        .equal => sized(valtype, .i32_eq, .i64_eq),
+        .not_equal => sized(valtype, .i32_ne, .i64_ne),
+        else => unreachable
+    });
+
+    switch (infix.operator) {
+        .add, .subtract, .multiply, .lshift => try self.wrap(operand),
+        else => {}
+    }
+}
+
+// The rhs is only evaluated when the lhs doesn't already decide the result
+fn generateShortCircuit(self: *Wasm, infix: Node.Infix) GenerateError!void {
+    try self.generateNode(infix.lhs);
+    try self.emitBlock(.@"if", .i32);
+
+    if (infix.operator == .@"and") {
+        try self.generateNode(infix.rhs);
+        try self.emit(.@"else");
+        try self.emit(.i32_const);
+        try self.emitSigned(0);
+    } else {
+        try self.emit(.i32_const);
+        try self.emitSigned(1);
+        try self.emit(.@"else");
+        try self.generateNode(infix.rhs);
+    }
+
+    try self.emitEnd();
+}
+
+fn generateCall(self: *Wasm, callee: *const Node, arguments: []const *Node) GenerateError!void {
+    const name = switch (callee.kind) {
+        .identifier => |identifier| identifier,
+        else => return self.unsupported(callee.span, "calls of values other than functions")
+    };
+
+    // Functions missing from the module had unsupported signatures, which were
+    // reported when declaring them, or are the prelude's assert, fmt or block_on
+    const index = self.indices.get(name) orelse {
+        if (std.mem.eql(u8, name, "assert")) return self.unsupported(callee.span, "asserts");
+        if (std.mem.eql(u8, name, "fmt")) return self.unsupported(callee.span, "fmt calls");
+        if (std.mem.eql(u8, name, "block_on")) return self.unsupported(callee.span, "async functions");
+        return error.UnsupportedNode;
+    };
+
+    for (arguments) |argument| try self.generateNode(argument);
+    try self.emit(.call);
+    try self.emitUnsigned(index);
+}
+
+// Encodes the module's sections, each function has a type of its own
+fn writeModule(self: *Wasm) ![]u8 {
+    var module = ArrayList(u8).init(self.allocator);
+    errdefer module.deinit();
+
+    try module.appendSlice(magic);
+    try module.appendSlice(&version);
+
+    var section = ArrayList(u8).init(self.allocator);
+    defer section.deinit();
+    const writer = section.writer();
+
+    const functions = self.functions.items;
+
+    try std.leb.writeUleb128(writer, functions.len);
+    for (functions) |function| {
+        try writer.writeByte(0x60);
+        try std.leb.writeUleb128(writer, function.params.len);
+        for (function.params) |param| try writer.writeByte(@intFromEnum(param));
+        try std.leb.writeUleb128(writer, @as(u32, if (function.ret == null) 0 else 1));
+        if (function.ret) |ret| try writer.writeByte(@intFromEnum(ret));
+    }
+    try appendSection(&module, .type, &section);
+
+    try std.leb.writeUleb128(writer, functions.len);
+    for (0..functions.len) |i| try std.leb.writeUleb128(writer, i);
+    try appendSection(&module, .function, &section);
+
+    var exports: usize = 0;
+    for (functions) |function| exports = exports + @intFromBool(function.exported);
+
+    try std.leb.writeUleb128(writer, exports);
+    for (functions, 0..) |function, i| {
+        if (!function.exported) continue;
+
+        try std.leb.writeUleb128(writer, function.fn_def.name.len);
+        try writer.writeAll(function.fn_def.name);
+        // Exported as a function
+        try writer.writeByte(0x00);
+        try std.leb.writeUleb128(writer, i);
+    }
+    try appendSection(&module, .@"export", &section);
+
+    try std.leb.writeUleb128(writer, functions.len);
+    for (functions) |function| {
+        try std.leb.writeUleb128(writer, function.body.len);
+        try writer.writeAll(function.body);
+    }
+    try appendSection(&module, .code, &section);
+
+    return try module.toOwnedSlice();
+}
+
+// Appends the section to the module, preceded by its id and size, then clears it
+fn appendSection(module: *ArrayList(u8), id: SectionId, section: *ArrayList(u8)) !void {
+    try module.append(@intFromEnum(id));
+    try std.leb.writeUleb128(module.writer(), section.items.len);
+    try module.appendSlice(section.items);
+
+    section.clearRetainingCapacity();
+}
+
+test "wasm modules" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    // Checks source and generates its module
+    fn checkAndGenerate(unit: *Unit) ![]u8 {
+        const result = try unit.check();
+        defer result.deinit();
+
+        var generator = try Wasm.init(unit, result.ast, result.types);
+        defer generator.deinit();
+
+        return try generator.generate();
+    }
+
+    test "functions are encoded as a module, exporting those declared pub" {
+        if (!enabled) return error.SkipZigTest;
+
+        const source =
+            \\pub fn add(a: i32, b: i32) -> i32 { a + b }
+            \\fn double(x: i64) -> i64 { x * 2 }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const module = try checkAndGenerate(unit);
+        defer testing.allocator.free(module);
+
+        try testing.expectEqualSlices(u8, &[_]u8{
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+            // Types
+            0x01, 0x0c, 0x02, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, 0x60, 0x01, 0x7e, 0x01, 0x7e,
+            // Functions
+            0x03, 0x03, 0x02, 0x00, 0x01,
+            // Exports
+            0x07, 0x07, 0x01, 0x03, 'a', 'd', 'd', 0x00, 0x00,
+            // Code
+            0x0a, 0x11, 0x02,
+            0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b,
+            0x07, 0x00, 0x20, 0x00, 0x42, 0x02, 0x7e, 0x0b
+        }, module);
+    }
+
+    // The module is run with wasmtime when it's installed, as it is in CI
+    test "loops and conditionals run in a wasm runtime" {
+        if (!enabled) return error.SkipZigTest;
+
+        const source =
+            \\pub fn fib(n: i64) -> i64 {
+            \\    var a = 0
+            \\    var b = 1
+            \\    var i = 0
+            \\    while i < n {
+            \\        let t = a + b
+            \\        a = b
+            \\        b = t
+            \\        i = i + 1
+            \\        if i > 90 { break }
+            \\    }
+            \\    if a < 0 or n < 0 { 0 } else { a }
+            \\}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const module = try checkAndGenerate(unit);
+        defer testing.allocator.free(module);
+
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.writeFile(.{ .sub_path = "fib.wasm", .data = module });
+        const path = try tmp.dir.realpathAlloc(testing.allocator, "fib.wasm");
+        defer testing.allocator.free(path);
+
+        const run = std.process.Child.run(.{
+            .allocator = testing.allocator,
+            .argv = &.{"wasmtime", "--invoke", "fib", path, "10"}
+        }) catch |err| switch (err) {
+            error.FileNotFound => return error.SkipZigTest,
+            else => return err
+        };
+        defer {
+            testing.allocator.free(run.stdout);
+            testing.allocator.free(run.stderr);
+        }
+
+        try testing.expectEqual(std.process.Child.Term{ .Exited = 0 }, run.term);
+        try testing.expectEqualStrings("55\n", run.stdout);
+    }
+
+    test "unsupported constructs are reported" {
+        if (!enabled) return error.SkipZigTest;
+
+        const source =
+            \\fn greet(name: str) -> i64 { 1 }
+            \\fn adder(n: i64) -> i64 {
+            \\    let f = fn(x: i64) -> i64 { x + n }
+            \\    f(1)
+            \\}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.CodegenFailed, checkAndGenerate(unit));
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqualStrings(
+            "values of type str are not supported by the wasm backend",
+            unit.diagnostics.items[0].message
+        );
+        try testing.expectEqualStrings(
+            "closures are not supported by the wasm backend",
+            unit.diagnostics.items[1].message
+        );
+    }
+
+    test "emitting without the backend fails" {
+        if (enabled) return error.SkipZigTest;
+
+        var input = std.io.fixedBufferStream("fn f() -> i64 { 1 }");
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var opts: Unit.UnitOptions = .testing(input.reader().any(), output.writer().any());
+        opts.options.emit = .wasm;
+
+        var unit = try Unit.init(opts);
+        defer unit.deinit();
+
+        try testing.expectError(error.BackendUnavailable, unit.compile());
+        try testing.expectEqual(0, output.items.len);
+    }
+};
@@ -0,0 +1,388 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The tokens of files compiled before, so those unchanged since are parsed from
+//! them rather than scanned again. The cache is kept in a file between compilations,
+//! which is discarded when it was written by another version of the compiler.
+//!
+//! Asts aren't cached, they are moved into the merged ast of each compilation
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+const ArrayList = std.ArrayList;
+const Dir = std.fs.Dir;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const Interner = ruka.Interner;
+const Keyword = ruka.Keyword;
+const Mode = ruka.Mode;
+const Token = ruka.Token;
+const constants = ruka.constants;
+
+/// Keyed by the paths of the files
+entries: StringHashMap(Entry),
+/// Interns the identifiers of cached tokens, they are interned again by the units
+/// the tokens are copied into
+interner: Interner,
+/// The number of lookups which found the file unchanged, and those which didn't
+hits: usize,
+misses: usize,
+/// Set when entries change, so unchanged caches aren't written again
+dirty: bool,
+
+/// Owns the paths and tokens of entries
+arena: ArenaAllocator,
+allocator: Allocator,
+
+const Cache = @This();
+
+const log = std.log.scoped(.cache);
+
+pub const Entry = struct {
+    /// The modification time of the file when it was scanned
+    mtime: i128,
+    tokens: []const Token
+};
+
+/// The name of the file the cache is kept in, within the cache's directory
+pub const file_name = "tokens";
+
+// Begins the file, so caches of other versions are recognized
+const header = "ruka cache " ++ constants.version_str ++ "\n";
+
+pub fn init(allocator: Allocator) Cache {
+    return Cache {
+        .entries = .init(allocator),
+        .interner = .init(allocator),
+        .hits = 0,
+        .misses = 0,
+        .dirty = false,
+        .arena = .init(allocator),
+        .allocator = allocator
+    };
+}
+
+pub fn deinit(self: *Cache) void {
+    self.entries.deinit();
+    self.interner.deinit();
+    self.arena.deinit();
+}
+
+/// Reads the cache kept in dir, which is empty if there is none or it can't be read
+pub fn load(allocator: Allocator, dir: Dir) !Cache {
+    var cache = Cache.init(allocator);
+    errdefer cache.deinit();
+
+    const data = dir.readFileAlloc(allocator, file_name, std.math.maxInt(usize)) catch |err| switch (err) {
+        error.FileNotFound => return cache,
+        else => |e| return e
+    };
+    defer allocator.free(data);
+
+    cache.decode(data) catch |err| switch (err) {
+        error.OutOfMemory => return error.OutOfMemory,
+        else => {
+            log.warn("discarding the cache, it couldn't be read: {}", .{err});
+
+            cache.deinit();
+            cache = Cache.init(allocator);
+        }
+    };
+
+    return cache;
+}
+
+/// Writes the cache into dir if it has changed since it was loaded
+pub fn save(self: *Cache, dir: Dir) !void {
+    if (!self.dirty) return;
+
+    var data = ArrayList(u8).init(self.allocator);
+    defer data.deinit();
+
+    try self.encode(data.writer());
+    try dir.writeFile(.{ .sub_path = file_name, .data = data.items });
+
+    self.dirty = false;
+}
+
+/// The tokens of the file at path, if it hasn't been modified since they were cached
+pub fn lookup(self: *Cache, path: []const u8, mtime: i128) ?[]const Token {
+    if (self.entries.get(path)) |entry| if (entry.mtime == mtime) {
+        self.hits = self.hits + 1;
+        return entry.tokens;
+    };
+
+    self.misses = self.misses + 1;
+    return null;
+}
+
+/// Caches the tokens scanned from the file at path, whose identifiers were
+/// interned by interner
+pub fn put(self: *Cache, path: []const u8, mtime: i128, tokens: []const Token, interner: *const Interner) !void {
+    const allocator = self.arena.allocator();
+
+    const entry = try self.entries.getOrPut(path);
+    if (!entry.found_existing) {
+        entry.key_ptr.* = allocator.dupe(u8, path) catch |err| {
+            self.entries.removeByPtr(entry.key_ptr);
+            return err;
+        };
+    }
+
+    // The tokens span the cached path, rather than memory owned by their unit
+    const owned = try allocator.alloc(Token, tokens.len);
+    for (owned, tokens) |*token, scanned| {
+        token.* = try copy(scanned, allocator, interner, &self.interner);
+        token.span.file = entry.key_ptr.*;
+    }
+
+    entry.value_ptr.* = .{ .mtime = mtime, .tokens = owned };
+
+    self.dirty = true;
+}
+
+/// Copies cached tokens into memory owned by allocator, interning their
+/// identifiers with interner. The copies span file
+pub fn copyTokens(
+    self: *const Cache,
+    tokens: []const Token,
+    file: []const u8,
+    allocator: Allocator,
+    interner: *Interner
+) !ArrayList(Token) {
+    var copies = try ArrayList(Token).initCapacity(allocator, tokens.len);
+    errdefer {
+        for (copies.items) |token| token.deinit();
+        copies.deinit();
+    }
+
+    for (tokens) |token| {
+        var copied = try copy(token, allocator, &self.interner, interner);
+        copied.span.file = file;
+        copies.appendAssumeCapacity(copied);
+    }
+
+    return copies;
+}
+
+// Copies token into memory owned by allocator, moving its identifier from one interner to another
+fn copy(token: Token, allocator: Allocator, from: *const Interner, to: *Interner) !Token {
+    return switch (token.kind) {
+        .identifier => |id| Token.initSpan(.{ .identifier = try to.intern(from.get(id)) }, token.span),
+        else => try token.clone(allocator)
+    };
+}
+
+// The file holds the header, then the number of entries followed by each entry's
+// path, modification time and tokens. Integers are little endian
+fn encode(self: *const Cache, writer: anytype) !void {
+    try writer.writeAll(header);
+    try writer.writeInt(u64, self.entries.count(), .little);
+
+    var entries = self.entries.iterator();
+    while (entries.next()) |entry| {
+        try writeString(writer, entry.key_ptr.*);
+        try writer.writeInt(i128, entry.value_ptr.mtime, .little);
+
+        try writer.writeInt(u64, entry.value_ptr.tokens.len, .little);
+        for (entry.value_ptr.tokens) |token| try self.writeToken(writer, token);
+    }
+}
+
+fn writeToken(self: *const Cache, writer: anytype, token: Token) !void {
+    try writer.writeByte(@intFromEnum(std.meta.activeTag(token.kind)));
+    switch (token.kind) {
+        .identifier => |id| try writeString(writer, self.interner.get(id)),
+        .@"enum", .string, .doc_comment => |bytes| try writeString(writer, bytes.items),
+        .character => |byte| try writer.writeByte(byte),
+        .integer => |integer| try writer.writeInt(i64, integer, .little),
+        .float => |float| try writer.writeInt(u64, @bitCast(float), .little),
+        .keyword => |keyword| try writer.writeByte(@intFromEnum(keyword)),
+        .mode => |mode| try writer.writeByte(@intFromEnum(mode)),
+        else => {}
+    }
+
+    // Tokens span the file they are cached for, so only the positions are written
+    try writer.writeInt(u64, token.span.start, .little);
+    try writer.writeInt(u64, token.span.end, .little);
+    try writer.writeInt(u64, token.span.pos.line, .little);
+    try writer.writeInt(u64, token.span.pos.col, .little);
+}
+
+fn writeString(writer: anytype, string: []const u8) !void {
+    try writer.writeInt(u64, string.len, .little);
+    try writer.writeAll(string);
+}
+
+fn decode(self: *Cache, data: []const u8) !void {
+    if (!std.mem.startsWith(u8, data, header)) return error.OtherVersion;
+
+    var stream = std.io.fixedBufferStream(data[header.len..]);
+    var decoder: Decoder = .{ .stream = &stream, .arena = self.arena.allocator() };
+
+    // Each entry is at least its path's length, modification time and number of tokens
+    const count = try decoder.length(@sizeOf(u64) * 4);
+    for (0..count) |_| {
+        const path = try decoder.string();
+        const mtime = try decoder.int(i128);
+
+        const tokens = try decoder.arena.alloc(Token, try decoder.length(@sizeOf(u64) * 4));
+        for (tokens) |*token| token.* = try decoder.token(path, &self.interner);
+
+        try self.entries.put(path, .{ .mtime = mtime, .tokens = tokens });
+    }
+}
+
+const Decoder = struct {
+    stream: *std.io.FixedBufferStream([]const u8),
+    arena: Allocator,
+
+    fn int(self: *Decoder, comptime T: type) !T {
+        return try self.stream.reader().readInt(T, .little);
+    }
+
+    // Reads the length of a sequence, which can't be longer than the rest of the
+    // data as each element is at least min_size bytes
+    fn length(self: *Decoder, min_size: usize) !usize {
+        const len = try self.int(u64);
+        if (len > (self.stream.buffer.len - self.stream.pos) / @max(min_size, 1)) return error.InvalidLength;
+
+        return @intCast(len);
+    }
+
+    fn string(self: *Decoder) ![]u8 {
+        const bytes = try self.arena.alloc(u8, try self.length(1));
+        try self.stream.reader().readNoEof(bytes);
+
+        return bytes;
+    }
+
+    fn enumeration(self: *Decoder, comptime T: type) !T {
+        return std.meta.intToEnum(T, try self.stream.reader().readByte()) catch return error.InvalidTag;
+    }
+
+    fn token(self: *Decoder, file: []const u8, interner: *Interner) !Token {
+        const Tag = std.meta.Tag(Token.Kind);
+
+        const kind: Token.Kind = switch (try self.enumeration(Tag)) {
+            .identifier => .{ .identifier = try interner.intern(try self.string()) },
+            .@"enum" => try Token.Kind.initEnum(try self.string(), self.arena),
+            .string => try Token.Kind.initString(try self.string(), self.arena),
+            .doc_comment => .{ .doc_comment = (try Token.Kind.initString(try self.string(), self.arena)).string },
+            .character => .{ .character = try self.stream.reader().readByte() },
+            .integer => .{ .integer = try self.int(i64) },
+            .float => .{ .float = @bitCast(try self.int(u64)) },
+            .keyword => .{ .keyword = try self.enumeration(Keyword) },
+            .mode => .{ .mode = try self.enumeration(Mode) },
+            inline else => |tag| @unionInit(Token.Kind, @tagName(tag), {})
+        };
+
+        return Token.initSpan(kind, .{
+            .file = file,
+            .start = @intCast(try self.int(u64)),
+            .end = @intCast(try self.int(u64)),
+            .pos = .init(@intCast(try self.int(u64)), @intCast(try self.int(u64)))
+        });
+    }
+};
+
+test "cache" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "tokens of unmodified files are found" {
+        var cache = Cache.init(testing.allocator);
+        defer cache.deinit();
+
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const span: ruka.Span = .{ .file = "main.ruka", .start = 4, .end = 5, .pos = .init(1, 5) };
+        try cache.put("main.ruka", 10, &.{
+            .initSpan(.{ .keyword = .let }, span),
+            .initSpan(.{ .identifier = try interner.intern("x") }, span),
+            .initSpan(.eof, span)
+        }, &interner);
+
+        try testing.expectEqual(null, cache.lookup("main.ruka", 11));
+        try testing.expectEqual(null, cache.lookup("other.ruka", 10));
+
+        const tokens = cache.lookup("main.ruka", 10).?;
+        try testing.expectEqual(1, cache.hits);
+        try testing.expectEqual(2, cache.misses);
+
+        var other = Interner.init(testing.allocator);
+        defer other.deinit();
+        _ = try other.intern("y");
+
+        var copies = try cache.copyTokens(tokens, "main.ruka", testing.allocator, &other);
+        defer {
+            for (copies.items) |token| token.deinit();
+            copies.deinit();
+        }
+
+        try testing.expectEqual(3, copies.items.len);
+        try testing.expectEqualStrings("x", other.get(copies.items[1].kind.identifier));
+        try testing.expectEqual(5, copies.items[1].span.pos.col);
+    }
+
+    test "caches are kept in files" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        var string = try Token.Kind.initString("hello", testing.allocator);
+        defer string.deinit();
+
+        {
+            var cache = Cache.init(testing.allocator);
+            defer cache.deinit();
+
+            const span: ruka.Span = .{ .file = "main.ruka", .start = 8, .end = 15, .pos = .init(2, 1) };
+            try cache.put("main.ruka", -3, &.{
+                .initSpan(.{ .identifier = try interner.intern("greeting") }, span),
+                .initSpan(string, span),
+                .initSpan(.{ .float = 2.5 }, span),
+                .initSpan(.{ .keyword = .@"while" }, span),
+                .initSpan(.newline, span)
+            }, &interner);
+
+            try cache.save(tmp.dir);
+        }
+
+        var cache = try Cache.load(testing.allocator, tmp.dir);
+        defer cache.deinit();
+
+        const tokens = cache.lookup("main.ruka", -3).?;
+        try testing.expectEqual(5, tokens.len);
+        try testing.expectEqualStrings("greeting", cache.interner.get(tokens[0].kind.identifier));
+        try testing.expectEqualStrings("hello", tokens[1].kind.string.items);
+        try testing.expectEqual(2.5, tokens[2].kind.float);
+        try testing.expectEqual(Keyword.@"while", tokens[3].kind.keyword);
+        try testing.expect(tokens[4].kind == .newline);
+        try testing.expectEqual(15, tokens[4].span.end);
+        try testing.expectEqualStrings("main.ruka", tokens[4].span.file);
+    }
+
+    test "unreadable caches are discarded" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.writeFile(.{ .sub_path = file_name, .data = header ++ "\xff\xff\xff\xff\xff\xff\xff\xff" });
+        var truncated = try Cache.load(testing.allocator, tmp.dir);
+        defer truncated.deinit();
+        try testing.expectEqual(0, truncated.entries.count());
+
+        try tmp.dir.writeFile(.{ .sub_path = file_name, .data = "ruka cache 0.0.0\n" });
+        var other_version = try Cache.load(testing.allocator, tmp.dir);
+        defer other_version.deinit();
+        try testing.expectEqual(0, other_version.entries.count());
+    }
+};
@@ -0,0 +1,183 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The modules of a compilation and the modules each imports, for compiling
+//! with options.parallel. Modules are loaded a wave at a time, each wave being
+//! the files imported by the wave before it, which don't depend on each other
+//! until they are parsed and so are parsed concurrently. Once every module is
+//! loaded they are sorted so each follows the modules it imports.
+//!
+//! Files are parsed on the compiler's thread pool by units of their own, which
+//! share the compiler's allocator, options and cache. The cache and the profile
+//! are guarded by mutexes for this, while the modules, the diagnostics kept and
+//! the arena are only changed by the thread loading the modules, or with the
+//! compiler's mutex locked
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const StringArrayHashMap = std.StringArrayHashMap;
+
+const ruka = @import("../prelude.zig");
+const Node = ruka.Node;
+
+/// The imports of each module by its resolved path, in the order they were added
+modules: StringArrayHashMap(ArrayList(Import)),
+allocator: Allocator,
+
+const CompileGraph = @This();
+
+/// An import of the module at path, written as node
+pub const Import = struct {
+    path: []const u8,
+    /// The dotted name the import was written with
+    name: []const u8,
+    node: *const Node
+};
+
+pub fn init(allocator: Allocator) CompileGraph {
+    return .{
+        .modules = .init(allocator),
+        .allocator = allocator
+    };
+}
+
+pub fn deinit(self: *CompileGraph) void {
+    for (self.modules.values()) |*imports| imports.deinit();
+    self.modules.deinit();
+}
+
+/// Adds the module at path, returning false if it was already added
+pub fn addModule(self: *CompileGraph, path: []const u8) Allocator.Error!bool {
+    const entry = try self.modules.getOrPut(path);
+    if (entry.found_existing) return false;
+
+    entry.value_ptr.* = .init(self.allocator);
+    return true;
+}
+
+/// Adds an import of the module at import.path by the module at importer, which
+/// must have been added. Imports of modules which are never added are ignored
+/// when sorting, as they couldn't be loaded
+pub fn addImport(self: *CompileGraph, importer: []const u8, import: Import) Allocator.Error!void {
+    try self.modules.getPtr(importer).?.append(import);
+}
+
+// How far sorting has visited a module
+const Mark = enum {
+    unvisited,
+    /// Its imports are being visited, so importing it again creates a cycle
+    visiting,
+    visited
+};
+
+/// Appends the modules reachable from roots to order, each after the modules it
+/// imports, visiting roots and imports in the order they were added. Imports
+/// which create cycles are appended to cycles rather than followed
+pub fn sort(
+    self: *const CompileGraph,
+    roots: []const []const u8,
+    order: *ArrayList([]const u8),
+    cycles: *ArrayList(Import)
+) Allocator.Error!void {
+    const marks = try self.allocator.alloc(Mark, self.modules.count());
+    defer self.allocator.free(marks);
+    @memset(marks, .unvisited);
+
+    for (roots) |root| {
+        const index = self.modules.getIndex(root) orelse continue;
+        try self.visit(index, marks, order, cycles);
+    }
+}
+
+fn visit(
+    self: *const CompileGraph,
+    index: usize,
+    marks: []Mark,
+    order: *ArrayList([]const u8),
+    cycles: *ArrayList(Import)
+) Allocator.Error!void {
+    if (marks[index] != .unvisited) return;
+    marks[index] = .visiting;
+
+    for (self.modules.values()[index].items) |import| {
+        const imported = self.modules.getIndex(import.path) orelse continue;
+        switch (marks[imported]) {
+            .unvisited => try self.visit(imported, marks, order, cycles),
+            .visiting => try cycles.append(import),
+            .visited => {}
+        }
+    }
+
+    marks[index] = .visited;
+    try order.append(self.modules.keys()[index]);
+}
+
+test "compile graph" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const node: Node = .{
+        .id = 0,
+        .kind = .@"continue",
+        .span = .{ .file = "test source" }
+    };
+
+    fn importOf(path: []const u8) Import {
+        return .{ .path = path, .name = path, .node = &node };
+    }
+
+    test "modules follow the modules they import" {
+        var graph = CompileGraph.init(testing.allocator);
+        defer graph.deinit();
+
+        for ([_][]const u8{"main", "b", "a", "shared"}) |path| try testing.expect(try graph.addModule(path));
+        try testing.expect(!try graph.addModule("a"));
+
+        try graph.addImport("main", importOf("a"));
+        try graph.addImport("main", importOf("b"));
+        try graph.addImport("a", importOf("shared"));
+        try graph.addImport("b", importOf("shared"));
+        try graph.addImport("b", importOf("missing"));
+
+        var order = ArrayList([]const u8).init(testing.allocator);
+        defer order.deinit();
+        var cycles = ArrayList(Import).init(testing.allocator);
+        defer cycles.deinit();
+
+        try graph.sort(&.{"main"}, &order, &cycles);
+
+        try testing.expectEqual(4, order.items.len);
+        for ([_][]const u8{"shared", "a", "b", "main"}, order.items) |expected, path| {
+            try testing.expectEqualStrings(expected, path);
+        }
+        try testing.expectEqual(0, cycles.items.len);
+    }
+
+    test "imports creating cycles" {
+        var graph = CompileGraph.init(testing.allocator);
+        defer graph.deinit();
+
+        for ([_][]const u8{"a", "b", "c"}) |path| _ = try graph.addModule(path);
+
+        try graph.addImport("a", importOf("b"));
+        try graph.addImport("b", importOf("c"));
+        try graph.addImport("c", importOf("a"));
+        try graph.addImport("c", importOf("c"));
+
+        var order = ArrayList([]const u8).init(testing.allocator);
+        defer order.deinit();
+        var cycles = ArrayList(Import).init(testing.allocator);
+        defer cycles.deinit();
+
+        try graph.sort(&.{"a", "c"}, &order, &cycles);
+
+        try testing.expectEqual(3, order.items.len);
+        try testing.expectEqual(2, cycles.items.len);
+        try testing.expectEqualStrings("a", cycles.items[0].path);
+        try testing.expectEqualStrings("c", cycles.items[1].path);
+    }
+};
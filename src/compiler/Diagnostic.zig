@@ -0,0 +1,438 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const AnyWriter = std.io.AnyWriter;
+
+const ruka = @import("../prelude.zig");
+const Span = ruka.Span;
+
+severity: Severity,
+message: []const u8,
+span: Span,
+/// Notes and help attached to this diagnostic
+children: []const Diagnostic,
+/// The lint which reported this diagnostic, attributes allowing or denying it
+/// decide whether it is reported and how severely
+lint: ?Lint = null,
+
+const Diagnostic = @This();
+
+pub const Severity = enum {
+    @"error",
+    warning,
+    note,
+    help,
+
+    pub fn toStr(self: Severity) []const u8 {
+        return @tagName(self);
+    }
+
+    // The ansi escape sequence labels of this severity are colored with
+    fn color(self: Severity) []const u8 {
+        return switch (self) {
+            .@"error" => "\x1b[1;31m",
+            .warning => "\x1b[1;33m",
+            .note => "\x1b[1;36m",
+            .help => "\x1b[1;32m"
+        };
+    }
+};
+
+/// The warnings attributes may allow or deny by name, `#[allow(dead_code)]`
+pub const Lint = enum {
+    /// References to bindings which may go out of scope before them
+    dangling_reference,
+    /// Code which is never run
+    dead_code,
+    /// Gotos written outside of unsafe blocks, which are errors unless allowed
+    goto,
+    /// Functions whose bodies hold more statements than Options.max_function_statements
+    long_function,
+    /// Functions and bindings whose names aren't snake_case, and types whose names
+    /// aren't PascalCase
+    naming_conventions,
+    /// Constants too large for their types
+    overflow,
+    /// Bindings hiding bindings of the same name
+    shadowing,
+    /// Casts to integer types too small for every value of the type cast from
+    truncation,
+    /// Match arms which never match, earlier arms matching everything they do
+    unreachable_pattern,
+    /// Imports of modules none of whose names are used by the importing module
+    unused_imports,
+    /// Values given to variables which are never used
+    unused_variable,
+
+    /// Finds the lint called name in attributes
+    pub fn fromName(name: []const u8) ?Lint {
+        return std.meta.stringToEnum(Lint, name);
+    }
+};
+
+pub fn init(severity: Severity, message: []const u8, span: Span) Diagnostic {
+    return Diagnostic {
+        .severity = severity,
+        .message = message,
+        .span = span,
+        .children = &.{}
+    };
+}
+
+/// Creates a warning reported by lint
+pub fn initLint(lint: Lint, message: []const u8, span: Span) Diagnostic {
+    var diagnostic = init(.warning, message, span);
+    diagnostic.lint = lint;
+
+    return diagnostic;
+}
+
+/// Deep copies the diagnostic so it no longer references memory owned by its
+/// unit, intended for use with an arena
+pub fn clone(self: Diagnostic, allocator: Allocator) Allocator.Error!Diagnostic {
+    const children = try allocator.alloc(Diagnostic, self.children.len);
+    for (children, self.children) |*child, original| child.* = try original.clone(allocator);
+
+    var span = self.span;
+    span.file = try allocator.dupe(u8, span.file);
+
+    return .{
+        .severity = self.severity,
+        .message = try allocator.dupe(u8, self.message),
+        .span = span,
+        .children = children,
+        .lint = self.lint
+    };
+}
+
+/// Writes the diagnostic as a json object holding its severity, message, file, the
+/// lines and columns its span starts and ends at, its lint and its children. The
+/// end is found in source, the contents of the span's file
+pub fn writeJson(self: Diagnostic, writer: anytype, source: []const u8) @TypeOf(writer).Error!void {
+    const end = self.span.endPos(source);
+
+    try writer.print("{{\"severity\":\"{s}\",\"message\":", .{self.severity.toStr()});
+    try std.json.encodeJsonString(self.message, .{}, writer);
+    try writer.writeAll(",\"file\":");
+    try std.json.encodeJsonString(self.span.file, .{}, writer);
+    try writer.print(",\"start_line\":{},\"start_col\":{},\"end_line\":{},\"end_col\":{}", .{
+        self.span.pos.line,
+        self.span.pos.col,
+        end.line,
+        end.col
+    });
+
+    try writer.writeAll(",\"lint\":");
+    if (self.lint) |lint| try writer.print("\"{s}\"", .{@tagName(lint)}) else try writer.writeAll("null");
+
+    // Children in other files end where they start, their sources aren't at hand
+    try writer.writeAll(",\"children\":[");
+    for (self.children, 0..) |child, i| {
+        if (i > 0) try writer.writeByte(',');
+        try child.writeJson(writer, if (std.mem.eql(u8, child.span.file, self.span.file)) source else "");
+    }
+    try writer.writeAll("]}");
+}
+
+/// Renders diagnostics for the user
+pub const Emitter = struct {
+    ptr: *anyopaque,
+    emitFn: *const fn (ptr: *anyopaque, diagnostic: *const Diagnostic, source: []const u8) anyerror!void,
+
+    /// Renders diagnostic, whose span refers to source
+    pub fn emit(self: Emitter, diagnostic: *const Diagnostic, source: []const u8) !void {
+        try self.emitFn(self.ptr, diagnostic, source);
+    }
+};
+
+/// When diagnostics written to a file are colored
+pub const ColorChoice = enum {
+    /// Only if the file is a terminal supporting ansi escape sequences
+    auto,
+    always,
+    never,
+
+    /// Finds the choice called name on the command line
+    pub fn fromName(name: []const u8) ?ColorChoice {
+        return std.meta.stringToEnum(ColorChoice, name);
+    }
+
+    pub fn enabled(self: ColorChoice, file: std.fs.File) bool {
+        return switch (self) {
+            .auto => file.supportsAnsiEscapeCodes(),
+            .always => true,
+            .never => false
+        };
+    }
+};
+
+/// Renders diagnostics caret style, beneath the lines of source they refer to,
+/// with their locations in bold and labels and carets colored by severity
+pub const TerminalEmitter = struct {
+    writer: AnyWriter,
+    color: bool,
+
+    pub fn init(writer: AnyWriter, color: bool) TerminalEmitter {
+        return TerminalEmitter {
+            .writer = writer,
+            .color = color
+        };
+    }
+
+    pub fn emitter(self: *TerminalEmitter) Emitter {
+        return .{
+            .ptr = self,
+            .emitFn = emitOpaque
+        };
+    }
+
+    fn emitOpaque(ptr: *anyopaque, diagnostic: *const Diagnostic, source: []const u8) anyerror!void {
+        const self: *TerminalEmitter = @ptrCast(@alignCast(ptr));
+        try self.emit(diagnostic, source);
+    }
+
+    /// Renders diagnostic, whose span refers to source. Diagnostics without a
+    /// location, whose spans are at line 0, are rendered without any source
+    pub fn emit(self: *TerminalEmitter, diagnostic: *const Diagnostic, source: []const u8) !void {
+        const span = diagnostic.span;
+        if (span.pos.line == 0) {
+            try self.writeLabel(diagnostic.severity);
+            try self.writer.print(": {s}\n", .{diagnostic.message});
+            return;
+        }
+
+        const start = @min(span.start, source.len);
+        const end = @min(@max(span.end, start), source.len);
+
+        // Spans ending with a newline don't cover the line after it
+        const last_line = span.pos.line + std.mem.count(u8, source[start..@max(start, end -| 1)], "\n");
+        const gutter: usize = @intCast(std.fmt.count("{}", .{last_line}));
+
+        try self.writeLabel(diagnostic.severity);
+        try self.writer.print(": {s}\n", .{diagnostic.message});
+
+        try self.writer.writeByteNTimes(' ', gutter);
+        try self.writer.writeAll("--> ");
+        try self.setBold();
+        try self.writer.print("{}", .{span});
+        try self.resetColor();
+        try self.writer.writeByte('\n');
+
+        try self.writer.writeByteNTimes(' ', gutter);
+        try self.writer.writeAll(" |\n");
+
+        // Each line the span covers, with the part of it covered underlined
+        var line = span.pos.line;
+        var line_start = if (std.mem.lastIndexOfScalar(u8, source[0..start], '\n')) |i| i + 1 else 0;
+        while (true) : (line = line + 1) {
+            const line_end = std.mem.indexOfScalarPos(u8, source, line_start, '\n') orelse source.len;
+            try self.writer.print("{[line]d:>[width]} | {[text]s}\n", .{
+                .line = line,
+                .width = gutter,
+                .text = source[line_start..line_end]
+            });
+
+            const from = @max(start, line_start);
+            const carets = @min(end, line_end) -| from;
+            if (line == span.pos.line or carets > 0) {
+                try self.writer.writeByteNTimes(' ', gutter);
+                try self.writer.writeAll(" | ");
+                try self.writer.writeByteNTimes(' ', from - line_start);
+                try self.setColor(diagnostic.severity);
+                try self.writer.writeByteNTimes('^', @max(1, carets));
+                try self.resetColor();
+                try self.writer.writeByte('\n');
+            }
+
+            if (line >= last_line) break;
+            line_start = line_end + 1;
+        }
+
+        for (diagnostic.children) |child| {
+            try self.writer.writeByteNTimes(' ', gutter);
+            try self.writer.writeAll(" = ");
+            try self.writeLabel(child.severity);
+            try self.writer.print(": {s}\n", .{child.message});
+        }
+    }
+
+    fn writeLabel(self: *TerminalEmitter, severity: Severity) !void {
+        try self.setColor(severity);
+        try self.writer.writeAll(severity.toStr());
+        try self.resetColor();
+    }
+
+    fn setColor(self: *TerminalEmitter, severity: Severity) !void {
+        if (self.color) try self.writer.writeAll(severity.color());
+    }
+
+    fn setBold(self: *TerminalEmitter) !void {
+        if (self.color) try self.writer.writeAll("\x1b[1m");
+    }
+
+    fn resetColor(self: *TerminalEmitter) !void {
+        if (self.color) try self.writer.writeAll("\x1b[0m");
+    }
+};
+
+test "diagnostic" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const source =
+        \\let x = 1
+        \\let y = "abc
+        \\let z = 3
+    ;
+
+    test "terminal rendering" {
+        var diagnostic = Diagnostic.init(
+            .@"error",
+            "unterminated string literal",
+            .init("test source", 18, 22, .init(2, 9))
+        );
+        diagnostic.children = &.{
+            .init(.note, "strings end at the end of the line", .{ .file = "test source" })
+        };
+
+        var out = std.ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        var terminal = TerminalEmitter.init(out.writer().any(), false);
+        try terminal.emitter().emit(&diagnostic, source);
+
+        try testing.expectEqualStrings(
+            \\error: unterminated string literal
+            \\ --> test source:2:9
+            \\  |
+            \\2 | let y = "abc
+            \\  |         ^^^^
+            \\  = note: strings end at the end of the line
+            \\
+        , out.items);
+    }
+
+    test "colored rendering of empty spans" {
+        const diagnostic = Diagnostic.init(.warning, "unused binding", .init("test source", 4, 4, .init(1, 5)));
+
+        var out = std.ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        var terminal = TerminalEmitter.init(out.writer().any(), true);
+        try terminal.emit(&diagnostic, source);
+
+        try testing.expectEqualStrings(
+            "\x1b[1;33mwarning\x1b[0m: unused binding\n" ++
+            " --> \x1b[1mtest source:1:5\x1b[0m\n" ++
+            "  |\n" ++
+            "1 | let x = 1\n" ++
+            "  |     \x1b[1;33m^\x1b[0m\n"
+        , out.items);
+    }
+
+    test "rendering spans over several lines" {
+        const multiline =
+            \\let x = if c {
+            \\    1
+            \\} else {
+            \\    2
+            \\}
+            \\let y = x
+        ;
+
+        var diagnostic = Diagnostic.init(.@"error", "mismatched types", .init("test source", 8, 37, .init(1, 9)));
+        diagnostic.children = &.{
+            .init(.help, "both branches must have the same type", .{ .file = "test source" })
+        };
+
+        var out = std.ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        var terminal = TerminalEmitter.init(out.writer().any(), true);
+        try terminal.emit(&diagnostic, multiline);
+
+        try testing.expectEqualStrings(
+            "\x1b[1;31merror\x1b[0m: mismatched types\n" ++
+            " --> \x1b[1mtest source:1:9\x1b[0m\n" ++
+            "  |\n" ++
+            "1 | let x = if c {\n" ++
+            "  |         \x1b[1;31m^^^^^^\x1b[0m\n" ++
+            "2 |     1\n" ++
+            "  | \x1b[1;31m^^^^^\x1b[0m\n" ++
+            "3 | } else {\n" ++
+            "  | \x1b[1;31m^^^^^^^^\x1b[0m\n" ++
+            "4 |     2\n" ++
+            "  | \x1b[1;31m^^^^^\x1b[0m\n" ++
+            "5 | }\n" ++
+            "  | \x1b[1;31m^\x1b[0m\n" ++
+            "  = \x1b[1;32mhelp\x1b[0m: both branches must have the same type\n"
+        , out.items);
+    }
+
+    test "color choices" {
+        const pipe = try std.posix.pipe();
+        const read_end = std.fs.File{ .handle = pipe[0] };
+        defer read_end.close();
+        const write_end = std.fs.File{ .handle = pipe[1] };
+        defer write_end.close();
+
+        // Pipes aren't terminals, so are only colored when it is always chosen
+        try testing.expect(!ColorChoice.auto.enabled(write_end));
+        try testing.expect(ColorChoice.always.enabled(write_end));
+        try testing.expect(!ColorChoice.never.enabled(write_end));
+
+        try testing.expectEqual(.never, ColorChoice.fromName("never"));
+        try testing.expectEqual(null, ColorChoice.fromName("sometimes"));
+    }
+
+    test "rendering diagnostics without a location" {
+        const diagnostic = Diagnostic.init(.note, "and 3 more errors not shown", .{ .file = "test source" });
+
+        var out = std.ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+
+        var terminal = TerminalEmitter.init(out.writer().any(), false);
+        try terminal.emit(&diagnostic, source);
+
+        try testing.expectEqualStrings("note: and 3 more errors not shown\n", out.items);
+    }
+
+    test "cloning" {
+        var arena = std.heap.ArenaAllocator.init(testing.allocator);
+        defer arena.deinit();
+
+        var diagnostic = Diagnostic.init(.@"error", "mismatched types", .{ .file = "test source" });
+        diagnostic.children = &.{ .init(.help, "add an annotation", .{ .file = "test source" }) };
+
+        const copy = try diagnostic.clone(arena.allocator());
+        try testing.expectEqualStrings(diagnostic.message, copy.message);
+        try testing.expect(diagnostic.message.ptr != copy.message.ptr);
+        try testing.expectEqual(.help, copy.children[0].severity);
+        try testing.expectEqualStrings("add an annotation", copy.children[0].message);
+    }
+
+    test "json writing" {
+        var diagnostic = Diagnostic.initLint(.shadowing, "y \"shadows\"", .init("test source", 14, 26, .init(2, 5)));
+        diagnostic.children = &.{
+            .init(.note, "declared here", .init("test source", 4, 5, .init(1, 5))),
+            .init(.help, "elsewhere", .init("other source", 0, 3, .init(7, 1)))
+        };
+
+        var out = std.ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+        try diagnostic.writeJson(out.writer(), source);
+
+        try testing.expectEqualStrings(
+            \\{"severity":"warning","message":"y \"shadows\"","file":"test source","start_line":2,"start_col":5,"end_line":3,"end_col":4,"lint":"shadowing","children":[
+        ++
+            \\{"severity":"note","message":"declared here","file":"test source","start_line":1,"start_col":5,"end_line":1,"end_col":6,"lint":null,"children":[]},
+        ++
+            \\{"severity":"help","message":"elsewhere","file":"other source","start_line":7,"start_col":1,"end_line":7,"end_col":1,"lint":null,"children":[]}]}
+        , out.items);
+    }
+};
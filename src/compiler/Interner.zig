@@ -0,0 +1,115 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+
+/// Maps each interned string to its id
+map: StringHashMap(Id),
+/// The interned strings, indexed by their ids
+strings: ArrayList([]const u8),
+
+/// Owns the interned strings
+arena: ArenaAllocator,
+
+const Interner = @This();
+
+/// Identifies an interned string, equal strings interned by the same interner
+/// have equal ids so they compare as integers
+pub const Id = enum(u32) {
+    _,
+
+    fn index(self: Id) usize {
+        return @intFromEnum(self);
+    }
+};
+
+pub fn init(allocator: Allocator) Interner {
+    return Interner {
+        .map = .init(allocator),
+        .strings = .init(allocator),
+        .arena = .init(allocator)
+    };
+}
+
+pub fn deinit(self: *Interner) void {
+    self.map.deinit();
+    self.strings.deinit();
+    self.arena.deinit();
+}
+
+/// Returns the id of string, interning a copy of it if it hasn't been already
+pub fn intern(self: *Interner, string: []const u8) !Id {
+    if (self.map.get(string)) |id| return id;
+
+    const id: Id = @enumFromInt(self.strings.items.len);
+    const owned = try self.arena.allocator().dupe(u8, string);
+
+    try self.strings.append(owned);
+    errdefer self.strings.items.len = self.strings.items.len - 1;
+    try self.map.put(owned, id);
+
+    return id;
+}
+
+/// Returns the id of string if it has been interned, without interning it
+pub fn find(self: *const Interner, string: []const u8) ?Id {
+    return self.map.get(string);
+}
+
+/// Returns the string id was interned from, which lives as long as the interner
+pub fn get(self: *const Interner, id: Id) []const u8 {
+    return self.strings.items[id.index()];
+}
+
+/// The number of distinct strings interned
+pub fn count(self: *const Interner) usize {
+    return self.strings.items.len;
+}
+
+test "interner" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "interning" {
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const x = try interner.intern("x");
+        const y = try interner.intern("y");
+
+        try testing.expect(x != y);
+        try testing.expectEqual(x, try interner.intern("x"));
+        try testing.expectEqual(2, interner.count());
+
+        try testing.expectEqualStrings("x", interner.get(x));
+        try testing.expectEqualStrings("y", interner.get(y));
+    }
+
+    test "interned strings are copied" {
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        var buf = "name".*;
+        const id = try interner.intern(&buf);
+        buf[0] = 'g';
+
+        try testing.expectEqualStrings("name", interner.get(id));
+        try testing.expectEqual(id, interner.find("name").?);
+        try testing.expect(interner.find("game") == null);
+    }
+
+    test "finding doesn't intern" {
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try testing.expectEqual(null, interner.find("x"));
+        try testing.expectEqual(0, interner.count());
+    }
+};
@@ -0,0 +1,112 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const builtin = @import("builtin");
+
+const ruka = @import("../prelude.zig");
+const Diagnostic = ruka.Diagnostic;
+const Formatter = ruka.Formatter;
+
+/// How aggressively generated code is optimized
+optimization_level: OptLevel = .debug,
+/// What compilation produces
+emit: EmitKind = .c,
+/// Stop after type checking, producing nothing but diagnostics
+check_only: bool = false,
+/// The target triple to compile for, the host when null. Must be one of
+/// Target.known
+target: ?[]const u8 = null,
+/// Passed to the c compiler after the generated source when building binaries,
+/// libraries to link with for example
+linker_flags: []const []const u8 = &.{},
+/// Include information mapping generated code back to the source
+debug_info: bool = true,
+/// Write a json source map next to generated c, mapping its statements to the
+/// spans they were generated from
+emit_source_map: bool = false,
+/// Fail compilation when a warning is reported
+warnings_as_errors: bool = false,
+/// When diagnostics written to stderr are colored
+color: ColorChoice = .auto,
+/// Stop reporting errors after this many, unlimited when null
+max_errors: ?usize = null,
+/// The directory the tokens of each file are cached in between compilations, so
+/// files which haven't changed aren't scanned again. Nothing is cached when null
+cache_dir: ?[]const u8 = null,
+/// Where compile writes a make rule listing the files it read, so build systems
+/// know to compile again when any change. Nothing is written when null
+dependency_file: ?[]const u8 = null,
+/// Scan large inputs on multiple threads, and parse the modules which don't
+/// depend on each other concurrently, see CompileGraph
+parallel: bool = false,
+/// Print the time each phase of compilation took to stderr, see Profile.zig
+profile_compile_time: bool = false,
+/// Run the lints checking the style of programs, see lints.zig
+lint: bool = false,
+/// The number of statements the body of a function may hold before the
+/// long_function lint warns about it
+max_function_statements: usize = 50,
+/// Compile the #[test] functions into a program running each of them in place
+/// of main
+tests: bool = false,
+/// How sources are laid out when formatted
+format: Formatter.Options = .{},
+
+pub const ColorChoice = Diagnostic.ColorChoice;
+
+pub const OptLevel = enum {
+    debug,
+    release_safe,
+    release_fast,
+    release_small
+};
+
+pub const EmitKind = enum {
+    /// The tokens of each source, compilation stops after scanning
+    tokens,
+    /// The merged ast as an indented tree, compilation stops after parsing
+    ast,
+    /// The merged ast as json, compilation stops after parsing
+    ast_json,
+    c,
+    /// Requires the compiler to be built with -Dllvm-backend
+    llvm_ir,
+    /// A binary, compiled from the generated c with the system's c compiler
+    native,
+    /// A WebAssembly module, requires the compiler to be built with -Dwasm-backend
+    wasm,
+    /// The time each phase of compiling to c took as json, in place of the c
+    timing_json,
+
+    // Map representing the emit kinds and their names on the command line
+    const names = std.StaticStringMap(EmitKind).initComptime(.{
+        .{"tokens", .tokens},
+        .{"ast", .ast},
+        .{"ast-json", .ast_json},
+        .{"c", .c},
+        .{"llvm-ir", .llvm_ir},
+        .{"native", .native},
+        .{"wasm", .wasm},
+        .{"timing-json", .timing_json}
+    });
+
+    /// Finds the emit kind called name on the command line
+    pub fn fromName(name: []const u8) ?EmitKind {
+        return names.get(name);
+    }
+
+    /// The extension of files holding this output
+    pub fn extension(self: EmitKind) []const u8 {
+        return switch (self) {
+            .tokens => ".tokens",
+            .ast => ".ast",
+            .ast_json => ".json",
+            .c => ".c",
+            .llvm_ir => ".ll",
+            .native => builtin.os.tag.exeFileExt(builtin.cpu.arch),
+            .wasm => ".wasm",
+            .timing_json => ".json"
+        };
+    }
+};
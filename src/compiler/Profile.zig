@@ -0,0 +1,160 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The time each phase of a compilation took, recorded when compiling with
+//! --profile-compile-time. Phases run more than once, such as scanning each
+//! file, are recorded as the total of their runs. Phases may be recorded from
+//! several threads at once
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const Mutex = std.Thread.Mutex;
+
+/// In the order each phase first ran
+phases: ArrayList(Phase),
+mutex: Mutex,
+
+const Profile = @This();
+
+pub const Phase = struct {
+    name: []const u8,
+    /// In nanoseconds
+    duration: u64
+};
+
+/// Times a phase from when it begins until end is called, measuring nothing when
+/// there is no profile to record it in
+pub const Measurement = struct {
+    profile: ?*Profile,
+    name: []const u8,
+    timer: ?std.time.Timer,
+
+    pub fn end(self: *Measurement) Allocator.Error!void {
+        const profile = self.profile orelse return;
+        const timer = if (self.timer) |*running| running else return;
+
+        try profile.record(self.name, timer.read());
+    }
+};
+
+pub fn init(allocator: Allocator) Profile {
+    return .{
+        .phases = .init(allocator),
+        .mutex = .{}
+    };
+}
+
+pub fn deinit(self: *Profile) void {
+    self.phases.deinit();
+}
+
+/// Forgets the phases recorded, before compiling again
+pub fn reset(self: *Profile) void {
+    self.phases.clearRetainingCapacity();
+}
+
+/// Begins measuring the phase called name, which is recorded in profile if
+/// there is one. Names must outlive the profile
+pub fn begin(profile: ?*Profile, name: []const u8) Measurement {
+    return .{
+        .profile = profile,
+        .name = name,
+        .timer = if (profile != null) std.time.Timer.start() catch null else null
+    };
+}
+
+/// Adds duration to the phase called name
+pub fn record(self: *Profile, name: []const u8, duration: u64) Allocator.Error!void {
+    self.mutex.lock();
+    defer self.mutex.unlock();
+
+    for (self.phases.items) |*phase| if (std.mem.eql(u8, phase.name, name)) {
+        phase.duration = phase.duration + duration;
+        return;
+    };
+
+    try self.phases.append(.{ .name = name, .duration = duration });
+}
+
+/// The duration of the phase called name, null if it didn't run
+pub fn durationOf(self: *const Profile, name: []const u8) ?u64 {
+    for (self.phases.items) |phase| if (std.mem.eql(u8, phase.name, name)) return phase.duration;
+    return null;
+}
+
+pub fn total(self: *const Profile) u64 {
+    var sum: u64 = 0;
+    for (self.phases.items) |phase| sum = sum + phase.duration;
+    return sum;
+}
+
+/// Writes each phase as `scan: 2.10ms, parse: 15.00ms, ...`, then the total
+pub fn writeTable(self: *const Profile, writer: anytype) !void {
+    for (self.phases.items) |phase| try writer.print("{s}: {d:.2}ms, ", .{phase.name, milliseconds(phase.duration)});
+    try writer.print("total: {d:.2}ms\n", .{milliseconds(self.total())});
+}
+
+/// Writes the phases as `{"phases":[{"name":"scan","ms":2.1}],"total_ms":2.1}`
+pub fn writeJson(self: *const Profile, writer: anytype) !void {
+    try writer.writeAll("{\"phases\":[");
+    for (self.phases.items, 0..) |phase, i| {
+        if (i > 0) try writer.writeByte(',');
+
+        try writer.writeAll("{\"name\":");
+        try std.json.encodeJsonString(phase.name, .{}, writer);
+        try writer.print(",\"ms\":{d}}}", .{milliseconds(phase.duration)});
+    }
+    try writer.print("],\"total_ms\":{d}}}\n", .{milliseconds(self.total())});
+}
+
+fn milliseconds(duration: u64) f64 {
+    return @as(f64, @floatFromInt(duration)) / std.time.ns_per_ms;
+}
+
+test "profile" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "phases run more than once are added together" {
+        var profile = Profile.init(testing.allocator);
+        defer profile.deinit();
+
+        try profile.record("scan", 2 * std.time.ns_per_ms);
+        try profile.record("parse", 500 * std.time.ns_per_us);
+        try profile.record("scan", 1 * std.time.ns_per_ms);
+
+        try testing.expectEqual(2, profile.phases.items.len);
+        try testing.expectEqual(3 * std.time.ns_per_ms, profile.durationOf("scan").?);
+        try testing.expectEqual(null, profile.durationOf("link"));
+
+        var table = ArrayList(u8).init(testing.allocator);
+        defer table.deinit();
+        try profile.writeTable(table.writer());
+        try testing.expectEqualStrings("scan: 3.00ms, parse: 0.50ms, total: 3.50ms\n", table.items);
+
+        var json = ArrayList(u8).init(testing.allocator);
+        defer json.deinit();
+        try profile.writeJson(json.writer());
+        try testing.expectEqualStrings(
+            \\{"phases":[{"name":"scan","ms":3},{"name":"parse","ms":0.5}],"total_ms":3.5}
+            ++ "\n",
+            json.items
+        );
+    }
+
+    test "measurements without a profile record nothing" {
+        var measurement = Profile.begin(null, "scan");
+        try measurement.end();
+
+        var profile = Profile.init(testing.allocator);
+        defer profile.deinit();
+
+        var scanning = Profile.begin(&profile, "scan");
+        try scanning.end();
+        try testing.expect(profile.durationOf("scan") != null);
+    }
+};
@@ -0,0 +1,93 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const AutoHashMap = std.AutoHashMap;
+
+const ruka = @import("../prelude.zig");
+const Interner = ruka.Interner;
+const Span = ruka.Span;
+const Type = ruka.Type;
+
+/// Maps interned names to the symbols they are bound to
+bindings: AutoHashMap(Interner.Id, Symbol),
+
+const Scope = @This();
+
+/// Represents what a name is bound to and where it was defined
+pub const Symbol = struct {
+    kind: Kind,
+    span: Span,
+    /// Set once the symbol has been type checked
+    @"type": ?Type = null,
+
+    pub const Kind = enum {
+        variable,
+        constant,
+        function,
+        /// Functions declared with extern fn, which are only called within unsafe
+        extern_function,
+        parameter,
+        @"type",
+        module
+    };
+
+    pub fn init(kind: Kind, span: Span) Symbol {
+        return Symbol {
+            .kind = kind,
+            .span = span
+        };
+    }
+};
+
+pub fn init(allocator: Allocator) Scope {
+    return Scope {
+        .bindings = .init(allocator)
+    };
+}
+
+pub fn deinit(self: *Scope) void {
+    self.bindings.deinit();
+}
+
+/// Binds name to symbol in this scope, errors if name is already bound
+pub fn define(self: *Scope, name: Interner.Id, symbol: Symbol) !void {
+    const entry = try self.bindings.getOrPut(name);
+    if (entry.found_existing) return error.DuplicateSymbol;
+
+    entry.value_ptr.* = symbol;
+}
+
+/// Binds name to symbol in this scope, replacing any symbol name is already bound to
+pub fn redefine(self: *Scope, name: Interner.Id, symbol: Symbol) !void {
+    try self.bindings.put(name, symbol);
+}
+
+pub fn get(self: *const Scope, name: Interner.Id) ?*const Symbol {
+    return self.bindings.getPtr(name);
+}
+
+test "scope" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "duplicate definitions" {
+        var scope = Scope.init(testing.allocator);
+        defer scope.deinit();
+
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const span: Span = .{ .file = "test source" };
+        const x = try interner.intern("x");
+
+        try scope.define(x, .init(.variable, span));
+        try testing.expectError(error.DuplicateSymbol, scope.define(x, .init(.constant, span)));
+        try testing.expectEqual(.variable, scope.get(x).?.kind);
+        try testing.expectEqual(null, scope.get(try interner.intern("y")));
+    }
+};
@@ -0,0 +1,495 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("../prelude.zig");
+const Interner = ruka.Interner;
+const Scope = ruka.Scope;
+const Span = ruka.Span;
+const Symbol = ruka.Symbol;
+
+/// A stack of scopes, the outermost holds the file's top level bindings and the
+/// innermost is last
+scopes: ArrayList(Scope),
+
+allocator: Allocator,
+
+const SymbolTable = @This();
+
+pub fn init(allocator: Allocator) !SymbolTable {
+    var table = SymbolTable {
+        .scopes = .init(allocator),
+        .allocator = allocator
+    };
+    errdefer table.scopes.deinit();
+
+    try table.scopes.append(.init(allocator));
+
+    return table;
+}
+
+pub fn deinit(self: *SymbolTable) void {
+    for (self.scopes.items) |*scope| scope.deinit();
+    self.scopes.deinit();
+}
+
+/// The number of scopes entered, including the outermost
+pub fn depth(self: *const SymbolTable) usize {
+    return self.scopes.items.len;
+}
+
+/// Enters a new innermost scope
+pub fn enterScope(self: *SymbolTable) !void {
+    try self.scopes.append(.init(self.allocator));
+}
+
+/// Exits the innermost scope, discarding its bindings. The outermost scope
+/// can't be exited
+pub fn exitScope(self: *SymbolTable) void {
+    std.debug.assert(self.scopes.items.len > 1);
+
+    self.scopes.items[self.scopes.items.len - 1].deinit();
+    self.scopes.items.len = self.scopes.items.len - 1;
+}
+
+/// Binds name in the innermost scope, errors if name is already bound there.
+/// Names bound in outer scopes are shadowed
+pub fn define(self: *SymbolTable, name: Interner.Id, symbol: Symbol) !void {
+    try self.scopes.items[self.scopes.items.len - 1].define(name, symbol);
+}
+
+/// Binds name in the innermost scope, replacing any symbol it is already bound to there
+pub fn redefine(self: *SymbolTable, name: Interner.Id, symbol: Symbol) !void {
+    try self.scopes.items[self.scopes.items.len - 1].redefine(name, symbol);
+}
+
+/// Finds the symbol bound to name, searching from the innermost scope outwards
+pub fn lookup(self: *const SymbolTable, name: Interner.Id) ?*const Symbol {
+    var i = self.scopes.items.len;
+    while (i > 0) {
+        i = i - 1;
+        if (self.scopes.items[i].get(name)) |symbol| return symbol;
+    }
+
+    return null;
+}
+
+/// The depth of the innermost scope name is bound in, the outermost is 1
+pub fn depthOf(self: *const SymbolTable, name: Interner.Id) ?usize {
+    var i = self.scopes.items.len;
+    while (i > 0) {
+        i = i - 1;
+        if (self.scopes.items[i].get(name) != null) return i + 1;
+    }
+
+    return null;
+}
+
+/// Finds the symbol bound to name in the innermost scope only
+pub fn lookupLocal(self: *const SymbolTable, name: Interner.Id) ?*const Symbol {
+    return self.scopes.items[self.scopes.items.len - 1].get(name);
+}
+
+test "symbol table" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const span: Span = .{ .file = "test source" };
+
+    test "defining and looking up" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.define(try interner.intern("x"), .init(.variable, span));
+        try table.define(try interner.intern("f"), .init(.function, span));
+
+        try testing.expectEqual(1, table.depth());
+        try testing.expectEqual(.variable, table.lookup(try interner.intern("x")).?.kind);
+        try testing.expectEqual(.function, table.lookup(try interner.intern("f")).?.kind);
+    }
+
+    test "missing symbols" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try testing.expectEqual(null, table.lookup(try interner.intern("x")));
+        try testing.expectEqual(null, table.lookupLocal(try interner.intern("x")));
+
+        try table.define(try interner.intern("x"), .init(.variable, span));
+        try testing.expectEqual(null, table.lookup(try interner.intern("X")));
+        try testing.expectEqual(null, table.lookup(try interner.intern("x1")));
+        try testing.expectEqual(null, table.lookup(try interner.intern("")));
+    }
+
+    test "duplicate definitions" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.define(try interner.intern("x"), .init(.variable, span));
+        try testing.expectError(error.DuplicateSymbol, table.define(try interner.intern("x"), .init(.constant, span)));
+        try testing.expectEqual(.variable, table.lookup(try interner.intern("x")).?.kind);
+
+        try table.enterScope();
+        try table.define(try interner.intern("y"), .init(.constant, span));
+        try testing.expectError(error.DuplicateSymbol, table.define(try interner.intern("y"), .init(.variable, span)));
+    }
+
+    test "shadowing" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.define(try interner.intern("x"), .init(.variable, span));
+        try table.enterScope();
+        try table.define(try interner.intern("x"), .init(.parameter, span));
+        try testing.expectEqual(.parameter, table.lookup(try interner.intern("x")).?.kind);
+
+        try table.enterScope();
+        try table.define(try interner.intern("x"), .init(.constant, span));
+        try testing.expectEqual(.constant, table.lookup(try interner.intern("x")).?.kind);
+
+        table.exitScope();
+        try testing.expectEqual(.parameter, table.lookup(try interner.intern("x")).?.kind);
+
+        table.exitScope();
+        try testing.expectEqual(.variable, table.lookup(try interner.intern("x")).?.kind);
+    }
+
+    test "nested scopes see outer bindings" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.define(try interner.intern("a"), .init(.constant, span));
+        try table.enterScope();
+        try table.define(try interner.intern("b"), .init(.constant, span));
+        try table.enterScope();
+        try table.define(try interner.intern("c"), .init(.constant, span));
+
+        try testing.expectEqual(3, table.depth());
+        for ([_][]const u8{"a", "b", "c"}) |name| {
+            try testing.expect(table.lookup(try interner.intern(name)) != null);
+        }
+
+        try testing.expect(table.lookupLocal(try interner.intern("c")) != null);
+        try testing.expectEqual(null, table.lookupLocal(try interner.intern("a")));
+        try testing.expectEqual(null, table.lookupLocal(try interner.intern("b")));
+
+        try testing.expectEqual(1, table.depthOf(try interner.intern("a")));
+        try testing.expectEqual(3, table.depthOf(try interner.intern("c")));
+        try testing.expectEqual(null, table.depthOf(try interner.intern("d")));
+    }
+
+    test "exiting scopes discards their bindings" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.enterScope();
+        try table.define(try interner.intern("x"), .init(.variable, span));
+        try table.enterScope();
+        try table.define(try interner.intern("y"), .init(.variable, span));
+
+        table.exitScope();
+        try testing.expectEqual(null, table.lookup(try interner.intern("y")));
+        try testing.expect(table.lookup(try interner.intern("x")) != null);
+
+        table.exitScope();
+        try testing.expectEqual(null, table.lookup(try interner.intern("x")));
+        try testing.expectEqual(1, table.depth());
+    }
+
+    test "names can be reused after exiting a scope" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.enterScope();
+        try table.define(try interner.intern("x"), .init(.variable, span));
+        table.exitScope();
+
+        try table.enterScope();
+        try table.define(try interner.intern("x"), .init(.constant, span));
+        try testing.expectEqual(.constant, table.lookup(try interner.intern("x")).?.kind);
+        table.exitScope();
+    }
+
+    test "sibling scopes are independent" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.enterScope();
+        try table.define(try interner.intern("left"), .init(.variable, span));
+        table.exitScope();
+
+        try table.enterScope();
+        try testing.expectEqual(null, table.lookup(try interner.intern("left")));
+        try table.define(try interner.intern("right"), .init(.variable, span));
+        table.exitScope();
+
+        try testing.expectEqual(null, table.lookup(try interner.intern("right")));
+    }
+
+    test "symbols keep their definitions" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const other: Span = .{ .file = "test source", .start = 4, .end = 5, .pos = .init(1, 5) };
+
+        try table.define(try interner.intern("x"), .{ .kind = .constant, .span = other, .@"type" = .@"i32" });
+        try table.enterScope();
+        try table.define(try interner.intern("x"), .init(.variable, span));
+
+        try testing.expectEqual(null, table.lookup(try interner.intern("x")).?.@"type");
+        table.exitScope();
+
+        const symbol = table.lookup(try interner.intern("x")).?;
+        try testing.expectEqual(4, symbol.span.start);
+        try testing.expectEqual(ruka.Type.@"i32", symbol.@"type".?);
+    }
+
+    test "deeply nested scopes" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.define(try interner.intern("root"), .init(.module, span));
+        for (0..64) |_| try table.enterScope();
+
+        try testing.expectEqual(65, table.depth());
+        try testing.expectEqual(.module, table.lookup(try interner.intern("root")).?.kind);
+
+        for (0..64) |_| table.exitScope();
+        try testing.expectEqual(1, table.depth());
+    }
+
+    test "shadowing across several levels" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const x = try interner.intern("x");
+        const kinds = [_]Symbol.Kind{.module, .constant, .variable, .parameter, .function};
+        for (kinds, 0..) |kind, i| {
+            if (i > 0) try table.enterScope();
+            try table.define(x, .init(kind, span));
+
+            try testing.expectEqual(kind, table.lookup(x).?.kind);
+            try testing.expectEqual(i + 1, table.depthOf(x));
+        }
+
+        var i = kinds.len;
+        while (i > 1) {
+            table.exitScope();
+            i = i - 1;
+
+            try testing.expectEqual(kinds[i - 1], table.lookup(x).?.kind);
+            try testing.expectEqual(i, table.depthOf(x));
+        }
+    }
+
+    test "shadowing skips scopes without the name" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const x = try interner.intern("x");
+        try table.define(x, .init(.constant, span));
+        try table.enterScope();
+        try table.enterScope();
+        try testing.expectEqual(.constant, table.lookup(x).?.kind);
+        try testing.expectEqual(1, table.depthOf(x));
+
+        try table.define(x, .init(.variable, span));
+        try table.enterScope();
+        try table.enterScope();
+        try testing.expectEqual(.variable, table.lookup(x).?.kind);
+        try testing.expectEqual(3, table.depthOf(x));
+        try testing.expectEqual(null, table.lookupLocal(x));
+    }
+
+    test "shadowed symbols keep their own spans" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const x = try interner.intern("x");
+        try table.define(x, .init(.variable, .{ .file = "test source", .start = 0, .end = 1 }));
+        try table.enterScope();
+        try table.define(x, .init(.variable, .{ .file = "test source", .start = 10, .end = 11 }));
+        try testing.expectEqual(10, table.lookup(x).?.span.start);
+
+        table.exitScope();
+        try testing.expectEqual(0, table.lookup(x).?.span.start);
+    }
+
+    test "lookup after exiting a scope" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const outer = try interner.intern("outer");
+        const inner = try interner.intern("inner");
+        try table.define(outer, .init(.variable, span));
+        try table.enterScope();
+        try table.define(inner, .init(.parameter, span));
+        try testing.expectEqual(2, table.depthOf(inner));
+
+        table.exitScope();
+        try testing.expectEqual(null, table.lookup(inner));
+        try testing.expectEqual(null, table.lookupLocal(inner));
+        try testing.expectEqual(null, table.depthOf(inner));
+        try testing.expectEqual(.variable, table.lookupLocal(outer).?.kind);
+        try testing.expectEqual(1, table.depthOf(outer));
+    }
+
+    test "lookup after exiting several scopes" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const names = [_][]const u8{"a", "b", "c", "d"};
+        for (names) |name| {
+            try table.enterScope();
+            try table.define(try interner.intern(name), .init(.constant, span));
+        }
+
+        table.exitScope();
+        table.exitScope();
+        try testing.expectEqual(3, table.depth());
+        try testing.expect(table.lookup(try interner.intern("a")) != null);
+        try testing.expect(table.lookup(try interner.intern("b")) != null);
+        try testing.expectEqual(null, table.lookup(try interner.intern("c")));
+        try testing.expectEqual(null, table.lookup(try interner.intern("d")));
+    }
+
+    test "redefinition in the same scope" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const x = try interner.intern("x");
+        try table.define(x, .init(.variable, span));
+        try table.redefine(x, .init(.function, span));
+        try testing.expectEqual(.function, table.lookup(x).?.kind);
+
+        // Redefining a name which isn't bound yet binds it
+        const y = try interner.intern("y");
+        try table.redefine(y, .init(.constant, span));
+        try testing.expectEqual(.constant, table.lookup(y).?.kind);
+
+        // Binding it again with define still fails
+        try testing.expectError(error.DuplicateSymbol, table.define(x, .init(.variable, span)));
+        try testing.expectEqual(.function, table.lookup(x).?.kind);
+    }
+
+    test "redefinition in an inner scope leaves outer bindings" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const x = try interner.intern("x");
+        try table.define(x, .init(.variable, span));
+        try table.enterScope();
+        try table.redefine(x, .init(.parameter, span));
+        try table.redefine(x, .init(.constant, span));
+        try testing.expectEqual(.constant, table.lookup(x).?.kind);
+        try testing.expectEqual(2, table.depthOf(x));
+
+        table.exitScope();
+        try testing.expectEqual(.variable, table.lookup(x).?.kind);
+    }
+
+    test "exiting back to the global scope" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const global = try interner.intern("global");
+        const local = try interner.intern("local");
+        try table.define(global, .init(.function, span));
+
+        for (0..3) |_| {
+            try table.enterScope();
+            try table.define(local, .init(.variable, span));
+            table.exitScope();
+
+            // The global scope is never exited, so its bindings remain
+            try testing.expectEqual(1, table.depth());
+            try testing.expectEqual(.function, table.lookupLocal(global).?.kind);
+            try testing.expectEqual(null, table.lookup(local));
+        }
+
+        // Bindings added to the global scope after exiting are kept as well
+        try table.define(local, .init(.constant, span));
+        try testing.expectEqual(1, table.depthOf(local));
+        try testing.expectError(error.DuplicateSymbol, table.define(global, .init(.variable, span)));
+    }
+
+    test "missing symbols in nested scopes" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        const missing = try interner.intern("missing");
+        try table.define(try interner.intern("x"), .init(.variable, span));
+        for (0..4) |_| {
+            try table.enterScope();
+            try table.define(try interner.intern("y"), .init(.variable, span));
+
+            try testing.expectEqual(null, table.lookup(missing));
+            try testing.expectEqual(null, table.lookupLocal(missing));
+            try testing.expectEqual(null, table.depthOf(missing));
+        }
+
+        // Names bound only in outer scopes aren't local to inner ones
+        try testing.expectEqual(null, table.lookupLocal(try interner.intern("x")));
+    }
+
+    test "missing symbols in sibling scopes" {
+        var table = try SymbolTable.init(testing.allocator);
+        defer table.deinit();
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        try table.enterScope();
+        try table.enterScope();
+        try table.define(try interner.intern("deep"), .init(.variable, span));
+        table.exitScope();
+        try table.enterScope();
+
+        try testing.expectEqual(3, table.depth());
+        try testing.expectEqual(null, table.lookup(try interner.intern("deep")));
+        try testing.expectEqual(null, table.depthOf(try interner.intern("deep")));
+    }
+};
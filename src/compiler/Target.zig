@@ -0,0 +1,114 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The target triples code can be generated for. Compilation is only tested on
+//! the hosts of the CI runners, x86_64-linux-gnu and aarch64-apple-darwin, the
+//! others are known to be valid but cross compiling to them is untested
+
+const std = @import("std");
+const builtin = @import("builtin");
+const Endian = std.builtin.Endian;
+
+triple: []const u8,
+/// The size of pointers in bytes
+pointer_size: u8,
+endian: Endian,
+/// Whether CI runs the compiler's tests on this target
+tested: bool = false,
+
+const Target = @This();
+
+pub const known = [_]Target{
+    .{ .triple = "x86_64-linux-gnu", .pointer_size = 8, .endian = .little, .tested = true },
+    .{ .triple = "x86_64-linux-musl", .pointer_size = 8, .endian = .little },
+    .{ .triple = "x86_64-apple-darwin", .pointer_size = 8, .endian = .little },
+    .{ .triple = "x86_64-pc-windows-msvc", .pointer_size = 8, .endian = .little },
+    .{ .triple = "x86_64-windows-gnu", .pointer_size = 8, .endian = .little },
+    .{ .triple = "i686-linux-gnu", .pointer_size = 4, .endian = .little },
+    .{ .triple = "aarch64-linux-gnu", .pointer_size = 8, .endian = .little },
+    .{ .triple = "aarch64-linux-musl", .pointer_size = 8, .endian = .little },
+    .{ .triple = "aarch64-apple-darwin", .pointer_size = 8, .endian = .little, .tested = true },
+    .{ .triple = "arm-linux-gnueabihf", .pointer_size = 4, .endian = .little },
+    .{ .triple = "riscv64-linux-gnu", .pointer_size = 8, .endian = .little },
+    .{ .triple = "powerpc64-linux-gnu", .pointer_size = 8, .endian = .big },
+    .{ .triple = "s390x-linux-gnu", .pointer_size = 8, .endian = .big },
+    .{ .triple = "wasm32-unknown-unknown", .pointer_size = 4, .endian = .little },
+    .{ .triple = "wasm32-wasi", .pointer_size = 4, .endian = .little }
+};
+
+/// Finds the known target with the triple, null if it isn't one
+pub fn find(triple: []const u8) ?*const Target {
+    for (&known) |*target| if (std.mem.eql(u8, target.triple, triple)) return target;
+    return null;
+}
+
+/// The size of pointers on the target with the triple, or on the host when it is
+/// null or unknown
+pub fn pointerSizeOf(triple: ?[]const u8) u8 {
+    const target = find(triple orelse return @sizeOf(usize)) orelse return @sizeOf(usize);
+    return target.pointer_size;
+}
+
+/// The architecture of the target with the triple, the first of its components,
+/// or the host's when it is null
+pub fn archOf(triple: ?[]const u8) []const u8 {
+    var components = std.mem.splitScalar(u8, triple orelse return @tagName(builtin.cpu.arch), '-');
+    return components.first();
+}
+
+const operating_systems = [_][]const u8{"linux", "darwin", "windows", "wasi"};
+
+/// The operating system of the target with the triple, unknown for those without
+/// one, or the host's when it is null
+pub fn osOf(triple: ?[]const u8) []const u8 {
+    const name = triple orelse return switch (builtin.os.tag) {
+        .macos => "darwin",
+        else => |tag| @tagName(tag)
+    };
+
+    var components = std.mem.splitScalar(u8, name, '-');
+    while (components.next()) |component| for (operating_systems) |os| {
+        if (std.mem.eql(u8, component, os)) return os;
+    };
+
+    return "unknown";
+}
+
+/// The largest value of uintptr_t on the target, as written in c
+pub fn maxPointer(self: *const Target) []const u8 {
+    return if (self.pointer_size == 4) "0xffffffff" else "0xffffffffffffffff";
+}
+
+test "target" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "known triples are found" {
+        const target = find("aarch64-apple-darwin").?;
+        try testing.expectEqual(8, target.pointer_size);
+        try testing.expect(target.tested);
+
+        try testing.expectEqual(.big, find("s390x-linux-gnu").?.endian);
+        try testing.expectEqualStrings("0xffffffff", find("wasm32-unknown-unknown").?.maxPointer());
+        try testing.expectEqual(4, pointerSizeOf("i686-linux-gnu"));
+        try testing.expectEqual(@sizeOf(usize), pointerSizeOf(null));
+    }
+
+    test "architectures and operating systems of triples" {
+        try testing.expectEqualStrings("aarch64", archOf("aarch64-apple-darwin"));
+        try testing.expectEqualStrings("darwin", osOf("aarch64-apple-darwin"));
+        try testing.expectEqualStrings("windows", osOf("x86_64-pc-windows-msvc"));
+        try testing.expectEqualStrings("wasi", osOf("wasm32-wasi"));
+        try testing.expectEqualStrings("unknown", osOf("wasm32-unknown-unknown"));
+        try testing.expectEqualStrings(@tagName(builtin.cpu.arch), archOf(null));
+    }
+
+    test "invalid triples aren't" {
+        try testing.expectEqual(null, find("x86_64"));
+        try testing.expectEqual(null, find("x86_64-linux-gnu-extra"));
+        try testing.expectEqual(null, find(""));
+    }
+};
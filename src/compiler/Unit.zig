@@ -5,32 +5,70 @@ const std = @import("std");
 const Allocator = std.mem.Allocator;
 const AnyReader = std.io.AnyReader;
 const AnyWriter = std.io.AnyWriter;
+const ArenaAllocator = std.heap.ArenaAllocator;
 const ArrayList = std.ArrayList;
 const Mutex = std.Thread.Mutex;
 
 const ruka = @import("../prelude.zig");
 const Ast = ruka.Ast;
+const codegen = ruka.codegen;
 const Compiler = ruka.Compiler;
-const Error = ruka.Error;
+const Diagnostic = ruka.Diagnostic;
+const Environment = ruka.Environment;
+const Interner = ruka.Interner;
+const Interpreter = ruka.Interpreter;
+const MacroExpander = ruka.MacroExpander;
+const Node = ruka.Node;
+const ParallelScanner = ruka.ParallelScanner;
 const Scanner = ruka.Scanner;
+const Span = ruka.Span;
 const Parser = ruka.Parser;
+const Position = ruka.Position;
+const Profile = Compiler.Profile;
+const SymbolTable = ruka.SymbolTable;
+const Token = ruka.Token;
 const Transport = ruka.Transport;
+const TypeChecker = ruka.TypeChecker;
+const TypeTable = TypeChecker.TypeTable;
+const Value = ruka.Value;
+const Visitor = ruka.Visitor;
 
 input: []const u8,
 output: []const u8,
 transport: *Transport,
-errors: ArrayList(Error),
+diagnostics: ArrayList(Diagnostic),
+/// The spans of the comments in the input last scanned, in source order, which the
+/// formatter writes back
+comments: ArrayList(Span),
+/// Interns the unit's identifiers, which its symbols are keyed by
+interner: Interner,
+symbols: SymbolTable,
+options: Compiler.Options,
+/// The json source map of the c last emitted, when options.emit_source_map is set
+source_map: ?[]const u8,
+/// Where attributes allow or deny lints, in the order the attributes appear
+lint_levels: ArrayList(LintLevel),
+/// The compiler whose merged ast the unit compiles, which holds the sources its
+/// spans refer to. Null for units compiling their input alone
+compiler: ?*const Compiler,
+/// Where the time each phase takes is recorded, null when it isn't profiled
+profile: ?*Profile,
 
+/// Owns the formatted messages of diagnostics
+arena: ArenaAllocator,
 allocator: Allocator,
 
 const Unit = @This();
 
+const log = std.log.scoped(.unit);
+
 pub const UnitOptions = struct {
     input: []const u8,
     output: []const u8,
     reader: AnyReader,
     writer: AnyWriter,
     allocator: Allocator,
+    options: Compiler.Options = .{},
 
     pub fn testing(reader: AnyReader, writer: AnyWriter) UnitOptions {
         return UnitOptions {
@@ -43,6 +81,31 @@ pub const UnitOptions = struct {
     }
 };
 
+/// A lint allowed or denied by an attribute, within the declaration it is applied to
+pub const LintLevel = struct {
+    lint: Diagnostic.Lint,
+    level: Level,
+    span: Span,
+
+    pub const Level = enum {
+        /// Its warnings aren't reported
+        allow,
+        /// Its warnings are reported as errors
+        deny
+    };
+};
+
+/// The ast of a compiled unit and the types of its nodes
+pub const Result = struct {
+    ast: *Ast,
+    types: *TypeTable,
+
+    pub fn deinit(self: Result) void {
+        self.types.deinit();
+        self.ast.deinit();
+    }
+};
+
 pub fn init(opts: UnitOptions) !*Unit {
     const unit = try opts.allocator.create(Unit);
     errdefer unit.deinit();
@@ -51,8 +114,17 @@ pub fn init(opts: UnitOptions) !*Unit {
         .input = opts.input,
         .output = opts.output,
         .transport = try .init(opts.allocator, opts.reader, opts.writer),
-        .errors = .init(opts.allocator),
+        .diagnostics = .init(opts.allocator),
+        .comments = .init(opts.allocator),
+        .interner = .init(opts.allocator),
+        .symbols = try .init(opts.allocator),
+        .options = opts.options,
+        .source_map = null,
+        .lint_levels = .init(opts.allocator),
+        .compiler = null,
+        .profile = null,
 
+        .arena = .init(opts.allocator),
         .allocator = opts.allocator
     };
 
@@ -60,36 +132,402 @@ pub fn init(opts: UnitOptions) !*Unit {
 }
 
 pub fn deinit(self: *Unit) void {
-    self.errors.deinit();
+    self.symbols.deinit();
+    self.interner.deinit();
+    self.diagnostics.deinit();
+    self.comments.deinit();
+    self.lint_levels.deinit();
+    self.arena.deinit();
     self.transport.deinit();
     self.allocator.destroy(self);
 }
 
-pub fn createError(self: *Unit, scanner: *Scanner, kind: []const u8, msg: []const u8) !void {
-    try self.errors.append(.{
-        .file = self.input,
-        .kind = kind,
-        .msg = msg,
-        .pos = scanner.current_pos
-    });
+/// Records a diagnostic, its message must outlive the unit. Diagnostics of lints
+/// allowed where they are reported are dropped, those of denied lints are errors
+pub fn report(self: *Unit, diagnostic: Diagnostic) !void {
+    var reported = diagnostic;
+    if (diagnostic.lint) |lint| if (self.lintLevel(lint, diagnostic.span)) |level| switch (level) {
+        .allow => return,
+        .deny => reported.severity = .@"error"
+    };
+
+    try self.diagnostics.append(reported);
+}
+
+/// Records the lints allowed and denied by attributes, within span. Attributes
+/// which aren't allow or deny, and lints which don't exist, are ignored as the
+/// parser reports them
+pub fn setLintLevels(self: *Unit, attributes: []const Node.Attribute, span: Span) !void {
+    for (attributes) |attribute| {
+        const level = std.meta.stringToEnum(LintLevel.Level, attribute.name) orelse continue;
+        for (attribute.args) |name| {
+            const lint = Diagnostic.Lint.fromName(name) orelse continue;
+            try self.lint_levels.append(.{ .lint = lint, .level = level, .span = span });
+        }
+    }
+}
+
+/// Records the lint levels set by the attributes of every declaration in ast,
+/// those of a unit it wasn't parsed by
+pub fn addLintLevels(self: *Unit, ast: *const Ast) !void {
+    var collector = LintCollector{ .unit = self };
+    try LintCollector.Walk.visit(&collector, ast.root);
+}
+
+/// Whether an attribute enclosing span allows lint
+pub fn isAllowed(self: *const Unit, lint: Diagnostic.Lint, span: Span) bool {
+    return self.lintLevel(lint, span) == .allow;
+}
+
+// The level of lint at span, set by the innermost attribute enclosing it. Later
+// attributes are nested within or follow earlier ones, so the last enclosing is innermost
+fn lintLevel(self: *const Unit, lint: Diagnostic.Lint, span: Span) ?LintLevel.Level {
+    var i = self.lint_levels.items.len;
+    while (i > 0) {
+        i = i - 1;
+
+        const level = self.lint_levels.items[i];
+        if (level.lint != lint or !std.mem.eql(u8, level.span.file, span.file)) continue;
+        if (level.span.start <= span.start and span.end <= level.span.end) return level.level;
+    }
+
+    return null;
+}
+
+const LintCollector = struct {
+    unit: *Unit,
+
+    const Walk = Visitor(LintCollector, Allocator.Error, void);
+
+    pub fn visitNode(self: *LintCollector, node: *const Node) Allocator.Error!void {
+        if (node.attributes.len > 0) {
+            try self.unit.setLintLevels(node.attributes, node.attributes[0].span.merge(node.span));
+        }
+
+        try Walk.dispatch(self, node);
+    }
+};
+
+/// The source node was parsed from, or node written back out as source if the
+/// unit doesn't hold the file it was parsed from. Owned by the unit
+pub fn sourceText(self: *Unit, node: *const Node) Allocator.Error![]const u8 {
+    if (self.compiler) |compiler| if (compiler.findSource(node.span.file)) |source| return node.span.slice(source);
+
+    // Writing to memory only fails when it runs out
+    var text = ArrayList(u8).init(self.arena.allocator());
+    Ast.writeSource(node, text.writer().any()) catch return error.OutOfMemory;
+
+    return text.items;
+}
+
+pub fn createError(self: *Unit, msg: []const u8, span: Span) !void {
+    try self.report(.init(.@"error", msg, span));
 }
 
-pub fn compile(self: *Unit) !*Ast {
-    var scanner = try Scanner.init(self);
+/// Records an error whose message is formatted into memory owned by the unit
+pub fn createErrorFmt(self: *Unit, span: Span, comptime fmt: []const u8, args: anytype) !void {
+    try self.createError(try std.fmt.allocPrint(self.arena.allocator(), fmt, args), span);
+}
+
+/// The number of diagnostics recorded which are errors, including warnings
+/// if they are treated as errors
+pub fn errorCount(self: *const Unit) usize {
+    var count: usize = 0;
+    for (self.diagnostics.items) |diagnostic| switch (diagnostic.severity) {
+        .@"error" => count = count + 1,
+        .warning => if (self.options.warnings_as_errors) {
+            count = count + 1;
+        },
+        else => {}
+    };
+
+    return count;
+}
+
+/// Scans the input, the caller owns the tokens
+pub fn scan(self: *Unit) !ArrayList(Token) {
+    self.comments.clearRetainingCapacity();
+
+    if (!self.options.parallel) {
+        var scanner = try Scanner.init(self);
+        defer scanner.deinit();
+
+        return try scanner.scan();
+    }
+
+    const source = try self.transport.readAll(self.allocator);
+    defer self.allocator.free(source);
+
+    var scanner = try ParallelScanner.init(self, source);
     defer scanner.deinit();
 
-    var parser = try Parser.init(self, scanner);
+    return try scanner.scan();
+}
+
+/// Scans and parses the input
+pub fn parse(self: *Unit) !*Ast {
+    var tokens = try self.scan();
+    defer {
+        for (tokens.items) |token| token.deinit();
+        tokens.deinit();
+    }
+
+    return try self.parseTokens(tokens.items);
+}
+
+/// Expands the macros of tokens of the input, whose identifiers are interned by
+/// the unit, then parses them
+pub fn parseTokens(self: *Unit, tokens: []const Token) !*Ast {
+    var expander = try MacroExpander.init(self);
+    defer expander.deinit();
+
+    var parser = try Parser.init(self, try expander.expand(tokens));
     defer parser.deinit();
 
     const ast = try parser.parse();
+    log.debug("{s}:\n{}", .{self.input, ast});
 
     return ast;
 }
 
+/// Scans, parses and type checks the input
+pub fn check(self: *Unit) !Result {
+    const ast = try self.parse();
+    errdefer ast.deinit();
+
+    return .{
+        .ast = ast,
+        .types = try self.checkAst(ast)
+    };
+}
+
+/// Type checks an ast, which may hold nodes parsed by other units
+pub fn checkAst(self: *Unit, ast: *Ast) !*TypeTable {
+    var checker = try TypeChecker.init(self, ast);
+    defer checker.deinit();
+
+    return try checker.check();
+}
+
+/// Checks the input, then writes what options.emit selects to the output
+pub fn compile(self: *Unit) !Result {
+    const result = try self.check();
+    errdefer result.deinit();
+
+    try self.emit(result);
+
+    return result;
+}
+
+/// Writes what options.emit selects, generated from a checked ast, to the output
+pub fn emit(self: *Unit, result: Result) !void {
+    if (self.options.check_only) return;
+
+    switch (self.options.emit) {
+        // Tokens aren't kept once they are parsed, see emitTokens
+        .tokens => {},
+        .ast, .ast_json => try self.emitAst(result.ast),
+        // Binaries are compiled from the generated c by the compiler, which
+        // replaces it with the profile when emitting timing json
+        .c, .native, .timing_json => try self.emitC(result.ast, result.types),
+        .llvm_ir => try self.emitLlvmIr(result.ast, result.types),
+        .wasm => try self.emitWasm(result.ast, result.types)
+    }
+}
+
+/// Scans the input, writing each token to the output on a line of its own
+pub fn emitTokens(self: *Unit) !void {
+    var tokens = try self.scan();
+    defer {
+        for (tokens.items) |token| token.deinit();
+        tokens.deinit();
+    }
+
+    for (tokens.items) |*token| {
+        try self.transport.printNoFlush("{} {s} {s}\n", .{
+            token.span,
+            @tagName(token.kind),
+            try token.kind.toStr(&self.interner, self.arena.allocator())
+        });
+    }
+
+    try self.transport.flush();
+}
+
+/// Writes a parsed ast to the output, as json if options.emit selects it and
+/// otherwise as an indented tree
+pub fn emitAst(self: *Unit, ast: *const Ast) !void {
+    var buffer = ArrayList(u8).init(self.allocator);
+    defer buffer.deinit();
+
+    const writer = buffer.writer();
+    switch (self.options.emit) {
+        .ast_json => try ast.writeJsonIndented(self.allocator, writer),
+        else => try writer.print("{}", .{ast.*})
+    }
+
+    try self.transport.writeAll(buffer.items);
+}
+
+/// Scans and parses the input, then evaluates it with its top level bindings
+/// defined in env, which may already hold bindings from earlier units. Env takes
+/// the ast, as function values reference it
+pub fn interpret(self: *Unit, env: *Environment) !Value {
+    const ast = try self.parse();
+    env.adopt(ast) catch |err| {
+        ast.deinit();
+        return err;
+    };
+
+    var interpreter = try Interpreter.init(self, env);
+    defer interpreter.deinit();
+
+    return try interpreter.eval(ast.root);
+}
+
+// Writes the C generated from the ast to the unit's output
+fn emitC(self: *Unit, ast: *Ast, types: *TypeTable) !void {
+    var generator = try codegen.C.init(self, ast, types);
+    defer generator.deinit();
+
+    const source = try generator.generate();
+    defer self.allocator.free(source);
+
+    try self.transport.writeAll(source);
+
+    if (self.options.emit_source_map) {
+        var json = ArrayList(u8).init(self.arena.allocator());
+        try generator.source_map.writeJson(json.writer());
+        self.source_map = json.items;
+    }
+}
+
+// Writes the llvm ir generated from the ast to the unit's output
+fn emitLlvmIr(self: *Unit, ast: *Ast, types: *TypeTable) !void {
+    if (!codegen.LLVM.enabled) {
+        log.err("{s}: the compiler was built without the llvm backend, enable it with -Dllvm-backend", .{self.input});
+        return error.BackendUnavailable;
+    }
+
+    var generator = try codegen.LLVM.init(self, ast, types);
+    defer generator.deinit();
+
+    const ir = try generator.generate();
+    defer self.allocator.free(ir);
+
+    try self.transport.writeAll(ir);
+}
+
+// Writes the wasm module generated from the ast to the unit's output
+fn emitWasm(self: *Unit, ast: *Ast, types: *TypeTable) !void {
+    if (!codegen.Wasm.enabled) {
+        log.err("{s}: the compiler was built without the wasm backend, enable it with -Dwasm-backend", .{self.input});
+        return error.BackendUnavailable;
+    }
+
+    var generator = try codegen.Wasm.init(self, ast, types);
+    defer generator.deinit();
+
+    const module = try generator.generate();
+    defer self.allocator.free(module);
+
+    try self.transport.writeAll(module);
+}
+
 test "unit modules" {
     _ = tests;
+    _ = SymbolTable;
+    _ = Interner;
+    _ = Diagnostic;
 }
 
 const tests = struct {
+    const testing = std.testing;
+
+    test "symbol table" {
+        var input = std.io.fixedBufferStream("");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        const span: Span = .{ .file = "test source" };
+
+        const x = try unit.interner.intern("x");
+        const y = try unit.interner.intern("y");
+
+        try unit.symbols.define(x, .init(.variable, span));
+        try unit.symbols.enterScope();
+        try unit.symbols.define(x, .init(.parameter, span));
+        try unit.symbols.define(y, .init(.constant, span));
+        try testing.expectError(error.DuplicateSymbol, unit.symbols.define(y, .init(.constant, span)));
+
+        try testing.expectEqual(.parameter, unit.symbols.lookup(x).?.kind);
+        try testing.expectEqual(.constant, unit.symbols.lookup(y).?.kind);
+
+        unit.symbols.exitScope();
+        try testing.expectEqual(.variable, unit.symbols.lookup(x).?.kind);
+        try testing.expectEqual(null, unit.symbols.lookup(y));
+    }
+
+    test "diagnostics" {
+        var input = std.io.fixedBufferStream("");
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        const span: Span = .{ .file = "test source" };
 
+        try unit.report(.init(.warning, "unused binding", span));
+        try unit.createErrorFmt(span, "expected {} arguments", .{2});
+
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqual(1, unit.errorCount());
+        try testing.expectEqualStrings("expected 2 arguments", unit.diagnostics.items[1].message);
+
+        unit.options.warnings_as_errors = true;
+        try testing.expectEqual(2, unit.errorCount());
+    }
+
+    test "allowed and denied lints" {
+        var input = std.io.fixedBufferStream("");
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const function: Span = .init("test source", 0, 100, .init(1, 1));
+        const binding: Span = .init("test source", 20, 40, .init(2, 5));
+
+        try unit.setLintLevels(&.{.{ .name = "deny", .args = &.{"unused_variable", "dead_code"}, .span = function }}, function);
+        try unit.setLintLevels(&.{.{ .name = "allow", .args = &.{"unused_variable"}, .span = binding }}, binding);
+
+        try unit.report(.initLint(.unused_variable, "value assigned but never used", .init("test source", 25, 30, .init(2, 10))));
+        try unit.report(.initLint(.unused_variable, "value assigned but never used", .init("test source", 50, 55, .init(4, 5))));
+        try unit.report(.initLint(.dead_code, "unreachable code", .init("other source", 50, 55, .init(4, 5))));
+
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+        try testing.expectEqual(.@"error", unit.diagnostics.items[0].severity);
+        try testing.expectEqual(Position.init(4, 5), unit.diagnostics.items[0].span.pos);
+        try testing.expectEqual(.warning, unit.diagnostics.items[1].severity);
+    }
+
+    test "emitting tokens" {
+        var input = std.io.fixedBufferStream("let x = 1");
+
+        var output = ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+
+        var unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        try unit.emitTokens();
+
+        try testing.expectStringStartsWith(output.items, "test source:1:1 keyword let\ntest source:1:5 identifier x\n");
+        try testing.expectStringEndsWith(output.items, "eof EOF\n");
+    }
 };
@@ -0,0 +1,269 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The file system the compiler reads sources from and writes its output to.
+//! Compilers use the real file system unless given another, such as a MemFs
+//! holding the files of a test, which is safe to share between threads
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const Dir = std.fs.Dir;
+const Mutex = std.Thread.Mutex;
+const StringHashMap = std.StringHashMap;
+
+ptr: *anyopaque,
+readFn: *const fn (ptr: *anyopaque, allocator: Allocator, path: []const u8) anyerror!File,
+writeFn: *const fn (ptr: *anyopaque, path: []const u8, data: []const u8) anyerror!void,
+mtimeFn: *const fn (ptr: *anyopaque, path: []const u8) anyerror!?i128,
+deleteFn: *const fn (ptr: *anyopaque, path: []const u8) anyerror!void,
+
+const VirtualFs = @This();
+
+/// The contents of a file, and when it was last modified if the file system
+/// records it. Files without modification times aren't cached or watched
+pub const File = struct {
+    contents: []u8,
+    mtime: ?i128 = null
+};
+
+/// Reads the whole file at path into memory owned by allocator
+pub fn read(self: VirtualFs, allocator: Allocator, path: []const u8) !File {
+    return try self.readFn(self.ptr, allocator, path);
+}
+
+/// Replaces the contents of the file at path with data, creating it if needed
+pub fn write(self: VirtualFs, path: []const u8, data: []const u8) !void {
+    try self.writeFn(self.ptr, path, data);
+}
+
+/// When the file at path was last modified, without reading it. Null if the file
+/// system doesn't record modification times
+pub fn mtime(self: VirtualFs, path: []const u8) !?i128 {
+    return try self.mtimeFn(self.ptr, path);
+}
+
+/// Removes the file at path
+pub fn delete(self: VirtualFs, path: []const u8) !void {
+    try self.deleteFn(self.ptr, path);
+}
+
+/// The files of the operating system, with relative paths resolved from dir.
+/// Holding a pointer lets the directory change after the fs is created
+pub const RealFs = struct {
+    dir: *const Dir,
+
+    pub fn init(dir: *const Dir) RealFs {
+        return .{ .dir = dir };
+    }
+
+    pub fn fs(self: *RealFs) VirtualFs {
+        return .{
+            .ptr = self,
+            .readFn = readOpaque,
+            .writeFn = writeOpaque,
+            .mtimeFn = mtimeOpaque,
+            .deleteFn = deleteOpaque
+        };
+    }
+
+    pub fn read(self: *const RealFs, allocator: Allocator, path: []const u8) !File {
+        const file = try self.dir.openFile(path, .{});
+        defer file.close();
+
+        const stat = try file.stat();
+        return .{
+            .contents = try file.readToEndAlloc(allocator, std.math.maxInt(usize)),
+            .mtime = stat.mtime
+        };
+    }
+
+    pub fn write(self: *const RealFs, path: []const u8, data: []const u8) !void {
+        try self.dir.writeFile(.{ .sub_path = path, .data = data });
+    }
+
+    pub fn mtime(self: *const RealFs, path: []const u8) !?i128 {
+        return (try self.dir.statFile(path)).mtime;
+    }
+
+    pub fn delete(self: *const RealFs, path: []const u8) !void {
+        try self.dir.deleteFile(path);
+    }
+
+    fn readOpaque(ptr: *anyopaque, allocator: Allocator, path: []const u8) anyerror!File {
+        const self: *RealFs = @ptrCast(@alignCast(ptr));
+        return try self.read(allocator, path);
+    }
+
+    fn writeOpaque(ptr: *anyopaque, path: []const u8, data: []const u8) anyerror!void {
+        const self: *RealFs = @ptrCast(@alignCast(ptr));
+        try self.write(path, data);
+    }
+
+    fn mtimeOpaque(ptr: *anyopaque, path: []const u8) anyerror!?i128 {
+        const self: *RealFs = @ptrCast(@alignCast(ptr));
+        return try self.mtime(path);
+    }
+
+    fn deleteOpaque(ptr: *anyopaque, path: []const u8) anyerror!void {
+        const self: *RealFs = @ptrCast(@alignCast(ptr));
+        try self.delete(path);
+    }
+};
+
+/// Files held in memory by their paths, which are compared as they are written
+/// rather than resolved
+pub const MemFs = struct {
+    /// Owns its paths and contents
+    files: StringHashMap([]u8),
+    mutex: Mutex,
+    allocator: Allocator,
+
+    pub fn init(allocator: Allocator) MemFs {
+        return .{
+            .files = .init(allocator),
+            .mutex = .{},
+            .allocator = allocator
+        };
+    }
+
+    pub fn deinit(self: *MemFs) void {
+        var entries = self.files.iterator();
+        while (entries.next()) |entry| {
+            self.allocator.free(entry.key_ptr.*);
+            self.allocator.free(entry.value_ptr.*);
+        }
+        self.files.deinit();
+    }
+
+    pub fn fs(self: *MemFs) VirtualFs {
+        return .{
+            .ptr = self,
+            .readFn = readOpaque,
+            .writeFn = writeOpaque,
+            .mtimeFn = mtimeOpaque,
+            .deleteFn = deleteOpaque
+        };
+    }
+
+    pub fn read(self: *MemFs, allocator: Allocator, path: []const u8) !File {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        const contents = self.files.get(path) orelse return error.FileNotFound;
+        return .{ .contents = try allocator.dupe(u8, contents) };
+    }
+
+    pub fn write(self: *MemFs, path: []const u8, data: []const u8) !void {
+        const contents = try self.allocator.dupe(u8, data);
+        errdefer self.allocator.free(contents);
+
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        const entry = try self.files.getOrPut(path);
+        if (entry.found_existing) {
+            self.allocator.free(entry.value_ptr.*);
+        } else {
+            entry.key_ptr.* = self.allocator.dupe(u8, path) catch |err| {
+                self.files.removeByPtr(entry.key_ptr);
+                return err;
+            };
+        }
+        entry.value_ptr.* = contents;
+    }
+
+    /// The contents of the file at path, which stay owned by the fs
+    pub fn get(self: *MemFs, path: []const u8) ?[]const u8 {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        return self.files.get(path);
+    }
+
+    // Files in memory have no modification times, only missing files are errors
+    pub fn mtime(self: *MemFs, path: []const u8) !?i128 {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        if (!self.files.contains(path)) return error.FileNotFound;
+        return null;
+    }
+
+    pub fn delete(self: *MemFs, path: []const u8) !void {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+
+        const entry = self.files.fetchRemove(path) orelse return error.FileNotFound;
+        self.allocator.free(entry.key);
+        self.allocator.free(entry.value);
+    }
+
+    fn readOpaque(ptr: *anyopaque, allocator: Allocator, path: []const u8) anyerror!File {
+        const self: *MemFs = @ptrCast(@alignCast(ptr));
+        return try self.read(allocator, path);
+    }
+
+    fn writeOpaque(ptr: *anyopaque, path: []const u8, data: []const u8) anyerror!void {
+        const self: *MemFs = @ptrCast(@alignCast(ptr));
+        try self.write(path, data);
+    }
+
+    fn mtimeOpaque(ptr: *anyopaque, path: []const u8) anyerror!?i128 {
+        const self: *MemFs = @ptrCast(@alignCast(ptr));
+        return try self.mtime(path);
+    }
+
+    fn deleteOpaque(ptr: *anyopaque, path: []const u8) anyerror!void {
+        const self: *MemFs = @ptrCast(@alignCast(ptr));
+        try self.delete(path);
+    }
+};
+
+test "virtual fs" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "files written to memory are read back" {
+        var memory = MemFs.init(testing.allocator);
+        defer memory.deinit();
+
+        const vfs = memory.fs();
+        try vfs.write("lib/math.ruka", "let x = 1\n");
+        try vfs.write("lib/math.ruka", "let x = 2\n");
+
+        const file = try vfs.read(testing.allocator, "lib/math.ruka");
+        defer testing.allocator.free(file.contents);
+
+        try testing.expectEqualStrings("let x = 2\n", file.contents);
+        try testing.expectEqual(null, file.mtime);
+        try testing.expectEqual(null, try vfs.mtime("lib/math.ruka"));
+        try testing.expectError(error.FileNotFound, vfs.read(testing.allocator, "lib/missing.ruka"));
+        try testing.expectError(error.FileNotFound, vfs.mtime("lib/missing.ruka"));
+
+        try vfs.delete("lib/math.ruka");
+        try testing.expectEqual(null, memory.get("lib/math.ruka"));
+        try testing.expectError(error.FileNotFound, vfs.delete("lib/math.ruka"));
+    }
+
+    test "real files are read with their modification times" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        var real = RealFs.init(&tmp.dir);
+        const vfs = real.fs();
+        try vfs.write("main.ruka", "let y = 3\n");
+
+        const file = try vfs.read(testing.allocator, "main.ruka");
+        defer testing.allocator.free(file.contents);
+
+        try testing.expectEqualStrings("let y = 3\n", file.contents);
+        try testing.expect(file.mtime != null);
+        try testing.expectEqual(file.mtime, try vfs.mtime("main.ruka"));
+
+        try vfs.delete("main.ruka");
+        try testing.expectError(error.FileNotFound, vfs.mtime("main.ruka"));
+    }
+};
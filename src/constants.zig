@@ -12,6 +12,8 @@ pub const subcommands_display =
     \\        build   : Builds the project in the current directory
     \\        test    : Tests the project in the current directory
     \\        run     : Runs the project in the current directory
+    \\        repl    : Starts an interactive session, also started by --repl
+    \\        lsp     : Starts a language server over stdin and stdout, also started by --lsp
     \\        version : Displays the current ruka version installed
     \\        help    : Displays the help menu
 ;
@@ -19,6 +21,19 @@ pub const subcommands_display =
 const options_display =
     \\    options:
     \\        --change_dir, -C [subpath] : Changes the directory to a subdirectory of the cwd
+    \\        --color [when]             : Colors diagnostics always, never or only on terminals when auto, the default
+    \\        --dependency-file [path]   : Writes a make rule listing the files compiled to path
+    \\        --emit [kind]              : Selects what is produced: tokens, ast, ast-json, c, llvm-ir, native, wasm or timing-json
+    \\        --linker-flag [flag]       : Passes a flag to the c compiler when building binaries, repeatable
+    \\        --max-errors [count]       : Stops showing errors after count of them, showing every error by default
+    \\        --target [triple]          : Compiles for another target, such as aarch64-linux-gnu or wasm32-wasi
+    \\        --check                    : Checks the project for errors without producing anything
+    \\        --fmt                      : Formats the project's sources, only checking them with --check
+    \\        --lint                     : Warns about unused imports, misnamed declarations and long functions
+    \\        --print-ast                : Writes the ast as indented json, as --emit ast-json does
+    \\        --profile-compile-time     : Prints the time each phase of compilation takes to stderr
+    \\        --test                     : Builds a program running the project's #[test] functions in place of main
+    \\        --watch                    : Checks or builds the project again whenever a file it uses changes
 ;
 
 pub const version_str = std.fmt.comptimePrint("{}", .{project_options.version});
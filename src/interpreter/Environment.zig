@@ -0,0 +1,259 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const stdlib = ruka.stdlib;
+const Value = ruka.Value;
+
+/// A stack of scopes, the outermost holds the top level bindings and the
+/// innermost is last
+scopes: ArrayList(Scope),
+/// The outermost scope of the function being evaluated, the scopes between it
+/// and the top level belong to its callers and aren't visible
+base: usize,
+/// The asts of the units evaluated in this environment, which function values reference
+asts: ArrayList(*Ast),
+/// The streams the io module reads and writes
+io: stdlib.io.Streams,
+
+/// Owns the bound names and the memory of values, so they outlive the
+/// interpreters and asts that produced them
+arena: ArenaAllocator,
+allocator: Allocator,
+
+const Environment = @This();
+
+const Scope = StringHashMap(Value);
+
+pub fn init(allocator: Allocator) !*Environment {
+    const env = try allocator.create(Environment);
+    errdefer allocator.destroy(env);
+
+    env.* = .{
+        .scopes = .init(allocator),
+        .base = 0,
+        .asts = .init(allocator),
+        .io = .{},
+        .arena = .init(allocator),
+        .allocator = allocator
+    };
+    errdefer env.scopes.deinit();
+
+    try env.scopes.append(.init(allocator));
+
+    return env;
+}
+
+pub fn deinit(self: *Environment) void {
+    for (self.scopes.items) |*scope| scope.deinit();
+    self.scopes.deinit();
+    for (self.asts.items) |ast| ast.deinit();
+    self.asts.deinit();
+    self.arena.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Takes ownership of an ast whose values may be bound in the environment
+pub fn adopt(self: *Environment, ast: *Ast) !void {
+    try self.asts.append(ast);
+}
+
+/// Discards every binding, along with the asts and memory their values reference
+pub fn reset(self: *Environment) void {
+    for (self.scopes.items[1..]) |*scope| scope.deinit();
+    self.scopes.shrinkRetainingCapacity(1);
+    self.scopes.items[0].clearAndFree();
+    self.base = 0;
+
+    for (self.asts.items) |ast| ast.deinit();
+    self.asts.clearRetainingCapacity();
+
+    _ = self.arena.reset(.retain_capacity);
+}
+
+/// Allocates the memory of values, which lives as long as the environment
+pub fn valueAllocator(self: *Environment) Allocator {
+    return self.arena.allocator();
+}
+
+/// Enters a new innermost scope
+pub fn enterScope(self: *Environment) !void {
+    try self.scopes.append(.init(self.allocator));
+}
+
+/// Exits the innermost scope, discarding its bindings. The outermost scope
+/// can't be exited
+pub fn exitScope(self: *Environment) void {
+    std.debug.assert(self.scopes.items.len > 1);
+
+    self.scopes.items[self.scopes.items.len - 1].deinit();
+    self.scopes.items.len = self.scopes.items.len - 1;
+}
+
+/// Enters the outermost scope of a called function, hiding every binding except
+/// the top level ones. Returns the base exitFrame restores
+pub fn enterFrame(self: *Environment) !usize {
+    const base = self.base;
+
+    try self.enterScope();
+    self.base = self.scopes.items.len - 1;
+
+    return base;
+}
+
+/// Exits the scope entered by enterFrame, making the caller's bindings visible again
+pub fn exitFrame(self: *Environment, base: usize) void {
+    self.exitScope();
+    self.base = base;
+}
+
+/// Binds name in the innermost scope, replacing any binding of name already there.
+/// Names bound in outer scopes are shadowed
+pub fn define(self: *Environment, name: []const u8, value: Value) !void {
+    const scope = &self.scopes.items[self.scopes.items.len - 1];
+
+    const entry = try scope.getOrPut(name);
+    if (!entry.found_existing) entry.key_ptr.* = try self.arena.allocator().dupe(u8, name);
+    entry.value_ptr.* = value;
+}
+
+/// Finds the value bound to name, searching from the innermost visible scope outwards
+pub fn lookup(self: *const Environment, name: []const u8) ?Value {
+    return if (self.find(name)) |value| value.* else null;
+}
+
+/// Rebinds the nearest visible binding of name to value, returning false if
+/// name isn't bound
+pub fn assign(self: *Environment, name: []const u8, value: Value) bool {
+    const bound = self.find(name) orelse return false;
+    bound.* = value;
+
+    return true;
+}
+
+/// The index of the scope holding the nearest visible binding of name, which
+/// get and set find it in while the scope lasts
+pub fn locate(self: *const Environment, name: []const u8) ?usize {
+    var i = self.scopes.items.len;
+    while (i > self.base) {
+        i = i - 1;
+        if (self.scopes.items[i].contains(name)) return i;
+    }
+
+    // The top level is always visible
+    if (self.base == 0 or !self.scopes.items[0].contains(name)) return null;
+    return 0;
+}
+
+/// The value bound to name in the scope at index, whether or not it is visible.
+/// Null once the scope is exited
+pub fn get(self: *const Environment, scope: usize, name: []const u8) ?Value {
+    if (scope >= self.scopes.items.len) return null;
+    return self.scopes.items[scope].get(name);
+}
+
+/// Rebinds name in the scope at index, returning false if it isn't bound there
+pub fn set(self: *Environment, scope: usize, name: []const u8, value: Value) bool {
+    if (scope >= self.scopes.items.len) return false;
+
+    const bound = self.scopes.items[scope].getPtr(name) orelse return false;
+    bound.* = value;
+
+    return true;
+}
+
+fn find(self: *const Environment, name: []const u8) ?*Value {
+    const scope = self.locate(name) orelse return null;
+    return self.scopes.items[scope].getPtr(name);
+}
+
+test "environment" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "scopes and shadowing" {
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        try env.define("x", .{ .integer = 1 });
+        try env.enterScope();
+        try env.define("x", .{ .boolean = true });
+        try env.define("y", .unit);
+
+        try testing.expectEqual(Value{ .boolean = true }, env.lookup("x").?);
+        try testing.expect(env.assign("y", .{ .integer = 2 }));
+        try testing.expectEqual(Value{ .integer = 2 }, env.lookup("y").?);
+
+        env.exitScope();
+        try testing.expectEqual(Value{ .integer = 1 }, env.lookup("x").?);
+        try testing.expectEqual(null, env.lookup("y"));
+        try testing.expect(!env.assign("y", .unit));
+    }
+
+    test "frames hide their callers' bindings" {
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        try env.define("global", .{ .integer = 1 });
+        try env.enterScope();
+        try env.define("local", .{ .integer = 2 });
+
+        const base = try env.enterFrame();
+        try env.define("param", .{ .integer = 3 });
+
+        try testing.expectEqual(Value{ .integer = 1 }, env.lookup("global").?);
+        try testing.expectEqual(Value{ .integer = 3 }, env.lookup("param").?);
+        try testing.expectEqual(null, env.lookup("local"));
+
+        env.exitFrame(base);
+        try testing.expectEqual(Value{ .integer = 2 }, env.lookup("local").?);
+        try testing.expectEqual(null, env.lookup("param"));
+    }
+
+    test "bindings are found by scope while it lasts" {
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        try env.enterScope();
+        try env.define("local", .{ .integer = 1 });
+        const scope = env.locate("local").?;
+
+        const base = try env.enterFrame();
+        try testing.expectEqual(null, env.locate("local"));
+        try testing.expect(env.set(scope, "local", .{ .integer = 2 }));
+        env.exitFrame(base);
+
+        try testing.expectEqual(Value{ .integer = 2 }, env.get(scope, "local").?);
+
+        env.exitScope();
+        try testing.expectEqual(null, env.get(scope, "local"));
+        try testing.expect(!env.set(scope, "local", .unit));
+    }
+
+    test "resetting" {
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        try env.define("x", .{ .string = try env.valueAllocator().dupe(u8, "abc") });
+        try env.enterScope();
+        try env.adopt(try Ast.init(testing.allocator, "test source"));
+
+        env.reset();
+        try testing.expectEqual(1, env.scopes.items.len);
+        try testing.expectEqual(0, env.asts.items.len);
+        try testing.expectEqual(null, env.lookup("x"));
+
+        try env.define("x", .unit);
+        try testing.expectEqual(Value.unit, env.lookup("x").?);
+    }
+};
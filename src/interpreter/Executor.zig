@@ -0,0 +1,84 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Drives the futures of async function calls to completion on a single thread.
+//! Futures are lazy, the function a future calls runs when it is first polled.
+//! The interpreter can't suspend a body partway through, so polling runs the
+//! function to its end, awaiting the futures within it as they are reached
+
+const std = @import("std");
+
+const ruka = @import("../prelude.zig");
+const Interpreter = ruka.Interpreter;
+const Span = ruka.Span;
+const Value = ruka.Value;
+
+interpreter: *Interpreter,
+/// The number of times futures were polled by this executor
+polls: usize,
+
+const Executor = @This();
+
+pub fn init(interpreter: *Interpreter) Executor {
+    return .{
+        .interpreter = interpreter,
+        .polls = 0
+    };
+}
+
+/// Polls future, running the function it calls if it hasn't run yet, and gives
+/// the value the function returned. Futures keep their result, polling them again
+/// doesn't run the function again
+pub fn blockOn(self: *Executor, span: Span, future: *Value.Future) Interpreter.EvalError!Value {
+    self.polls = self.polls + 1;
+    if (future.result) |result| return result;
+
+    future.result = try self.interpreter.runFuture(span, future);
+    return future.result.?;
+}
+
+test "executor" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const Environment = ruka.Environment;
+    const Unit = ruka.Unit;
+
+    test "futures run their function once, when first polled" {
+        const source =
+            \\var calls = 0
+            \\async fn add(a: i32, b: i32) -> i32 {
+            \\    calls = calls + 1
+            \\    a + b
+            \\}
+            \\let future = add(1, 2)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        _ = try unit.interpret(env);
+
+        const future = env.lookup("future").?.future;
+        try testing.expect(future.result == null);
+        try testing.expect(env.lookup("calls").?.eql(.{ .integer = 0 }));
+
+        const interpreter = try Interpreter.init(unit, env);
+        defer interpreter.deinit();
+
+        const span: Span = .{ .file = "test source" };
+        var executor = Executor.init(interpreter);
+        try testing.expect((try executor.blockOn(span, future)).eql(.{ .integer = 3 }));
+        try testing.expect((try executor.blockOn(span, future)).eql(.{ .integer = 3 }));
+
+        try testing.expectEqual(2, executor.polls);
+        try testing.expect(env.lookup("calls").?.eql(.{ .integer = 1 }));
+    }
+};
@@ -0,0 +1,79 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Lints checking the style of programs rather than their correctness, run over
+//! checked asts when compiling with --lint. Each rule reports the lint it is
+//! named after, which attributes may allow or deny as they do other lints
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("prelude.zig");
+const Ast = ruka.Ast;
+const Lint = ruka.Diagnostic.Lint;
+const Unit = ruka.Unit;
+
+pub const LongFunction = @import("lints/LongFunction.zig");
+pub const NamingConventions = @import("lints/NamingConventions.zig");
+pub const UnusedImports = @import("lints/UnusedImports.zig");
+
+/// A lint run over whole asts, which reports its diagnostics to the unit it was
+/// created for
+pub const Rule = struct {
+    ptr: *anyopaque,
+    id: Lint,
+    runFn: *const fn (ptr: *anyopaque, ast: *const Ast) Allocator.Error!void,
+
+    pub fn run(self: Rule, ast: *const Ast) Allocator.Error!void {
+        try self.runFn(self.ptr, ast);
+    }
+};
+
+/// Runs each of its rules in the order they were added
+pub const Runner = struct {
+    rules: ArrayList(Rule),
+
+    pub fn init(allocator: Allocator) Runner {
+        return .{ .rules = .init(allocator) };
+    }
+
+    pub fn deinit(self: *Runner) void {
+        self.rules.deinit();
+    }
+
+    pub fn add(self: *Runner, rule: Rule) Allocator.Error!void {
+        try self.rules.append(rule);
+    }
+
+    pub fn run(self: *const Runner, ast: *const Ast) Allocator.Error!void {
+        for (self.rules.items) |rule| try rule.run(ast);
+    }
+};
+
+/// Runs the built in lints over a checked ast, recording their warnings in unit
+pub fn lint(unit: *Unit, ast: *const Ast) !void {
+    var long_function = try LongFunction.init(unit, unit.options.max_function_statements);
+    defer long_function.deinit();
+
+    var naming_conventions = try NamingConventions.init(unit);
+    defer naming_conventions.deinit();
+
+    var unused_imports = try UnusedImports.init(unit);
+    defer unused_imports.deinit();
+
+    var runner = Runner.init(unit.allocator);
+    defer runner.deinit();
+
+    try runner.add(long_function.rule());
+    try runner.add(naming_conventions.rule());
+    try runner.add(unused_imports.rule());
+
+    try runner.run(ast);
+}
+
+test "lint modules" {
+    _ = LongFunction;
+    _ = NamingConventions;
+    _ = UnusedImports;
+}
@@ -0,0 +1,147 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Warns about functions whose bodies hold more statements than a limit, counting
+//! the statements of the blocks nested in them. Functions defined within others
+//! are counted on their own
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Rule = ruka.lints.Rule;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+unit: *Unit,
+/// The number of statements a function may hold
+max_statements: usize,
+allocator: Allocator,
+
+const LongFunction = @This();
+
+const Walk = Visitor(LongFunction, Allocator.Error, void);
+
+pub fn init(unit: *Unit, max_statements: usize) !*LongFunction {
+    const lint = try unit.allocator.create(LongFunction);
+
+    lint.* = .{
+        .unit = unit,
+        .max_statements = max_statements,
+        .allocator = unit.allocator
+    };
+
+    return lint;
+}
+
+pub fn deinit(self: *LongFunction) void {
+    self.allocator.destroy(self);
+}
+
+pub fn rule(self: *LongFunction) Rule {
+    return .{
+        .ptr = self,
+        .id = .long_function,
+        .runFn = runOpaque
+    };
+}
+
+fn runOpaque(ptr: *anyopaque, ast: *const Ast) Allocator.Error!void {
+    const self: *LongFunction = @ptrCast(@alignCast(ptr));
+    try self.run(ast);
+}
+
+pub fn run(self: *LongFunction, ast: *const Ast) Allocator.Error!void {
+    try Walk.visit(self, ast.root);
+}
+
+pub fn visitFnDef(self: *LongFunction, node: *const Node, fn_def: Node.FunctionDef) Allocator.Error!void {
+    var counter: Counter = .{};
+    try CountWalk.visit(&counter, fn_def.body);
+
+    if (counter.statements > self.max_statements) {
+        try self.unit.report(.initLint(.long_function, try std.fmt.allocPrint(
+            self.unit.arena.allocator(),
+            "function {s} holds {} statements, more than the {} functions may hold",
+            .{fn_def.name, counter.statements, self.max_statements}
+        ), node.span));
+    }
+
+    try Walk.visitChildren(self, node);
+}
+
+// Counts the statements of the blocks within a function's body
+const Counter = struct {
+    statements: usize = 0,
+
+    pub fn visitBlock(self: *Counter, node: *const Node, block: Node.Block) Allocator.Error!void {
+        self.statements = self.statements + block.statements.len;
+        try CountWalk.visitChildren(self, node);
+    }
+
+    // Counted as the function they define
+    pub fn visitFnDef(_: *Counter, _: *const Node, _: Node.FunctionDef) Allocator.Error!void {}
+};
+
+const CountWalk = Visitor(Counter, Allocator.Error, void);
+
+test "long function" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    fn expectWarnings(source: []const u8, max_statements: usize, expected: []const []const u8) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        var lint = try LongFunction.init(unit, max_statements);
+        defer lint.deinit();
+
+        try lint.rule().run(ast);
+
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+            try testing.expectEqual(.long_function, diagnostic.lint.?);
+        }
+    }
+
+    test "functions holding too many statements are warned about" {
+        try expectWarnings(
+            \\fn long(x: i64) -> i64 {
+            \\    let a = x + 1
+            \\    let b = a * 2
+            \\    if b > 3 {
+            \\        return a
+            \\    }
+            \\    b
+            \\}
+        , 4, &.{"function long holds 5 statements, more than the 4 functions may hold"});
+    }
+
+    test "short functions and nested functions aren't warned about" {
+        try expectWarnings(
+            \\fn short(x: i64) -> i64 {
+            \\    let a = x + 1
+            \\    a * 2
+            \\}
+            \\fn outer() {
+            \\    fn inner() {
+            \\        let a = 1
+            \\        let b = 2
+            \\        let c = 3
+            \\    }
+            \\    inner()
+            \\}
+        , 3, &.{});
+    }
+};
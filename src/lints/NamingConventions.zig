@@ -0,0 +1,223 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Warns about names which don't follow the language's conventions. Functions,
+//! parameters and bindings are snake_case, consts may also be SCREAMING_CASE,
+//! and structs, enums and their variants are PascalCase. Leading underscores
+//! are ignored, and extern functions keep the names C gives them
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Rule = ruka.lints.Rule;
+const Span = ruka.Span;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+unit: *Unit,
+allocator: Allocator,
+
+const NamingConventions = @This();
+
+const Walk = Visitor(NamingConventions, Allocator.Error, void);
+
+const Case = enum {
+    snake,
+    screaming,
+    pascal,
+
+    fn matches(self: Case, name: []const u8) bool {
+        const trimmed = std.mem.trimLeft(u8, name, "_");
+        if (trimmed.len == 0) return true;
+
+        return switch (self) {
+            .snake => for (trimmed) |c| {
+                if (!std.ascii.isLower(c) and !std.ascii.isDigit(c) and c != '_') break false;
+            } else true,
+            .screaming => for (trimmed) |c| {
+                if (!std.ascii.isUpper(c) and !std.ascii.isDigit(c) and c != '_') break false;
+            } else true,
+            .pascal => std.ascii.isUpper(trimmed[0]) and std.mem.indexOfScalar(u8, trimmed, '_') == null
+        };
+    }
+
+    fn toStr(self: Case) []const u8 {
+        return switch (self) {
+            .snake => "snake_case",
+            .screaming => "SCREAMING_CASE",
+            .pascal => "PascalCase"
+        };
+    }
+};
+
+pub fn init(unit: *Unit) !*NamingConventions {
+    const lint = try unit.allocator.create(NamingConventions);
+
+    lint.* = .{
+        .unit = unit,
+        .allocator = unit.allocator
+    };
+
+    return lint;
+}
+
+pub fn deinit(self: *NamingConventions) void {
+    self.allocator.destroy(self);
+}
+
+pub fn rule(self: *NamingConventions) Rule {
+    return .{
+        .ptr = self,
+        .id = .naming_conventions,
+        .runFn = runOpaque
+    };
+}
+
+fn runOpaque(ptr: *anyopaque, ast: *const Ast) Allocator.Error!void {
+    const self: *NamingConventions = @ptrCast(@alignCast(ptr));
+    try self.run(ast);
+}
+
+pub fn run(self: *NamingConventions, ast: *const Ast) Allocator.Error!void {
+    try Walk.visit(self, ast.root);
+}
+
+pub fn visitFnDef(self: *NamingConventions, node: *const Node, fn_def: Node.FunctionDef) Allocator.Error!void {
+    try self.expectCase("function", fn_def.name, .snake, null, node.span);
+    try self.checkParams(fn_def.params);
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitClosure(self: *NamingConventions, node: *const Node, closure: Node.Closure) Allocator.Error!void {
+    try self.checkParams(closure.params);
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitVarDecl(self: *NamingConventions, node: *const Node, binding: Node.Binding) Allocator.Error!void {
+    try self.expectCase("binding", binding.name, .snake, null, node.span);
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitConstDecl(self: *NamingConventions, node: *const Node, binding: Node.Binding) Allocator.Error!void {
+    try self.expectCase("const", binding.name, .snake, .screaming, node.span);
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitStructDef(self: *NamingConventions, node: *const Node, struct_def: Node.StructDef) Allocator.Error!void {
+    try self.expectCase("struct", struct_def.name, .pascal, null, node.span);
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitUnionDef(self: *NamingConventions, node: *const Node, union_def: Node.UnionDef) Allocator.Error!void {
+    try self.expectCase("union", union_def.name, .pascal, null, node.span);
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitEnumDef(self: *NamingConventions, node: *const Node, enum_def: Node.EnumDef) Allocator.Error!void {
+    try self.expectCase("enum", enum_def.name, .pascal, null, node.span);
+    for (enum_def.variants) |variant| try self.expectCase("variant", variant.name, .pascal, null, variant.span);
+    try Walk.visitChildren(self, node);
+}
+
+// Methods with defaults are checked as the functions they define
+pub fn visitTraitDef(self: *NamingConventions, node: *const Node, trait_def: Node.TraitDef) Allocator.Error!void {
+    try self.expectCase("trait", trait_def.name, .pascal, null, node.span);
+    for (trait_def.methods) |method| if (method.default == null) {
+        try self.expectCase("method", method.name, .snake, null, method.span);
+        try self.checkParams(method.params);
+    };
+    try Walk.visitChildren(self, node);
+}
+
+fn checkParams(self: *NamingConventions, params: []const Node.Parameter) Allocator.Error!void {
+    for (params) |param| try self.expectCase("parameter", param.name, .snake, null, param.span);
+}
+
+// Warns when name is in neither case nor alternative
+fn expectCase(
+    self: *NamingConventions,
+    what: []const u8,
+    name: []const u8,
+    case: Case,
+    alternative: ?Case,
+    span: Span
+) Allocator.Error!void {
+    if (case.matches(name)) return;
+    if (alternative) |other| if (other.matches(name)) return;
+
+    const message = if (alternative) |other| try std.fmt.allocPrint(
+        self.unit.arena.allocator(),
+        "{s} {s} should be named in {s} or {s}",
+        .{what, name, case.toStr(), other.toStr()}
+    ) else try std.fmt.allocPrint(
+        self.unit.arena.allocator(),
+        "{s} {s} should be named in {s}",
+        .{what, name, case.toStr()}
+    );
+
+    try self.unit.report(.initLint(.naming_conventions, message, span));
+}
+
+test "naming conventions" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    fn expectWarnings(source: []const u8, expected: []const []const u8) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        var lint = try NamingConventions.init(unit);
+        defer lint.deinit();
+
+        try lint.rule().run(ast);
+
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+            try testing.expectEqual(.naming_conventions, diagnostic.lint.?);
+        }
+    }
+
+    test "names breaking the conventions are warned about" {
+        try expectWarnings(
+            \\struct point_2d { x: i64, y: i64 }
+            \\enum Shape { Circle(i64), unit_square }
+            \\const maxSize = 10
+            \\fn drawLine(startX: i64) {
+            \\    let Total = startX * 2
+            \\    let f = fn(Scale: i64) -> i64 { Scale }
+            \\}
+        , &.{
+            "struct point_2d should be named in PascalCase",
+            "variant unit_square should be named in PascalCase",
+            "const maxSize should be named in snake_case or SCREAMING_CASE",
+            "function drawLine should be named in snake_case",
+            "parameter startX should be named in snake_case",
+            "binding Total should be named in snake_case",
+            "parameter Scale should be named in snake_case"
+        });
+    }
+
+    test "names following the conventions aren't warned about" {
+        try expectWarnings(
+            \\struct Point2d { x: i64, y: i64 }
+            \\enum Shape { Circle(i64), UnitSquare }
+            \\const MAX_SIZE = 10
+            \\const tau = 6.28
+            \\fn draw_line(start_x: i64, _unused: i64) {
+            \\    let total_2 = start_x * 2
+            \\}
+        , &.{});
+    }
+};
@@ -0,0 +1,241 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Warns about imports of modules none of whose exported names are used by the
+//! module importing them. Which modules are imported is only known to the
+//! compiler which loaded them, so nothing is warned about in units compiling a
+//! single file
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Rule = ruka.lints.Rule;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+unit: *Unit,
+allocator: Allocator,
+
+const UnusedImports = @This();
+
+pub fn init(unit: *Unit) !*UnusedImports {
+    const lint = try unit.allocator.create(UnusedImports);
+
+    lint.* = .{
+        .unit = unit,
+        .allocator = unit.allocator
+    };
+
+    return lint;
+}
+
+pub fn deinit(self: *UnusedImports) void {
+    self.allocator.destroy(self);
+}
+
+pub fn rule(self: *UnusedImports) Rule {
+    return .{
+        .ptr = self,
+        .id = .unused_imports,
+        .runFn = runOpaque
+    };
+}
+
+fn runOpaque(ptr: *anyopaque, ast: *const Ast) Allocator.Error!void {
+    const self: *UnusedImports = @ptrCast(@alignCast(ptr));
+    try self.run(ast);
+}
+
+/// Warns about the unused imports of the modules merged into ast
+pub fn run(self: *UnusedImports, ast: *const Ast) Allocator.Error!void {
+    const compiler = self.unit.compiler orelse return;
+
+    var uses = Uses.init(self.allocator);
+    defer uses.deinit();
+
+    try UseWalk.visit(&uses, ast.root);
+
+    // The imports of a module are resolved in the order they are written, so the
+    // nth import of a file loaded the nth module it imports
+    var imported = StringHashMap(usize).init(self.allocator);
+    defer imported.deinit();
+
+    for (ast.root.kind.module.items) |item| {
+        const import = switch (item.kind) {
+            .import => |import| import,
+            else => continue
+        };
+
+        const file = item.span.file;
+        const module = compiler.modules.get(file) orelse continue;
+
+        const entry = try imported.getOrPut(file);
+        if (!entry.found_existing) entry.value_ptr.* = 0;
+        const index = entry.value_ptr.*;
+        entry.value_ptr.* = index + 1;
+
+        if (index >= module.imports.items.len) continue;
+        const dependency = compiler.modules.get(module.imports.items[index]) orelse continue;
+
+        var definitions = dependency.definitions.iterator();
+        const used = while (definitions.next()) |definition| {
+            if (definition.value_ptr.* and uses.contains(file, definition.key_ptr.*)) break true;
+        } else false;
+        if (used) continue;
+
+        try self.unit.report(.initLint(.unused_imports, try std.fmt.allocPrint(
+            self.unit.arena.allocator(),
+            "{s} is imported, but none of its names are used",
+            .{try std.mem.join(self.unit.arena.allocator(), ".", import.path)}
+        ), item.span));
+    }
+}
+
+// The names each file refers to, as values, types, and in literals and patterns
+const Uses = struct {
+    files: StringHashMap(StringHashMap(void)),
+
+    fn init(allocator: Allocator) Uses {
+        return .{ .files = .init(allocator) };
+    }
+
+    fn deinit(self: *Uses) void {
+        var names = self.files.valueIterator();
+        while (names.next()) |set| set.deinit();
+        self.files.deinit();
+    }
+
+    fn contains(self: *const Uses, file: []const u8, name: []const u8) bool {
+        const names = self.files.getPtr(file) orelse return false;
+        return names.contains(name);
+    }
+
+    fn use(self: *Uses, file: []const u8, name: []const u8) Allocator.Error!void {
+        const entry = try self.files.getOrPut(file);
+        if (!entry.found_existing) entry.value_ptr.* = .init(self.files.allocator);
+
+        try entry.value_ptr.put(name, {});
+    }
+
+    fn usePattern(self: *Uses, pattern: Node.Pattern) Allocator.Error!void {
+        const file = pattern.span.file;
+        switch (pattern.kind) {
+            .literal, .identifier, .wildcard => {},
+            .@"struct" => |structure| {
+                try self.use(file, structure.name);
+                for (structure.fields) |field| try self.usePattern(field.pattern);
+            },
+            .variant => |variant| {
+                for (variant.path) |segment| try self.use(file, segment);
+                switch (variant.fields) {
+                    .unit => {},
+                    .tuple => |fields| for (fields) |field| try self.usePattern(field),
+                    .@"struct" => |fields| for (fields) |field| try self.usePattern(field.pattern)
+                }
+            },
+            .tuple, .@"or" => |patterns| for (patterns) |alternative| try self.usePattern(alternative)
+        }
+    }
+
+    pub fn visitIdentifier(self: *Uses, node: *const Node, name: []const u8) Allocator.Error!void {
+        try self.use(node.span.file, name);
+    }
+
+    pub fn visitTypeExpr(self: *Uses, node: *const Node, type_expr: Node.TypeExpr) Allocator.Error!void {
+        switch (type_expr) {
+            .generic => |generic| try self.use(node.span.file, generic.name),
+            .impl => |trait| try self.use(node.span.file, trait),
+            else => {}
+        }
+        try UseWalk.visitChildren(self, node);
+    }
+
+    pub fn visitStructLiteral(self: *Uses, node: *const Node, literal: Node.StructLiteral) Allocator.Error!void {
+        try self.use(node.span.file, literal.name);
+        try UseWalk.visitChildren(self, node);
+    }
+
+    pub fn visitVariantLiteral(self: *Uses, node: *const Node, literal: Node.VariantLiteral) Allocator.Error!void {
+        try self.use(node.span.file, literal.@"enum");
+        try UseWalk.visitChildren(self, node);
+    }
+
+    pub fn visitImpl(self: *Uses, node: *const Node, impl: Node.Impl) Allocator.Error!void {
+        try self.use(node.span.file, impl.name);
+        if (impl.trait) |trait| try self.use(node.span.file, trait);
+        try UseWalk.visitChildren(self, node);
+    }
+
+    pub fn visitMatch(self: *Uses, node: *const Node, match: Node.Match) Allocator.Error!void {
+        for (match.arms) |arm| try self.usePattern(arm.pattern);
+        try UseWalk.visitChildren(self, node);
+    }
+
+    pub fn visitDestructure(self: *Uses, node: *const Node, destructure: Node.Destructure) Allocator.Error!void {
+        try self.usePattern(destructure.pattern);
+        try UseWalk.visitChildren(self, node);
+    }
+};
+
+const UseWalk = Visitor(Uses, Allocator.Error, void);
+
+test "unused imports" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const Compiler = ruka.Compiler;
+    const MemFs = ruka.VirtualFs.MemFs;
+
+    fn expectWarnings(main: []const u8, expected: []const []const u8) !void {
+        var memory = MemFs.init(testing.allocator);
+        defer memory.deinit();
+
+        try memory.write("main.ruka", main);
+        try memory.write("lib/math.ruka", "pub fn double(x: i64) -> i64 { x * 2 }\nlet hidden = 1\n");
+        try memory.write("lib/shapes.ruka", "pub struct Square { side: i64 }\n");
+
+        var compiler = try Compiler.initWithFs(testing.allocator, "main.ruka", null, .{ .check_only = true, .lint = true }, memory.fs());
+        defer compiler.deinit();
+
+        const result = (try compiler.compile()).?;
+        defer result.deinit();
+
+        var count: usize = 0;
+        for (compiler.diagnostics.items) |diagnostic| {
+            const lint = diagnostic.lint orelse continue;
+            if (lint != .unused_imports) continue;
+
+            try testing.expect(count < expected.len);
+            try testing.expectEqualStrings(expected[count], diagnostic.message);
+            count = count + 1;
+        }
+        try testing.expectEqual(expected.len, count);
+    }
+
+    test "imports whose names aren't used are warned about" {
+        try expectWarnings(
+            \\import lib.math
+            \\import lib.shapes
+            \\let y = 2
+        , &.{
+            "lib.math is imported, but none of its names are used",
+            "lib.shapes is imported, but none of its names are used"
+        });
+    }
+
+    test "imports whose names are used aren't warned about" {
+        try expectWarnings(
+            \\import lib.math
+            \\import lib.shapes
+            \\let y = double(2)
+            \\fn side(s: Square) -> i64 { s.side }
+        , &.{});
+    }
+};
@@ -3,10 +3,12 @@
 
 const std = @import("std");
 const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
 
 const ruka = @import("prelude.zig");
 const ArgumentParser = ruka.ArgumentParser;
 const Compiler = ruka.Compiler;
+const LanguageServer = ruka.LanguageServer;
 const Repl = ruka.Repl;
 const Transport = ruka.Transport;
 
@@ -46,10 +48,11 @@ pub fn main() !void {
 
     switch (arg_parser.getSubcommand().?) {
         .new => try newProject(),
-        .build => try buildProject(arg_parser, allocator),
-        .@"test" => try testProject(),
+        .build => try buildProject(arg_parser, allocator, false),
+        .@"test" => try buildProject(arg_parser, allocator, true),
         .run => try runProject(),
         .repl => try startRepl(allocator),
+        .lsp => try startLanguageServer(allocator),
         .version => try displayVersion(transport),
         .help => try displayHelp(transport)
     }
@@ -71,37 +74,145 @@ fn isProperProject() void {
 
 }
 
-fn buildProject(arg_parser: *ArgumentParser, allocator: Allocator) !void {
-    var compiler = try Compiler.init(allocator);
+// Testing a project builds it with --test
+fn buildProject(arg_parser: *ArgumentParser, allocator: Allocator, with_tests: bool) !void {
+    var compiler = try Compiler.init(allocator, .{});
     defer compiler.deinit();
 
-    if (arg_parser.getOption()) |option| {
+    compiler.options.tests = with_tests;
+
+    var linker_flags = ArrayList([]const u8).init(allocator);
+    defer linker_flags.deinit();
+
+    var format = false;
+    var watch = false;
+    while (arg_parser.getOption()) |option| {
         switch (option) {
             .change_dir => |path| {
                 compiler.cwd = try compiler.cwd.openDir(path, .{});
-            }
+            },
+            .color => |choice| compiler.options.color = choice,
+            .dependency_file => |path| compiler.options.dependency_file = path,
+            .emit => |kind| compiler.options.emit = kind,
+            .linker_flag => |flag| try linker_flags.append(flag),
+            .max_errors => |max| compiler.options.max_errors = max,
+            .target => |triple| compiler.options.target = triple,
+            .check => compiler.options.check_only = true,
+            .fmt => format = true,
+            .lint => compiler.options.lint = true,
+            .profile_compile_time => compiler.options.profile_compile_time = true,
+            .@"test" => compiler.options.tests = true,
+            .watch => watch = true
         }
     }
 
-    try compiler.buildProject();
+    // Checking the format fails when any file would be changed by formatting
+    if (format) {
+        const unformatted = try compiler.formatProject();
+        if (compiler.options.check_only and unformatted > 0) std.process.exit(1);
+
+        return;
+    }
+
+    compiler.options.linker_flags = linker_flags.items;
+    if (watch) {
+        try compiler.addProjectSources();
+        try compiler.watch();
+
+        return;
+    }
+
+    if (!try build(compiler)) std.process.exit(1);
 }
 
+// Compiles the sources of the project together, noting why compilation failed
+// when it does. The diagnostics are rendered as they are reported
+fn build(compiler: *Compiler) !bool {
+    const result = compileProject(compiler) catch {
+        try compiler.transport.print("Compilation failed: {}\n", .{compiler.failure.?});
+        return false;
+    };
+    if (result) |compiled| compiled.deinit();
 
-fn testProject() !void {
+    return true;
+}
 
+fn compileProject(compiler: *Compiler) Compiler.Error!?Compiler.Unit.Result {
+    try compiler.addProjectSources();
+    return try compiler.compile();
 }
 
+
 fn runProject() !void {
 
 }
 
 fn startRepl(allocator: Allocator) !void {
-    var repl = try Repl.init(allocator);
+    var repl = try Repl.initTerminal(allocator);
     defer repl.deinit();
 
     try repl.run();
 }
 
+fn startLanguageServer(allocator: Allocator) !void {
+    var server = try LanguageServer.initStdio(allocator);
+    defer server.deinit();
+
+    // The protocol expects a failing exit code when the client exits without shutting down
+    if (!try server.run()) std.process.exit(1);
+}
+
 test "ruka modules" {
     _ = ruka;
+    _ = tests;
 }
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "building a project" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.makeDir("src");
+        try tmp.dir.writeFile(.{ .sub_path = "src/main.ruka", .data = "let x = 1 + 2\n" });
+        try tmp.dir.writeFile(.{ .sub_path = "src/other.ruka", .data = "let y = x * 2\n" });
+
+        var compiler = try Compiler.init(testing.allocator, .{ .check_only = true });
+        defer compiler.deinit();
+        compiler.cwd = tmp.dir;
+
+        try testing.expect(try build(compiler));
+        try testing.expectEqual(2, compiler.sources.items.len);
+        try testing.expectEqual(0, compiler.diagnostics.items.len);
+        try testing.expectEqual(null, compiler.failure);
+    }
+
+    test "building a project with errors" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        try tmp.dir.makeDir("src");
+        try tmp.dir.writeFile(.{ .sub_path = "src/main.ruka", .data = "let x: bool = 1\n" });
+
+        var compiler = try Compiler.init(testing.allocator, .{ .check_only = true });
+        defer compiler.deinit();
+        compiler.cwd = tmp.dir;
+
+        try testing.expect(!try build(compiler));
+        try testing.expectEqual(1, compiler.diagnostics.items.len);
+        try testing.expectEqual(Compiler.Failure{ .errors = 1 }, compiler.failure.?);
+    }
+
+    test "building a project without sources" {
+        var tmp = testing.tmpDir(.{});
+        defer tmp.cleanup();
+
+        var compiler = try Compiler.init(testing.allocator, .{ .check_only = true });
+        defer compiler.deinit();
+        compiler.cwd = tmp.dir;
+
+        try testing.expect(!try build(compiler));
+        try testing.expect(compiler.failure.? == .io);
+    }
+};
@@ -0,0 +1,34 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Passes simplifying parsed asts before they are type checked
+
+const ruka = @import("prelude.zig");
+const Ast = ruka.Ast;
+const Profile = ruka.Compiler.Profile;
+const Unit = ruka.Unit;
+
+pub const ConstantFolder = @import("optimizer/ConstantFolder.zig");
+pub const DeadCodeEliminator = @import("optimizer/DeadCodeEliminator.zig");
+
+/// Runs each pass over ast in order, recording their warnings in unit
+pub fn optimize(unit: *Unit, ast: *Ast) !void {
+    var folder = try ConstantFolder.init(unit);
+    defer folder.deinit();
+
+    var folding = Profile.begin(unit.profile, "constant folding");
+    try folder.fold(ast);
+    try folding.end();
+
+    var eliminator = try DeadCodeEliminator.init(unit);
+    defer eliminator.deinit();
+
+    var eliminating = Profile.begin(unit.profile, "dead code elimination");
+    try eliminator.eliminate(ast);
+    try eliminating.end();
+}
+
+test "optimizer modules" {
+    _ = ConstantFolder;
+    _ = DeadCodeEliminator;
+}
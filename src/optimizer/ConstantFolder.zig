@@ -0,0 +1,371 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Replaces infix expressions whose operands are literals with the literal they
+//! evaluate to, and the sizes of the built in types with their values
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const AutoHashMap = std.AutoHashMap;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Target = ruka.Target;
+const Type = ruka.Type;
+const Unit = ruka.Unit;
+const VisitorMut = ruka.VisitorMut;
+
+unit: *Unit,
+/// The number of nodes folded by the current run over the ast
+folded: usize,
+/// The ids of nodes which overflowed when folded, so each is only warned about once
+overflowed: AutoHashMap(usize, void),
+
+allocator: Allocator,
+
+const ConstantFolder = @This();
+
+// Folds children first, so nested constant expressions collapse in a single run
+const Walk = VisitorMut(ConstantFolder, Allocator.Error, void);
+
+const Operator = Node.Infix.Operator;
+const Literal = Node.Literal;
+
+pub fn init(unit: *Unit) !*ConstantFolder {
+    const folder = try unit.allocator.create(ConstantFolder);
+
+    folder.* = .{
+        .unit = unit,
+        .folded = 0,
+        .overflowed = .init(unit.allocator),
+        .allocator = unit.allocator
+    };
+
+    return folder;
+}
+
+pub fn deinit(self: *ConstantFolder) void {
+    self.overflowed.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Folds the constant expressions of ast, running over it until nothing is left to fold
+pub fn fold(self: *ConstantFolder, ast: *Ast) !void {
+    while (true) {
+        self.folded = 0;
+        try Walk.visit(self, ast.root);
+
+        if (self.folded == 0) break;
+    }
+}
+
+pub fn visitInfix(self: *ConstantFolder, node: *Node, infix: *Node.Infix) Allocator.Error!void {
+    try Walk.visitChildren(self, node);
+
+    const operator = infix.operator;
+    switch (operator) {
+        .@"and", .@"or" => return self.foldLogical(node, infix.*),
+        else => {}
+    }
+
+    const lhs = literalOf(infix.lhs) orelse return;
+    const rhs = literalOf(infix.rhs) orelse return;
+
+    const folded = foldLiterals(operator, lhs, rhs) catch |err| switch (err) {
+        // Left unfolded, so the overflow happens when the program runs
+        error.Overflow => {
+            const entry = try self.overflowed.getOrPut(node.id);
+            if (!entry.found_existing) try self.unit.report(.initLint(.overflow, "integer overflow in constant expression", node.span));
+
+            return;
+        }
+    } orelse return;
+
+    node.kind = .{ .literal = folded };
+    self.folded = self.folded + 1;
+}
+
+pub fn visitSizeof(self: *ConstantFolder, node: *Node, annotation: **Node) Allocator.Error!void {
+    const size = knownSize(annotation.*, Target.pointerSizeOf(self.unit.options.target)) orelse return;
+
+    node.kind = .{ .literal = .{ .integer = @intCast(size) } };
+    self.folded = self.folded + 1;
+}
+
+/// The size of the type annotation names if it is known before the program is
+/// checked, which it is for the built in types and references. The sizes of
+/// other types are left to the checker
+pub fn knownSize(annotation: *const Node, pointer_size: u8) ?u64 {
+    const @"type": Type = switch (annotation.kind) {
+        .identifier => |name| Type.fromName(name) orelse return null,
+        .type_expr => |type_expr| if (type_expr == .reference) return pointer_size else return null,
+        else => return null
+    };
+
+    const layout = @"type".layout(pointer_size) orelse return null;
+    return layout.size;
+}
+
+// A constant left operand decides whether the right operand is evaluated, when it
+// doesn't the expression evaluates to the right operand
+fn foldLogical(self: *ConstantFolder, node: *Node, infix: Node.Infix) void {
+    const lhs = literalOf(infix.lhs) orelse return;
+    if (lhs != .boolean) return;
+
+    if (lhs.boolean == (infix.operator == .@"or")) {
+        // The right operand is skipped, but is only dropped when it is also
+        // constant so any mistakes in it are still reported
+        const rhs = literalOf(infix.rhs) orelse return;
+        if (rhs != .boolean) return;
+
+        node.kind = .{ .literal = lhs };
+    } else {
+        // Non boolean operands are left for the type checker to report
+        if (literalOf(infix.rhs)) |rhs| if (rhs != .boolean) return;
+
+        node.kind = infix.rhs.kind;
+    }
+
+    self.folded = self.folded + 1;
+}
+
+fn literalOf(node: *const Node) ?Literal {
+    return switch (node.kind) {
+        .literal => |literal| literal,
+        else => null
+    };
+}
+
+/// The literal operator produces from lhs and rhs, or null if it can't be folded
+pub fn foldLiterals(operator: Operator, lhs: Literal, rhs: Literal) error{Overflow}!?Literal {
+    // Integers mixed with floats are promoted, as the type checker coerces their literals
+    switch (lhs) {
+        .integer => |l| switch (rhs) {
+            .integer => |r| return try foldIntegers(operator, l, r),
+            .float => |r| return foldFloats(operator, @floatFromInt(l), r),
+            else => return null
+        },
+        .float => |l| switch (rhs) {
+            .integer => |r| return foldFloats(operator, l, @floatFromInt(r)),
+            .float => |r| return foldFloats(operator, l, r),
+            else => return null
+        },
+        else => {}
+    }
+
+    // Other literals can only be compared for equality
+    if (std.meta.activeTag(lhs) != std.meta.activeTag(rhs)) return null;
+
+    const equal = switch (lhs) {
+        .boolean => |l| l == rhs.boolean,
+        .character => |l| l == rhs.character,
+        .string => |l| std.mem.eql(u8, l, rhs.string),
+        .unit => true,
+        .integer, .float => unreachable
+    };
+
+    return switch (operator) {
+        .equal => .{ .boolean = equal },
+        .not_equal => .{ .boolean = !equal },
+        else => null
+    };
+}
+
+// Division by zero and out of range shifts aren't folded, so they are reported
+// when the program runs
+fn foldIntegers(operator: Operator, lhs: i64, rhs: i64) error{Overflow}!?Literal {
+    return switch (operator) {
+        .add => .{ .integer = try checked(@addWithOverflow(lhs, rhs)) },
+        .subtract => .{ .integer = try checked(@subWithOverflow(lhs, rhs)) },
+        .multiply => .{ .integer = try checked(@mulWithOverflow(lhs, rhs)) },
+        .divide, .modulo => {
+            if (rhs == 0) return null;
+            if (lhs == std.math.minInt(i64) and rhs == -1) return error.Overflow;
+
+            return .{ .integer = if (operator == .divide) @divTrunc(lhs, rhs) else @rem(lhs, rhs) };
+        },
+        .exponent => .{ .integer = try power(lhs, rhs) orelse return null },
+        .bit_and => .{ .integer = lhs & rhs },
+        .bit_or => .{ .integer = lhs | rhs },
+        .bit_xor => .{ .integer = lhs ^ rhs },
+        .lshift, .rshift => {
+            if (rhs < 0 or rhs >= 64) return null;
+
+            const amount: u6 = @intCast(rhs);
+            return .{ .integer = if (operator == .lshift) try checked(@shlWithOverflow(lhs, amount)) else lhs >> amount };
+        },
+        .lesser => .{ .boolean = lhs < rhs },
+        .lesser_eq => .{ .boolean = lhs <= rhs },
+        .greater => .{ .boolean = lhs > rhs },
+        .greater_eq => .{ .boolean = lhs >= rhs },
+        .equal => .{ .boolean = lhs == rhs },
+        .not_equal => .{ .boolean = lhs != rhs },
+        else => null
+    };
+}
+
+fn checked(result: anytype) error{Overflow}!i64 {
+    if (result[1] != 0) return error.Overflow;
+    return result[0];
+}
+
+// Negative exponents truncate towards zero, matching the interpreter
+fn power(base: i64, exponent: i64) error{Overflow}!?i64 {
+    if (exponent < 0) {
+        if (base == 0) return null;
+        if (base == 1) return 1;
+        if (base == -1) return if (@rem(exponent, 2) == 0) 1 else -1;
+        return 0;
+    }
+
+    return std.math.powi(i64, base, exponent) catch return error.Overflow;
+}
+
+fn foldFloats(operator: Operator, lhs: f64, rhs: f64) ?Literal {
+    return switch (operator) {
+        .add => .{ .float = lhs + rhs },
+        .subtract => .{ .float = lhs - rhs },
+        .multiply => .{ .float = lhs * rhs },
+        .divide => .{ .float = lhs / rhs },
+        .modulo => .{ .float = @rem(lhs, rhs) },
+        .exponent => .{ .float = std.math.pow(f64, lhs, rhs) },
+        .lesser => .{ .boolean = lhs < rhs },
+        .lesser_eq => .{ .boolean = lhs <= rhs },
+        .greater => .{ .boolean = lhs > rhs },
+        .greater_eq => .{ .boolean = lhs >= rhs },
+        .equal => .{ .boolean = lhs == rhs },
+        .not_equal => .{ .boolean = lhs != rhs },
+        else => null
+    };
+}
+
+test "constant folder" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    // Parses and folds the unit's input
+    fn foldSource(unit: *Unit) !*Ast {
+        const ast = try unit.parse();
+        errdefer ast.deinit();
+
+        var folder = try ConstantFolder.init(unit);
+        defer folder.deinit();
+
+        try folder.fold(ast);
+        return ast;
+    }
+
+    fn valueOf(ast: *const Ast, index: usize) *const Node {
+        return ast.root.kind.module.items[index].kind.var_decl.value.?;
+    }
+
+    test "folding respects precedence" {
+        const source =
+            \\let a = 2 + 3 * 4
+            \\let b = 2 * 3 ** 2
+            \\let c = -2 ** 2
+            \\let d = 1 - 2 - 3
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try foldSource(unit);
+        defer ast.deinit();
+
+        try testing.expectEqual(Literal{ .integer = 14 }, valueOf(ast, 0).kind.literal);
+        try testing.expectEqual(Literal{ .integer = 18 }, valueOf(ast, 1).kind.literal);
+        try testing.expectEqual(Literal{ .integer = 4 }, valueOf(ast, 2).kind.prefix.operand.kind.literal);
+        try testing.expectEqual(Literal{ .integer = -4 }, valueOf(ast, 3).kind.literal);
+    }
+
+    test "arithmetic and comparisons" {
+        const source =
+            \\let a = (1 + 2) * 3 < 10
+            \\let b = 7 / 2 + 0.5
+            \\let c = 'a' == 'b'
+            \\let d = x + (2 ** 10)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try foldSource(unit);
+        defer ast.deinit();
+
+        try testing.expectEqual(Literal{ .boolean = true }, valueOf(ast, 0).kind.literal);
+        try testing.expectEqual(Literal{ .float = 3.5 }, valueOf(ast, 1).kind.literal);
+        try testing.expectEqual(Literal{ .boolean = false }, valueOf(ast, 2).kind.literal);
+        try testing.expectEqual(Literal{ .integer = 1024 }, valueOf(ast, 3).kind.infix.rhs.kind.literal);
+    }
+
+    test "short circuiting" {
+        const source =
+            \\let a = true and x
+            \\let b = false or y
+            \\let c = false and x
+            \\let d = true or false
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try foldSource(unit);
+        defer ast.deinit();
+
+        try testing.expectEqualStrings("x", valueOf(ast, 0).kind.identifier);
+        try testing.expectEqualStrings("y", valueOf(ast, 1).kind.identifier);
+        try testing.expect(valueOf(ast, 2).kind == .infix);
+        try testing.expectEqual(Literal{ .boolean = true }, valueOf(ast, 3).kind.literal);
+    }
+
+    test "sizes of built in types" {
+        const source =
+            \\let a = sizeof(i64) == 8
+            \\let b = sizeof(i8) + sizeof(i16) + sizeof(f32) + sizeof(bool)
+            \\let c = sizeof(&Point)
+            \\let d = sizeof(Point)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try foldSource(unit);
+        defer ast.deinit();
+
+        try testing.expectEqual(Literal{ .boolean = true }, valueOf(ast, 0).kind.literal);
+        try testing.expectEqual(Literal{ .integer = 8 }, valueOf(ast, 1).kind.literal);
+        try testing.expectEqual(Literal{ .integer = @sizeOf(usize) }, valueOf(ast, 2).kind.literal);
+        try testing.expect(valueOf(ast, 3).kind == .sizeof);
+    }
+
+    test "overflow is warned about once and not folded" {
+        const source =
+            \\let x = 9223372036854775807 + 1
+            \\let y = 1 + 1
+            \\let z = 1 / 0
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try foldSource(unit);
+        defer ast.deinit();
+
+        try testing.expect(valueOf(ast, 0).kind == .infix);
+        try testing.expectEqual(Literal{ .integer = 2 }, valueOf(ast, 1).kind.literal);
+        try testing.expect(valueOf(ast, 2).kind == .infix);
+
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+        try testing.expectEqual(.warning, unit.diagnostics.items[0].severity);
+    }
+};
@@ -0,0 +1,250 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Removes the statements of blocks which follow an unconditional return, break or
+//! continue, as they can never run. Statements holding a label may be jumped to by
+//! a goto, so they and the statements after them are kept
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+const VisitorMut = ruka.VisitorMut;
+
+unit: *Unit,
+allocator: Allocator,
+
+const DeadCodeEliminator = @This();
+
+const Walk = VisitorMut(DeadCodeEliminator, Allocator.Error, void);
+
+pub fn init(unit: *Unit) !*DeadCodeEliminator {
+    const eliminator = try unit.allocator.create(DeadCodeEliminator);
+
+    eliminator.* = .{
+        .unit = unit,
+        .allocator = unit.allocator
+    };
+
+    return eliminator;
+}
+
+pub fn deinit(self: *DeadCodeEliminator) void {
+    self.allocator.destroy(self);
+}
+
+/// Removes the unreachable statements of every block in ast, warning about each
+pub fn eliminate(self: *DeadCodeEliminator, ast: *Ast) !void {
+    try Walk.visit(self, ast.root);
+}
+
+// Blocks ending in a return, break or continue never produce a value of their
+// own, so truncating them doesn't change their type. Blocks without one keep
+// their final statement, which is their value
+pub fn visitBlock(self: *DeadCodeEliminator, node: *Node, block: *Node.Block) Allocator.Error!void {
+    var kept: usize = 0;
+    var reachable = true;
+    for (block.statements) |statement| {
+        if (!reachable and hasLabel(statement)) reachable = true;
+        if (!reachable) {
+            try self.unit.report(.initLint(.dead_code, "unreachable code", statement.span));
+            continue;
+        }
+
+        block.statements[kept] = statement;
+        kept = kept + 1;
+        switch (statement.kind) {
+            .@"return", .@"break", .@"continue" => reachable = false,
+            else => {}
+        }
+    }
+
+    block.statements = block.statements[0..kept];
+
+    // The removed statements aren't visited, so nothing within them is warned about
+    try Walk.visitChildren(self, node);
+}
+
+// Whether node is or holds a label of the function it is written in
+fn hasLabel(node: *const Node) bool {
+    var search = LabelSearch{ .found = false };
+    LabelSearch.Walk.visit(&search, node) catch unreachable;
+
+    return search.found;
+}
+
+const LabelSearch = struct {
+    found: bool,
+
+    const Walk = Visitor(LabelSearch, error{}, void);
+
+    pub fn visitLabel(self: *LabelSearch, _: *const Node, _: []const u8) error{}!void {
+        self.found = true;
+    }
+
+    // Functions and closures have labels of their own
+    pub fn visitFnDef(_: *LabelSearch, _: *const Node, _: Node.FunctionDef) error{}!void {}
+
+    pub fn visitClosure(_: *LabelSearch, _: *const Node, _: Node.Closure) error{}!void {}
+};
+
+test "dead code eliminator" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const AstBuilder = ruka.AstBuilder;
+
+    fn eliminateIn(unit: *Unit, ast: *Ast) !void {
+        var eliminator = try DeadCodeEliminator.init(unit);
+        defer eliminator.deinit();
+
+        try eliminator.eliminate(ast);
+    }
+
+    test "statements after returns are removed" {
+        const ast = try Ast.init(testing.allocator, "test source");
+        defer ast.deinit();
+
+        const b = AstBuilder.init(ast);
+
+        // fn f() -> i64 { 1; return 2; 3; { return 4; 5 }; 6 }
+        const nested = try b.block(&.{try b.node(.{ .@"return" = try b.integer(4) }), try b.integer(5)});
+        const body = try b.block(&.{
+            try b.integer(1),
+            try b.node(.{ .@"return" = try b.integer(2) }),
+            try b.integer(3),
+            nested,
+            try b.integer(6)
+        });
+        try b.module(&.{try b.function("f", &.{}, try b.identifier("i64"), body)});
+
+        var input = std.io.fixedBufferStream("");
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try eliminateIn(unit, ast);
+
+        try testing.expectEqual(2, body.kind.block.statements.len);
+        try testing.expect(body.kind.block.statements[1].kind == .@"return");
+
+        // The nested block was removed before it was visited
+        try testing.expectEqual(2, nested.kind.block.statements.len);
+
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+        for (unit.diagnostics.items) |diagnostic| {
+            try testing.expectEqual(.warning, diagnostic.severity);
+            try testing.expectEqualStrings("unreachable code", diagnostic.message);
+        }
+    }
+
+    test "statements after breaks are removed" {
+        const ast = try Ast.init(testing.allocator, "test source");
+        defer ast.deinit();
+
+        const b = AstBuilder.init(ast);
+
+        // while true { 1; break; 2; 3 }
+        const body = try b.block(&.{
+            try b.integer(1),
+            try b.node(.{ .@"break" = null }),
+            try b.integer(2),
+            try b.integer(3)
+        });
+        try b.module(&.{try b.node(.{ .@"while" = .{ .condition = try b.boolean(true), .body = body } })});
+
+        var input = std.io.fixedBufferStream("");
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try eliminateIn(unit, ast);
+
+        try testing.expectEqual(2, body.kind.block.statements.len);
+        try testing.expect(body.kind.block.statements[1].kind == .@"break");
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+    }
+
+    test "statements after continues are removed" {
+        const ast = try Ast.init(testing.allocator, "test source");
+        defer ast.deinit();
+
+        const b = AstBuilder.init(ast);
+
+        // while true { if true { continue; 1 }; continue; 2 }
+        const then_block = try b.block(&.{try b.node(.@"continue"), try b.integer(1)});
+        const body = try b.block(&.{
+            try b.conditional(try b.boolean(true), then_block, null),
+            try b.node(.@"continue"),
+            try b.integer(2)
+        });
+        try b.module(&.{try b.node(.{ .@"while" = .{ .condition = try b.boolean(true), .body = body } })});
+
+        var input = std.io.fixedBufferStream("");
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try eliminateIn(unit, ast);
+
+        // A continue within a conditional only ends its own block
+        try testing.expectEqual(2, body.kind.block.statements.len);
+        try testing.expect(body.kind.block.statements[1].kind == .@"continue");
+        try testing.expectEqual(1, then_block.kind.block.statements.len);
+        try testing.expectEqual(2, unit.diagnostics.items.len);
+    }
+
+    test "statements from a label after a return are kept" {
+        const ast = try Ast.init(testing.allocator, "test source");
+        defer ast.deinit();
+
+        const b = AstBuilder.init(ast);
+
+        // fn f() { return; 1; { again: }; 2 }
+        const labelled = try b.block(&.{try b.node(.{ .label = "again" })});
+        const body = try b.block(&.{
+            try b.node(.{ .@"return" = null }),
+            try b.integer(1),
+            labelled,
+            try b.integer(2)
+        });
+        try b.module(&.{try b.function("f", &.{}, null, body)});
+
+        var input = std.io.fixedBufferStream("");
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try eliminateIn(unit, ast);
+
+        try testing.expectEqual(3, body.kind.block.statements.len);
+        try testing.expect(body.kind.block.statements[1] == labelled);
+        try testing.expectEqual(1, unit.diagnostics.items.len);
+    }
+
+    test "blocks without returns keep their final statement" {
+        const ast = try Ast.init(testing.allocator, "test source");
+        defer ast.deinit();
+
+        const b = AstBuilder.init(ast);
+
+        const body = try b.block(&.{try b.integer(1), try b.integer(2)});
+        try b.module(&.{body});
+
+        var input = std.io.fixedBufferStream("");
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try eliminateIn(unit, ast);
+
+        try testing.expectEqual(2, body.kind.block.statements.len);
+        try testing.expectEqual(0, unit.diagnostics.items.len);
+    }
+};
@@ -3,157 +3,2304 @@
 
 const std = @import("std");
 const Allocator = std.mem.Allocator;
+const AnyWriter = std.io.AnyWriter;
+const ArenaAllocator = std.heap.ArenaAllocator;
 const ArrayList = std.ArrayList;
 
 const ruka = @import("../prelude.zig");
-const Position = ruka.Position;
+const Span = ruka.Span;
 const Token = ruka.Token;
+const Visitor = ruka.Visitor;
+const VisitorMut = ruka.VisitorMut;
 
-root: ?*Node,
+/// The module representing the whole file
+root: *Node,
+file: []const u8,
+/// The number of nodes created, and so the id of the next one
+node_count: Node.Id,
+/// The asts whose nodes were moved into this one, they own the memory of those nodes
+adopted: ArrayList(*Ast),
 
+arena: ArenaAllocator,
 allocator: Allocator,
 
 const Ast = @This();
 
+/// Represents a single construct of the program and the span of the source it covers,
+/// all nodes and the memory they reference are owned by the ast's arena
 pub const Node = struct {
+    id: Id,
     kind: Kind,
-    token: Token,
-    lhs: ?*Node,
-    rhs: ?*Node,
+    span: Span,
+    /// The attributes written before the node, only declarations have any
+    attributes: []const Attribute = &.{},
 
-    allocator: Allocator,
+    /// Unique within an ast, used to key information about nodes stored outside of it
+    pub const Id = u32;
 
-    pub const Kind = enum {
-        unit,
-        identifier,
-        integer,
-        float,
-        boolean,
-        string,
-        block,
-        @"if",
-        match,
-        fn_def,
-        closure,
-        fn_call,
-        meth_call,
-        prefix,
-        infix,
-        postfix,
-        binding,
-        @"type",
-        interpet,
-        @"return"
+    /// `#[allow(dead_code, shadowing)]`, a name and the names it is given
+    pub const Attribute = struct {
+        name: []const u8,
+        args: []const []const u8,
+        span: Span,
+
+        pub fn format(self: Attribute, comptime _: []const u8, _: std.fmt.FormatOptions, writer: anytype) !void {
+            try writer.print("#[{s}", .{self.name});
+            if (self.args.len > 0) {
+                try writer.writeByte('(');
+                for (self.args, 0..) |arg, i| {
+                    if (i > 0) try writer.writeAll(", ");
+                    try writer.writeAll(arg);
+                }
+                try writer.writeByte(')');
+            }
+            try writer.writeByte(']');
+        }
+    };
+
+    pub const Kind = union(enum) {
+        // Declarations
+        module: Module,
+        fn_def: FunctionDef,
+        extern_fn: ExternFn,    // extern fn puts(s: str) -> i32
+        struct_def: StructDef,
+        union_def: UnionDef,
+        enum_def: EnumDef,
+        impl: Impl,
+        trait_def: TraitDef,
+        var_decl: Binding,      // let, var
+        const_decl: Binding,    // const
+        destructure: Destructure, // let (a, b) = pair
+        import: Import,
+        @"export": *Node,       // pub, wrapping the exported declaration
+        // Statements and control flow
+        block: Block,
+        @"if": If,
+        @"while": While,
+        @"for": For,
+        match: Match,
+        @"return": ?*Node,
+        @"break": ?*Node,
+        @"continue",
+        @"defer": *Node,        // defer close(file), run as its block is left
+        @"unsafe": *Node,       // unsafe { free(p) }, where extern functions can be called
+        @"comptime": *Node,     // comptime { sizeof(i64) }, replaced by its value once checked
+        label: []const u8,      // retry:, which gotos within its function jump to
+        goto: []const u8,       // goto retry
+        assignment: Assignment,
+        // Expressions
+        prefix: Prefix,
+        infix: Infix,
+        fn_call: Call,
+        struct_literal: StructLiteral,
+        variant_literal: VariantLiteral,
+        field_access: FieldAccess,
+        array_literal: []*Node, // [1, 2, 3]
+        index: Index,           // a[i], a[i..j]
+        tuple_literal: []*Node, // (1, "a"), (1,)
+        tuple_index: TupleIndex, // t.0
+        @"try": *Node,          // value?, returning the value's error
+        reference: *Node,       // &x
+        dereference: *Node,     // *r
+        @"await": *Node,        // await fetch(), only within async functions
+        sizeof: *Node,          // sizeof(i32), the size in bytes of the type
+        cast: Cast,             // x as i32
+        closure: Closure,       // fn(x: i64) -> i64 { x + n }
+        identifier: []const u8,
+        literal: Literal,
+        // Types
+        type_expr: TypeExpr,
     };
 
-    pub fn init(allocator: Allocator, kind: Kind, token: Token) !*Node {
-        const node = try allocator.create(Node);
+    /// Formats the node and its children as an indented tree for debugging
+    pub fn format(self: *const Node, comptime _: []const u8, _: std.fmt.FormatOptions, writer: anytype) !void {
+        try writeTree(writer, self, 0);
+    }
 
-        node.* = .{
-            .kind = kind,
-            .token = token,
-            .lhs = null,
-            .rhs = null,
-            .allocator = allocator
+    /// The declaration node exports, or node itself if it isn't an export
+    pub fn unwrapExport(self: *Node) *Node {
+        return switch (self.kind) {
+            .@"export" => |declaration| declaration,
+            else => self
         };
+    }
 
-        return node;
+    pub fn hasAttribute(self: *const Node, name: []const u8) bool {
+        for (self.attributes) |attribute| if (std.mem.eql(u8, attribute.name, name)) return true;
+        return false;
     }
 
-    pub fn deinit(self: ?*Node) void {
-        if (self) |node| {
-            defer node.allocator.destroy(node);
+    pub const Module = struct {
+        name: []const u8,
+        items: []*Node
+    };
+
+    pub const Import = struct {
+        /// The segments of the dotted path, naming a file relative to the importing one
+        path: []const []const u8,
+        alias: ?[]const u8
+    };
+
+    pub const FunctionDef = struct {
+        name: []const u8,
+        /// The names of the types the function is generic over, `T` in `fn id<T>(x: T)`
+        type_params: []const []const u8 = &.{},
+        params: []Parameter,
+        return_type: ?*Node,
+        body: *Node,
+        /// Written `async fn`, calls to it return a Future of its return type which
+        /// runs the body once awaited
+        @"async": bool = false
+    };
+
+    pub const Parameter = struct {
+        name: []const u8,
+        annotation: ?*Node,
+        span: Span,
+        /// Written `self` or `&self` as the first parameter of a function in an impl,
+        /// annotated with the struct or a reference to it
+        shorthand: bool = false
+    };
+
+    /// Declares a function defined in C, whose parameters are all annotated. Variadic
+    /// functions take any number of arguments after their parameters, `...`
+    pub const ExternFn = struct {
+        name: []const u8,
+        params: []Parameter,
+        variadic: bool,
+        return_type: ?*Node
+    };
+
+    pub const StructDef = struct {
+        name: []const u8,
+        /// The names of the types the struct is generic over, `A` and `B` in `struct Pair<A, B>`
+        type_params: []const []const u8 = &.{},
+        fields: []Field,
+
+        pub const Field = struct {
+            name: []const u8,
+            annotation: *Node,
+            span: Span
+        };
+    };
+
+    /// A type whose fields overlay the same memory, so only the field last written
+    /// holds a value, `union Bits { i: i32, f: f32 }`
+    pub const UnionDef = struct {
+        name: []const u8,
+        fields: []StructDef.Field
+    };
+
+    /// A type whose values are one of its variants, each of which holds either no
+    /// values, values in order, `Circle(f64)`, or named fields, `KeyPress { key: u8 }`
+    pub const EnumDef = struct {
+        name: []const u8,
+        variants: []Variant,
+
+        pub const Variant = struct {
+            name: []const u8,
+            fields: Fields,
+            span: Span,
+
+            pub const Fields = union(enum) {
+                unit,
+                /// The annotations of the values
+                tuple: []*Node,
+                @"struct": []StructDef.Field
+            };
+        };
+    };
+
+    /// The functions defined for a struct, those whose first parameter is the struct
+    /// are methods called on its values, the others are called through its name
+    pub const Impl = struct {
+        name: []const u8,
+        functions: []*Node,
+        /// The trait whose methods the functions implement, `Printable` in
+        /// `impl Printable for Point`
+        trait: ?[]const u8 = null
+    };
+
+    /// The methods a type must define to implement a trait, those with a default
+    /// body are defined for the implementations which don't define them
+    pub const TraitDef = struct {
+        name: []const u8,
+        methods: []Method,
+
+        pub const Method = struct {
+            name: []const u8,
+            params: []Parameter,
+            return_type: ?*Node,
+            /// The fn_def defined for implementations which don't define the method,
+            /// null if each must
+            default: ?*Node,
+            span: Span
+        };
+    };
+
+    pub const Binding = struct {
+        name: []const u8,
+        mutable: bool,
+        annotation: ?*Node,
+        value: ?*Node
+    };
+
+    /// A let binding the names of an irrefutable pattern to the parts of its value
+    pub const Destructure = struct {
+        pattern: Pattern,
+        annotation: ?*Node,
+        value: *Node
+    };
+
+    pub const Block = struct {
+        statements: []*Node
+    };
 
-            node.token.deinit();
+    pub const If = struct {
+        condition: *Node,
+        then_block: *Node,
+        else_block: ?*Node
+    };
+
+    pub const While = struct {
+        condition: *Node,
+        body: *Node
+    };
+
+    pub const For = struct {
+        variable: []const u8,
+        iterable: *Node,
+        body: *Node
+    };
+
+    /// Evaluates the body of the first arm whose pattern matches the subject and
+    /// whose guard, if it has one, is true
+    pub const Match = struct {
+        subject: *Node,
+        arms: []Arm,
 
-            Node.deinit(node.lhs);
-            Node.deinit(node.rhs);
+        pub const Arm = struct {
+            pattern: Pattern,
+            guard: ?*Node,
+            body: *Node
+        };
+    };
+
+    /// Describes the shape of the values a match arm accepts, binding the parts
+    /// of the value named by identifiers
+    pub const Pattern = struct {
+        kind: Kind,
+        span: Span,
+
+        pub const Kind = union(enum) {
+            literal: Literal,
+            identifier: []const u8,
+            wildcard,                   // _
+            @"struct": StructPattern,   // Point { x: 0, y }
+            variant: VariantPattern,    // Shape::Circle(r)
+            tuple: []Pattern,           // (a, b)
+            @"or": []Pattern,           // 1 | 2
+        };
+
+        pub const StructPattern = struct {
+            name: []const u8,
+            fields: []Field,
+
+            /// Fields without a pattern, `y` in `Point { y }`, bind the field to its name
+            pub const Field = struct {
+                name: []const u8,
+                pattern: Pattern
+            };
+        };
+
+        pub const VariantPattern = struct {
+            /// The segments of the path naming the variant, the enum is left out when
+            /// it is the only segment
+            path: []const []const u8,
+            fields: Fields,
+
+            pub const Fields = union(enum) {
+                unit,
+                tuple: []Pattern,
+                @"struct": []StructPattern.Field
+            };
+        };
+
+        /// Whether the pattern binds any names
+        pub fn binds(self: Pattern) bool {
+            return switch (self.kind) {
+                .identifier => true,
+                .literal, .wildcard => false,
+                .@"struct" => |pattern| anyFieldBinds(pattern.fields),
+                .variant => |pattern| switch (pattern.fields) {
+                    .unit => false,
+                    .tuple => |fields| anyBinds(fields),
+                    .@"struct" => |fields| anyFieldBinds(fields)
+                },
+                .tuple, .@"or" => |patterns| anyBinds(patterns)
+            };
         }
-    }
 
-    pub fn addLeft(self: *Node, kind: Kind, token: Token) !*Node {
-        const node = try Node.init(self.allocator, kind, token);
+        /// Whether the pattern matches every value of the type it matches, variants
+        /// are refutable as their enum can have others
+        pub fn isIrrefutable(self: Pattern) bool {
+            return switch (self.kind) {
+                .identifier, .wildcard => true,
+                .literal => |literal| literal == .unit,
+                .@"struct" => |pattern| for (pattern.fields) |field| {
+                    if (!field.pattern.isIrrefutable()) break false;
+                } else true,
+                .variant => false,
+                .tuple => |elements| for (elements) |element| {
+                    if (!element.isIrrefutable()) break false;
+                } else true,
+                .@"or" => |alternatives| for (alternatives) |alternative| {
+                    if (alternative.isIrrefutable()) break true;
+                } else false
+            };
+        }
 
-        self.lhs = node;
+        fn anyBinds(patterns: []const Pattern) bool {
+            for (patterns) |pattern| if (pattern.binds()) return true;
+            return false;
+        }
 
-        return node;
-    }
+        fn anyFieldBinds(fields: []const StructPattern.Field) bool {
+            for (fields) |field| if (field.pattern.binds()) return true;
+            return false;
+        }
+    };
+
+    pub const Assignment = struct {
+        lhs: *Node,
+        rhs: *Node
+    };
+
+    pub const Prefix = struct {
+        operator: Operator,
+        operand: *Node,
+
+        pub const Operator = enum {
+            negate,     // -
+            not,        // !, not
+            bit_not,    // ~
+
+            pub fn fromKind(kind: Token.Kind) ?Operator {
+                return switch (kind) {
+                    .minus => .negate,
+                    .bang => .not,
+                    .tilde => .bit_not,
+                    .keyword => |keyword| if (keyword == .not) .not else null,
+                    else => null
+                };
+            }
 
-    pub fn addRight(self: *Node, kind: Kind, token: Token) !*Node {
-        const node = try Node.init(self.allocator, kind, token);
+            pub fn toStr(self: Operator) []const u8 {
+                return switch (self) {
+                    .negate  => "-",
+                    .not     => "!",
+                    .bit_not => "~"
+                };
+            }
+        };
+    };
+
+    pub const Infix = struct {
+        operator: Operator,
+        lhs: *Node,
+        rhs: *Node,
 
-        self.rhs = node;
+        pub const Operator = enum {
+            // Arithmetic
+            add,            // +
+            subtract,       // -
+            multiply,       // *
+            divide,         // /
+            modulo,         // %
+            exponent,       // **
+            // Bitwise
+            bit_and,        // &
+            bit_or,         // |
+            bit_xor,        // ^
+            lshift,         // <<
+            rshift,         // >>
+            // Comparators
+            lesser,         // <
+            lesser_eq,      // <=
+            greater,        // >
+            greater_eq,     // >=
+            equal,          // ==
+            not_equal,      // !=
+            // Logical
+            @"and",         // and, &&
+            @"or",          // or, ||
+            // Miscelaneous
+            concat,         // <>
+            range_exc,      // ..
+            range_inc,      // ..=
+            forward_app,    // <|
+            reverse_app,    // |>
+
+            pub fn fromKind(kind: Token.Kind) ?Operator {
+                return switch (kind) {
+                    .plus        => .add,
+                    .minus       => .subtract,
+                    .asterisk    => .multiply,
+                    .slash       => .divide,
+                    .percent     => .modulo,
+                    .square      => .exponent,
+                    .ampersand   => .bit_and,
+                    .pipe        => .bit_or,
+                    .caret       => .bit_xor,
+                    .lshift      => .lshift,
+                    .rshift      => .rshift,
+                    .lesser      => .lesser,
+                    .lesser_eq   => .lesser_eq,
+                    .greater     => .greater,
+                    .greater_eq  => .greater_eq,
+                    .equal       => .equal,
+                    .not_equal   => .not_equal,
+                    .logical_and => .@"and",
+                    .logical_or  => .@"or",
+                    .concat      => .concat,
+                    .range_exc   => .range_exc,
+                    .range_inc   => .range_inc,
+                    .forward_app => .forward_app,
+                    .reverse_app => .reverse_app,
+                    .keyword => |keyword| switch (keyword) {
+                        .@"and" => .@"and",
+                        .@"or"  => .@"or",
+                        else => null
+                    },
+                    else => null
+                };
+            }
+
+            pub fn toStr(self: Operator) []const u8 {
+                return switch (self) {
+                    .add         => "+",
+                    .subtract    => "-",
+                    .multiply    => "*",
+                    .divide      => "/",
+                    .modulo      => "%",
+                    .exponent    => "**",
+                    .bit_and     => "&",
+                    .bit_or      => "|",
+                    .bit_xor     => "^",
+                    .lshift      => "<<",
+                    .rshift      => ">>",
+                    .lesser      => "<",
+                    .lesser_eq   => "<=",
+                    .greater     => ">",
+                    .greater_eq  => ">=",
+                    .equal       => "==",
+                    .not_equal   => "!=",
+                    .@"and"      => "and",
+                    .@"or"       => "or",
+                    .concat      => "<>",
+                    .range_exc   => "..",
+                    .range_inc   => "..=",
+                    .forward_app => "<|",
+                    .reverse_app => "|>"
+                };
+            }
 
-        return node;
+            pub fn precedence(self: Operator) Precedence {
+                return switch (self) {
+                    .forward_app, .reverse_app => .pipe,
+                    .range_exc, .range_inc => .range,
+                    .@"or" => .@"or",
+                    .@"and" => .@"and",
+                    .bit_or => .bit_or,
+                    .bit_xor => .bit_xor,
+                    .bit_and => .bit_and,
+                    .equal, .not_equal => .equality,
+                    .lesser, .lesser_eq, .greater, .greater_eq => .relational,
+                    .lshift, .rshift => .shift,
+                    .add, .subtract, .concat => .additive,
+                    .multiply, .divide, .modulo => .multiplicative,
+                    .exponent => .exponent
+                };
+            }
+
+            /// Which operand a chain of operators with the same precedence groups
+            /// towards, non associative operators can't be chained at all
+            pub fn associativity(self: Operator) Associativity {
+                return switch (self) {
+                    .exponent, .forward_app => .right,
+                    .lesser, .lesser_eq, .greater, .greater_eq, .range_exc, .range_inc => .none,
+                    else => .left
+                };
+            }
+        };
+    };
+
+    /// How tightly each kind of expression binds its operands, from loosest to tightest
+    pub const Precedence = enum {
+        assignment,     // =
+        pipe,           // |>, <|
+        range,          // .., ..=
+        @"or",          // or, ||
+        @"and",         // and, &&
+        bit_or,         // |
+        bit_xor,        // ^
+        bit_and,        // &
+        equality,       // ==, !=
+        relational,     // <, <=, >, >=
+        shift,          // <<, >>
+        additive,       // +, -, <>
+        multiplicative, // *, /, %
+        cast,           // as
+        prefix,         // -, !, ~, not
+        exponent,       // **
+        /// Literals, identifiers, calls and parenthesized expressions
+        primary,
+
+        /// The precedence binding one step tighter, primaries are the tightest
+        pub fn next(self: Precedence) Precedence {
+            return if (self == .primary) .primary else @enumFromInt(@intFromEnum(self) + 1);
+        }
+
+        pub fn lessThan(self: Precedence, other: Precedence) bool {
+            return @intFromEnum(self) < @intFromEnum(other);
+        }
+    };
+
+    pub const Associativity = enum {
+        left,
+        right,
+        none
+    };
+
+    /// The precedence of the outermost construct of the node
+    pub fn precedence(self: *const Node) Precedence {
+        return switch (self.kind) {
+            .assignment => .assignment,
+            .prefix, .reference, .dereference, .@"await" => .prefix,
+            .cast => .cast,
+            .infix => |infix| infix.operator.precedence(),
+            else => .primary
+        };
     }
+
+    /// Whether the node has a struct literal outside of any delimiters, whose brace
+    /// would be read as beginning the block following a condition it is written in
+    pub fn hasBareStructLiteral(self: *const Node) bool {
+        return switch (self.kind) {
+            .struct_literal => true,
+            .variant_literal => |literal| literal.fields == .@"struct",
+            .assignment => |assignment| assignment.lhs.hasBareStructLiteral() or assignment.rhs.hasBareStructLiteral(),
+            .prefix => |prefix| prefix.operand.hasBareStructLiteral(),
+            .infix => |infix| infix.lhs.hasBareStructLiteral() or infix.rhs.hasBareStructLiteral(),
+            .fn_call => |call| call.callee.hasBareStructLiteral(),
+            .field_access => |access| access.object.hasBareStructLiteral(),
+            .index => |index| index.object.hasBareStructLiteral(),
+            .tuple_index => |access| access.object.hasBareStructLiteral(),
+            .cast => |cast| cast.value.hasBareStructLiteral(),
+            .@"try", .reference, .dereference, .@"await" => |operand| operand.hasBareStructLiteral(),
+            else => false
+        };
+    }
+
+    pub const Call = struct {
+        callee: *Node,
+        arguments: []*Node
+    };
+
+    /// An anonymous function, which holds the values of the bindings of enclosing
+    /// functions its body refers to as they were when it was evaluated
+    pub const Closure = struct {
+        params: []Parameter,
+        /// Closures without a return type return the type of their body
+        return_type: ?*Node,
+        body: *Node,
+        /// The names of the bindings the body captures, in the order it first refers
+        /// to them. Found once the whole ast is parsed, see CaptureAnalyzer
+        captures: []const []const u8 = &.{}
+    };
+
+    pub const StructLiteral = struct {
+        name: []const u8,
+        fields: []Field,
+
+        pub const Field = struct {
+            name: []const u8,
+            value: *Node,
+            span: Span
+        };
+    };
+
+    /// Constructs a value of an enum, `Shape::Circle(1.0)`, giving the variant's
+    /// fields in the style it declares them
+    pub const VariantLiteral = struct {
+        @"enum": []const u8,
+        variant: []const u8,
+        fields: Fields,
+        /// The variants of the prelude's enums are written by their name alone, `Ok(x)`
+        qualified: bool = true,
+
+        /// The enums of the variants written by their name alone, keyed by variant
+        pub const prelude = std.StaticStringMap([]const u8).initComptime(.{
+            .{"Ok", "Result"},
+            .{"Err", "Result"},
+            .{"Some", "Option"},
+            .{"None", "Option"}
+        });
+
+        pub const Fields = union(enum) {
+            unit,
+            tuple: []*Node,
+            @"struct": []StructLiteral.Field
+        };
+    };
+
+    pub const FieldAccess = struct {
+        object: *Node,
+        field: []const u8
+    };
+
+    /// Indexing with a range, `a[i..j]`, slices the elements it covers
+    pub const Index = struct {
+        object: *Node,
+        index: *Node
+    };
+
+    /// Accesses the element of a tuple at a position written as an integer, `t.0`
+    pub const TupleIndex = struct {
+        object: *Node,
+        index: usize
+    };
+
+    /// Converts value to the type target, `x as i32`
+    pub const Cast = struct {
+        value: *Node,
+        target: *Node
+    };
+
+    /// A type written in an annotation, other than the names of types, which are
+    /// identifiers, and unit, which is the unit literal
+    pub const TypeExpr = union(enum) {
+        generic: Generic,           // Option<T>
+        array: Array,               // [T; N]
+        slice: *Node,               // [T]
+        tuple: []*Node,             // (T, U), (T,)
+        function: FunctionType,     // fn(T) -> U
+        reference: *Node,           // &T
+        impl: []const u8,           // impl Printable, any type implementing the trait
+        variadic,                   // ..., the last parameter of a function type
+
+        pub const Generic = struct {
+            name: []const u8,
+            arguments: []*Node
+        };
+
+        pub const Array = struct {
+            element: *Node,
+            len: *Node
+        };
+
+        /// Function types without a return type return unit, as functions do
+        pub const FunctionType = struct {
+            params: []*Node,
+            return_type: ?*Node
+        };
+    };
+
+    pub const Literal = union(enum) {
+        integer: i64,
+        float: f64,
+        boolean: bool,
+        string: []const u8,
+        character: u8,
+        unit
+    };
 };
 
-pub fn init(allocator: Allocator) !*Ast {
+/// Creates an ast for file, whose root is an empty module
+pub fn init(allocator: Allocator, file: []const u8) !*Ast {
     const ast = try allocator.create(Ast);
+    errdefer allocator.destroy(ast);
+
     ast.* = .{
-        .allocator = allocator,
-        .root = null
+        .root = undefined,
+        .file = undefined,
+        .node_count = 0,
+        .adopted = .init(allocator),
+        .arena = .init(allocator),
+        .allocator = allocator
     };
+    errdefer ast.arena.deinit();
+
+    ast.file = try ast.arena.allocator().dupe(u8, file);
+    ast.root = try ast.createNode(.{ .module = .{
+        .name = ast.file,
+        .items = &.{}
+    }}, .{ .file = ast.file });
 
     return ast;
 }
 
 pub fn deinit(self: *Ast) void {
-    Node.deinit(self.root);
-
+    for (self.adopted.items) |ast| ast.deinit();
+    self.adopted.deinit();
+    self.arena.deinit();
     self.allocator.destroy(self);
 }
 
-pub fn initRoot(self: *Ast, node_kind: Node.Kind, token: Token) !*Node {
-    const root = try Node.init(
-        self.allocator,
-        node_kind,
-        token
-    );
+/// Creates a node owned by the ast
+pub fn createNode(self: *Ast, kind: Node.Kind, span: Span) !*Node {
+    const node = try self.arena.allocator().create(Node);
+    node.* = .{
+        .id = self.node_count,
+        .kind = kind,
+        .span = span
+    };
+    self.node_count = self.node_count + 1;
+
+    return node;
+}
+
+/// Takes ownership of other, moving the items of its module to the end of this
+/// ast's module. Other's nodes are renumbered so ids stay unique within this ast
+pub fn adopt(self: *Ast, other: *Ast) !void {
+    try self.adopted.ensureUnusedCapacity(1);
+
+    const module = &self.root.kind.module;
+    const items = other.root.kind.module.items;
+    module.items = try std.mem.concat(self.arena.allocator(), *Node, &.{module.items, items});
+
+    var renumbering: Renumbering = .{ .ast = self };
+    for (items) |item| try Renumbering.Walk.visit(&renumbering, item);
+
+    self.adopted.appendAssumeCapacity(other);
+}
+
+// Gives every node visited the next id of ast
+const Renumbering = struct {
+    ast: *Ast,
+
+    const Walk = VisitorMut(Renumbering, error{}, void);
+
+    pub fn visitNode(self: *Renumbering, node: *Node) error{}!void {
+        node.id = self.ast.node_count;
+        self.ast.node_count = self.ast.node_count + 1;
+
+        try Walk.dispatch(self, node);
+    }
+};
+
+/// The innermost node whose span contains the byte at offset in file, null if
+/// none does. Asts which adopted others hold the nodes of more than one file
+pub fn nodeAt(self: *const Ast, file: []const u8, offset: usize) ?*const Node {
+    var finder: NodeFinder = .{ .file = file, .offset = offset };
+    NodeFinder.Walk.visit(&finder, self.root) catch |err| switch (err) {};
+
+    return finder.found;
+}
+
+// Records the last node visited containing offset, as children are visited after
+// their parents and siblings don't overlap, it is the innermost
+const NodeFinder = struct {
+    file: []const u8,
+    offset: usize,
+    found: ?*const Node = null,
+
+    const Walk = Visitor(NodeFinder, error{}, void);
+
+    pub fn visitNode(self: *NodeFinder, node: *const Node) error{}!void {
+        const span = node.span;
+        if (span.start <= self.offset and self.offset < span.end and std.mem.eql(u8, span.file, self.file)) {
+            self.found = node;
+        }
+
+        try Walk.dispatch(self, node);
+    }
+};
+
+/// Copies items into memory owned by the ast
+pub fn dupe(self: *Ast, comptime T: type, items: []const T) ![]T {
+    return try self.arena.allocator().dupe(T, items);
+}
+
+/// Writes the ast back out as source
+pub fn write(self: *Ast, writer: AnyWriter) !void {
+    try writeNode(writer, self.root, 0);
+}
+
+/// Writes a node of an ast back out as source
+pub fn writeSource(node: *const Node, writer: AnyWriter) !void {
+    try writeNode(writer, node, 0);
+}
+
+/// Formats the ast as an indented tree for debugging
+pub fn format(self: Ast, comptime _: []const u8, _: std.fmt.FormatOptions, writer: anytype) !void {
+    try writeTree(writer, self.root, 0);
+}
+
+/// Writes the ast as json, each node an object holding its kind, id, span and the
+/// fields of its kind
+pub fn writeJson(self: *const Ast, writer: anytype) @TypeOf(writer).Error!void {
+    try writeJsonNode(writer, self.root);
+    try writer.writeByte('\n');
+}
+
+/// Writes the ast as json like writeJson, indented for people to read
+pub fn writeJsonIndented(self: *const Ast, allocator: Allocator, writer: anytype) !void {
+    var compact = ArrayList(u8).init(allocator);
+    defer compact.deinit();
+
+    try self.writeJson(compact.writer());
+    try indentJson(writer, compact.items);
+}
+
+/// Reads an ast back from the json writeJson or writeJsonIndented wrote
+pub fn readJson(allocator: Allocator, json: []const u8) !*Ast {
+    return try ruka.Parser.AstReader.read(allocator, json);
+}
+
+// Indents compact json by four spaces a level, empty arrays and objects stay on one line
+fn indentJson(writer: anytype, json: []const u8) @TypeOf(writer).Error!void {
+    var depth: usize = 0;
+    var in_string = false;
+
+    var i: usize = 0;
+    while (i < json.len) : (i = i + 1) {
+        const byte = json[i];
+        if (in_string) {
+            try writer.writeByte(byte);
+            if (byte == '\\' and i + 1 < json.len) {
+                i = i + 1;
+                try writer.writeByte(json[i]);
+            } else if (byte == '"') {
+                in_string = false;
+            }
+            continue;
+        }
+
+        switch (byte) {
+            '"' => {
+                in_string = true;
+                try writer.writeByte(byte);
+            },
+            '{', '[' => {
+                try writer.writeByte(byte);
+                if (i + 1 < json.len and (json[i + 1] == '}' or json[i + 1] == ']')) {
+                    i = i + 1;
+                    try writer.writeByte(json[i]);
+                    continue;
+                }
+
+                depth = depth + 1;
+                try writer.writeByte('\n');
+                try writer.writeByteNTimes(' ', depth * 4);
+            },
+            '}', ']' => {
+                depth = depth -| 1;
+                try writer.writeByte('\n');
+                try writer.writeByteNTimes(' ', depth * 4);
+                try writer.writeByte(byte);
+            },
+            ',' => {
+                try writer.writeByte(byte);
+                try writer.writeByte('\n');
+                try writer.writeByteNTimes(' ', depth * 4);
+            },
+            ':' => try writer.writeAll(": "),
+            else => try writer.writeByte(byte)
+        }
+    }
+}
+
+fn writeIndent(writer: AnyWriter, depth: usize) !void {
+    try writer.writeByteNTimes(' ', depth * 4);
+}
+
+// Floats always include a decimal point so they are read back as floats
+fn writeFloat(writer: anytype, float: f64) @TypeOf(writer).Error!void {
+    // Large enough for the longest decimal representation of an f64
+    var buf: [512]u8 = undefined;
+    const str = std.fmt.bufPrint(&buf, "{d}", .{float}) catch unreachable;
+    try writer.writeAll(str);
+
+    if (std.mem.indexOfAny(u8, str, ".ein") == null) try writer.writeAll(".0");
+}
+
+fn writeNode(writer: AnyWriter, node: *const Node, depth: usize) !void {
+    for (node.attributes) |attribute| {
+        try writer.print("{}\n", .{attribute});
+        try writeIndent(writer, depth);
+    }
+
+    switch (node.kind) {
+        .module => |module| {
+            for (module.items) |item| {
+                try writeIndent(writer, depth);
+                try writeNode(writer, item, depth);
+                try writer.writeByte('\n');
+            }
+        },
+        .fn_def => |fn_def| {
+            try writer.print("{s}fn {s}", .{if (fn_def.@"async") "async " else "", fn_def.name});
+            try writeTypeParams(writer, fn_def.type_params);
+            try writeSignature(writer, fn_def.params, fn_def.return_type, depth);
+            try writeNode(writer, fn_def.body, depth);
+        },
+        .extern_fn => |extern_fn| {
+            try writer.print("extern fn {s}(", .{extern_fn.name});
+            for (extern_fn.params, 0..) |param, i| {
+                if (i > 0) try writer.writeAll(", ");
+                try writer.print("{s}: ", .{param.name});
+                try writeNode(writer, param.annotation.?, depth);
+            }
+            if (extern_fn.variadic) try writer.writeAll(if (extern_fn.params.len > 0) ", ..." else "...");
+            try writer.writeByte(')');
+            if (extern_fn.return_type) |return_type| {
+                try writer.writeAll(" -> ");
+                try writeNode(writer, return_type, depth);
+            }
+        },
+        .struct_def => |struct_def| {
+            try writer.print("struct {s}", .{struct_def.name});
+            try writeTypeParams(writer, struct_def.type_params);
+            try writer.writeAll(" {\n");
+            for (struct_def.fields) |field| {
+                try writeIndent(writer, depth + 1);
+                try writer.print("{s}: ", .{field.name});
+                try writeNode(writer, field.annotation, depth + 1);
+                try writer.writeByte('\n');
+            }
+            try writeIndent(writer, depth);
+            try writer.writeByte('}');
+        },
+        .union_def => |union_def| {
+            try writer.print("union {s} {{\n", .{union_def.name});
+            for (union_def.fields) |field| {
+                try writeIndent(writer, depth + 1);
+                try writer.print("{s}: ", .{field.name});
+                try writeNode(writer, field.annotation, depth + 1);
+                try writer.writeByte('\n');
+            }
+            try writeIndent(writer, depth);
+            try writer.writeByte('}');
+        },
+        .enum_def => |enum_def| {
+            try writer.print("enum {s} {{\n", .{enum_def.name});
+            for (enum_def.variants) |variant| {
+                try writeIndent(writer, depth + 1);
+                try writer.writeAll(variant.name);
+                switch (variant.fields) {
+                    .unit => {},
+                    .tuple => |annotations| {
+                        try writer.writeByte('(');
+                        for (annotations, 0..) |annotation, i| {
+                            if (i > 0) try writer.writeAll(", ");
+                            try writeNode(writer, annotation, depth + 1);
+                        }
+                        try writer.writeByte(')');
+                    },
+                    .@"struct" => |fields| {
+                        try writer.writeAll(" {");
+                        for (fields, 0..) |field, i| {
+                            try writer.writeAll(if (i > 0) ", " else " ");
+                            try writer.print("{s}: ", .{field.name});
+                            try writeNode(writer, field.annotation, depth + 1);
+                        }
+                        try writer.writeAll(if (fields.len > 0) " }" else "}");
+                    }
+                }
+                try writer.writeByte('\n');
+            }
+            try writeIndent(writer, depth);
+            try writer.writeByte('}');
+        },
+        .impl => |impl| {
+            if (impl.trait) |trait| {
+                try writer.print("impl {s} for {s} {{\n", .{trait, impl.name});
+            } else {
+                try writer.print("impl {s} {{\n", .{impl.name});
+            }
+            for (impl.functions) |function| {
+                try writeIndent(writer, depth + 1);
+                try writeNode(writer, function, depth + 1);
+                try writer.writeByte('\n');
+            }
+            try writeIndent(writer, depth);
+            try writer.writeByte('}');
+        },
+        .trait_def => |trait_def| {
+            try writer.print("trait {s} {{\n", .{trait_def.name});
+            for (trait_def.methods) |method| {
+                try writeIndent(writer, depth + 1);
+                if (method.default) |default| {
+                    try writeNode(writer, default, depth + 1);
+                } else {
+                    try writer.print("fn {s}", .{method.name});
+                    try writeParams(writer, method.params, depth + 1);
+                    if (method.return_type) |return_type| {
+                        try writer.writeAll(" -> ");
+                        try writeNode(writer, return_type, depth + 1);
+                    }
+                }
+                try writer.writeByte('\n');
+            }
+            try writeIndent(writer, depth);
+            try writer.writeByte('}');
+        },
+        .var_decl, .const_decl => |binding| {
+            const keyword = if (node.kind == .const_decl) "const"
+                else if (binding.mutable) "var"
+                else "let";
+
+            try writer.print("{s} {s}", .{keyword, binding.name});
+            if (binding.annotation) |annotation| {
+                try writer.writeAll(": ");
+                try writeNode(writer, annotation, depth);
+            }
+            if (binding.value) |value| {
+                try writer.writeAll(" = ");
+                try writeNode(writer, value, depth);
+            }
+        },
+        .destructure => |destructure| {
+            try writer.writeAll("let ");
+            try writePattern(writer, destructure.pattern);
+            if (destructure.annotation) |annotation| {
+                try writer.writeAll(": ");
+                try writeNode(writer, annotation, depth);
+            }
+            try writer.writeAll(" = ");
+            try writeNode(writer, destructure.value, depth);
+        },
+        .import => |import| {
+            try writer.writeAll("import ");
+            for (import.path, 0..) |segment, i| {
+                if (i > 0) try writer.writeByte('.');
+                try writer.writeAll(segment);
+            }
+            if (import.alias) |alias| try writer.print(" as {s}", .{alias});
+        },
+        .@"export" => |declaration| {
+            try writer.writeAll("pub ");
+            try writeNode(writer, declaration, depth);
+        },
+        .block => |block| {
+            try writer.writeAll("{\n");
+            for (block.statements) |statement| {
+                try writeIndent(writer, depth + 1);
+                try writeNode(writer, statement, depth + 1);
+                try writer.writeByte('\n');
+            }
+            try writeIndent(writer, depth);
+            try writer.writeByte('}');
+        },
+        .@"if" => |conditional| {
+            try writer.writeAll("if ");
+            try writeCondition(writer, conditional.condition, depth);
+            try writer.writeByte(' ');
+            try writeNode(writer, conditional.then_block, depth);
+            if (conditional.else_block) |else_block| {
+                try writer.writeAll(" else ");
+                try writeNode(writer, else_block, depth);
+            }
+        },
+        .@"while" => |loop| {
+            try writer.writeAll("while ");
+            try writeCondition(writer, loop.condition, depth);
+            try writer.writeByte(' ');
+            try writeNode(writer, loop.body, depth);
+        },
+        .@"for" => |loop| {
+            try writer.print("for {s} in ", .{loop.variable});
+            try writeCondition(writer, loop.iterable, depth);
+            try writer.writeByte(' ');
+            try writeNode(writer, loop.body, depth);
+        },
+        .match => |match| {
+            try writer.writeAll("match ");
+            try writeCondition(writer, match.subject, depth);
+            try writer.writeAll(" {\n");
+            for (match.arms) |arm| {
+                try writeIndent(writer, depth + 1);
+                try writePattern(writer, arm.pattern);
+                if (arm.guard) |guard| {
+                    try writer.writeAll(" if ");
+                    try writeNode(writer, guard, depth + 1);
+                }
+                try writer.writeAll(" => ");
+                try writeNode(writer, arm.body, depth + 1);
+                try writer.writeByte('\n');
+            }
+            try writeIndent(writer, depth);
+            try writer.writeByte('}');
+        },
+        .@"return" => |value| {
+            try writer.writeAll("return");
+            if (value) |v| {
+                try writer.writeByte(' ');
+                try writeNode(writer, v, depth);
+            }
+        },
+        .@"break" => |value| {
+            try writer.writeAll("break");
+            if (value) |v| {
+                try writer.writeByte(' ');
+                try writeNode(writer, v, depth);
+            }
+        },
+        .@"continue" => try writer.writeAll("continue"),
+        .@"defer" => |body| {
+            try writer.writeAll("defer ");
+            try writeNode(writer, body, depth);
+        },
+        .@"unsafe" => |body| {
+            try writer.writeAll("unsafe ");
+            try writeNode(writer, body, depth);
+        },
+        .@"comptime" => |body| {
+            try writer.writeAll("comptime ");
+            try writeNode(writer, body, depth);
+        },
+        .label => |name| try writer.print("{s}:", .{name}),
+        .goto => |target| try writer.print("goto {s}", .{target}),
+        .assignment => |assignment| {
+            try writeNode(writer, assignment.lhs, depth);
+            try writer.writeAll(" = ");
+            try writeNode(writer, assignment.rhs, depth);
+        },
+        .prefix => |prefix| {
+            try writer.writeAll(prefix.operator.toStr());
+            try writeOperand(writer, prefix.operand, depth);
+        },
+        .infix => |infix| {
+            try writer.writeByte('(');
+            try writeOperand(writer, infix.lhs, depth);
+            try writer.print(" {s} ", .{infix.operator.toStr()});
+            try writeOperand(writer, infix.rhs, depth);
+            try writer.writeByte(')');
+        },
+        .fn_call => |call| {
+            try writeNode(writer, call.callee, depth);
+            try writer.writeByte('(');
+            for (call.arguments, 0..) |argument, i| {
+                if (i > 0) try writer.writeAll(", ");
+                try writeNode(writer, argument, depth);
+            }
+            try writer.writeByte(')');
+        },
+        .struct_literal => |literal| {
+            try writer.print("{s} ", .{literal.name});
+            try writeFieldValues(writer, literal.fields, depth);
+        },
+        .variant_literal => |literal| {
+            if (literal.qualified) try writer.print("{s}::", .{literal.@"enum"});
+            try writer.writeAll(literal.variant);
+            switch (literal.fields) {
+                .unit => {},
+                .tuple => |values| {
+                    try writer.writeByte('(');
+                    for (values, 0..) |value, i| {
+                        if (i > 0) try writer.writeAll(", ");
+                        try writeNode(writer, value, depth);
+                    }
+                    try writer.writeByte(')');
+                },
+                .@"struct" => |fields| {
+                    try writer.writeByte(' ');
+                    try writeFieldValues(writer, fields, depth);
+                }
+            }
+        },
+        .closure => |closure| {
+            try writer.writeAll("fn");
+            try writeSignature(writer, closure.params, closure.return_type, depth);
+            try writeNode(writer, closure.body, depth);
+        },
+        .field_access => |access| {
+            try writeOperand(writer, access.object, depth);
+            try writer.print(".{s}", .{access.field});
+        },
+        .array_literal => |elements| {
+            try writer.writeByte('[');
+            try writeTypes(writer, elements, depth);
+            try writer.writeByte(']');
+        },
+        .index => |index| {
+            try writeOperand(writer, index.object, depth);
+            try writer.writeByte('[');
+            try writeNode(writer, index.index, depth);
+            try writer.writeByte(']');
+        },
+        .tuple_literal => |elements| {
+            try writer.writeByte('(');
+            try writeTypes(writer, elements, depth);
+            // A single value in parentheses is only grouped, not a tuple
+            if (elements.len == 1) try writer.writeByte(',');
+            try writer.writeByte(')');
+        },
+        .tuple_index => |access| {
+            try writeOperand(writer, access.object, depth);
+            try writer.print(".{}", .{access.index});
+        },
+        .@"try" => |operand| {
+            try writeOperand(writer, operand, depth);
+            try writer.writeByte('?');
+        },
+        .reference, .dereference => |operand| {
+            try writer.writeByte(if (node.kind == .reference) '&' else '*');
+            try writeOperand(writer, operand, depth);
+        },
+        .@"await" => |operand| {
+            try writer.writeAll("await ");
+            try writeOperand(writer, operand, depth);
+        },
+        .sizeof => |annotation| {
+            try writer.writeAll("sizeof(");
+            try writeNode(writer, annotation, depth);
+            try writer.writeByte(')');
+        },
+        .cast => |cast| {
+            try writeOperand(writer, cast.value, depth);
+            try writer.writeAll(" as ");
+            try writeNode(writer, cast.target, depth);
+        },
+        .identifier => |identifier| try writer.writeAll(identifier),
+        .literal => |literal| try writeLiteral(writer, literal),
+        .type_expr => |type_expr| switch (type_expr) {
+            .generic => |generic| {
+                try writer.print("{s}<", .{generic.name});
+                try writeTypes(writer, generic.arguments, depth);
+                try writer.writeByte('>');
+            },
+            .array => |array| {
+                try writer.writeByte('[');
+                try writeNode(writer, array.element, depth);
+                try writer.writeAll("; ");
+                try writeNode(writer, array.len, depth);
+                try writer.writeByte(']');
+            },
+            .slice => |element| {
+                try writer.writeByte('[');
+                try writeNode(writer, element, depth);
+                try writer.writeByte(']');
+            },
+            .tuple => |elements| {
+                try writer.writeByte('(');
+                try writeTypes(writer, elements, depth);
+                // A single type in parentheses is only grouped, not a tuple
+                if (elements.len == 1) try writer.writeByte(',');
+                try writer.writeByte(')');
+            },
+            .function => |function| {
+                try writer.writeAll("fn(");
+                try writeTypes(writer, function.params, depth);
+                try writer.writeByte(')');
+                if (function.return_type) |return_type| {
+                    try writer.writeAll(" -> ");
+                    try writeNode(writer, return_type, depth);
+                }
+            },
+            .reference => |referenced| {
+                try writer.writeByte('&');
+                try writeNode(writer, referenced, depth);
+            },
+            .impl => |trait| try writer.print("impl {s}", .{trait}),
+            .variadic => try writer.writeAll("...")
+        }
+    }
+}
+
+/// Whether annotation is a reference type, `&T`
+pub fn isReferenceType(annotation: *const Node) bool {
+    return annotation.kind == .type_expr and annotation.kind.type_expr == .reference;
+}
+
+// The parameters and return type of a function, followed by the space before its body
+fn writeSignature(writer: AnyWriter, params: []const Node.Parameter, return_type: ?*const Node, depth: usize) anyerror!void {
+    try writeParams(writer, params, depth);
+    try writer.writeByte(' ');
+    if (return_type) |ret| {
+        try writer.writeAll("-> ");
+        try writeNode(writer, ret, depth);
+        try writer.writeByte(' ');
+    }
+}
 
-    self.root = root;
+fn writeParams(writer: AnyWriter, params: []const Node.Parameter, depth: usize) anyerror!void {
+    try writer.writeByte('(');
+    for (params, 0..) |param, i| {
+        if (i > 0) try writer.writeAll(", ");
+        if (param.shorthand) {
+            try writer.writeAll(if (isReferenceType(param.annotation.?)) "&self" else "self");
+            continue;
+        }
 
-    return root;
+        try writer.writeAll(param.name);
+        if (param.annotation) |annotation| {
+            try writer.writeAll(": ");
+            try writeNode(writer, annotation, depth);
+        }
+    }
+    try writer.writeByte(')');
 }
 
-pub fn write(self: *Ast, writer: std.io.AnyWriter) !void {
-    const node = self.root orelse return error.UninitializeAst;
-    try self.writeInternal(writer, node);
+fn writeTypeParams(writer: AnyWriter, type_params: []const []const u8) anyerror!void {
+    if (type_params.len == 0) return;
+
+    try writer.writeByte('<');
+    for (type_params, 0..) |type_param, i| {
+        if (i > 0) try writer.writeAll(", ");
+        try writer.writeAll(type_param);
+    }
+    try writer.writeByte('>');
 }
 
-fn writeInternal(self: *Ast, writer: std.io.AnyWriter, node: *Node) !void {
+fn writeTypes(writer: AnyWriter, types: []const *Node, depth: usize) anyerror!void {
+    for (types, 0..) |@"type", i| {
+        if (i > 0) try writer.writeAll(", ");
+        try writeNode(writer, @"type", depth);
+    }
+}
+
+fn writeFieldValues(writer: AnyWriter, fields: []const Node.StructLiteral.Field, depth: usize) anyerror!void {
+    try writer.writeByte('{');
+    for (fields, 0..) |field, i| {
+        try writer.writeAll(if (i > 0) ", " else " ");
+        try writer.print("{s}: ", .{field.name});
+        try writeNode(writer, field.value, depth);
+    }
+    try writer.writeAll(if (fields.len > 0) " }" else "}");
+}
+
+// Struct literals in conditions are parenthesized, so their braces aren't read as
+// beginning the block after the condition
+fn writeCondition(writer: AnyWriter, node: *const Node, depth: usize) anyerror!void {
+    if (node.hasBareStructLiteral()) {
+        try writer.writeByte('(');
+        try writeNode(writer, node, depth);
+        try writer.writeByte(')');
+    } else {
+        try writeNode(writer, node, depth);
+    }
+}
+
+// Infix expressions parenthesize themselves, the other operators are parenthesized
+// when they are operands so they are read back with the same precedence
+fn writeOperand(writer: AnyWriter, node: *const Node, depth: usize) anyerror!void {
     switch (node.kind) {
-        .@"if" => {
-            try writer.writeAll("if (");
-            try self.writeInternal(writer, node.lhs.?);
-            try writer.writeAll(") {\n    ");
-            try self.writeInternal(writer, node.rhs.?);
-            try writer.writeAll("\n}");
-
-            if (node.rhs.?.rhs) |consequence| {
-                try writer.writeAll(" else {\n    ");
-                try self.writeInternal(writer, consequence);
-                try writer.writeAll("\n}");
-            }
-        },
-        .integer => {
-            switch (node.token.kind) {
-                .integer => |integer| {
-                    try writer.writeAll(integer.items);
+        .assignment, .prefix, .reference, .dereference, .@"await", .cast => {
+            try writer.writeByte('(');
+            try writeNode(writer, node, depth);
+            try writer.writeByte(')');
+        },
+        else => try writeNode(writer, node, depth)
+    }
+}
+
+/// Writes literal as it is written in source
+pub fn writeLiteral(writer: anytype, literal: Node.Literal) @TypeOf(writer).Error!void {
+    switch (literal) {
+        .integer => |integer| try writer.print("{}", .{integer}),
+        .float => |float| try writeFloat(writer, float),
+        .boolean => |boolean| try writer.writeAll(if (boolean) "true" else "false"),
+        .string => |string| try writer.print("\"{}\"", .{std.zig.fmtEscapes(string)}),
+        .character => |character| try writer.print("'{}'", .{std.zig.fmtEscapes(&.{character})}),
+        .unit => try writer.writeAll("()")
+    }
+}
+
+/// Writes pattern as it is written in source
+pub fn writePattern(writer: anytype, pattern: Node.Pattern) @TypeOf(writer).Error!void {
+    switch (pattern.kind) {
+        .literal => |literal| try writeLiteral(writer, literal),
+        .identifier => |name| try writer.writeAll(name),
+        .wildcard => try writer.writeByte('_'),
+        .@"struct" => |structure| {
+            try writer.print("{s} ", .{structure.name});
+            try writeFieldPatterns(writer, structure.fields);
+        },
+        .variant => |variant| {
+            for (variant.path, 0..) |segment, i| {
+                if (i > 0) try writer.writeAll("::");
+                try writer.writeAll(segment);
+            }
+            switch (variant.fields) {
+                .unit => {},
+                .tuple => |fields| {
+                    try writer.writeByte('(');
+                    try writePatterns(writer, fields, ", ");
+                    try writer.writeByte(')');
                 },
-                else => unreachable
+                .@"struct" => |fields| {
+                    try writer.writeByte(' ');
+                    try writeFieldPatterns(writer, fields);
+                }
+            }
+        },
+        .tuple => |elements| {
+            try writer.writeByte('(');
+            try writePatterns(writer, elements, ", ");
+            // A single element in parentheses is only grouped, unless followed by a comma
+            if (elements.len == 1) try writer.writeByte(',');
+            try writer.writeByte(')');
+        },
+        .@"or" => |alternatives| try writePatterns(writer, alternatives, " | ")
+    }
+}
+
+fn writeFieldPatterns(writer: anytype, fields: []const Node.Pattern.StructPattern.Field) @TypeOf(writer).Error!void {
+    try writer.writeByte('{');
+    for (fields, 0..) |field, i| {
+        try writer.writeAll(if (i > 0) ", " else " ");
+        try writer.writeAll(field.name);
+
+        // Fields binding their own name are written in shorthand
+        const shorthand = switch (field.pattern.kind) {
+            .identifier => |name| std.mem.eql(u8, name, field.name),
+            else => false
+        };
+        if (!shorthand) {
+            try writer.writeAll(": ");
+            try writePattern(writer, field.pattern);
+        }
+    }
+    try writer.writeAll(if (fields.len > 0) " }" else "}");
+}
+
+fn writePatterns(writer: anytype, patterns: []const Node.Pattern, separator: []const u8) @TypeOf(writer).Error!void {
+    for (patterns, 0..) |pattern, i| {
+        if (i > 0) try writer.writeAll(separator);
+        try writePattern(writer, pattern);
+    }
+}
+
+fn writeTreeIndent(writer: anytype, depth: usize) @TypeOf(writer).Error!void {
+    try writer.writeByteNTimes(' ', depth * 2);
+}
+
+// Labels children which are optional, so they can't be confused with the ones that follow
+fn writeTreeField(writer: anytype, label: []const u8, node: *const Node, depth: usize) @TypeOf(writer).Error!void {
+    try writeTreeIndent(writer, depth);
+    try writer.print("{s}\n", .{label});
+    try writeTree(writer, node, depth + 1);
+}
+
+fn writeTreeParams(writer: anytype, params: []const Node.Parameter, depth: usize) @TypeOf(writer).Error!void {
+    for (params) |param| {
+        try writeTreeIndent(writer, depth);
+        try writer.print("parameter {s}\n", .{param.name});
+        if (param.annotation) |annotation| try writeTree(writer, annotation, depth + 1);
+    }
+}
+
+fn writeTree(writer: anytype, node: *const Node, depth: usize) @TypeOf(writer).Error!void {
+    try writeTreeIndent(writer, depth);
+
+    switch (node.kind) {
+        .module => |module| {
+            try writer.print("module {s}\n", .{module.name});
+            for (module.items) |item| try writeTree(writer, item, depth + 1);
+        },
+        .fn_def => |fn_def| {
+            try writer.print("fn_def {s}{s}\n", .{fn_def.name, if (fn_def.@"async") " async" else ""});
+            for (fn_def.type_params) |type_param| {
+                try writeTreeIndent(writer, depth + 1);
+                try writer.print("type_param {s}\n", .{type_param});
+            }
+            try writeTreeParams(writer, fn_def.params, depth + 1);
+            if (fn_def.return_type) |return_type| try writeTreeField(writer, "returns", return_type, depth + 1);
+            try writeTree(writer, fn_def.body, depth + 1);
+        },
+        .extern_fn => |extern_fn| {
+            try writer.print("extern_fn {s}{s}\n", .{extern_fn.name, if (extern_fn.variadic) " variadic" else ""});
+            try writeTreeParams(writer, extern_fn.params, depth + 1);
+            if (extern_fn.return_type) |return_type| try writeTreeField(writer, "returns", return_type, depth + 1);
+        },
+        .struct_def => |struct_def| {
+            try writer.print("struct_def {s}\n", .{struct_def.name});
+            for (struct_def.type_params) |type_param| {
+                try writeTreeIndent(writer, depth + 1);
+                try writer.print("type_param {s}\n", .{type_param});
+            }
+            for (struct_def.fields) |field| {
+                try writeTreeIndent(writer, depth + 1);
+                try writer.print("field {s}\n", .{field.name});
+                try writeTree(writer, field.annotation, depth + 2);
+            }
+        },
+        .union_def => |union_def| {
+            try writer.print("union_def {s}\n", .{union_def.name});
+            for (union_def.fields) |field| {
+                try writeTreeIndent(writer, depth + 1);
+                try writer.print("field {s}\n", .{field.name});
+                try writeTree(writer, field.annotation, depth + 2);
+            }
+        },
+        .enum_def => |enum_def| {
+            try writer.print("enum_def {s}\n", .{enum_def.name});
+            for (enum_def.variants) |variant| {
+                try writeTreeIndent(writer, depth + 1);
+                try writer.print("variant {s}\n", .{variant.name});
+                switch (variant.fields) {
+                    .unit => {},
+                    .tuple => |annotations| for (annotations) |annotation| try writeTree(writer, annotation, depth + 2),
+                    .@"struct" => |fields| for (fields) |field| {
+                        try writeTreeIndent(writer, depth + 2);
+                        try writer.print("field {s}\n", .{field.name});
+                        try writeTree(writer, field.annotation, depth + 3);
+                    }
+                }
+            }
+        },
+        .impl => |impl| {
+            if (impl.trait) |trait| {
+                try writer.print("impl {s} for {s}\n", .{trait, impl.name});
+            } else {
+                try writer.print("impl {s}\n", .{impl.name});
+            }
+            for (impl.functions) |function| try writeTree(writer, function, depth + 1);
+        },
+        .trait_def => |trait_def| {
+            try writer.print("trait_def {s}\n", .{trait_def.name});
+            for (trait_def.methods) |method| {
+                if (method.default) |default| {
+                    try writeTree(writer, default, depth + 1);
+                    continue;
+                }
+
+                try writeTreeIndent(writer, depth + 1);
+                try writer.print("method {s}\n", .{method.name});
+                try writeTreeParams(writer, method.params, depth + 2);
+                if (method.return_type) |return_type| try writeTreeField(writer, "returns", return_type, depth + 2);
+            }
+        },
+        .var_decl, .const_decl => |binding| {
+            try writer.print("{s} {s}{s}\n", .{
+                @tagName(node.kind),
+                binding.name,
+                if (binding.mutable) " mutable" else ""
+            });
+            if (binding.annotation) |annotation| try writeTreeField(writer, "type", annotation, depth + 1);
+            if (binding.value) |value| try writeTree(writer, value, depth + 1);
+        },
+        .destructure => |destructure| {
+            try writer.writeAll("destructure ");
+            try writePattern(writer, destructure.pattern);
+            try writer.writeByte('\n');
+            if (destructure.annotation) |annotation| try writeTreeField(writer, "type", annotation, depth + 1);
+            try writeTree(writer, destructure.value, depth + 1);
+        },
+        .import => |import| {
+            try writer.writeAll("import ");
+            for (import.path, 0..) |segment, i| {
+                if (i > 0) try writer.writeByte('.');
+                try writer.writeAll(segment);
+            }
+            if (import.alias) |alias| try writer.print(" as {s}", .{alias});
+            try writer.writeByte('\n');
+        },
+        .@"export" => |declaration| {
+            try writer.writeAll("export\n");
+            try writeTree(writer, declaration, depth + 1);
+        },
+        .block => |block| {
+            try writer.writeAll("block\n");
+            for (block.statements) |statement| try writeTree(writer, statement, depth + 1);
+        },
+        .@"if" => |conditional| {
+            try writer.writeAll("if\n");
+            try writeTree(writer, conditional.condition, depth + 1);
+            try writeTree(writer, conditional.then_block, depth + 1);
+            if (conditional.else_block) |else_block| try writeTreeField(writer, "else", else_block, depth + 1);
+        },
+        .@"while" => |loop| {
+            try writer.writeAll("while\n");
+            try writeTree(writer, loop.condition, depth + 1);
+            try writeTree(writer, loop.body, depth + 1);
+        },
+        .@"for" => |loop| {
+            try writer.print("for {s}\n", .{loop.variable});
+            try writeTree(writer, loop.iterable, depth + 1);
+            try writeTree(writer, loop.body, depth + 1);
+        },
+        .match => |match| {
+            try writer.writeAll("match\n");
+            try writeTree(writer, match.subject, depth + 1);
+            for (match.arms) |arm| {
+                try writeTreeIndent(writer, depth + 1);
+                try writer.writeAll("arm ");
+                try writePattern(writer, arm.pattern);
+                try writer.writeByte('\n');
+                if (arm.guard) |guard| try writeTreeField(writer, "guard", guard, depth + 2);
+                try writeTree(writer, arm.body, depth + 2);
+            }
+        },
+        .@"return" => |value| {
+            try writer.writeAll("return\n");
+            if (value) |v| try writeTree(writer, v, depth + 1);
+        },
+        .@"break" => |value| {
+            try writer.writeAll("break\n");
+            if (value) |v| try writeTree(writer, v, depth + 1);
+        },
+        .@"continue" => try writer.writeAll("continue\n"),
+        .@"defer" => |body| {
+            try writer.writeAll("defer\n");
+            try writeTree(writer, body, depth + 1);
+        },
+        .@"unsafe" => |body| {
+            try writer.writeAll("unsafe\n");
+            try writeTree(writer, body, depth + 1);
+        },
+        .@"comptime" => |body| {
+            try writer.writeAll("comptime\n");
+            try writeTree(writer, body, depth + 1);
+        },
+        .label => |name| try writer.print("label {s}\n", .{name}),
+        .goto => |target| try writer.print("goto {s}\n", .{target}),
+        .assignment => |assignment| {
+            try writer.writeAll("assignment\n");
+            try writeTree(writer, assignment.lhs, depth + 1);
+            try writeTree(writer, assignment.rhs, depth + 1);
+        },
+        .prefix => |prefix| {
+            try writer.print("prefix {s}\n", .{prefix.operator.toStr()});
+            try writeTree(writer, prefix.operand, depth + 1);
+        },
+        .infix => |infix| {
+            try writer.print("infix {s}\n", .{infix.operator.toStr()});
+            try writeTree(writer, infix.lhs, depth + 1);
+            try writeTree(writer, infix.rhs, depth + 1);
+        },
+        .fn_call => |call| {
+            try writer.writeAll("fn_call\n");
+            try writeTree(writer, call.callee, depth + 1);
+            for (call.arguments) |argument| try writeTree(writer, argument, depth + 1);
+        },
+        .struct_literal => |literal| {
+            try writer.print("struct_literal {s}\n", .{literal.name});
+            for (literal.fields) |field| {
+                try writeTreeIndent(writer, depth + 1);
+                try writer.print("field {s}\n", .{field.name});
+                try writeTree(writer, field.value, depth + 2);
+            }
+        },
+        .variant_literal => |literal| {
+            try writer.print("variant_literal {s}::{s}\n", .{literal.@"enum", literal.variant});
+            switch (literal.fields) {
+                .unit => {},
+                .tuple => |values| for (values) |value| try writeTree(writer, value, depth + 1),
+                .@"struct" => |fields| for (fields) |field| {
+                    try writeTreeIndent(writer, depth + 1);
+                    try writer.print("field {s}\n", .{field.name});
+                    try writeTree(writer, field.value, depth + 2);
+                }
+            }
+        },
+        .field_access => |access| {
+            try writer.print("field_access {s}\n", .{access.field});
+            try writeTree(writer, access.object, depth + 1);
+        },
+        .array_literal => |elements| {
+            try writer.writeAll("array_literal\n");
+            for (elements) |element| try writeTree(writer, element, depth + 1);
+        },
+        .index => |index| {
+            try writer.writeAll("index\n");
+            try writeTree(writer, index.object, depth + 1);
+            try writeTree(writer, index.index, depth + 1);
+        },
+        .tuple_literal => |elements| {
+            try writer.writeAll("tuple_literal\n");
+            for (elements) |element| try writeTree(writer, element, depth + 1);
+        },
+        .tuple_index => |access| {
+            try writer.print("tuple_index {}\n", .{access.index});
+            try writeTree(writer, access.object, depth + 1);
+        },
+        .@"try" => |operand| {
+            try writer.writeAll("try\n");
+            try writeTree(writer, operand, depth + 1);
+        },
+        .reference, .dereference, .@"await" => |operand| {
+            try writer.print("{s}\n", .{@tagName(node.kind)});
+            try writeTree(writer, operand, depth + 1);
+        },
+        .sizeof => |annotation| {
+            try writer.writeAll("sizeof\n");
+            try writeTree(writer, annotation, depth + 1);
+        },
+        .cast => |cast| {
+            try writer.writeAll("cast\n");
+            try writeTree(writer, cast.value, depth + 1);
+            try writeTreeField(writer, "as", cast.target, depth + 1);
+        },
+        .closure => |closure| {
+            try writer.writeAll("closure\n");
+            for (closure.captures) |capture| {
+                try writeTreeIndent(writer, depth + 1);
+                try writer.print("capture {s}\n", .{capture});
+            }
+            try writeTreeParams(writer, closure.params, depth + 1);
+            if (closure.return_type) |return_type| try writeTreeField(writer, "returns", return_type, depth + 1);
+            try writeTree(writer, closure.body, depth + 1);
+        },
+        .identifier => |identifier| try writer.print("identifier {s}\n", .{identifier}),
+        .literal => |literal| switch (literal) {
+            .integer => |integer| try writer.print("integer {}\n", .{integer}),
+            .float => |float| {
+                try writer.writeAll("float ");
+                try writeFloat(writer, float);
+                try writer.writeByte('\n');
+            },
+            .boolean => |boolean| try writer.print("boolean {}\n", .{boolean}),
+            .string => |string| try writer.print("string \"{}\"\n", .{std.zig.fmtEscapes(string)}),
+            .character => |character| try writer.print("character '{}'\n", .{std.zig.fmtEscapes(&.{character})}),
+            .unit => try writer.writeAll("unit\n")
+        },
+        .type_expr => |type_expr| switch (type_expr) {
+            .generic => |generic| {
+                try writer.print("type_expr generic {s}\n", .{generic.name});
+                for (generic.arguments) |argument| try writeTree(writer, argument, depth + 1);
+            },
+            .array => |array| {
+                try writer.writeAll("type_expr array\n");
+                try writeTree(writer, array.element, depth + 1);
+                try writeTreeField(writer, "len", array.len, depth + 1);
+            },
+            .slice => |element| {
+                try writer.writeAll("type_expr slice\n");
+                try writeTree(writer, element, depth + 1);
+            },
+            .tuple => |elements| {
+                try writer.writeAll("type_expr tuple\n");
+                for (elements) |element| try writeTree(writer, element, depth + 1);
+            },
+            .function => |function| {
+                try writer.writeAll("type_expr function\n");
+                for (function.params) |param| try writeTree(writer, param, depth + 1);
+                if (function.return_type) |return_type| try writeTreeField(writer, "returns", return_type, depth + 1);
+            },
+            .reference => |referenced| {
+                try writer.writeAll("type_expr reference\n");
+                try writeTree(writer, referenced, depth + 1);
+            },
+            .impl => |trait| try writer.print("type_expr impl {s}\n", .{trait}),
+            .variadic => try writer.writeAll("type_expr variadic\n")
+        }
+    }
+}
+
+fn writeJsonString(writer: anytype, string: []const u8) @TypeOf(writer).Error!void {
+    try std.json.encodeJsonString(string, .{}, writer);
+}
+
+// Writes the name of the next field of an object, after a comma separating it from the last
+fn writeJsonField(writer: anytype, name: []const u8) @TypeOf(writer).Error!void {
+    try writer.print(",\"{s}\":", .{name});
+}
+
+fn writeJsonStrings(writer: anytype, strings: []const []const u8) @TypeOf(writer).Error!void {
+    try writer.writeByte('[');
+    for (strings, 0..) |string, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writeJsonString(writer, string);
+    }
+    try writer.writeByte(']');
+}
+
+fn writeJsonOptional(writer: anytype, node: ?*const Node) @TypeOf(writer).Error!void {
+    if (node) |n| try writeJsonNode(writer, n) else try writer.writeAll("null");
+}
+
+fn writeJsonNodes(writer: anytype, nodes: []const *Node) @TypeOf(writer).Error!void {
+    try writer.writeByte('[');
+    for (nodes, 0..) |node, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writeJsonNode(writer, node);
+    }
+    try writer.writeByte(']');
+}
+
+fn writeJsonParams(writer: anytype, params: []const Node.Parameter) @TypeOf(writer).Error!void {
+    try writer.writeByte('[');
+    for (params, 0..) |param, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writer.writeAll("{\"name\":");
+        try writeJsonString(writer, param.name);
+        try writeJsonField(writer, "type");
+        try writeJsonOptional(writer, param.annotation);
+        try writeJsonField(writer, "span");
+        try writeJsonSpan(writer, param.span);
+        try writer.print(",\"shorthand\":{}", .{param.shorthand});
+        try writer.writeByte('}');
+    }
+    try writer.writeByte(']');
+}
+
+fn writeJsonFieldTypes(writer: anytype, fields: []const Node.StructDef.Field) @TypeOf(writer).Error!void {
+    try writer.writeByte('[');
+    for (fields, 0..) |field, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writer.writeAll("{\"name\":");
+        try writeJsonString(writer, field.name);
+        try writeJsonField(writer, "type");
+        try writeJsonNode(writer, field.annotation);
+        try writeJsonField(writer, "span");
+        try writeJsonSpan(writer, field.span);
+        try writer.writeByte('}');
+    }
+    try writer.writeByte(']');
+}
+
+fn writeJsonFieldValues(writer: anytype, fields: []const Node.StructLiteral.Field) @TypeOf(writer).Error!void {
+    try writer.writeByte('[');
+    for (fields, 0..) |field, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writer.writeAll("{\"name\":");
+        try writeJsonString(writer, field.name);
+        try writeJsonField(writer, "value");
+        try writeJsonNode(writer, field.value);
+        try writeJsonField(writer, "span");
+        try writeJsonSpan(writer, field.span);
+        try writer.writeByte('}');
+    }
+    try writer.writeByte(']');
+}
+
+fn writeJsonSpan(writer: anytype, span: Span) @TypeOf(writer).Error!void {
+    try writer.writeAll("{\"file\":");
+    try writeJsonString(writer, span.file);
+    try writer.print(",\"start\":{},\"end\":{},\"line\":{},\"column\":{}}}", .{
+        span.start,
+        span.end,
+        span.pos.line,
+        span.pos.col
+    });
+}
+
+fn writeJsonNode(writer: anytype, node: *const Node) @TypeOf(writer).Error!void {
+    try writer.print("{{\"kind\":\"{s}\",\"id\":{}", .{@tagName(node.kind), node.id});
+    try writeJsonField(writer, "span");
+    try writeJsonSpan(writer, node.span);
+
+    if (node.attributes.len > 0) {
+        try writeJsonField(writer, "attributes");
+        try writer.writeByte('[');
+        for (node.attributes, 0..) |attribute, i| {
+            if (i > 0) try writer.writeByte(',');
+            try writer.writeAll("{\"name\":");
+            try writeJsonString(writer, attribute.name);
+            try writeJsonField(writer, "args");
+            try writeJsonStrings(writer, attribute.args);
+            try writeJsonField(writer, "span");
+            try writeJsonSpan(writer, attribute.span);
+            try writer.writeByte('}');
+        }
+        try writer.writeByte(']');
+    }
+
+    switch (node.kind) {
+        .module => |module| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, module.name);
+            try writeJsonField(writer, "items");
+            try writeJsonNodes(writer, module.items);
+        },
+        .fn_def => |fn_def| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, fn_def.name);
+            try writeJsonField(writer, "type_params");
+            try writeJsonStrings(writer, fn_def.type_params);
+            try writeJsonField(writer, "params");
+            try writeJsonParams(writer, fn_def.params);
+            try writeJsonField(writer, "return_type");
+            try writeJsonOptional(writer, fn_def.return_type);
+            try writeJsonField(writer, "body");
+            try writeJsonNode(writer, fn_def.body);
+            try writer.print(",\"async\":{}", .{fn_def.@"async"});
+        },
+        .extern_fn => |extern_fn| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, extern_fn.name);
+            try writeJsonField(writer, "params");
+            try writeJsonParams(writer, extern_fn.params);
+            try writer.print(",\"variadic\":{}", .{extern_fn.variadic});
+            try writeJsonField(writer, "return_type");
+            try writeJsonOptional(writer, extern_fn.return_type);
+        },
+        .struct_def => |struct_def| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, struct_def.name);
+            try writeJsonField(writer, "type_params");
+            try writeJsonStrings(writer, struct_def.type_params);
+            try writeJsonField(writer, "fields");
+            try writeJsonFieldTypes(writer, struct_def.fields);
+        },
+        .union_def => |union_def| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, union_def.name);
+            try writeJsonField(writer, "fields");
+            try writeJsonFieldTypes(writer, union_def.fields);
+        },
+        .enum_def => |enum_def| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, enum_def.name);
+            try writeJsonField(writer, "variants");
+            try writer.writeByte('[');
+            for (enum_def.variants, 0..) |variant, i| {
+                if (i > 0) try writer.writeByte(',');
+                try writer.writeAll("{\"name\":");
+                try writeJsonString(writer, variant.name);
+                try writer.print(",\"style\":\"{s}\"", .{@tagName(variant.fields)});
+                try writeJsonField(writer, "fields");
+                switch (variant.fields) {
+                    .unit => try writer.writeAll("[]"),
+                    .tuple => |annotations| try writeJsonNodes(writer, annotations),
+                    .@"struct" => |fields| try writeJsonFieldTypes(writer, fields)
+                }
+                try writeJsonField(writer, "span");
+                try writeJsonSpan(writer, variant.span);
+                try writer.writeByte('}');
+            }
+            try writer.writeByte(']');
+        },
+        .impl => |impl| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, impl.name);
+            try writeJsonField(writer, "functions");
+            try writeJsonNodes(writer, impl.functions);
+            try writeJsonField(writer, "trait");
+            if (impl.trait) |trait| try writeJsonString(writer, trait) else try writer.writeAll("null");
+        },
+        .trait_def => |trait_def| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, trait_def.name);
+            try writeJsonField(writer, "methods");
+            try writer.writeByte('[');
+            for (trait_def.methods, 0..) |method, i| {
+                if (i > 0) try writer.writeByte(',');
+                try writer.writeAll("{\"name\":");
+                try writeJsonString(writer, method.name);
+                try writeJsonField(writer, "params");
+                try writeJsonParams(writer, method.params);
+                try writeJsonField(writer, "return_type");
+                try writeJsonOptional(writer, method.return_type);
+                try writeJsonField(writer, "default");
+                try writeJsonOptional(writer, method.default);
+                try writeJsonField(writer, "span");
+                try writeJsonSpan(writer, method.span);
+                try writer.writeByte('}');
+            }
+            try writer.writeByte(']');
+        },
+        .var_decl, .const_decl => |binding| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, binding.name);
+            try writer.print(",\"mutable\":{}", .{binding.mutable});
+            try writeJsonField(writer, "type");
+            try writeJsonOptional(writer, binding.annotation);
+            try writeJsonField(writer, "value");
+            try writeJsonOptional(writer, binding.value);
+        },
+        .destructure => |destructure| {
+            try writeJsonField(writer, "pattern");
+            try writeJsonPattern(writer, destructure.pattern);
+            try writeJsonField(writer, "type");
+            try writeJsonOptional(writer, destructure.annotation);
+            try writeJsonField(writer, "value");
+            try writeJsonNode(writer, destructure.value);
+        },
+        .import => |import| {
+            try writeJsonField(writer, "path");
+            try writer.writeByte('[');
+            for (import.path, 0..) |segment, i| {
+                if (i > 0) try writer.writeByte(',');
+                try writeJsonString(writer, segment);
+            }
+            try writer.writeByte(']');
+            try writeJsonField(writer, "alias");
+            if (import.alias) |alias| try writeJsonString(writer, alias) else try writer.writeAll("null");
+        },
+        .@"export" => |declaration| {
+            try writeJsonField(writer, "declaration");
+            try writeJsonNode(writer, declaration);
+        },
+        .block => |block| {
+            try writeJsonField(writer, "statements");
+            try writeJsonNodes(writer, block.statements);
+        },
+        .@"if" => |conditional| {
+            try writeJsonField(writer, "condition");
+            try writeJsonNode(writer, conditional.condition);
+            try writeJsonField(writer, "then");
+            try writeJsonNode(writer, conditional.then_block);
+            try writeJsonField(writer, "else");
+            try writeJsonOptional(writer, conditional.else_block);
+        },
+        .@"while" => |loop| {
+            try writeJsonField(writer, "condition");
+            try writeJsonNode(writer, loop.condition);
+            try writeJsonField(writer, "body");
+            try writeJsonNode(writer, loop.body);
+        },
+        .@"for" => |loop| {
+            try writeJsonField(writer, "variable");
+            try writeJsonString(writer, loop.variable);
+            try writeJsonField(writer, "iterable");
+            try writeJsonNode(writer, loop.iterable);
+            try writeJsonField(writer, "body");
+            try writeJsonNode(writer, loop.body);
+        },
+        .match => |match| {
+            try writeJsonField(writer, "subject");
+            try writeJsonNode(writer, match.subject);
+            try writeJsonField(writer, "arms");
+            try writer.writeByte('[');
+            for (match.arms, 0..) |arm, i| {
+                if (i > 0) try writer.writeByte(',');
+                try writer.writeAll("{\"pattern\":");
+                try writeJsonPattern(writer, arm.pattern);
+                try writeJsonField(writer, "guard");
+                try writeJsonOptional(writer, arm.guard);
+                try writeJsonField(writer, "body");
+                try writeJsonNode(writer, arm.body);
+                try writer.writeByte('}');
+            }
+            try writer.writeByte(']');
+        },
+        .@"return", .@"break" => |value| {
+            try writeJsonField(writer, "value");
+            try writeJsonOptional(writer, value);
+        },
+        .@"continue" => {},
+        .@"defer", .@"unsafe", .@"comptime" => |body| {
+            try writeJsonField(writer, "body");
+            try writeJsonNode(writer, body);
+        },
+        .label, .goto => |name| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, name);
+        },
+        .assignment => |assignment| {
+            try writeJsonField(writer, "lhs");
+            try writeJsonNode(writer, assignment.lhs);
+            try writeJsonField(writer, "rhs");
+            try writeJsonNode(writer, assignment.rhs);
+        },
+        .prefix => |prefix| {
+            try writer.print(",\"operator\":\"{s}\"", .{prefix.operator.toStr()});
+            try writeJsonField(writer, "operand");
+            try writeJsonNode(writer, prefix.operand);
+        },
+        .infix => |infix| {
+            try writer.print(",\"operator\":\"{s}\"", .{infix.operator.toStr()});
+            try writeJsonField(writer, "lhs");
+            try writeJsonNode(writer, infix.lhs);
+            try writeJsonField(writer, "rhs");
+            try writeJsonNode(writer, infix.rhs);
+        },
+        .fn_call => |call| {
+            try writeJsonField(writer, "callee");
+            try writeJsonNode(writer, call.callee);
+            try writeJsonField(writer, "arguments");
+            try writeJsonNodes(writer, call.arguments);
+        },
+        .struct_literal => |literal| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, literal.name);
+            try writeJsonField(writer, "fields");
+            try writeJsonFieldValues(writer, literal.fields);
+        },
+        .variant_literal => |literal| {
+            try writeJsonField(writer, "enum");
+            try writeJsonString(writer, literal.@"enum");
+            try writeJsonField(writer, "variant");
+            try writeJsonString(writer, literal.variant);
+            try writer.print(",\"qualified\":{}", .{literal.qualified});
+            try writer.print(",\"style\":\"{s}\"", .{@tagName(literal.fields)});
+            try writeJsonField(writer, "fields");
+            switch (literal.fields) {
+                .unit => try writer.writeAll("[]"),
+                .tuple => |values| try writeJsonNodes(writer, values),
+                .@"struct" => |fields| try writeJsonFieldValues(writer, fields)
             }
         },
-        .identifier => {
-            switch (node.token.kind) {
-                .identifier => |identifier| {
-                    try writer.writeAll(identifier.items);
+        .field_access => |access| {
+            try writeJsonField(writer, "object");
+            try writeJsonNode(writer, access.object);
+            try writeJsonField(writer, "field");
+            try writeJsonString(writer, access.field);
+        },
+        .array_literal => |elements| {
+            try writeJsonField(writer, "elements");
+            try writeJsonNodes(writer, elements);
+        },
+        .index => |index| {
+            try writeJsonField(writer, "object");
+            try writeJsonNode(writer, index.object);
+            try writeJsonField(writer, "index");
+            try writeJsonNode(writer, index.index);
+        },
+        .tuple_literal => |elements| {
+            try writeJsonField(writer, "elements");
+            try writeJsonNodes(writer, elements);
+        },
+        .tuple_index => |access| {
+            try writeJsonField(writer, "object");
+            try writeJsonNode(writer, access.object);
+            try writer.print(",\"index\":{}", .{access.index});
+        },
+        .@"try", .reference, .dereference, .@"await" => |operand| {
+            try writeJsonField(writer, "operand");
+            try writeJsonNode(writer, operand);
+        },
+        .sizeof => |annotation| {
+            try writeJsonField(writer, "type");
+            try writeJsonNode(writer, annotation);
+        },
+        .cast => |cast| {
+            try writeJsonField(writer, "value");
+            try writeJsonNode(writer, cast.value);
+            try writeJsonField(writer, "target");
+            try writeJsonNode(writer, cast.target);
+        },
+        .closure => |closure| {
+            try writeJsonField(writer, "params");
+            try writeJsonParams(writer, closure.params);
+            try writeJsonField(writer, "return_type");
+            try writeJsonOptional(writer, closure.return_type);
+            try writeJsonField(writer, "captures");
+            try writeJsonStrings(writer, closure.captures);
+            try writeJsonField(writer, "body");
+            try writeJsonNode(writer, closure.body);
+        },
+        .identifier => |identifier| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, identifier);
+        },
+        .literal => |literal| try writeJsonLiteral(writer, literal),
+        .type_expr => |type_expr| {
+            try writer.print(",\"type\":\"{s}\"", .{@tagName(type_expr)});
+            switch (type_expr) {
+                .generic => |generic| {
+                    try writeJsonField(writer, "name");
+                    try writeJsonString(writer, generic.name);
+                    try writeJsonField(writer, "arguments");
+                    try writeJsonNodes(writer, generic.arguments);
+                },
+                .array => |array| {
+                    try writeJsonField(writer, "element");
+                    try writeJsonNode(writer, array.element);
+                    try writeJsonField(writer, "len");
+                    try writeJsonNode(writer, array.len);
+                },
+                .slice, .reference => |element| {
+                    try writeJsonField(writer, "element");
+                    try writeJsonNode(writer, element);
+                },
+                .impl => |trait| {
+                    try writeJsonField(writer, "trait");
+                    try writeJsonString(writer, trait);
+                },
+                .tuple => |elements| {
+                    try writeJsonField(writer, "elements");
+                    try writeJsonNodes(writer, elements);
+                },
+                .function => |function| {
+                    try writeJsonField(writer, "params");
+                    try writeJsonNodes(writer, function.params);
+                    try writeJsonField(writer, "return_type");
+                    try writeJsonOptional(writer, function.return_type);
                 },
-                else => unreachable
+                .variadic => {}
             }
+        }
+    }
+
+    try writer.writeByte('}');
+}
+
+fn writeJsonLiteral(writer: anytype, literal: Node.Literal) @TypeOf(writer).Error!void {
+    try writer.print(",\"literal\":\"{s}\",\"value\":", .{@tagName(literal)});
+    switch (literal) {
+        .integer => |integer| try writer.print("{}", .{integer}),
+        // Json has no representation of infinities or nan
+        .float => |float| if (std.math.isFinite(float)) try writeFloat(writer, float)
+            else try writer.writeAll("null"),
+        .boolean => |boolean| try writer.print("{}", .{boolean}),
+        .string => |string| try writeJsonString(writer, string),
+        .character => |character| try writeJsonString(writer, &.{character}),
+        .unit => try writer.writeAll("null")
+    }
+}
+
+fn writeJsonPatterns(writer: anytype, patterns: []const Node.Pattern) @TypeOf(writer).Error!void {
+    try writer.writeByte('[');
+    for (patterns, 0..) |pattern, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writeJsonPattern(writer, pattern);
+    }
+    try writer.writeByte(']');
+}
+
+fn writeJsonFieldPatterns(writer: anytype, fields: []const Node.Pattern.StructPattern.Field) @TypeOf(writer).Error!void {
+    try writer.writeByte('[');
+    for (fields, 0..) |field, i| {
+        if (i > 0) try writer.writeByte(',');
+        try writer.writeAll("{\"name\":");
+        try writeJsonString(writer, field.name);
+        try writeJsonField(writer, "pattern");
+        try writeJsonPattern(writer, field.pattern);
+        try writer.writeByte('}');
+    }
+    try writer.writeByte(']');
+}
+
+fn writeJsonPattern(writer: anytype, pattern: Node.Pattern) @TypeOf(writer).Error!void {
+    try writer.print("{{\"kind\":\"{s}\"", .{@tagName(pattern.kind)});
+    try writeJsonField(writer, "span");
+    try writeJsonSpan(writer, pattern.span);
+
+    switch (pattern.kind) {
+        .literal => |literal| try writeJsonLiteral(writer, literal),
+        .identifier => |name| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, name);
+        },
+        .wildcard => {},
+        .@"struct" => |structure| {
+            try writeJsonField(writer, "name");
+            try writeJsonString(writer, structure.name);
+            try writeJsonField(writer, "fields");
+            try writeJsonFieldPatterns(writer, structure.fields);
         },
-        else => return
+        .variant => |variant| {
+            try writeJsonField(writer, "path");
+            try writer.writeByte('[');
+            for (variant.path, 0..) |segment, i| {
+                if (i > 0) try writer.writeByte(',');
+                try writeJsonString(writer, segment);
+            }
+            try writer.writeByte(']');
+            try writer.print(",\"style\":\"{s}\"", .{@tagName(variant.fields)});
+            try writeJsonField(writer, "fields");
+            switch (variant.fields) {
+                .unit => try writer.writeAll("[]"),
+                .tuple => |fields| try writeJsonPatterns(writer, fields),
+                .@"struct" => |fields| try writeJsonFieldPatterns(writer, fields)
+            }
+        },
+        .tuple => |elements| {
+            try writeJsonField(writer, "elements");
+            try writeJsonPatterns(writer, elements);
+        },
+        .@"or" => |alternatives| {
+            try writeJsonField(writer, "alternatives");
+            try writeJsonPatterns(writer, alternatives);
+        }
     }
+
+    try writer.writeByte('}');
 }
 
 test "ast" {
@@ -162,26 +2309,182 @@ test "ast" {
 
 const tests = struct {
     const testing = std.testing;
+    const AstBuilder = ruka.AstBuilder;
 
-    test "ast initialization and deinitialization" {
-        const allocator = std.testing.allocator;
-        var program = try Ast.init(allocator);
+    test "ast initialization and writing" {
+        var program = try Ast.init(testing.allocator, "test source");
         defer program.deinit();
 
-        const keyword: Token = .init(.{ .keyword = .@"if" }, "test", .init(0, 0));
-        const condition: Token = .init(try .initIdentifier("x", allocator), "test", .init(0, 0));
-        const consequence: Token = .init(try .initInteger("12", allocator), "test", .init(0, 0));
-        const alternative: Token = .init(try .initInteger("13", allocator), "test", .init(0, 0));
-
-        const root = try program.initRoot(.@"if", keyword);
-        _ = try root.addLeft(.identifier, condition);
-        const c = try root.addRight(.integer, consequence);
-        _ = try c.addRight(.integer, alternative);
+        const b = AstBuilder.init(program);
+        try b.module(&.{try b.conditional(
+            try b.identifier("x"),
+            try b.block(&.{try b.integer(12)}),
+            try b.block(&.{try b.integer(13)})
+        )});
 
         var buf: [4096]u8 = undefined;
         var stream = std.io.fixedBufferStream(&buf);
         try program.write(stream.writer().any());
 
-        std.debug.print("{s}\n", .{buf[0..stream.pos]});
+        try testing.expectEqualStrings(
+            \\if x {
+            \\    12
+            \\} else {
+            \\    13
+            \\}
+            \\
+        , buf[0..stream.pos]);
+    }
+
+    test "the innermost node containing an offset is found" {
+        var program = try Ast.init(testing.allocator, "test source");
+        defer program.deinit();
+
+        // `-x`
+        const operand = try program.createNode(.{ .identifier = "x" }, .init(program.file, 1, 2, .init(1, 2)));
+        const negation = try program.createNode(.{ .prefix = .{
+            .operator = .negate,
+            .operand = operand
+        }}, .init(program.file, 0, 2, .init(1, 1)));
+
+        program.root.kind.module.items = try program.dupe(*Node, &.{negation});
+
+        try testing.expectEqual(negation, program.nodeAt(program.file, 0).?);
+        try testing.expectEqual(operand, program.nodeAt(program.file, 1).?);
+        try testing.expectEqual(null, program.nodeAt(program.file, 2));
+        try testing.expectEqual(null, program.nodeAt("other source", 1));
+    }
+
+    test "adopting asts" {
+        var program = try Ast.init(testing.allocator, "main");
+        defer program.deinit();
+
+        var first = try Ast.init(testing.allocator, "first.ruka");
+        var second = try Ast.init(testing.allocator, "second.ruka");
+        {
+            errdefer first.deinit();
+            errdefer second.deinit();
+
+            first.root.kind.module.items = try first.dupe(*Node, &.{
+                try first.createNode(.{ .identifier = "a" }, .{ .file = first.file })
+            });
+            second.root.kind.module.items = try second.dupe(*Node, &.{
+                try second.createNode(.{ .prefix = .{
+                    .operator = .negate,
+                    .operand = try second.createNode(.{ .identifier = "b" }, .{ .file = second.file })
+                }}, .{ .file = second.file })
+            });
+        }
+
+        program.adopt(first) catch |err| {
+            first.deinit();
+            second.deinit();
+            return err;
+        };
+        program.adopt(second) catch |err| {
+            second.deinit();
+            return err;
+        };
+
+        const items = program.root.kind.module.items;
+        try testing.expectEqual(2, items.len);
+        try testing.expectEqualStrings("a", items[0].kind.identifier);
+        try testing.expectEqualStrings("second.ruka", items[1].span.file);
+
+        // The root is 0, the adopted nodes follow in the order they were visited
+        try testing.expectEqual(1, items[0].id);
+        try testing.expectEqual(2, items[1].id);
+        try testing.expectEqual(3, items[1].kind.prefix.operand.id);
+        try testing.expectEqual(4, program.node_count);
+    }
+
+    test "tree formatting" {
+        var program = try Ast.init(testing.allocator, "test source");
+        defer program.deinit();
+
+        const b = AstBuilder.init(program);
+        try b.module(&.{try b.binding("x", true, try b.identifier("f64"), try b.prefix(.negate, try b.float(2)))});
+
+        var buf: [4096]u8 = undefined;
+        try testing.expectEqualStrings(
+            \\module test source
+            \\  var_decl x mutable
+            \\    type
+            \\      identifier f64
+            \\    prefix -
+            \\      float 2.0
+            \\
+        , try std.fmt.bufPrint(&buf, "{}", .{program}));
+    }
+
+    test "json writing" {
+        var program = try Ast.init(testing.allocator, "test source");
+        defer program.deinit();
+
+        const b = AstBuilder.init(program);
+        try b.module(&.{try b.constant("s", null, try b.infix(.concat, try b.string("a\"b"), try b.float(2)))});
+
+        var out = std.ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+        try program.writeJson(out.writer());
+
+        const parsed = try std.json.parseFromSlice(std.json.Value, testing.allocator, out.items, .{});
+        defer parsed.deinit();
+
+        const module = parsed.value.object;
+        try testing.expectEqualStrings("module", module.get("kind").?.string);
+
+        const item = module.get("items").?.array.items[0].object;
+        try testing.expectEqualStrings("const_decl", item.get("kind").?.string);
+        try testing.expectEqual(.null, std.meta.activeTag(item.get("type").?));
+
+        const value = item.get("value").?.object;
+        try testing.expectEqualStrings("<>", value.get("operator").?.string);
+        try testing.expectEqualStrings("a\"b", value.get("lhs").?.object.get("value").?.string);
+        try testing.expectEqual(2.0, value.get("rhs").?.object.get("value").?.float);
+    }
+
+    test "indented json writing" {
+        var program = try Ast.init(testing.allocator, "test.ruka");
+        defer program.deinit();
+
+        program.root.kind.module.items = try program.dupe(*Node, &.{
+            try program.createNode(.{ .literal = .{ .string = "{a, b}" } }, .init(program.file, 4, 12, .init(1, 5)))
+        });
+
+        var out = std.ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+        try program.writeJsonIndented(testing.allocator, out.writer());
+
+        try testing.expectEqualStrings(
+            \\{
+            \\    "kind": "module",
+            \\    "id": 0,
+            \\    "span": {
+            \\        "file": "test.ruka",
+            \\        "start": 0,
+            \\        "end": 0,
+            \\        "line": 0,
+            \\        "column": 0
+            \\    },
+            \\    "name": "test.ruka",
+            \\    "items": [
+            \\        {
+            \\            "kind": "literal",
+            \\            "id": 1,
+            \\            "span": {
+            \\                "file": "test.ruka",
+            \\                "start": 4,
+            \\                "end": 12,
+            \\                "line": 1,
+            \\                "column": 5
+            \\            },
+            \\            "literal": "string",
+            \\            "value": "{a, b}"
+            \\        }
+            \\    ]
+            \\}
+            \\
+        , out.items);
     }
 };
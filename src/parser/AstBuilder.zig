@@ -0,0 +1,185 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Creates nodes in an ast from their parts, for tests and passes which build
+//! nodes rather than parse them. Every node is given the builder's span, which
+//! is empty unless set, and slices given are copied into the ast
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Span = ruka.Span;
+
+ast: *Ast,
+span: Span,
+
+const AstBuilder = @This();
+
+/// The name and annotation of a parameter of a function
+pub const Param = struct { []const u8, ?*Node };
+
+pub fn init(ast: *Ast) AstBuilder {
+    return .{
+        .ast = ast,
+        .span = .{ .file = ast.file }
+    };
+}
+
+pub fn node(self: AstBuilder, kind: Node.Kind) Allocator.Error!*Node {
+    return try self.ast.createNode(kind, self.span);
+}
+
+/// Makes items the items of the ast's module
+pub fn module(self: AstBuilder, items: []const *Node) Allocator.Error!void {
+    self.ast.root.kind.module.items = try self.ast.dupe(*Node, items);
+}
+
+pub fn identifier(self: AstBuilder, name: []const u8) Allocator.Error!*Node {
+    return try self.node(.{ .identifier = name });
+}
+
+pub fn integer(self: AstBuilder, value: i64) Allocator.Error!*Node {
+    return try self.node(.{ .literal = .{ .integer = value } });
+}
+
+pub fn float(self: AstBuilder, value: f64) Allocator.Error!*Node {
+    return try self.node(.{ .literal = .{ .float = value } });
+}
+
+pub fn boolean(self: AstBuilder, value: bool) Allocator.Error!*Node {
+    return try self.node(.{ .literal = .{ .boolean = value } });
+}
+
+pub fn string(self: AstBuilder, value: []const u8) Allocator.Error!*Node {
+    return try self.node(.{ .literal = .{ .string = value } });
+}
+
+pub fn prefix(self: AstBuilder, operator: Node.Prefix.Operator, operand: *Node) Allocator.Error!*Node {
+    return try self.node(.{ .prefix = .{
+        .operator = operator,
+        .operand = operand
+    }});
+}
+
+pub fn infix(self: AstBuilder, operator: Node.Infix.Operator, lhs: *Node, rhs: *Node) Allocator.Error!*Node {
+    return try self.node(.{ .infix = .{
+        .operator = operator,
+        .lhs = lhs,
+        .rhs = rhs
+    }});
+}
+
+pub fn block(self: AstBuilder, statements: []const *Node) Allocator.Error!*Node {
+    return try self.node(.{ .block = .{ .statements = try self.ast.dupe(*Node, statements) } });
+}
+
+/// A call of the function called name
+pub fn call(self: AstBuilder, name: []const u8, arguments: []const *Node) Allocator.Error!*Node {
+    return try self.node(.{ .fn_call = .{
+        .callee = try self.identifier(name),
+        .arguments = try self.ast.dupe(*Node, arguments)
+    }});
+}
+
+/// A let binding, or a var binding if mutable
+pub fn binding(self: AstBuilder, name: []const u8, mutable: bool, annotation: ?*Node, value: ?*Node) Allocator.Error!*Node {
+    return try self.node(.{ .var_decl = .{
+        .name = name,
+        .mutable = mutable,
+        .annotation = annotation,
+        .value = value
+    }});
+}
+
+pub fn constant(self: AstBuilder, name: []const u8, annotation: ?*Node, value: *Node) Allocator.Error!*Node {
+    return try self.node(.{ .const_decl = .{
+        .name = name,
+        .mutable = false,
+        .annotation = annotation,
+        .value = value
+    }});
+}
+
+/// An assignment to the variable called name
+pub fn assignment(self: AstBuilder, name: []const u8, value: *Node) Allocator.Error!*Node {
+    return try self.node(.{ .assignment = .{
+        .lhs = try self.identifier(name),
+        .rhs = value
+    }});
+}
+
+pub fn conditional(self: AstBuilder, condition: *Node, then_block: *Node, else_block: ?*Node) Allocator.Error!*Node {
+    return try self.node(.{ .@"if" = .{
+        .condition = condition,
+        .then_block = then_block,
+        .else_block = else_block
+    }});
+}
+
+pub fn function(
+    self: AstBuilder,
+    name: []const u8,
+    params: []const Param,
+    return_type: ?*Node,
+    body: *Node
+) Allocator.Error!*Node {
+    const parameters = try self.ast.arena.allocator().alloc(Node.Parameter, params.len);
+    for (parameters, params) |*parameter, param| parameter.* = .{
+        .name = param[0],
+        .annotation = param[1],
+        .span = self.span
+    };
+
+    return try self.node(.{ .fn_def = .{
+        .name = name,
+        .params = parameters,
+        .return_type = return_type,
+        .body = body
+    }});
+}
+
+test "ast builder" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "building a function" {
+        var program = try Ast.init(testing.allocator, "test source");
+        defer program.deinit();
+
+        const b = AstBuilder.init(program);
+
+        try b.module(&.{
+            try b.function("double", &.{.{"x", try b.identifier("i64")}}, try b.identifier("i64"), try b.block(&.{
+                try b.binding("y", true, null, try b.infix(.multiply, try b.identifier("x"), try b.integer(2))),
+                try b.conditional(try b.boolean(true), try b.block(&.{try b.assignment("y", try b.prefix(.negate, try b.identifier("y")))}), null),
+                try b.identifier("y")
+            })),
+            try b.constant("z", null, try b.call("double", &.{try b.integer(21)}))
+        });
+
+        var out = std.ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+        try program.write(out.writer().any());
+
+        try testing.expectEqualStrings(
+            \\fn double(x: i64) -> i64 {
+            \\    var y = (x * 2)
+            \\    if true {
+            \\        y = -y
+            \\    }
+            \\    y
+            \\}
+            \\const z = double(21)
+            \\
+        , out.items);
+
+        // Every node shares the builder's span
+        try testing.expectEqualStrings(program.file, program.root.kind.module.items[1].span.file);
+    }
+};
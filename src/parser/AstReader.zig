@@ -0,0 +1,547 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Reads asts back from the json Ast.writeJson writes, so tools which transform
+//! the json can hand it back to the compiler. Nodes keep the ids and spans they
+//! were written with
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ObjectMap = std.json.ObjectMap;
+const Value = std.json.Value;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Span = ruka.Span;
+
+ast: *Ast,
+
+const AstReader = @This();
+
+pub const Error = Allocator.Error || error{InvalidAst};
+
+/// Reads an ast from json written by Ast.writeJson, compact or indented. Json
+/// which isn't an ast is an InvalidAst
+pub fn read(allocator: Allocator, json: []const u8) Error!*Ast {
+    const parsed = std.json.parseFromSlice(Value, allocator, json, .{}) catch |err| switch (err) {
+        error.OutOfMemory => return error.OutOfMemory,
+        else => return error.InvalidAst
+    };
+    defer parsed.deinit();
+
+    const root = try object(parsed.value);
+    const ast = try Ast.init(allocator, try rawString(root, "name"));
+    errdefer ast.deinit();
+
+    var reader: AstReader = .{ .ast = ast };
+    ast.root = try reader.node(root);
+    if (ast.root.kind != .module) return error.InvalidAst;
+
+    return ast;
+}
+
+fn node(self: *AstReader, fields: ObjectMap) Error!*Node {
+    const id = try integer(Node.Id, fields, "id");
+
+    const created = try self.ast.arena.allocator().create(Node);
+    created.* = .{
+        .id = id,
+        .kind = try self.kind(fields),
+        .span = try self.spanOf(fields),
+        .attributes = if (fields.contains("attributes"))
+            try self.list(Node.Attribute, fields, "attributes", attribute)
+            else &.{}
+    };
+
+    // Ids continue after the largest read, as they do after the last node created
+    self.ast.node_count = @max(self.ast.node_count, id + 1);
+    return created;
+}
+
+fn kind(self: *AstReader, fields: ObjectMap) Error!Node.Kind {
+    return switch (try tag(std.meta.Tag(Node.Kind), fields, "kind")) {
+        .module => .{ .module = .{
+            .name = try self.string(fields, "name"),
+            .items = try self.nodes(fields, "items")
+        }},
+        .fn_def => .{ .fn_def = .{
+            .name = try self.string(fields, "name"),
+            .type_params = try self.strings(fields, "type_params"),
+            .params = try self.list(Node.Parameter, fields, "params", parameter),
+            .return_type = try self.optional(fields, "return_type"),
+            .body = try self.child(fields, "body"),
+            .@"async" = try boolean(fields, "async")
+        }},
+        .extern_fn => .{ .extern_fn = .{
+            .name = try self.string(fields, "name"),
+            .params = try self.list(Node.Parameter, fields, "params", parameter),
+            .variadic = try boolean(fields, "variadic"),
+            .return_type = try self.optional(fields, "return_type")
+        }},
+        .struct_def => .{ .struct_def = .{
+            .name = try self.string(fields, "name"),
+            .type_params = try self.strings(fields, "type_params"),
+            .fields = try self.list(Node.StructDef.Field, fields, "fields", fieldType)
+        }},
+        .union_def => .{ .union_def = .{
+            .name = try self.string(fields, "name"),
+            .fields = try self.list(Node.StructDef.Field, fields, "fields", fieldType)
+        }},
+        .enum_def => .{ .enum_def = .{
+            .name = try self.string(fields, "name"),
+            .variants = try self.list(Node.EnumDef.Variant, fields, "variants", variant)
+        }},
+        .impl => .{ .impl = .{
+            .name = try self.string(fields, "name"),
+            .functions = try self.nodes(fields, "functions"),
+            .trait = try self.optionalString(fields, "trait")
+        }},
+        .trait_def => .{ .trait_def = .{
+            .name = try self.string(fields, "name"),
+            .methods = try self.list(Node.TraitDef.Method, fields, "methods", method)
+        }},
+        .var_decl => .{ .var_decl = try self.binding(fields) },
+        .const_decl => .{ .const_decl = try self.binding(fields) },
+        .destructure => .{ .destructure = .{
+            .pattern = try self.pattern(try object(try field(fields, "pattern"))),
+            .annotation = try self.optional(fields, "type"),
+            .value = try self.child(fields, "value")
+        }},
+        .import => .{ .import = .{
+            .path = try self.strings(fields, "path"),
+            .alias = try self.optionalString(fields, "alias")
+        }},
+        .@"export" => .{ .@"export" = try self.child(fields, "declaration") },
+        .block => .{ .block = .{ .statements = try self.nodes(fields, "statements") } },
+        .@"if" => .{ .@"if" = .{
+            .condition = try self.child(fields, "condition"),
+            .then_block = try self.child(fields, "then"),
+            .else_block = try self.optional(fields, "else")
+        }},
+        .@"while" => .{ .@"while" = .{
+            .condition = try self.child(fields, "condition"),
+            .body = try self.child(fields, "body")
+        }},
+        .@"for" => .{ .@"for" = .{
+            .variable = try self.string(fields, "variable"),
+            .iterable = try self.child(fields, "iterable"),
+            .body = try self.child(fields, "body")
+        }},
+        .match => .{ .match = .{
+            .subject = try self.child(fields, "subject"),
+            .arms = try self.list(Node.Match.Arm, fields, "arms", arm)
+        }},
+        .@"return" => .{ .@"return" = try self.optional(fields, "value") },
+        .@"break" => .{ .@"break" = try self.optional(fields, "value") },
+        .@"continue" => .@"continue",
+        .@"defer" => .{ .@"defer" = try self.child(fields, "body") },
+        .@"unsafe" => .{ .@"unsafe" = try self.child(fields, "body") },
+        .@"comptime" => .{ .@"comptime" = try self.child(fields, "body") },
+        .label => .{ .label = try self.string(fields, "name") },
+        .goto => .{ .goto = try self.string(fields, "name") },
+        .assignment => .{ .assignment = .{
+            .lhs = try self.child(fields, "lhs"),
+            .rhs = try self.child(fields, "rhs")
+        }},
+        .prefix => .{ .prefix = .{
+            .operator = try operator(Node.Prefix.Operator, fields),
+            .operand = try self.child(fields, "operand")
+        }},
+        .infix => .{ .infix = .{
+            .operator = try operator(Node.Infix.Operator, fields),
+            .lhs = try self.child(fields, "lhs"),
+            .rhs = try self.child(fields, "rhs")
+        }},
+        .fn_call => .{ .fn_call = .{
+            .callee = try self.child(fields, "callee"),
+            .arguments = try self.nodes(fields, "arguments")
+        }},
+        .struct_literal => .{ .struct_literal = .{
+            .name = try self.string(fields, "name"),
+            .fields = try self.list(Node.StructLiteral.Field, fields, "fields", fieldValue)
+        }},
+        .variant_literal => .{ .variant_literal = .{
+            .@"enum" = try self.string(fields, "enum"),
+            .variant = try self.string(fields, "variant"),
+            .qualified = try boolean(fields, "qualified"),
+            .fields = switch (try tag(std.meta.Tag(Node.VariantLiteral.Fields), fields, "style")) {
+                .unit => .unit,
+                .tuple => .{ .tuple = try self.nodes(fields, "fields") },
+                .@"struct" => .{ .@"struct" = try self.list(Node.StructLiteral.Field, fields, "fields", fieldValue) }
+            }
+        }},
+        .field_access => .{ .field_access = .{
+            .object = try self.child(fields, "object"),
+            .field = try self.string(fields, "field")
+        }},
+        .array_literal => .{ .array_literal = try self.nodes(fields, "elements") },
+        .index => .{ .index = .{
+            .object = try self.child(fields, "object"),
+            .index = try self.child(fields, "index")
+        }},
+        .tuple_literal => .{ .tuple_literal = try self.nodes(fields, "elements") },
+        .tuple_index => .{ .tuple_index = .{
+            .object = try self.child(fields, "object"),
+            .index = try integer(usize, fields, "index")
+        }},
+        .@"try" => .{ .@"try" = try self.child(fields, "operand") },
+        .reference => .{ .reference = try self.child(fields, "operand") },
+        .dereference => .{ .dereference = try self.child(fields, "operand") },
+        .@"await" => .{ .@"await" = try self.child(fields, "operand") },
+        .sizeof => .{ .sizeof = try self.child(fields, "type") },
+        .cast => .{ .cast = .{
+            .value = try self.child(fields, "value"),
+            .target = try self.child(fields, "target")
+        }},
+        .closure => .{ .closure = .{
+            .params = try self.list(Node.Parameter, fields, "params", parameter),
+            .return_type = try self.optional(fields, "return_type"),
+            .body = try self.child(fields, "body"),
+            .captures = try self.strings(fields, "captures")
+        }},
+        .identifier => .{ .identifier = try self.string(fields, "name") },
+        .literal => .{ .literal = try self.literal(fields) },
+        .type_expr => .{ .type_expr = switch (try tag(std.meta.Tag(Node.TypeExpr), fields, "type")) {
+            .generic => .{ .generic = .{
+                .name = try self.string(fields, "name"),
+                .arguments = try self.nodes(fields, "arguments")
+            }},
+            .array => .{ .array = .{
+                .element = try self.child(fields, "element"),
+                .len = try self.child(fields, "len")
+            }},
+            .slice => .{ .slice = try self.child(fields, "element") },
+            .tuple => .{ .tuple = try self.nodes(fields, "elements") },
+            .function => .{ .function = .{
+                .params = try self.nodes(fields, "params"),
+                .return_type = try self.optional(fields, "return_type")
+            }},
+            .reference => .{ .reference = try self.child(fields, "element") },
+            .impl => .{ .impl = try self.string(fields, "trait") },
+            .variadic => .variadic
+        }}
+    };
+}
+
+fn binding(self: *AstReader, fields: ObjectMap) Error!Node.Binding {
+    return .{
+        .name = try self.string(fields, "name"),
+        .mutable = try boolean(fields, "mutable"),
+        .annotation = try self.optional(fields, "type"),
+        .value = try self.optional(fields, "value")
+    };
+}
+
+fn literal(self: *AstReader, fields: ObjectMap) Error!Node.Literal {
+    const value = try field(fields, "value");
+
+    return switch (try tag(std.meta.Tag(Node.Literal), fields, "literal")) {
+        .integer => .{ .integer = if (value == .integer) value.integer else return error.InvalidAst },
+        .float => .{ .float = switch (value) {
+            .float => |float| float,
+            .integer => |int| @floatFromInt(int),
+            // Infinities and nan are written as null, so can't be told apart
+            .null => std.math.nan(f64),
+            else => return error.InvalidAst
+        }},
+        .boolean => .{ .boolean = if (value == .bool) value.bool else return error.InvalidAst },
+        .string => .{ .string = try self.string(fields, "value") },
+        .character => .{ .character = if (value == .string and value.string.len == 1) value.string[0]
+            else return error.InvalidAst },
+        .unit => .unit
+    };
+}
+
+fn pattern(self: *AstReader, fields: ObjectMap) Error!Node.Pattern {
+    return .{
+        .kind = switch (try tag(std.meta.Tag(Node.Pattern.Kind), fields, "kind")) {
+            .literal => .{ .literal = try self.literal(fields) },
+            .identifier => .{ .identifier = try self.string(fields, "name") },
+            .wildcard => .wildcard,
+            .@"struct" => .{ .@"struct" = .{
+                .name = try self.string(fields, "name"),
+                .fields = try self.list(Node.Pattern.StructPattern.Field, fields, "fields", fieldPattern)
+            }},
+            .variant => .{ .variant = .{
+                .path = try self.strings(fields, "path"),
+                .fields = switch (try tag(std.meta.Tag(Node.Pattern.VariantPattern.Fields), fields, "style")) {
+                    .unit => .unit,
+                    .tuple => .{ .tuple = try self.list(Node.Pattern, fields, "fields", pattern) },
+                    .@"struct" => .{ .@"struct" = try self.list(Node.Pattern.StructPattern.Field, fields, "fields", fieldPattern) }
+                }
+            }},
+            .tuple => .{ .tuple = try self.list(Node.Pattern, fields, "elements", pattern) },
+            .@"or" => .{ .@"or" = try self.list(Node.Pattern, fields, "alternatives", pattern) }
+        },
+        .span = try self.spanOf(fields)
+    };
+}
+
+fn attribute(self: *AstReader, fields: ObjectMap) Error!Node.Attribute {
+    return .{
+        .name = try self.string(fields, "name"),
+        .args = try self.strings(fields, "args"),
+        .span = try self.spanOf(fields)
+    };
+}
+
+fn parameter(self: *AstReader, fields: ObjectMap) Error!Node.Parameter {
+    return .{
+        .name = try self.string(fields, "name"),
+        .annotation = try self.optional(fields, "type"),
+        .span = try self.spanOf(fields),
+        .shorthand = try boolean(fields, "shorthand")
+    };
+}
+
+fn fieldType(self: *AstReader, fields: ObjectMap) Error!Node.StructDef.Field {
+    return .{
+        .name = try self.string(fields, "name"),
+        .annotation = try self.child(fields, "type"),
+        .span = try self.spanOf(fields)
+    };
+}
+
+fn fieldValue(self: *AstReader, fields: ObjectMap) Error!Node.StructLiteral.Field {
+    return .{
+        .name = try self.string(fields, "name"),
+        .value = try self.child(fields, "value"),
+        .span = try self.spanOf(fields)
+    };
+}
+
+fn fieldPattern(self: *AstReader, fields: ObjectMap) Error!Node.Pattern.StructPattern.Field {
+    return .{
+        .name = try self.string(fields, "name"),
+        .pattern = try self.pattern(try object(try field(fields, "pattern")))
+    };
+}
+
+fn variant(self: *AstReader, fields: ObjectMap) Error!Node.EnumDef.Variant {
+    return .{
+        .name = try self.string(fields, "name"),
+        .fields = switch (try tag(std.meta.Tag(Node.EnumDef.Variant.Fields), fields, "style")) {
+            .unit => .unit,
+            .tuple => .{ .tuple = try self.nodes(fields, "fields") },
+            .@"struct" => .{ .@"struct" = try self.list(Node.StructDef.Field, fields, "fields", fieldType) }
+        },
+        .span = try self.spanOf(fields)
+    };
+}
+
+fn method(self: *AstReader, fields: ObjectMap) Error!Node.TraitDef.Method {
+    return .{
+        .name = try self.string(fields, "name"),
+        .params = try self.list(Node.Parameter, fields, "params", parameter),
+        .return_type = try self.optional(fields, "return_type"),
+        .default = try self.optional(fields, "default"),
+        .span = try self.spanOf(fields)
+    };
+}
+
+fn arm(self: *AstReader, fields: ObjectMap) Error!Node.Match.Arm {
+    return .{
+        .pattern = try self.pattern(try object(try field(fields, "pattern"))),
+        .guard = try self.optional(fields, "guard"),
+        .body = try self.child(fields, "body")
+    };
+}
+
+// Spans of the ast's own file share its name, as those the parser creates do
+fn spanOf(self: *AstReader, fields: ObjectMap) Error!Span {
+    const span = try object(try field(fields, "span"));
+    const file = try rawString(span, "file");
+
+    return .init(
+        if (std.mem.eql(u8, file, self.ast.file)) self.ast.file else try self.dupe(file),
+        try integer(usize, span, "start"),
+        try integer(usize, span, "end"),
+        .init(try integer(usize, span, "line"), try integer(usize, span, "column"))
+    );
+}
+
+fn child(self: *AstReader, fields: ObjectMap, name: []const u8) Error!*Node {
+    return try self.node(try object(try field(fields, name)));
+}
+
+fn optional(self: *AstReader, fields: ObjectMap, name: []const u8) Error!?*Node {
+    const value = try field(fields, name);
+    return if (value == .null) null else try self.node(try object(value));
+}
+
+fn nodes(self: *AstReader, fields: ObjectMap, name: []const u8) Error![]*Node {
+    return try self.list(*Node, fields, name, node);
+}
+
+// Reads each object of the array named name with readOne
+fn list(
+    self: *AstReader,
+    comptime T: type,
+    fields: ObjectMap,
+    name: []const u8,
+    comptime readOne: fn (*AstReader, ObjectMap) Error!T
+) Error![]T {
+    const values = try array(fields, name);
+
+    const items = try self.ast.arena.allocator().alloc(T, values.len);
+    for (values, items) |value, *item| item.* = try readOne(self, try object(value));
+
+    return items;
+}
+
+fn strings(self: *AstReader, fields: ObjectMap, name: []const u8) Error![]const []const u8 {
+    const values = try array(fields, name);
+
+    const items = try self.ast.arena.allocator().alloc([]const u8, values.len);
+    for (values, items) |value, *item| {
+        item.* = if (value == .string) try self.dupe(value.string) else return error.InvalidAst;
+    }
+
+    return items;
+}
+
+// The parsed json is freed once read, so the strings kept are copied into the ast
+fn string(self: *AstReader, fields: ObjectMap, name: []const u8) Error![]const u8 {
+    return try self.dupe(try rawString(fields, name));
+}
+
+fn optionalString(self: *AstReader, fields: ObjectMap, name: []const u8) Error!?[]const u8 {
+    return switch (try field(fields, name)) {
+        .null => null,
+        .string => |str| try self.dupe(str),
+        else => error.InvalidAst
+    };
+}
+
+fn dupe(self: *AstReader, str: []const u8) Error![]const u8 {
+    return try self.ast.arena.allocator().dupe(u8, str);
+}
+
+fn operator(comptime Operator: type, fields: ObjectMap) Error!Operator {
+    const name = try rawString(fields, "operator");
+    for (std.enums.values(Operator)) |candidate| {
+        if (std.mem.eql(u8, candidate.toStr(), name)) return candidate;
+    }
+
+    return error.InvalidAst;
+}
+
+fn tag(comptime Tag: type, fields: ObjectMap, name: []const u8) Error!Tag {
+    return std.meta.stringToEnum(Tag, try rawString(fields, name)) orelse error.InvalidAst;
+}
+
+fn field(fields: ObjectMap, name: []const u8) Error!Value {
+    return fields.get(name) orelse error.InvalidAst;
+}
+
+fn object(value: Value) Error!ObjectMap {
+    return if (value == .object) value.object else error.InvalidAst;
+}
+
+fn array(fields: ObjectMap, name: []const u8) Error![]const Value {
+    const value = try field(fields, name);
+    return if (value == .array) value.array.items else error.InvalidAst;
+}
+
+fn rawString(fields: ObjectMap, name: []const u8) Error![]const u8 {
+    const value = try field(fields, name);
+    return if (value == .string) value.string else error.InvalidAst;
+}
+
+fn boolean(fields: ObjectMap, name: []const u8) Error!bool {
+    const value = try field(fields, name);
+    return if (value == .bool) value.bool else error.InvalidAst;
+}
+
+fn integer(comptime T: type, fields: ObjectMap, name: []const u8) Error!T {
+    const value = try field(fields, name);
+    if (value != .integer) return error.InvalidAst;
+
+    return std.math.cast(T, value.integer) orelse error.InvalidAst;
+}
+
+test "ast reader" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const Unit = ruka.Unit;
+
+    // Parses source, writes its ast as indented json then reads it back, expecting
+    // the same ast
+    fn expectRoundtrip(source: []const u8) !void {
+        var input = std.io.fixedBufferStream(source);
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        var json = std.ArrayList(u8).init(testing.allocator);
+        defer json.deinit();
+        try ast.writeJsonIndented(testing.allocator, json.writer());
+
+        const read_back = try read(testing.allocator, json.items);
+        defer read_back.deinit();
+
+        try testing.expectEqualStrings(ast.file, read_back.file);
+        try testing.expectEqualDeep(ast.root.*, read_back.root.*);
+    }
+
+    test "a program of several functions" {
+        try expectRoundtrip(
+            \\import std.math as m
+            \\
+            \\#[allow(dead_code)]
+            \\struct Pair<A, B> { first: A, second: B }
+            \\
+            \\enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Empty }
+            \\
+            \\impl Pair {
+            \\    fn swap(self: Pair<i64, i64>) -> (i64, i64) { (self.second, self.first) }
+            \\}
+            \\
+            \\fn area(shape: Shape) -> f64 {
+            \\    match shape {
+            \\        Shape::Circle(r) if r > 0.0 => 3.14 * r ** 2.0
+            \\        Shape::Rect { w, h: 1.0 } => w
+            \\        Shape::Empty | _ => 0.0
+            \\    }
+            \\}
+            \\
+            \\fn half(n: i64) -> Option<i64> { if n % 2 == 0 { Some(n / 2) } else { None } }
+            \\
+            \\fn apply(f: fn(i64) -> i64, xs: &[i64]) -> i64 {
+            \\    var total = 0
+            \\    for i in 0..=10 {
+            \\        if not (i < 3) { continue }
+            \\        total = total + f(i)
+            \\    }
+            \\    while true { break total }
+            \\    total
+            \\}
+            \\
+            \\pub fn main() -> i64 {
+            \\    const limit: i64 = -5
+            \\    let scale = fn(x: i64) -> i64 { x * limit }
+            \\    let text = "tab\t\"quoted\"\n" <> m.name
+            \\    let p = Pair { first: 'c', second: false }
+            \\    let s = Shape::Rect { w: 1.5e3, h: 2.0 }
+            \\    return apply(scale, numbers) |> half
+            \\}
+        );
+    }
+
+    test "malformed json" {
+        try testing.expectError(error.InvalidAst, read(testing.allocator, "{"));
+        try testing.expectError(error.InvalidAst, read(testing.allocator, "[]"));
+        try testing.expectError(error.InvalidAst, read(testing.allocator,
+            \\{"kind":"module","id":0,"name":"a","items":[]}
+        ));
+        try testing.expectError(error.InvalidAst, read(testing.allocator,
+            \\{"kind":"ship","id":0,"name":"a","items":[],"span":{"file":"a","start":0,"end":0,"line":0,"column":0}}
+        ));
+    }
+};
@@ -0,0 +1,281 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Finds the bindings each closure captures, those its body refers to which are
+//! declared in the functions and blocks enclosing it. Top level bindings are
+//! visible everywhere, so they are never captured
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Unit = ruka.Unit;
+const VisitorMut = ruka.VisitorMut;
+
+ast: *Ast,
+/// The names bound in the scopes enclosing the node being analyzed, innermost last
+bound: ArrayList([]const u8),
+/// The index of the first name in bound the node being analyzed can refer to, the
+/// names before it belong to the functions enclosing a nested function
+visible: usize,
+/// The number of scopes enclosing the node being analyzed, names bound outside of
+/// every scope are top level
+scopes: usize,
+/// The closures enclosing the node being analyzed, innermost last
+closures: ArrayList(Frame),
+
+allocator: Allocator,
+
+const CaptureAnalyzer = @This();
+
+const Walk = VisitorMut(CaptureAnalyzer, Allocator.Error, void);
+
+const Frame = struct {
+    closure: *Node.Closure,
+    /// The index in bound of the first name the closure binds itself
+    base: usize,
+    captures: ArrayList([]const u8)
+};
+
+pub fn init(unit: *Unit) !*CaptureAnalyzer {
+    const analyzer = try unit.allocator.create(CaptureAnalyzer);
+
+    analyzer.* = .{
+        .ast = undefined,
+        .bound = .init(unit.allocator),
+        .visible = 0,
+        .scopes = 0,
+        .closures = .init(unit.allocator),
+        .allocator = unit.allocator
+    };
+
+    return analyzer;
+}
+
+pub fn deinit(self: *CaptureAnalyzer) void {
+    self.bound.deinit();
+    for (self.closures.items) |frame| frame.captures.deinit();
+    self.closures.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Records the captures of every closure in ast
+pub fn analyze(self: *CaptureAnalyzer, ast: *Ast) Allocator.Error!void {
+    self.ast = ast;
+    try Walk.visit(self, ast.root);
+}
+
+fn bind(self: *CaptureAnalyzer, name: []const u8) Allocator.Error!void {
+    if (self.scopes > 0) try self.bound.append(name);
+}
+
+fn bindPattern(self: *CaptureAnalyzer, pattern: Node.Pattern) Allocator.Error!void {
+    switch (pattern.kind) {
+        .identifier => |name| try self.bind(name),
+        .literal, .wildcard => {},
+        .@"struct" => |structure| for (structure.fields) |field| try self.bindPattern(field.pattern),
+        .variant => |variant| switch (variant.fields) {
+            .unit => {},
+            .tuple => |fields| for (fields) |field| try self.bindPattern(field),
+            .@"struct" => |fields| for (fields) |field| try self.bindPattern(field.pattern)
+        },
+        .tuple, .@"or" => |patterns| for (patterns) |inner| try self.bindPattern(inner)
+    }
+}
+
+// Enters a scope, returning the mark exitScope discards the names bound in it to
+fn enterScope(self: *CaptureAnalyzer) usize {
+    self.scopes = self.scopes + 1;
+    return self.bound.items.len;
+}
+
+fn exitScope(self: *CaptureAnalyzer, mark: usize) void {
+    self.scopes = self.scopes - 1;
+    self.bound.shrinkRetainingCapacity(mark);
+}
+
+// The index in bound of the innermost visible binding of name
+fn find(self: *const CaptureAnalyzer, name: []const u8) ?usize {
+    var i = self.bound.items.len;
+    while (i > self.visible) {
+        i = i - 1;
+        if (std.mem.eql(u8, self.bound.items[i], name)) return i;
+    }
+
+    return null;
+}
+
+pub fn visitFnDef(self: *CaptureAnalyzer, _: *Node, fn_def: *Node.FunctionDef) Allocator.Error!void {
+    const mark = self.enterScope();
+    defer self.exitScope(mark);
+
+    // Functions can't refer to the bindings of the functions enclosing them
+    const visible = self.visible;
+    self.visible = mark;
+    defer self.visible = visible;
+
+    for (fn_def.params) |param| try self.bind(param.name);
+    try Walk.visit(self, fn_def.body);
+}
+
+pub fn visitClosure(self: *CaptureAnalyzer, _: *Node, closure: *Node.Closure) Allocator.Error!void {
+    const mark = self.enterScope();
+
+    try self.closures.append(.{
+        .closure = closure,
+        .base = mark,
+        .captures = .init(self.allocator)
+    });
+
+    for (closure.params) |param| try self.bind(param.name);
+    try Walk.visit(self, closure.body);
+
+    self.exitScope(mark);
+
+    const frame = self.closures.items[self.closures.items.len - 1];
+    self.closures.items.len = self.closures.items.len - 1;
+    defer frame.captures.deinit();
+
+    closure.captures = try self.ast.dupe([]const u8, frame.captures.items);
+}
+
+pub fn visitBlock(self: *CaptureAnalyzer, node: *Node, _: *Node.Block) Allocator.Error!void {
+    const mark = self.enterScope();
+    defer self.exitScope(mark);
+
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitVarDecl(self: *CaptureAnalyzer, _: *Node, binding: *Node.Binding) Allocator.Error!void {
+    try self.visitBinding(binding.*);
+}
+
+pub fn visitConstDecl(self: *CaptureAnalyzer, _: *Node, binding: *Node.Binding) Allocator.Error!void {
+    try self.visitBinding(binding.*);
+}
+
+// The value is evaluated before the name is bound, so it refers to any earlier
+// binding of the name
+fn visitBinding(self: *CaptureAnalyzer, binding: Node.Binding) Allocator.Error!void {
+    if (binding.value) |value| try Walk.visit(self, value);
+    try self.bind(binding.name);
+}
+
+pub fn visitDestructure(self: *CaptureAnalyzer, _: *Node, destructure: *Node.Destructure) Allocator.Error!void {
+    try Walk.visit(self, destructure.value);
+    try self.bindPattern(destructure.pattern);
+}
+
+pub fn visitFor(self: *CaptureAnalyzer, _: *Node, loop: *Node.For) Allocator.Error!void {
+    try Walk.visit(self, loop.iterable);
+
+    const mark = self.enterScope();
+    defer self.exitScope(mark);
+
+    try self.bind(loop.variable);
+    try Walk.visit(self, loop.body);
+}
+
+pub fn visitMatch(self: *CaptureAnalyzer, _: *Node, match: *Node.Match) Allocator.Error!void {
+    try Walk.visit(self, match.subject);
+
+    for (match.arms) |arm| {
+        const mark = self.enterScope();
+        defer self.exitScope(mark);
+
+        try self.bindPattern(arm.pattern);
+        if (arm.guard) |guard| try Walk.visit(self, guard);
+        try Walk.visit(self, arm.body);
+    }
+}
+
+// Every closure between the reference and the binding captures it, so the
+// innermost can capture it from the closure enclosing it
+pub fn visitIdentifier(self: *CaptureAnalyzer, _: *Node, name: *[]const u8) Allocator.Error!void {
+    const index = self.find(name.*) orelse return;
+
+    var i = self.closures.items.len;
+    while (i > 0) {
+        i = i - 1;
+
+        const frame = &self.closures.items[i];
+        if (frame.base <= index) break;
+
+        for (frame.captures.items) |capture| {
+            if (std.mem.eql(u8, capture, name.*)) break;
+        } else try frame.captures.append(name.*);
+    }
+}
+
+// Annotations name types, which are never captured
+pub fn visitTypeExpr(_: *CaptureAnalyzer, _: *Node, _: *Node.TypeExpr) Allocator.Error!void {}
+
+test "capture analyzer" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    // The closure a binding statement is bound to
+    fn closureOf(statement: *const Node) Node.Closure {
+        return statement.kind.var_decl.value.?.kind.closure;
+    }
+
+    fn expectCaptures(expected: []const []const u8, closure: Node.Closure) !void {
+        try testing.expectEqual(expected.len, closure.captures.len);
+        for (expected, closure.captures) |name, capture| try testing.expectEqualStrings(name, capture);
+    }
+
+    test "closures capture the bindings of enclosing scopes they refer to" {
+        const source =
+            \\let top = 1
+            \\fn f(a: i64) -> i64 {
+            \\    let b = 2
+            \\    let g = fn(c: i64) -> i64 {
+            \\        let d = c + b
+            \\        let h = fn() -> i64 { a + d + top }
+            \\        h() + b
+            \\    }
+            \\    let s = fn(b: i64) -> i64 { b * top }
+            \\    g(a) + s(a)
+            \\}
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        const body = ast.root.kind.module.items[1].kind.fn_def.body.kind.block.statements;
+
+        const g = closureOf(body[1]);
+        // a is captured by g so that h can capture it from g
+        try expectCaptures(&.{"b", "a"}, g);
+        try expectCaptures(&.{"a", "d"}, closureOf(g.body.kind.block.statements[1]));
+        // Parameters shadow the bindings they share a name with
+        try expectCaptures(&.{}, closureOf(body[2]));
+    }
+
+    test "top level closures capture nothing" {
+        const source =
+            \\let n = 1
+            \\let f = fn(x: i64) -> i64 { x + n }
+            \\fn g() -> i64 { f(n) }
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        try expectCaptures(&.{}, closureOf(ast.root.kind.module.items[1]));
+    }
+};
@@ -0,0 +1,436 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Writes asts in a compact binary form and reads them back, so caches can keep
+//! parsed asts between compilations. Integers are written as leb128, floats by
+//! their bits, and sequences and strings by their length then their elements.
+//! Each node is written field by field in the order Node declares them, so the
+//! form changes with Node and data written by other versions is rejected.
+//!
+//! Spans name their file once, later spans of the same file refer to it by index
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Span = ruka.Span;
+const constants = ruka.constants;
+
+pub const Error = Allocator.Error || error{InvalidAst};
+
+// Begins the data, so asts written by other versions are recognized
+const header = "ruka ast " ++ constants.version_str ++ "\n";
+
+/// Writes ast into memory owned by the caller
+pub fn serialize(allocator: Allocator, ast: *const Ast) Allocator.Error![]u8 {
+    var encoder = Encoder.init(allocator);
+    defer encoder.files.deinit();
+    errdefer encoder.bytes.deinit();
+
+    try encoder.bytes.appendSlice(header);
+    try encoder.value([]const u8, ast.file);
+    try encoder.value(Node.Id, ast.node_count);
+    try encoder.value(Node, ast.root.*);
+
+    return try encoder.bytes.toOwnedSlice();
+}
+
+/// Reads an ast written by serialize, owned by allocator. Data which isn't an
+/// ast written by this version of the compiler is an InvalidAst
+pub fn deserialize(allocator: Allocator, bytes: []const u8) Error!*Ast {
+    if (!std.mem.startsWith(u8, bytes, header)) return error.InvalidAst;
+
+    var decoder: Decoder = .{
+        .stream = std.io.fixedBufferStream(bytes[header.len..]),
+        .files = .init(allocator),
+        .arena = undefined
+    };
+    defer decoder.files.deinit();
+
+    // The file is read into a temporary arena first, as the ast is created by it
+    var scratch = std.heap.ArenaAllocator.init(allocator);
+    defer scratch.deinit();
+    decoder.arena = scratch.allocator();
+
+    const ast = try Ast.init(allocator, try decoder.value([]const u8));
+    errdefer ast.deinit();
+
+    decoder.arena = ast.arena.allocator();
+    ast.node_count = try decoder.value(Node.Id);
+
+    const root = try decoder.value(*Node);
+    if (root.kind != .module or decoder.stream.pos != decoder.stream.buffer.len) return error.InvalidAst;
+    ast.root = root;
+
+    return ast;
+}
+
+const Encoder = struct {
+    bytes: ArrayList(u8),
+    /// The index each file was given when a span first named it
+    files: StringHashMap(u32),
+
+    fn init(allocator: Allocator) Encoder {
+        return .{
+            .bytes = .init(allocator),
+            .files = .init(allocator)
+        };
+    }
+
+    fn value(self: *Encoder, comptime T: type, data: T) Allocator.Error!void {
+        const writer = self.bytes.writer();
+
+        if (T == Span) return try self.span(data);
+        switch (@typeInfo(T)) {
+            .void => {},
+            .bool => try writer.writeByte(@intFromBool(data)),
+            .int => |info| switch (info.signedness) {
+                .signed => try std.leb.writeIleb128(writer, data),
+                .unsigned => try std.leb.writeUleb128(writer, data)
+            },
+            .float => try writer.writeInt(u64, @bitCast(@as(f64, data)), .little),
+            .@"enum" => try std.leb.writeUleb128(writer, @intFromEnum(data)),
+            .optional => |info| if (data) |child| {
+                try writer.writeByte(1);
+                try self.value(info.child, child);
+            } else try writer.writeByte(0),
+            .pointer => |info| switch (info.size) {
+                .one => try self.value(info.child, data.*),
+                .slice => {
+                    try std.leb.writeUleb128(writer, data.len);
+                    if (info.child == u8) return try writer.writeAll(data);
+
+                    for (data) |element| try self.value(info.child, element);
+                },
+                else => @compileError("asts can't hold " ++ @typeName(T))
+            },
+            .@"struct" => |info| inline for (info.fields) |field| {
+                try self.value(field.type, @field(data, field.name));
+            },
+            .@"union" => |info| {
+                try self.value(info.tag_type.?, std.meta.activeTag(data));
+                switch (data) {
+                    inline else => |payload| try self.value(@TypeOf(payload), payload)
+                }
+            },
+            else => @compileError("asts can't hold " ++ @typeName(T))
+        }
+    }
+
+    fn span(self: *Encoder, data: Span) Allocator.Error!void {
+        const entry = try self.files.getOrPut(data.file);
+        if (!entry.found_existing) entry.value_ptr.* = @intCast(self.files.count() - 1);
+
+        try self.value(u32, entry.value_ptr.*);
+        if (!entry.found_existing) try self.value([]const u8, data.file);
+
+        try self.value(usize, data.start);
+        try self.value(usize, data.end);
+        try self.value(usize, data.pos.line);
+        try self.value(usize, data.pos.col);
+    }
+};
+
+const Decoder = struct {
+    stream: std.io.FixedBufferStream([]const u8),
+    /// The files named by spans so far, by their index
+    files: ArrayList([]const u8),
+    arena: Allocator,
+
+    fn value(self: *Decoder, comptime T: type) Error!T {
+        const reader = self.stream.reader();
+
+        if (T == Span) return try self.span();
+        switch (@typeInfo(T)) {
+            .void => return {},
+            .bool => return switch (try self.byte()) {
+                0 => false,
+                1 => true,
+                else => error.InvalidAst
+            },
+            .int => |info| switch (info.signedness) {
+                .signed => return std.leb.readIleb128(T, reader) catch error.InvalidAst,
+                .unsigned => return std.leb.readUleb128(T, reader) catch error.InvalidAst
+            },
+            .float => {
+                const bits = reader.readInt(u64, .little) catch return error.InvalidAst;
+                return @floatCast(@as(f64, @bitCast(bits)));
+            },
+            .@"enum" => |info| return std.meta.intToEnum(T, try self.value(info.tag_type)) catch error.InvalidAst,
+            .optional => |info| return switch (try self.byte()) {
+                0 => null,
+                1 => try self.value(info.child),
+                else => error.InvalidAst
+            },
+            .pointer => |info| switch (info.size) {
+                .one => {
+                    const pointee = try self.arena.create(info.child);
+                    pointee.* = try self.value(info.child);
+                    return pointee;
+                },
+                .slice => {
+                    const elements = try self.arena.alloc(info.child, try self.length());
+                    if (info.child == u8) {
+                        reader.readNoEof(elements) catch return error.InvalidAst;
+                        return elements;
+                    }
+
+                    for (elements) |*element| element.* = try self.value(info.child);
+                    return elements;
+                },
+                else => @compileError("asts can't hold " ++ @typeName(T))
+            },
+            .@"struct" => |info| {
+                var result: T = undefined;
+                inline for (info.fields) |field| @field(result, field.name) = try self.value(field.type);
+                return result;
+            },
+            .@"union" => |info| switch (try self.value(info.tag_type.?)) {
+                inline else => |tag| return @unionInit(T, @tagName(tag), try self.value(std.meta.TagPayload(T, tag)))
+            },
+            else => @compileError("asts can't hold " ++ @typeName(T))
+        }
+    }
+
+    fn byte(self: *Decoder) Error!u8 {
+        return self.stream.reader().readByte() catch error.InvalidAst;
+    }
+
+    // Reads the length of a sequence, which can't be longer than the rest of the
+    // data as each element is at least a byte
+    fn length(self: *Decoder) Error!usize {
+        const len = try self.value(usize);
+        if (len > self.stream.buffer.len - self.stream.pos) return error.InvalidAst;
+
+        return len;
+    }
+
+    fn span(self: *Decoder) Error!Span {
+        const index = try self.value(u32);
+        if (index == self.files.items.len) {
+            try self.files.append(try self.value([]const u8));
+        } else if (index > self.files.items.len) return error.InvalidAst;
+
+        return .{
+            .file = self.files.items[index],
+            .start = try self.value(usize),
+            .end = try self.value(usize),
+            .pos = .{
+                .line = try self.value(usize),
+                .col = try self.value(usize)
+            }
+        };
+    }
+};
+
+test "bincode" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const AstBuilder = ruka.AstBuilder;
+    const Unit = ruka.Unit;
+
+    fn expectRoundtrip(ast: *const Ast) !usize {
+        const bytes = try serialize(testing.allocator, ast);
+        defer testing.allocator.free(bytes);
+
+        const read_back = try deserialize(testing.allocator, bytes);
+        defer read_back.deinit();
+
+        try testing.expectEqualStrings(ast.file, read_back.file);
+        try testing.expectEqual(ast.node_count, read_back.node_count);
+        try testing.expectEqualDeep(ast.root.*, read_back.root.*);
+
+        return bytes.len;
+    }
+
+    test "parsed programs are read back unchanged" {
+        var input = std.io.fixedBufferStream(
+            \\import std.math as m
+            \\
+            \\#[allow(dead_code)]
+            \\struct Pair<A, B> { first: A, second: B }
+            \\
+            \\enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Empty }
+            \\
+            \\fn area(shape: Shape) -> f64 {
+            \\    match shape {
+            \\        Shape::Circle(r) if r > 0.0 => 3.14 * r ** 2.0
+            \\        Shape::Rect { w, h: 1.0 } => w
+            \\        (1, 'c') | _ => -0.5
+            \\    }
+            \\}
+            \\
+            \\pub fn main() -> i64 {
+            \\    const limit: i64 = -5
+            \\    let scale = fn(x: i64) -> i64 { x * limit }
+            \\    let xs: [i64; 3] = [1, 2, 3]
+            \\    let p = Pair { first: "text", second: false }
+            \\    defer free(&xs[0..2] as &i8)
+            \\    return scale(xs.0)
+            \\}
+        );
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        _ = try expectRoundtrip(ast);
+    }
+
+    test "asts of 500 nodes are written in under 50kb" {
+        var source = ArrayList(u8).init(testing.allocator);
+        defer source.deinit();
+
+        // Each binding is six nodes
+        for (0..84) |i| try source.writer().print("let value_{} = count_{} + {} * 2\n", .{i, i, i});
+
+        var input = std.io.fixedBufferStream(source.items);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        try testing.expect(ast.node_count >= 500);
+        try testing.expect(try expectRoundtrip(ast) < 50 * 1024);
+    }
+
+    // Builds random expressions of the node kinds below, with random spans
+    const Generator = struct {
+        builder: AstBuilder,
+        random: std.Random,
+
+        const names = [_][]const u8{"a", "b", "count", "x_1", ""};
+        const files = [_][]const u8{"main.ruka", "lib/math.ruka"};
+
+        fn expression(self: *Generator, depth: usize) Allocator.Error!*Node {
+            self.builder.span = .{
+                .file = files[self.random.uintLessThan(usize, files.len)],
+                .start = self.random.int(u16),
+                .end = self.random.int(u16),
+                .pos = .{ .line = self.random.int(u8), .col = self.random.int(u8) }
+            };
+
+            const choice = if (depth == 0) self.random.uintLessThan(u8, 2) else self.random.uintLessThan(u8, 8);
+            return switch (choice) {
+                0 => try self.builder.node(.{ .literal = self.literal() }),
+                1 => try self.builder.identifier(self.name()),
+                2 => try self.builder.prefix(self.random.enumValue(Node.Prefix.Operator), try self.expression(depth - 1)),
+                3 => try self.builder.infix(
+                    self.random.enumValue(Node.Infix.Operator),
+                    try self.expression(depth - 1),
+                    try self.expression(depth - 1)
+                ),
+                4 => try self.builder.block(try self.expressions(depth - 1)),
+                5 => try self.builder.conditional(
+                    try self.expression(depth - 1),
+                    try self.expression(depth - 1),
+                    if (self.random.boolean()) try self.expression(depth - 1) else null
+                ),
+                6 => try self.builder.node(.{ .type_expr = .{ .generic = .{
+                    .name = self.name(),
+                    .arguments = try self.expressions(depth - 1)
+                }}}),
+                else => try self.builder.node(.{ .match = .{
+                    .subject = try self.expression(depth - 1),
+                    .arms = try self.arms(depth - 1)
+                }})
+            };
+        }
+
+        fn expressions(self: *Generator, depth: usize) Allocator.Error![]*Node {
+            const nodes = try self.builder.ast.arena.allocator().alloc(*Node, self.random.uintLessThan(usize, 4));
+            for (nodes) |*node| node.* = try self.expression(depth);
+            return nodes;
+        }
+
+        fn arms(self: *Generator, depth: usize) Allocator.Error![]Node.Match.Arm {
+            const generated = try self.builder.ast.arena.allocator().alloc(Node.Match.Arm, self.random.uintLessThan(usize, 3));
+            for (generated) |*arm| arm.* = .{
+                .pattern = try self.pattern(depth),
+                .guard = if (self.random.boolean()) try self.expression(depth) else null,
+                .body = try self.expression(depth)
+            };
+            return generated;
+        }
+
+        fn pattern(self: *Generator, depth: usize) Allocator.Error!Node.Pattern {
+            const choice = if (depth == 0) self.random.uintLessThan(u8, 3) else self.random.uintLessThan(u8, 4);
+            return .{
+                .kind = switch (choice) {
+                    0 => .{ .literal = self.literal() },
+                    1 => .{ .identifier = self.name() },
+                    2 => .wildcard,
+                    else => blk: {
+                        const elements = try self.builder.ast.arena.allocator().alloc(Node.Pattern, self.random.uintLessThan(usize, 3));
+                        for (elements) |*element| element.* = try self.pattern(depth - 1);
+                        break :blk .{ .tuple = elements };
+                    }
+                },
+                .span = self.builder.span
+            };
+        }
+
+        fn literal(self: *Generator) Node.Literal {
+            return switch (self.random.uintLessThan(u8, 6)) {
+                0 => .{ .integer = self.random.int(i64) },
+                1 => .{ .float = self.random.float(f64) * 1e6 - 5e5 },
+                2 => .{ .boolean = self.random.boolean() },
+                3 => .{ .string = self.name() },
+                4 => .{ .character = self.random.int(u8) },
+                else => .unit
+            };
+        }
+
+        fn name(self: *Generator) []const u8 {
+            return names[self.random.uintLessThan(usize, names.len)];
+        }
+    };
+
+    test "random asts are read back unchanged" {
+        var prng = std.Random.DefaultPrng.init(0x5eed);
+
+        for (0..64) |_| {
+            const ast = try Ast.init(testing.allocator, "main.ruka");
+            defer ast.deinit();
+
+            var generator: Generator = .{
+                .builder = .init(ast),
+                .random = prng.random()
+            };
+            try generator.builder.module(try generator.expressions(4));
+
+            _ = try expectRoundtrip(ast);
+        }
+    }
+
+    test "data which isn't an ast" {
+        try testing.expectError(error.InvalidAst, deserialize(testing.allocator, ""));
+        try testing.expectError(error.InvalidAst, deserialize(testing.allocator, "ruka ast 0.0.0-old\n"));
+        try testing.expectError(error.InvalidAst, deserialize(testing.allocator, header ++ "\x09main.ruka\x01"));
+
+        const ast = try Ast.init(testing.allocator, "main.ruka");
+        defer ast.deinit();
+
+        const bytes = try serialize(testing.allocator, ast);
+        defer testing.allocator.free(bytes);
+
+        // Truncated, and with bytes following the ast
+        try testing.expectError(error.InvalidAst, deserialize(testing.allocator, bytes[0 .. bytes.len - 1]));
+
+        const extended = try std.mem.concat(testing.allocator, u8, &.{bytes, "\x00"});
+        defer testing.allocator.free(extended);
+        try testing.expectError(error.InvalidAst, deserialize(testing.allocator, extended));
+    }
+};
@@ -0,0 +1,390 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Dispatches the nodes of an ast to the methods of a pass, so passes don't each
+//! walk the node structure themselves.
+//!
+//! A pass declares a `pub fn` for each kind of node it handles, named after the kind,
+//! e.g. `visitInfix(self: *Pass, node: *const Node, infix: Node.Infix) Error!Result`.
+//! Kinds without a method have their children visited instead, which is only
+//! possible for passes whose result is void, other passes must handle every kind.
+//! A pass may also declare `visitNode`, which is given every node before it is
+//! dispatched and continues by calling `dispatch` itself
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+
+const ruka = @import("../prelude.zig");
+const Node = ruka.Node;
+
+/// Visits nodes read only, methods are given the node and a copy of its payload
+pub fn Visitor(comptime Context: type, comptime Error: type, comptime Result: type) type {
+    return Dispatcher(Context, Error, Result, false);
+}
+
+/// Visits nodes mutably, methods are given the node and a pointer to its payload
+pub fn VisitorMut(comptime Context: type, comptime Error: type, comptime Result: type) type {
+    return Dispatcher(Context, Error, Result, true);
+}
+
+fn Dispatcher(
+    comptime Context: type,
+    comptime Error: type,
+    comptime Result: type,
+    comptime mutable: bool
+) type {
+    return struct {
+        const NodePtr = if (mutable) *Node else *const Node;
+
+        /// Visits node with context's visitNode if it declares one, otherwise dispatches it
+        pub fn visit(context: *Context, node: NodePtr) Error!Result {
+            if (comptime @hasDecl(Context, "visitNode")) {
+                return try context.visitNode(node);
+            } else {
+                return try dispatch(context, node);
+            }
+        }
+
+        /// Calls the method of context for node's kind, or visits node's children if
+        /// context doesn't handle its kind
+        pub fn dispatch(context: *Context, node: NodePtr) Error!Result {
+            switch (node.kind) {
+                inline else => |*payload, tag| {
+                    const name = comptime methodName(tag);
+
+                    if (comptime @hasDecl(Context, name)) {
+                        return try @field(Context, name)(context, node, if (mutable) payload else payload.*);
+                    } else if (Result == void) {
+                        try visitChildren(context, node);
+                    } else {
+                        @compileError(@typeName(Context) ++ " must declare " ++ name ++ " as its result isn't void");
+                    }
+                }
+            }
+        }
+
+        /// Visits each of node's children in source order, discarding their results
+        pub fn visitChildren(context: *Context, node: NodePtr) Error!void {
+            switch (node.kind) {
+                .module => |module| for (module.items) |item| {
+                    _ = try visit(context, item);
+                },
+                .fn_def => |fn_def| {
+                    for (fn_def.params) |param| if (param.annotation) |annotation| {
+                        _ = try visit(context, annotation);
+                    };
+                    if (fn_def.return_type) |return_type| _ = try visit(context, return_type);
+                    _ = try visit(context, fn_def.body);
+                },
+                .extern_fn => |extern_fn| {
+                    for (extern_fn.params) |param| if (param.annotation) |annotation| {
+                        _ = try visit(context, annotation);
+                    };
+                    if (extern_fn.return_type) |return_type| _ = try visit(context, return_type);
+                },
+                .struct_def => |struct_def| for (struct_def.fields) |field| {
+                    _ = try visit(context, field.annotation);
+                },
+                .union_def => |union_def| for (union_def.fields) |field| {
+                    _ = try visit(context, field.annotation);
+                },
+                .enum_def => |enum_def| for (enum_def.variants) |variant| switch (variant.fields) {
+                    .unit => {},
+                    .tuple => |annotations| for (annotations) |annotation| {
+                        _ = try visit(context, annotation);
+                    },
+                    .@"struct" => |fields| for (fields) |field| {
+                        _ = try visit(context, field.annotation);
+                    }
+                },
+                .impl => |impl| for (impl.functions) |function| {
+                    _ = try visit(context, function);
+                },
+                // Methods with defaults are visited as the functions they define
+                .trait_def => |trait_def| for (trait_def.methods) |method| {
+                    if (method.default) |default| {
+                        _ = try visit(context, default);
+                        continue;
+                    }
+
+                    for (method.params) |param| if (param.annotation) |annotation| {
+                        _ = try visit(context, annotation);
+                    };
+                    if (method.return_type) |return_type| _ = try visit(context, return_type);
+                },
+                .var_decl, .const_decl => |binding| {
+                    if (binding.annotation) |annotation| _ = try visit(context, annotation);
+                    if (binding.value) |value| _ = try visit(context, value);
+                },
+                .destructure => |destructure| {
+                    if (destructure.annotation) |annotation| _ = try visit(context, annotation);
+                    _ = try visit(context, destructure.value);
+                },
+                .import => {},
+                .@"export" => |declaration| _ = try visit(context, declaration),
+                .block => |block| for (block.statements) |statement| {
+                    _ = try visit(context, statement);
+                },
+                .@"if" => |conditional| {
+                    _ = try visit(context, conditional.condition);
+                    _ = try visit(context, conditional.then_block);
+                    if (conditional.else_block) |else_block| _ = try visit(context, else_block);
+                },
+                .@"while" => |loop| {
+                    _ = try visit(context, loop.condition);
+                    _ = try visit(context, loop.body);
+                },
+                .@"for" => |loop| {
+                    _ = try visit(context, loop.iterable);
+                    _ = try visit(context, loop.body);
+                },
+                .match => |match| {
+                    _ = try visit(context, match.subject);
+                    for (match.arms) |arm| {
+                        if (arm.guard) |guard| _ = try visit(context, guard);
+                        _ = try visit(context, arm.body);
+                    }
+                },
+                .@"return", .@"break" => |value| if (value) |v| {
+                    _ = try visit(context, v);
+                },
+                .@"defer", .@"unsafe", .@"comptime" => |body| _ = try visit(context, body),
+                .assignment => |assignment| {
+                    _ = try visit(context, assignment.lhs);
+                    _ = try visit(context, assignment.rhs);
+                },
+                .prefix => |prefix| _ = try visit(context, prefix.operand),
+                .infix => |infix| {
+                    _ = try visit(context, infix.lhs);
+                    _ = try visit(context, infix.rhs);
+                },
+                .fn_call => |call| {
+                    _ = try visit(context, call.callee);
+                    for (call.arguments) |argument| _ = try visit(context, argument);
+                },
+                .struct_literal => |literal| for (literal.fields) |field| {
+                    _ = try visit(context, field.value);
+                },
+                .variant_literal => |literal| switch (literal.fields) {
+                    .unit => {},
+                    .tuple => |values| for (values) |value| {
+                        _ = try visit(context, value);
+                    },
+                    .@"struct" => |fields| for (fields) |field| {
+                        _ = try visit(context, field.value);
+                    }
+                },
+                .field_access => |access| _ = try visit(context, access.object),
+                .array_literal => |elements| for (elements) |element| {
+                    _ = try visit(context, element);
+                },
+                .index => |index| {
+                    _ = try visit(context, index.object);
+                    _ = try visit(context, index.index);
+                },
+                .tuple_literal => |elements| for (elements) |element| {
+                    _ = try visit(context, element);
+                },
+                .tuple_index => |access| _ = try visit(context, access.object),
+                .cast => |cast| {
+                    _ = try visit(context, cast.value);
+                    _ = try visit(context, cast.target);
+                },
+                .@"try", .reference, .dereference, .@"await", .sizeof => |operand| _ = try visit(context, operand),
+                .closure => |closure| {
+                    for (closure.params) |param| if (param.annotation) |annotation| {
+                        _ = try visit(context, annotation);
+                    };
+                    if (closure.return_type) |return_type| _ = try visit(context, return_type);
+                    _ = try visit(context, closure.body);
+                },
+                .type_expr => |type_expr| switch (type_expr) {
+                    .generic => |generic| for (generic.arguments) |argument| {
+                        _ = try visit(context, argument);
+                    },
+                    .array => |array| {
+                        _ = try visit(context, array.element);
+                        _ = try visit(context, array.len);
+                    },
+                    .slice, .reference => |element| _ = try visit(context, element),
+                    .tuple => |elements| for (elements) |element| {
+                        _ = try visit(context, element);
+                    },
+                    .function => |function| {
+                        for (function.params) |param| _ = try visit(context, param);
+                        if (function.return_type) |return_type| _ = try visit(context, return_type);
+                    },
+                    .impl, .variadic => {}
+                },
+                .@"continue", .label, .goto, .identifier, .literal => {}
+            }
+        }
+    };
+}
+
+/// The name of the method which visits nodes of kind tag
+pub fn methodName(comptime tag: std.meta.Tag(Node.Kind)) []const u8 {
+    return switch (tag) {
+        .module          => "visitModule",
+        .fn_def          => "visitFnDef",
+        .extern_fn       => "visitExternFn",
+        .struct_def      => "visitStructDef",
+        .union_def       => "visitUnionDef",
+        .enum_def        => "visitEnumDef",
+        .impl            => "visitImpl",
+        .trait_def       => "visitTraitDef",
+        .var_decl        => "visitVarDecl",
+        .const_decl      => "visitConstDecl",
+        .destructure     => "visitDestructure",
+        .import          => "visitImport",
+        .@"export"       => "visitExport",
+        .block           => "visitBlock",
+        .@"if"           => "visitIf",
+        .@"while"        => "visitWhile",
+        .@"for"          => "visitFor",
+        .match           => "visitMatch",
+        .@"return"       => "visitReturn",
+        .@"break"        => "visitBreak",
+        .@"continue"     => "visitContinue",
+        .@"defer"        => "visitDefer",
+        .@"unsafe"       => "visitUnsafe",
+        .@"comptime"     => "visitComptime",
+        .label           => "visitLabel",
+        .goto            => "visitGoto",
+        .assignment      => "visitAssignment",
+        .prefix          => "visitPrefix",
+        .infix           => "visitInfix",
+        .fn_call         => "visitFnCall",
+        .struct_literal  => "visitStructLiteral",
+        .variant_literal => "visitVariantLiteral",
+        .field_access    => "visitFieldAccess",
+        .array_literal   => "visitArrayLiteral",
+        .index           => "visitIndex",
+        .tuple_literal   => "visitTupleLiteral",
+        .tuple_index     => "visitTupleIndex",
+        .@"try"          => "visitTry",
+        .reference       => "visitReference",
+        .dereference     => "visitDereference",
+        .@"await"        => "visitAwait",
+        .sizeof          => "visitSizeof",
+        .cast            => "visitCast",
+        .closure         => "visitClosure",
+        .identifier      => "visitIdentifier",
+        .literal         => "visitLiteral",
+        .type_expr       => "visitTypeExpr"
+    };
+}
+
+test "visitor" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+    const Ast = ruka.Ast;
+    const AstBuilder = ruka.AstBuilder;
+
+    // Builds `let x = a + (b * 2)` followed by `fn f() { c }`
+    fn buildAst() !*Ast {
+        const ast = try Ast.init(testing.allocator, "test source");
+        errdefer ast.deinit();
+
+        const b = AstBuilder.init(ast);
+
+        const product = try b.infix(.multiply, try b.identifier("b"), try b.integer(2));
+        const binding = try b.binding("x", false, null, try b.infix(.add, try b.identifier("a"), product));
+        const function = try b.function("f", &.{}, null, try b.block(&.{try b.identifier("c")}));
+
+        try b.module(&.{binding, function});
+        return ast;
+    }
+
+    const Collector = struct {
+        names: std.ArrayList([]const u8),
+        skip_functions: bool = false,
+
+        const Walk = Visitor(Collector, Allocator.Error, void);
+
+        pub fn visitIdentifier(self: *Collector, _: *const Node, name: []const u8) Allocator.Error!void {
+            try self.names.append(name);
+        }
+
+        pub fn visitFnDef(self: *Collector, node: *const Node, _: Node.FunctionDef) Allocator.Error!void {
+            if (!self.skip_functions) try Walk.visitChildren(self, node);
+        }
+    };
+
+    test "unhandled kinds visit their children" {
+        const ast = try buildAst();
+        defer ast.deinit();
+
+        var collector: Collector = .{ .names = .init(testing.allocator) };
+        defer collector.names.deinit();
+
+        try Collector.Walk.visit(&collector, ast.root);
+
+        try testing.expectEqual(3, collector.names.items.len);
+        for ([_][]const u8{"a", "b", "c"}, collector.names.items) |expected, actual| {
+            try testing.expectEqualStrings(expected, actual);
+        }
+    }
+
+    test "handled kinds decide whether to visit their children" {
+        const ast = try buildAst();
+        defer ast.deinit();
+
+        var collector: Collector = .{ .names = .init(testing.allocator), .skip_functions = true };
+        defer collector.names.deinit();
+
+        try Collector.Walk.visit(&collector, ast.root);
+
+        try testing.expectEqual(2, collector.names.items.len);
+    }
+
+    test "visiting every node" {
+        const ast = try buildAst();
+        defer ast.deinit();
+
+        const Counter = struct {
+            count: usize = 0,
+
+            const Walk = Visitor(@This(), error{}, void);
+
+            pub fn visitNode(self: *@This(), node: *const Node) error{}!void {
+                self.count = self.count + 1;
+                try Walk.dispatch(self, node);
+            }
+        };
+
+        var counter: Counter = .{};
+        try Counter.Walk.visit(&counter, ast.root);
+
+        try testing.expectEqual(10, counter.count);
+    }
+
+    test "mutable visitors" {
+        const ast = try buildAst();
+        defer ast.deinit();
+
+        // Replaces every identifier with the literal 1
+        const Replacer = struct {
+            count: usize = 0,
+
+            pub fn visitIdentifier(self: *@This(), node: *Node, _: *[]const u8) error{}!void {
+                node.kind = .{ .literal = .{ .integer = 1 } };
+                self.count = self.count + 1;
+            }
+        };
+
+        var replacer: Replacer = .{};
+        try VisitorMut(Replacer, error{}, void).visit(&replacer, ast.root);
+
+        try testing.expectEqual(3, replacer.count);
+
+        var collector: Collector = .{ .names = .init(testing.allocator) };
+        defer collector.names.deinit();
+
+        try Collector.Walk.visit(&collector, ast.root);
+        try testing.expectEqual(0, collector.names.items.len);
+    }
+};
@@ -3,6 +3,7 @@
 
 pub const ArgumentParser = @import("ArgumentParser.zig");
 pub const Chrono = @import("Chrono.zig");
+pub const LanguageServer = @import("LanguageServer.zig");
 pub const Repl = @import("Repl.zig");
 pub const Transport = @import("Transport.zig");
 
@@ -10,32 +11,75 @@ pub const constants = @import("constants.zig");
 pub const logging = @import("logging.zig");
 
 pub const utilities = @import("utilities.zig");
-pub const Error = utilities.Error;
 pub const Position = utilities.Position;
+pub const Span = utilities.Span;
 pub const isAlphabetical = utilities.isAlphabetical;
 pub const isAlphanumerical = utilities.isAlphanumerical;
 pub const isIntegral = utilities.isIntegral;
 pub const isNumeric = utilities.isNumeric;
 
+pub const testing = if (@import("builtin").is_test) @import("testing.zig") else struct {};
+
 pub const Compiler = @import("Compiler.zig");
-pub const Job = Compiler.Job;
+pub const Diagnostic = Compiler.Diagnostic;
+pub const Interner = Compiler.Interner;
+pub const Options = Compiler.Options;
+pub const Scope = Compiler.Scope;
+pub const Symbol = Scope.Symbol;
+pub const SymbolTable = Compiler.SymbolTable;
+pub const Target = Compiler.Target;
 pub const Unit = Compiler.Unit;
+pub const VirtualFs = Compiler.VirtualFs;
 
 pub const Scanner = @import("Scanner.zig");
+pub const MacroExpander = Scanner.MacroExpander;
+pub const ParallelScanner = Scanner.ParallelScanner;
 pub const Token = Scanner.Token;
 pub const Keyword = Token.Keyword;
 pub const Mode = Token.Mode;
 
 pub const Parser = @import("Parser.zig");
 pub const Ast = Parser.Ast;
+pub const AstBuilder = Parser.AstBuilder;
 pub const Node = Ast.Node;
+pub const Visitor = Parser.visitor.Visitor;
+pub const VisitorMut = Parser.visitor.VisitorMut;
+
+pub const Formatter = @import("Formatter.zig");
+
+pub const TypeChecker = @import("TypeChecker.zig");
+pub const Type = TypeChecker.Type;
+pub const TypeTable = TypeChecker.TypeTable;
+
+pub const analysis = @import("analysis.zig");
+pub const ControlFlowGraph = analysis.ControlFlowGraph;
+
+pub const optimizer = @import("optimizer.zig");
+
+pub const lints = @import("lints.zig");
+
+pub const codegen = @import("codegen.zig");
+
+pub const stdlib = @import("stdlib.zig");
 
 pub const Interpreter = @import("Interpreter.zig");
+pub const Environment = Interpreter.Environment;
+pub const Value = Interpreter.Value;
 
 test "ruka modules" {
+    _ = utilities;
+    _ = testing;
     _ = Chrono;
+    _ = LanguageServer;
     _ = Compiler;
     _ = Scanner;
     _ = Parser;
+    _ = Formatter;
+    _ = TypeChecker;
+    _ = analysis;
+    _ = optimizer;
+    _ = lints;
+    _ = codegen;
+    _ = stdlib;
     _ = Interpreter;
 }
@@ -0,0 +1,670 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Expands declarative macros in scanned tokens before they are parsed. Macros
+//! are defined by rules matching the tokens they are invoked with:
+//!
+//!     macro inc {
+//!         ($e:expr) => { $e + 1 }
+//!     }
+//!
+//!     let x = inc!(41)
+//!
+//! The first rule whose pattern matches replaces the invocation, with the rule's
+//! variables substituted by the tokens they matched. Variables are expressions,
+//! identifiers or blocks, expressions are grouped in parentheses so they keep
+//! their precedence, as are invocations which don't begin a statement.
+//!
+//! Macros may be invoked before they are defined, and within the expansions
+//! of others but not their own. Tokens generated by an expansion are given the
+//! span of the invocation, while those substituted keep their own
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+const ArrayList = std.ArrayList;
+const AutoHashMap = std.AutoHashMap;
+
+const ruka = @import("../prelude.zig");
+const Interner = ruka.Interner;
+const Span = ruka.Span;
+const Token = ruka.Token;
+const Unit = ruka.Unit;
+
+const log = std.log.scoped(.macros);
+
+unit: *Unit,
+/// The macros defined, keyed by their names
+macros: AutoHashMap(Interner.Id, Macro),
+/// The names of the macros being expanded, innermost last
+expanding: ArrayList(Interner.Id),
+
+arena: ArenaAllocator,
+
+const MacroExpander = @This();
+
+const Error = Allocator.Error;
+
+pub const Macro = struct {
+    span: Span,
+    rules: []const Rule
+};
+
+pub const Rule = struct {
+    pattern: []const Token,
+    body: []const Token
+};
+
+/// What a variable of a rule matches
+pub const Fragment = enum {
+    expr,
+    ident,
+    block
+};
+
+pub fn init(unit: *Unit) !*MacroExpander {
+    const expander = try unit.allocator.create(MacroExpander);
+
+    expander.* = .{
+        .unit = unit,
+        .macros = .init(unit.allocator),
+        .expanding = .init(unit.allocator),
+        .arena = .init(unit.allocator)
+    };
+
+    return expander;
+}
+
+pub fn deinit(self: *MacroExpander) void {
+    self.macros.deinit();
+    self.expanding.deinit();
+    self.arena.deinit();
+    self.unit.allocator.destroy(self);
+}
+
+/// Expands the macros invoked in tokens, removing their definitions. Tokens
+/// without macros are returned as they are, otherwise the expanded tokens refer
+/// to the memory of tokens and are freed with the expander. Expansion continues
+/// past errors so all of them are recorded in the unit, but fails if any were
+/// encountered
+pub fn expand(self: *MacroExpander, tokens: []const Token) ![]const Token {
+    if (!hasMacros(tokens)) return tokens;
+
+    const error_count = self.unit.errorCount();
+
+    const rest = try self.collectDefinitions(tokens);
+
+    var expanded = ArrayList(Token).init(self.arena.allocator());
+    try self.expandInto(rest, &expanded);
+
+    const expansion_errors = self.unit.errorCount() - error_count;
+    if (expansion_errors > 0) {
+        log.err("{s}: expanding macros failed with {} error(s)", .{self.unit.input, expansion_errors});
+        return error.ExpansionFailed;
+    }
+
+    return expanded.items;
+}
+
+/// Whether tokens define or invoke any macros
+pub fn hasMacros(tokens: []const Token) bool {
+    for (tokens, 0..) |token, i| switch (token.kind) {
+        .keyword => |keyword| if (keyword == .macro) return true,
+        .identifier => if (isInvocation(tokens, i)) return true,
+        else => {}
+    };
+
+    return false;
+}
+
+// Whether the tokens at i are `name!(`
+fn isInvocation(tokens: []const Token, i: usize) bool {
+    return i + 2 < tokens.len and
+        tokens[i].kind == .identifier and
+        tokens[i + 1].kind == .bang and
+        tokens[i + 2].kind == .lparen;
+}
+
+// Records the macros defined in tokens, returning the tokens outside of their
+// definitions
+fn collectDefinitions(self: *MacroExpander, tokens: []const Token) Error![]const Token {
+    var rest = ArrayList(Token).init(self.arena.allocator());
+
+    var i: usize = 0;
+    while (i < tokens.len) {
+        const token = tokens[i];
+        if (token.kind != .keyword or token.kind.keyword != .macro) {
+            try rest.append(token);
+            i = i + 1;
+            continue;
+        }
+
+        i = try self.parseDefinition(tokens, i);
+        // The definition's statement is dropped along with it
+        while (i < tokens.len and tokens[i].kind == .newline) i = i + 1;
+    }
+
+    return rest.items;
+}
+
+// Parses the definition beginning at start, returning the index of the token
+// after it. Malformed definitions are reported and skipped to their end
+fn parseDefinition(self: *MacroExpander, tokens: []const Token, start: usize) Error!usize {
+    var i = start + 1;
+
+    const name = switch (tokens[i].kind) {
+        .identifier => |id| id,
+        else => {
+            try self.unit.createError("expected a name after 'macro'", tokens[i].span);
+            return skipStatement(tokens, i);
+        }
+    };
+    i = skipNewlines(tokens, i + 1);
+
+    if (tokens[i].kind != .lsquirly) {
+        try self.unit.createError("expected '{' to begin the rules of macro", tokens[i].span);
+        return skipStatement(tokens, i);
+    }
+
+    const open = i;
+    var rules = ArrayList(Rule).init(self.arena.allocator());
+
+    i = skipRuleSeparators(tokens, i + 1);
+    while (tokens[i].kind != .rsquirly) {
+        if (tokens[i].kind == .eof) {
+            try self.unit.createError("expected '}' to close the rules of macro", tokens[i].span);
+            return i;
+        }
+
+        i = try self.parseRule(tokens, i, &rules) orelse return skipGroup(tokens, open);
+        i = skipRuleSeparators(tokens, i);
+    }
+
+    const span = tokens[start].span.merge(tokens[i].span);
+    if (self.macros.contains(name)) {
+        try self.unit.createErrorFmt(span, "macro '{s}' is already defined", .{self.unit.interner.get(name)});
+    } else {
+        try self.macros.put(name, .{ .span = span, .rules = rules.items });
+    }
+
+    return i + 1;
+}
+
+// Parses the rule `(pattern) => { body }` at start, returning the index of the
+// token after it, or null if it is malformed
+fn parseRule(self: *MacroExpander, tokens: []const Token, start: usize, rules: *ArrayList(Rule)) Error!?usize {
+    if (tokens[start].kind != .lparen) {
+        try self.unit.createError("expected '(' to begin the pattern of rule", tokens[start].span);
+        return null;
+    }
+
+    const pattern_end = try self.closing(tokens, start, "expected ')' to close the pattern of rule") orelse return null;
+    const pattern = tokens[start + 1..pattern_end];
+    if (!try self.checkPattern(pattern)) return null;
+
+    var i = skipNewlines(tokens, pattern_end + 1);
+    if (tokens[i].kind != .wide_arrow) {
+        try self.unit.createError("expected '=>' after the pattern of rule", tokens[i].span);
+        return null;
+    }
+
+    i = skipNewlines(tokens, i + 1);
+    if (tokens[i].kind != .lsquirly) {
+        try self.unit.createError("expected '{' to begin the body of rule", tokens[i].span);
+        return null;
+    }
+
+    const body_end = try self.closing(tokens, i, "expected '}' to close the body of rule") orelse return null;
+    const body = trimNewlines(tokens[i + 1..body_end]);
+    if (!try self.checkBody(pattern, body)) return null;
+
+    try rules.append(.{ .pattern = pattern, .body = body });
+    return body_end + 1;
+}
+
+// Whether the variables of pattern are each `$name:fragment`, reporting those which aren't
+fn checkPattern(self: *MacroExpander, pattern: []const Token) Error!bool {
+    var i: usize = 0;
+    while (i < pattern.len) : (i = i + 1) {
+        if (pattern[i].kind != .cash) continue;
+
+        if (i + 3 >= pattern.len or
+            pattern[i + 1].kind != .identifier or
+            pattern[i + 2].kind != .colon or
+            pattern[i + 3].kind != .identifier
+        ) {
+            try self.unit.createError("expected a variable `$name:fragment` in pattern", pattern[i].span);
+            return false;
+        }
+
+        const fragment = self.unit.interner.get(pattern[i + 3].kind.identifier);
+        if (std.meta.stringToEnum(Fragment, fragment) == null) {
+            try self.unit.createErrorFmt(pattern[i + 3].span, "unknown fragment '{s}', expected expr, ident or block", .{fragment});
+            return false;
+        }
+
+        i = i + 3;
+    }
+
+    return true;
+}
+
+// Whether every variable of body is one of pattern, reporting those which aren't
+fn checkBody(self: *MacroExpander, pattern: []const Token, body: []const Token) Error!bool {
+    for (body, 0..) |token, i| {
+        if (token.kind != .cash) continue;
+
+        if (i + 1 == body.len or body[i + 1].kind != .identifier) {
+            try self.unit.createError("expected a variable name after '$'", token.span);
+            return false;
+        }
+
+        const name = body[i + 1].kind.identifier;
+        if (!isVariable(pattern, name)) {
+            try self.unit.createErrorFmt(token.span.merge(body[i + 1].span), "'${s}' isn't a variable of the rule's pattern", .{
+                self.unit.interner.get(name)
+            });
+            return false;
+        }
+    }
+
+    return true;
+}
+
+// Whether pattern, whose variables have been checked, has a variable called name
+fn isVariable(pattern: []const Token, name: Interner.Id) bool {
+    if (pattern.len < 2) return false;
+
+    for (pattern[0..pattern.len - 1], pattern[1..]) |token, next| {
+        if (token.kind == .cash and next.kind.identifier == name) return true;
+    }
+
+    return false;
+}
+
+// Appends tokens to out, replacing the invocations of macros by their expansions
+fn expandInto(self: *MacroExpander, tokens: []const Token, out: *ArrayList(Token)) Error!void {
+    var i: usize = 0;
+    while (i < tokens.len) {
+        if (!isInvocation(tokens, i)) {
+            try out.append(tokens[i]);
+            i = i + 1;
+            continue;
+        }
+
+        i = try self.expandInvocation(tokens, i, out);
+    }
+}
+
+// Appends the expansion of the invocation at start to out, returning the index
+// of the token after it. Invocations which can't be expanded are reported and
+// dropped
+fn expandInvocation(self: *MacroExpander, tokens: []const Token, start: usize, out: *ArrayList(Token)) Error!usize {
+    const name_token = tokens[start];
+    const name = name_token.kind.identifier;
+    const name_str = self.unit.interner.get(name);
+
+    const end = try self.closing(tokens, start + 2, "expected ')' to close the arguments of macro") orelse return tokens.len;
+    const span = name_token.span.merge(tokens[end].span);
+
+    const macro = self.macros.get(name) orelse {
+        // fmt! is a synonym of the fmt built in, the invocation is left as a call
+        // of it with the arguments expanded
+        if (std.mem.eql(u8, name_str, "fmt")) {
+            try out.append(name_token);
+            return start + 2;
+        }
+
+        try self.unit.createErrorFmt(name_token.span, "undefined macro '{s}'", .{name_str});
+        return end + 1;
+    };
+
+    for (self.expanding.items) |expanding| if (expanding == name) {
+        try self.unit.createErrorFmt(span, "macro '{s}' expands to itself", .{name_str});
+        return end + 1;
+    };
+
+    // Invocations within the arguments are expanded first, as they aren't part
+    // of the expansion of this one
+    var expanded = ArrayList(Token).init(self.arena.allocator());
+    try self.expandInto(tokens[start + 3..end], &expanded);
+
+    // Arguments may be written over several lines, the newlines within their
+    // groups separate statements of blocks
+    var arguments = ArrayList(Token).init(self.arena.allocator());
+    var depth: usize = 0;
+    for (expanded.items) |token| {
+        switch (token.kind) {
+            .lparen, .lbracket, .lsquirly => depth = depth + 1,
+            .rparen, .rbracket, .rsquirly => depth = depth -| 1,
+            .newline => if (depth == 0) continue,
+            else => {}
+        }
+
+        try arguments.append(token);
+    }
+
+    const matched: struct { Rule, Bindings } = for (macro.rules) |rule| {
+        if (try self.match(rule.pattern, arguments.items)) |bindings| break .{rule, bindings};
+    } else {
+        try self.unit.createErrorFmt(span, "no rule of macro '{s}' matches its arguments", .{name_str});
+        return end + 1;
+    };
+
+    var generated = ArrayList(Token).init(self.arena.allocator());
+    try substitute(matched[0], matched[1], span, &generated);
+
+    const grouped = !beginsStatement(out.items);
+    if (grouped) try out.append(.initSpan(.lparen, span));
+
+    try self.expanding.append(name);
+    try self.expandInto(generated.items, out);
+    self.expanding.shrinkRetainingCapacity(self.expanding.items.len - 1);
+
+    if (grouped) try out.append(.initSpan(.rparen, span));
+
+    return end + 1;
+}
+
+const Bindings = AutoHashMap(Interner.Id, Binding);
+
+const Binding = struct {
+    fragment: Fragment,
+    tokens: []const Token
+};
+
+// The tokens bound to the variables of pattern, if arguments match it
+fn match(self: *MacroExpander, pattern: []const Token, arguments: []const Token) Error!?Bindings {
+    var bindings = Bindings.init(self.arena.allocator());
+
+    var p: usize = 0;
+    var a: usize = 0;
+    while (p < pattern.len) {
+        if (pattern[p].kind == .newline) {
+            p = p + 1;
+            continue;
+        }
+
+        if (pattern[p].kind != .cash) {
+            if (a == arguments.len or !sameKind(pattern[p].kind, arguments[a].kind)) return null;
+            p = p + 1;
+            a = a + 1;
+            continue;
+        }
+
+        const name = pattern[p + 1].kind.identifier;
+        const fragment = std.meta.stringToEnum(Fragment, self.unit.interner.get(pattern[p + 3].kind.identifier)).?;
+        p = p + 4;
+
+        if (a == arguments.len) return null;
+        const matched_end: usize = switch (fragment) {
+            .ident => if (arguments[a].kind == .identifier) a + 1 else return null,
+            .block => if (arguments[a].kind == .lsquirly) (groupEnd(arguments, a) orelse return null) + 1 else return null,
+            // Expressions extend to the token following the variable in the pattern
+            .expr => expressionEnd(arguments, a, if (p < pattern.len) pattern[p].kind else null) orelse return null
+        };
+
+        try bindings.put(name, .{ .fragment = fragment, .tokens = arguments[a..matched_end] });
+        a = matched_end;
+    }
+
+    if (a != arguments.len) return null;
+    return bindings;
+}
+
+// Appends the body of rule to out with its variables substituted, giving the
+// tokens of the body span
+fn substitute(rule: Rule, bindings: Bindings, span: Span, out: *ArrayList(Token)) Error!void {
+    var i: usize = 0;
+    while (i < rule.body.len) : (i = i + 1) {
+        const token = rule.body[i];
+        if (token.kind != .cash) {
+            try out.append(.initSpan(token.kind, span));
+            continue;
+        }
+
+        const binding = bindings.get(rule.body[i + 1].kind.identifier).?;
+        i = i + 1;
+
+        if (binding.fragment == .expr) try out.append(.initSpan(.lparen, span));
+        try out.appendSlice(binding.tokens);
+        if (binding.fragment == .expr) try out.append(.initSpan(.rparen, span));
+    }
+}
+
+// The index of the token closing the group opened at start, or null if the
+// group isn't closed, reporting msg at its end
+fn closing(self: *MacroExpander, tokens: []const Token, start: usize, msg: []const u8) Error!?usize {
+    if (groupEnd(tokens, start)) |end| return end;
+
+    try self.unit.createError(msg, tokens[tokens.len - 1].span);
+    return null;
+}
+
+// The index of the token closing the group opened at start
+fn groupEnd(tokens: []const Token, start: usize) ?usize {
+    var depth: usize = 0;
+    for (tokens[start..], start..) |token, i| switch (token.kind) {
+        .lparen, .lbracket, .lsquirly => depth = depth + 1,
+        .rparen, .rbracket, .rsquirly => {
+            depth = depth - 1;
+            if (depth == 0) return i;
+        },
+        else => {}
+    };
+
+    return null;
+}
+
+// The index after the expression beginning at start, which ends before the
+// first token of kind outside of any group, or at the end of tokens
+fn expressionEnd(tokens: []const Token, start: usize, until: ?Token.Kind) ?usize {
+    var i = start;
+    while (i < tokens.len) {
+        if (until) |kind| if (sameKind(kind, tokens[i].kind)) break;
+
+        i = switch (tokens[i].kind) {
+            .lparen, .lbracket, .lsquirly => (groupEnd(tokens, i) orelse return null) + 1,
+            .rparen, .rbracket, .rsquirly => return null,
+            else => i + 1
+        };
+    }
+
+    if (i == start or (until != null and i == tokens.len)) return null;
+    return i;
+}
+
+// Whether a token appended after tokens begins a statement
+fn beginsStatement(tokens: []const Token) bool {
+    if (tokens.len == 0) return true;
+
+    return switch (tokens[tokens.len - 1].kind) {
+        .newline, .semicolon, .lsquirly => true,
+        else => false
+    };
+}
+
+fn sameKind(a: Token.Kind, b: Token.Kind) bool {
+    if (std.meta.activeTag(a) != std.meta.activeTag(b)) return false;
+
+    return switch (a) {
+        .identifier => |id| id == b.identifier,
+        .@"enum" => |en| std.mem.eql(u8, en.items, b.@"enum".items),
+        .string => |st| std.mem.eql(u8, st.items, b.string.items),
+        .character => |ch| ch == b.character,
+        .integer => |in| in == b.integer,
+        .float => |fl| fl == b.float,
+        .keyword => |ke| ke == b.keyword,
+        .mode => |mo| mo == b.mode,
+        else => true
+    };
+}
+
+fn trimNewlines(tokens: []const Token) []const Token {
+    var start: usize = 0;
+    var end = tokens.len;
+    while (start < end and tokens[start].kind == .newline) start = start + 1;
+    while (end > start and tokens[end - 1].kind == .newline) end = end - 1;
+
+    return tokens[start..end];
+}
+
+fn skipNewlines(tokens: []const Token, start: usize) usize {
+    var i = start;
+    while (tokens[i].kind == .newline) i = i + 1;
+
+    return i;
+}
+
+// Rules may be separated by newlines, commas or semicolons
+fn skipRuleSeparators(tokens: []const Token, start: usize) usize {
+    var i = start;
+    while (true) switch (tokens[i].kind) {
+        .newline, .comma, .semicolon => i = i + 1,
+        else => return i
+    };
+}
+
+fn skipStatement(tokens: []const Token, start: usize) usize {
+    var i = start;
+    while (tokens[i].kind != .newline and tokens[i].kind != .eof) i = i + 1;
+
+    return i;
+}
+
+fn skipGroup(tokens: []const Token, start: usize) usize {
+    return if (groupEnd(tokens, start)) |end| end + 1 else tokens.len - 1;
+}
+
+test "macro expander" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    fn expectExpansion(source: []const u8, expected: []const u8) !void {
+        var input = std.io.fixedBufferStream(source);
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        var out = ArrayList(u8).init(testing.allocator);
+        defer out.deinit();
+        try ast.write(out.writer().any());
+
+        try testing.expectEqualStrings(expected, out.items);
+    }
+
+    test "expressions keep their precedence" {
+        try expectExpansion(
+            \\macro double { ($e:expr) => { $e * 2 } }
+            \\let x = 1 + double!(2 + 3)
+            \\
+        ,
+            \\let x = (1 + ((2 + 3) * 2))
+            \\
+        );
+    }
+
+    test "identifiers, blocks and several rules" {
+        try expectExpansion(
+            \\macro unless {
+            \\    ($cond:expr, $body:block) => { if not $cond $body }
+            \\}
+            \\
+            \\var n = 0
+            \\var done = false
+            \\unless!(done, {
+            \\    set!(n to n + 1)
+            \\})
+            \\set!(n)
+            \\
+            \\macro set {
+            \\    ($name:ident to $value:expr) => { $name = $value }
+            \\    ($name:ident) => { $name = 0 }
+            \\}
+            \\
+        ,
+            \\var n = 0
+            \\var done = false
+            \\if !done {
+            \\    n = (n + 1)
+            \\}
+            \\n = 0
+            \\
+        );
+    }
+
+    test "assert as a macro" {
+        const source =
+            \\macro assert {
+            \\    ($cond:expr) => { if !$cond { panic("assertion failed") } }
+            \\}
+            \\
+            \\fn check(x: i64) {
+            \\    assert!(x > 0)
+            \\}
+            \\
+        ;
+
+        try expectExpansion(source,
+            \\fn check(x: i64) {
+            \\    if !(x > 0) {
+            \\        panic("assertion failed")
+            \\    }
+            \\}
+            \\
+        );
+
+        var input = std.io.fixedBufferStream(source);
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        const ast = try unit.parse();
+        defer ast.deinit();
+
+        // The generated if has the span of the invocation, the condition substituted keeps its own
+        const body = ast.root.kind.module.items[0].kind.fn_def.body;
+        const conditional = body.kind.block.statements[0];
+        try testing.expectEqual(std.mem.indexOf(u8, source, "assert!(x").?, conditional.span.start);
+        try testing.expectEqual(std.mem.indexOf(u8, source, ")\n}").? + 1, conditional.span.end);
+        try testing.expectEqual(ruka.Position.init(6, 5), conditional.span.pos);
+
+        const condition = conditional.kind.@"if".condition.kind.prefix.operand;
+        try testing.expectEqual(ruka.Position.init(6, 13), condition.span.pos);
+        try testing.expectEqualStrings("x > 0", condition.span.slice(source));
+    }
+
+    test "recursive, undefined and unmatched invocations" {
+        const source =
+            \\macro bad { ($e:thing) => { $e } }
+            \\macro ping { () => { pong!() } }
+            \\macro pong { () => { ping!() } }
+            \\macro one { ($e:ident) => { $e } }
+            \\ping!()
+            \\missing!(1)
+            \\one!(1)
+            \\
+        ;
+
+        var input = std.io.fixedBufferStream(source);
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.ExpansionFailed, unit.parse());
+
+        const expected = [_][]const u8{
+            "unknown fragment 'thing', expected expr, ident or block",
+            "macro 'ping' expands to itself",
+            "undefined macro 'missing'",
+            "no rule of macro 'one' matches its arguments"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+};
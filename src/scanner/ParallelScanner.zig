@@ -0,0 +1,497 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const Thread = std.Thread;
+
+const ruka = @import("../prelude.zig");
+const Scanner = ruka.Scanner;
+const Token = ruka.Token;
+const Unit = ruka.Unit;
+
+source: []const u8,
+
+unit: *Unit,
+
+allocator: Allocator,
+
+const ParallelScanner = @This();
+
+const log = std.log.scoped(.scanner);
+
+/// Sources smaller than this are not worth splitting
+const min_chunk_size = 16 * 1024;
+
+/// A run of whole lines scanned on its own thread
+const Chunk = struct {
+    source: []const u8,
+    /// Where the chunk starts within the whole source
+    line: usize,
+    index: usize,
+
+    unit: ?*Unit = null,
+    result: anyerror!ArrayList(Token) = error.NotScanned,
+
+    fn deinit(self: *Chunk) void {
+        if (self.result) |tokens| {
+            for (tokens.items) |token| token.deinit();
+            tokens.deinit();
+        } else |_| {}
+
+        if (self.unit) |unit| unit.deinit();
+    }
+};
+
+/// Creates a scanner over source, which must be the whole of unit's input
+pub fn init(unit: *Unit, source: []const u8) !*ParallelScanner {
+    const scanner = try unit.allocator.create(ParallelScanner);
+
+    scanner.* = .{
+        .source = source,
+        .unit = unit,
+        .allocator = unit.allocator
+    };
+
+    return scanner;
+}
+
+pub fn deinit(self: *ParallelScanner) void {
+    self.allocator.destroy(self);
+}
+
+/// Splits the source at line boundaries, scans each chunk on its own thread and
+/// merges the tokens and errors back in source order. Produces the same tokens
+/// as Scanner.scan
+pub fn scan(self: *ParallelScanner) !ArrayList(Token) {
+    const cpus = Thread.getCpuCount() catch 1;
+    const count = @max(1, @min(cpus, self.source.len / min_chunk_size));
+
+    var chunks = try self.splitChunks(count);
+    defer {
+        for (chunks.items) |*chunk| chunk.deinit();
+        chunks.deinit();
+    }
+
+    // Reserved up front so every spawned thread is joined
+    var threads = try ArrayList(?Thread).initCapacity(self.allocator, chunks.items.len);
+    defer threads.deinit();
+
+    for (chunks.items) |*chunk| {
+        const thread = if (chunks.items.len == 1) null
+            else Thread.spawn(.{}, scanChunk, .{chunk, self.unit}) catch null;
+
+        // Chunks which couldn't be given a thread are scanned here instead
+        if (thread == null) scanChunk(chunk, self.unit);
+        threads.appendAssumeCapacity(thread);
+    }
+
+    for (threads.items) |thread| if (thread) |t| t.join();
+
+    return try self.merge(chunks.items);
+}
+
+// Chunks only start at the beginning of lines, and never inside a multiline comment or string,
+// so no token is split between chunks
+fn splitChunks(self: *ParallelScanner, count: usize) !ArrayList(Chunk) {
+    var chunks = ArrayList(Chunk).init(self.allocator);
+    errdefer chunks.deinit();
+
+    const source = self.source;
+    const target = source.len / count;
+
+    var state: enum {
+        code,
+        line_comment,
+        block_comment,
+        string,
+        raw_string,
+        triple_string,
+        raw_triple_string,
+        multi_string
+    } = .code;
+    // How many block comments are open, as they may be nested
+    var depth: usize = 0;
+    var start: usize = 0;
+    var line: usize = 0;
+
+    var i: usize = 0;
+    while (i < source.len): (i = i + 1) {
+        const byte = source[i];
+        const next = if (i + 1 < source.len) source[i + 1] else '\x00';
+
+        switch (state) {
+            .code => switch (byte) {
+                '/' => switch (next) {
+                    '/' => state = .line_comment,
+                    '*' => {
+                        state = .block_comment;
+                        depth = 1;
+                        i = i + 1;
+                    },
+                    else => {}
+                },
+                '"' => if (next == '|') {
+                    state = .multi_string;
+                } else if (std.mem.startsWith(u8, source[i..], "\"\"\"")) {
+                    state = .triple_string;
+                    i = i + 2;
+                } else {
+                    state = .string;
+                },
+                // Raw strings start at an r which doesn't end an identifier
+                'r' => if (next == '"' and (i == 0 or !isIdentifierByte(source[i - 1]))) {
+                    i = i + 1;
+                    if (std.mem.startsWith(u8, source[i..], "\"\"\"")) {
+                        state = .raw_triple_string;
+                        i = i + 2;
+                    } else {
+                        state = .raw_string;
+                    }
+                },
+                // Skip character literals so the quotes inside them aren't mistaken for strings
+                '\'' => if (next == '\\') {
+                    i = i + 2;
+                    while (i + 1 < source.len and source[i + 1] != '\'' and source[i + 1] != '\n') {
+                        i = i + 1;
+                    }
+                    i = i + 1;
+                } else if (i + 2 < source.len and source[i + 2] == '\'') {
+                    i = i + 2;
+                },
+                else => {}
+            },
+            .line_comment => {},
+            .block_comment => if (byte == '/' and next == '*') {
+                depth = depth + 1;
+                i = i + 1;
+            } else if (byte == '*' and next == '/') {
+                depth = depth - 1;
+                if (depth == 0) state = .code;
+                i = i + 1;
+            },
+            .string => switch (byte) {
+                '\\' => if (next != '\n') {
+                    i = i + 1;
+                },
+                '"' => state = .code,
+                else => {}
+            },
+            // Backslashes are kept as written in raw strings, so none escape a quote
+            .raw_string => if (byte == '"') {
+                state = .code;
+            },
+            // Triple quoted strings span lines, and only end at the closing quotes
+            .triple_string => if (byte == '\\') {
+                i = i + 1;
+            } else if (std.mem.startsWith(u8, source[i..], "\"\"\"")) {
+                state = .code;
+                i = i + 2;
+            },
+            .raw_triple_string => if (std.mem.startsWith(u8, source[i..], "\"\"\"")) {
+                state = .code;
+                i = i + 2;
+            },
+            .multi_string => switch (byte) {
+                '\\' => if (next != '\n') {
+                    i = i + 1;
+                },
+                '"' => state = .code,
+                else => {}
+            }
+        }
+
+        if (byte != '\n') continue;
+
+        // Single line strings and comments end with the line
+        if (state == .line_comment or state == .string or state == .raw_string) state = .code;
+
+        if (state == .code and i + 1 - start >= target and chunks.items.len + 1 < count) {
+            try chunks.append(.{ .source = source[start..i + 1], .line = line, .index = start });
+
+            line = line + std.mem.count(u8, source[start..i + 1], "\n");
+            start = i + 1;
+        }
+    }
+
+    try chunks.append(.{ .source = source[start..], .line = line, .index = start });
+
+    return chunks;
+}
+
+fn isIdentifierByte(byte: u8) bool {
+    return ruka.isAlphanumerical(byte) or byte == '_';
+}
+
+fn scanChunk(chunk: *Chunk, parent: *Unit) void {
+    chunk.result = scanChunkSource(chunk, parent);
+}
+
+fn scanChunkSource(chunk: *Chunk, parent: *Unit) !ArrayList(Token) {
+    var input = std.io.fixedBufferStream(chunk.source);
+
+    // Each chunk records its errors in its own unit, so threads never share one
+    chunk.unit = try Unit.init(.{
+        .input = parent.input,
+        .output = parent.output,
+        .reader = input.reader().any(),
+        .writer = std.io.null_writer.any(),
+        .allocator = parent.allocator
+    });
+
+    var scanner = try Scanner.init(chunk.unit.?);
+    defer scanner.deinit();
+
+    return try scanner.scan();
+}
+
+// Moves each chunk's tokens, errors and comments into the results, offsetting their positions
+// by where the chunk starts
+fn merge(self: *ParallelScanner, chunks: []Chunk) !ArrayList(Token) {
+    var tokens = ArrayList(Token).init(self.allocator);
+    errdefer {
+        for (tokens.items) |token| token.deinit();
+        tokens.deinit();
+    }
+
+    var failure: ?anyerror = null;
+    for (chunks, 0..) |*chunk, i| {
+        if (chunk.unit) |unit| {
+            // Copied as the chunk's unit owns the memory of formatted messages
+            for (unit.diagnostics.items) |diagnostic| {
+                var moved = try diagnostic.clone(self.unit.arena.allocator());
+                moved.span.start = moved.span.start + chunk.index;
+                moved.span.end = moved.span.end + chunk.index;
+                moved.span.pos.line = moved.span.pos.line + chunk.line;
+
+                try self.unit.report(moved);
+            }
+
+            for (unit.comments.items) |comment| {
+                var moved = comment;
+                moved.start = moved.start + chunk.index;
+                moved.end = moved.end + chunk.index;
+                moved.pos.line = moved.pos.line + chunk.line;
+
+                try self.unit.comments.append(moved);
+            }
+        }
+
+        const chunk_tokens = chunk.result catch |err| {
+            if (failure == null or failure.? == error.ScanningFailed) failure = err;
+            continue;
+        };
+
+        // Only the final chunk's eof ends the source
+        const last = i == chunks.len - 1;
+        const kept = if (last) chunk_tokens.items.len else chunk_tokens.items.len - 1;
+
+        // Ids are only meaningful to the interner of the chunk's unit, so identifiers are
+        // reinterned before any token is moved, as moving can't fail part way
+        for (chunk_tokens.items[0..kept]) |*token| switch (token.kind) {
+            .identifier => |id| token.kind = .{
+                .identifier = try self.unit.interner.intern(chunk.unit.?.interner.get(id))
+            },
+            else => {}
+        };
+
+        try tokens.ensureUnusedCapacity(kept);
+        for (chunk_tokens.items[0..kept]) |token| {
+            var moved = token;
+            moved.span.start = moved.span.start + chunk.index;
+            moved.span.end = moved.span.end + chunk.index;
+            moved.span.pos.line = moved.span.pos.line + chunk.line;
+
+            tokens.appendAssumeCapacity(moved);
+        }
+
+        for (chunk_tokens.items[kept..]) |token| token.deinit();
+        chunk_tokens.deinit();
+        chunk.result = error.NotScanned;
+    }
+
+    if (failure) |err| {
+        if (err == error.ScanningFailed) {
+            log.err("{s}: scanning failed with {} error(s)", .{self.unit.input, self.unit.errorCount()});
+        }
+
+        return err;
+    }
+
+    return tokens;
+}
+
+test "parallel scanner" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    fn payload(unit: *const Unit, kind: Token.Kind) ?[]const u8 {
+        return switch (kind) {
+            .identifier => |id| unit.interner.get(id),
+            .@"enum", .string, .doc_comment => |list| list.items,
+            else => null
+        };
+    }
+
+    fn scanWith(source: []const u8, parallel: bool) !struct { *Unit, ArrayList(Token) } {
+        var input = std.io.fixedBufferStream(source);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        const unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        errdefer unit.deinit();
+
+        if (parallel) {
+            var scanner = try ParallelScanner.init(unit, source);
+            defer scanner.deinit();
+
+            return .{unit, try scanner.scan()};
+        }
+
+        var scanner = try Scanner.init(unit);
+        defer scanner.deinit();
+
+        return .{unit, try scanner.scan()};
+    }
+
+    fn freeTokens(tokens: ArrayList(Token)) void {
+        for (tokens.items) |token| token.deinit();
+        tokens.deinit();
+    }
+
+    // Scans source both sequentially and in parallel, expecting the same tokens and comments
+    fn expectSameTokens(source: []const u8) !void {
+        const sequential_unit, const sequential = try scanWith(source, false);
+        defer sequential_unit.deinit();
+        defer freeTokens(sequential);
+
+        const parallel_unit, const parallel = try scanWith(source, true);
+        defer parallel_unit.deinit();
+        defer freeTokens(parallel);
+
+        try testing.expectEqual(sequential.items.len, parallel.items.len);
+        for (sequential.items, parallel.items) |expected, actual| {
+            try testing.expectEqual(std.meta.activeTag(expected.kind), std.meta.activeTag(actual.kind));
+            try testing.expectEqual(expected.span.start, actual.span.start);
+            try testing.expectEqual(expected.span.end, actual.span.end);
+            try testing.expectEqual(expected.span.pos, actual.span.pos);
+
+            if (payload(sequential_unit, expected.kind)) |items| {
+                try testing.expectEqualStrings(items, payload(parallel_unit, actual.kind).?);
+            }
+            switch (expected.kind) {
+                .integer => |integer| try testing.expectEqual(integer, actual.kind.integer),
+                .float => |float| try testing.expectEqual(float, actual.kind.float),
+                else => {}
+            }
+        }
+
+        try testing.expectEqual(sequential_unit.comments.items.len, parallel_unit.comments.items.len);
+        for (sequential_unit.comments.items, parallel_unit.comments.items) |expected, actual| {
+            try testing.expectEqual(expected.start, actual.start);
+            try testing.expectEqual(expected.end, actual.end);
+            try testing.expectEqual(expected.pos, actual.pos);
+        }
+    }
+
+    // Splits source into count chunks, expecting each to start with prefix
+    fn expectChunksStartWith(source: []const u8, count: usize, prefix: []const u8) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        const unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        var scanner = try ParallelScanner.init(unit, source);
+        defer scanner.deinit();
+
+        var chunks = try scanner.splitChunks(count);
+        defer chunks.deinit();
+
+        try testing.expectEqual(count, chunks.items.len);
+        for (chunks.items) |chunk| try testing.expectStringStartsWith(chunk.source, prefix);
+    }
+
+    test "parallel scanning matches sequential scanning" {
+        var source = ArrayList(u8).init(testing.allocator);
+        defer source.deinit();
+
+        // 26,000 lines, with comments and strings spanning lines that must not be split
+        for (0..2_000) |i| {
+            try source.writer().print("let x{} = {} + 2.5 <> \"a // not a comment\"\n", .{i, i});
+            try source.appendSlice("/* a comment\n   spanning lines */ let c = '\"'\n");
+            try source.appendSlice("var s = \"|first\n    |second\n    |\"\n");
+            try source.appendSlice("var e = \"|an \\\" escaped\n    |quote // in a string\n    |\"\n");
+            try source.appendSlice("// a \"line comment\n");
+            try source.appendSlice("/* nested /* comments\n */ let y = \"\n */ /// docs\n");
+        }
+
+        try expectSameTokens(source.items);
+    }
+
+    test "triple quoted strings aren't split between chunks" {
+        var source = ArrayList(u8).init(testing.allocator);
+        defer source.deinit();
+
+        // Most lines are within a literal, which holds quotes and escaped quotes
+        for (0..1_000) |i| {
+            try source.writer().print("let s{} = \"\"\"\n", .{i});
+            for (0..8) |_| try source.appendSlice("    a \"quoted\" line, \\\"\"\" escaped\n");
+            try source.appendSlice("    \"\"\"\n");
+        }
+
+        try expectChunksStartWith(source.items, 4, "let s");
+        try expectSameTokens(source.items);
+    }
+
+    test "raw strings aren't split between chunks" {
+        var source = ArrayList(u8).init(testing.allocator);
+        defer source.deinit();
+
+        // A backslash before the closing quote doesn't escape it in a raw string, so
+        // what follows is code rather than string. An r ending an identifier isn't raw
+        for (0..1_000) |i| {
+            try source.writer().print("let r{} = r\"C:\\dir\\\" /* \"\n */\n", .{i});
+            try source.appendSlice("let t = r\"\"\"\n    raw \\\"\"\"\n");
+            try source.appendSlice("let u = \"\"\"\n    not raw \\\"\"\" line\n    \"\"\"\n");
+            try source.appendSlice("let v = bar\"a\"\n");
+        }
+
+        try expectChunksStartWith(source.items, 4, "let r");
+        try expectSameTokens(source.items);
+    }
+
+    test "errors are merged in source order" {
+        var source = ArrayList(u8).init(testing.allocator);
+        defer source.deinit();
+
+        for (0..10_000) |i| {
+            if (i % 2_500 == 0) {
+                try source.appendSlice("let \x01 = 1\n");
+            } else {
+                try source.appendSlice("let x = 1\n");
+            }
+        }
+
+        var input = std.io.fixedBufferStream(source.items);
+
+        var buf: [10]u8 = undefined;
+        var output = std.io.fixedBufferStream(&buf);
+
+        const unit = try Unit.init(.testing(input.reader().any(), output.writer().any()));
+        defer unit.deinit();
+
+        var scanner = try ParallelScanner.init(unit, source.items);
+        defer scanner.deinit();
+
+        try testing.expectError(error.ScanningFailed, scanner.scan());
+        try testing.expectEqual(4, unit.diagnostics.items.len);
+        for (unit.diagnostics.items, 0..) |diagnostic, i| {
+            try testing.expectEqual(ruka.Position.init(i * 2_500 + 1, 5), diagnostic.span.pos);
+        }
+    }
+};
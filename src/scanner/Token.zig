@@ -6,43 +6,70 @@ const Allocator = std.mem.Allocator;
 const ArrayList = std.ArrayList;
 
 const ruka = @import("../prelude.zig");
+const Interner = ruka.Interner;
 const Position = ruka.Position;
+const Span = ruka.Span;
 
 const Token = @This();
 
-/// Represents a lexeme: it's kind, file, and position within that file
+/// Represents a lexeme: it's kind, and the span of the file it was read from
 kind: Kind,
-file: []const u8,
-pos: Position,
+span: Span,
 
-/// Creates a new token
+/// Creates a new token starting at pos, without a byte range
 pub fn init(kind: Kind, file: []const u8, pos: Position) Token {
     return Token {
         .kind = kind,
-        .file = file,
-        .pos = pos
+        .span = .{ .file = file, .pos = pos }
     };
 }
 
-///
+/// Creates a new token covering span
+pub fn initSpan(kind: Kind, span: Span) Token {
+    return Token {
+        .kind = kind,
+        .span = span
+    };
+}
+
+/// Frees the memory owned by the token's kind, if any
 pub fn deinit(self: Token) void {
     self.kind.deinit();
 }
 
+/// Creates a copy of the token which owns its own memory
+pub fn clone(self: Token, allocator: Allocator) !Token {
+    return Token {
+        .kind = try self.kind.clone(allocator),
+        .span = self.span
+    };
+}
+
 /// Represents the kind of lexeme and corresponding value when applicable
 pub const Kind = union(enum) {
     // Literals
-    identifier: ArrayList(u8),
+    /// Interned by the unit being scanned
+    identifier: Interner.Id,
     @"enum": ArrayList(u8),
     string: ArrayList(u8),
     character: u8,
-    integer: ArrayList(u8),
-    float: ArrayList(u8),
+    integer: i64,
+    float: f64,
     keyword: Keyword,
     mode: Mode,
     // Assignment
     assign,        // =
     assign_exp,    // :=
+    assign_add,    // +=
+    assign_sub,    // -=
+    assign_mul,    // *=
+    assign_div,    // /=
+    assign_mod,    // %=
+    assign_and,    // &=
+    assign_or,     // |=
+    assign_xor,    // ^=
+    assign_lshift, // <<=
+    assign_rshift, // >>=
     // Punctuation
     dot,           // .
     comma,         // ,
@@ -57,6 +84,7 @@ pub const Kind = union(enum) {
     backtick,      // `
     backslash,     // \
     colon,         // :
+    double_colon,  // ::
     semicolon,     // ;
     arrow,         // ->
     wide_arrow,    // =>
@@ -68,6 +96,7 @@ pub const Kind = union(enum) {
     question,      // ?
     range_exc,     // ..
     range_inc,     // ..=
+    ellipsis,      // ...
     forward_app,   // <|
     reverse_app,   // |>
     concat,        // <>
@@ -80,6 +109,9 @@ pub const Kind = union(enum) {
     increment,     // ++
     decrement,     // --
     square,        // **
+    // Logical
+    logical_and,   // &&
+    logical_or,    // ||
     // Bitwise
     ampersand,     // &
     pipe,          // |
@@ -95,16 +127,15 @@ pub const Kind = union(enum) {
     equal,         // ==
     not_equal,     // !=
     // Miscelaneous
+    /// The text of a `///` comment after its slashes, kept for documentation
+    doc_comment: ArrayList(u8),
     newline,       // \n
     illegal,
     eof,           // \x00
 
-    pub fn initIdentifier(source: []const u8, allocator: Allocator) !Kind {
-        var identifier = ArrayList(u8).init(allocator);
-        try identifier.appendSlice(source);
-
+    pub fn initIdentifier(source: []const u8, interner: *Interner) !Kind {
         return Kind {
-            .identifier = identifier
+            .identifier = try interner.intern(source)
         };
     }
 
@@ -126,25 +157,8 @@ pub const Kind = union(enum) {
         };
     }
 
-    pub fn initInteger(source: []const u8, allocator: Allocator) !Kind {
-        var integer = ArrayList(u8).init(allocator);
-        try integer.appendSlice(source);
-
-        return Kind {
-            .integer = integer
-        };
-    }
-
-    pub fn initFloat(source: []const u8, allocator: Allocator) !Kind {
-        var float = ArrayList(u8).init(allocator);
-        try float.appendSlice(source);
-
-        return Kind {
-            .float = float
-        };
-    }
-
-    // Tries to create a Kind from a byte
+    /// Tries to create a Kind from a byte, returning illegal if the byte
+    /// does not represent a single character token
     pub fn fromByte(byte: u8) Kind {
         return switch(byte) {
             // Assignment
@@ -191,32 +205,51 @@ pub const Kind = union(enum) {
         };
     }
 
+    /// Creates a copy of the kind which owns its own memory
+    pub fn clone(self: Kind, allocator: Allocator) !Kind {
+        return switch (self) {
+            .@"enum"      => |en| try initEnum(en.items, allocator),
+            .string       => |st| try initString(st.items, allocator),
+            .doc_comment  => |dc| .{ .doc_comment = (try initString(dc.items, allocator)).string },
+            else => self
+        };
+    }
+
     pub fn deinit(self: Kind) void {
         switch (self) {
-            .identifier   => |id| id.deinit(),
             .@"enum"      => |en| en.deinit(),
             .string       => |st| st.deinit(),
-            .integer      => |in| in.deinit(),
-            .float        => |fl| fl.deinit(),
+            .doc_comment  => |dc| dc.deinit(),
             else => {}
         }
     }
 
-    // Converts a Kind into a string slice
-    pub fn toStr(self: *const Kind, allocator: Allocator) ![]const u8 {
+    /// Converts a Kind into a string slice, identifiers are looked up in the
+    /// interner they were interned by
+    pub fn toStr(self: *const Kind, interner: *const Interner, allocator: Allocator) ![]const u8 {
         return switch(self.*) {
             // Kinds with associated values
-            .identifier   => |id| id.items,
+            .identifier   => |id| interner.get(id),
             .@"enum"      => |en| en.items,
             .string       => |st| st.items,
             .character    => |ch| try self.charToString(ch, allocator),
-            .integer      => |in| in.items,
-            .float        => |fl| fl.items,
+            .integer      => |in| try std.fmt.allocPrint(allocator, "{}", .{in}),
+            .float        => |fl| try std.fmt.allocPrint(allocator, "{d}", .{fl}),
             .keyword      => |ke| ke.toStr(),
             .mode         => |mo| mo.toStr(),
             // Assignment
             .assign       => "=",
             .assign_exp   => ":=",
+            .assign_add   => "+=",
+            .assign_sub   => "-=",
+            .assign_mul   => "*=",
+            .assign_div   => "/=",
+            .assign_mod   => "%=",
+            .assign_and   => "&=",
+            .assign_or    => "|=",
+            .assign_xor   => "^=",
+            .assign_lshift => "<<=",
+            .assign_rshift => ">>=",
             // Punctuation
             .dot          => ".",
             .comma        => ",",
@@ -231,6 +264,7 @@ pub const Kind = union(enum) {
             .backtick     => "`",
             .backslash    => "\\",
             .colon        => ":",
+            .double_colon => "::",
             .semicolon    => ";",
             .arrow        => "->",
             .wide_arrow   => "=>",
@@ -242,6 +276,7 @@ pub const Kind = union(enum) {
             .question     => "?",
             .range_exc    => "..",
             .range_inc    => "..=",
+            .ellipsis     => "...",
             .forward_app  => "<|",
             .reverse_app  => "|>",
             .concat       => "<>",
@@ -254,6 +289,9 @@ pub const Kind = union(enum) {
             .increment    => "++",
             .decrement    => "--",
             .square       => "**",
+            // Logical
+            .logical_and  => "&&",
+            .logical_or   => "||",
             // Bitwise
             .ampersand    => "&",
             .pipe         => "|",
@@ -269,6 +307,7 @@ pub const Kind = union(enum) {
             .equal        => "==",
             .not_equal    => "!=",
             // Miscelaneous
+            .doc_comment  => |dc| dc.items,
             .newline      => "\\n",
             .illegal      => "ILLEGAL",
             .eof          => "EOF"
@@ -306,8 +345,10 @@ pub const Keyword = enum {
     do,
     end,
     module,
+    import,
     record,
     @"struct",
+    @"union",
     tuple,
     @"enum",
     interface,
@@ -330,16 +371,25 @@ pub const Keyword = enum {
     @"test",
     @"fn",
     in,
+    impl,
+    sizeof,
+    goto,
     // Reserved
     @"inline",
     derive,
     static,
     macro,
     from,
-    impl,
     when,
     use,
     as,
+    @"export",
+    @"type",
+    trait,
+    unsafe,
+    @"extern",
+    @"async",
+    @"await",
 
     /// Converts a keyword into a string slice
     pub fn toStr(self: *const Keyword) []const u8 {
@@ -364,8 +414,10 @@ const keywords = std.StaticStringMap(Keyword).initComptime(.{
     .{"do", .do},
     .{"end", .end},
     .{"module", .module},
+    .{"import", .import},
     .{"record", .record},
     .{"struct", .@"struct"},
+    .{"union", .@"union"},
     .{"tuple", .tuple},
     .{"enum", .@"enum"},
     .{"interface", .interface},
@@ -388,16 +440,25 @@ const keywords = std.StaticStringMap(Keyword).initComptime(.{
     .{"test", .@"test"},
     .{"fn", .@"fn"},
     .{"in", .in},
+    .{"impl", .impl},
+    .{"sizeof", .sizeof},
+    .{"goto", .goto},
     // Reserved
     .{"inline", .@"inline"},
     .{"derive", .derive},
     .{"static", .static},
     .{"macro", .macro},
     .{"from", .from},
-    .{"impl", .impl},
     .{"when", .when},
     .{"use", .use},
-    .{"as", .as}
+    .{"as", .as},
+    .{"export", .@"export"},
+    .{"type", .@"type"},
+    .{"trait", .trait},
+    .{"unsafe", .unsafe},
+    .{"extern", .@"extern"},
+    .{"async", .@"async"},
+    .{"await", .@"await"}
 });
 
 // Compile time assert no missing or extra entries in keywords
@@ -475,4 +536,28 @@ const tests = struct {
 
         try testing.expectEqual(mode.mode, mode2.mode);
     }
+
+    test "keyword lookup" {
+        const testing = std.testing;
+
+        try testing.expectEqual(Keyword.@"fn", Kind.tryKeyword("fn").?.keyword);
+        try testing.expectEqual(null, Kind.tryKeyword("fns"));
+        try testing.expectEqual(null, Kind.tryMode("fn"));
+    }
+
+    test "single byte kinds" {
+        const testing = std.testing;
+
+        var interner = Interner.init(testing.allocator);
+        defer interner.deinit();
+
+        for ("=.,()[]{}'\"`\\:;@$#!?+-*/%&|^~<>") |byte| {
+            const kind = Kind.fromByte(byte);
+            try testing.expectEqualStrings(&.{byte}, try kind.toStr(&interner, testing.allocator));
+        }
+
+        try testing.expectEqual(Kind.eof, Kind.fromByte('\x00'));
+        try testing.expectEqual(Kind.newline, Kind.fromByte('\n'));
+        try testing.expectEqual(Kind.illegal, Kind.fromByte('\x01'));
+    }
 };
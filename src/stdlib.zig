@@ -0,0 +1,152 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The modules built into the language, whose functions programs call through
+//! the module's name, `math.sqrt(x)`. The checker gives them their types, the
+//! interpreter calls the functions here and the backends translate them
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+
+const ruka = @import("prelude.zig");
+const Value = ruka.Value;
+
+pub const fmt = @import("stdlib/fmt.zig");
+pub const io = @import("stdlib/io.zig");
+pub const math = @import("stdlib/math.zig");
+
+/// A function of a built in module
+pub const Function = struct {
+    module: []const u8,
+    name: []const u8,
+    params: []const Param,
+    ret: Ret,
+
+    pub const Param = enum {
+        /// An integer or float, of the same type as the function's other numbers
+        number,
+        float,
+        str
+    };
+
+    pub const Ret = enum {
+        /// Of the type of the function's number parameters
+        number,
+        float,
+        unit,
+        /// A Result holding the line read, or a message if none could be
+        line
+    };
+};
+
+pub const Module = struct {
+    name: []const u8,
+    functions: []const Function
+};
+
+pub const modules = [_]Module{
+    .{ .name = "io", .functions = &io.functions },
+    .{ .name = "math", .functions = &math.functions }
+};
+
+/// What the functions of the modules use when they are called by the interpreter
+pub const Context = struct {
+    /// Owns the values the functions produce
+    allocator: Allocator,
+    streams: io.Streams
+};
+
+pub const CallError = error{InvalidArguments, Overflow, IoFailed} || Allocator.Error;
+
+pub fn isModule(name: []const u8) bool {
+    for (modules) |module| if (std.mem.eql(u8, module.name, name)) return true;
+    return false;
+}
+
+/// The function named name in the module named module_name, null if there isn't one
+pub fn find(module_name: []const u8, name: []const u8) ?*const Function {
+    for (modules) |module| {
+        if (!std.mem.eql(u8, module.name, module_name)) continue;
+
+        for (module.functions) |*function| if (std.mem.eql(u8, function.name, name)) return function;
+    }
+
+    return null;
+}
+
+/// Calls function with arguments, whose number the caller checked matches its parameters
+pub fn call(function: *const Function, arguments: []const Value, context: Context) CallError!Value {
+    if (std.mem.eql(u8, function.module, "math")) return try math.call(function.name, arguments);
+    return try io.call(function.name, arguments, context);
+}
+
+test "stdlib modules" {
+    _ = fmt;
+    _ = io;
+    _ = math;
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const Environment = ruka.Environment;
+    const Unit = ruka.Unit;
+
+    test "programs read lines, apply math functions and print the results" {
+        const source =
+            \\let line = match io.read_line() { Ok(s) => s, Err(_) => "nothing" }
+            \\io.println(line)
+            \\let n = math.max(math.abs(-7), 3)
+            \\if math.sqrt(49.0) == 7.0 and n == 7 { io.print("seven") } else { io.print("not seven") }
+            \\n
+        ;
+
+        {
+            var input = std.io.fixedBufferStream(source);
+
+            var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+            defer unit.deinit();
+
+            const result = try unit.check();
+            defer result.deinit();
+
+            try testing.expectEqual(0, unit.diagnostics.items.len);
+        }
+
+        var env = try Environment.init(testing.allocator);
+        defer env.deinit();
+
+        var stdin = std.io.fixedBufferStream("ruka\nignored\n");
+        var stdout = std.ArrayList(u8).init(testing.allocator);
+        defer stdout.deinit();
+
+        env.io = .{ .input = stdin.reader().any(), .output = stdout.writer().any() };
+
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectEqual(Value{ .integer = 7 }, try unit.interpret(env));
+        try testing.expectEqualStrings("ruka\nseven", stdout.items);
+    }
+
+    test "misused stdlib functions are reported" {
+        const source =
+            \\let a = math.abs("a")
+            \\let b = math.root(2.0)
+            \\let c = io.println(1)
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+        try testing.expectEqual(3, unit.diagnostics.items.len);
+        try testing.expectEqualStrings("math.abs expects integers or floats, found str", unit.diagnostics.items[0].message);
+        try testing.expectEqualStrings("math has no function named root", unit.diagnostics.items[1].message);
+        try testing.expectEqualStrings("argument does not match the parameter's type", unit.diagnostics.items[2].message);
+    }
+};
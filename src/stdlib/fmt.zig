@@ -0,0 +1,178 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The templates of the fmt built in, `fmt("{} is {:x}", a, b)`. Each `{}` is
+//! replaced by the next argument, `{:x}` by an integer in hex and `{:.N}` by a
+//! float with N digits after the point. `{{` and `}}` are written as braces.
+//! Templates are string literals, so the checker counts their placeholders
+//! against the arguments, the interpreter formats values here and the backends
+//! translate templates to their own
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+
+const ruka = @import("../prelude.zig");
+const Value = ruka.Value;
+
+/// How a placeholder formats its argument
+pub const Specifier = union(enum) {
+    default,
+    hex,
+    /// The digits written after the point
+    precision: usize
+};
+
+/// Templates are split into the text between placeholders and the placeholders
+pub const Piece = union(enum) {
+    text: []const u8,
+    placeholder: Specifier
+};
+
+pub const ParseError = error{UnclosedPlaceholder, UnknownSpecifier, UnmatchedBrace};
+
+pub const FormatError = ParseError || error{InvalidArguments} || Allocator.Error;
+
+pub const Iterator = struct {
+    template: []const u8,
+    index: usize = 0,
+
+    pub fn init(template: []const u8) Iterator {
+        return .{ .template = template };
+    }
+
+    pub fn next(self: *Iterator) ParseError!?Piece {
+        const rest = self.template[self.index..];
+        if (rest.len == 0) return null;
+
+        if (std.mem.startsWith(u8, rest, "{{") or std.mem.startsWith(u8, rest, "}}")) {
+            self.index = self.index + 2;
+            return .{ .text = rest[0..1] };
+        }
+
+        switch (rest[0]) {
+            '}' => return error.UnmatchedBrace,
+            '{' => {
+                const close = std.mem.indexOfScalar(u8, rest, '}') orelse return error.UnclosedPlaceholder;
+                self.index = self.index + close + 1;
+
+                return .{ .placeholder = try parseSpecifier(rest[1..close]) };
+            },
+            else => {
+                const end = std.mem.indexOfAny(u8, rest, "{}") orelse rest.len;
+                self.index = self.index + end;
+
+                return .{ .text = rest[0..end] };
+            }
+        }
+    }
+};
+
+fn parseSpecifier(specifier: []const u8) ParseError!Specifier {
+    if (specifier.len == 0) return .default;
+    if (specifier[0] != ':') return error.UnknownSpecifier;
+
+    const options = specifier[1..];
+    if (std.mem.eql(u8, options, "x")) return .hex;
+    if (options.len > 1 and options[0] == '.') {
+        return .{ .precision = std.fmt.parseInt(usize, options[1..], 10) catch return error.UnknownSpecifier };
+    }
+
+    return error.UnknownSpecifier;
+}
+
+/// What is wrong with a template, for diagnostics
+pub fn describe(err: ParseError) []const u8 {
+    return switch (err) {
+        error.UnclosedPlaceholder => "unclosed placeholder in template, expected '}'",
+        error.UnknownSpecifier => "unknown placeholder in template, expected {}, {:x} or {:.N}",
+        error.UnmatchedBrace => "unmatched '}' in template, write '}}' for a brace"
+    };
+}
+
+pub fn countPlaceholders(template: []const u8) ParseError!usize {
+    var count: usize = 0;
+    var pieces = Iterator.init(template);
+    while (try pieces.next()) |piece| {
+        if (piece == .placeholder) count = count + 1;
+    }
+
+    return count;
+}
+
+/// Formats arguments into template, the caller owns the string returned
+pub fn format(allocator: Allocator, template: []const u8, arguments: []const Value) FormatError![]u8 {
+    var out = std.ArrayList(u8).init(allocator);
+    errdefer out.deinit();
+
+    const writer = out.writer();
+
+    var next: usize = 0;
+    var pieces = Iterator.init(template);
+    while (try pieces.next()) |piece| {
+        const specifier = switch (piece) {
+            .text => |text| {
+                try writer.writeAll(text);
+                continue;
+            },
+            .placeholder => |specifier| specifier
+        };
+
+        if (next == arguments.len) return error.InvalidArguments;
+        const argument = arguments[next];
+        next = next + 1;
+
+        switch (specifier) {
+            // Negative integers are written as their two's complement, as C writes them
+            .hex => switch (argument) {
+                .integer => |integer| try writer.print("{x}", .{@as(u64, @bitCast(integer))}),
+                else => return error.InvalidArguments
+            },
+            .precision => |precision| switch (argument) {
+                .float => |float| try writer.print("{d:.[1]}", .{float, precision}),
+                else => return error.InvalidArguments
+            },
+            // Strings and characters are written without their quotes
+            .default => switch (argument) {
+                .string => |string| try writer.writeAll(string),
+                .character => |character| try writer.writeByte(character),
+                else => try writer.print("{}", .{argument})
+            }
+        }
+    }
+
+    if (next != arguments.len) return error.InvalidArguments;
+    return try out.toOwnedSlice();
+}
+
+test "fmt templates" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    fn expectFormatted(expected: []const u8, template: []const u8, arguments: []const Value) !void {
+        const formatted = try format(testing.allocator, template, arguments);
+        defer testing.allocator.free(formatted);
+
+        try testing.expectEqualStrings(expected, formatted);
+    }
+
+    test "placeholders are replaced by their arguments" {
+        try expectFormatted("no placeholders", "no placeholders", &.{});
+        try expectFormatted("x = 3", "x = {}", &.{.{ .integer = 3 }});
+        try expectFormatted("ruka, c and true", "{}, {} and {}", &.{.{ .string = "ruka" }, .{ .character = 'c' }, .{ .boolean = true }});
+        try expectFormatted("ff ffffffffffffffff {braces}", "{:x} {:x} {{braces}}", &.{.{ .integer = 255 }, .{ .integer = -1 }});
+        try expectFormatted("3.14 2.0", "{:.2} {}", &.{.{ .float = 3.14159 }, .{ .float = 2.0 }});
+    }
+
+    test "malformed templates are rejected" {
+        try testing.expectEqual(3, try countPlaceholders("{} {:x} {:.3} {{}}"));
+        try testing.expectError(error.UnclosedPlaceholder, countPlaceholders("{"));
+        try testing.expectError(error.UnknownSpecifier, countPlaceholders("{:y}"));
+        try testing.expectError(error.UnknownSpecifier, countPlaceholders("{:.}"));
+        try testing.expectError(error.UnmatchedBrace, countPlaceholders("}"));
+        try testing.expectError(error.InvalidArguments, format(testing.allocator, "{:x}", &.{.{ .float = 1.0 }}));
+        try testing.expectError(error.InvalidArguments, format(testing.allocator, "{} {}", &.{.{ .integer = 1 }}));
+    }
+};
@@ -0,0 +1,130 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Writing strings to the standard output and error streams, and reading lines
+//! from the standard input
+
+const std = @import("std");
+const AnyReader = std.io.AnyReader;
+const AnyWriter = std.io.AnyWriter;
+
+const ruka = @import("../prelude.zig");
+const stdlib = ruka.stdlib;
+const Function = stdlib.Function;
+const Value = ruka.Value;
+
+pub const functions = [_]Function{
+    .{ .module = "io", .name = "print", .params = &.{.str}, .ret = .unit },
+    .{ .module = "io", .name = "println", .params = &.{.str}, .ret = .unit },
+    .{ .module = "io", .name = "eprint", .params = &.{.str}, .ret = .unit },
+    .{ .module = "io", .name = "eprintln", .params = &.{.str}, .ret = .unit },
+    .{ .module = "io", .name = "read_line", .params = &.{}, .ret = .line }
+};
+
+/// The streams the functions use, null for those of the process
+pub const Streams = struct {
+    input: ?AnyReader = null,
+    output: ?AnyWriter = null,
+    errors: ?AnyWriter = null
+};
+
+/// Lines longer than this can't be read
+const max_line = 64 * 1024;
+
+/// Calls the function named name
+pub fn call(name: []const u8, arguments: []const Value, context: stdlib.Context) stdlib.CallError!Value {
+    if (std.mem.eql(u8, name, "read_line")) return try readLine(context);
+
+    const string = switch (arguments[0]) {
+        .string => |string| string,
+        else => return error.InvalidArguments
+    };
+
+    const to_errors = std.mem.startsWith(u8, name, "e");
+    const newline = std.mem.endsWith(u8, name, "ln");
+
+    const file = (if (to_errors) std.io.getStdErr() else std.io.getStdOut()).writer();
+    const writer = (if (to_errors) context.streams.errors else context.streams.output) orelse file.any();
+
+    write(writer, string, newline) catch return error.IoFailed;
+    return .unit;
+}
+
+fn write(writer: AnyWriter, string: []const u8, newline: bool) anyerror!void {
+    try writer.writeAll(string);
+    if (newline) try writer.writeByte('\n');
+}
+
+// The line is returned without its newline, or an Err once the input ends
+fn readLine(context: stdlib.Context) stdlib.CallError!Value {
+    const stdin = std.io.getStdIn().reader();
+    const reader = context.streams.input orelse stdin.any();
+
+    const line = reader.readUntilDelimiterOrEofAlloc(context.allocator, '\n', max_line) catch |err| switch (err) {
+        error.OutOfMemory => return error.OutOfMemory,
+        else => return error.IoFailed
+    };
+
+    const values = try context.allocator.alloc(Value, 1);
+    const variant = if (line) |read| block: {
+        values[0] = .{ .string = std.mem.trimRight(u8, read, "\r") };
+        break :block "Ok";
+    } else block: {
+        values[0] = .{ .string = "end of input" };
+        break :block "Err";
+    };
+
+    return .{ .variant = .{
+        .@"enum" = "Result",
+        .name = variant,
+        .fields = .{ .tuple = values }
+    }};
+}
+
+test "io functions" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "strings are written to the output and error streams" {
+        var output = std.ArrayList(u8).init(testing.allocator);
+        defer output.deinit();
+        var errors = std.ArrayList(u8).init(testing.allocator);
+        defer errors.deinit();
+
+        const context: stdlib.Context = .{
+            .allocator = testing.allocator,
+            .streams = .{ .output = output.writer().any(), .errors = errors.writer().any() }
+        };
+
+        _ = try call("print", &.{.{ .string = "a" }}, context);
+        _ = try call("println", &.{.{ .string = "b" }}, context);
+        _ = try call("eprintln", &.{.{ .string = "c" }}, context);
+
+        try testing.expectEqualStrings("ab\n", output.items);
+        try testing.expectEqualStrings("c\n", errors.items);
+    }
+
+    test "lines are read until the input ends" {
+        var arena = std.heap.ArenaAllocator.init(testing.allocator);
+        defer arena.deinit();
+
+        var input = std.io.fixedBufferStream("first\r\nsecond");
+        const context: stdlib.Context = .{
+            .allocator = arena.allocator(),
+            .streams = .{ .input = input.reader().any() }
+        };
+
+        const expected = [_][]const u8{"first", "second"};
+        for (expected) |line| {
+            const value = try call("read_line", &.{}, context);
+            try testing.expectEqualStrings("Ok", value.variant.name);
+            try testing.expectEqualStrings(line, value.variant.fields.tuple[0].string);
+        }
+
+        const end = try call("read_line", &.{}, context);
+        try testing.expectEqualStrings("Err", end.variant.name);
+    }
+};
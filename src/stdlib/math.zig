@@ -0,0 +1,87 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Numeric functions, abs, min and max take integers or floats and the others floats
+
+const std = @import("std");
+
+const ruka = @import("../prelude.zig");
+const stdlib = ruka.stdlib;
+const Function = stdlib.Function;
+const Value = ruka.Value;
+
+pub const functions = [_]Function{
+    .{ .module = "math", .name = "abs", .params = &.{.number}, .ret = .number },
+    .{ .module = "math", .name = "min", .params = &.{.number, .number}, .ret = .number },
+    .{ .module = "math", .name = "max", .params = &.{.number, .number}, .ret = .number },
+    .{ .module = "math", .name = "sqrt", .params = &.{.float}, .ret = .float },
+    .{ .module = "math", .name = "pow", .params = &.{.float, .float}, .ret = .float },
+    .{ .module = "math", .name = "floor", .params = &.{.float}, .ret = .float },
+    .{ .module = "math", .name = "ceil", .params = &.{.float}, .ret = .float }
+};
+
+/// Calls the function named name, numbers given together must be of the same type
+pub fn call(name: []const u8, arguments: []const Value) stdlib.CallError!Value {
+    if (std.mem.eql(u8, name, "abs")) return switch (arguments[0]) {
+        .integer => |integer| {
+            if (integer == std.math.minInt(i64)) return error.Overflow;
+            return .{ .integer = if (integer < 0) -integer else integer };
+        },
+        .float => |float| .{ .float = @abs(float) },
+        else => error.InvalidArguments
+    };
+
+    if (std.mem.eql(u8, name, "min") or std.mem.eql(u8, name, "max")) {
+        const max = std.mem.eql(u8, name, "max");
+        const a = arguments[0];
+        const b = arguments[1];
+
+        if (a == .integer and b == .integer) {
+            return .{ .integer = if (max) @max(a.integer, b.integer) else @min(a.integer, b.integer) };
+        }
+        if (a == .float and b == .float) {
+            return .{ .float = if (max) @max(a.float, b.float) else @min(a.float, b.float) };
+        }
+
+        return error.InvalidArguments;
+    }
+
+    for (arguments) |argument| if (argument != .float) return error.InvalidArguments;
+    const x = arguments[0].float;
+
+    if (std.mem.eql(u8, name, "sqrt")) return .{ .float = @sqrt(x) };
+    if (std.mem.eql(u8, name, "pow")) return .{ .float = std.math.pow(f64, x, arguments[1].float) };
+    if (std.mem.eql(u8, name, "floor")) return .{ .float = @floor(x) };
+    if (std.mem.eql(u8, name, "ceil")) return .{ .float = @ceil(x) };
+
+    unreachable;
+}
+
+test "math functions" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    fn expectCall(expected: Value, name: []const u8, arguments: []const Value) !void {
+        try testing.expectEqual(expected, try call(name, arguments));
+    }
+
+    test "numbers keep their type" {
+        try expectCall(.{ .integer = 3 }, "abs", &.{.{ .integer = -3 }});
+        try expectCall(.{ .float = 2.5 }, "abs", &.{.{ .float = -2.5 }});
+        try expectCall(.{ .integer = -1 }, "min", &.{.{ .integer = 4 }, .{ .integer = -1 }});
+        try expectCall(.{ .float = 4.0 }, "max", &.{.{ .float = 4.0 }, .{ .float = -1.0 }});
+        try expectCall(.{ .float = 3.0 }, "sqrt", &.{.{ .float = 9.0 }});
+        try expectCall(.{ .float = 8.0 }, "pow", &.{.{ .float = 2.0 }, .{ .float = 3.0 }});
+        try expectCall(.{ .float = -2.0 }, "floor", &.{.{ .float = -1.5 }});
+        try expectCall(.{ .float = -1.0 }, "ceil", &.{.{ .float = -1.5 }});
+    }
+
+    test "invalid arguments are errors" {
+        try testing.expectError(error.InvalidArguments, call("min", &.{.{ .integer = 1 }, .{ .float = 1.0 }}));
+        try testing.expectError(error.InvalidArguments, call("sqrt", &.{.{ .integer = 4 }}));
+        try testing.expectError(error.Overflow, call("abs", &.{.{ .integer = std.math.minInt(i64) }}));
+    }
+};
@@ -0,0 +1,32 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Expectations shared by the tests of several modules, only available to tests
+
+const std = @import("std");
+
+/// Fails a test when haystack doesn't contain needle, reporting both as the
+/// string expectations of std.testing do
+pub fn expectContains(haystack: []const u8, needle: []const u8) !void {
+    if (std.mem.indexOf(u8, haystack, needle) != null) return;
+
+    std.debug.print("\n====== expected to contain: =========\n{s}", .{needle});
+    std.debug.print("\n====== instead found: ===============\n{s}", .{haystack});
+    std.debug.print("\n=====================================\n", .{});
+
+    return error.TestExpectedContains;
+}
+
+test "testing" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "expecting strings to contain others" {
+        try expectContains("> 42\n> ", "42\n");
+        try expectContains("anything", "");
+        try testing.expectError(error.TestExpectedContains, expectContains("> 42\n", "43"));
+    }
+};
@@ -0,0 +1,334 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Evaluates the values of const declarations while they are checked, which
+//! must be known before the program runs. Arithmetic, comparisons and logic of
+//! literals, sizeof, string concatenation and other consts can be evaluated.
+//! The value of each const is evaluated once and kept for the consts after it.
+//!
+//! Comptime blocks are evaluated the same way, and within them blocks, ifs and the
+//! let bindings of blocks can be evaluated too. The target being compiled for is
+//! known within them as TARGET_BITS, TARGET_OS and TARGET_ARCH
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const ConstantFolder = ruka.optimizer.ConstantFolder;
+const Node = ruka.Node;
+const Target = ruka.Target;
+const TypeTable = ruka.TypeTable;
+const Unit = ruka.Unit;
+
+unit: *Unit,
+types: *const TypeTable,
+/// The values of the consts declared so far by name, null for those whose values
+/// couldn't be evaluated
+values: StringHashMap(?Literal),
+/// The bindings of the blocks of the comptime blocks being evaluated, innermost last
+locals: ArrayList(Local),
+/// How many comptime blocks are being evaluated
+depth: usize,
+
+const ConstEval = @This();
+
+const Literal = Node.Literal;
+
+const Local = struct {
+    name: []const u8,
+    value: Literal
+};
+
+// Explicit so the recursive evaluating functions can resolve their error sets
+pub const Error = error{
+    /// The expression can't be evaluated before the program runs
+    NotConstant,
+    /// The expression refers to a const whose value couldn't be evaluated, which
+    /// was already reported
+    InvalidConstant
+} || Allocator.Error;
+
+pub fn init(unit: *Unit, types: *const TypeTable) ConstEval {
+    return .{
+        .unit = unit,
+        .types = types,
+        .values = .init(unit.allocator),
+        .locals = .init(unit.allocator),
+        .depth = 0
+    };
+}
+
+pub fn deinit(self: *ConstEval) void {
+    self.values.deinit();
+    self.locals.deinit();
+}
+
+/// Evaluates the value of the const called name, recording it for the consts
+/// declared after it
+pub fn declare(self: *ConstEval, name: []const u8, value: *const Node) Error!Literal {
+    const literal = self.eval(value) catch |err| {
+        try self.invalidate(name);
+        return err;
+    };
+
+    try self.values.put(name, literal);
+    return literal;
+}
+
+/// Records that the value of the const called name is unknown, so consts
+/// referring to it aren't reported again
+pub fn invalidate(self: *ConstEval, name: []const u8) Allocator.Error!void {
+    try self.values.put(name, null);
+}
+
+/// Forgets the const called name, once a binding which isn't a const hides it
+pub fn forget(self: *ConstEval, name: []const u8) void {
+    _ = self.values.remove(name);
+}
+
+/// The value of the const called name, if it has been declared
+pub fn valueOf(self: *const ConstEval, name: []const u8) ?Literal {
+    return self.values.get(name) orelse null;
+}
+
+/// The value of the name the target is known by within comptime blocks
+fn targetValue(triple: ?[]const u8, name: []const u8) ?Literal {
+    if (std.mem.eql(u8, name, "TARGET_BITS")) return .{ .integer = @as(i64, Target.pointerSizeOf(triple)) * 8 };
+    if (std.mem.eql(u8, name, "TARGET_OS")) return .{ .string = Target.osOf(triple) };
+    if (std.mem.eql(u8, name, "TARGET_ARCH")) return .{ .string = Target.archOf(triple) };
+    return null;
+}
+
+/// The literal the body of a comptime block evaluates to, which must have been checked
+pub fn evalComptime(self: *ConstEval, body: *const Node) Error!Literal {
+    self.depth = self.depth + 1;
+    defer self.depth = self.depth - 1;
+
+    return try self.eval(body);
+}
+
+/// The literal expr evaluates to, which must have been checked
+pub fn eval(self: *ConstEval, expr: *const Node) Error!Literal {
+    return switch (expr.kind) {
+        .literal => |literal| literal,
+        .identifier => |name| {
+            if (self.localOf(name)) |value| return value;
+            if (self.depth > 0) if (targetValue(self.unit.options.target, name)) |value| return value;
+
+            const value = self.values.get(name) orelse return error.NotConstant;
+            return value orelse error.InvalidConstant;
+        },
+        .prefix => |prefix| try self.evalPrefix(prefix),
+        .infix => |infix| try self.evalInfix(infix),
+        .@"comptime" => |body| try self.evalComptime(body),
+        .block => |block| if (self.depth > 0) try self.evalBlock(block) else error.NotConstant,
+        .@"if" => |conditional| if (self.depth > 0) try self.evalIf(conditional) else error.NotConstant,
+        .sizeof => |annotation| {
+            const @"type" = self.types.get(annotation.id) orelse return error.NotConstant;
+            const layout = @"type".layout(Target.pointerSizeOf(self.unit.options.target)) orelse return error.NotConstant;
+
+            return .{ .integer = @intCast(layout.size) };
+        },
+        else => error.NotConstant
+    };
+}
+
+// The innermost binding called name of the blocks being evaluated
+fn localOf(self: *const ConstEval, name: []const u8) ?Literal {
+    var i = self.locals.items.len;
+    while (i > 0) {
+        i = i - 1;
+        if (std.mem.eql(u8, self.locals.items[i].name, name)) return self.locals.items[i].value;
+    }
+
+    return null;
+}
+
+// Assignments aren't evaluated, so var bindings are only constant until assigned
+fn evalBlock(self: *ConstEval, block: Node.Block) Error!Literal {
+    const scope = self.locals.items.len;
+    defer self.locals.shrinkRetainingCapacity(scope);
+
+    var value: Literal = .unit;
+    for (block.statements) |statement| value = switch (statement.kind) {
+        .var_decl, .const_decl => |binding| binding: {
+            const initial = try self.eval(binding.value orelse return error.NotConstant);
+            try self.locals.append(.{ .name = binding.name, .value = initial });
+
+            break :binding .unit;
+        },
+        else => try self.eval(statement)
+    };
+
+    return value;
+}
+
+fn evalIf(self: *ConstEval, conditional: Node.If) Error!Literal {
+    const condition = try self.eval(conditional.condition);
+    if (condition != .boolean) return error.NotConstant;
+
+    if (condition.boolean) return try self.eval(conditional.then_block);
+    return if (conditional.else_block) |otherwise| try self.eval(otherwise) else .unit;
+}
+
+fn evalPrefix(self: *ConstEval, prefix: Node.Prefix) Error!Literal {
+    const operand = try self.eval(prefix.operand);
+
+    return switch (prefix.operator) {
+        .negate => switch (operand) {
+            .integer => |integer| try self.fold(.subtract, .{ .integer = 0 }, .{ .integer = integer }),
+            .float => |float| .{ .float = -float },
+            else => error.NotConstant
+        },
+        .not => switch (operand) {
+            .boolean => |boolean| .{ .boolean = !boolean },
+            else => error.NotConstant
+        },
+        .bit_not => switch (operand) {
+            .integer => |integer| .{ .integer = ~integer },
+            else => error.NotConstant
+        }
+    };
+}
+
+fn evalInfix(self: *ConstEval, infix: Node.Infix) Error!Literal {
+    const lhs = try self.eval(infix.lhs);
+
+    // The right operand of logical operators is only evaluated when the left doesn't decide them
+    switch (infix.operator) {
+        .@"and", .@"or" => {
+            if (lhs != .boolean) return error.NotConstant;
+            if (lhs.boolean == (infix.operator == .@"or")) return lhs;
+
+            const rhs = try self.eval(infix.rhs);
+            return if (rhs == .boolean) rhs else error.NotConstant;
+        },
+        else => {}
+    }
+
+    const rhs = try self.eval(infix.rhs);
+    if (infix.operator == .concat) {
+        if (lhs != .string or rhs != .string) return error.NotConstant;
+
+        return .{ .string = try std.mem.concat(self.unit.arena.allocator(), u8, &.{lhs.string, rhs.string}) };
+    }
+
+    return try self.fold(infix.operator, lhs, rhs);
+}
+
+// Operations which overflow or can't be folded, such as division by zero, fail
+// when the program runs so aren't constant
+fn fold(_: *ConstEval, operator: Node.Infix.Operator, lhs: Literal, rhs: Literal) Error!Literal {
+    const folded = ConstantFolder.foldLiterals(operator, lhs, rhs) catch return error.NotConstant;
+    return folded orelse error.NotConstant;
+}
+
+test "const eval" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    fn expectConst(source: []const u8, name: []const u8, expected: Literal) !void {
+        try expectConstFor(null, source, name, expected);
+    }
+
+    // The value of the const called name when compiling for target
+    fn expectConstFor(target: ?[]const u8, source: []const u8, name: []const u8, expected: Literal) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var options = Unit.UnitOptions.testing(input.reader().any(), std.io.null_writer.any());
+        options.options.target = target;
+
+        var unit = try Unit.init(options);
+        defer unit.deinit();
+
+        const result = try unit.check();
+        defer result.deinit();
+
+        // Evaluated again from the checked ast, as the checker's evaluator isn't kept
+        var consts = ConstEval.init(unit, result.types);
+        defer consts.deinit();
+
+        for (result.ast.root.kind.module.items) |item| switch (item.kind) {
+            .const_decl => |binding| _ = try consts.declare(binding.name, binding.value.?),
+            else => {}
+        };
+
+        try testing.expectEqualDeep(expected, consts.valueOf(name).?);
+    }
+
+    test "consts are evaluated" {
+        try expectConst("const MAX: i32 = 1 + 2 * 3", "MAX", .{ .integer = 7 });
+        try expectConst("const MSG: str = \"hello\" <> \" world\"", "MSG", .{ .string = "hello world" });
+        try expectConst("const HALF = -1.0 / 2.0", "HALF", .{ .float = -0.5 });
+        try expectConst("const ON = not (1 < 2) or 2 ** 3 == 8", "ON", .{ .boolean = true });
+        try expectConst("const BITS = sizeof(i32) * 8 - 1", "BITS", .{ .integer = 31 });
+    }
+
+    test "consts refer to the consts before them" {
+        try expectConst(
+            \\const WIDTH = 4
+            \\const HEIGHT = WIDTH * 2
+            \\const AREA = WIDTH * HEIGHT + sizeof((i8, i64))
+        , "AREA", .{ .integer = 48 });
+    }
+
+    test "comptime blocks are evaluated" {
+        try expectConst("const SIZE = comptime { sizeof(i64) }", "SIZE", .{ .integer = 8 });
+        try expectConst(
+            \\const WORD = comptime {
+            \\    let bytes = TARGET_BITS / 8
+            \\    if bytes < 4 { 4 } else { bytes }
+            \\}
+        , "WORD", .{ .integer = @max(4, @sizeOf(usize)) });
+        try expectConst("const NESTED = comptime { let a = 2\n comptime { a * 3 } }", "NESTED", .{ .integer = 6 });
+    }
+
+    test "comptime blocks know the target" {
+        const source =
+            \\const LIBRARY = comptime {
+            \\    if TARGET_OS == "windows" { "kernel32.dll" } else if TARGET_OS == "darwin" { "libSystem.dylib" } else { "libc.so.6" }
+            \\}
+            \\const BITS = comptime { TARGET_BITS }
+            \\const ARCH = comptime { TARGET_ARCH }
+        ;
+
+        try expectConstFor("x86_64-pc-windows-msvc", source, "LIBRARY", .{ .string = "kernel32.dll" });
+        try expectConstFor("aarch64-apple-darwin", source, "LIBRARY", .{ .string = "libSystem.dylib" });
+        try expectConstFor("riscv64-linux-gnu", source, "LIBRARY", .{ .string = "libc.so.6" });
+        try expectConstFor("i686-linux-gnu", source, "BITS", .{ .integer = 32 });
+        try expectConstFor("riscv64-linux-gnu", source, "ARCH", .{ .string = "riscv64" });
+    }
+
+    test "consts which can't be evaluated are reported" {
+        const source =
+            \\fn two() -> i64 { 2 }
+            \\let x = 1
+            \\const A = x + 1
+            \\const B = two()
+            \\const C = A * 2
+            \\const D = 1 / 0
+        ;
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        try testing.expectError(error.TypeCheckingFailed, unit.check());
+
+        // C refers to A, which was already reported
+        const expected = [_][]const u8{
+            "const expression must be evaluable at compile time",
+            "const expression must be evaluable at compile time",
+            "const expression must be evaluable at compile time"
+        };
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |message, diagnostic| {
+            try testing.expectEqualStrings(message, diagnostic.message);
+        }
+    }
+};
@@ -0,0 +1,314 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Checks every match covers each value of the type it matches, once the types
+//! of the matched values are known. Arms which can't be reached, as the arms
+//! before them match everything they do, are warned about
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("../prelude.zig");
+const Ast = ruka.Ast;
+const Node = ruka.Node;
+const Type = ruka.Type;
+const TypeTable = ruka.TypeTable;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+unit: *Unit,
+types: *const TypeTable,
+allocator: Allocator,
+
+const ExhaustivenessChecker = @This();
+
+const Walk = Visitor(ExhaustivenessChecker, Allocator.Error, void);
+
+/// The values of the matched type an alternative of a pattern matches. Values
+/// are split into cases for enums, one per variant, and bool, true then false
+const Case = union(enum) {
+    /// Every value
+    all,
+    /// Every value of a case
+    whole: usize,
+    /// Some of the values of a case
+    part: usize,
+    /// The value of a literal which isn't a boolean
+    literal: Node.Literal,
+    /// Values which aren't tracked, so never cover an arm
+    some
+};
+
+/// What the arms checked so far match, ignoring arms with guards
+const Coverage = struct {
+    all: bool,
+    /// Whether each case is wholly matched, empty for types without cases
+    cases: []bool,
+    literals: ArrayList(Node.Literal),
+
+    fn covers(self: *const Coverage, case: Case) bool {
+        if (self.all) return true;
+
+        return switch (case) {
+            .all, .some => false,
+            .whole, .part => |index| self.cases[index],
+            .literal => |literal| for (self.literals.items) |matched| {
+                if (sameLiteral(matched, literal)) break true;
+            } else false
+        };
+    }
+
+    fn add(self: *Coverage, case: Case) Allocator.Error!void {
+        switch (case) {
+            .all => self.all = true,
+            .whole => |index| {
+                self.cases[index] = true;
+                self.all = std.mem.indexOfScalar(bool, self.cases, false) == null;
+            },
+            .literal => |literal| try self.literals.append(literal),
+            .part, .some => {}
+        }
+    }
+};
+
+pub fn init(unit: *Unit, types: *const TypeTable) !*ExhaustivenessChecker {
+    const checker = try unit.allocator.create(ExhaustivenessChecker);
+
+    checker.* = .{
+        .unit = unit,
+        .types = types,
+        .allocator = unit.allocator
+    };
+
+    return checker;
+}
+
+pub fn deinit(self: *ExhaustivenessChecker) void {
+    self.allocator.destroy(self);
+}
+
+/// Reports each match in ast which doesn't cover its subject's type, and warns
+/// about each arm it can't reach
+pub fn check(self: *ExhaustivenessChecker, ast: *const Ast) Allocator.Error!void {
+    try Walk.visit(self, ast.root);
+}
+
+pub fn visitMatch(self: *ExhaustivenessChecker, node: *const Node, match: Node.Match) Allocator.Error!void {
+    try Walk.visitChildren(self, node);
+
+    const subject = self.types.get(match.subject.id) orelse return;
+    // Subjects which failed to check were reported already
+    if (subject == .invalid or subject == .variable) return;
+
+    const case_count: usize = switch (subject) {
+        .@"enum" => |enumeration| enumeration.variants.len,
+        .@"bool" => 2,
+        else => 0
+    };
+
+    var coverage: Coverage = .{
+        .all = false,
+        .cases = try self.allocator.alloc(bool, case_count),
+        .literals = .init(self.allocator)
+    };
+    defer {
+        self.allocator.free(coverage.cases);
+        coverage.literals.deinit();
+    }
+    @memset(coverage.cases, false);
+
+    var cases = ArrayList(Case).init(self.allocator);
+    defer cases.deinit();
+
+    for (match.arms) |arm| {
+        cases.clearRetainingCapacity();
+        try collectCases(arm.pattern, subject, &cases);
+
+        const reachable = for (cases.items) |case| {
+            if (!coverage.covers(case)) break true;
+        } else false;
+        if (!reachable) {
+            try self.unit.report(.initLint(.unreachable_pattern, "match arm is unreachable, earlier arms match every value it does", arm.pattern.span));
+        }
+
+        // Guards can reject any value, so guarded arms never count as covering
+        if (arm.guard != null) continue;
+        for (cases.items) |case| try coverage.add(case);
+    }
+
+    if (coverage.all or (case_count == 0 and subject == .@"enum")) return;
+    try self.reportMissing(node, subject, coverage);
+}
+
+// Lists the cases no arm covers, types without cases are only covered by arms
+// matching every value
+fn reportMissing(self: *ExhaustivenessChecker, node: *const Node, subject: Type, coverage: Coverage) Allocator.Error!void {
+    var missing = ArrayList(u8).init(self.allocator);
+    defer missing.deinit();
+
+    switch (subject) {
+        .@"enum" => |enumeration| for (enumeration.variants, coverage.cases) |variant, covered| if (!covered) {
+            if (missing.items.len > 0) try missing.appendSlice(", ");
+            try missing.writer().print("{s}::{s}", .{enumeration.name, variant.name});
+        },
+        .@"bool" => for ([_][]const u8{"true", "false"}, coverage.cases) |name, covered| if (!covered) {
+            if (missing.items.len > 0) try missing.appendSlice(", ");
+            try missing.appendSlice(name);
+        },
+        else => return try self.unit.createErrorFmt(node.span, "match does not cover every {}, it needs a _ arm", .{subject})
+    }
+
+    try self.unit.createErrorFmt(node.span, "match does not cover {s}", .{missing.items});
+}
+
+// Appends the case each alternative of pattern matches
+fn collectCases(pattern: Node.Pattern, subject: Type, cases: *ArrayList(Case)) Allocator.Error!void {
+    switch (pattern.kind) {
+        .@"or" => |alternatives| for (alternatives) |alternative| try collectCases(alternative, subject, cases),
+        else => try cases.append(caseOf(pattern, subject))
+    }
+}
+
+// Patterns which don't match the subject's type were reported by the type checker,
+// they match values which aren't tracked so they don't hide the arms after them
+fn caseOf(pattern: Node.Pattern, subject: Type) Case {
+    switch (pattern.kind) {
+        .identifier, .wildcard => return .all,
+        .literal => |literal| switch (literal) {
+            .unit => return if (subject == .unit) .all else .some,
+            .boolean => |value| {
+                if (subject == .@"bool") return .{ .whole = if (value) 0 else 1 };
+                return .{ .literal = literal };
+            },
+            else => return .{ .literal = literal }
+        },
+        .@"struct" => |structure_pattern| {
+            const structure = switch (subject) {
+                .@"struct" => |structure| structure,
+                else => return .some
+            };
+
+            if (!std.mem.eql(u8, structure_pattern.name, structure.name)) return .some;
+            return if (pattern.isIrrefutable()) .all else .some;
+        },
+        .variant => |variant| {
+            const enumeration = switch (subject) {
+                .@"enum" => |enumeration| enumeration,
+                else => return .some
+            };
+
+            const path = variant.path;
+            if (path.len > 2 or (path.len == 2 and !std.mem.eql(u8, path[0], enumeration.name))) return .some;
+            const index = enumeration.variantIndex(path[path.len - 1]) orelse return .some;
+
+            const irrefutable = switch (variant.fields) {
+                .unit => true,
+                .tuple => |fields| for (fields) |field| {
+                    if (!field.isIrrefutable()) break false;
+                } else true,
+                .@"struct" => |fields| for (fields) |field| {
+                    if (!field.pattern.isIrrefutable()) break false;
+                } else true
+            };
+
+            return if (irrefutable) .{ .whole = index } else .{ .part = index };
+        },
+        .tuple => return if (subject == .tuple and pattern.isIrrefutable()) .all else .some,
+        .@"or" => unreachable
+    }
+}
+
+fn sameLiteral(a: Node.Literal, b: Node.Literal) bool {
+    if (std.meta.activeTag(a) != std.meta.activeTag(b)) return false;
+
+    return switch (a) {
+        .integer => |value| value == b.integer,
+        .float => |value| value == b.float,
+        .boolean => |value| value == b.boolean,
+        .string => |value| std.mem.eql(u8, value, b.string),
+        .character => |value| value == b.character,
+        .unit => true
+    };
+}
+
+test "exhaustiveness checker" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const Diagnostic = ruka.Diagnostic;
+
+    fn expectDiagnostics(source: []const u8, expected: []const struct { Diagnostic.Severity, []const u8 }) !void {
+        var input = std.io.fixedBufferStream(source);
+
+        var unit = try Unit.init(.testing(input.reader().any(), std.io.null_writer.any()));
+        defer unit.deinit();
+
+        if (unit.check()) |result| {
+            result.deinit();
+        } else |err| {
+            try testing.expectEqual(error.TypeCheckingFailed, err);
+        }
+
+        try testing.expectEqual(expected.len, unit.diagnostics.items.len);
+        for (expected, unit.diagnostics.items) |diagnostic, actual| {
+            try testing.expectEqual(diagnostic[0], actual.severity);
+            try testing.expectEqualStrings(diagnostic[1], actual.message);
+        }
+    }
+
+    test "enum matches missing a variant are reported" {
+        try expectDiagnostics(
+            \\enum Color { Red, Green, Blue }
+            \\let c = Color::Red
+            \\let a = match c { Color::Red => 1, Color::Blue => 2 }
+            \\let b = match c { Color::Red | Color::Green => 1, Color::Blue if a > 1 => 2 }
+            \\let d = match c { Color::Red => 1, _ => 2, Color::Green => 3 }
+        , &.{
+            .{ .@"error", "match does not cover Color::Green" },
+            .{ .@"error", "match does not cover Color::Blue" },
+            .{ .warning, "match arm is unreachable, earlier arms match every value it does" }
+        });
+    }
+
+    test "option matches missing a variant are reported" {
+        try expectDiagnostics(
+            \\let o: Option<i64> = Some(1)
+            \\let a = match o { Some(n) => n }
+            \\let b = match o { Some(n) if n > 0 => n, None => 0 }
+            \\let c = match o { Some(_) => 1, None => 0 }
+        , &.{
+            .{ .@"error", "match does not cover Option::None" },
+            .{ .@"error", "match does not cover Option::Some" }
+        });
+    }
+
+    test "bool matches missing a value are reported" {
+        try expectDiagnostics(
+            \\let t = true
+            \\let a = match t { true => 1 }
+            \\let b = match t { true => 1, false => 0, true => 2 }
+        , &.{
+            .{ .@"error", "match does not cover false" },
+            .{ .warning, "match arm is unreachable, earlier arms match every value it does" }
+        });
+    }
+
+    test "integer matches are covered by wildcards" {
+        try expectDiagnostics(
+            \\let n = 3
+            \\let a = match n { 0 => 1, m if m > 3 => m, _ => 2 }
+            \\let b = match n { 0 | 1 => 1, m => m }
+        , &.{});
+
+        try expectDiagnostics(
+            \\let n = 3
+            \\let a = match n { 0 => 1, 1 | 0 => 2, 2 => 3 }
+        , &.{
+            .{ .@"error", "match does not cover every i64, it needs a _ arm" }
+        });
+    }
+};
@@ -0,0 +1,272 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! Infers the types of bindings declared without an annotation, from how they
+//! are used. Each such binding is given a type variable, and each use constrains
+//! it, unifying the variables used together with a union find so each set of
+//! them has a single type
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+
+const ruka = @import("../prelude.zig");
+const Span = ruka.Span;
+const Type = ruka.Type;
+
+/// One entry per type variable, indexed by the variable
+variables: ArrayList(Variable),
+
+allocator: Allocator,
+
+const Inference = @This();
+
+pub const TypeVar = u32;
+
+/// The family of types a variable is known to be in before its type is known.
+/// Bindings of numeric literals can be used as any type of the literal's family
+pub const Class = enum {
+    any,
+    integer,
+    float,
+
+    pub fn admits(self: Class, @"type": Type) bool {
+        return switch (self) {
+            .any => true,
+            .integer => @"type".isInteger() or @"type" == .invalid,
+            .float => @"type".isFloat() or @"type" == .invalid
+        };
+    }
+
+    /// The type variables of the class are given when nothing else is inferred
+    pub fn default(self: Class) ?Type {
+        return switch (self) {
+            .any => null,
+            .integer => .@"i64",
+            .float => .@"f64"
+        };
+    }
+};
+
+/// What a use of a variable requires of it
+pub const Constraint = union(enum) {
+    @"type": Type,
+    class: Class,
+
+    pub fn format(self: Constraint, comptime _: []const u8, _: std.fmt.FormatOptions, writer: anytype) !void {
+        switch (self) {
+            .@"type" => |@"type"| try writer.print("{}", .{@"type"}),
+            .class => |class| try writer.writeAll(switch (class) {
+                .any => "any type",
+                .integer => "an integer",
+                .float => "a float"
+            })
+        }
+    }
+};
+
+/// A constraint which contradicts what was inferred for a variable earlier
+pub const Conflict = struct {
+    previous: Constraint,
+    /// Where previous was inferred
+    origin: Span
+};
+
+const Variable = struct {
+    /// The name and span of the binding the variable was created for
+    name: []const u8,
+    span: Span,
+
+    parent: TypeVar,
+    rank: u8,
+    /// Only kept up to date for roots, each describes the set it is the root of
+    bound: ?Type,
+    class: Class,
+    /// Where the set was last constrained
+    origin: Span
+};
+
+pub fn init(allocator: Allocator) Inference {
+    return .{
+        .variables = .init(allocator),
+        .allocator = allocator
+    };
+}
+
+pub fn deinit(self: *Inference) void {
+    self.variables.deinit();
+}
+
+/// The number of variables created, variables created later have greater indices
+pub fn count(self: *const Inference) usize {
+    return self.variables.items.len;
+}
+
+/// Creates a variable for the binding name, which is known to be in class
+/// because of the value at origin
+pub fn fresh(self: *Inference, name: []const u8, span: Span, class: Class, origin: Span) !Type {
+    const variable: TypeVar = @intCast(self.variables.items.len);
+
+    try self.variables.append(.{
+        .name = name,
+        .span = span,
+        .parent = variable,
+        .rank = 0,
+        .bound = null,
+        .class = class,
+        .origin = origin
+    });
+
+    return .{ .variable = variable };
+}
+
+pub fn nameOf(self: *const Inference, variable: TypeVar) []const u8 {
+    return self.variables.items[variable].name;
+}
+
+pub fn spanOf(self: *const Inference, variable: TypeVar) Span {
+    return self.variables.items[variable].span;
+}
+
+/// The root of the set variable is in, compressing the path to it
+pub fn find(self: *Inference, variable: TypeVar) TypeVar {
+    var root = variable;
+    while (self.variables.items[root].parent != root) root = self.variables.items[root].parent;
+
+    var current = variable;
+    while (current != root) {
+        const parent = self.variables.items[current].parent;
+        self.variables.items[current].parent = root;
+        current = parent;
+    }
+
+    return root;
+}
+
+pub fn classOf(self: *Inference, variable: TypeVar) Class {
+    return self.variables.items[self.find(variable)].class;
+}
+
+/// The type inferred for a variable, or the root of its set while nothing is.
+/// Types which aren't variables are returned unchanged
+pub fn resolve(self: *Inference, @"type": Type) Type {
+    const variable = switch (@"type") {
+        .variable => |variable| variable,
+        else => return @"type"
+    };
+
+    const root = self.find(variable);
+    return self.variables.items[root].bound orelse .{ .variable = root };
+}
+
+/// Constrains variable by a use at span, returning what was inferred for it
+/// earlier if the constraint contradicts it
+pub fn constrain(self: *Inference, variable: TypeVar, constraint: Constraint, span: Span) ?Conflict {
+    const root = self.find(variable);
+    const entry = &self.variables.items[root];
+
+    switch (constraint) {
+        .@"type" => |@"type"| switch (self.resolve(@"type")) {
+            .variable => |other| return self.unite(root, other),
+            else => |resolved| {
+                if (entry.bound) |bound| {
+                    if (bound == .invalid or resolved == .invalid or bound.eql(resolved)) return null;
+                    return .{ .previous = .{ .@"type" = bound }, .origin = entry.origin };
+                }
+
+                if (!entry.class.admits(resolved)) return .{ .previous = .{ .class = entry.class }, .origin = entry.origin };
+
+                entry.bound = resolved;
+                entry.origin = span;
+            }
+        },
+        .class => |class| {
+            if (entry.bound) |bound| {
+                if (class.admits(bound)) return null;
+                return .{ .previous = .{ .@"type" = bound }, .origin = entry.origin };
+            }
+
+            if (class == .any or class == entry.class) return null;
+            if (entry.class != .any) return .{ .previous = .{ .class = entry.class }, .origin = entry.origin };
+
+            entry.class = class;
+            entry.origin = span;
+        }
+    }
+
+    return null;
+}
+
+// Merges the sets of two roots, the one with the greater rank becomes the root
+// so paths stay short
+fn unite(self: *Inference, a: TypeVar, b: TypeVar) ?Conflict {
+    if (a == b) return null;
+
+    // What was inferred for b constrains a, so a describes both sets
+    const other = self.variables.items[b];
+    const conflict = if (other.bound) |bound| self.constrain(a, .{ .@"type" = bound }, other.origin)
+        else self.constrain(a, .{ .class = other.class }, other.origin);
+    if (conflict) |c| return c;
+
+    const merged = &self.variables.items[a];
+
+    if (merged.rank < other.rank) {
+        merged.parent = b;
+
+        const root = &self.variables.items[b];
+        root.bound = merged.bound;
+        root.class = merged.class;
+        root.origin = merged.origin;
+    } else {
+        self.variables.items[b].parent = a;
+        if (merged.rank == other.rank) merged.rank = merged.rank + 1;
+    }
+
+    return null;
+}
+
+test "inference" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    const span: Span = .{ .file = "test" };
+
+    test "variables united are inferred as the same type" {
+        var inference = Inference.init(testing.allocator);
+        defer inference.deinit();
+
+        const a = (try inference.fresh("a", span, .any, span)).variable;
+        const b = (try inference.fresh("b", span, .integer, span)).variable;
+        const c = (try inference.fresh("c", span, .any, span)).variable;
+
+        try testing.expect(inference.constrain(a, .{ .@"type" = .{ .variable = b } }, span) == null);
+        try testing.expect(inference.constrain(c, .{ .@"type" = .{ .variable = a } }, span) == null);
+        try testing.expectEqual(inference.find(a), inference.find(c));
+        try testing.expectEqual(Class.integer, inference.classOf(c));
+
+        try testing.expect(inference.constrain(c, .{ .@"type" = .@"i32" }, span) == null);
+        try testing.expectEqual(Type.@"i32", inference.resolve(.{ .variable = a }));
+        try testing.expectEqual(Type.@"i32", inference.resolve(.{ .variable = b }));
+    }
+
+    test "contradicting constraints are conflicts" {
+        var inference = Inference.init(testing.allocator);
+        defer inference.deinit();
+
+        const origin: Span = .{ .file = "test", .pos = .{ .line = 2, .col = 1 } };
+
+        const a = (try inference.fresh("a", span, .float, origin)).variable;
+        const b = (try inference.fresh("b", span, .any, span)).variable;
+
+        const conflict = inference.constrain(a, .{ .@"type" = .@"bool" }, span).?;
+        try testing.expectEqual(Constraint{ .class = .float }, conflict.previous);
+        try testing.expectEqual(2, conflict.origin.pos.line);
+
+        try testing.expect(inference.constrain(b, .{ .@"type" = .@"i64" }, origin) == null);
+        // The class of a contradicts what b was inferred as
+        try testing.expect(inference.constrain(a, .{ .@"type" = .{ .variable = b } }, span) != null);
+    }
+};
@@ -0,0 +1,101 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+//! The labels of a function's body, which the gotos within it may jump to, and
+//! the bindings gotos may jump over. The functions and closures defined within
+//! the body have labels of their own, so aren't searched
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArrayList = std.ArrayList;
+const StringHashMap = std.StringHashMap;
+
+const ruka = @import("../prelude.zig");
+const Node = ruka.Node;
+const Unit = ruka.Unit;
+const Visitor = ruka.Visitor;
+
+unit: *Unit,
+labels: StringHashMap(*const Node),
+/// The let, var and const bindings of the body, in source order
+bindings: ArrayList(Binding),
+/// Whether the body defers anything, which gotos would skip running
+defers: bool,
+
+const Labels = @This();
+
+const Walk = Visitor(Labels, Allocator.Error, void);
+
+pub const Binding = struct {
+    node: *const Node,
+    name: []const u8,
+    /// Where the block it is declared in ends, it is in scope until then
+    scope_end: usize
+};
+
+/// Finds the labels of body, reporting labels defined more than once
+pub fn find(unit: *Unit, body: *const Node) Allocator.Error!Labels {
+    var labels = Labels{
+        .unit = unit,
+        .labels = .init(unit.allocator),
+        .bindings = .init(unit.allocator),
+        .defers = false
+    };
+    errdefer labels.deinit();
+
+    try Walk.visit(&labels, body);
+    return labels;
+}
+
+pub fn deinit(self: *Labels) void {
+    self.labels.deinit();
+    self.bindings.deinit();
+}
+
+pub fn get(self: *const Labels, name: []const u8) ?*const Node {
+    return self.labels.get(name);
+}
+
+/// The first binding a goto to label skips the declaration of, while jumping into
+/// its scope. Gotos jumping backwards, or out of the binding's block, skip none
+pub fn skippedBinding(self: *const Labels, goto: *const Node, label: *const Node) ?Binding {
+    for (self.bindings.items) |binding| {
+        const start = binding.node.span.start;
+        if (goto.span.end <= start and start < label.span.start and label.span.start < binding.scope_end) {
+            return binding;
+        }
+    }
+
+    return null;
+}
+
+pub fn visitBlock(self: *Labels, node: *const Node, block: Node.Block) Allocator.Error!void {
+    for (block.statements) |statement| switch (statement.kind) {
+        .var_decl, .const_decl => |binding| try self.bindings.append(.{
+            .node = statement,
+            .name = binding.name,
+            .scope_end = node.span.end
+        }),
+        else => {}
+    };
+
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitLabel(self: *Labels, node: *const Node, name: []const u8) Allocator.Error!void {
+    const entry = try self.labels.getOrPut(name);
+    if (entry.found_existing) {
+        return try self.unit.createErrorFmt(node.span, "label {s} is defined more than once in this function", .{name});
+    }
+
+    entry.value_ptr.* = node;
+}
+
+pub fn visitDefer(self: *Labels, node: *const Node, _: *Node) Allocator.Error!void {
+    self.defers = true;
+    try Walk.visitChildren(self, node);
+}
+
+pub fn visitFnDef(_: *Labels, _: *const Node, _: Node.FunctionDef) Allocator.Error!void {}
+
+pub fn visitClosure(_: *Labels, _: *const Node, _: Node.Closure) Allocator.Error!void {}
@@ -0,0 +1,479 @@
+// @author: ruka-lang
+// @created: 2026-10-14
+
+const std = @import("std");
+const Allocator = std.mem.Allocator;
+const ArenaAllocator = std.heap.ArenaAllocator;
+const ArrayList = std.ArrayList;
+const AutoHashMap = std.AutoHashMap;
+
+const ruka = @import("../prelude.zig");
+const Node = ruka.Node;
+const Span = ruka.Span;
+const Type = ruka.Type;
+
+/// The type of each checked node, keyed by the node's id
+types: AutoHashMap(Node.Id, Type),
+/// The types each call of a generic function substitutes for its type parameters,
+/// in the order they are declared, keyed by the call's id
+instantiations: AutoHashMap(Node.Id, []const Type),
+/// The types of bindings declared without an annotation or value, which are
+/// inferred from the values later assigned to them, keyed by the binding's id
+bindings: AutoHashMap(Node.Id, Type),
+/// The types of the bindings each closure captures, in the order it captures
+/// them, keyed by the closure's id
+captures: AutoHashMap(Node.Id, []Type),
+/// The spans of the definitions identifiers refer to, keyed by the identifier's
+/// id. Names defined by the prelude have no definition in source, so aren't kept
+definitions: AutoHashMap(Node.Id, Span),
+/// The instances of generic structs, each is created once so struct types can
+/// still be compared by identity
+instances: ArrayList(*Type.Struct),
+/// The instances of generic enums, created once like those of structs
+enum_instances: ArrayList(*Type.Enum),
+/// The traits implemented by each struct, as the impls of the program declare them
+implementations: ArrayList(Implementation),
+
+/// Owns the memory referenced by compound types
+arena: ArenaAllocator,
+allocator: Allocator,
+
+const TypeTable = @This();
+
+pub const Implementation = struct {
+    trait: *const Type.Trait,
+    structure: *const Type.Struct
+};
+
+pub fn init(allocator: Allocator) !*TypeTable {
+    const table = try allocator.create(TypeTable);
+
+    table.* = .{
+        .types = .init(allocator),
+        .instantiations = .init(allocator),
+        .bindings = .init(allocator),
+        .captures = .init(allocator),
+        .definitions = .init(allocator),
+        .instances = .init(allocator),
+        .enum_instances = .init(allocator),
+        .implementations = .init(allocator),
+        .arena = .init(allocator),
+        .allocator = allocator
+    };
+
+    return table;
+}
+
+pub fn deinit(self: *TypeTable) void {
+    self.types.deinit();
+    self.instantiations.deinit();
+    self.bindings.deinit();
+    self.captures.deinit();
+    self.definitions.deinit();
+    self.instances.deinit();
+    self.enum_instances.deinit();
+    self.implementations.deinit();
+    self.arena.deinit();
+    self.allocator.destroy(self);
+}
+
+/// Records the type of a node, replacing any previously recorded type
+pub fn put(self: *TypeTable, id: Node.Id, @"type": Type) !void {
+    try self.types.put(id, @"type");
+}
+
+pub fn get(self: *const TypeTable, id: Node.Id) ?Type {
+    return self.types.get(id);
+}
+
+/// Records the type arguments a call of a generic function was instantiated with
+pub fn putInstantiation(self: *TypeTable, id: Node.Id, arguments: []const Type) !void {
+    try self.instantiations.put(id, try self.arena.allocator().dupe(Type, arguments));
+}
+
+pub fn getInstantiation(self: *const TypeTable, id: Node.Id) ?[]const Type {
+    return self.instantiations.get(id);
+}
+
+pub fn putBinding(self: *TypeTable, id: Node.Id, @"type": Type) !void {
+    try self.bindings.put(id, @"type");
+}
+
+pub fn getBinding(self: *const TypeTable, id: Node.Id) ?Type {
+    return self.bindings.get(id);
+}
+
+pub fn putCaptures(self: *TypeTable, id: Node.Id, types: []const Type) !void {
+    try self.captures.put(id, try self.arena.allocator().dupe(Type, types));
+}
+
+pub fn getCaptures(self: *const TypeTable, id: Node.Id) ?[]const Type {
+    return self.captures.get(id);
+}
+
+pub fn putDefinition(self: *TypeTable, id: Node.Id, span: Span) !void {
+    try self.definitions.put(id, span);
+}
+
+pub fn getDefinition(self: *const TypeTable, id: Node.Id) ?Span {
+    return self.definitions.get(id);
+}
+
+/// Creates a function type owned by the table
+pub fn function(self: *TypeTable, params: []const Type, ret: Type) !Type {
+    return try self.genericFunction(&.{}, params, ret);
+}
+
+/// Creates a function type generic over type_params owned by the table
+pub fn genericFunction(self: *TypeTable, type_params: []const []const u8, params: []const Type, ret: Type) !Type {
+    const allocator = self.arena.allocator();
+
+    const return_type = try allocator.create(Type);
+    return_type.* = ret;
+
+    return .{ .function = .{
+        .type_params = try self.dupeNames(type_params),
+        .params = try allocator.dupe(Type, params),
+        .ret = return_type
+    }};
+}
+
+/// Creates a type parameter whose name is owned by the table
+pub fn parameter(self: *TypeTable, name: []const u8) !Type {
+    return .{ .parameter = try self.arena.allocator().dupe(u8, name) };
+}
+
+/// Creates a range type owned by the table
+pub fn range(self: *TypeTable, element: Type) !Type {
+    const element_type = try self.arena.allocator().create(Type);
+    element_type.* = element;
+
+    return .{ .range = element_type };
+}
+
+/// Creates a future type owned by the table
+pub fn future(self: *TypeTable, value: Type) !Type {
+    const value_type = try self.arena.allocator().create(Type);
+    value_type.* = value;
+
+    return .{ .future = value_type };
+}
+
+/// Creates an array type of len elements owned by the table
+pub fn array(self: *TypeTable, element: Type, len: usize) !Type {
+    const element_type = try self.arena.allocator().create(Type);
+    element_type.* = element;
+
+    return .{ .array = .{
+        .element = element_type,
+        .len = len
+    }};
+}
+
+/// Creates a tuple type owned by the table
+pub fn tuple(self: *TypeTable, elements: []const Type) !Type {
+    return .{ .tuple = try self.arena.allocator().dupe(Type, elements) };
+}
+
+/// Creates a slice type owned by the table
+pub fn slice(self: *TypeTable, element: Type) !Type {
+    const element_type = try self.arena.allocator().create(Type);
+    element_type.* = element;
+
+    return .{ .slice = element_type };
+}
+
+/// Creates a reference type owned by the table
+pub fn reference(self: *TypeTable, element: Type) !Type {
+    const element_type = try self.arena.allocator().create(Type);
+    element_type.* = element;
+
+    return .{ .reference = element_type };
+}
+
+/// Creates a struct type owned by the table, its fields are set once they are resolved
+pub fn structure(self: *TypeTable, name: []const u8) !*Type.Struct {
+    const allocator = self.arena.allocator();
+
+    const struct_type = try allocator.create(Type.Struct);
+    struct_type.* = .{
+        .name = try allocator.dupe(u8, name),
+        .fields = &.{}
+    };
+
+    return struct_type;
+}
+
+/// Creates a struct type generic over type_params owned by the table
+pub fn genericStructure(self: *TypeTable, name: []const u8, type_params: []const []const u8) !*Type.Struct {
+    const struct_type = try self.structure(name);
+    struct_type.type_params = try self.dupeNames(type_params);
+
+    return struct_type;
+}
+
+/// The instance of the generic struct definition substituting arguments for its
+/// type parameters, null if it hasn't been created
+pub fn findInstance(self: *const TypeTable, definition: *const Type.Struct, arguments: []const Type) ?*Type.Struct {
+    for (self.instances.items) |instance| {
+        if (instance.generic != definition) continue;
+
+        for (instance.arguments, arguments) |argument, other| {
+            if (!argument.eql(other)) break;
+        } else return instance;
+    }
+
+    return null;
+}
+
+/// Creates an instance of the generic struct definition, its fields are set once
+/// the arguments are substituted into those of the definition
+pub fn instance(self: *TypeTable, definition: *const Type.Struct, arguments: []const Type) !*Type.Struct {
+    std.debug.assert(arguments.len == definition.type_params.len);
+    try self.instances.ensureUnusedCapacity(1);
+
+    const struct_type = try self.structure(definition.name);
+    struct_type.generic = definition;
+    struct_type.arguments = try self.arena.allocator().dupe(Type, arguments);
+
+    self.instances.appendAssumeCapacity(struct_type);
+    return struct_type;
+}
+
+/// Sets the fields of a struct type created by the table
+pub fn setFields(self: *TypeTable, struct_type: *Type.Struct, fields: []const Type.Struct.Field) !void {
+    struct_type.fields = try self.dupeFields(fields);
+}
+
+/// Creates an enum type owned by the table, its variants are set once they are resolved
+pub fn enumeration(self: *TypeTable, name: []const u8) !*Type.Enum {
+    const allocator = self.arena.allocator();
+
+    const enum_type = try allocator.create(Type.Enum);
+    enum_type.* = .{
+        .name = try allocator.dupe(u8, name),
+        .variants = &.{}
+    };
+
+    return enum_type;
+}
+
+/// Creates an enum type generic over type_params owned by the table
+pub fn genericEnumeration(self: *TypeTable, name: []const u8, type_params: []const []const u8) !*Type.Enum {
+    const enum_type = try self.enumeration(name);
+    enum_type.type_params = try self.dupeNames(type_params);
+
+    return enum_type;
+}
+
+/// The instance of the generic enum definition substituting arguments for its
+/// type parameters, null if it hasn't been created
+pub fn findEnumInstance(self: *const TypeTable, definition: *const Type.Enum, arguments: []const Type) ?*Type.Enum {
+    for (self.enum_instances.items) |instance| {
+        if (instance.generic != definition) continue;
+
+        for (instance.arguments, arguments) |argument, other| {
+            if (!argument.eql(other)) break;
+        } else return instance;
+    }
+
+    return null;
+}
+
+/// Creates an instance of the generic enum definition, its variants are set once
+/// the arguments are substituted into those of the definition
+pub fn enumInstance(self: *TypeTable, definition: *const Type.Enum, arguments: []const Type) !*Type.Enum {
+    std.debug.assert(arguments.len == definition.type_params.len);
+    try self.enum_instances.ensureUnusedCapacity(1);
+
+    const enum_type = try self.enumeration(definition.name);
+    enum_type.generic = definition;
+    enum_type.arguments = try self.arena.allocator().dupe(Type, arguments);
+
+    self.enum_instances.appendAssumeCapacity(enum_type);
+    return enum_type;
+}
+
+/// Sets the variants of an enum type created by the table
+pub fn setVariants(self: *TypeTable, enum_type: *Type.Enum, variants: []const Type.Enum.Variant) !void {
+    const allocator = self.arena.allocator();
+
+    const owned = try allocator.alloc(Type.Enum.Variant, variants.len);
+    for (owned, variants) |*variant, resolved| variant.* = .{
+        .name = try allocator.dupe(u8, resolved.name),
+        .fields = switch (resolved.fields) {
+            .unit => .unit,
+            .tuple => |types| .{ .tuple = try allocator.dupe(Type, types) },
+            .@"struct" => |fields| .{ .@"struct" = try self.dupeFields(fields) }
+        }
+    };
+
+    enum_type.variants = owned;
+}
+
+/// Creates a trait type owned by the table, its methods are set once they are resolved
+pub fn trait(self: *TypeTable, name: []const u8) !*Type.Trait {
+    const allocator = self.arena.allocator();
+
+    const trait_type = try allocator.create(Type.Trait);
+    trait_type.* = .{
+        .name = try allocator.dupe(u8, name),
+        .methods = &.{}
+    };
+
+    return trait_type;
+}
+
+/// Sets the methods of a trait type created by the table, the types of their
+/// functions must be owned by the table
+pub fn setMethods(self: *TypeTable, trait_type: *Type.Trait, methods: []const Type.Trait.Method) !void {
+    const allocator = self.arena.allocator();
+
+    const owned = try allocator.alloc(Type.Trait.Method, methods.len);
+    for (owned, methods) |*method, resolved| {
+        method.* = resolved;
+        method.name = try allocator.dupe(u8, resolved.name);
+    }
+
+    trait_type.methods = owned;
+}
+
+/// Records that structure implements trait
+pub fn implement(self: *TypeTable, trait_type: *const Type.Trait, structure_type: *const Type.Struct) !void {
+    try self.implementations.append(.{ .trait = trait_type, .structure = structure_type });
+}
+
+/// Whether values of type implement trait, structs do if an impl of the trait is
+/// written for them and traits implement themselves
+pub fn implements(self: *const TypeTable, @"type": Type, trait_type: *const Type.Trait) bool {
+    return switch (@"type") {
+        .@"struct" => |structure_type| for (self.implementations.items) |implementation| {
+            if (implementation.trait == trait_type and implementation.structure == structure_type) break true;
+        } else false,
+        .trait => |other| other == trait_type,
+        else => false
+    };
+}
+
+fn dupeNames(self: *TypeTable, names: []const []const u8) ![]const []const u8 {
+    const allocator = self.arena.allocator();
+
+    const owned = try allocator.alloc([]const u8, names.len);
+    for (owned, names) |*name, borrowed| name.* = try allocator.dupe(u8, borrowed);
+
+    return owned;
+}
+
+fn dupeFields(self: *TypeTable, fields: []const Type.Struct.Field) ![]const Type.Struct.Field {
+    const allocator = self.arena.allocator();
+
+    const owned = try allocator.alloc(Type.Struct.Field, fields.len);
+    for (owned, fields) |*field, resolved| field.* = .{
+        .name = try allocator.dupe(u8, resolved.name),
+        .@"type" = resolved.@"type"
+    };
+
+    return owned;
+}
+
+test "type table" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "recording and formatting types" {
+        var table = try TypeTable.init(testing.allocator);
+        defer table.deinit();
+
+        const function_type = try table.function(&.{.@"i32", .str}, .unit);
+        try table.put(0, function_type);
+        try table.put(1, try table.range(.@"i64"));
+        try table.put(1, .@"bool");
+
+        try testing.expect(table.get(0).?.eql(function_type));
+        try testing.expectEqual(Type.@"bool", table.get(1).?);
+        try testing.expectEqual(null, table.get(2));
+
+        var buf: [64]u8 = undefined;
+        try testing.expectEqualStrings("fn(i32, str) -> ()", try std.fmt.bufPrint(&buf, "{}", .{function_type}));
+    }
+
+    test "struct types are distinct" {
+        var table = try TypeTable.init(testing.allocator);
+        defer table.deinit();
+
+        const point = try table.structure("Point");
+        try table.setFields(point, &.{.{ .name = "x", .@"type" = .@"f64" }});
+        const other = try table.structure("Point");
+
+        const point_type: Type = .{ .@"struct" = point };
+        try testing.expect(point_type.eql(.{ .@"struct" = point }));
+        try testing.expect(!point_type.eql(.{ .@"struct" = other }));
+        try testing.expectEqual(0, point.fieldIndex("x"));
+        try testing.expectEqual(null, point.fieldIndex("y"));
+
+        var buf: [64]u8 = undefined;
+        try testing.expectEqualStrings("Point", try std.fmt.bufPrint(&buf, "{}", .{point_type}));
+    }
+
+    test "instances of generic structs are created once" {
+        var table = try TypeTable.init(testing.allocator);
+        defer table.deinit();
+
+        const pair = try table.genericStructure("Pair", &.{"A", "B"});
+        try table.setFields(pair, &.{
+            .{ .name = "first", .@"type" = .{ .parameter = "A" } },
+            .{ .name = "second", .@"type" = .{ .parameter = "B" } }
+        });
+
+        try testing.expectEqual(null, table.findInstance(pair, &.{.@"i64", .@"bool"}));
+
+        const instance = try table.instance(pair, &.{.@"i64", .@"bool"});
+        try testing.expectEqual(instance, table.findInstance(pair, &.{.@"i64", .@"bool"}).?);
+        try testing.expectEqual(null, table.findInstance(pair, &.{.@"bool", .@"i64"}));
+
+        var buf: [64]u8 = undefined;
+        try testing.expectEqualStrings("Pair<i64, bool>", try std.fmt.bufPrint(&buf, "{}", .{Type{ .@"struct" = instance }}));
+        try testing.expectEqualStrings("Pair", try std.fmt.bufPrint(&buf, "{}", .{Type{ .@"struct" = pair }}));
+    }
+
+    test "enum variants are owned by the table" {
+        var table = try TypeTable.init(testing.allocator);
+        defer table.deinit();
+
+        var types = [_]Type{.@"f64", .@"f64"};
+        const shape = try table.enumeration("Shape");
+        try table.setVariants(shape, &.{
+            .{ .name = "Empty", .fields = .unit },
+            .{ .name = "Rect", .fields = .{ .tuple = &types } }
+        });
+        types[0] = .@"bool";
+
+        try testing.expectEqual(1, shape.variantIndex("Rect"));
+        try testing.expectEqual(null, shape.variantIndex("Circle"));
+        try testing.expectEqual(Type.@"f64", shape.variants[1].fields.tuple[0]);
+
+        const shape_type: Type = .{ .@"enum" = shape };
+        try testing.expect(!shape_type.eql(.{ .@"enum" = try table.enumeration("Shape") }));
+
+        var buf: [64]u8 = undefined;
+        try testing.expectEqualStrings("Shape", try std.fmt.bufPrint(&buf, "{}", .{shape_type}));
+    }
+
+    test "instances of generic enums are created once" {
+        var table = try TypeTable.init(testing.allocator);
+        defer table.deinit();
+
+        const result = try table.genericEnumeration("Result", &.{"T", "E"});
+        try testing.expectEqual(null, table.findEnumInstance(result, &.{.@"i64", .str}));
+
+        const instance = try table.enumInstance(result, &.{.@"i64", .str});
+        try testing.expectEqual(instance, table.findEnumInstance(result, &.{.@"i64", .str}).?);
+        try testing.expectEqual(null, table.findEnumInstance(result, &.{.str, .@"i64"}));
+
+        var buf: [64]u8 = undefined;
+        try testing.expectEqualStrings("Result<i64, str>", try std.fmt.bufPrint(&buf, "{}", .{Type{ .@"enum" = instance }}));
+    }
+};
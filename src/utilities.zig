@@ -3,13 +3,6 @@
 
 const std = @import("std");
 
-pub const Error = struct { 
-    file: []const u8,
-    kind: []const u8,
-    msg: []const u8,
-    pos: Position,
-};
-
 pub const Position = struct {
     line: usize = 0,
     col: usize = 0,
@@ -22,6 +15,84 @@ pub const Position = struct {
     }
 };
 
+/// Represents a range of bytes within a file, along with the line and column
+/// the range begins at
+pub const Span = struct {
+    file: []const u8,
+    start: usize = 0,
+    end: usize = 0,
+    pos: Position = .{},
+
+    pub fn init(file: []const u8, start: usize, end: usize, pos: Position) Span {
+        return Span {
+            .file = file,
+            .start = start,
+            .end = end,
+            .pos = pos
+        };
+    }
+
+    /// Creates a span covering both spans, both spans must belong to the same file
+    pub fn merge(self: Span, other: Span) Span {
+        std.debug.assert(std.mem.eql(u8, self.file, other.file));
+
+        return Span {
+            .file = self.file,
+            .start = @min(self.start, other.start),
+            .end = @max(self.end, other.end),
+            .pos = if (self.start <= other.start) self.pos else other.pos
+        };
+    }
+
+    /// Returns the portion of the source the span covers
+    pub fn slice(self: Span, source: []const u8) []const u8 {
+        return source[@min(self.start, source.len)..@min(self.end, source.len)];
+    }
+
+    /// Calculates the line and column of the start of the span by counting
+    /// the newlines in source preceding it
+    pub fn locate(self: Span, source: []const u8) Position {
+        var pos: Position = .init(1, 1);
+
+        for (source[0..@min(self.start, source.len)]) |byte| {
+            if (byte == '\n') {
+                pos.line = pos.line + 1;
+                pos.col = 1;
+            } else {
+                pos.col = pos.col + 1;
+            }
+        }
+
+        return pos;
+    }
+
+    /// The line and column just past the end of the span, found by counting from
+    /// its start through the part of source it covers
+    pub fn endPos(self: Span, source: []const u8) Position {
+        var pos = self.pos;
+
+        for (self.slice(source)) |byte| {
+            if (byte == '\n') {
+                pos.line = pos.line + 1;
+                pos.col = 1;
+            } else {
+                pos.col = pos.col + 1;
+            }
+        }
+
+        return pos;
+    }
+
+    pub fn format(
+        self: Span,
+        comptime _: []const u8,
+        _: std.fmt.FormatOptions,
+        writer: anytype
+    ) !void {
+        try writer.print("{s}:{}:{}", .{self.file, self.pos.line, self.pos.col});
+    }
+};
+
 pub fn isAlphabetical(byte: u8) bool {
     return switch(byte) {
         inline 'a'...'z', 'A'...'Z' => true,
@@ -43,3 +114,34 @@ pub fn isIntegral(byte: u8) bool {
 pub fn isNumeric(byte: u8) bool {
     return isIntegral(byte) or byte == '.';
 }
+
+test "utilities" {
+    _ = tests;
+}
+
+const tests = struct {
+    const testing = std.testing;
+
+    test "span merging" {
+        const file = "test source";
+        const lhs: Span = .init(file, 4, 5, .init(1, 5));
+        const rhs: Span = .init(file, 8, 12, .init(2, 2));
+
+        const merged = lhs.merge(rhs);
+        try testing.expectEqual(4, merged.start);
+        try testing.expectEqual(12, merged.end);
+        try testing.expectEqual(Position.init(1, 5), merged.pos);
+        try testing.expectEqual(merged, rhs.merge(lhs));
+    }
+
+    test "span location and formatting" {
+        const source = "let x\nlet yz = 1";
+        const span: Span = .init("test source", 10, 12, .init(2, 5));
+
+        try testing.expectEqual(span.pos, span.locate(source));
+        try testing.expectEqualStrings("yz", span.slice(source));
+
+        var buf: [32]u8 = undefined;
+        try testing.expectEqualStrings("test source:2:5", try std.fmt.bufPrint(&buf, "{}", .{span}));
+    }
+};